@@ -48,5 +48,9 @@ if (import.meta.main) {
       `)
     )
     await run(['deno', 'fmt', '-q', './dist/wasm-pack.js'])
+    await Deno.writeTextFile(
+      './dist/wasm-pack.d.ts',
+      await Deno.readTextFile('./pkg/aleph_compiler.d.ts')
+    )
   }
 }
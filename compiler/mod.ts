@@ -5,7 +5,8 @@ import log from '../shared/log.ts'
 import type { LoaderPlugin } from '../types.ts'
 import { VERSION } from '../version.ts'
 import { checksum } from './dist/wasm-checksum.js'
-import init, { parseExportNamesSync, transformSync } from './dist/wasm-pack.js'
+import { loadNative } from './native.ts'
+import initWasmModule, { astSync, buildAll, bundleSync, convertCJS, emitModuleSync, freeModuleSync, initMemorySync, lookupGeneratedPosition, lookupOriginalPosition, parseExportNamesSync, parseModuleSync, parseSync, resetMemorySync, resolveSync, transformAll, transformBothSync, transformHtmlSync, transformSync, validateImportMapSync } from './dist/wasm-pack.js'
 
 export enum SourceType {
   JS = 'js',
@@ -21,43 +22,644 @@ export type ImportMap = {
 }
 
 export type SWCOptions = {
+  // override the source type that is detected by the extension, the `.js` modules
+  // with JSX elements or a `/* @jsx */` pragma are detected as JSX
   sourceType?: SourceType
   target?: 'es5' | 'es2015' | 'es2016' | 'es2017' | 'es2018' | 'es2019' | 'es2020'
+  // the target browsers as a browserslist-like query (e.g. `'safari 13, edge 18'`), or
+  // the supported syntax features (e.g. `{ optionalChaining: false }`), the unsupported
+  // features are lowered in addition to the ones newer than the `target`
+  targets?: string | {
+    nullishCoalescing?: boolean
+    optionalChaining?: boolean
+    exportNamespaceFrom?: boolean
+    objectRestSpread?: boolean
+    optionalCatchBinding?: boolean
+    asyncFunctions?: boolean
+    exponentiation?: boolean
+    topLevelAwait?: boolean
+  }
+  // keep JSX as is for another JSX-aware compiler, default is 'transform'
+  jsx?: 'transform' | 'preserve'
+  // the `/** @jsx h */`, `/** @jsxFrag Fragment */` and `/** @jsxImportSource preact */`
+  // pragmas at the top of a module override the jsx options for the module
   jsxFactory?: string
   jsxFragmentFactory?: string
+  jsxRuntime?: 'classic' | 'automatic'
+  jsxImportSource?: string
+  // add the `__source` and `__self` props to the JSX elements in development, default is true
+  jsxSource?: boolean
+  // how the whitespace of the JSX text is normalized, 'babel' and 'typescript' match the
+  // JSX transforms of babel and tsc for the hydration of the server-rendered text,
+  // default is 'collapse' which collapses every run of the whitespace into a space
+  jsxWhitespace?: 'collapse' | 'babel' | 'typescript'
+  // drop the empty string children like `{""}`, default is false
+  jsxDropEmptyChildren?: boolean
+  // pass the `key` and the `ref` props as the separate args of the classic JSX factory,
+  // like `h(type, props, key, ref, ...children)` for the view libraries like Solid,
+  // `void 0` is passed for a missing prop, default is 'props'
+  jsxKey?: 'props' | 'arg'
+  jsxRef?: 'props' | 'arg'
+  // the module format of the output, default is 'es6'
+  moduleType?: 'es6' | 'commonjs' | 'systemjs'
+  // strip the `useDeno` callbacks (with the imports that are only used by them) and
+  // replace `import.meta.env.SSR` for the runtime, the module runs in both the browser
+  // and deno if not specified
+  targetEnv?: 'browser' | 'deno'
+  // enable the decorators syntax in JS/JSX, TS/TSX always support the decorators
+  experimentalDecorators?: boolean
+  // the syntax features of the parser, `satisfies`, `accessor` and
+  // `explicitResourceManagement` are not supported by the parser of this swc version,
+  // enabling them fails the options with an error that names the syntax
+  parser?: {
+    // default is false for JS/JSX and true for TS/TSX
+    decorators?: boolean
+    // default is true
+    importAssertions?: boolean
+    // `export v from 'mod'`, default is true, not supported in TS/TSX
+    exportDefaultFrom?: boolean
+    // default is true
+    topLevelAwait?: boolean
+    satisfies?: boolean
+    accessor?: boolean
+    explicitResourceManagement?: boolean
+  }
+  // report the errors of the constructs that can't be transpiled file by file, like
+  // re-exporting a type without `export type` or accessing an ambient const enum
+  isolatedModules?: boolean
+  // emit the `design:type` and `design:paramtypes` metadata of the decorated classes
+  emitDecoratorMetadata?: boolean
+  // compress the output, mangle the local names and strip whitespace in production
+  minify?: boolean
+  // import the helpers from `@swc/helpers` (resolved by the import map) instead of
+  // inlining them in every module
+  externalHelpers?: boolean
+  // lower the top-level await into an async IIFE even if the targets support it,
+  // the exported `__tla` promise resolves when the module is evaluated, the modules
+  // with imports are lowered too to wait for the `__tla` of the imports
+  lowerTopLevelAwait?: boolean
+  // lower the class fields and the private fields even if the target supports them
+  // (e.g. for the Safari quirks), the private methods are kept
+  lowerClassFields?: boolean
+  // use the `Object.defineProperty` semantics for the class fields, or assign them in
+  // the constructor like the `useDefineForClassFields: false` of tsc, default is true
+  useDefineForClassFields?: boolean
+  // recover from the syntax errors in `parse`, the errors are returned with the
+  // partial metadata instead of being thrown
+  tolerant?: boolean
+}
+
+export type RefreshOptions = {
+  // apply the fast refresh transform in development, default is true. a module
+  // can also opt out with the `/* @refresh skip */` comment, and `/* @refresh reset */`
+  // remounts its components on every edit
+  enabled?: boolean
+  // the function name to register components, default is `$RefreshReg$`
+  registration?: string
+  // the function name to create hook signatures, default is `$RefreshSig$`
+  signature?: string
+  // the runtime module to import, e.g. `https://esm.sh/@prefresh/core`
+  runtimeImport?: string
+  // include the literal initial state of `useState`/`useReducer` in the hook signatures, default is true
+  signatureLiteralArgs?: boolean
+}
+
+export type StyledOptions = {
+  // add the `displayName` of the styled components, default is true
+  displayName?: boolean
+  // add the stable `componentId` of the styled components for SSR, default is true
+  ssr?: boolean
+  // create the elements with the emotion JSX runtime when the module uses the `css` prop
+  emotion?: boolean
+  // the import source of the emotion JSX runtime, default is 'https://esm.sh/@emotion/react'
+  emotionImportSource?: string
 }
 
 export type TransformOptions = {
   importMap?: ImportMap
   reactVersion?: string
+  // rewrite the bare specifiers that are not in the import map to the cdn, e.g. 'https://esm.sh'
+  npmCdn?: string
+  // pin the versions of the npm packages, e.g. `{ dayjs: '1.10.4' }`
+  npmVersions?: Record<string, string>
+  // pin the versions of the remote modules, keyed by the package name or the url,
+  // e.g. `{ std: '0.100.0', 'https://esm.sh/swr': 'https://esm.sh/swr@0.5.6' }`,
+  // the remote imports without a version are reported in the `warnings`
+  versions?: Record<string, string>
+  // the polyfills of the `node:` builtin modules for the browser target, keyed by
+  // the module name, e.g. `{ path: 'npm:path-browserify' }`, the bare imports like
+  // `events` are mapped too, an empty string stubs the module, and the builtin
+  // modules without a polyfill are reported in the `warnings`
+  nodePolyfills?: Record<string, string>
+  // the output paths of the compiled modules, default is `/-/` for the remote modules,
+  // `/` for the local modules, and the `js` extension
+  remotePrefix?: string
+  localPrefix?: string
+  outputExt?: string
+  // rewrite the extensions of the imports, keyed by the extension of the source (the
+  // longest matched one wins), the `''` key matches the paths without an extension,
+  // e.g. `{ '.page.tsx': '.page.js', '.json': '.json', '': '.js' }`
+  outputExts?: Record<string, string>
+  // the content hashes of the local modules keyed by the url, the import urls of
+  // the dependencies embed the hash for cache busting, e.g. `./logo.js#/logo.tsx@1a2b3c`
+  depHashes?: Record<string, string>
+  // import the assets (like `./logo.svg` or `./lib.wasm`) as the url constants, the
+  // template formats the url with the `[url]` and the `[hash]` (of the `depHashes`)
+  // placeholders, e.g. `/_aleph/assets[url]?v=[hash]`, the assets are returned in `assets`
+  assetUrl?: string
+  // the custom resolve rules that are applied before the import map, the first
+  // matched rule wins, e.g. `[{ test: '^~/', replace: '/' }]`
+  resolveRules?: { test: string, replace: string }[]
+  // the path aliases like the `paths` of tsconfig, which are applied before the import
+  // map, the targets are relative to the root, e.g. `{ '~/*': './src/*' }`
+  aliases?: Record<string, string>
+  // the root that the root-absolute imports of the local modules are relative to, a path
+  // or a remote url, e.g. with `./src` the `/components/logo.tsx` import is resolved to
+  // `/src/components/logo.tsx`
+  baseUrl?: string
+  // the integrity of the remote modules keyed by the url (the lockfile), which is
+  // carried into `remoteDeps`
+  lock?: Record<string, string>
   alephPkgUri?: string
   swcOptions?: SWCOptions
-  sourceMap?: boolean
+  sourceMap?: 'external' | 'inline' | 'none'
+  // the source map of the input code from a pre-transform, e.g. MDX
+  inputSourceMap?: string
+  // embed the original sources in the `sourcesContent` of the source map, for the
+  // modules that are not served, e.g. the remote modules
+  inlineSourcesContent?: boolean
+  // the `sourceRoot` of the source map
+  sourceRoot?: string
   isDev?: boolean
   transpileOnly?: boolean
+  // return the `stats` of the transform, for `aleph build --analyze`
+  measure?: boolean
+  // wrap the module with the HMR runtime in development, the modules that only
+  // export components accept their own updates
+  hmr?: boolean
   resolveStarExports?: boolean
+  // remove the css imports from the module and return them in `cssDeps`,
+  // in development the imports are rewritten to `applyCSS()` calls
+  extractCss?: boolean
+  // scope the CSS of the `css` tagged templates (like css`button { color: red; }`) to the
+  // JSX elements of the same component with the hashed classes, the scoped CSS is
+  // returned in `scopedCss`, in development it's applied by the `applyCSS()` calls
+  scopedCss?: boolean
+  // pass the chunk urls of the lazy components as the second argument of the lazy calls,
+  // like `React.lazy(() => import('./chart.js'), '/chart.js')`, for the framework to
+  // preload the chunks (e.g. on the hover of a route link)
+  lazyPreload?: boolean
+  // replace the server-only exports (all the exports of a `"use server"` module, or the
+  // exported functions with their own `"use server"` directive) with the stubs that
+  // throw, when the `targetEnv` is 'browser'
+  stripServerExports?: boolean
+  // return the exported constants with the static values (like `export const config = {}`)
+  // of the page and the api modules in `routeMeta`, as JSON
+  extractRouteMeta?: boolean
+  // return the syntax (like `optional-chaining`) and the API (like `Array.prototype.at`)
+  // features that the module uses in `features`, for deciding whether the legacy
+  // bundle or the polyfills are needed
+  reportFeatures?: boolean
+  // the sources of the CSS modules (`*.module.css`) keyed by the resolved url, the
+  // imports are replaced with the hashed class names and the generated CSS is
+  // returned in `cssModules`
+  cssModules?: Record<string, string>
+  // the prefetched contents of the JSON modules keyed by the resolved url, the imports
+  // with `assert { type: 'json' }` are inlined, others keep the assertions
+  jsonModules?: Record<string, string>
+  // the contents of the `.json` and the `?text` imports keyed by the resolved url without
+  // the query, the imports that are smaller than the `inlineLimit` are inlined as the
+  // constants, which saves the requests of the tiny files in development
+  inlineImports?: Record<string, string>
+  // the byte size limit of the inlined imports, default is 4096
+  inlineLimit?: number
+  // the members of the const enums exported by the modules keyed by the resolved url
+  // and the enum name, the imported const enums are inlined
+  constEnums?: Record<string, Record<string, Record<string, number | string>>>
+  // replaces the `import.meta.url`, default is the original url of the module, the
+  // `import.meta.main` is replaced with `false` for the browser
+  importMetaUrl?: string
+  // the files matched by the `import.meta.glob` patterns (see `importGlobs` of the
+  // metadata) keyed by the pattern, the calls are expanded to the lazy imports
+  globFiles?: Record<string, string[]>
   bundleMode?: boolean
   bundleExternal?: string[]
+  // replace the global constants, e.g. `{ 'process.env.NODE_ENV': '"production"' }`
+  define?: Record<string, string>
+  // the export names that are used by the other modules (from the analysis of the
+  // module graph), the unused exports and the bindings that are not referenced
+  // anymore are dropped, with `bundleSync` for the exports of the entry
+  usedExports?: string[]
+  // the deps (the fixed urls) that are side-effect free (the `sideEffectFree` of their
+  // outputs), the imports of them are dropped if no specifier is used, the other
+  // imports are kept as `import "..."` for the side effects
+  sideEffectFreeDeps?: string[]
+  // remove the `console.*()` calls and the `debugger` statements in production
+  dropConsole?: boolean
+  dropDebugger?: boolean
+  // remove the `data-testid` and `data-test` JSX attributes, and the calls of the
+  // functions like `['invariantDev', 'dev.assert']` in production
+  stripDataTestAttrs?: boolean
+  stripCalls?: string[]
+  // keep the `name` of the functions and the classes that are renamed by the
+  // transforms (like the conflicting names of a bundle), the original names are
+  // defined with `Object.defineProperty(fn, 'name', ...)`
+  keepFnNames?: boolean
+  keepClassNames?: boolean
+  // hoist the JSX elements without the dynamic props or children to the module scope
+  // constants in production, so they are not created again on each render
+  hoistStaticJsx?: boolean
+  // instrument the module with the istanbul coverage counters of the statements, the
+  // functions and the branches, which are collected in `globalThis.__coverage__` for
+  // the lcov reports, the coverage map is returned in `coverage`
+  coverage?: boolean
+  // the comments of the production build, `legal` strips the comments but returns the
+  // `/*! ... */` and `@license` comments in the `legalComments`, default is `all`
+  comments?: 'all' | 'legal' | 'none'
+  // the fast refresh runtime in development, default is React Refresh
+  refresh?: boolean | RefreshOptions
+  // configure the styled-components (and the emotion `css` prop) for SSR hydration
+  styled?: StyledOptions
+  // the `cacheKey` of the previous results keyed by the url, the transform of an
+  // unchanged module is skipped with an `unchanged` result
+  cacheKeys?: Record<string, string>
+  // persist the results in the directory by the `cacheKey`, native backend only
+  cacheDir?: string
   // loaders for inline styles transform
   loaders?: LoaderPlugin[]
+  // transform the modules whose code is longer than the threshold (in chars) in a worker,
+  // so the large modules (like the generated code or the vendored bundles) don't block
+  // the event loop, the modules are transformed in the current thread by default
+  asyncThreshold?: number
+}
+
+export type ResolveOptions = Pick<TransformOptions, 'importMap' | 'alephPkgUri' | 'reactVersion' | 'npmCdn' | 'npmVersions' | 'nodePolyfills' | 'remotePrefix' | 'localPrefix' | 'outputExt' | 'outputExts' | 'resolveRules' | 'aliases' | 'baseUrl'>
+
+export type BundleResult = {
+  code: string
+  map: string | null
+  // the external dependencies of the bundle
+  deps: DependencyDescriptor[]
+  // the bundled modules in evaluation order
+  modules: string[]
+  // the import cycles between the bundled modules
+  warnings: Diagnostic[] | null
+  // the emitted file of the bundle, the bundled modules share the file of the entry
+  manifest: BuildManifest
+}
+
+export type ManifestEntry = {
+  // the emitted file name with the content hash, like `/pages/index.1a2b3c4d.js`
+  file: string
+  // the hex sha-1 of the emitted code
+  hash: string
+  // the emitted files that the dynamic imports landed in, keyed by the specifier
+  dynamicImports?: Record<string, string>
+}
+
+export type BuildManifest = {
+  // the emitted files keyed by the specifier of the modules
+  modules: Record<string, ManifestEntry>
+  // the emitted files of the dynamic imports keyed by the `alephChunkName`
+  chunks: Record<string, string[]>
+}
+
+export type BuildResult = {
+  // the outputs in the order of the sources
+  modules: TransformResult[]
+  manifest: BuildManifest
+}
+
+export type TransformHtmlResult = {
+  code: string
+  // the deps of the module scripts, including the imports of the inline scripts
+  deps: DependencyDescriptor[]
+}
+
+export type ConvertCJSResult = {
+  code: string
+  // the named exports that are inferred from the static `exports.x = ...` assignments
+  exports: string[]
+  // the specifiers of the static `require()` calls, which are imported by the facade
+  requires: string[]
+}
+
+export type OriginalPosition = {
+  source: string
+  // 1-based
+  line: number
+  // 0-based
+  column: number
+  name: string | null
+}
+
+export type GeneratedPosition = {
+  // 1-based
+  line: number
+  // 0-based
+  column: number
+}
+
+export type InitOptions = {
+  // pre-grow the wasm memory to the bytes, so the first transforms of the dev server
+  // don't grow the memory again and again, ignored by the native backend
+  initialMemory?: number
+  // the number of the parsed source maps that are kept for `lookupOriginalPosition`
+  // and `lookupGeneratedPosition`, `0` disables the cache, 16 by default
+  sourceMapCacheSize?: number
+}
+
+export type MemoryStats = {
+  // the size of the wasm memory in bytes, `null` with the native backend
+  memoryBytes: number | null
+  cachedSourceMaps: number
+  // the parsed modules of `parseModule` that are not freed by `freeModule`
+  parsedModules: number
+}
+
+// the opaque handle of a module that is parsed by `parseModule`
+export type ModuleHandle = number
+
+export type ResolveResult = {
+  // the fixed url after the import map and the CDN rules are applied
+  url: string
+  // the path of the compiled module relative to the referrer
+  resolvedPath: string
+}
+
+export type AstPosition = {
+  // 1-based
+  line: number
+  // 0-based char index
+  column: number
+}
+
+export type AstSpan = {
+  // the byte offsets in the module source
+  start: number
+  end: number
+  loc: { start: AstPosition, end: AstPosition }
+}
+
+// the swc AST node, see https://swc.rs
+export type AstNode = {
+  type: string
+  span: AstSpan
+  // deno-lint-ignore no-explicit-any
+  [key: string]: any
+}
+
+export type ImportMapDiagnostic = {
+  // the scope of the entry, undefined for the top-level imports
+  scope?: string
+  key: string
+  value: string
+  reason: string
+}
+
+export type ImportMapValidation = {
+  // the invalid entries that are ignored by the resolver
+  errors: ImportMapDiagnostic[]
+  // the suspicious entries that are kept
+  warnings: ImportMapDiagnostic[]
 }
 
 export type TransformResult = {
   code: string
+  // the hex sha-1 of the compiled code (before the inline styles are resolved)
+  hash: string
+  // whether the module has no side effects when it's evaluated, the bundler can
+  // drop the module if none of its exports are used
+  sideEffectFree: boolean
+  // the export signature, the HMR runtime diffs it across rebuilds to decide
+  // between hot-applying the update and a full reload
+  exports: ExportSignature
+  // `boundary` if the module only exports the components, so it accepts its own
+  // updates, `mixed` if it exports the other values too, the updates of the others
+  // bubble to the importers
+  refreshBoundary: 'boundary' | 'mixed' | 'none'
+  // the `"use client"` or `"use server"` directive, the boundaries of the server components
+  directive: 'client' | 'server' | null
+  // the server-only exports, like the functions with the `"use server"` directive
+  serverExports: string[] | null
   deps: DependencyDescriptor[]
+  // the remote dependencies for the lockfile and the `modulepreload` headers
+  remoteDeps: RemoteDependency[] | null
+  // the absolute urls of the static dependencies (not the dynamic imports or the
+  // workers), for the `<link rel="modulepreload">` tags in SSR
+  preload: string[] | null
   starExports: string[] | null
+  dynamicImportPatterns: string[] | null
+  // the ids of the `useDeno` hooks, for pre-running the callbacks in SSR
+  denoHooks: string[] | null
+  cssDeps: string[] | null
+  cssModules: Record<string, string> | null
+  // the scoped CSS of the `css` templates keyed by the scope class with `scopedCss`
+  scopedCss: Record<string, string> | null
+  // the resolved urls of the assets that are imported with the `assetUrl` option
+  assets: string[] | null
+  // the istanbul coverage map with the `coverage` option
+  coverage: Record<string, unknown> | null
+  // the front-matter of the markdown module (`*.md`), which is compiled into a JSX page
+  frontMatter: Record<string, any> | null
+  // the statically analyzable exported constants with the `extractRouteMeta` option,
+  // the server reads the route config without importing the module
+  routeMeta: Record<string, any> | null
+  // the features that the module uses with the `reportFeatures` option
+  features: { syntax: string[], apis: string[] } | null
+  // the non-fatal warnings, like the unused imports, for printing in the dev mode
+  warnings: Diagnostic[] | null
+  // the timing and the sizes of the transform with the `measure` option
+  stats: TransformStats | null
+  // the stripped legal comments with `comments: 'legal'`, for a third-party notices file
+  legalComments: string | null
+  // the hash of the input and the options for the `cacheKeys` of the next transform
+  cacheKey: string | null
+  // whether the module is unchanged since the `cacheKeys`, the other fields are
+  // empty and the caller reuses its previous result
+  unchanged: boolean
   map: string | null
 }
 
+export type TransformStats = {
+  parseMs: number
+  transformMs: number
+  emitMs: number
+  inputBytes: number
+  outputBytes: number
+}
+
 type InlineStyles = Record<string, { type: string, quasis: string[], exprs: string[] }>
 
-type DependencyDescriptor = {
+export type DependencyDescriptor = {
   specifier: string
   isDynamic: boolean
+  span?: { start: number, end: number }
+  // the web workers are emitted as the separate entries
+  kind?: 'worker'
+  // the name of the split chunk, set by the `/* alephChunkName: "admin" */` comment
+  // of the dynamic import
+  chunkName?: string
+  // re-exported by `export ... from`, the exports of a barrel module include
+  // the exports of its re-exported dependencies
+  reexport?: boolean
+  // the loader of a lazy component, like `React.lazy(() => import('./chart.tsx'))`,
+  // the framework can prefetch the chunk of the lazy boundary
+  lazy?: boolean
+}
+
+export type RemoteDependency = {
+  url: string
+  resolvedPath: string
+  // the integrity in the lock, like `sha384-...`
+  integrity?: string
+}
+
+export type ModuleMetadata = {
+  exports: string[]
+  imports: DependencyDescriptor[]
+  // the patterns of the `import.meta.glob` calls
+  importGlobs: string[]
+  hasDefaultExport: boolean
+  usesJsx: boolean
+  hasTopLevelAwait: boolean
+  directive?: 'client' | 'server'
+  // the exported constants with the static values of an api module (under `api/`), like
+  // `export const methods = ['GET']`, to register the handlers without importing it
+  routeMeta: Record<string, any> | null
+  // the syntax errors with the `tolerant` option
+  errors?: Diagnostic[]
+}
+
+export type ExportSignature = {
+  names: string[]
+  // the resolved urls of `export * from '...'`
+  starExports: string[]
+  hasDefaultExport: boolean
+}
+
+export type Diagnostic = {
+  fileName: string
+  line: number
+  column: number
+  length: number
+  message: string
+  // the stable code of the diagnostic, for the error overlay to tell the kinds apart
+  code: 'syntax-error' | 'transform-error' | 'invalid-options' | 'warning' | 'import-cycle'
+  // the source lines around the span, the `highlight` is the `[start, end)` columns
+  // of the span in the line
+  frame?: { line: number, text: string, highlight: [number, number] | null }[]
+  // the modules of the import cycle with the `import-cycle` code, from and back to
+  // the `fileName`
+  cycle?: string[]
+}
+
+/** The error thrown by the compiler, with the diagnostics to render code frames. */
+export class CompileError extends Error {
+  diagnostics: Diagnostic[]
+
+  constructor(diagnostics: Diagnostic[]) {
+    super(diagnostics.map(({ message, line, column }) => line > 0 ? `${message} at ${line}:${column}` : message).join('\n'))
+    this.name = 'CompileError'
+    this.diagnostics = diagnostics
+  }
+}
+
+function withDiagnostics<T>(fn: () => T): T {
+  try {
+    return fn()
+  } catch (error) {
+    if (error !== null && typeof error === 'object' && Array.isArray(error.diagnostics)) {
+      throw new CompileError(error.diagnostics)
+    }
+    throw error
+  }
 }
 
 let wasmReady: Promise<void> | boolean = false
 
+// the backend of the compiler, the wasm module by default, or the native library that
+// is loaded with Deno FFI when the `ALEPH_COMPILER_NATIVE` env points to it
+let backend = { astSync, buildAll, bundleSync, convertCJS, emitModuleSync, freeModuleSync, initMemorySync, lookupGeneratedPosition, lookupOriginalPosition, parseExportNamesSync, parseModuleSync, parseSync, resetMemorySync, resolveSync, transformAll, transformBothSync, transformHtmlSync, transformSync, validateImportMapSync }
+
+// the worker that transforms the modules above the `asyncThreshold`, it's started on
+// demand and terminated after it's idle for `WORKER_IDLE_TIMEOUT` ms, so it's reused
+// by the bursts of the transforms but doesn't keep the process alive
+const WORKER_IDLE_TIMEOUT = 1000
+let worker: Worker | null = null
+let workerIdleTimer: number | null = null
+let workerTaskId = 0
+// deno-lint-ignore no-explicit-any
+const workerTasks = new Map<number, { resolve: (output: any) => void, reject: (error: Error) => void }>()
+
+// deno-lint-ignore no-explicit-any
+function transformInWorker(url: string, code: string, options: Omit<TransformOptions, 'loaders' | 'asyncThreshold'>): Promise<any> {
+  if (worker === null) {
+    // the worker reads the `ALEPH_COMPILER_NATIVE` env and the deno dir like `initWasm`
+    // deno-lint-ignore no-explicit-any
+    const w: Worker = new Worker(new URL('./worker.ts', import.meta.url).href, { type: 'module', deno: { namespace: true } } as any)
+    w.onmessage = ({ data: { id, output, error } }: MessageEvent) => {
+      const task = workerTasks.get(id)
+      workerTasks.delete(id)
+      if (workerTasks.size === 0) {
+        workerIdleTimer = setTimeout(terminateWorker, WORKER_IDLE_TIMEOUT)
+      }
+      if (task === undefined) {
+        return
+      }
+      if (error === undefined) {
+        task.resolve(output)
+      } else if (Array.isArray(error.diagnostics)) {
+        task.reject(new CompileError(error.diagnostics))
+      } else {
+        task.reject(new Error(error.message))
+      }
+    }
+    // a crashed worker can't answer the pending tasks, the next transform starts a new one
+    w.onerror = (e: ErrorEvent) => {
+      e.preventDefault()
+      resetWorker(new Error(`the worker crashed: ${e.message}`))
+    }
+    w.onmessageerror = () => {
+      resetWorker(new Error('could not deserialize the message of the worker'))
+    }
+    worker = w
+  }
+  if (workerIdleTimer !== null) {
+    clearTimeout(workerIdleTimer)
+    workerIdleTimer = null
+  }
+  const id = ++workerTaskId
+  return new Promise((resolve, reject) => {
+    workerTasks.set(id, { resolve, reject })
+    worker!.postMessage({ id, url, code, options })
+  })
+}
+
+/**
+ * terminate the worker of the `asyncThreshold` transforms now instead of waiting for
+ * the idle timeout, the pending transforms are rejected.
+ */
+export function terminateWorker() {
+  resetWorker(new Error('the worker is terminated'))
+}
+
+// terminates the worker and rejects the pending tasks with the error
+function resetWorker(error: Error) {
+  if (workerIdleTimer !== null) {
+    clearTimeout(workerIdleTimer)
+    workerIdleTimer = null
+  }
+  if (worker !== null) {
+    worker.terminate()
+    worker = null
+  }
+  for (const { reject } of workerTasks.values()) {
+    reject(error)
+  }
+  workerTasks.clear()
+}
+
 async function getDenoDir() {
   const p = Deno.run({
     cmd: [Deno.execPath(), 'info', '--json', '--unstable'],
@@ -70,20 +672,42 @@ async function getDenoDir() {
 }
 
 export async function initWasm() {
+  const nativePath = Deno.env.get('ALEPH_COMPILER_NATIVE')
+  if (nativePath) {
+    backend = loadNative(nativePath)
+    return
+  }
   const cacheDir = join(await getDenoDir(), `deps/https/deno.land/aleph@v${VERSION}`)
   const cachePath = `${cacheDir}/compiler.${checksum}.wasm`
   if (existsFileSync(cachePath)) {
     const wasmData = await Deno.readFile(cachePath)
-    await init(wasmData)
+    await initWasmModule(wasmData)
   } else {
     const { default: getWasmData } = await import('./dist/wasm.js')
     const wasmData = getWasmData()
-    await init(wasmData)
+    await initWasmModule(wasmData)
     await ensureDir(cacheDir)
     await Deno.writeFile(cachePath, wasmData)
   }
 }
 
+// initialize the backend once before the first call, the concurrent calls wait for
+// the same initialization
+async function ensureWasm() {
+  let t: number | null = null
+  if (wasmReady === false) {
+    t = performance.now()
+    wasmReady = initWasm()
+  }
+  if (wasmReady instanceof Promise) {
+    await wasmReady
+    wasmReady = true
+  }
+  if (t !== null) {
+    log.debug(`init compiler wasm in ${Math.round(performance.now() - t)}ms`)
+  }
+}
+
 /**
  * transform module by swc.
  *
@@ -109,31 +733,95 @@ export async function initWasm() {
  * @param {object} options - the transform options.
  */
 export async function transform(url: string, code: string, options: TransformOptions = {}): Promise<TransformResult> {
-  let t: number | null = null
-  if (wasmReady === false) {
-    t = performance.now()
-    wasmReady = initWasm()
-  }
-  if (wasmReady instanceof Promise) {
-    await wasmReady
-    wasmReady = true
-  }
-  if (t !== null) {
-    log.debug(`init compiler wasm in ${Math.round(performance.now() - t)}ms`)
-  }
+  await ensureWasm()
+
+  const { loaders, asyncThreshold, ...transformOptions } = options
+
+  const output = asyncThreshold !== undefined && code.length > asyncThreshold
+    ? await transformInWorker(url, code, transformOptions)
+    : withDiagnostics(() => backend.transformSync(url, code, transformOptions))
+  return resolveOutput(loaders)(output)
+}
+
+/**
+ * transform the module in the current thread and return the raw output of the
+ * compiler, without resolving the inline styles. it's called by the compiler worker.
+ *
+ * @param {string} url - the module URL.
+ * @param {string} code - the module code.
+ * @param {object} options - the transform options without the `loaders`.
+ */
+export async function transformRaw(url: string, code: string, options: Omit<TransformOptions, 'loaders' | 'asyncThreshold'> = {}) {
+  await ensureWasm()
+
+  return backend.transformSync(url, code, options)
+}
+
+/**
+ * transform the module into the dev variant (with fast refresh and the HMR wrapper)
+ * and the minified prod variant, the module is parsed only once.
+ *
+ * @param {string} url - the module URL.
+ * @param {string} code - the module code.
+ * @param {object} options - the transform options, the `isDev` and `hmr` are ignored.
+ */
+export async function transformBoth(url: string, code: string, options: TransformOptions = {}): Promise<{ dev: TransformResult, prod: TransformResult }> {
+  await ensureWasm()
 
   const { loaders, ...transformOptions } = options
+  const outputs = withDiagnostics(() => backend.transformBothSync(url, code, transformOptions))
+  const [dev, prod] = await Promise.all([outputs.dev, outputs.prod].map(resolveOutput(loaders)))
+  return { dev, prod }
+}
 
-  let {
-    code: jsContent,
-    deps,
-    map,
-    inlineStyles,
-    starExports
-  } = transformSync(url, code, transformOptions)
+/**
+ * transform many modules in one call, the options are shared by all the modules.
+ *
+ * @param {object[]} sources - the modules to transform.
+ * @param {object} options - the transform options.
+ */
+export async function transformAll(sources: { filename: string, code: string }[], options: TransformOptions = {}): Promise<TransformResult[]> {
+  await ensureWasm()
+
+  const { loaders, ...transformOptions } = options
+  const outputs = withDiagnostics(() => backend.transformAll(sources, transformOptions))
+
+  return Promise.all(outputs.map(resolveOutput(loaders)))
+}
+
+/**
+ * transform many modules like `transformAll`, with the manifest that maps the modules
+ * to the emitted file names with the content hashes, for the `export` command. the
+ * unchanged modules of the `cacheKeys` are not in the manifest.
+ *
+ * @param {object[]} sources - the modules to transform.
+ * @param {object} options - the transform options.
+ */
+export async function buildAll(sources: { filename: string, code: string }[], options: TransformOptions = {}): Promise<BuildResult> {
+  await ensureWasm()
+
+  const { loaders, ...transformOptions } = options
+  const { modules, manifest } = withDiagnostics(() => backend.buildAll(sources, transformOptions))
 
-  // resolve inline-style
-  await Promise.all(Object.entries(inlineStyles as InlineStyles).map(async ([key, style]) => {
+  return {
+    modules: await Promise.all(modules.map(resolveOutput(loaders))),
+    manifest,
+  }
+}
+
+// resolves the inline styles of a transform output with the loaders, the other fields
+// of the output are returned as is
+function resolveOutput(loaders?: LoaderPlugin[]) {
+  // deno-lint-ignore no-explicit-any
+  return async ({ code, inlineStyles, ...rest }: any): Promise<TransformResult> => ({
+    ...rest,
+    code: await resolveInlineStyles(code, inlineStyles, loaders),
+  })
+}
+
+/* resolve inline-style with loaders */
+async function resolveInlineStyles(jsContent: string, inlineStyles: InlineStyles, loaders?: LoaderPlugin[]): Promise<string> {
+  await Promise.all(Object.entries(inlineStyles).map(async ([key, style]) => {
     let tpl = style.quasis.reduce((tpl, quais, i, a) => {
       tpl += quais
       if (i < a.length - 1) {
@@ -175,25 +863,179 @@ export async function transform(url: string, code: string, options: TransformOpt
     jsContent = jsContent.replace(`"%%${key}-placeholder%%"`, '`' + tpl + '`')
   }))
 
-  return { code: jsContent, deps, map, starExports }
+  return jsContent
 }
 
 /* parse export names of the module */
 export async function parseExportNames(url: string, code: string, options: SWCOptions = {}): Promise<string[]> {
-  let t: number | null = null
-  if (wasmReady === false) {
-    t = performance.now()
-    wasmReady = initWasm()
-  }
+  await ensureWasm()
+
+  return withDiagnostics(() => backend.parseExportNamesSync(url, code, options))
+}
+
+/* parse the AST of the module for the tooling, the source type is detected by the filename */
+export async function ast(filename: string, code: string): Promise<AstNode> {
+  await ensureWasm()
+
+  return withDiagnostics(() => backend.astSync(filename, code))
+}
+
+/* parse the metadata of the module without emitting code */
+export async function parse(url: string, code: string, options: SWCOptions = {}): Promise<ModuleMetadata> {
+  await ensureWasm()
+
+  return withDiagnostics(() => backend.parseSync(url, code, options))
+}
+
+/* resolve the specifier imported by the referrer in the same way as the transformer */
+export async function resolve(specifier: string, referrer: string, options: ResolveOptions = {}): Promise<ResolveResult> {
+  await ensureWasm()
+
+  return backend.resolveSync(specifier, referrer, options)
+}
+
+/* validate the import map, the invalid entries are ignored by the transformer silently */
+export async function validateImportMap(importMap: Partial<ImportMap>): Promise<ImportMapValidation> {
+  await ensureWasm()
+
+  return backend.validateImportMapSync(importMap)
+}
+
+/**
+ * bundle the module graph of the entry into a single ES module, the `modules` are
+ * the pre-fetched sources keyed by the resolved url, the imports of other modules
+ * are kept as the external imports of the bundle.
+ *
+ * ```ts
+ * bundle('/pages/index.tsx', {
+ *   '/pages/index.tsx': `import { sum } from '../lib/util.ts'; export default sum(1, 2)`,
+ *   '/lib/util.ts': `export const sum = (a: number, b: number) => a + b`,
+ * })
+ * ```
+ */
+export async function bundle(entry: string, modules: Record<string, string>, options: TransformOptions = {}): Promise<BundleResult> {
+  await ensureWasm()
+
+  const { loaders, ...bundleOptions } = options
+  return withDiagnostics(() => backend.bundleSync(entry, modules, bundleOptions))
+}
+
+/**
+ * transform an HTML entry like `index.html`, the `src` of the module scripts is
+ * resolved and the inline module scripts are transformed in the same way as the
+ * modules, with the same import map and resolve options.
+ */
+export async function transformHtml(url: string, code: string, options: TransformOptions = {}): Promise<TransformHtmlResult> {
+  await ensureWasm()
+
+  const { loaders, asyncThreshold, ...htmlOptions } = options
+  return withDiagnostics(() => backend.transformHtmlSync(url, code, htmlOptions))
+}
+
+/**
+ * convert a CommonJS module into an ES module facade with the named exports, for the
+ * proxy of the remote modules that are served as CommonJS.
+ *
+ * ```ts
+ * convertCJS('https://cdn.example.com/lib.js', `exports.sum = (a, b) => a + b`)
+ * ```
+ */
+export async function convertCJS(url: string, code: string): Promise<ConvertCJSResult> {
+  await ensureWasm()
+
+  return withDiagnostics(() => backend.convertCJS(url, code))
+}
+
+/**
+ * look up the original position of the generated position (the `line` is 1-based and
+ * the `column` is 0-based) in the source map, for symbolicating the stack traces of
+ * the SSR. returns `null` if the line has no mappings.
+ */
+export async function lookupOriginalPosition(map: string, line: number, column: number): Promise<OriginalPosition | null> {
+  await ensureWasm()
+
+  return withDiagnostics(() => backend.lookupOriginalPosition(map, line, column))
+}
+
+/* look up the generated position of the original position of the `source`, the inverse of `lookupOriginalPosition` */
+export async function lookupGeneratedPosition(map: string, source: string, line: number, column: number): Promise<GeneratedPosition | null> {
+  await ensureWasm()
+
+  return withDiagnostics(() => backend.lookupGeneratedPosition(map, source, line, column))
+}
+
+/* tune the memory of the compiler for the long-running processes like the dev server */
+export async function init(options: InitOptions = {}): Promise<MemoryStats> {
+  await ensureWasm()
+
+  return backend.initMemorySync(options)
+}
+
+/**
+ * parse the module once and keep it in the compiler memory, the returned handle can be
+ * emitted many times with the different options by `emitModule` (like for SSR and the
+ * client) without parsing the module again, and must be freed by `freeModule`.
+ *
+ * @param {string} url - the module URL.
+ * @param {string} code - the module code.
+ * @param {object} options - the parse options (like `swcOptions.sourceType`,
+ *                           `extractRouteMeta` and `reportFeatures`), the others are ignored.
+ */
+export async function parseModule(url: string, code: string, options: Omit<TransformOptions, 'loaders' | 'asyncThreshold'> = {}): Promise<ModuleHandle> {
+  await ensureWasm()
+
+  return withDiagnostics(() => backend.parseModuleSync(url, code, options))
+}
+
+/**
+ * emit the module that is parsed by `parseModule`, like `transform` but the parse
+ * options are taken from `parseModule`. with the native backend, the handle is only
+ * valid on the thread that parsed the module.
+ *
+ * @param {number} handle - the handle of the parsed module.
+ * @param {object} options - the transform options.
+ */
+export async function emitModule(handle: ModuleHandle, options: Omit<TransformOptions, 'asyncThreshold'> = {}): Promise<TransformResult> {
   if (wasmReady instanceof Promise) {
     await wasmReady
     wasmReady = true
   }
-  if (t !== null) {
-    log.debug(`init compiler wasm in ${Math.round(performance.now() - t)}ms`)
+
+  const { loaders, ...transformOptions } = options
+  const output = withDiagnostics(() => backend.emitModuleSync(handle, transformOptions))
+  return resolveOutput(loaders)(output)
+}
+
+/**
+ * free the module that is parsed by `parseModule`, returns `false` if the handle is
+ * unknown or already freed.
+ *
+ * @param {number} handle - the handle of the parsed module.
+ */
+export async function freeModule(handle: ModuleHandle): Promise<boolean> {
+  if (wasmReady instanceof Promise) {
+    await wasmReady
+    wasmReady = true
   }
 
-  return parseExportNamesSync(url, code, options)
+  return wasmReady === true && backend.freeModuleSync(handle)
+}
+
+/**
+ * drop the global caches of the compiler, the wasm memory never shrinks but the freed
+ * memory is reused by the next transforms.
+ */
+export async function reset(): Promise<MemoryStats> {
+  if (wasmReady !== true) {
+    // nothing is cached before the compiler is initialized
+    if (wasmReady instanceof Promise) {
+      await wasmReady
+      wasmReady = true
+    } else {
+      return { memoryBytes: null, cachedSourceMaps: 0, parsedModules: 0 }
+    }
+  }
+  return backend.resetMemorySync()
 }
 
 /**
@@ -0,0 +1,77 @@
+// the native backend of the compiler, the `cdylib` of the crate that is built for the
+// host (`cargo build --release`) is loaded with Deno FFI (requires `--unstable --allow-ffi`).
+// it has the same exports as the wasm module, but without the memory limits of wasm.
+
+const symbols = {
+  aleph_transform: { parameters: ['buffer', 'buffer', 'buffer'], result: 'pointer' },
+  aleph_transform_both: { parameters: ['buffer', 'buffer', 'buffer'], result: 'pointer' },
+  aleph_transform_all: { parameters: ['buffer', 'buffer'], result: 'pointer' },
+  aleph_build_all: { parameters: ['buffer', 'buffer'], result: 'pointer' },
+  aleph_parse: { parameters: ['buffer', 'buffer', 'buffer'], result: 'pointer' },
+  aleph_parse_export_names: { parameters: ['buffer', 'buffer', 'buffer'], result: 'pointer' },
+  aleph_ast: { parameters: ['buffer', 'buffer'], result: 'pointer' },
+  aleph_resolve: { parameters: ['buffer', 'buffer', 'buffer'], result: 'pointer' },
+  aleph_validate_import_map: { parameters: ['buffer'], result: 'pointer' },
+  aleph_bundle: { parameters: ['buffer', 'buffer', 'buffer'], result: 'pointer' },
+  aleph_transform_html: { parameters: ['buffer', 'buffer', 'buffer'], result: 'pointer' },
+  aleph_convert_cjs: { parameters: ['buffer', 'buffer'], result: 'pointer' },
+  aleph_lookup_original_position: { parameters: ['buffer', 'u32', 'u32'], result: 'pointer' },
+  aleph_lookup_generated_position: { parameters: ['buffer', 'buffer', 'u32', 'u32'], result: 'pointer' },
+  aleph_init_memory: { parameters: ['buffer'], result: 'pointer' },
+  aleph_reset_memory: { parameters: [], result: 'pointer' },
+  aleph_parse_module: { parameters: ['buffer', 'buffer', 'buffer'], result: 'pointer' },
+  aleph_emit_module: { parameters: ['u32', 'buffer'], result: 'pointer' },
+  aleph_free_module: { parameters: ['u32'], result: 'pointer' },
+  aleph_free_string: { parameters: ['pointer'], result: 'void' },
+} as const
+
+const encoder = new TextEncoder()
+
+// encodes a NUL-terminated string, the objects are passed as JSON
+function cstr(value: unknown): Uint8Array {
+  return encoder.encode((typeof value === 'string' ? value : JSON.stringify(value ?? {})) + '\0')
+}
+
+export function loadNative(path: string) {
+  // deno-lint-ignore no-explicit-any
+  const { symbols: lib } = (Deno as any).dlopen(path, symbols)
+
+  // the native functions return `{ ok }` or `{ error: { diagnostics } }`, the error is
+  // thrown as the wasm module does, the numbers are passed as is.
+  // deno-lint-ignore no-explicit-any
+  const call = (fn: (...args: any[]) => any, ...args: unknown[]) => {
+    const ptr = fn(...args.map(arg => typeof arg === 'number' ? arg : cstr(arg)))
+    try {
+      // deno-lint-ignore no-explicit-any
+      const { ok, error } = JSON.parse(new (Deno as any).UnsafePointerView(ptr).getCString())
+      if (error !== undefined) {
+        throw error
+      }
+      return ok
+    } finally {
+      lib.aleph_free_string(ptr)
+    }
+  }
+
+  return {
+    transformSync: (url: string, code: string, options: unknown) => call(lib.aleph_transform, url, code, options),
+    transformBothSync: (url: string, code: string, options: unknown) => call(lib.aleph_transform_both, url, code, options),
+    transformAll: (sources: unknown, options: unknown) => call(lib.aleph_transform_all, sources, options),
+    buildAll: (sources: unknown, options: unknown) => call(lib.aleph_build_all, sources, options),
+    parseSync: (url: string, code: string, options: unknown) => call(lib.aleph_parse, url, code, options),
+    parseExportNamesSync: (url: string, code: string, options: unknown) => call(lib.aleph_parse_export_names, url, code, options),
+    astSync: (filename: string, code: string) => call(lib.aleph_ast, filename, code),
+    resolveSync: (specifier: string, referrer: string, options: unknown) => call(lib.aleph_resolve, specifier, referrer, options),
+    validateImportMapSync: (importMap: unknown) => call(lib.aleph_validate_import_map, importMap),
+    bundleSync: (entry: string, modules: unknown, options: unknown) => call(lib.aleph_bundle, entry, modules, options),
+    transformHtmlSync: (url: string, code: string, options: unknown) => call(lib.aleph_transform_html, url, code, options),
+    convertCJS: (url: string, code: string) => call(lib.aleph_convert_cjs, url, code),
+    lookupOriginalPosition: (map: string, line: number, column: number) => call(lib.aleph_lookup_original_position, map, line, column),
+    lookupGeneratedPosition: (map: string, source: string, line: number, column: number) => call(lib.aleph_lookup_generated_position, map, source, line, column),
+    initMemorySync: (options: unknown) => call(lib.aleph_init_memory, options),
+    resetMemorySync: () => call(lib.aleph_reset_memory),
+    parseModuleSync: (url: string, code: string, options: unknown) => call(lib.aleph_parse_module, url, code, options),
+    emitModuleSync: (handle: number, options: unknown) => call(lib.aleph_emit_module, handle, options),
+    freeModuleSync: (handle: number) => call(lib.aleph_free_module, handle),
+  }
+}
@@ -0,0 +1,176 @@
+use crate::resolve::Resolver;
+
+use std::{cell::RefCell, rc::Rc};
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::quote_ident;
+use swc_ecma_visit::{noop_fold_type, Fold};
+
+/// Creates a fold that replaces the imports of the assets with the url constants,
+/// the assets are recorded in the `assets` of the resolver:
+///
+/// ```js
+/// import logo from "./logo.svg"
+/// ```
+///
+/// is transformed to (with the `/_aleph/assets[url]` asset url):
+///
+/// ```js
+/// const logo = "/_aleph/assets/logo.svg";
+/// ```
+///
+/// The imports of the names other than `default` are kept.
+pub fn asset_import_fold(resolver: Rc<RefCell<Resolver>>) -> impl Fold {
+  AssetImportFold { resolver }
+}
+
+struct AssetImportFold {
+  resolver: Rc<RefCell<Resolver>>,
+}
+
+impl Fold for AssetImportFold {
+  noop_fold_type!();
+
+  fn fold_module_items(&mut self, items: Vec<ModuleItem>) -> Vec<ModuleItem> {
+    let mut output = Vec::with_capacity(items.len());
+    for item in items {
+      if let ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+        specifiers,
+        src,
+        type_only: false,
+        ..
+      })) = &item
+      {
+        if let Some(decls) = new_asset_var_decls(specifiers) {
+          if let Some(url) = self.resolver.borrow_mut().resolve_asset(src.value.as_ref()) {
+            // match: import "./logo.svg"
+            if !decls.is_empty() {
+              output.push(ModuleItem::Stmt(Stmt::Decl(Decl::Var(VarDecl {
+                span: DUMMY_SP,
+                kind: VarDeclKind::Const,
+                declare: false,
+                decls: decls
+                  .into_iter()
+                  .map(|(local, is_namespace)| VarDeclarator {
+                    span: DUMMY_SP,
+                    name: Pat::Ident(BindingIdent {
+                      id: local,
+                      type_ann: None,
+                    }),
+                    init: Some(Box::new(new_url_expr(url.as_str(), is_namespace))),
+                    definite: false,
+                  })
+                  .collect(),
+              }))));
+            }
+            continue;
+          }
+        }
+      }
+      output.push(item);
+    }
+    output
+  }
+}
+
+/// returns the locals of the import specifiers with the namespace flag, or `None`
+/// if the specifiers import the names other than `default`.
+fn new_asset_var_decls(specifiers: &[ImportSpecifier]) -> Option<Vec<(Ident, bool)>> {
+  specifiers
+    .iter()
+    .map(|specifier| match specifier {
+      // match: import logo from "./logo.svg"
+      ImportSpecifier::Default(ImportDefaultSpecifier { local, .. }) => {
+        Some((local.clone(), false))
+      }
+      // match: import { default as logo } from "./logo.svg"
+      ImportSpecifier::Named(ImportNamedSpecifier {
+        local,
+        imported: Some(imported),
+        ..
+      }) if imported.sym.as_ref().eq("default") => Some((local.clone(), false)),
+      // match: import * as logo from "./logo.svg"
+      ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => {
+        Some((local.clone(), true))
+      }
+      _ => None,
+    })
+    .collect()
+}
+
+// match: "/logo.svg" or { default: "/logo.svg" }
+fn new_url_expr(url: &str, is_namespace: bool) -> Expr {
+  let url = Expr::Lit(Lit::Str(Str {
+    span: DUMMY_SP,
+    value: url.into(),
+    has_escape: false,
+    kind: StrKind::Synthesized,
+  }));
+  if is_namespace {
+    Expr::Object(ObjectLit {
+      span: DUMMY_SP,
+      props: vec![PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+        key: PropName::Ident(quote_ident!("default")),
+        value: Box::new(url),
+      })))],
+    })
+  } else {
+    url
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::import_map::ImportHashMap;
+  use crate::resolve::Resolver;
+  use crate::swc::{EmitOptions, SWC};
+  use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+  #[test]
+  fn asset_imports() {
+    let source = r#"
+      import logo from "../assets/logo.svg"
+      import * as font from "/fonts/inter.woff2"
+      import { default as wasmUrl } from "./lib.wasm"
+      import "./banner.png"
+      import { width } from "./chart.svg"
+      import Button from "../components/button.tsx"
+      export default { logo, font, wasmUrl, width, Button }
+    "#;
+    let mut hashes: HashMap<String, String> = HashMap::new();
+    hashes.insert("/assets/logo.svg".into(), "1a2b3c4d".into());
+    let module = SWC::parse("/pages/index.tsx", source, None).expect("could not parse module");
+    let resolver = Rc::new(RefCell::new(
+      Resolver::new(
+        "/pages/index.tsx",
+        ImportHashMap::default(),
+        None,
+        None,
+        false,
+        vec![],
+      )
+      .with_dep_hashes(hashes)
+      .with_asset_url("/_aleph/assets[url]?v=[hash]"),
+    ));
+    let (code, _) = module
+      .transform(resolver.clone(), &EmitOptions::default())
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains("const logo = \"/_aleph/assets/assets/logo.svg?v=1a2b3c\""));
+    assert!(code
+      .contains("const font = {\n    default: \"/_aleph/assets/fonts/inter.woff2?v=000000\"\n}"));
+    assert!(code.contains("const wasmUrl = \"/_aleph/assets/pages/lib.wasm?v=000000\""));
+    assert!(!code.contains("banner.png"));
+    assert!(code.contains("import { width } from \"./chart.svg.js"));
+    assert!(code.contains("import Button from \"../components/button.js"));
+    assert_eq!(
+      resolver.borrow().assets,
+      vec![
+        "/assets/logo.svg",
+        "/fonts/inter.woff2",
+        "/pages/lib.wasm",
+        "/pages/banner.png",
+      ]
+    );
+  }
+}
@@ -0,0 +1,99 @@
+use crate::swc::SWC;
+
+use serde_json::{json, Map, Value};
+use swc_common::{BytePos, SourceMap};
+
+/// returns the AST of the module as JSON for the tooling, the `span` of each node is
+/// mapped to the byte offsets in the module source with the `loc` like ESTree:
+///
+/// ```json
+/// { "start": 6, "end": 11, "loc": { "start": { "line": 1, "column": 6 }, "end": { "line": 1, "column": 11 } } }
+/// ```
+///
+/// The lines are 1-based and the columns are the 0-based char indexes, the syntax
+/// contexts of the spans are dropped since the module is not resolved.
+pub fn module_to_json(swc: &SWC) -> Value {
+  let mut value = serde_json::to_value(&swc.module).unwrap_or(Value::Null);
+  map_spans(&mut value, swc.source_map.as_ref());
+  value
+}
+
+fn map_spans(value: &mut Value, source_map: &SourceMap) {
+  match value {
+    Value::Object(object) => {
+      for (key, value) in object.iter_mut() {
+        match get_span_range(value) {
+          Some((start, end)) if key == "span" => *value = new_span(start, end, source_map),
+          _ => map_spans(value, source_map),
+        }
+      }
+    }
+    Value::Array(array) => {
+      for value in array.iter_mut() {
+        map_spans(value, source_map);
+      }
+    }
+    _ => {}
+  }
+}
+
+// match: { "start": 6, "end": 11, "ctxt": 0 }
+fn get_span_range(value: &Value) -> Option<(u32, u32)> {
+  let object = value.as_object()?;
+  if object.len() != 3 || !object.contains_key("ctxt") {
+    return None;
+  }
+  let start = object.get("start")?.as_u64()?;
+  let end = object.get("end")?.as_u64()?;
+  Some((start as u32, end as u32))
+}
+
+fn new_span(start: u32, end: u32, source_map: &SourceMap) -> Value {
+  let mut span = Map::new();
+  let mut loc = Map::new();
+  for (key, pos) in [("start", start), ("end", end)].iter() {
+    let pos = source_map.lookup_char_pos(BytePos(*pos));
+    loc.insert(
+      key.to_string(),
+      json!({ "line": pos.line, "column": pos.col.0 }),
+    );
+  }
+  span.insert("start".into(), json!(start));
+  span.insert("end".into(), json!(end));
+  span.insert("loc".into(), Value::Object(loc));
+  Value::Object(span)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn ast_to_json() {
+    let source = "const a = 1\nexport default <p>ä{a}</p>\n";
+    let swc = SWC::parse("/app.tsx", source, None).expect("could not parse module");
+    let ast = module_to_json(&swc);
+    println!("{}", ast);
+    assert_eq!(ast["type"], "Module");
+    assert_eq!(ast["body"][0]["type"], "VariableDeclaration");
+    assert_eq!(
+      ast["body"][0]["span"],
+      json!({ "start": 0, "end": 11, "loc": { "start": { "line": 1, "column": 0 }, "end": { "line": 1, "column": 11 } } })
+    );
+    let declarator = &ast["body"][0]["declarations"][0];
+    assert_eq!(declarator["id"]["value"], "a");
+    assert_eq!(declarator["id"]["span"]["loc"]["start"]["column"], 6);
+    let element = &ast["body"][1]["expression"];
+    assert_eq!(element["type"], "JSXElement");
+    assert_eq!(
+      element["span"]["loc"]["start"],
+      json!({ "line": 2, "column": 15 })
+    );
+    // the offsets are in bytes, the columns are in chars
+    assert_eq!(element["span"]["end"], 39);
+    assert_eq!(
+      element["span"]["loc"]["end"],
+      json!({ "line": 2, "column": 26 })
+    );
+  }
+}
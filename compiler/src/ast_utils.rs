@@ -0,0 +1,60 @@
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::quote_ident;
+
+/// creates the string literal of the `value`.
+pub fn new_str(value: &str) -> Str {
+  Str {
+    span: DUMMY_SP,
+    value: value.into(),
+    has_escape: false,
+    kind: Default::default(),
+  }
+}
+
+/// creates the `callee(...args)` call.
+pub fn new_call(callee: Expr, args: Vec<Expr>) -> Expr {
+  Expr::Call(CallExpr {
+    span: DUMMY_SP,
+    callee: ExprOrSuper::Expr(Box::new(callee)),
+    args: args
+      .into_iter()
+      .map(|expr| ExprOrSpread {
+        spread: None,
+        expr: Box::new(expr),
+      })
+      .collect(),
+    type_args: None,
+  })
+}
+
+/// creates the `obj.prop` member expression.
+pub fn new_member(obj: Expr, prop: &str) -> Expr {
+  Expr::Member(MemberExpr {
+    span: DUMMY_SP,
+    obj: ExprOrSuper::Expr(Box::new(obj)),
+    prop: Box::new(Expr::Ident(quote_ident!(prop))),
+    computed: false,
+  })
+}
+
+/// creates the `left = right` statement.
+pub fn new_assign_stmt(left: Ident, right: Expr) -> Stmt {
+  new_expr_stmt(Expr::Assign(AssignExpr {
+    span: DUMMY_SP,
+    op: AssignOp::Assign,
+    left: PatOrExpr::Pat(Box::new(Pat::Ident(BindingIdent {
+      id: left,
+      type_ann: None,
+    }))),
+    right: Box::new(right),
+  }))
+}
+
+/// creates the statement of the `expr`.
+pub fn new_expr_stmt(expr: Expr) -> Stmt {
+  Stmt::Expr(ExprStmt {
+    span: DUMMY_SP,
+    expr: Box::new(expr),
+  })
+}
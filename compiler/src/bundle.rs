@@ -0,0 +1,969 @@
+use crate::ast_utils::new_str;
+use crate::cycles::find_cycles;
+use crate::define::define_fold;
+use crate::jsx_whitespace::{jsx_whitespace_fold, JsxWhitespace};
+use crate::keep_names::keep_names_fold;
+use crate::resolve::Resolver;
+use crate::source_type::SourceType;
+use crate::swc::{jsx_options, EmitOptions, SourceMapOptions, SWC};
+use crate::tree_shake::tree_shake_fold;
+
+use indexmap::{IndexMap, IndexSet};
+use std::{
+  cell::RefCell,
+  collections::{HashMap, HashSet},
+  path::Path,
+  rc::Rc,
+};
+use swc_common::{chain, comments::SingleThreadedComments, Globals, Mark, SourceMap, DUMMY_SP};
+use swc_ecma_ast::*;
+use swc_ecma_transforms_typescript::strip;
+use swc_ecma_utils::{find_ids, id, private_ident, quote_ident, Id};
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+use swc_ecmascript::transforms::{
+  fixer, hygiene,
+  optimization::simplify::{dead_branch_remover, expr_simplifier},
+  pass::Optional,
+  react,
+  resolver::resolver_with_mark,
+};
+
+/// The code, the source map, the bundled modules and the import cycles of a bundle.
+pub type Bundled = (String, Option<String>, Vec<String>, Vec<Vec<String>>);
+
+/// Concatenates the module graph of the `entry` into a single ES module. The modules
+/// in `sources` (the pre-fetched sources keyed by the fixed url) are hoisted into the
+/// scope of the bundle and the imports between them are replaced with the bindings
+/// they refer to, the imports of other modules are kept as the external imports of
+/// the bundle and resolved relative to the `entry`:
+///
+/// ```js
+/// // /lib/util.ts
+/// export const sum = (a, b) => a + b
+/// // /pages/index.tsx
+/// import { sum } from "../lib/util.ts"
+/// export const answer = sum(40, 2)
+/// ```
+///
+/// is bundled to:
+///
+/// ```js
+/// const sum = (a, b)=>a + b;
+/// const answer = sum(40, 2);
+/// export { answer as answer };
+/// ```
+///
+/// Returns the code, the source map, the bundled modules in evaluation order and the
+/// import cycles between the bundled modules.
+pub fn bundle(
+  entry: &str,
+  sources: &HashMap<String, String>,
+  resolver: Rc<RefCell<Resolver>>,
+  options: &EmitOptions,
+) -> Result<Bundled, anyhow::Error> {
+  swc_common::GLOBALS.set(&Globals::new(), || {
+    let mut bundler = Bundler {
+      sources,
+      resolver,
+      options,
+      source_map: Rc::new(SourceMap::default()),
+      comments: SingleThreadedComments::default(),
+      loaded: HashSet::new(),
+      modules: vec![],
+      links: HashMap::new(),
+      externals: IndexMap::new(),
+      namespaces: IndexMap::new(),
+      namespace_decls: vec![],
+    };
+    bundler.load(entry)?;
+    let module = bundler.link(entry)?;
+    let specifiers = bundler
+      .modules
+      .iter()
+      .map(|(specifier, _)| specifier.clone())
+      .collect();
+    let swc = SWC {
+      specifier: entry.into(),
+      module,
+      source_type: SourceType::JS,
+      source_map: bundler.source_map.clone(),
+      comments: bundler.comments.clone(),
+    };
+    // the unused exports of the entry and the bindings of the bundled modules that
+    // are not referenced anymore are dropped
+    let used_exports = options.used_exports.clone().unwrap_or_default();
+    let keep_names = options.keep_fn_names || options.keep_class_names;
+    let (names_collector, keep_names_fold) =
+      keep_names_fold(options.keep_fn_names, options.keep_class_names);
+    let (code, map) = swc.apply_transform(
+      chain!(
+        // the names are recorded before the hygiene renames the conflicting names
+        Optional::new(names_collector, keep_names),
+        Optional::new(
+          tree_shake_fold(
+            bundler.resolver.clone(),
+            &used_exports,
+            vec![],
+            &options.side_effect_free_deps
+          ),
+          options.used_exports.is_some()
+        ),
+        hygiene(),
+        Optional::new(keep_names_fold, keep_names),
+        fixer(Some(&swc.comments))
+      ),
+      &options.source_map,
+      SourceMapOptions {
+        input_source_map: None,
+        ..options.source_map_options()
+      },
+      options.minify,
+      false,
+    )?;
+    Ok((code, map, specifiers, bundler.find_cycles()))
+  })
+}
+
+/// What an imported or exported name refers to.
+#[derive(Clone, Debug)]
+enum Binding {
+  /// a top-level binding of a bundled module.
+  Local(Ident),
+  /// an export of a bundled module.
+  Reexport(String, String),
+  /// the namespace object of a bundled module.
+  Namespace(String),
+  /// an export of an external module, `*` for the namespace.
+  External(String, String),
+}
+
+/// The imports and exports of a bundled module.
+#[derive(Default)]
+struct ModuleLinks {
+  /// the fixed urls of the static imports/exports keyed by the source.
+  deps: IndexMap<String, String>,
+  /// the bindings of the imports keyed by the local id.
+  imports: HashMap<Id, Binding>,
+  /// the bindings of the exports keyed by the exported name.
+  exports: IndexMap<String, Binding>,
+  /// the fixed urls of `export * from "..."`.
+  star_exports: Vec<String>,
+}
+
+struct Bundler<'a> {
+  sources: &'a HashMap<String, String>,
+  resolver: Rc<RefCell<Resolver>>,
+  options: &'a EmitOptions,
+  source_map: Rc<SourceMap>,
+  comments: SingleThreadedComments,
+  loaded: HashSet<String>,
+  /// the bundled modules in evaluation order.
+  modules: Vec<(String, Module)>,
+  links: HashMap<String, ModuleLinks>,
+  /// the locals of the external imports keyed by the fixed url and the name.
+  externals: IndexMap<(String, String), Ident>,
+  /// the locals of the namespace objects keyed by the fixed url.
+  namespaces: IndexMap<String, Ident>,
+  /// the declarations of the namespace objects that are not emitted yet.
+  namespace_decls: Vec<ModuleItem>,
+}
+
+impl<'a> Bundler<'a> {
+  fn is_bundled(&self, url: &str) -> bool {
+    self.sources.contains_key(url)
+  }
+
+  /// finds the import cycles between the bundled modules, the modules are visited
+  /// from the entry, so a cycle starts with the module that is imported first.
+  fn find_cycles(&self) -> Vec<Vec<String>> {
+    let graph: IndexMap<String, Vec<String>> = self
+      .modules
+      .iter()
+      .rev()
+      .map(|(specifier, _)| {
+        let deps = self.links[specifier].deps.values().cloned().collect();
+        (specifier.clone(), deps)
+      })
+      .collect();
+    find_cycles(&graph)
+  }
+
+  /// parses and transforms the module, then loads its dependencies before adding
+  /// the module, so the dependencies come first in the bundle.
+  fn load(&mut self, specifier: &str) -> Result<(), anyhow::Error> {
+    if !self.loaded.insert(specifier.into()) {
+      return Ok(());
+    }
+    let source = self
+      .sources
+      .get(specifier)
+      .ok_or_else(|| anyhow::anyhow!("module \"{}\" is not found", specifier))?;
+    let swc = SWC::parse_with_source_map(
+      specifier,
+      source,
+      None,
+      &Default::default(),
+      self.source_map.clone(),
+      self.comments.clone(),
+    )?;
+    let is_ts = matches!(swc.source_type, SourceType::TS | SourceType::TSX);
+    let is_jsx = matches!(swc.source_type, SourceType::JSX | SourceType::TSX);
+    let define = &self.options.define;
+    let mut module = {
+      let mut passes = chain!(
+        Optional::new(
+          chain!(
+            define_fold(define, &self.source_map)?,
+            expr_simplifier(),
+            dead_branch_remover()
+          ),
+          !define.is_empty()
+        ),
+        Optional::new(
+          jsx_whitespace_fold(
+            self.options.jsx_whitespace,
+            self.options.jsx_drop_empty_children
+          ),
+          is_jsx
+            && (self.options.jsx_whitespace != JsxWhitespace::Collapse
+              || self.options.jsx_drop_empty_children)
+        ),
+        Optional::new(
+          react::jsx(
+            self.source_map.clone(),
+            Some(&self.comments),
+            jsx_options(self.options),
+          ),
+          is_jsx
+        ),
+        Optional::new(strip(), is_ts),
+        // the top-level bindings of every module are marked, the hygiene renames
+        // the conflicting names after the modules are concatenated.
+        resolver_with_mark(Mark::fresh(Mark::root())),
+      );
+      swc.module.fold_with(&mut passes)
+    };
+    let links = self.collect_links(specifier, &mut module);
+    let deps: IndexSet<String> = links.deps.values().cloned().collect();
+    self.links.insert(specifier.into(), links);
+    for url in deps {
+      if self.is_bundled(url.as_str()) {
+        self.load(url.as_str())?;
+      }
+    }
+    self.modules.push((specifier.into(), module));
+    Ok(())
+  }
+
+  /// collects the imports and exports of the module, the default exports are
+  /// bound to the local names.
+  fn collect_links(&self, specifier: &str, module: &mut Module) -> ModuleLinks {
+    let resolver = self.resolver.borrow().fork(specifier);
+    let mut links = ModuleLinks::default();
+    let default_name = format!("{}_default", get_ident_name(specifier));
+    let fix_url = |links: &mut ModuleLinks, src: &Str| -> String {
+      let url = resolver.fix_url(src.value.as_ref());
+      links.deps.insert(src.value.as_ref().into(), url.clone());
+      url
+    };
+    for item in module.body.iter_mut() {
+      match item {
+        ModuleItem::ModuleDecl(ModuleDecl::Import(decl)) => {
+          let url = fix_url(&mut links, &decl.src);
+          let bundled = self.is_bundled(url.as_str());
+          for specifier in decl.specifiers.iter() {
+            let (local, name) = match specifier {
+              ImportSpecifier::Named(ImportNamedSpecifier {
+                local, imported, ..
+              }) => (local, imported.as_ref().unwrap_or(local).sym.as_ref()),
+              ImportSpecifier::Default(ImportDefaultSpecifier { local, .. }) => (local, "default"),
+              ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => (local, "*"),
+            };
+            let binding = match (bundled, name) {
+              (true, "*") => Binding::Namespace(url.clone()),
+              (true, _) => Binding::Reexport(url.clone(), name.into()),
+              (false, _) => Binding::External(url.clone(), name.into()),
+            };
+            links.imports.insert(id(local), binding);
+          }
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => {
+          let ids: Vec<Ident> = match decl {
+            Decl::Fn(FnDecl { ident, .. }) | Decl::Class(ClassDecl { ident, .. }) => {
+              vec![ident.clone()]
+            }
+            Decl::Var(var) => find_ids(&var.decls),
+            _ => vec![],
+          };
+          for id in ids {
+            links
+              .exports
+              .insert(id.sym.as_ref().into(), Binding::Local(id));
+          }
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(ExportDefaultDecl {
+          decl, ..
+        })) => {
+          let ident = match decl {
+            DefaultDecl::Fn(FnExpr { ident, .. }) | DefaultDecl::Class(ClassExpr { ident, .. }) => {
+              ident.get_or_insert_with(|| private_ident!(default_name.clone()))
+            }
+            DefaultDecl::TsInterfaceDecl(_) => continue,
+          };
+          links
+            .exports
+            .insert("default".into(), Binding::Local(ident.clone()));
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(ExportDefaultExpr {
+          expr, ..
+        })) => {
+          // export default expr -> const _default = expr
+          let ident = private_ident!(default_name.clone());
+          links
+            .exports
+            .insert("default".into(), Binding::Local(ident.clone()));
+          *item = ModuleItem::Stmt(Stmt::Decl(Decl::Var(VarDecl {
+            span: DUMMY_SP,
+            kind: VarDeclKind::Const,
+            declare: false,
+            decls: vec![VarDeclarator {
+              span: DUMMY_SP,
+              name: Pat::Ident(BindingIdent {
+                id: ident,
+                type_ann: None,
+              }),
+              init: Some(std::mem::replace(
+                expr,
+                Box::new(Expr::Invalid(Invalid { span: DUMMY_SP })),
+              )),
+              definite: false,
+            }],
+          })));
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(NamedExport {
+          specifiers, src, ..
+        })) => {
+          let url = src.as_ref().map(|src| fix_url(&mut links, src));
+          for specifier in specifiers.iter() {
+            let (name, binding) = match (specifier, &url) {
+              // match: export { a as b }
+              (ExportSpecifier::Named(ExportNamedSpecifier { orig, exported, .. }), None) => (
+                exported.as_ref().unwrap_or(orig).sym.as_ref(),
+                Binding::Local(orig.clone()),
+              ),
+              // match: export { a as b } from "./mod.ts"
+              (ExportSpecifier::Named(ExportNamedSpecifier { orig, exported, .. }), Some(url)) => (
+                exported.as_ref().unwrap_or(orig).sym.as_ref(),
+                self.new_reexport(url, orig.sym.as_ref()),
+              ),
+              // match: export * as ns from "./mod.ts"
+              (ExportSpecifier::Namespace(ExportNamespaceSpecifier { name, .. }), Some(url)) => (
+                name.sym.as_ref(),
+                if self.is_bundled(url) {
+                  Binding::Namespace(url.clone())
+                } else {
+                  Binding::External(url.clone(), "*".into())
+                },
+              ),
+              // match: export v from "./mod.ts"
+              (ExportSpecifier::Default(ExportDefaultSpecifier { exported }), Some(url)) => {
+                (exported.sym.as_ref(), self.new_reexport(url, "default"))
+              }
+              _ => continue,
+            };
+            links.exports.insert(name.into(), binding);
+          }
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportAll(ExportAll { src, .. })) => {
+          let url = fix_url(&mut links, src);
+          links.star_exports.push(url);
+        }
+        _ => {}
+      }
+    }
+    links
+  }
+
+  fn new_reexport(&self, url: &str, name: &str) -> Binding {
+    if self.is_bundled(url) {
+      Binding::Reexport(url.into(), name.into())
+    } else {
+      Binding::External(url.into(), name.into())
+    }
+  }
+
+  /// resolves the export `name` of the bundled module `url` to a binding that is
+  /// not a re-export.
+  fn resolve_export(&self, url: &str, name: &str, depth: usize) -> Result<Binding, anyhow::Error> {
+    if depth > 64 {
+      return Err(anyhow::anyhow!(
+        "circular re-export of \"{}\" in module \"{}\"",
+        name,
+        url
+      ));
+    }
+    let links = &self.links[url];
+    if let Some(binding) = links.exports.get(name) {
+      return self.resolve_binding(url, binding, depth + 1);
+    }
+    // the default export is not re-exported by `export *`
+    if name != "default" {
+      let mut external = None;
+      for star in links.star_exports.iter() {
+        if self.is_bundled(star) {
+          if let Ok(binding) = self.resolve_export(star, name, depth + 1) {
+            return Ok(binding);
+          }
+        } else if external.is_none() {
+          external = Some(Binding::External(star.clone(), name.into()));
+        }
+      }
+      if let Some(binding) = external {
+        return Ok(binding);
+      }
+    }
+    Err(anyhow::anyhow!(
+      "module \"{}\" has no exported member \"{}\"",
+      url,
+      name
+    ))
+  }
+
+  fn resolve_binding(
+    &self,
+    url: &str,
+    binding: &Binding,
+    depth: usize,
+  ) -> Result<Binding, anyhow::Error> {
+    match binding {
+      // the local may be imported from another module
+      Binding::Local(ident) => match self.links[url].imports.get(&id(ident)) {
+        Some(binding) => self.resolve_binding(url, &binding.clone(), depth + 1),
+        None => Ok(binding.clone()),
+      },
+      Binding::Reexport(url, name) => self.resolve_export(url, name, depth + 1),
+      _ => Ok(binding.clone()),
+    }
+  }
+
+  /// returns all the export names of the bundled module `url`, and the external
+  /// modules that the names are re-exported from with `export *`.
+  fn export_names(
+    &self,
+    url: &str,
+    visited: &mut HashSet<String>,
+  ) -> (IndexSet<String>, IndexSet<String>) {
+    let mut names = IndexSet::new();
+    let mut externals = IndexSet::new();
+    if !visited.insert(url.into()) {
+      return (names, externals);
+    }
+    let links = &self.links[url];
+    names.extend(links.exports.keys().cloned());
+    for star in links.star_exports.iter() {
+      if self.is_bundled(star) {
+        let (star_names, star_externals) = self.export_names(star, visited);
+        names.extend(star_names.into_iter().filter(|name| name != "default"));
+        externals.extend(star_externals);
+      } else {
+        externals.insert(star.clone());
+      }
+    }
+    (names, externals)
+  }
+
+  /// returns the local ident of a resolved binding.
+  fn get_ident(&mut self, binding: Binding) -> Result<Ident, anyhow::Error> {
+    Ok(match binding {
+      Binding::Local(ident) => ident,
+      Binding::External(url, name) => self
+        .externals
+        .entry((url.clone(), name.clone()))
+        .or_insert_with(|| {
+          private_ident!(match name.as_str() {
+            "*" | "default" => get_ident_name(url.as_str()),
+            _ => name.clone(),
+          })
+        })
+        .clone(),
+      Binding::Namespace(url) => self.get_namespace_ident(url.as_str())?,
+      Binding::Reexport(url, name) => {
+        let binding = self.resolve_export(url.as_str(), name.as_str(), 0)?;
+        self.get_ident(binding)?
+      }
+    })
+  }
+
+  /// returns the local ident of the namespace object of the bundled module `url`,
+  /// the object is declared before the module that uses it:
+  ///
+  /// ```js
+  /// const util = Object.freeze({ __proto__: null, get sum () { return sum; } });
+  /// ```
+  fn get_namespace_ident(&mut self, url: &str) -> Result<Ident, anyhow::Error> {
+    if let Some(ident) = self.namespaces.get(url) {
+      return Ok(ident.clone());
+    }
+    let ident = private_ident!(get_ident_name(url));
+    self.namespaces.insert(url.into(), ident.clone());
+    let (names, _) = self.export_names(url, &mut HashSet::new());
+    let mut props = vec![PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+      key: PropName::Ident(quote_ident!("__proto__")),
+      value: Box::new(Expr::Lit(Lit::Null(Null { span: DUMMY_SP }))),
+    })))];
+    for name in names {
+      let binding = self.resolve_export(url, name.as_str(), 0)?;
+      let local = self.get_ident(binding)?;
+      props.push(PropOrSpread::Prop(Box::new(Prop::Getter(GetterProp {
+        span: DUMMY_SP,
+        key: PropName::Ident(quote_ident!(name)),
+        type_ann: None,
+        body: Some(BlockStmt {
+          span: DUMMY_SP,
+          stmts: vec![Stmt::Return(ReturnStmt {
+            span: DUMMY_SP,
+            arg: Some(Box::new(Expr::Ident(local))),
+          })],
+        }),
+      }))));
+    }
+    self
+      .namespace_decls
+      .push(new_const_decl(ident.clone(), new_object_freeze(props)));
+    Ok(ident)
+  }
+
+  /// concatenates the bundled modules into one module.
+  fn link(&mut self, entry: &str) -> Result<Module, anyhow::Error> {
+    let mut body = vec![];
+    let modules = std::mem::take(&mut self.modules);
+    for (specifier, module) in modules.iter() {
+      let mut renames = HashMap::new();
+      let imports: Vec<(Id, Binding)> = self.links[specifier]
+        .imports
+        .iter()
+        .map(|(local, binding)| (local.clone(), binding.clone()))
+        .collect();
+      for (local, binding) in imports {
+        let binding = self.resolve_binding(specifier, &binding, 0)?;
+        // the default and namespace imports of the external modules use the local name
+        if let Binding::External(url, name) = &binding {
+          if name == "*" || name == "default" {
+            self
+              .externals
+              .entry((url.clone(), name.clone()))
+              .or_insert_with(|| private_ident!(local.0.clone()));
+          }
+        }
+        renames.insert(local, self.get_ident(binding)?);
+      }
+      let mut items = vec![];
+      for item in module.body.iter() {
+        match item {
+          ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => {
+            items.push(ModuleItem::Stmt(Stmt::Decl(decl.clone())));
+          }
+          ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(ExportDefaultDecl {
+            decl, ..
+          })) => match decl {
+            DefaultDecl::Fn(FnExpr {
+              ident: Some(ident),
+              function,
+            }) => items.push(ModuleItem::Stmt(Stmt::Decl(Decl::Fn(FnDecl {
+              ident: ident.clone(),
+              declare: false,
+              function: function.clone(),
+            })))),
+            DefaultDecl::Class(ClassExpr {
+              ident: Some(ident),
+              class,
+            }) => items.push(ModuleItem::Stmt(Stmt::Decl(Decl::Class(ClassDecl {
+              ident: ident.clone(),
+              declare: false,
+              class: class.clone(),
+            })))),
+            _ => {}
+          },
+          ModuleItem::ModuleDecl(ModuleDecl::TsImportEquals(_))
+          | ModuleItem::ModuleDecl(ModuleDecl::TsExportAssignment(_)) => {
+            return Err(anyhow::anyhow!(
+              "module \"{}\" can't be bundled: `import =` and `export =` are not supported",
+              specifier
+            ))
+          }
+          // the imports and exports are linked
+          ModuleItem::ModuleDecl(_) => {}
+          ModuleItem::Stmt(stmt) => items.push(ModuleItem::Stmt(stmt.clone())),
+        }
+      }
+      let mut fold = LinkFold {
+        renames,
+        module_resolver: self.resolver.borrow().fork(specifier),
+        resolver: self.resolver.clone(),
+      };
+      body.append(&mut self.namespace_decls);
+      body.extend(items.fold_with(&mut fold));
+    }
+    self.modules = modules;
+
+    // export { a as a, b as default }
+    let mut specifiers = vec![];
+    let (names, star_externals) = self.export_names(entry, &mut HashSet::new());
+    for name in names {
+      let binding = self.resolve_export(entry, name.as_str(), 0)?;
+      let local = self.get_ident(binding)?;
+      // the exported name shares the context with the local if they are same,
+      // so the hygiene doesn't rename the local for the conflict.
+      let exported = if local.sym.as_ref() == name {
+        local.clone()
+      } else {
+        quote_ident!(name)
+      };
+      specifiers.push(ExportSpecifier::Named(ExportNamedSpecifier {
+        span: DUMMY_SP,
+        orig: local,
+        exported: Some(exported),
+      }));
+    }
+    // the namespaces of the entry exports
+    body.append(&mut self.namespace_decls);
+    if !specifiers.is_empty() {
+      body.push(ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(
+        NamedExport {
+          span: DUMMY_SP,
+          specifiers,
+          src: None,
+          type_only: false,
+          asserts: None,
+        },
+      )));
+    }
+
+    // the external imports are hoisted to the top of the bundle, in the order
+    // they are imported by the modules.
+    let mut resolver = self.resolver.borrow_mut();
+    let mut imports: IndexMap<String, Vec<ImportSpecifier>> = IndexMap::new();
+    for (specifier, _) in self.modules.iter() {
+      for url in self.links[specifier].deps.values() {
+        if !self.is_bundled(url) {
+          imports.entry(url.clone()).or_default();
+        }
+      }
+    }
+    for ((url, name), local) in self.externals.iter() {
+      let specifier = match name.as_str() {
+        "*" => ImportSpecifier::Namespace(ImportStarAsSpecifier {
+          span: DUMMY_SP,
+          local: local.clone(),
+        }),
+        "default" => ImportSpecifier::Default(ImportDefaultSpecifier {
+          span: DUMMY_SP,
+          local: local.clone(),
+        }),
+        _ => ImportSpecifier::Named(ImportNamedSpecifier {
+          span: DUMMY_SP,
+          local: local.clone(),
+          imported: Some(quote_ident!(name.clone())),
+        }),
+      };
+      imports.entry(url.clone()).or_default().push(specifier);
+    }
+    let mut items = vec![];
+    for (url, specifiers) in imports {
+      let (resolved_path, _) = resolver.resolve(url.as_str(), false, None);
+      // the namespace import can't be combined with the named imports
+      let (namespaces, specifiers): (Vec<_>, Vec<_>) = specifiers
+        .into_iter()
+        .partition(|specifier| matches!(specifier, ImportSpecifier::Namespace(_)));
+      let namespaces_len = namespaces.len();
+      for namespace in namespaces {
+        items.push(new_import_decl(vec![namespace], resolved_path.as_str()));
+      }
+      if !specifiers.is_empty() || namespaces_len == 0 {
+        items.push(new_import_decl(specifiers, resolved_path.as_str()));
+      }
+    }
+    for url in star_externals {
+      let (resolved_path, _) = resolver.resolve(url.as_str(), false, None);
+      body.push(ModuleItem::ModuleDecl(ModuleDecl::ExportAll(ExportAll {
+        span: DUMMY_SP,
+        src: new_str(resolved_path.as_str()),
+        asserts: None,
+      })));
+    }
+    items.append(&mut body);
+
+    Ok(Module {
+      span: DUMMY_SP,
+      body: items,
+      shebang: None,
+    })
+  }
+}
+
+/// Replaces the imported locals with the bindings they refer to, and resolves the
+/// dynamic imports relative to the entry.
+struct LinkFold {
+  renames: HashMap<Id, Ident>,
+  module_resolver: Resolver,
+  resolver: Rc<RefCell<Resolver>>,
+}
+
+impl Fold for LinkFold {
+  noop_fold_type!();
+
+  fn fold_ident(&mut self, ident: Ident) -> Ident {
+    match self.renames.get(&id(&ident)) {
+      Some(local) => Ident {
+        span: ident.span.with_ctxt(local.span.ctxt),
+        sym: local.sym.clone(),
+        ..ident
+      },
+      None => ident,
+    }
+  }
+
+  // match: { a } -> { a: b }
+  fn fold_prop(&mut self, prop: Prop) -> Prop {
+    match prop {
+      Prop::Shorthand(ident) if self.renames.contains_key(&id(&ident)) => {
+        Prop::KeyValue(KeyValueProp {
+          key: PropName::Ident(quote_ident!(ident.span, ident.sym.clone())),
+          value: Box::new(Expr::Ident(self.fold_ident(ident))),
+        })
+      }
+      _ => prop.fold_children_with(self),
+    }
+  }
+
+  // match: import("./mod.ts")
+  fn fold_call_expr(&mut self, call: CallExpr) -> CallExpr {
+    let mut call = call.fold_children_with(self);
+    let is_import = matches!(&call.callee, ExprOrSuper::Expr(callee) if matches!(callee.as_ref(), Expr::Ident(id) if id.sym.as_ref().eq("import")));
+    if is_import {
+      if let Some(ExprOrSpread { expr, .. }) = call.args.first_mut() {
+        if let Expr::Lit(Lit::Str(src)) = expr.as_mut() {
+          let fixed_url = self.module_resolver.fix_url(src.value.as_ref());
+          let (resolved_path, _) =
+            self
+              .resolver
+              .borrow_mut()
+              .resolve(fixed_url.as_str(), true, Some(src.span));
+          *src = new_str(resolved_path.as_str());
+        }
+      }
+    }
+    call
+  }
+}
+
+/// returns a readable identifier name for the module, like `/lib/util.ts` -> `util`.
+fn get_ident_name(url: &str) -> String {
+  let stem = Path::new(url.trim_end_matches('/'))
+    .file_stem()
+    .and_then(|stem| stem.to_str())
+    .unwrap_or("mod");
+  let mut name: String = stem
+    .chars()
+    .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+    .collect();
+  if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+    name.insert(0, '_');
+  }
+  name
+}
+
+fn new_import_decl(specifiers: Vec<ImportSpecifier>, src: &str) -> ModuleItem {
+  ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+    span: DUMMY_SP,
+    specifiers,
+    src: new_str(src),
+    type_only: false,
+    asserts: None,
+  }))
+}
+
+fn new_const_decl(ident: Ident, init: Expr) -> ModuleItem {
+  ModuleItem::Stmt(Stmt::Decl(Decl::Var(VarDecl {
+    span: DUMMY_SP,
+    kind: VarDeclKind::Const,
+    declare: false,
+    decls: vec![VarDeclarator {
+      span: DUMMY_SP,
+      name: Pat::Ident(BindingIdent {
+        id: ident,
+        type_ann: None,
+      }),
+      init: Some(Box::new(init)),
+      definite: false,
+    }],
+  })))
+}
+
+// match: Object.freeze({})
+fn new_object_freeze(props: Vec<PropOrSpread>) -> Expr {
+  Expr::Call(CallExpr {
+    span: DUMMY_SP,
+    callee: ExprOrSuper::Expr(Box::new(Expr::Member(MemberExpr {
+      span: DUMMY_SP,
+      obj: ExprOrSuper::Expr(Box::new(Expr::Ident(quote_ident!("Object")))),
+      prop: Box::new(Expr::Ident(quote_ident!("freeze"))),
+      computed: false,
+    }))),
+    args: vec![ExprOrSpread {
+      spread: None,
+      expr: Box::new(Expr::Object(ObjectLit {
+        span: DUMMY_SP,
+        props,
+      })),
+    }],
+    type_args: None,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::swc::test_resolver;
+
+  fn b(entry: &str, sources: &[(&str, &str)]) -> Result<Bundled, anyhow::Error> {
+    let sources: HashMap<String, String> = sources
+      .iter()
+      .map(|(specifier, source)| (specifier.to_string(), source.to_string()))
+      .collect();
+    let resolver = test_resolver(entry);
+    let bundled = bundle(entry, &sources, resolver, &EmitOptions::default())?;
+    println!("{}", bundled.0);
+    Ok(bundled)
+  }
+
+  #[test]
+  fn bundle_modules() {
+    let (code, _, modules, cycles) = b(
+      "/pages/index.tsx",
+      &[
+        (
+          "/pages/index.tsx",
+          r#"
+          import React from "https://esm.sh/react"
+          import { sum, version as v } from "../lib/util.ts"
+          import * as math from "../lib/math.ts"
+          import Logo from "../components/logo.tsx"
+          import "../lib/setup.ts"
+          export { sub } from "../lib/math.ts"
+          export * from "../lib/util.ts"
+          const version = "index"
+          export default function Index() {
+            return <div title={version + v}><Logo />{sum(1, 2)}{math.sub(3, 1)}</div>
+          }
+        "#,
+        ),
+        (
+          "/lib/util.ts",
+          r#"
+          import { useState } from "https://esm.sh/react"
+          export const version: string = "1.0.0"
+          export function sum(a: number, b: number) { return a + b }
+          export const useCount = () => useState(0)
+        "#,
+        ),
+        (
+          "/lib/math.ts",
+          r#"
+          export { sum as add } from "./util.ts"
+          export const sub = (a: number, b: number) => a - b
+        "#,
+        ),
+        (
+          "/lib/setup.ts",
+          r#"
+          import "https://esm.sh/tailwindcss/dist/base.css"
+          console.log("setup")
+        "#,
+        ),
+        (
+          "/components/logo.tsx",
+          r#"
+          import React, { useState } from "https://esm.sh/react"
+          const version = "logo"
+          export default () => <img src="/logo.svg" alt={version} />
+        "#,
+        ),
+      ],
+    )
+    .expect("could not bundle modules");
+    assert_eq!(
+      modules,
+      vec![
+        "/lib/util.ts",
+        "/lib/math.ts",
+        "/components/logo.tsx",
+        "/lib/setup.ts",
+        "/pages/index.tsx"
+      ]
+    );
+    assert!(cycles.is_empty());
+    assert!(code.starts_with(
+      "import React, { useState as useState } from \"../-/esm.sh/react.js\";\nimport   \"../-/esm.sh/tailwindcss/dist/base.css.js\";\n"
+    ));
+    assert!(!code.contains("../lib/"));
+    assert!(code.contains("const version = \"1.0.0\";"));
+    assert!(code.contains("function sum(a, b) {"));
+    assert!(code.contains("const useCount = ()=>useState(0)"));
+    assert!(code.contains("const sub = (a, b)=>a - b"));
+    assert!(code.contains("get add () {\n        return sum;\n    }"));
+    assert!(code.contains("const version1 = \"logo\";"));
+    assert!(code.contains("const logo_default = ()=>React.createElement(\"img\""));
+    assert!(code.contains("console.log(\"setup\");"));
+    assert!(code.contains("const version2 = \"index\";"));
+    assert!(code.contains("title: version2 + version\n"));
+    assert!(code.contains("React.createElement(logo_default, null)"));
+    assert!(code.contains("math.sub(3, 1)"));
+    assert!(code.contains("export { sub as sub, Index as default, version as version, sum as sum, useCount as useCount };"));
+  }
+
+  #[test]
+  fn bundle_cycles() {
+    let (code, _, modules, cycles) = b(
+      "/app.ts",
+      &[
+        (
+          "/app.ts",
+          "import { a } from './a.ts'; import { c } from './c.ts'; console.log(a(), c)",
+        ),
+        (
+          "/a.ts",
+          "import { b } from './b.ts'; export const a = () => b()",
+        ),
+        (
+          "/b.ts",
+          "import { c } from './c.ts'; export const b = () => c",
+        ),
+        (
+          "/c.ts",
+          "import { a } from './a.ts'; export const c = () => a",
+        ),
+      ],
+    )
+    .expect("could not bundle modules");
+    assert_eq!(modules, vec!["/c.ts", "/b.ts", "/a.ts", "/app.ts"]);
+    assert_eq!(cycles, vec![vec!["/a.ts", "/b.ts", "/c.ts", "/a.ts"]]);
+    assert!(code.contains("console.log(a(), c);"));
+  }
+
+  #[test]
+  fn bundle_errors() {
+    let err = b(
+      "/app.ts",
+      &[
+        ("/app.ts", "import { foo } from './lib.ts'; foo()"),
+        ("/lib.ts", "export const bar = 1"),
+      ],
+    )
+    .unwrap_err();
+    assert_eq!(
+      err.to_string(),
+      "module \"/lib.ts\" has no exported member \"foo\""
+    );
+  }
+}
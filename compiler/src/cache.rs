@@ -0,0 +1,133 @@
+use crate::{Options, TransformOutput};
+
+use sha1::{Digest, Sha1};
+#[cfg(not(target_arch = "wasm32"))]
+use std::{fs, path::Path};
+
+/// returns the cache key of the transform, the hex sha-1 of the compiler version,
+/// the url, the options and the code. the options are hashed as the JSON with the
+/// sorted keys (the maps of `serde_json` are ordered), so the key doesn't depend on
+/// the order of the options, the cache options are not included.
+pub fn cache_key(url: &str, code: &str, options: &Options) -> String {
+  let options = serde_json::to_value(options)
+    .map(|value| value.to_string())
+    .unwrap_or_default();
+  let mut hasher = Sha1::new();
+  hasher.update(env!("CARGO_PKG_VERSION").as_bytes());
+  hasher.update(b"\n");
+  hasher.update(url.as_bytes());
+  hasher.update(b"\n");
+  hasher.update(options.as_bytes());
+  hasher.update(b"\n");
+  hasher.update(code.as_bytes());
+  format!("{:x}", hasher.finalize())
+}
+
+/// reads the output persisted by `write_cache`, a broken or missing cache file is a
+/// cache miss.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_cache(cache_dir: &str, cache_key: &str) -> Option<TransformOutput> {
+  let data = fs::read(Path::new(cache_dir).join(format!("{}.json", cache_key))).ok()?;
+  serde_json::from_slice(&data).ok()
+}
+
+/// persists the output as `{cache_dir}/{cache_key}.json`, the file is written to a
+/// temporary path and renamed, so the parallel transforms never read a partial file.
+/// the cache is best effort, the errors are ignored.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn write_cache(cache_dir: &str, output: &TransformOutput) {
+  let cache_key = match &output.cache_key {
+    Some(cache_key) => cache_key,
+    None => return,
+  };
+  let dir = Path::new(cache_dir);
+  let path = dir.join(format!("{}.json", cache_key));
+  let tmp_path = dir.join(format!(
+    "{}.json.{:?}.tmp",
+    cache_key,
+    std::thread::current().id()
+  ));
+  let _ = fs::create_dir_all(dir)
+    .and_then(|_| fs::write(&tmp_path, serde_json::to_vec(output).unwrap_or_default()))
+    .and_then(|_| fs::rename(&tmp_path, &path));
+}
+
+/// the file system isn't available in wasm, the `cacheDir` option is ignored.
+#[cfg(target_arch = "wasm32")]
+pub fn read_cache(_cache_dir: &str, _cache_key: &str) -> Option<TransformOutput> {
+  None
+}
+
+#[cfg(target_arch = "wasm32")]
+pub fn write_cache(_cache_dir: &str, _output: &TransformOutput) {}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::transform;
+
+  #[test]
+  fn transform_cache() {
+    let code = "export default function Index() { return <h1>Hi</h1> }";
+    let define = (0..32)
+      .map(|i| format!(r#""process.env.KEY_{}":"\"{}\"""#, i, i))
+      .collect::<Vec<String>>()
+      .join(",");
+    let options_json = format!(r#"{{"define":{{{}}}}}"#, define);
+    let options: Options = serde_json::from_str(options_json.as_str()).unwrap();
+    let output = transform("/pages/index.tsx", code, &options).expect("could not transform module");
+    let key = output.cache_key.clone().expect("missing cache key");
+    assert!(!output.unchanged);
+    // the hash maps of the options are iterated in a random order
+    let options: Options = serde_json::from_str(options_json.as_str()).unwrap();
+    assert_eq!(cache_key("/pages/index.tsx", code, &options), key);
+    assert_ne!(cache_key("/pages/about.tsx", code, &options), key);
+    assert_ne!(cache_key("/pages/index.tsx", "export {}", &options), key);
+    let options: Options = serde_json::from_str(r#"{"isDev":true}"#).unwrap();
+    assert_ne!(cache_key("/pages/index.tsx", code, &options), key);
+
+    let options: Options = serde_json::from_str(
+      format!(
+        r#"{{"define":{{{}}},"cacheKeys":{{"/pages/index.tsx":"{}"}}}}"#,
+        define, key
+      )
+      .as_str(),
+    )
+    .unwrap();
+    let output = transform("/pages/index.tsx", code, &options).expect("could not transform module");
+    assert!(output.unchanged);
+    assert_eq!(output.cache_key, Some(key.clone()));
+    assert!(output.code.is_empty());
+    let output =
+      transform("/pages/index.tsx", "export {}", &options).expect("could not transform module");
+    assert!(!output.unchanged);
+    assert_ne!(output.cache_key, Some(key));
+  }
+
+  #[test]
+  fn transform_cache_dir() {
+    let cache_dir =
+      std::env::temp_dir().join(format!("aleph-compiler-cache-{}", std::process::id()));
+    let options: Options = serde_json::from_value(serde_json::json!({
+      "cacheDir": cache_dir.to_str().unwrap(),
+    }))
+    .unwrap();
+    let code = "export const a: number = 1";
+    let output = transform("/a.ts", code, &options).expect("could not transform module");
+    let key = output.cache_key.clone().expect("missing cache key");
+    let path = cache_dir.join(format!("{}.json", key));
+    assert!(path.exists());
+
+    let mut cached = output.clone();
+    cached.code = "export const a = 2;\n".into();
+    fs::write(&path, serde_json::to_vec(&cached).unwrap()).unwrap();
+    let output = transform("/a.ts", code, &options).expect("could not transform module");
+    assert_eq!(output.code, "export const a = 2;\n");
+    assert_eq!(output.cache_key, Some(key));
+
+    fs::write(&path, "{").unwrap();
+    let output = transform("/a.ts", code, &options).expect("could not transform module");
+    assert_eq!(output.code, "export const a = 1;\n");
+    fs::remove_dir_all(&cache_dir).unwrap();
+  }
+}
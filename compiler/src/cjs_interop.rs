@@ -0,0 +1,339 @@
+use crate::source_type::SourceType;
+use crate::swc::SWC;
+
+use indexmap::IndexSet;
+use swc_common::Span;
+use swc_ecma_ast::*;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// The ES module facade of a CommonJS module.
+#[derive(Debug, Default)]
+pub struct CjsFacade {
+  pub code: String,
+  /// the named exports that are inferred from the static assignments.
+  pub exports: Vec<String>,
+  /// the specifiers of the static `require()` calls, which are imported by the facade.
+  pub requires: Vec<String>,
+}
+
+/// Converts a CommonJS module (like the CJS builds of the CDN modules) into an ES
+/// module facade, the code runs in a function with the `module` and the `exports`,
+/// the static `require("...")` calls are replaced with the default imports, and the
+/// named exports are inferred from the static `exports.x = ...` assignments:
+///
+/// ```js
+/// const React = require("react")
+/// exports.version = React.version
+/// ```
+///
+/// is converted to:
+///
+/// ```js
+/// import __cjs_require_0 from "react";
+/// const __cjs_module = { exports: {} };
+/// (function (module, exports) {
+/// const React = __cjs_require_0
+/// exports.version = React.version
+/// }).call(__cjs_module.exports, __cjs_module, __cjs_module.exports);
+/// const { version: __cjs_export_version } = __cjs_module.exports;
+/// export { __cjs_export_version as version };
+/// export default __cjs_module.exports;
+/// ```
+///
+/// The default export is `exports.default` if the module is marked with `__esModule`
+/// (e.g. the output of babel or tsc), the code of the module is kept as is otherwise.
+pub fn cjs_to_esm(specifier: &str, source: &str) -> Result<CjsFacade, anyhow::Error> {
+  let swc = SWC::parse(specifier, source, Some(SourceType::JS))?;
+  if swc
+    .module
+    .body
+    .iter()
+    .any(|item| matches!(item, ModuleItem::ModuleDecl(_)))
+  {
+    return Err(anyhow::anyhow!(
+      "module \"{}\" is not a CommonJS module: it has the imports or the exports",
+      specifier
+    ));
+  }
+
+  let mut names = IndexSet::new();
+  for item in swc.module.body.iter() {
+    if let ModuleItem::Stmt(Stmt::Expr(ExprStmt { expr, .. })) = item {
+      collect_exports(expr, &mut names);
+    }
+  }
+  let es_module = names.shift_remove("__esModule");
+  names.shift_remove("default");
+  let exports: Vec<String> = names.into_iter().filter(|name| is_ident(name)).collect();
+
+  let mut collector = RequireCollector::default();
+  swc.module.clone().fold_with(&mut collector);
+  let requires: IndexSet<String> = collector.calls.iter().map(|(_, src)| src.clone()).collect();
+  let mut code = source.to_owned();
+  // replaced from the end, so the offsets of the calls before are not changed
+  for (span, src) in collector.calls.iter().rev() {
+    let index = requires.get_index_of(src).unwrap();
+    let lo = swc.source_map.lookup_byte_offset(span.lo).pos.0 as usize;
+    let hi = swc.source_map.lookup_byte_offset(span.hi).pos.0 as usize;
+    code.replace_range(lo..hi, format!("__cjs_require_{}", index).as_str());
+  }
+
+  let mut output = vec![];
+  for (index, src) in requires.iter().enumerate() {
+    output.push(format!(
+      "import __cjs_require_{} from {};",
+      index,
+      serde_json::to_string(src).unwrap()
+    ));
+  }
+  output.push("const __cjs_module = { exports: {} };".into());
+  output.push("(function (module, exports) {".into());
+  output.push(code);
+  output.push("}).call(__cjs_module.exports, __cjs_module, __cjs_module.exports);".into());
+  if !exports.is_empty() {
+    let bindings: Vec<String> = exports
+      .iter()
+      .map(|name| format!("{}: __cjs_export_{}", name, name))
+      .collect();
+    let specifiers: Vec<String> = exports
+      .iter()
+      .map(|name| format!("__cjs_export_{} as {}", name, name))
+      .collect();
+    output.push(format!(
+      "const {{ {} }} = __cjs_module.exports;",
+      bindings.join(", ")
+    ));
+    output.push(format!("export {{ {} }};", specifiers.join(", ")));
+  }
+  output.push(if es_module {
+    "export default __cjs_module.exports.default;".into()
+  } else {
+    "export default __cjs_module.exports;".into()
+  });
+  output.push(String::new());
+
+  Ok(CjsFacade {
+    code: output.join("\n"),
+    exports,
+    requires: requires.into_iter().collect(),
+  })
+}
+
+/// collects the names of the static exports of the expression:
+/// - `exports.a = ...` or `module.exports.a = ...`, the chains like
+///   `exports.a = exports.b = void 0`
+/// - `module.exports = { a, b: ... }`
+/// - `Object.defineProperty(exports, "a", ...)`
+fn collect_exports(expr: &Expr, names: &mut IndexSet<String>) {
+  match expr {
+    Expr::Seq(SeqExpr { exprs, .. }) => {
+      for expr in exprs {
+        collect_exports(expr, names);
+      }
+    }
+    Expr::Paren(ParenExpr { expr, .. }) => collect_exports(expr, names),
+    Expr::Assign(AssignExpr {
+      op: AssignOp::Assign,
+      left,
+      right,
+      ..
+    }) => {
+      let left = match left {
+        PatOrExpr::Expr(expr) => Some(expr.as_ref()),
+        PatOrExpr::Pat(pat) => match pat.as_ref() {
+          Pat::Expr(expr) => Some(expr.as_ref()),
+          _ => None,
+        },
+      };
+      if let Some(left) = left {
+        // match: module.exports = { ... }
+        if is_exports(left) && !matches!(left, Expr::Ident(_)) {
+          if let Expr::Object(ObjectLit { props, .. }) = right.as_ref() {
+            for prop in props {
+              if let PropOrSpread::Prop(prop) = prop {
+                let key = match prop.as_ref() {
+                  Prop::Shorthand(ident) => Some(ident.sym.as_ref().to_owned()),
+                  Prop::KeyValue(KeyValueProp { key, .. })
+                  | Prop::Method(MethodProp { key, .. })
+                  | Prop::Getter(GetterProp { key, .. }) => get_prop_name(key),
+                  _ => None,
+                };
+                if let Some(key) = key {
+                  names.insert(key);
+                }
+              }
+            }
+          }
+        } else if let Some(name) = get_export_name(left) {
+          names.insert(name);
+        }
+      }
+      collect_exports(right, names);
+    }
+    // match: Object.defineProperty(exports, "a", { ... })
+    Expr::Call(CallExpr {
+      callee: ExprOrSuper::Expr(callee),
+      args,
+      ..
+    }) => {
+      if let Expr::Member(MemberExpr {
+        obj: ExprOrSuper::Expr(obj),
+        prop,
+        computed: false,
+        ..
+      }) = callee.as_ref()
+      {
+        if let (Expr::Ident(obj), Expr::Ident(prop)) = (obj.as_ref(), prop.as_ref()) {
+          if obj.sym.as_ref() == "Object"
+            && prop.sym.as_ref() == "defineProperty"
+            && args.len() == 3
+          {
+            if let (true, Expr::Lit(Lit::Str(name))) =
+              (is_exports(&args[0].expr), args[1].expr.as_ref())
+            {
+              names.insert(name.value.as_ref().to_owned());
+            }
+          }
+        }
+      }
+    }
+    _ => {}
+  }
+}
+
+/// checks if the expression is `exports` or `module.exports`.
+fn is_exports(expr: &Expr) -> bool {
+  match expr {
+    Expr::Ident(ident) => ident.sym.as_ref() == "exports",
+    Expr::Member(MemberExpr {
+      obj: ExprOrSuper::Expr(obj),
+      prop,
+      computed: false,
+      ..
+    }) => match (obj.as_ref(), prop.as_ref()) {
+      (Expr::Ident(obj), Expr::Ident(prop)) => {
+        obj.sym.as_ref() == "module" && prop.sym.as_ref() == "exports"
+      }
+      _ => false,
+    },
+    _ => false,
+  }
+}
+
+// match: exports.a, exports["a"], module.exports.a
+fn get_export_name(expr: &Expr) -> Option<String> {
+  match expr {
+    Expr::Member(MemberExpr {
+      obj: ExprOrSuper::Expr(obj),
+      prop,
+      computed,
+      ..
+    }) if is_exports(obj) => match (prop.as_ref(), computed) {
+      (Expr::Ident(ident), false) => Some(ident.sym.as_ref().to_owned()),
+      (Expr::Lit(Lit::Str(s)), true) => Some(s.value.as_ref().to_owned()),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+fn get_prop_name(key: &PropName) -> Option<String> {
+  match key {
+    PropName::Ident(ident) => Some(ident.sym.as_ref().to_owned()),
+    PropName::Str(s) => Some(s.value.as_ref().to_owned()),
+    _ => None,
+  }
+}
+
+/// checks if the export name can be the key of the destructuring and the name of
+/// the export specifier, which are the identifier names.
+fn is_ident(name: &str) -> bool {
+  let mut chars = name.chars();
+  match chars.next() {
+    Some(c) if c.is_alphabetic() || c == '_' || c == '$' => {
+      chars.all(|c| c.is_alphanumeric() || c == '_' || c == '$')
+    }
+    _ => false,
+  }
+}
+
+/// Collects the spans and the specifiers of the `require("...")` calls.
+#[derive(Default)]
+struct RequireCollector {
+  calls: Vec<(Span, String)>,
+}
+
+impl Fold for RequireCollector {
+  noop_fold_type!();
+
+  fn fold_call_expr(&mut self, call: CallExpr) -> CallExpr {
+    if let ExprOrSuper::Expr(callee) = &call.callee {
+      if let Expr::Ident(ident) = callee.as_ref() {
+        if ident.sym.as_ref() == "require" && call.args.len() == 1 {
+          if let ExprOrSpread { spread: None, expr } = &call.args[0] {
+            if let Expr::Lit(Lit::Str(src)) = expr.as_ref() {
+              self.calls.push((call.span, src.value.as_ref().to_owned()));
+              return call;
+            }
+          }
+        }
+      }
+    }
+    call.fold_children_with(self)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn cjs_interop() {
+    let source = r#"
+      "use strict";
+      Object.defineProperty(exports, "__esModule", { value: true });
+      exports.b = exports.a = void 0;
+      const react = require("react");
+      const { cx } = require('./cx.js');
+      exports.a = react.version;
+      exports["b"] = () => require("react");
+      exports["not-ident"] = 1;
+      module.exports.c = cx;
+      Object.defineProperty(exports, "d", { get: () => 1 });
+      exports.default = "default";
+    "#;
+    let facade = cjs_to_esm("https://esm.sh/lib.js", source).expect("could not convert module");
+    println!("{}", facade.code);
+    assert_eq!(facade.exports, vec!["b", "a", "c", "d"]);
+    assert_eq!(facade.requires, vec!["react", "./cx.js"]);
+    assert!(facade.code.starts_with(
+      "import __cjs_require_0 from \"react\";\nimport __cjs_require_1 from \"./cx.js\";\n"
+    ));
+    assert!(facade.code.contains("const react = __cjs_require_0;"));
+    assert!(facade.code.contains("const { cx } = __cjs_require_1;"));
+    assert!(facade
+      .code
+      .contains("exports[\"b\"] = () => __cjs_require_0;"));
+    assert!(facade
+      .code
+      .contains("}).call(__cjs_module.exports, __cjs_module, __cjs_module.exports);"));
+    assert!(facade.code.contains(
+      "const { b: __cjs_export_b, a: __cjs_export_a, c: __cjs_export_c, d: __cjs_export_d } = __cjs_module.exports;"
+    ));
+    assert!(facade.code.contains(
+      "export { __cjs_export_b as b, __cjs_export_a as a, __cjs_export_c as c, __cjs_export_d as d };"
+    ));
+    assert!(facade
+      .code
+      .ends_with("export default __cjs_module.exports.default;\n"));
+
+    let facade = cjs_to_esm("/lib.js", "module.exports = { sum, mul: (a, b) => a * b }")
+      .expect("could not convert module");
+    assert_eq!(facade.exports, vec!["sum", "mul"]);
+    assert!(facade
+      .code
+      .ends_with("export default __cjs_module.exports;\n"));
+
+    assert!(cjs_to_esm("/lib.js", "export const a = 1").is_err());
+  }
+}
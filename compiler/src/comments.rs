@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+use swc_common::{
+  comments::{Comment, CommentKind, Comments, SingleThreadedComments},
+  BytePos,
+};
+
+/// Which comments of the module are kept in the output.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommentsMode {
+  /// keeps all the comments.
+  #[default]
+  All,
+  /// strips the comments, the legal comments are returned in the `legalComments`
+  /// of the output for a third-party notices file.
+  Legal,
+  /// strips the comments.
+  None,
+}
+
+/// checks if the comment is a legal comment like `/*! MIT License */` or the
+/// comment with the `@license` or `@preserve` tag, like esbuild.
+pub fn is_legal_comment(comment: &Comment) -> bool {
+  comment.text.starts_with('!')
+    || comment.text.contains("@license")
+    || comment.text.contains("@preserve")
+}
+
+/// returns the legal comments of the module in source order, one comment per line.
+pub fn extract_legal_comments(comments: &SingleThreadedComments) -> Option<String> {
+  let mut legal_comments: Vec<(BytePos, String)> = vec![];
+  {
+    let (leading, trailing) = comments.borrow_all();
+    for (pos, list) in leading.iter().chain(trailing.iter()) {
+      for comment in list.iter().filter(|comment| is_legal_comment(comment)) {
+        legal_comments.push((
+          *pos,
+          match comment.kind {
+            CommentKind::Line => format!("//{}", comment.text),
+            CommentKind::Block => format!("/*{}*/", comment.text),
+          },
+        ));
+      }
+    }
+  }
+  if legal_comments.is_empty() {
+    return None;
+  }
+  legal_comments.sort_by_key(|(pos, _)| *pos);
+  Some(
+    legal_comments
+      .into_iter()
+      .map(|(_, comment)| comment)
+      .collect::<Vec<String>>()
+      .join("\n"),
+  )
+}
+
+/// removes the comments before the emit, the `#__PURE__` annotations are kept since
+/// the bundlers depend on them.
+pub fn strip_comments(comments: &SingleThreadedComments) {
+  let (leading, trailing) = {
+    let (leading, trailing) = comments.borrow_all();
+    (
+      leading.keys().copied().collect::<Vec<BytePos>>(),
+      trailing.keys().copied().collect::<Vec<BytePos>>(),
+    )
+  };
+  for pos in leading {
+    if let Some(list) = comments.take_leading(pos) {
+      let list = retain_pure_annotations(list);
+      if !list.is_empty() {
+        comments.add_leading_comments(pos, list);
+      }
+    }
+  }
+  for pos in trailing {
+    if let Some(list) = comments.take_trailing(pos) {
+      let list = retain_pure_annotations(list);
+      if !list.is_empty() {
+        comments.add_trailing_comments(pos, list);
+      }
+    }
+  }
+}
+
+fn retain_pure_annotations(list: Vec<Comment>) -> Vec<Comment> {
+  list
+    .into_iter()
+    .filter(|comment| comment.text.eq("#__PURE__") || comment.text.eq("@__PURE__"))
+    .collect()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::swc::{test_resolver, EmitOptions, SWC};
+
+  fn transform(source: &str, comments: CommentsMode) -> String {
+    let module = SWC::parse("/lib.tsx", source, None).expect("could not parse module");
+    let resolver = test_resolver("/lib.tsx");
+    let options = EmitOptions {
+      comments,
+      ..Default::default()
+    };
+    let (code, _) = module
+      .transform(resolver, &options)
+      .expect("could not transform module");
+    println!("{}", code);
+    code
+  }
+
+  #[test]
+  fn legal_comments() {
+    let source = r#"
+      /*! lib v1.0.0 | MIT License */
+      import React from "https://esm.sh/react"
+      /**
+       * @license Apache-2.0
+       */
+      // the logo component
+      export const Logo = () => <img src="/logo.svg" /> // trailing
+      //! keep this
+    "#;
+    let module = SWC::parse("/lib.tsx", source, None).expect("could not parse module");
+    assert_eq!(
+      extract_legal_comments(&module.comments),
+      Some(
+        "/*! lib v1.0.0 | MIT License */\n/**\n       * @license Apache-2.0\n       */\n//! keep this"
+          .into()
+      )
+    );
+    let module = SWC::parse("/lib.ts", "// no license", None).expect("could not parse module");
+    assert_eq!(extract_legal_comments(&module.comments), None);
+
+    let code = transform(source, CommentsMode::All);
+    assert!(code.contains("/*! lib v1.0.0 | MIT License */"));
+    assert!(code.contains("// the logo component"));
+    for mode in [CommentsMode::Legal, CommentsMode::None].iter() {
+      let code = transform(source, *mode);
+      assert!(!code.contains("// the logo component"));
+      assert!(!code.contains("MIT License"));
+      assert!(!code.contains("@license"));
+      assert!(code.contains("/*#__PURE__*/ React.createElement(\"img\""));
+    }
+  }
+}
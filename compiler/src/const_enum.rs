@@ -0,0 +1,371 @@
+use crate::resolve::Resolver;
+
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use swc_common::{Span, DUMMY_SP};
+use swc_ecma_ast::*;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// The value of a const enum member.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum ConstEnumValue {
+  Number(f64),
+  String(String),
+}
+
+/// The members of the const enums exported by a module, keyed by the export name
+/// of the enum and the member name.
+pub type ConstEnums = HashMap<String, HashMap<String, ConstEnumValue>>;
+
+/// Creates a fold that inlines the members of the const enums, which are declared
+/// in the module or imported from the modules in `const_enums` (keyed by the fixed
+/// url), since the imported const enums don't exist at runtime after the types are
+/// stripped:
+///
+/// ```ts
+/// import { Direction } from "./direction.ts"
+/// const enum Color { Red = "red" }
+/// move(Direction.Up, Color.Red)
+/// ```
+///
+/// is transformed to:
+///
+/// ```js
+/// move(0, "red")
+/// ```
+pub fn const_enum_fold(
+  resolver: Rc<RefCell<Resolver>>,
+  const_enums: HashMap<String, ConstEnums>,
+) -> impl Fold {
+  ConstEnumFold {
+    resolver,
+    const_enums,
+    enums: HashMap::new(),
+    errors: vec![],
+  }
+}
+
+/// checks the member accesses of the imported const enums, returns the errors of
+/// the members that don't exist in the `const_enums`.
+pub fn check_const_enums(
+  module: &Module,
+  resolver: Rc<RefCell<Resolver>>,
+  const_enums: HashMap<String, ConstEnums>,
+) -> Vec<(Span, String)> {
+  let mut fold = ConstEnumFold {
+    resolver,
+    const_enums,
+    enums: HashMap::new(),
+    errors: vec![],
+  };
+  Program::Module(module.clone()).fold_with(&mut fold);
+  fold.errors
+}
+
+/// A const enum in the scope of the module.
+struct ConstEnum {
+  /// the members of the enum, the value is `None` if it can't be computed.
+  members: HashMap<String, Option<ConstEnumValue>>,
+  is_imported: bool,
+}
+
+struct ConstEnumFold {
+  resolver: Rc<RefCell<Resolver>>,
+  const_enums: HashMap<String, ConstEnums>,
+  /// the const enums keyed by the local name
+  enums: HashMap<String, ConstEnum>,
+  errors: Vec<(Span, String)>,
+}
+
+impl ConstEnumFold {
+  fn add_imports(&mut self, decl: &ImportDecl) {
+    if decl.type_only {
+      return;
+    }
+    let fixed_url = self.resolver.borrow().fix_url(decl.src.value.as_ref());
+    let exports = match self.const_enums.get(&fixed_url) {
+      Some(exports) => exports,
+      None => return,
+    };
+    for specifier in decl.specifiers.iter() {
+      let (local, imported) = match specifier {
+        // match: import Direction from "./direction.ts"
+        ImportSpecifier::Default(ImportDefaultSpecifier { local, .. }) => (local, "default"),
+        // match: import { Direction } from "./direction.ts"
+        ImportSpecifier::Named(ImportNamedSpecifier {
+          local, imported, ..
+        }) => (local, imported.as_ref().unwrap_or(local).sym.as_ref()),
+        ImportSpecifier::Namespace(_) => continue,
+      };
+      if let Some(members) = exports.get(imported) {
+        self.enums.insert(
+          local.sym.as_ref().into(),
+          ConstEnum {
+            members: members
+              .iter()
+              .map(|(name, value)| (name.clone(), Some(value.clone())))
+              .collect(),
+            is_imported: true,
+          },
+        );
+      }
+    }
+  }
+
+  fn add_enum(&mut self, decl: &TsEnumDecl) {
+    if !decl.is_const {
+      return;
+    }
+    let mut members = HashMap::new();
+    // the members without initializer are incremented from the previous number
+    let mut next = Some(0.0);
+    for member in decl.members.iter() {
+      let name = match &member.id {
+        TsEnumMemberId::Ident(id) => id.sym.as_ref().to_owned(),
+        TsEnumMemberId::Str(s) => s.value.as_ref().to_owned(),
+      };
+      let value = match &member.init {
+        Some(init) => eval(init, decl.id.sym.as_ref(), &members),
+        None => next.map(ConstEnumValue::Number),
+      };
+      next = match &value {
+        Some(ConstEnumValue::Number(n)) => Some(n + 1.0),
+        _ => None,
+      };
+      members.insert(name, value);
+    }
+    self.enums.insert(
+      decl.id.sym.as_ref().into(),
+      ConstEnum {
+        members,
+        is_imported: false,
+      },
+    );
+  }
+
+  /// returns the value of the member access of a const enum, match: `Direction.Up`
+  /// or `Direction["Up"]`.
+  fn get_member_value(&mut self, member: &MemberExpr) -> Option<Expr> {
+    let obj = match &member.obj {
+      ExprOrSuper::Expr(obj) => match obj.as_ref() {
+        Expr::Ident(id) => id,
+        _ => return None,
+      },
+      _ => return None,
+    };
+    let const_enum = self.enums.get(obj.sym.as_ref())?;
+    let name = match (member.prop.as_ref(), member.computed) {
+      (Expr::Ident(prop), false) => prop.sym.as_ref(),
+      (Expr::Lit(Lit::Str(prop)), true) => prop.value.as_ref(),
+      _ => return None,
+    };
+    match const_enum.members.get(name) {
+      Some(value) => value.as_ref().map(new_value_expr),
+      None => {
+        if const_enum.is_imported {
+          self.errors.push((
+            member.span,
+            format!(
+              "Property '{}' does not exist on the const enum '{}'",
+              name, obj.sym
+            ),
+          ));
+        }
+        None
+      }
+    }
+  }
+}
+
+impl Fold for ConstEnumFold {
+  noop_fold_type!();
+
+  fn fold_module(&mut self, module: Module) -> Module {
+    for item in module.body.iter() {
+      match item {
+        ModuleItem::ModuleDecl(ModuleDecl::Import(decl)) => self.add_imports(decl),
+        ModuleItem::Stmt(Stmt::Decl(Decl::TsEnum(decl)))
+        | ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+          decl: Decl::TsEnum(decl),
+          ..
+        })) => self.add_enum(decl),
+        _ => {}
+      }
+    }
+    if self.enums.is_empty() {
+      return module;
+    }
+    module.fold_children_with(self)
+  }
+
+  fn fold_expr(&mut self, expr: Expr) -> Expr {
+    if let Expr::Member(member) = &expr {
+      if let Some(value) = self.get_member_value(member) {
+        return value;
+      }
+    }
+    expr.fold_children_with(self)
+  }
+}
+
+/// computes the constant expression of an enum member, the `members` are the
+/// previous members of the enum `name`.
+fn eval(
+  expr: &Expr,
+  name: &str,
+  members: &HashMap<String, Option<ConstEnumValue>>,
+) -> Option<ConstEnumValue> {
+  match expr {
+    Expr::Lit(Lit::Num(Number { value, .. })) => Some(ConstEnumValue::Number(*value)),
+    Expr::Lit(Lit::Str(Str { value, .. })) => Some(ConstEnumValue::String(value.as_ref().into())),
+    Expr::Tpl(Tpl { exprs, quasis, .. }) if exprs.is_empty() => quasis
+      .first()
+      .map(|quasi| ConstEnumValue::String(quasi.raw.value.as_ref().into())),
+    Expr::Paren(ParenExpr { expr, .. }) => eval(expr, name, members),
+    // match: `B = A`
+    Expr::Ident(id) => members.get(id.sym.as_ref())?.clone(),
+    // match: `B = Enum.A`
+    Expr::Member(MemberExpr {
+      obj: ExprOrSuper::Expr(obj),
+      prop,
+      computed: false,
+      ..
+    }) => match (obj.as_ref(), prop.as_ref()) {
+      (Expr::Ident(obj), Expr::Ident(prop)) if obj.sym.eq(name) => {
+        members.get(prop.sym.as_ref())?.clone()
+      }
+      _ => None,
+    },
+    Expr::Unary(UnaryExpr { op, arg, .. }) => match (op, eval(arg, name, members)?) {
+      (UnaryOp::Minus, ConstEnumValue::Number(n)) => Some(ConstEnumValue::Number(-n)),
+      (UnaryOp::Plus, ConstEnumValue::Number(n)) => Some(ConstEnumValue::Number(n)),
+      (UnaryOp::Tilde, ConstEnumValue::Number(n)) => {
+        Some(ConstEnumValue::Number(!(n as i32) as f64))
+      }
+      _ => None,
+    },
+    Expr::Bin(BinExpr {
+      op, left, right, ..
+    }) => match (eval(left, name, members)?, eval(right, name, members)?) {
+      (ConstEnumValue::Number(l), ConstEnumValue::Number(r)) => {
+        let value = match op {
+          BinaryOp::Add => l + r,
+          BinaryOp::Sub => l - r,
+          BinaryOp::Mul => l * r,
+          BinaryOp::Div => l / r,
+          BinaryOp::Mod => l % r,
+          BinaryOp::Exp => l.powf(r),
+          BinaryOp::BitOr => ((l as i32) | (r as i32)) as f64,
+          BinaryOp::BitAnd => ((l as i32) & (r as i32)) as f64,
+          BinaryOp::BitXor => ((l as i32) ^ (r as i32)) as f64,
+          BinaryOp::LShift => (l as i32).wrapping_shl(r as u32) as f64,
+          BinaryOp::RShift => (l as i32).wrapping_shr(r as u32) as f64,
+          BinaryOp::ZeroFillRShift => (l as u32).wrapping_shr(r as u32) as f64,
+          _ => return None,
+        };
+        Some(ConstEnumValue::Number(value))
+      }
+      (ConstEnumValue::String(l), ConstEnumValue::String(r)) if *op == BinaryOp::Add => {
+        Some(ConstEnumValue::String(l + r.as_str()))
+      }
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+fn new_value_expr(value: &ConstEnumValue) -> Expr {
+  match value {
+    ConstEnumValue::Number(n) if *n < 0.0 => Expr::Unary(UnaryExpr {
+      span: DUMMY_SP,
+      op: UnaryOp::Minus,
+      arg: Box::new(new_value_expr(&ConstEnumValue::Number(-n))),
+    }),
+    ConstEnumValue::Number(n) => Expr::Lit(Lit::Num(Number {
+      span: DUMMY_SP,
+      value: *n,
+    })),
+    ConstEnumValue::String(s) => Expr::Lit(Lit::Str(Str {
+      span: DUMMY_SP,
+      value: s.as_str().into(),
+      has_escape: false,
+      kind: StrKind::Synthesized,
+    })),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::error::DiagnosticBuffer;
+  use crate::swc::{test_resolver, EmitOptions, SWC};
+
+  #[test]
+  fn inline_const_enums() {
+    let source = r#"
+      import { Direction, Mode as M } from "../lib/direction.ts"
+      const enum Flags {
+        None,
+        A = 1 << 0,
+        B = 1 << 1,
+        AB = A | B,
+        C = Flags.AB + 1,
+        Neg = -1,
+      }
+      export const enum Color { Red = "red", Dark = `dark-` + Red }
+      enum Runtime { X }
+      move(Direction.Up, M["Fast"], Flags.AB, Flags.C, Flags.Neg, Color.Dark, Runtime.X)
+    "#;
+    let transform = |const_enums: HashMap<String, ConstEnums>| {
+      let module = SWC::parse("/pages/index.ts", source, None).expect("could not parse module");
+      let resolver = test_resolver("/pages/index.ts");
+      module.transform(
+        resolver,
+        &EmitOptions {
+          const_enums,
+          ..Default::default()
+        },
+      )
+    };
+
+    let (code, _) = transform(HashMap::new()).expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains("move(Direction.Up, M[\"Fast\"], 3, 4, -1, \"dark-red\", Runtime.X);"));
+
+    let mut exports: ConstEnums = HashMap::new();
+    exports.insert(
+      "Direction".into(),
+      vec![("Up".into(), ConstEnumValue::Number(0.0))]
+        .into_iter()
+        .collect(),
+    );
+    exports.insert(
+      "Mode".into(),
+      vec![("Fast".into(), ConstEnumValue::String("fast".into()))]
+        .into_iter()
+        .collect(),
+    );
+    let mut const_enums = HashMap::new();
+    const_enums.insert("/lib/direction.ts".to_owned(), exports);
+    let (code, _) = transform(const_enums.clone()).expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains("move(0, \"fast\", 3, 4, -1, \"dark-red\", Runtime.X);"));
+    assert!(!code.contains("/lib/direction"));
+
+    const_enums
+      .get_mut("/lib/direction.ts")
+      .unwrap()
+      .get_mut("Direction")
+      .unwrap()
+      .remove("Up");
+    let err = transform(const_enums).err().unwrap();
+    let buffer = err.downcast::<DiagnosticBuffer>().unwrap();
+    assert_eq!(buffer.diagnostics.len(), 1);
+    assert_eq!(buffer.diagnostics[0].line, 13);
+    assert_eq!(
+      buffer.diagnostics[0].message,
+      "Property 'Up' does not exist on the const enum 'Direction'"
+    );
+  }
+}
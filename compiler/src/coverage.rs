@@ -0,0 +1,632 @@
+use crate::resolve::Resolver;
+
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::BTreeMap, rc::Rc};
+use swc_common::{FileName, SourceMap, Span, Spanned, DUMMY_SP};
+use swc_ecma_ast::*;
+use swc_ecma_utils::{drop_span, private_ident, quote_ident};
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+use swc_ecmascript::parser::{lexer::Lexer, JscTarget, Parser, StringInput, Syntax};
+
+/// The coverage of a module in the istanbul format, which is collected in the
+/// `globalThis.__coverage__` by the instrumented code.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileCoverage {
+  pub path: String,
+  pub statement_map: BTreeMap<usize, Location>,
+  pub fn_map: BTreeMap<usize, FunctionMapping>,
+  pub branch_map: BTreeMap<usize, BranchMapping>,
+  /// the counters of the statements, the functions and the branches
+  pub s: BTreeMap<usize, u32>,
+  pub f: BTreeMap<usize, u32>,
+  pub b: BTreeMap<usize, Vec<u32>>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Location {
+  pub start: Position,
+  pub end: Position,
+}
+
+/// the 1-based line and the 0-based column.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct Position {
+  pub line: usize,
+  pub column: usize,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct FunctionMapping {
+  pub name: String,
+  pub decl: Location,
+  pub loc: Location,
+  pub line: usize,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct BranchMapping {
+  pub loc: Location,
+  /// `if`, `cond-expr`, `binary-expr` or `switch`
+  pub r#type: String,
+  pub locations: Vec<Location>,
+  pub line: usize,
+}
+
+/// Creates a fold that instruments the module with the istanbul-compatible counters
+/// of the statements, the functions and the branches:
+///
+/// ```js
+/// export function greet(name) {
+///   return name ? `Hello ${name}` : "Hello"
+/// }
+/// ```
+///
+/// is transformed to:
+///
+/// ```js
+/// const _cov = (function (coverage) { ... })(globalThis.__coverage__ || ...);
+/// export function greet(name) {
+///   _cov.f[0]++;
+///   _cov.s[0]++;
+///   return name ? (_cov.b[0][0]++, `Hello ${name}`) : (_cov.b[0][1]++, "Hello")
+/// }
+/// ```
+///
+/// The coverage map is recorded in the `coverage` of the resolver.
+pub fn coverage_fold(
+  resolver: Rc<RefCell<Resolver>>,
+  source_map: Rc<SourceMap>,
+  specifier: &str,
+) -> impl Fold {
+  CoverageFold {
+    resolver,
+    source_map,
+    cov: private_ident!("_cov"),
+    coverage: FileCoverage {
+      path: specifier.into(),
+      ..Default::default()
+    },
+    fn_name: None,
+  }
+}
+
+struct CoverageFold {
+  resolver: Rc<RefCell<Resolver>>,
+  source_map: Rc<SourceMap>,
+  cov: Ident,
+  coverage: FileCoverage,
+  /// the name and the span of the declaration of the next function
+  fn_name: Option<(String, Span)>,
+}
+
+impl CoverageFold {
+  fn location(&self, span: Span) -> Location {
+    if span.is_dummy() {
+      return Location::default();
+    }
+    let start = self.source_map.lookup_char_pos(span.lo);
+    let end = self.source_map.lookup_char_pos(span.hi);
+    Location {
+      start: Position {
+        line: start.line,
+        column: start.col.0,
+      },
+      end: Position {
+        line: end.line,
+        column: end.col.0,
+      },
+    }
+  }
+
+  fn add_statement(&mut self, span: Span) -> usize {
+    let index = self.coverage.statement_map.len();
+    let loc = self.location(span);
+    self.coverage.statement_map.insert(index, loc);
+    self.coverage.s.insert(index, 0);
+    index
+  }
+
+  fn add_function(&mut self, span: Span) -> usize {
+    let index = self.coverage.fn_map.len();
+    let (name, decl) = self
+      .fn_name
+      .take()
+      .unwrap_or_else(|| (format!("(anonymous_{})", index), span));
+    let loc = self.location(span);
+    self.coverage.fn_map.insert(
+      index,
+      FunctionMapping {
+        name,
+        decl: self.location(decl),
+        line: loc.start.line,
+        loc,
+      },
+    );
+    self.coverage.f.insert(index, 0);
+    index
+  }
+
+  fn add_branch(&mut self, kind: &str, span: Span, locations: Vec<Span>) -> usize {
+    let index = self.coverage.branch_map.len();
+    let loc = self.location(span);
+    self.coverage.branch_map.insert(
+      index,
+      BranchMapping {
+        r#type: kind.into(),
+        locations: locations.iter().map(|span| self.location(*span)).collect(),
+        line: loc.start.line,
+        loc,
+      },
+    );
+    self.coverage.b.insert(index, vec![0; locations.len()]);
+    index
+  }
+
+  // match: _cov.s[0]++ or _cov.b[0][1]++
+  fn counter(&self, kind: &str, index: usize, branch: Option<usize>) -> Expr {
+    let mut target = Expr::Member(MemberExpr {
+      span: DUMMY_SP,
+      obj: ExprOrSuper::Expr(Box::new(Expr::Ident(self.cov.clone()))),
+      prop: Box::new(Expr::Ident(quote_ident!(kind))),
+      computed: false,
+    });
+    for index in Some(index).into_iter().chain(branch) {
+      target = Expr::Member(MemberExpr {
+        span: DUMMY_SP,
+        obj: ExprOrSuper::Expr(Box::new(target)),
+        prop: Box::new(Expr::Lit(Lit::Num(Number {
+          span: DUMMY_SP,
+          value: index as f64,
+        }))),
+        computed: true,
+      });
+    }
+    Expr::Update(UpdateExpr {
+      span: DUMMY_SP,
+      op: UpdateOp::PlusPlus,
+      prefix: false,
+      arg: Box::new(target),
+    })
+  }
+
+  fn counter_stmt(&self, kind: &str, index: usize, branch: Option<usize>) -> Stmt {
+    Stmt::Expr(ExprStmt {
+      span: DUMMY_SP,
+      expr: Box::new(self.counter(kind, index, branch)),
+    })
+  }
+
+  // match: (_cov.b[0][0]++, expr)
+  fn count_branch(&self, index: usize, branch: usize, expr: Box<Expr>) -> Box<Expr> {
+    Box::new(Expr::Paren(ParenExpr {
+      span: DUMMY_SP,
+      expr: Box::new(Expr::Seq(SeqExpr {
+        span: DUMMY_SP,
+        exprs: vec![Box::new(self.counter("b", index, Some(branch))), expr],
+      })),
+    }))
+  }
+
+  /// counts the function in the body after the directives.
+  fn count_function(&self, index: usize, body: &mut BlockStmt) {
+    let position = body
+      .stmts
+      .iter()
+      .position(|stmt| !is_directive(stmt))
+      .unwrap_or(body.stmts.len());
+    body
+      .stmts
+      .insert(position, self.counter_stmt("f", index, None));
+  }
+
+  /// the expression of the coverage object of the module, which is shared by the
+  /// instances of the module.
+  fn new_coverage_expr(&self) -> Expr {
+    let path = serde_json::to_string(&self.coverage.path).unwrap();
+    let code = format!(
+      "(function (coverage) {{ return coverage[{path}] || (coverage[{path}] = {map}); }})(globalThis.__coverage__ || (globalThis.__coverage__ = {{}}))",
+      path = path,
+      map = serde_json::to_string(&self.coverage).unwrap(),
+    );
+    let source_file = self
+      .source_map
+      .new_source_file(FileName::Custom("<coverage>".into()), code);
+    let lexer = Lexer::new(
+      Syntax::Es(Default::default()),
+      JscTarget::Es2020,
+      StringInput::from(&*source_file),
+      None,
+    );
+    let expr = Parser::new_from(lexer)
+      .parse_expr()
+      .expect("could not parse the coverage map");
+    drop_span(*expr)
+  }
+}
+
+impl Fold for CoverageFold {
+  noop_fold_type!();
+
+  fn fold_module(&mut self, module: Module) -> Module {
+    let mut module = module.fold_children_with(self);
+    // the imports are hoisted, the counters of the statements between them are
+    // evaluated after the declaration
+    let index = module
+      .body
+      .iter()
+      .position(|item| !matches!(item, ModuleItem::Stmt(stmt) if is_directive(stmt)))
+      .unwrap_or(module.body.len());
+    module.body.insert(
+      index,
+      ModuleItem::Stmt(Stmt::Decl(Decl::Var(VarDecl {
+        span: DUMMY_SP,
+        kind: VarDeclKind::Const,
+        declare: false,
+        decls: vec![VarDeclarator {
+          span: DUMMY_SP,
+          name: Pat::Ident(BindingIdent {
+            id: self.cov.clone(),
+            type_ann: None,
+          }),
+          init: Some(Box::new(self.new_coverage_expr())),
+          definite: false,
+        }],
+      }))),
+    );
+    self.resolver.borrow_mut().coverage = Some(self.coverage.clone());
+    module
+  }
+
+  fn fold_module_items(&mut self, items: Vec<ModuleItem>) -> Vec<ModuleItem> {
+    let mut output = Vec::with_capacity(items.len() * 2);
+    let mut in_directives = true;
+    for item in items {
+      let is_countable = match &item {
+        ModuleItem::Stmt(stmt) => {
+          if in_directives && is_directive(stmt) {
+            output.push(item);
+            continue;
+          }
+          is_countable(stmt)
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => {
+          is_countable_decl(decl)
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(ExportDefaultDecl {
+          decl: DefaultDecl::Class(_),
+          ..
+        })) => true,
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(_)) => true,
+        _ => false,
+      };
+      in_directives = false;
+      if is_countable {
+        let index = self.add_statement(item.span());
+        output.push(ModuleItem::Stmt(self.counter_stmt("s", index, None)));
+      }
+      output.push(item.fold_with(self));
+    }
+    output
+  }
+
+  fn fold_stmts(&mut self, stmts: Vec<Stmt>) -> Vec<Stmt> {
+    let mut output = Vec::with_capacity(stmts.len() * 2);
+    let mut in_directives = true;
+    for stmt in stmts {
+      if in_directives && is_directive(&stmt) {
+        output.push(stmt);
+        continue;
+      }
+      in_directives = false;
+      if is_countable(&stmt) {
+        let index = self.add_statement(stmt.span());
+        output.push(self.counter_stmt("s", index, None));
+      }
+      output.push(stmt.fold_with(self));
+    }
+    output
+  }
+
+  fn fold_fn_decl(&mut self, decl: FnDecl) -> FnDecl {
+    self.fn_name = Some((decl.ident.sym.as_ref().into(), decl.ident.span));
+    decl.fold_children_with(self)
+  }
+
+  fn fold_fn_expr(&mut self, expr: FnExpr) -> FnExpr {
+    if let Some(ident) = &expr.ident {
+      self.fn_name = Some((ident.sym.as_ref().into(), ident.span));
+    }
+    expr.fold_children_with(self)
+  }
+
+  // match: const App = () => {}
+  fn fold_var_declarator(&mut self, decl: VarDeclarator) -> VarDeclarator {
+    if let (Pat::Ident(BindingIdent { id, .. }), Some(init)) = (&decl.name, &decl.init) {
+      if matches!(
+        init.as_ref(),
+        Expr::Arrow(_) | Expr::Fn(FnExpr { ident: None, .. })
+      ) {
+        self.fn_name = Some((id.sym.as_ref().into(), id.span));
+      }
+    }
+    decl.fold_children_with(self)
+  }
+
+  fn fold_class_method(&mut self, method: ClassMethod) -> ClassMethod {
+    if let Some(name) = prop_name(&method.key) {
+      self.fn_name = Some((name, method.key.span()));
+    }
+    method.fold_children_with(self)
+  }
+
+  fn fold_method_prop(&mut self, method: MethodProp) -> MethodProp {
+    if let Some(name) = prop_name(&method.key) {
+      self.fn_name = Some((name, method.key.span()));
+    }
+    method.fold_children_with(self)
+  }
+
+  fn fold_function(&mut self, func: Function) -> Function {
+    // the overloads and the abstract methods
+    if func.body.is_none() {
+      self.fn_name = None;
+      return func;
+    }
+    let index = self.add_function(func.span);
+    let mut func = func.fold_children_with(self);
+    if let Some(body) = &mut func.body {
+      self.count_function(index, body);
+    }
+    func
+  }
+
+  fn fold_constructor(&mut self, constructor: Constructor) -> Constructor {
+    if constructor.body.is_none() {
+      return constructor;
+    }
+    self.fn_name = Some(("constructor".into(), constructor.key.span()));
+    let index = self.add_function(constructor.span);
+    let mut constructor = constructor.fold_children_with(self);
+    if let Some(body) = &mut constructor.body {
+      self.count_function(index, body);
+    }
+    constructor
+  }
+
+  fn fold_arrow_expr(&mut self, arrow: ArrowExpr) -> ArrowExpr {
+    let index = self.add_function(arrow.span);
+    let mut arrow = match arrow.body {
+      // match: () => expr, which is counted as a statement
+      BlockStmtOrExpr::Expr(expr) => {
+        let span = expr.span();
+        ArrowExpr {
+          body: BlockStmtOrExpr::BlockStmt(BlockStmt {
+            span,
+            stmts: vec![Stmt::Return(ReturnStmt {
+              span,
+              arg: Some(expr),
+            })],
+          }),
+          ..arrow
+        }
+      }
+      BlockStmtOrExpr::BlockStmt(_) => arrow,
+    }
+    .fold_children_with(self);
+    if let BlockStmtOrExpr::BlockStmt(body) = &mut arrow.body {
+      self.count_function(index, body);
+    }
+    arrow
+  }
+
+  fn fold_if_stmt(&mut self, stmt: IfStmt) -> IfStmt {
+    let alt_span = stmt.alt.as_ref().map_or(stmt.span, |alt| alt.span());
+    let index = self.add_branch("if", stmt.span, vec![stmt.cons.span(), alt_span]);
+    // the statements of the branches are counted in the blocks
+    let IfStmt {
+      span,
+      test,
+      cons,
+      alt,
+    } = IfStmt {
+      cons: Box::new(into_block(*stmt.cons)),
+      alt: Some(Box::new(match stmt.alt {
+        Some(alt) => into_block(*alt),
+        None => Stmt::Block(BlockStmt {
+          span: DUMMY_SP,
+          stmts: vec![],
+        }),
+      })),
+      ..stmt
+    }
+    .fold_children_with(self);
+    let mut branches = vec![*cons, *alt.unwrap()];
+    for (branch, stmt) in branches.iter_mut().enumerate() {
+      if let Stmt::Block(block) = stmt {
+        block
+          .stmts
+          .insert(0, self.counter_stmt("b", index, Some(branch)));
+      }
+    }
+    let alt = branches.pop().map(Box::new);
+    let cons = Box::new(branches.pop().unwrap());
+    IfStmt {
+      span,
+      test,
+      cons,
+      alt,
+    }
+  }
+
+  fn fold_switch_stmt(&mut self, stmt: SwitchStmt) -> SwitchStmt {
+    let locations = stmt.cases.iter().map(|case| case.span).collect();
+    let index = self.add_branch("switch", stmt.span, locations);
+    let mut stmt = stmt.fold_children_with(self);
+    for (branch, case) in stmt.cases.iter_mut().enumerate() {
+      case
+        .cons
+        .insert(0, self.counter_stmt("b", index, Some(branch)));
+    }
+    stmt
+  }
+
+  fn fold_expr(&mut self, expr: Expr) -> Expr {
+    match expr {
+      Expr::Cond(cond) => {
+        let index = self.add_branch(
+          "cond-expr",
+          cond.span,
+          vec![cond.cons.span(), cond.alt.span()],
+        );
+        let CondExpr {
+          span,
+          test,
+          cons,
+          alt,
+        } = cond.fold_children_with(self);
+        Expr::Cond(CondExpr {
+          span,
+          test,
+          cons: self.count_branch(index, 0, cons),
+          alt: self.count_branch(index, 1, alt),
+        })
+      }
+      Expr::Bin(bin)
+        if matches!(
+          bin.op,
+          BinaryOp::LogicalAnd | BinaryOp::LogicalOr | BinaryOp::NullishCoalescing
+        ) =>
+      {
+        let index = self.add_branch(
+          "binary-expr",
+          bin.span,
+          vec![bin.left.span(), bin.right.span()],
+        );
+        let BinExpr {
+          span,
+          op,
+          left,
+          right,
+        } = bin.fold_children_with(self);
+        Expr::Bin(BinExpr {
+          span,
+          op,
+          left: self.count_branch(index, 0, left),
+          right: self.count_branch(index, 1, right),
+        })
+      }
+      _ => expr.fold_children_with(self),
+    }
+  }
+}
+
+fn is_directive(stmt: &Stmt) -> bool {
+  match stmt {
+    Stmt::Expr(ExprStmt { expr, .. }) => matches!(expr.as_ref(), Expr::Lit(Lit::Str(_))),
+    _ => false,
+  }
+}
+
+/// checks if the statement is executed, the function declarations are hoisted and
+/// counted as the functions, the type declarations are erased.
+fn is_countable(stmt: &Stmt) -> bool {
+  match stmt {
+    Stmt::Empty(_) => false,
+    Stmt::Decl(decl) => is_countable_decl(decl),
+    _ => true,
+  }
+}
+
+fn is_countable_decl(decl: &Decl) -> bool {
+  match decl {
+    Decl::Fn(_) | Decl::TsInterface(_) | Decl::TsTypeAlias(_) => false,
+    Decl::Class(ClassDecl { declare, .. }) => !declare,
+    Decl::Var(VarDecl { declare, .. }) => !declare,
+    Decl::TsEnum(TsEnumDecl { declare, .. }) => !declare,
+    Decl::TsModule(TsModuleDecl { declare, .. }) => !declare,
+  }
+}
+
+fn into_block(stmt: Stmt) -> Stmt {
+  match stmt {
+    Stmt::Block(_) => stmt,
+    _ => Stmt::Block(BlockStmt {
+      span: stmt.span(),
+      stmts: vec![stmt],
+    }),
+  }
+}
+
+fn prop_name(key: &PropName) -> Option<String> {
+  match key {
+    PropName::Ident(id) => Some(id.sym.as_ref().into()),
+    PropName::Str(s) => Some(s.value.as_ref().into()),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::swc::{test_resolver, EmitOptions, SWC};
+
+  #[test]
+  fn coverage_counters() {
+    let source = r#"
+      "use client"
+      import { useState } from "https://esm.sh/react"
+      interface Props { name?: string }
+      export default function Greeting({ name }: Props) {
+        const [count, setCount] = useState(0)
+        if (!name) return <p>{count}</p>
+        const onClick = () => setCount(count + 1)
+        return <p onClick={onClick}>{name ? `Hello ${name}` : "Hello"}{name && "!"}</p>
+      }
+    "#;
+    let module =
+      SWC::parse("/components/greeting.tsx", source, None).expect("could not parse module");
+    let resolver = test_resolver("/components/greeting.tsx");
+    let (code, _) = module
+      .transform(
+        resolver.clone(),
+        &EmitOptions {
+          coverage: true,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.starts_with("\"use client\";\nconst _cov = function(coverage) {"));
+    assert!(code.contains("globalThis.__coverage__ || (globalThis.__coverage__ = {\n})"));
+    assert!(code.contains("_cov.f[0]++;\n    _cov.s[0]++;\n    const [count, setCount]"));
+    assert!(code.contains("_cov.b[0][0]++;\n        _cov.s[2]++;\n        return"));
+    assert!(code.contains("const onClick = ()=>{\n        _cov.f[1]++;\n        _cov.s[4]++;"));
+    assert!(code.contains("name ? (_cov.b[1][0]++, `Hello ${name}`) : (_cov.b[1][1]++, \"Hello\")"));
+    assert!(code.contains("(_cov.b[2][0]++, name) && (_cov.b[2][1]++, \"!\")"));
+
+    let coverage = resolver.borrow().coverage.clone().unwrap();
+    assert_eq!(coverage.path, "/components/greeting.tsx");
+    assert_eq!(coverage.s.len(), 6);
+    assert_eq!(coverage.fn_map[&0].name, "Greeting");
+    assert_eq!(coverage.fn_map[&0].line, 5);
+    assert_eq!(coverage.fn_map[&1].name, "onClick");
+    assert_eq!(coverage.branch_map[&0].r#type, "if");
+    assert_eq!(coverage.branch_map[&1].r#type, "cond-expr");
+    assert_eq!(coverage.branch_map[&2].r#type, "binary-expr");
+    assert_eq!(coverage.b[&2], vec![0, 0]);
+    assert_eq!(
+      coverage.statement_map[&0],
+      Location {
+        start: Position { line: 6, column: 8 },
+        end: Position {
+          line: 6,
+          column: 45
+        },
+      }
+    );
+    let json = serde_json::to_value(&coverage).unwrap();
+    assert_eq!(json["statementMap"]["0"]["start"]["line"], 6);
+    assert_eq!(json["b"]["0"], serde_json::json!([0, 0]));
+  }
+}
@@ -0,0 +1,586 @@
+use crate::ast_utils::new_str;
+use crate::resolve::Resolver;
+
+use regex::{Captures, Regex};
+use sha1::{Digest, Sha1};
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::quote_ident;
+use swc_ecma_visit::{noop_fold_type, Fold};
+
+lazy_static! {
+  // the comments and the attribute selectors are matched to be skipped
+  static ref RE_CLASS_SELECTOR: Regex =
+    Regex::new(r"/\*[\s\S]*?\*/|\[[^\]]*\]|\.(-?[_a-zA-Z][_a-zA-Z0-9-]*)").unwrap();
+}
+
+/// Creates a fold that removes the CSS imports like `import "./style.css"` from the
+/// module and records them in the `css_deps` of the resolver. With `apply_css`, the
+/// imports are rewritten to the `applyCSS()` calls of the aleph runtime.
+///
+/// The imports of the CSS modules like `import styles from "./button.module.css"`
+/// are replaced with an object of the hashed class names when the source of the CSS
+/// is given in `css_modules` (keyed by the fixed url), the generated CSS is recorded
+/// in the `css_modules` of the resolver.
+pub fn css_import_fold(
+  resolver: Rc<RefCell<Resolver>>,
+  extract_css: bool,
+  apply_css: bool,
+  css_modules: HashMap<String, String>,
+) -> impl Fold {
+  CssImportFold {
+    resolver,
+    extract_css,
+    apply_css,
+    css_modules,
+  }
+}
+
+struct CssImportFold {
+  resolver: Rc<RefCell<Resolver>>,
+  extract_css: bool,
+  apply_css: bool,
+  css_modules: HashMap<String, String>,
+}
+
+impl CssImportFold {
+  /// replaces the import of a CSS module with the declarations of the class names:
+  /// - `import styles from "./a.module.css"` -> `const styles = { "title": "title_xxxxxx" }`
+  /// - `import { title } from "./a.module.css"` -> `const title = "title_xxxxxx"`
+  fn transform_css_module_import(
+    &self,
+    specifiers: Vec<ImportSpecifier>,
+    class_names: &[(String, String)],
+  ) -> ModuleItem {
+    let decls = specifiers
+      .into_iter()
+      .map(|specifier| {
+        let (local, init) = match specifier {
+          ImportSpecifier::Default(ImportDefaultSpecifier { local, .. })
+          | ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => {
+            let props = class_names
+              .iter()
+              .map(|(name, hashed)| {
+                PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                  key: PropName::Str(new_str(name)),
+                  value: Box::new(Expr::Lit(Lit::Str(new_str(hashed)))),
+                })))
+              })
+              .collect();
+            (
+              local,
+              Expr::Object(ObjectLit {
+                span: DUMMY_SP,
+                props,
+              }),
+            )
+          }
+          ImportSpecifier::Named(ImportNamedSpecifier {
+            local, imported, ..
+          }) => {
+            let name = imported.as_ref().unwrap_or(&local).sym.as_ref().to_owned();
+            let hashed = class_names
+              .iter()
+              .find(|(n, _)| n.eq(&name))
+              .map(|(_, hashed)| Expr::Lit(Lit::Str(new_str(hashed))))
+              .unwrap_or_else(|| Expr::Ident(quote_ident!("undefined")));
+            (local, hashed)
+          }
+        };
+        VarDeclarator {
+          span: DUMMY_SP,
+          name: Pat::Ident(BindingIdent {
+            id: local,
+            type_ann: None,
+          }),
+          init: Some(Box::new(init)),
+          definite: false,
+        }
+      })
+      .collect();
+    ModuleItem::Stmt(Stmt::Decl(Decl::Var(VarDecl {
+      span: DUMMY_SP,
+      kind: VarDeclKind::Const,
+      declare: false,
+      decls,
+    })))
+  }
+}
+
+impl Fold for CssImportFold {
+  noop_fold_type!();
+
+  fn fold_module_items(&mut self, module_items: Vec<ModuleItem>) -> Vec<ModuleItem> {
+    let mut items = Vec::<ModuleItem>::new();
+    let mut resolver = self.resolver.borrow_mut();
+
+    let mut applied_css = false;
+
+    for item in module_items {
+      match item {
+        // match: import styles from "./button.module.css"
+        ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+          specifiers,
+          src,
+          type_only: false,
+          ..
+        }))
+          if !specifiers.is_empty()
+            && is_css_module_url(src.value.as_ref())
+            && self
+              .css_modules
+              .contains_key(&resolver.fix_url(src.value.as_ref())) =>
+        {
+          let fixed_url = resolver.fix_url(src.value.as_ref());
+          let (css, class_names) =
+            transform_css_module(&fixed_url, self.css_modules.get(&fixed_url).unwrap());
+          items.push(self.transform_css_module_import(specifiers, &class_names));
+          if self.apply_css {
+            // applyCSS("/button.module.css", ".button_xxxxxx{}")
+            items.push(new_apply_css_stmt(&fixed_url, Some(&css)));
+            applied_css = true;
+          }
+          resolver.css_modules.insert(fixed_url, css);
+        }
+        // match: import "./style.css"
+        ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+          specifiers,
+          src,
+          type_only: false,
+          ..
+        }))
+          if self.extract_css && specifiers.is_empty() && is_css_url(src.value.as_ref()) =>
+        {
+          let fixed_url = resolver.fix_url(src.value.as_ref());
+          if self.apply_css {
+            // applyCSS("/style.css")
+            items.push(new_apply_css_stmt(&fixed_url, None));
+            applied_css = true;
+          }
+          resolver.css_deps.push(fixed_url);
+        }
+        _ => items.push(item),
+      }
+    }
+
+    if applied_css {
+      // import { applyCSS } from "https://deno.land/x/aleph/framework/core/style.ts"
+      items.insert(
+        0,
+        ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+          span: DUMMY_SP,
+          specifiers: vec![ImportSpecifier::Named(ImportNamedSpecifier {
+            span: DUMMY_SP,
+            local: quote_ident!("applyCSS"),
+            imported: None,
+          })],
+          src: new_str(&format!(
+            "{}/framework/core/style.ts",
+            resolver.get_aleph_pkg_uri()
+          )),
+          type_only: false,
+          asserts: None,
+        })),
+      );
+    }
+    items
+  }
+}
+
+/// checks if the url is a CSS file, including the preprocessors like LESS and SASS.
+pub fn is_css_url(url: &str) -> bool {
+  let path = url.split(&['?', '#'][..]).next().unwrap();
+  [".css", ".less", ".sass", ".scss", ".styl", ".pcss"]
+    .iter()
+    .any(|ext| path.ends_with(ext))
+}
+
+/// creates the `applyCSS(url, css?)` call statement.
+pub fn new_apply_css_stmt(url: &str, css: Option<&str>) -> ModuleItem {
+  let mut args = vec![ExprOrSpread {
+    spread: None,
+    expr: Box::new(Expr::Lit(Lit::Str(new_str(url)))),
+  }];
+  if let Some(css) = css {
+    args.push(ExprOrSpread {
+      spread: None,
+      expr: Box::new(Expr::Lit(Lit::Str(new_str(css)))),
+    });
+  }
+  ModuleItem::Stmt(Stmt::Expr(ExprStmt {
+    span: DUMMY_SP,
+    expr: Box::new(Expr::Call(CallExpr {
+      span: DUMMY_SP,
+      callee: ExprOrSuper::Expr(Box::new(Expr::Ident(quote_ident!("applyCSS")))),
+      args,
+      type_args: None,
+    })),
+  }))
+}
+
+/// checks if the url is a CSS module like `./button.module.css`.
+pub fn is_css_module_url(url: &str) -> bool {
+  let path = url.split(&['?', '#'][..]).next().unwrap();
+  is_css_url(path) && path.rsplit('/').next().unwrap().contains(".module.")
+}
+
+/// transforms the source of a CSS module to the CSS with hashed class names, returns
+/// the generated CSS and the class names in order of appearance. The class names are
+/// hashed with the `url` of the module, e.g. `.title` -> `.title_xxxxxx`.
+pub fn transform_css_module(url: &str, css: &str) -> (String, Vec<(String, String)>) {
+  let mut hasher = Sha1::new();
+  hasher.update(url);
+  let hash = base64::encode(hasher.finalize())
+    .replace("+", "")
+    .replace("/", "")
+    .replace("=", "");
+  let hash = &hash[..6];
+  let mut class_names: Vec<(String, String)> = vec![];
+  let rename_selector = |selector: &str| -> String {
+    RE_CLASS_SELECTOR
+      .replace_all(selector, |caps: &Captures| match caps.get(1) {
+        Some(name) => {
+          let name = name.as_str();
+          let hashed = format!("{}_{}", name, hash);
+          if !class_names.iter().any(|(n, _)| n.eq(name)) {
+            class_names.push((name.into(), hashed.clone()));
+          }
+          format!(".{}", hashed)
+        }
+        None => caps[0].to_owned(),
+      })
+      .into_owned()
+  };
+  let output = map_selectors(css, rename_selector);
+  (output, class_names)
+}
+
+/// scopes the CSS with the `scope` class, the class is appended to the last compound
+/// selector of every selector before the pseudo-classes and the pseudo-elements,
+/// e.g. `.list > li:hover` -> `.list > li.css_xxxxxx:hover`. the selectors in
+/// `:global(...)` are not scoped.
+pub fn scope_css(css: &str, scope: &str) -> String {
+  map_selectors(css, |prelude| {
+    split_selectors(prelude)
+      .into_iter()
+      .map(|selector| scope_selector(selector, scope))
+      .collect::<Vec<String>>()
+      .join(",")
+  })
+}
+
+/// maps the selectors of the rules (including the rules in `@media`), the other
+/// parts of the CSS like the declarations and the comments are copied.
+fn map_selectors(css: &str, mut map: impl FnMut(&str) -> String) -> String {
+  let mut output = String::with_capacity(css.len());
+  let mut prelude = String::new();
+  // whether the blocks contain rules (the top-level or `@media`) or declarations
+  let mut contexts: Vec<bool> = vec![true];
+  let mut chars = css.chars().peekable();
+  while let Some(c) = chars.next() {
+    match c {
+      '/' if chars.peek() == Some(&'*') => {
+        // copy the comment
+        let mut comment = String::from("/");
+        let mut prev = ' ';
+        for c in chars.by_ref() {
+          comment.push(c);
+          if prev == '*' && c == '/' && comment.len() > 3 {
+            break;
+          }
+          prev = c;
+        }
+        prelude.push_str(&comment);
+      }
+      '"' | '\'' => {
+        // copy the string
+        prelude.push(c);
+        let mut escaped = false;
+        for q in chars.by_ref() {
+          prelude.push(q);
+          if escaped {
+            escaped = false;
+          } else if q == '\\' {
+            escaped = true;
+          } else if q == c {
+            break;
+          }
+        }
+      }
+      '{' => {
+        let in_rules = *contexts.last().unwrap();
+        if in_rules {
+          if let Some(at_rule) = prelude.trim_start().strip_prefix('@') {
+            let at_rule = at_rule
+              .split(|c: char| !c.is_alphanumeric() && c != '-')
+              .next()
+              .unwrap();
+            output.push_str(&prelude);
+            contexts.push(matches!(
+              at_rule,
+              "media" | "supports" | "document" | "layer" | "container"
+            ));
+          } else {
+            output.push_str(&map(&prelude));
+            contexts.push(false);
+          }
+        } else {
+          output.push_str(&prelude);
+          contexts.push(false);
+        }
+        output.push('{');
+        prelude.clear();
+      }
+      '}' => {
+        output.push_str(&prelude);
+        output.push('}');
+        prelude.clear();
+        if contexts.len() > 1 {
+          contexts.pop();
+        }
+      }
+      ';' => {
+        output.push_str(&prelude);
+        output.push(';');
+        prelude.clear();
+      }
+      _ => prelude.push(c),
+    }
+  }
+  output.push_str(&prelude);
+  output
+}
+
+/// splits the selector list by the commas that are not in the parentheses, the
+/// brackets or the strings.
+fn split_selectors(prelude: &str) -> Vec<&str> {
+  let mut selectors = vec![];
+  let mut depth = 0;
+  let mut quote: Option<char> = None;
+  let mut start = 0;
+  for (i, c) in prelude.char_indices() {
+    match (quote, c) {
+      (Some(q), c) if c == q => quote = None,
+      (Some(_), _) => {}
+      (None, '"' | '\'') => quote = Some(c),
+      (None, '(' | '[') => depth += 1,
+      (None, ')' | ']') => depth -= 1,
+      (None, ',') if depth == 0 => {
+        selectors.push(&prelude[start..i]);
+        start = i + 1;
+      }
+      _ => {}
+    }
+  }
+  selectors.push(&prelude[start..]);
+  selectors
+}
+
+fn scope_selector(selector: &str, scope: &str) -> String {
+  let trimmed = selector.trim();
+  if trimmed.is_empty() {
+    return selector.into();
+  }
+  let leading = &selector[..selector.find(trimmed).unwrap()];
+  let trailing = &selector[leading.len() + trimmed.len()..];
+  if let Some(index) = trimmed.find(":global(") {
+    // match: :global(.dark) .button
+    let inner = &trimmed[index + 8..];
+    let end = inner.rfind(')').unwrap_or(inner.len());
+    return format!(
+      "{}{}{}{}{}",
+      leading,
+      &trimmed[..index],
+      &inner[..end],
+      inner.get(end + 1..).unwrap_or_default(),
+      trailing
+    );
+  }
+  // the start of the last compound selector, and the first pseudo of it
+  let mut depth = 0;
+  let mut compound_start = 0;
+  let mut pseudo: Option<usize> = None;
+  for (i, c) in trimmed.char_indices() {
+    match c {
+      '(' | '[' => depth += 1,
+      ')' | ']' => depth -= 1,
+      ' ' | '\n' | '\t' | '>' | '+' | '~' if depth == 0 => {
+        compound_start = i + 1;
+        pseudo = None;
+      }
+      ':' if depth == 0 && pseudo.is_none() => pseudo = Some(i),
+      _ => {}
+    }
+  }
+  let index = pseudo.unwrap_or(trimmed.len()).max(compound_start);
+  format!(
+    "{}{}.{}{}{}",
+    leading,
+    &trimmed[..index],
+    scope,
+    &trimmed[index..],
+    trailing
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::transform_css_module;
+  use crate::import_map::ImportHashMap;
+  use crate::resolve::Resolver;
+  use crate::swc::{EmitOptions, SWC};
+  use std::{cell::RefCell, collections::HashMap, rc::Rc};
+
+  #[test]
+  fn extract_css_imports() {
+    let source = r#"
+      import React from "https://esm.sh/react"
+      import "../styles/app.css"
+      import "./index.less?inline"
+      import "https://esm.sh/tailwindcss/dist/tailwind.min.css"
+      export default function Index() {
+        return <h1>Hi</h1>
+      }
+    "#;
+    let transform = |is_dev: bool| {
+      let module = SWC::parse("/pages/index.tsx", source, None).expect("could not parse module");
+      let resolver = Rc::new(RefCell::new(Resolver::new(
+        "/pages/index.tsx",
+        ImportHashMap::default(),
+        Some("https://deno.land/x/aleph@v0.3.0".into()),
+        None,
+        false,
+        vec![],
+      )));
+      let (code, _) = module
+        .transform(
+          resolver.clone(),
+          &EmitOptions {
+            extract_css: true,
+            is_dev,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      let r = resolver.borrow();
+      assert_eq!(
+        r.css_deps,
+        vec![
+          "/styles/app.css",
+          "/pages/index.less?inline",
+          "https://esm.sh/tailwindcss/dist/tailwind.min.css"
+        ]
+      );
+      assert!(!r.dep_graph.iter().any(|dep| dep.specifier.contains(".css")));
+      code
+    };
+
+    let code = transform(false);
+    assert!(!code.contains(".css"));
+    assert!(!code.contains(".less"));
+    assert!(!code.contains("applyCSS"));
+
+    let code = transform(true);
+    assert!(code.contains(
+      "import { applyCSS } from \"../-/deno.land/x/aleph@v0.3.0/framework/core/style.js\";"
+    ));
+    assert!(code.contains("applyCSS(\"/styles/app.css\");"));
+    assert!(code.contains("applyCSS(\"https://esm.sh/tailwindcss/dist/tailwind.min.css\");"));
+  }
+
+  #[test]
+  fn css_modules() {
+    let source = r#"
+      import styles from "./button.module.css"
+      import { title as titleClass } from "../styles/title.module.css"
+      export default function Button() {
+        return <button className={styles.button + " " + styles["is-primary"]}>
+          <span className={titleClass}>Hi</span>
+        </button>
+      }
+    "#;
+    let mut css_modules = HashMap::new();
+    css_modules.insert(
+      "/components/button.module.css".into(),
+      r#"
+        /* .comment {} */
+        .button, .button:hover > .icon { color: red; background: url("./a.png"); }
+        @media (max-width: 600px) { .is-primary { margin: 0.5em; } }
+        @keyframes fade { 0.5% { opacity: 0; } }
+        a[href$=".pdf"] { color: blue; }
+      "#
+      .into(),
+    );
+    css_modules.insert(
+      "/styles/title.module.css".into(),
+      ".title { font-weight: bold; }".into(),
+    );
+    let transform = |is_dev: bool| {
+      let module =
+        SWC::parse("/components/button.tsx", source, None).expect("could not parse module");
+      let resolver = Rc::new(RefCell::new(Resolver::new(
+        "/components/button.tsx",
+        ImportHashMap::default(),
+        Some("https://deno.land/x/aleph@v0.3.0".into()),
+        None,
+        false,
+        vec![],
+      )));
+      let (code, _) = module
+        .transform(
+          resolver.clone(),
+          &EmitOptions {
+            css_modules: css_modules.clone(),
+            is_dev,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      let r = resolver.borrow();
+      assert!(r.css_deps.is_empty());
+      assert!(!r.dep_graph.iter().any(|dep| dep.specifier.contains(".css")));
+      (code, r.css_modules.clone())
+    };
+
+    let (css, class_names) = transform_css_module(
+      "/components/button.module.css",
+      &css_modules["/components/button.module.css"],
+    );
+    let hash = class_names[0].1.trim_start_matches("button_").to_owned();
+    assert_eq!(hash.len(), 6);
+    assert_eq!(
+      class_names
+        .iter()
+        .map(|(name, _)| name.as_str())
+        .collect::<Vec<&str>>(),
+      vec!["button", "icon", "is-primary"]
+    );
+    assert!(css.contains("/* .comment {} */"));
+    assert!(css.contains(&format!(
+      ".button_{0}, .button_{0}:hover > .icon_{0} {{",
+      hash
+    )));
+    assert!(css.contains("url(\"./a.png\")"));
+    assert!(css.contains(&format!(".is-primary_{} {{ margin: 0.5em; }}", hash)));
+    assert!(css.contains("0.5% { opacity: 0; }"));
+    assert!(css.contains("a[href$=\".pdf\"] { color: blue; }"));
+
+    let (code, generated) = transform(false);
+    assert!(!code.contains(".module.css"));
+    assert!(!code.contains("applyCSS"));
+    assert!(code.contains(&format!("\"button\": \"button_{}\"", hash)));
+    assert!(code.contains(&format!("\"is-primary\": \"is-primary_{}\"", hash)));
+    assert!(code.contains("const titleClass = \"title_"));
+    assert_eq!(generated["/components/button.module.css"], css);
+    assert!(generated["/styles/title.module.css"].starts_with(".title_"));
+
+    let (code, _) = transform(true);
+    assert!(code.contains(
+      "import { applyCSS } from \"../-/deno.land/x/aleph@v0.3.0/framework/core/style.js\";"
+    ));
+    assert!(code.contains("applyCSS(\"/components/button.module.css\", \""));
+  }
+}
@@ -0,0 +1,200 @@
+use crate::error::{DiagnosticCode, DiagnosticMessage};
+
+use indexmap::{IndexMap, IndexSet};
+use std::collections::{HashMap, VecDeque};
+
+/// Finds the import cycles of the module `graph`, which maps the modules to the
+/// modules they import statically. Each strongly connected component of the graph
+/// reports one cycle, the shortest one from its first module in the order of the
+/// `graph`, as the path that starts and ends with that module:
+///
+/// ```text
+/// ["/a.ts", "/b.ts", "/c.ts", "/a.ts"]
+/// ```
+///
+/// The imports of the modules that are not in the `graph` are ignored.
+pub fn find_cycles(graph: &IndexMap<String, Vec<String>>) -> Vec<Vec<String>> {
+  let mut tarjan = Tarjan {
+    graph,
+    index: 0,
+    indexes: HashMap::new(),
+    low_links: HashMap::new(),
+    stack: IndexSet::new(),
+    components: vec![],
+  };
+  for node in graph.keys() {
+    if !tarjan.indexes.contains_key(node.as_str()) {
+      tarjan.connect(node.as_str());
+    }
+  }
+  let mut cycles: Vec<(usize, Vec<String>)> = tarjan
+    .components
+    .iter()
+    .filter_map(|component| {
+      // the first module of the component in the order of the graph
+      let (start, _) = component
+        .iter()
+        .map(|node| (*node, graph.get_index_of(*node).unwrap()))
+        .min_by_key(|(_, index)| *index)?;
+      let path = shortest_cycle(graph, component, start)?;
+      Some((graph.get_index_of(start).unwrap(), path))
+    })
+    .collect();
+  cycles.sort_by_key(|(index, _)| *index);
+  cycles.into_iter().map(|(_, path)| path).collect()
+}
+
+/// Creates the warning of the import `cycle` for the first module of the path.
+pub fn new_cycle_warning(cycle: &[String]) -> DiagnosticMessage {
+  DiagnosticMessage {
+    file_name: cycle.first().cloned().unwrap_or_default(),
+    line: 0,
+    column: 0,
+    length: 0,
+    message: format!("import cycle: {}", cycle.join(" -> ")),
+    code: DiagnosticCode::ImportCycle,
+    frame: None,
+    cycle: Some(cycle.to_vec()),
+  }
+}
+
+/// Rotates the `cycle` to start and end with the `node`, returns `None` if the node
+/// is not in the cycle.
+pub fn rotate_cycle(cycle: &[String], node: &str) -> Option<Vec<String>> {
+  let nodes = &cycle[..cycle.len().saturating_sub(1)];
+  let index = nodes.iter().position(|n| n == node)?;
+  let mut rotated: Vec<String> = nodes[index..]
+    .iter()
+    .chain(nodes[..index].iter())
+    .cloned()
+    .collect();
+  rotated.push(node.into());
+  Some(rotated)
+}
+
+struct Tarjan<'a> {
+  graph: &'a IndexMap<String, Vec<String>>,
+  index: usize,
+  indexes: HashMap<&'a str, usize>,
+  low_links: HashMap<&'a str, usize>,
+  stack: IndexSet<&'a str>,
+  /// the strongly connected components that have a cycle.
+  components: Vec<Vec<&'a str>>,
+}
+
+impl<'a> Tarjan<'a> {
+  fn connect(&mut self, node: &'a str) {
+    self.indexes.insert(node, self.index);
+    self.low_links.insert(node, self.index);
+    self.index += 1;
+    self.stack.insert(node);
+    let mut self_import = false;
+    for dep in self.graph[node].iter() {
+      let dep = match self.graph.get_key_value(dep) {
+        Some((dep, _)) => dep.as_str(),
+        None => continue,
+      };
+      if dep == node {
+        self_import = true;
+      }
+      if !self.indexes.contains_key(dep) {
+        self.connect(dep);
+        let low_link = self.low_links[node].min(self.low_links[dep]);
+        self.low_links.insert(node, low_link);
+      } else if self.stack.contains(dep) {
+        let low_link = self.low_links[node].min(self.indexes[dep]);
+        self.low_links.insert(node, low_link);
+      }
+    }
+    if self.low_links[node] == self.indexes[node] {
+      let index = self.stack.get_index_of(node).unwrap();
+      let component: Vec<&str> = self.stack.drain(index..).collect();
+      if component.len() > 1 || self_import {
+        self.components.push(component);
+      }
+    }
+  }
+}
+
+/// finds the shortest path from the `start` back to itself in the `component` by
+/// the breadth-first search.
+fn shortest_cycle(
+  graph: &IndexMap<String, Vec<String>>,
+  component: &[&str],
+  start: &str,
+) -> Option<Vec<String>> {
+  let mut parents: HashMap<&str, &str> = HashMap::new();
+  let mut queue = VecDeque::new();
+  queue.push_back(start);
+  while let Some(node) = queue.pop_front() {
+    for dep in graph[node].iter() {
+      let dep = dep.as_str();
+      if dep == start {
+        let mut path = vec![start.to_owned()];
+        let mut current = node;
+        while current != start {
+          path.push(current.into());
+          current = parents[current];
+        }
+        path.push(start.into());
+        path.reverse();
+        return Some(path);
+      }
+      if component.contains(&dep) && !parents.contains_key(dep) {
+        parents.insert(dep, node);
+        queue.push_back(dep);
+      }
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn import_cycles() {
+    let mut graph: IndexMap<String, Vec<String>> = IndexMap::new();
+    let mut add = |node: &str, deps: &[&str]| {
+      graph.insert(node.into(), deps.iter().map(|dep| (*dep).into()).collect());
+    };
+    add("/app.tsx", &["/a.ts", "/self.ts", "https://esm.sh/react"]);
+    add("/a.ts", &["/b.ts"]);
+    add("/b.ts", &["/c.ts", "/a.ts"]);
+    add("/c.ts", &["/a.ts", "/d.ts"]);
+    add("/d.ts", &[]);
+    add("/self.ts", &["/self.ts"]);
+    let cycles = find_cycles(&graph);
+    assert_eq!(
+      cycles,
+      vec![
+        vec!["/a.ts", "/b.ts", "/a.ts"],
+        vec!["/self.ts", "/self.ts"],
+      ]
+    );
+    assert_eq!(
+      rotate_cycle(
+        &[
+          "/a.ts".into(),
+          "/b.ts".into(),
+          "/c.ts".into(),
+          "/a.ts".into()
+        ],
+        "/c.ts"
+      ),
+      Some(vec![
+        "/c.ts".into(),
+        "/a.ts".into(),
+        "/b.ts".into(),
+        "/c.ts".into()
+      ])
+    );
+    assert_eq!(rotate_cycle(&cycles[1], "/d.ts"), None);
+
+    let warning = new_cycle_warning(&cycles[0]);
+    assert_eq!(warning.file_name, "/a.ts");
+    assert_eq!(warning.message, "import cycle: /a.ts -> /b.ts -> /a.ts");
+    assert_eq!(warning.code, DiagnosticCode::ImportCycle);
+  }
+}
@@ -0,0 +1,325 @@
+use std::collections::{HashMap, HashSet};
+use swc_common::{FileName, Mark, SourceMap, Span, SyntaxContext};
+use swc_ecma_ast::*;
+use swc_ecma_utils::drop_span;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+use swc_ecmascript::parser::{lexer::Lexer, JscTarget, StringInput, Syntax};
+use swc_ecmascript::transforms::resolver::ts_resolver;
+
+/// Creates a fold that replaces the `defines` keys with the constant values, e.g.
+/// `{ "process.env.NODE_ENV": "\"production\"" }`. Only the globals are replaced,
+/// the paths of the local bindings (like a `process` param) and the assignment targets
+/// like `process.env.NODE_ENV = "test"` are kept.
+pub fn define_fold(
+  defines: &HashMap<String, String>,
+  source_map: &SourceMap,
+) -> Result<impl Fold, anyhow::Error> {
+  let mut values = HashMap::new();
+  for (key, value) in defines.iter() {
+    let source_file =
+      source_map.new_source_file(FileName::Custom(format!("<define:{}>", key)), value.into());
+    let lexer = Lexer::new(
+      Syntax::Es(Default::default()),
+      JscTarget::Es2020,
+      StringInput::from(&*source_file),
+      None,
+    );
+    let expr = swc_ecmascript::parser::Parser::new_from(lexer)
+      .parse_expr()
+      .map_err(|_| anyhow::anyhow!("invalid define value of '{}': {}", key, value))?;
+    values.insert(key.into(), drop_span(*expr));
+  }
+  Ok(DefineFold {
+    values,
+    globals: HashSet::new(),
+  })
+}
+
+struct DefineFold {
+  values: HashMap<String, Expr>,
+  /// the spans of the identifiers that are not resolved to a binding.
+  globals: HashSet<Span>,
+}
+
+impl DefineFold {
+  /// folds the children of an assignment target, the target itself is not replaced.
+  fn fold_target(&mut self, expr: Expr) -> Expr {
+    match expr {
+      Expr::Member(MemberExpr {
+        span,
+        obj,
+        prop,
+        computed,
+      }) => Expr::Member(MemberExpr {
+        span,
+        obj: obj.fold_with(self),
+        prop: if computed { prop.fold_with(self) } else { prop },
+        computed,
+      }),
+      Expr::Paren(ParenExpr { span, expr }) => Expr::Paren(ParenExpr {
+        span,
+        expr: Box::new(self.fold_target(*expr)),
+      }),
+      _ => expr,
+    }
+  }
+}
+
+impl Fold for DefineFold {
+  noop_fold_type!();
+
+  fn fold_module(&mut self, module: Module) -> Module {
+    // the scopes are resolved on a copy, the module is kept clean for the resolvers
+    // of the later passes
+    let top_level_mark = Mark::fresh(Mark::root());
+    let resolved = module.clone().fold_with(&mut ts_resolver(top_level_mark));
+    let mut collector = GlobalsCollector {
+      top_level_ctxt: SyntaxContext::empty().apply_mark(top_level_mark),
+      refs: vec![],
+      bindings: HashSet::new(),
+    };
+    resolved.fold_with(&mut collector);
+    let GlobalsCollector { refs, bindings, .. } = collector;
+    self.globals = refs
+      .into_iter()
+      .filter(|id| !bindings.contains(id.sym.as_ref()))
+      .map(|id| id.span.with_ctxt(SyntaxContext::empty()))
+      .collect();
+    module.fold_children_with(self)
+  }
+
+  fn fold_expr(&mut self, expr: Expr) -> Expr {
+    if let Some(path) = get_expr_path(&expr) {
+      if let Some(value) = self.values.get(&path) {
+        let is_global = match get_root_ident(&expr) {
+          Some(id) => self.globals.contains(&id.span),
+          None => true,
+        };
+        if is_global {
+          return value.clone();
+        }
+      }
+    }
+    match expr {
+      // the property of a non-computed member expression is not a reference
+      Expr::Member(MemberExpr {
+        span,
+        obj,
+        prop,
+        computed: false,
+      }) => Expr::Member(MemberExpr {
+        span,
+        obj: obj.fold_with(self),
+        prop,
+        computed: false,
+      }),
+      _ => expr.fold_children_with(self),
+    }
+  }
+
+  // match: process.env.NODE_ENV = "test"
+  fn fold_pat_or_expr(&mut self, target: PatOrExpr) -> PatOrExpr {
+    match target {
+      PatOrExpr::Expr(expr) => PatOrExpr::Expr(Box::new(self.fold_target(*expr))),
+      PatOrExpr::Pat(pat) => PatOrExpr::Pat(pat.fold_with(self)),
+    }
+  }
+
+  // match: [process.env.NODE_ENV] = ["test"]
+  fn fold_pat(&mut self, pat: Pat) -> Pat {
+    match pat {
+      Pat::Expr(expr) => Pat::Expr(Box::new(self.fold_target(*expr))),
+      _ => pat.fold_children_with(self),
+    }
+  }
+
+  // match: __COUNT__++
+  fn fold_update_expr(&mut self, expr: UpdateExpr) -> UpdateExpr {
+    UpdateExpr {
+      arg: Box::new(self.fold_target(*expr.arg)),
+      ..expr
+    }
+  }
+}
+
+/// collects the identifiers of the resolved module that are in the top-level context,
+/// the resolver marks the unresolved references with the top-level mark too, so the
+/// names of the top-level bindings are collected to tell them apart.
+struct GlobalsCollector {
+  top_level_ctxt: SyntaxContext,
+  refs: Vec<Ident>,
+  bindings: HashSet<String>,
+}
+
+impl GlobalsCollector {
+  fn add_binding(&mut self, id: &Ident) {
+    if id.span.ctxt() == self.top_level_ctxt {
+      self.bindings.insert(id.sym.as_ref().into());
+    }
+  }
+}
+
+impl Fold for GlobalsCollector {
+  noop_fold_type!();
+
+  fn fold_ident(&mut self, id: Ident) -> Ident {
+    if id.span.ctxt() == self.top_level_ctxt {
+      self.refs.push(id.clone());
+    }
+    id
+  }
+
+  fn fold_binding_ident(&mut self, ident: BindingIdent) -> BindingIdent {
+    self.add_binding(&ident.id);
+    ident.fold_children_with(self)
+  }
+
+  fn fold_fn_decl(&mut self, decl: FnDecl) -> FnDecl {
+    self.add_binding(&decl.ident);
+    decl.fold_children_with(self)
+  }
+
+  fn fold_class_decl(&mut self, decl: ClassDecl) -> ClassDecl {
+    self.add_binding(&decl.ident);
+    decl.fold_children_with(self)
+  }
+
+  fn fold_import_specifier(&mut self, specifier: ImportSpecifier) -> ImportSpecifier {
+    match &specifier {
+      ImportSpecifier::Named(ImportNamedSpecifier { local, .. })
+      | ImportSpecifier::Default(ImportDefaultSpecifier { local, .. })
+      | ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => self.add_binding(local),
+    }
+    specifier
+  }
+}
+
+/// gets the root identifier of a path like `process.env.NODE_ENV`.
+fn get_root_ident(expr: &Expr) -> Option<&Ident> {
+  match expr {
+    Expr::Ident(id) => Some(id),
+    Expr::Member(MemberExpr {
+      obj: ExprOrSuper::Expr(obj),
+      ..
+    }) => get_root_ident(obj.as_ref()),
+    _ => None,
+  }
+}
+
+/// gets the dotted path of an expression like `import.meta.env.MODE`.
+fn get_expr_path(expr: &Expr) -> Option<String> {
+  match expr {
+    Expr::Ident(id) => Some(id.sym.as_ref().into()),
+    Expr::MetaProp(MetaPropExpr { meta, prop }) => {
+      Some(format!("{}.{}", meta.sym.as_ref(), prop.sym.as_ref()))
+    }
+    Expr::Member(MemberExpr {
+      obj: ExprOrSuper::Expr(obj),
+      prop,
+      computed,
+      ..
+    }) => {
+      let name = match (prop.as_ref(), computed) {
+        (Expr::Ident(id), false) => id.sym.as_ref(),
+        (Expr::Lit(Lit::Str(Str { value, .. })), true) => value.as_ref(),
+        _ => return None,
+      };
+      get_expr_path(obj.as_ref()).map(|path| format!("{}.{}", path, name))
+    }
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::swc::{test_resolver, EmitOptions, SWC};
+  use std::collections::HashMap;
+
+  #[test]
+  fn define_constants() {
+    let source = r#"
+      if (process.env.NODE_ENV !== "production") {
+        console.log("dev")
+        import("./devtools.ts")
+      } else {
+        console.log("prod")
+      }
+      if (import.meta.env.MODE === "development") {
+        console.log("dev mode")
+      }
+      const mode = import.meta.env["MODE"]
+      const env = { NODE_ENV: "test" }
+      console.log(env.NODE_ENV, __DEBUG__ && "debug")
+    "#;
+    let module = SWC::parse("/app.ts", source, None).expect("could not parse module");
+    let resolver = test_resolver("/app.ts");
+    let mut define = HashMap::new();
+    define.insert("process.env.NODE_ENV".into(), "\"production\"".into());
+    define.insert("import.meta.env.MODE".into(), "\"production\"".into());
+    define.insert("__DEBUG__".into(), "false".into());
+    let (code, _) = module
+      .transform(
+        resolver.clone(),
+        &EmitOptions {
+          define,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains("console.log(\"prod\")"));
+    assert!(!code.contains("console.log(\"dev\")"));
+    assert!(!code.contains("dev mode"));
+    assert!(code.contains("const mode = \"production\""));
+    assert!(code.contains("console.log(env.NODE_ENV, false)"));
+    assert!(resolver.borrow().dep_graph.is_empty());
+
+    let source = r#"
+      process.env.NODE_ENV = "test"
+      __DEBUG__ = true
+      __DEBUG__++
+      ;[process.env.NODE_ENV] = ["test"]
+      function f(process, __DEBUG__) {
+        return [process.env.NODE_ENV, __DEBUG__]
+      }
+      export const env = [f, process.env.NODE_ENV]
+    "#;
+    let module = SWC::parse("/env.ts", source, None).expect("could not parse module");
+    let mut define = HashMap::new();
+    define.insert("process.env.NODE_ENV".into(), "\"production\"".into());
+    define.insert("__DEBUG__".into(), "false".into());
+    let (code, _) = module
+      .transform(
+        resolver.clone(),
+        &EmitOptions {
+          define: define.clone(),
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains("process.env.NODE_ENV = \"test\";"));
+    assert!(code.contains("__DEBUG__ = true;"));
+    assert!(code.contains("__DEBUG__++;"));
+    assert!(code.contains("[process.env.NODE_ENV] = ["));
+    assert!(code.contains("return [\n        process.env.NODE_ENV,\n        __DEBUG__\n    ];"));
+    assert!(code.contains("f,\n    \"production\""));
+
+    let source = r#"
+      import { debug as __DEBUG__ } from "./debug.ts"
+      const process = { env: { NODE_ENV: "test" } }
+      export const env = [process.env.NODE_ENV, __DEBUG__]
+    "#;
+    let module = SWC::parse("/env.ts", source, None).expect("could not parse module");
+    let (code, _) = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          define,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    assert!(code.contains("process.env.NODE_ENV,\n    __DEBUG__"));
+  }
+}
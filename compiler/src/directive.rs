@@ -0,0 +1,336 @@
+use crate::metadata::parse_export_signature;
+
+use serde::{Deserialize, Serialize};
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::quote_ident;
+use swc_ecma_visit::{noop_fold_type, Fold};
+
+/// The `"use client"` or `"use server"` directive at the top of a module, which marks
+/// a boundary of the React Server Components.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModuleDirective {
+  Client,
+  Server,
+}
+
+/// parse the directive of the module from the directive prologue.
+pub fn parse_module_directive(module: &Module) -> Option<ModuleDirective> {
+  let stmts = module.body.iter().map_while(|item| match item {
+    ModuleItem::Stmt(stmt) => Some(stmt),
+    _ => None,
+  });
+  for directive in get_directives(stmts) {
+    match directive {
+      "use client" => return Some(ModuleDirective::Client),
+      "use server" => return Some(ModuleDirective::Server),
+      _ => {}
+    }
+  }
+  None
+}
+
+/// parse the names of the server-only exports, which are all the exports of a
+/// `"use server"` module, or the exported functions with their own `"use server"`
+/// directive in other modules.
+pub fn parse_server_exports(module: &Module) -> Vec<String> {
+  if parse_module_directive(module) == Some(ModuleDirective::Server) {
+    return parse_export_signature(module).names;
+  }
+  let mut names = vec![];
+  for item in module.body.iter() {
+    if let ModuleItem::ModuleDecl(decl) = item {
+      match decl {
+        // match: export async function save() { "use server" }
+        ModuleDecl::ExportDecl(ExportDecl {
+          decl: Decl::Fn(FnDecl {
+            ident, function, ..
+          }),
+          ..
+        }) if is_server_function(function) => names.push(ident.sym.as_ref().into()),
+        // match: export const save = async () => { "use server" }
+        ModuleDecl::ExportDecl(ExportDecl {
+          decl: Decl::Var(var),
+          ..
+        }) => {
+          for decl in var.decls.iter() {
+            if let (Pat::Ident(BindingIdent { id, .. }), Some(init)) = (&decl.name, &decl.init) {
+              if is_server_function_expr(init) {
+                names.push(id.sym.as_ref().into());
+              }
+            }
+          }
+        }
+        // match: export default async function() { "use server" }
+        ModuleDecl::ExportDefaultDecl(ExportDefaultDecl {
+          decl: DefaultDecl::Fn(FnExpr { function, .. }),
+          ..
+        }) if is_server_function(function) => names.push("default".into()),
+        _ => {}
+      }
+    }
+  }
+  names
+}
+
+/// Creates a fold that replaces the server-only exports (see `parse_server_exports`)
+/// with the stubs that throw when they are called, for emitting the module for the
+/// browser without the server code:
+///
+/// ```js
+/// export async function save(data) {
+///   "use server"
+///   await Deno.writeTextFile("./data.json", data)
+/// }
+/// ```
+///
+/// is transformed to:
+///
+/// ```js
+/// export function save() {
+///   throw new Error("the server function \"save\" can't be called in the browser")
+/// }
+/// ```
+///
+/// The whole body of a `"use server"` module is replaced, including the imports.
+pub fn strip_server_exports_fold() -> impl Fold {
+  StripServerExportsFold {}
+}
+
+struct StripServerExportsFold {}
+
+impl Fold for StripServerExportsFold {
+  noop_fold_type!();
+
+  fn fold_module(&mut self, module: Module) -> Module {
+    if parse_module_directive(&module) == Some(ModuleDirective::Server) {
+      let body = parse_export_signature(&module)
+        .names
+        .iter()
+        .map(|name| new_stub_export(name))
+        .collect();
+      return Module { body, ..module };
+    }
+    let body = module
+      .body
+      .into_iter()
+      .map(|item| match item {
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+          decl: Decl::Fn(FnDecl {
+            ident, function, ..
+          }),
+          ..
+        }))
+          if is_server_function(&function) =>
+        {
+          new_stub_export(ident.sym.as_ref())
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+          span,
+          decl: Decl::Var(var),
+        })) => {
+          let decls = var
+            .decls
+            .into_iter()
+            .map(|decl| match (&decl.name, &decl.init) {
+              (Pat::Ident(BindingIdent { id, .. }), Some(init))
+                if is_server_function_expr(init) =>
+              {
+                VarDeclarator {
+                  init: Some(Box::new(Expr::Fn(FnExpr {
+                    ident: None,
+                    function: new_stub_function(id.sym.as_ref()),
+                  }))),
+                  ..decl
+                }
+              }
+              _ => decl,
+            })
+            .collect();
+          ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+            span,
+            decl: Decl::Var(VarDecl { decls, ..var }),
+          }))
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(ExportDefaultDecl {
+          decl: DefaultDecl::Fn(FnExpr { function, .. }),
+          ..
+        }))
+          if is_server_function(&function) =>
+        {
+          new_stub_export("default")
+        }
+        _ => item,
+      })
+      .collect();
+    Module { body, ..module }
+  }
+}
+
+/// returns the string literals of the directive prologue.
+fn get_directives<'a>(stmts: impl Iterator<Item = &'a Stmt>) -> Vec<&'a str> {
+  stmts
+    .map_while(|stmt| match stmt {
+      Stmt::Expr(ExprStmt { expr, .. }) => match expr.as_ref() {
+        Expr::Lit(Lit::Str(s)) => Some(s.value.as_ref()),
+        _ => None,
+      },
+      _ => None,
+    })
+    .collect()
+}
+
+fn is_server_function(function: &Function) -> bool {
+  match &function.body {
+    Some(BlockStmt { stmts, .. }) => get_directives(stmts.iter()).contains(&"use server"),
+    None => false,
+  }
+}
+
+fn is_server_function_expr(expr: &Expr) -> bool {
+  match expr {
+    Expr::Fn(FnExpr { function, .. }) => is_server_function(function),
+    Expr::Arrow(ArrowExpr {
+      body: BlockStmtOrExpr::BlockStmt(BlockStmt { stmts, .. }),
+      ..
+    }) => get_directives(stmts.iter()).contains(&"use server"),
+    _ => false,
+  }
+}
+
+// match: export function save() { throw new Error("...") }
+fn new_stub_export(name: &str) -> ModuleItem {
+  let function = new_stub_function(name);
+  ModuleItem::ModuleDecl(if name.eq("default") {
+    ModuleDecl::ExportDefaultDecl(ExportDefaultDecl {
+      span: DUMMY_SP,
+      decl: DefaultDecl::Fn(FnExpr {
+        ident: None,
+        function,
+      }),
+    })
+  } else {
+    ModuleDecl::ExportDecl(ExportDecl {
+      span: DUMMY_SP,
+      decl: Decl::Fn(FnDecl {
+        ident: quote_ident!(name),
+        declare: false,
+        function,
+      }),
+    })
+  })
+}
+
+fn new_stub_function(name: &str) -> Function {
+  let message = format!(
+    "the server function \"{}\" can't be called in the browser",
+    name
+  );
+  Function {
+    params: vec![],
+    decorators: vec![],
+    span: DUMMY_SP,
+    body: Some(BlockStmt {
+      span: DUMMY_SP,
+      stmts: vec![Stmt::Throw(ThrowStmt {
+        span: DUMMY_SP,
+        arg: Box::new(Expr::New(NewExpr {
+          span: DUMMY_SP,
+          callee: Box::new(Expr::Ident(quote_ident!("Error"))),
+          args: Some(vec![ExprOrSpread {
+            spread: None,
+            expr: Box::new(Expr::Lit(Lit::Str(Str {
+              span: DUMMY_SP,
+              value: message.into(),
+              has_escape: false,
+              kind: Default::default(),
+            }))),
+          }]),
+          type_args: None,
+        })),
+      })],
+    }),
+    is_generator: false,
+    is_async: false,
+    type_params: None,
+    return_type: None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::swc::{test_resolver, EmitOptions, TargetEnv, SWC};
+
+  fn transform(source: &str, options: EmitOptions) -> String {
+    let module = SWC::parse("/actions.ts", source, None).expect("could not parse module");
+    let resolver = test_resolver("/actions.ts");
+    let (code, _) = module
+      .transform(resolver, &options)
+      .expect("could not transform module");
+    println!("{}", code);
+    code
+  }
+
+  #[test]
+  fn server_directives() {
+    let source = r#"
+      "use server"
+      import { db } from "./db.ts"
+      export async function save(data: string) {
+        await db.save(data)
+      }
+      export default async function remove() {}
+    "#;
+    let module = SWC::parse("/actions.ts", source, None).expect("could not parse module");
+    assert_eq!(
+      parse_module_directive(&module.module),
+      Some(ModuleDirective::Server)
+    );
+    assert_eq!(
+      parse_server_exports(&module.module),
+      vec!["save", "default"]
+    );
+    let browser = EmitOptions {
+      target_env: Some(TargetEnv::Browser),
+      strip_server_exports: true,
+      ..Default::default()
+    };
+    let code = transform(source, browser.clone());
+    assert!(!code.contains("import"));
+    assert!(code.contains(
+      "export function save() {\n    throw new Error(\"the server function \\\"save\\\" can't be called in the browser\");\n}"
+    ));
+    assert!(code.contains("export default function() {\n    throw new Error("));
+    let code = transform(source, EmitOptions::default());
+    assert!(code.contains("await db.save(data)"));
+
+    let source = r#"
+      "use client"
+      import { useState } from "https://esm.sh/react"
+      export const save = async (data: string) => {
+        "use server"
+        await Deno.writeTextFile("./data.json", data)
+      }
+      export function Form() {
+        const [data] = useState("")
+        return save(data)
+      }
+    "#;
+    let module = SWC::parse("/form.ts", source, None).expect("could not parse module");
+    assert_eq!(
+      parse_module_directive(&module.module),
+      Some(ModuleDirective::Client)
+    );
+    assert_eq!(parse_server_exports(&module.module), vec!["save"]);
+    let code = transform(source, browser);
+    assert!(!code.contains("Deno.writeTextFile"));
+    assert!(code.contains("export const save = function() {\n    throw new Error("));
+    assert!(code.contains("return save(data);"));
+
+    let module =
+      SWC::parse("/app.ts", "const a = 'use client'", None).expect("could not parse module");
+    assert_eq!(parse_module_directive(&module.module), None);
+  }
+}
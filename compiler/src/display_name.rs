@@ -0,0 +1,199 @@
+use crate::fast_refresh::is_componentish_name;
+
+use std::collections::HashSet;
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::quote_ident;
+use swc_ecma_visit::{noop_fold_type, Fold};
+
+/// Creates a fold that names the components in development for React DevTools,
+/// the anonymous function components and the `memo`/`forwardRef` wrappers that are
+/// assigned to the top-level bindings get a `displayName`:
+///
+/// ```js
+/// const Logo = memo(() => <img src="/logo.svg" />)
+/// Logo.displayName = "Logo";
+/// ```
+pub fn display_name_fold() -> impl Fold {
+  DisplayNameFold {}
+}
+
+struct DisplayNameFold {}
+
+impl Fold for DisplayNameFold {
+  noop_fold_type!();
+
+  fn fold_module_items(&mut self, items: Vec<ModuleItem>) -> Vec<ModuleItem> {
+    // the components that are named already, match: `App.displayName = "App"`
+    let mut named: HashSet<String> = HashSet::new();
+    for item in items.iter() {
+      if let ModuleItem::Stmt(Stmt::Expr(ExprStmt { expr, .. })) = item {
+        if let Some(name) = get_display_name_target(expr) {
+          named.insert(name);
+        }
+      }
+    }
+
+    let mut output = Vec::with_capacity(items.len());
+    for item in items {
+      let decl = match &item {
+        ModuleItem::Stmt(Stmt::Decl(Decl::Var(decl))) => Some(decl),
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+          decl: Decl::Var(decl),
+          ..
+        })) => Some(decl),
+        _ => None,
+      };
+      let mut names: Vec<String> = vec![];
+      if let Some(VarDecl { decls, .. }) = decl {
+        for decl in decls {
+          if let (Pat::Ident(BindingIdent { id, .. }), Some(init)) = (&decl.name, &decl.init) {
+            let name = id.sym.as_ref();
+            if is_componentish_name(name) && !named.contains(name) && is_unnamed_component(init) {
+              names.push(name.into());
+            }
+          }
+        }
+      }
+      output.push(item);
+      for name in names {
+        output.push(new_display_name_stmt(name.as_str()));
+      }
+    }
+    output
+  }
+}
+
+/// checks if the expression is an anonymous function or a wrapped component,
+/// match: `() => {}`, `function () {}`, `memo(() => {})`, `React.forwardRef(...)`.
+fn is_unnamed_component(expr: &Expr) -> bool {
+  match expr {
+    Expr::Arrow(_) => true,
+    Expr::Fn(FnExpr { ident: None, .. }) => true,
+    Expr::Paren(ParenExpr { expr, .. }) => is_unnamed_component(expr),
+    Expr::Call(CallExpr {
+      callee: ExprOrSuper::Expr(callee),
+      ..
+    }) => {
+      let name = match callee.as_ref() {
+        Expr::Ident(id) => id.sym.as_ref(),
+        Expr::Member(MemberExpr {
+          obj: ExprOrSuper::Expr(obj),
+          prop,
+          computed: false,
+          ..
+        }) => match (obj.as_ref(), prop.as_ref()) {
+          (Expr::Ident(obj), Expr::Ident(prop)) if obj.sym.as_ref().eq("React") => {
+            prop.sym.as_ref()
+          }
+          _ => return false,
+        },
+        _ => return false,
+      };
+      matches!(name, "memo" | "forwardRef")
+    }
+    _ => false,
+  }
+}
+
+fn get_display_name_target(expr: &Expr) -> Option<String> {
+  let left = match expr {
+    Expr::Assign(AssignExpr { left, .. }) => match left {
+      PatOrExpr::Expr(expr) => expr.as_ref(),
+      PatOrExpr::Pat(pat) => match pat.as_ref() {
+        Pat::Expr(expr) => expr.as_ref(),
+        _ => return None,
+      },
+    },
+    _ => return None,
+  };
+  if let Expr::Member(MemberExpr {
+    obj: ExprOrSuper::Expr(obj),
+    prop,
+    computed: false,
+    ..
+  }) = left
+  {
+    if let (Expr::Ident(obj), Expr::Ident(prop)) = (obj.as_ref(), prop.as_ref()) {
+      if prop.sym.as_ref().eq("displayName") {
+        return Some(obj.sym.as_ref().into());
+      }
+    }
+  }
+  None
+}
+
+// match: App.displayName = "App"
+fn new_display_name_stmt(name: &str) -> ModuleItem {
+  ModuleItem::Stmt(Stmt::Expr(ExprStmt {
+    span: DUMMY_SP,
+    expr: Box::new(Expr::Assign(AssignExpr {
+      span: DUMMY_SP,
+      op: AssignOp::Assign,
+      left: PatOrExpr::Expr(Box::new(Expr::Member(MemberExpr {
+        span: DUMMY_SP,
+        obj: ExprOrSuper::Expr(Box::new(Expr::Ident(quote_ident!(name)))),
+        prop: Box::new(Expr::Ident(quote_ident!("displayName"))),
+        computed: false,
+      }))),
+      right: Box::new(Expr::Lit(Lit::Str(Str {
+        span: DUMMY_SP,
+        value: name.into(),
+        has_escape: false,
+        kind: Default::default(),
+      }))),
+    })),
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::swc::{test_resolver, EmitOptions, SWC};
+
+  #[test]
+  fn inject_display_name() {
+    let source = r#"
+      import React, { memo, forwardRef } from "https://esm.sh/react"
+      const Logo = () => <img src="/logo.svg" />
+      export const Button = memo(function () { return <button /> })
+      export const Input = React.forwardRef((props, ref) => <input ref={ref} />)
+      const Title = memo(() => <h1 />)
+      Title.displayName = "PageTitle"
+      function Header() { return <header /> }
+      const Footer = function Footer() { return <footer /> }
+      const config = () => ({})
+      export default function App() {
+        return <Logo />
+      }
+    "#;
+    let transform = |is_dev: bool| {
+      let module = SWC::parse("/app.tsx", source, None).expect("could not parse module");
+      let resolver = test_resolver("/app.tsx");
+      let (code, _) = module
+        .transform(
+          resolver,
+          &EmitOptions {
+            is_dev,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      code
+    };
+
+    let code = transform(true);
+    assert!(code.contains("Logo.displayName = \"Logo\";"));
+    assert!(code.contains("Button.displayName = \"Button\";"));
+    assert!(code.contains("Input.displayName = \"Input\";"));
+    assert!(code.contains("Title.displayName = \"PageTitle\";"));
+    assert!(!code.contains("Title.displayName = \"Title\""));
+    assert!(!code.contains("Header.displayName"));
+    assert!(!code.contains("Footer.displayName"));
+    assert!(!code.contains("config.displayName"));
+    assert!(!code.contains("App.displayName"));
+
+    let code = transform(false);
+    assert!(!code.contains("Logo.displayName"));
+  }
+}
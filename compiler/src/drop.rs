@@ -0,0 +1,200 @@
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// Creates a fold that removes the `console.*()` calls, the `debugger` statements and
+/// the calls of the `drop_calls` (like `invariantDev` or `dev.assert`), the calls in
+/// expressions are replaced with `void 0`.
+pub fn drop_fold(drop_console: bool, drop_debugger: bool, drop_calls: Vec<String>) -> impl Fold {
+  DropFold {
+    drop_console,
+    drop_debugger,
+    drop_calls,
+  }
+}
+
+struct DropFold {
+  drop_console: bool,
+  drop_debugger: bool,
+  drop_calls: Vec<String>,
+}
+
+impl DropFold {
+  fn should_drop(&self, stmt: &Stmt) -> bool {
+    match stmt {
+      Stmt::Debugger(_) => self.drop_debugger,
+      Stmt::Expr(ExprStmt { expr, .. }) => self.should_drop_call(expr),
+      _ => false,
+    }
+  }
+
+  fn should_drop_call(&self, expr: &Expr) -> bool {
+    (self.drop_console && is_console_call(expr))
+      || (!self.drop_calls.is_empty()
+        && matches!(get_callee_name(expr), Some(name) if self.drop_calls.contains(&name)))
+  }
+}
+
+impl Fold for DropFold {
+  noop_fold_type!();
+
+  fn fold_module_items(&mut self, items: Vec<ModuleItem>) -> Vec<ModuleItem> {
+    let mut output = Vec::with_capacity(items.len());
+    for item in items {
+      if let ModuleItem::Stmt(stmt) = &item {
+        if self.should_drop(stmt) {
+          continue;
+        }
+      }
+      output.push(item.fold_with(self));
+    }
+    output
+  }
+
+  fn fold_stmts(&mut self, stmts: Vec<Stmt>) -> Vec<Stmt> {
+    let mut output = Vec::with_capacity(stmts.len());
+    for stmt in stmts {
+      if !self.should_drop(&stmt) {
+        output.push(stmt.fold_with(self));
+      }
+    }
+    output
+  }
+
+  // match: if (debug) console.log("debug")
+  fn fold_stmt(&mut self, stmt: Stmt) -> Stmt {
+    if self.should_drop(&stmt) {
+      return Stmt::Empty(EmptyStmt { span: DUMMY_SP });
+    }
+    stmt.fold_children_with(self)
+  }
+
+  // match: debug && console.log("debug")
+  fn fold_expr(&mut self, expr: Expr) -> Expr {
+    if self.should_drop_call(&expr) {
+      return Expr::Unary(UnaryExpr {
+        span: DUMMY_SP,
+        op: UnaryOp::Void,
+        arg: Box::new(Expr::Lit(Lit::Num(Number {
+          span: DUMMY_SP,
+          value: 0.0,
+        }))),
+      });
+    }
+    expr.fold_children_with(self)
+  }
+}
+
+/// checks if the expression is a call like `console.log()` or `console.log.apply()`.
+fn is_console_call(expr: &Expr) -> bool {
+  match expr {
+    Expr::Call(CallExpr {
+      callee: ExprOrSuper::Expr(callee),
+      ..
+    }) => is_console_member(callee),
+    Expr::Paren(ParenExpr { expr, .. }) => is_console_call(expr),
+    _ => false,
+  }
+}
+
+/// returns the name of the callee, like `invariant` or `dev.assert`.
+fn get_callee_name(expr: &Expr) -> Option<String> {
+  match expr {
+    Expr::Call(CallExpr {
+      callee: ExprOrSuper::Expr(callee),
+      ..
+    }) => get_member_name(callee),
+    Expr::Paren(ParenExpr { expr, .. }) => get_callee_name(expr),
+    _ => None,
+  }
+}
+
+fn get_member_name(expr: &Expr) -> Option<String> {
+  match expr {
+    Expr::Ident(id) => Some(id.sym.as_ref().into()),
+    Expr::Member(MemberExpr {
+      obj: ExprOrSuper::Expr(obj),
+      prop,
+      computed: false,
+      ..
+    }) => match prop.as_ref() {
+      Expr::Ident(prop) => Some(format!("{}.{}", get_member_name(obj)?, prop.sym)),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+fn is_console_member(expr: &Expr) -> bool {
+  match expr {
+    Expr::Member(MemberExpr {
+      obj: ExprOrSuper::Expr(obj),
+      ..
+    }) => match obj.as_ref() {
+      Expr::Ident(id) => id.sym.as_ref().eq("console"),
+      _ => is_console_member(obj),
+    },
+    _ => false,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::swc::{test_resolver, EmitOptions, SWC};
+
+  #[test]
+  fn drop_console_and_debugger() {
+    let source = r#"
+      console.log("top")
+      export function run(debug: boolean) {
+        debugger
+        console.warn.apply(console, ["warn"])
+        if (debug) console.info("debug")
+        const ok = debug && console.error("error")
+        window.console.log("window")
+        invariantDev(debug, "debug")
+        const valid = debug || dev.assert(false)
+        return log("keep")
+      }
+    "#;
+    let transform = |is_dev: bool, drop_debugger: bool| {
+      let module = SWC::parse("/app.ts", source, None).expect("could not parse module");
+      let resolver = test_resolver("/app.ts");
+      let (code, _) = module
+        .transform(
+          resolver,
+          &EmitOptions {
+            drop_console: true,
+            drop_debugger,
+            strip_calls: vec!["invariantDev".into(), "dev.assert".into()],
+            is_dev,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      code
+    };
+
+    let code = transform(false, true);
+    assert!(!code.contains("console.log(\"top\")"));
+    assert!(!code.contains("console.warn"));
+    assert!(!code.contains("console.info"));
+    assert!(!code.contains("console.error"));
+    assert!(code.contains("window.console.log(\"window\")"));
+    assert!(!code.contains("debugger"));
+    assert!(code.contains("const ok = debug && void 0"));
+    assert!(!code.contains("invariantDev"));
+    assert!(code.contains("const valid = debug || void 0"));
+    assert!(code.contains("log(\"keep\")"));
+
+    let code = transform(false, false);
+    assert!(!code.contains("console.warn"));
+    assert!(code.contains("debugger"));
+
+    let code = transform(true, true);
+    assert!(code.contains("console.log(\"top\")"));
+    assert!(code.contains("invariantDev(debug, \"debug\")"));
+    assert!(code.contains("debugger"));
+  }
+}
@@ -1,9 +1,13 @@
-use std::{fmt, sync::Arc, sync::RwLock};
+use serde::{Deserialize, Serialize};
+use std::{error::Error, fmt, sync::Arc, sync::RwLock};
 use swc_common::{
   errors::{Diagnostic, DiagnosticBuilder, Emitter},
-  Loc, Span,
+  Loc, SourceFile, Span,
 };
 
+/// the lines of the source before and after the span in the code frame.
+const CODE_FRAME_CONTEXT_LINES: usize = 2;
+
 /// A buffer for collecting errors from the AST parser.
 #[derive(Debug, Clone)]
 pub struct ErrorBuffer(Arc<RwLock<Vec<Diagnostic>>>);
@@ -20,34 +24,209 @@ impl Emitter for ErrorBuffer {
   }
 }
 
+/// The stable code of a diagnostic, for the dev overlay to tell the kinds of the
+/// errors apart without parsing the messages.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DiagnosticCode {
+  /// the source can't be parsed
+  SyntaxError,
+  /// the module is parsed but can't be transformed, like a `const enum` that is
+  /// not declared in the `constEnums` with `isolatedModules`
+  TransformError,
+  /// the options or the inputs of the compiler are invalid
+  InvalidOptions,
+  /// the non-fatal warnings of the module, like the unused imports
+  Warning,
+  /// the modules import each other statically, which cascades the HMR updates and
+  /// makes the initialization order of SSR depend on the entry
+  ImportCycle,
+}
+
+/// A diagnostic message with the location in the source.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticMessage {
+  pub file_name: String,
+  /// 1-based line number.
+  pub line: usize,
+  /// 0-based column number.
+  pub column: usize,
+  /// the length of the span in bytes.
+  pub length: usize,
+  pub message: String,
+  pub code: DiagnosticCode,
+  /// the source lines around the span, for the messages with a location
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub frame: Option<Vec<CodeFrameLine>>,
+  /// the modules of the import cycle, from and back to the `file_name`
+  #[serde(default, skip_serializing_if = "Option::is_none")]
+  pub cycle: Option<Vec<String>>,
+}
+
+/// A line of the code frame of a diagnostic.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct CodeFrameLine {
+  /// 1-based line number.
+  pub line: usize,
+  pub text: String,
+  /// the `[start, end)` columns of the span in the line, in chars, or `None` if
+  /// the line is the context.
+  pub highlight: Option<(usize, usize)>,
+}
+
+impl DiagnosticMessage {
+  /// Creates a message for the `span` at the location `loc`.
+  pub fn from_span(span: Span, loc: Loc, code: DiagnosticCode, message: String) -> Self {
+    DiagnosticMessage {
+      file_name: loc.file.name.to_string(),
+      line: loc.line,
+      column: loc.col_display,
+      length: (span.hi.0 - span.lo.0) as usize,
+      message,
+      code,
+      frame: new_code_frame(&loc.file, span),
+      cycle: None,
+    }
+  }
+}
+
+/// returns the lines of the `span` in the `file` with the context lines before and
+/// after it, or `None` if the span is not in the file.
+fn new_code_frame(file: &SourceFile, span: Span) -> Option<Vec<CodeFrameLine>> {
+  let src = file.src.as_str();
+  let lo = span.lo.0.checked_sub(file.start_pos.0)? as usize;
+  let hi = span.hi.0.checked_sub(file.start_pos.0)? as usize;
+  if lo > src.len() || hi < lo {
+    return None;
+  }
+  let hi = hi.min(src.len());
+  let first = src[..lo].matches('\n').count();
+  let last = src[..hi].matches('\n').count();
+  let columns =
+    |text: &str, offset: usize| text.get(..offset).map_or(offset, |s| s.chars().count());
+  let mut lines = vec![];
+  let mut offset = 0;
+  for (index, text) in src.split('\n').enumerate() {
+    if index > last + CODE_FRAME_CONTEXT_LINES {
+      break;
+    }
+    let next_offset = offset + text.len() + 1;
+    let text = text.trim_end_matches('\r');
+    if index + CODE_FRAME_CONTEXT_LINES >= first {
+      let highlight = if index >= first && index <= last {
+        let start = if index == first { lo - offset } else { 0 };
+        let end = if index == last {
+          hi - offset
+        } else {
+          text.len()
+        };
+        Some((columns(text, start), columns(text, end.min(text.len()))))
+      } else {
+        None
+      };
+      lines.push(CodeFrameLine {
+        line: index + 1,
+        text: text.into(),
+        highlight,
+      });
+    }
+    offset = next_offset;
+  }
+  Some(lines)
+}
+
+impl fmt::Display for DiagnosticMessage {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    if self.line > 0 {
+      write!(fmt, "{} at {}:{}", self.message, self.line, self.column)
+    } else {
+      write!(fmt, "{}", self.message)
+    }
+  }
+}
+
 /// A buffer for collecting diagnostic messages from the AST parser.
-#[derive(Debug)]
-pub struct DiagnosticBuffer(Vec<String>);
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiagnosticBuffer {
+  pub diagnostics: Vec<DiagnosticMessage>,
+}
 
 impl fmt::Display for DiagnosticBuffer {
   fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-    fmt.pad(&self.0.join(","))
+    let messages: Vec<String> = self.diagnostics.iter().map(|d| d.to_string()).collect();
+    fmt.pad(&messages.join(","))
   }
 }
 
+impl Error for DiagnosticBuffer {}
+
 impl DiagnosticBuffer {
-  pub fn from_error_buffer<F>(error_buffer: ErrorBuffer, get_loc: F) -> Self
+  pub fn from_error_buffer<F>(error_buffer: ErrorBuffer, code: DiagnosticCode, get_loc: F) -> Self
   where
     F: Fn(Span) -> Loc,
   {
     let diagnostics = error_buffer.0.read().unwrap().clone();
     let diagnostics = diagnostics
       .iter()
-      .map(|d| {
-        let mut message = d.message();
-        if let Some(span) = d.span.primary_span() {
-          let loc = get_loc(span);
-          message = format!("{} at {}:{}", message, loc.line, loc.col_display);
-        }
-        message
+      .map(|d| match d.span.primary_span() {
+        Some(span) => DiagnosticMessage::from_span(span, get_loc(span), code, d.message()),
+        None => DiagnosticMessage {
+          file_name: "".into(),
+          line: 0,
+          column: 0,
+          length: 0,
+          message: d.message(),
+          code,
+          frame: None,
+          cycle: None,
+        },
       })
       .collect();
 
-    Self(diagnostics)
+    Self { diagnostics }
+  }
+
+  /// Creates a buffer with a single message that has no location, for errors
+  /// that are not reported by the parser.
+  pub fn from_message(file_name: &str, code: DiagnosticCode, message: String) -> Self {
+    Self {
+      diagnostics: vec![DiagnosticMessage {
+        file_name: file_name.into(),
+        line: 0,
+        column: 0,
+        length: 0,
+        message,
+        code,
+        frame: None,
+        cycle: None,
+      }],
+    }
+  }
+}
+
+/// An invalid or suspicious entry of the import map, the `scope` is the scope
+/// that contains the entry, or `None` for the top-level imports.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ImportMapDiagnostic {
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub scope: Option<String>,
+  pub key: String,
+  pub value: String,
+  pub reason: String,
+}
+
+impl fmt::Display for ImportMapDiagnostic {
+  fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match &self.scope {
+      Some(scope) => write!(
+        fmt,
+        "{:?}: {:?} in the scope {:?}: {}",
+        self.key, self.value, scope, self.reason
+      ),
+      None => write!(fmt, "{:?}: {:?}: {}", self.key, self.value, self.reason),
+    }
   }
 }
@@ -1,26 +1,131 @@
 use indexmap::IndexSet;
+use serde::{Deserialize, Deserializer, Serialize};
 use sha1::{Digest, Sha1};
-use std::rc::Rc;
-use swc_common::{SourceMap, Spanned, DUMMY_SP};
+use swc_common::{comments::SingleThreadedComments, DUMMY_SP};
 use swc_ecma_ast::*;
 use swc_ecma_utils::{private_ident, quote_ident};
 use swc_ecma_visit::{noop_fold_type, Fold};
 
+/// The refresh runtime used by the fast refresh transform, e.g. React Refresh or
+/// Preact's `@prefresh/core`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct RefreshOptions {
+  /// the fast refresh transform is applied in development, `refresh: false`
+  /// opts the module out. default is `true`.
+  #[serde(default = "default_enabled")]
+  pub enabled: bool,
+  /// the function name to register components, default is `$RefreshReg$`.
+  #[serde(default = "default_refresh_reg")]
+  pub registration: String,
+  /// the function name to create hook signatures, default is `$RefreshSig$`.
+  #[serde(default = "default_refresh_sig")]
+  pub signature: String,
+  /// the url of the runtime module that is imported by the modules using refresh.
+  #[serde(default)]
+  pub runtime_import: Option<String>,
+  /// include the literal initial state of `useState`/`useReducer` in the hook
+  /// signatures, so changing it resets the component state. default is `true`.
+  #[serde(default = "default_signature_literal_args")]
+  pub signature_literal_args: bool,
+}
+
+impl Default for RefreshOptions {
+  fn default() -> Self {
+    RefreshOptions {
+      enabled: default_enabled(),
+      registration: default_refresh_reg(),
+      signature: default_refresh_sig(),
+      runtime_import: None,
+      signature_literal_args: default_signature_literal_args(),
+    }
+  }
+}
+
+/// deserializes the refresh options from `true`/`false` or the options object.
+pub fn deserialize_refresh_options<'de, D>(deserializer: D) -> Result<RefreshOptions, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum BoolOrOptions {
+    Bool(bool),
+    Options(RefreshOptions),
+  }
+
+  Ok(match BoolOrOptions::deserialize(deserializer)? {
+    BoolOrOptions::Bool(enabled) => RefreshOptions {
+      enabled,
+      ..Default::default()
+    },
+    BoolOrOptions::Options(options) => options,
+  })
+}
+
+fn default_enabled() -> bool {
+  true
+}
+
+fn default_refresh_reg() -> String {
+  "$RefreshReg$".into()
+}
+
+fn default_refresh_sig() -> String {
+  "$RefreshSig$".into()
+}
+
+fn default_signature_literal_args() -> bool {
+  true
+}
+
+/// The `@refresh` directive in the comments of a module, like react-refresh/babel.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RefreshDirective {
+  /// `/* @refresh skip */` opts the module out of fast refresh.
+  Skip,
+  /// `/* @refresh reset */` remounts the components of the module on every edit.
+  Reset,
+}
+
+/// finds the `@refresh` directive in the comments, `skip` wins over `reset`.
+pub fn parse_refresh_directive(comments: &SingleThreadedComments) -> Option<RefreshDirective> {
+  let mut directive = None;
+  let (leading, trailing) = comments.borrow_all();
+  for comment in leading.values().chain(trailing.values()).flatten() {
+    let mut words = comment.text.split_whitespace();
+    while let Some(word) = words.next() {
+      if word != "@refresh" {
+        continue;
+      }
+      match words.next() {
+        Some("skip") => return Some(RefreshDirective::Skip),
+        Some("reset") => directive = Some(RefreshDirective::Reset),
+        _ => {}
+      }
+    }
+  }
+  directive
+}
+
+/// Creates the fast refresh fold, the `force_reset` makes all the signatures reset
+/// the state of the components (the `@refresh reset` directive).
 pub fn react_refresh_fold(
-  refresh_reg: &str,
-  refresh_sig: &str,
+  options: &RefreshOptions,
   emit_full_signatures: bool,
-  source: Rc<SourceMap>,
+  force_reset: bool,
 ) -> impl Fold {
   ReactRefreshFold {
-    source,
     signature_index: 0,
     registration_index: 0,
     registrations: vec![],
     signatures: vec![],
-    refresh_reg: refresh_reg.into(),
-    refresh_sig: refresh_sig.into(),
+    refresh_reg: options.registration.clone(),
+    refresh_sig: options.signature.clone(),
+    runtime_import: options.runtime_import.clone(),
+    signature_literal_args: options.signature_literal_args,
     emit_full_signatures,
+    force_reset,
   }
 }
 
@@ -28,14 +133,16 @@ pub fn react_refresh_fold(
 ///
 /// @ref https://github.com/facebook/react/blob/master/packages/react-refresh/src/ReactFreshBabelPlugin.js
 pub struct ReactRefreshFold {
-  source: Rc<SourceMap>,
   signature_index: u32,
   registration_index: u32,
   registrations: Vec<(Ident, String)>,
   signatures: Vec<Signature>,
   refresh_reg: String,
   refresh_sig: String,
+  runtime_import: Option<String>,
+  signature_literal_args: bool,
   emit_full_signatures: bool,
+  force_reset: bool,
 }
 
 #[derive(Clone, Debug)]
@@ -346,35 +453,28 @@ impl ReactRefreshFold {
           && ident_str.starts_with("use")
           && ident_str[3..].starts_with(char::is_uppercase))
       {
+        // the key is generated from the AST instead of the source text, so the
+        // formatting changes will not reset the component state.
         let mut key = ident_str.to_owned();
         match pat {
           Some(pat) => {
-            let name = self.source.span_to_snippet(pat.span()).unwrap();
             key.push('{');
-            key.push_str(name.as_str());
+            key.push_str(get_pat_key(pat).as_str());
             // `useState` first argument is initial state.
-            if call.args.len() > 0 && is_builtin && ident_str == "useState" {
-              key.push('(');
-              key.push_str(
-                self
-                  .source
-                  .span_to_snippet(call.args[0].span())
-                  .unwrap()
-                  .as_str(),
-              );
-              key.push(')');
-            }
             // `useReducer` second argument is initial state.
-            if call.args.len() > 1 && is_builtin && ident_str == "useReducer" {
-              key.push('(');
-              key.push_str(
-                self
-                  .source
-                  .span_to_snippet(call.args[1].span())
-                  .unwrap()
-                  .as_str(),
-              );
-              key.push(')');
+            let initial_state = match ident_str {
+              "useState" if is_builtin => call.args.first(),
+              "useReducer" if is_builtin => call.args.get(1),
+              _ => None,
+            };
+            if let Some(ExprOrSpread { expr, .. }) = initial_state {
+              if self.signature_literal_args {
+                if let Some(lit) = get_literal_key(expr.as_ref()) {
+                  key.push('(');
+                  key.push_str(lit.as_str());
+                  key.push(')');
+                }
+              }
             }
             key.push('}');
           }
@@ -544,8 +644,7 @@ impl ReactRefreshFold {
       }),
       None => {}
     }
-    let mut force_reset = false;
-    // todo: parse @refresh reset command
+    let mut force_reset = self.force_reset;
     signature.hook_calls.clone().into_iter().for_each(|call| {
       key.push(call.key);
       if !call.is_builtin {
@@ -982,11 +1081,185 @@ impl Fold for ReactRefreshFold {
         })),
       })));
     }
+
+    // ! insert
+    // import "https://esm.sh/@prefresh/core";
+    if let Some(runtime_import) = &self.runtime_import {
+      if self.registration_index > 0 || self.signature_index > 0 {
+        items.insert(
+          0,
+          ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+            span: DUMMY_SP,
+            specifiers: vec![],
+            src: Str {
+              span: DUMMY_SP,
+              value: runtime_import.as_str().into(),
+              has_escape: false,
+              kind: Default::default(),
+            },
+            type_only: false,
+            asserts: None,
+          })),
+        );
+      }
+    }
     items
   }
 }
 
-fn is_componentish_name(name: &str) -> bool {
+/// gets the signature key of a binding pattern, like `[count, setCount]`.
+fn get_pat_key(pat: &Pat) -> String {
+  match pat {
+    Pat::Ident(BindingIdent { id, .. }) => id.sym.as_ref().into(),
+    Pat::Array(ArrayPat { elems, .. }) => format!(
+      "[{}]",
+      elems
+        .iter()
+        .map(|el| match el {
+          Some(pat) => get_pat_key(pat),
+          None => "".into(),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+    ),
+    Pat::Object(ObjectPat { props, .. }) => format!(
+      "{{{}}}",
+      props
+        .iter()
+        .map(|prop| match prop {
+          ObjectPatProp::Assign(AssignPatProp { key, .. }) => key.sym.as_ref().into(),
+          ObjectPatProp::KeyValue(KeyValuePatProp { key, value }) => {
+            let key = match key {
+              PropName::Ident(id) => id.sym.as_ref().into(),
+              PropName::Str(s) => format!("{:?}", s.value.as_ref()),
+              PropName::Num(n) => n.value.to_string(),
+              _ => "[]".into(),
+            };
+            format!("{}: {}", key, get_pat_key(value.as_ref()))
+          }
+          ObjectPatProp::Rest(RestPat { arg, .. }) => format!("...{}", get_pat_key(arg.as_ref())),
+        })
+        .collect::<Vec<String>>()
+        .join(", ")
+    ),
+    Pat::Assign(AssignPat { left, .. }) => get_pat_key(left.as_ref()),
+    Pat::Rest(RestPat { arg, .. }) => format!("...{}", get_pat_key(arg.as_ref())),
+    _ => "".into(),
+  }
+}
+
+/// gets the signature key of a literal argument, returns `None` for other expressions.
+fn get_literal_key(expr: &Expr) -> Option<String> {
+  match expr {
+    Expr::Lit(Lit::Str(s)) => Some(format!("{:?}", s.value.as_ref())),
+    Expr::Lit(Lit::Num(n)) => Some(n.value.to_string()),
+    Expr::Lit(Lit::Bool(b)) => Some(b.value.to_string()),
+    Expr::Lit(Lit::Null(_)) => Some("null".into()),
+    Expr::Unary(UnaryExpr {
+      op: UnaryOp::Minus,
+      arg,
+      ..
+    }) => match arg.as_ref() {
+      Expr::Lit(Lit::Num(n)) => Some(format!("-{}", n.value)),
+      _ => None,
+    },
+    Expr::Tpl(Tpl { exprs, quasis, .. }) if exprs.is_empty() && quasis.len() == 1 => {
+      Some(format!("{:?}", quasis[0].raw.value.as_ref()))
+    }
+    _ => None,
+  }
+}
+
+/// The classification of the exports of a module for the fast refresh, the HMR
+/// client accepts the update of a boundary, bubbles the update of a mixed module to
+/// the importers, and reloads the page if no importer accepts it.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RefreshBoundary {
+  /// only exports the components, the module can accept its own updates.
+  Boundary,
+  /// exports the components and the other values.
+  Mixed,
+  /// exports no components.
+  #[default]
+  None,
+}
+
+/// classifies the exports of the module, the exported types are ignored and the
+/// `export *` targets are treated as the values since they are unknown.
+pub fn refresh_boundary(module: &Module) -> RefreshBoundary {
+  let mut has_components = false;
+  let mut has_values = false;
+  for item in module.body.iter() {
+    let decl = match item {
+      ModuleItem::ModuleDecl(decl) => decl,
+      _ => continue,
+    };
+    // whether each export is a component
+    let exports: Vec<bool> = match decl {
+      ModuleDecl::ExportDecl(ExportDecl { decl, .. }) => match decl {
+        Decl::Fn(FnDecl { ident, .. }) | Decl::Class(ClassDecl { ident, .. }) => {
+          vec![is_componentish_name(ident.sym.as_ref())]
+        }
+        Decl::Var(VarDecl { decls, .. }) => decls
+          .iter()
+          .map(|decl| match &decl.name {
+            Pat::Ident(BindingIdent { id, .. }) => is_componentish_name(id.sym.as_ref()),
+            _ => false,
+          })
+          .collect(),
+        // the types are erased
+        Decl::TsInterface(_) | Decl::TsTypeAlias(_) => continue,
+        _ => vec![false],
+      },
+      ModuleDecl::ExportNamed(NamedExport {
+        type_only: true, ..
+      }) => continue,
+      ModuleDecl::ExportNamed(NamedExport { specifiers, .. }) => specifiers
+        .iter()
+        .map(|specifier| match specifier {
+          ExportSpecifier::Named(ExportNamedSpecifier { orig, exported, .. }) => {
+            let name = exported.as_ref().unwrap_or(orig).sym.as_ref();
+            name.eq("default") || is_componentish_name(name)
+          }
+          _ => false,
+        })
+        .collect(),
+      ModuleDecl::ExportDefaultDecl(ExportDefaultDecl { decl, .. }) => match decl {
+        DefaultDecl::TsInterfaceDecl(_) => continue,
+        _ => vec![true],
+      },
+      // match: export default App, export default memo(App)
+      ModuleDecl::ExportDefaultExpr(ExportDefaultExpr { expr, .. }) => match expr.as_ref() {
+        Expr::Ident(id) => vec![is_componentish_name(id.sym.as_ref())],
+        Expr::Arrow(_) | Expr::Fn(_) | Expr::Call(_) => vec![true],
+        _ => vec![false],
+      },
+      ModuleDecl::ExportAll(_) => vec![false],
+      _ => continue,
+    };
+    for is_component in exports {
+      if is_component {
+        has_components = true;
+      } else {
+        has_values = true;
+      }
+    }
+  }
+  match (has_components, has_values) {
+    (true, false) => RefreshBoundary::Boundary,
+    (true, true) => RefreshBoundary::Mixed,
+    _ => RefreshBoundary::None,
+  }
+}
+
+/// checks if the module is a refresh boundary that only exports components, the
+/// module can accept its own updates then.
+pub fn is_refresh_boundary(module: &Module) -> bool {
+  refresh_boundary(module) == RefreshBoundary::Boundary
+}
+
+pub fn is_componentish_name(name: &str) -> bool {
   name.starts_with(char::is_uppercase)
 }
 
@@ -1040,8 +1313,11 @@ fn get_call_callee(call: &CallExpr) -> Option<(Option<Ident>, Ident)> {
 #[cfg(test)]
 mod tests {
   use super::*;
-  use crate::swc::SWC;
+  use crate::import_map::ImportHashMap;
+  use crate::resolve::Resolver;
+  use crate::swc::{test_resolver, EmitOptions, SourceMapMode, SWC};
   use std::cmp::min;
+  use std::{cell::RefCell, rc::Rc};
   use swc_common::Globals;
 
   fn t(specifier: &str, source: &str, expect: &str) -> bool {
@@ -1049,12 +1325,10 @@ mod tests {
     let (code, _) = swc_common::GLOBALS.set(&Globals::new(), || {
       module
         .apply_transform(
-          react_refresh_fold(
-            "$RefreshReg$",
-            "$RefreshSig$",
-            true,
-            module.source_map.clone(),
-          ),
+          react_refresh_fold(&RefreshOptions::default(), true, false),
+          &SourceMapMode::None,
+          Default::default(),
+          false,
           false,
         )
         .expect("could not transpile module")
@@ -1077,6 +1351,183 @@ mod tests {
     code == expect
   }
 
+  #[test]
+  fn fast_refresh_directives() {
+    let transform = |source: &str, refresh: RefreshOptions| {
+      let module = SWC::parse("/pages/index.tsx", source, None).expect("could not parse module");
+      let resolver = Rc::new(RefCell::new(Resolver::new(
+        "/pages/index.tsx",
+        ImportHashMap::default(),
+        Some("https://deno.land/x/aleph@v0.3.0".into()),
+        None,
+        false,
+        vec![],
+      )));
+      let (code, _) = module
+        .transform(
+          resolver,
+          &EmitOptions {
+            is_dev: true,
+            hmr: true,
+            refresh,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      code
+    };
+    let source = r#"
+      import { useState } from "https://esm.sh/react"
+      import { useFancyState } from "../lib/hooks.ts"
+      export default function App() {
+        const [n] = useState(0)
+        const [m] = useFancyState()
+        return <p>{n}{m}</p>
+      }
+    "#;
+
+    let code = transform(source, RefreshOptions::default());
+    assert!(code.contains("_s(App, \"A9EDZeYRqjdgs+mYPlQ8lGgAxss=\", false, ()=>["));
+    assert!(code.contains("import.meta.hot.accept();"));
+    let code = transform(
+      &format!("// @refresh reset\n{}", source),
+      RefreshOptions::default(),
+    );
+    assert!(code.contains("_s(App, \"A9EDZeYRqjdgs+mYPlQ8lGgAxss=\", true, ()=>["));
+
+    for code in [
+      transform(
+        &format!("/* @refresh skip */\n{}", source),
+        RefreshOptions::default(),
+      ),
+      transform(
+        source,
+        RefreshOptions {
+          enabled: false,
+          ..Default::default()
+        },
+      ),
+    ]
+    .iter()
+    {
+      assert!(!code.contains("$RefreshReg$"));
+      assert!(!code.contains("$RefreshSig$"));
+      assert!(code.contains("import.meta.hot = createHotContext(\"/pages/index.tsx\");"));
+      assert!(!code.contains("import.meta.hot.accept()"));
+    }
+  }
+
+  #[test]
+  fn deserialize_refresh() {
+    #[derive(Deserialize)]
+    struct Options {
+      #[serde(default, deserialize_with = "deserialize_refresh_options")]
+      refresh: RefreshOptions,
+    }
+    let parse = |json: &str| -> RefreshOptions {
+      let options: Options = serde_json::from_str(json).unwrap();
+      options.refresh
+    };
+    assert!(parse("{}").enabled);
+    assert!(!parse("{ \"refresh\": false }").enabled);
+    let refresh = parse("{ \"refresh\": { \"registration\": \"__reg__\" } }");
+    assert!(refresh.enabled);
+    assert_eq!(refresh.registration, "__reg__");
+  }
+
+  #[test]
+  fn fast_refresh_custom_runtime() {
+    let source = r#"
+      import { useState } from "https://esm.sh/preact/hooks"
+      export default function App() {
+        const [n] = useState(0)
+        return <p>{n}</p>
+      }
+    "#;
+    let module = SWC::parse("/pages/index.tsx", source, None).expect("could not parse module");
+    let resolver = test_resolver("/pages/index.tsx");
+    let (code, _) = module
+      .transform(
+        resolver.clone(),
+        &EmitOptions {
+          is_dev: true,
+          refresh: RefreshOptions {
+            registration: "__prefresh_reg__".into(),
+            signature: "__prefresh_sig__".into(),
+            runtime_import: Some("https://esm.sh/@prefresh/core".into()),
+            ..Default::default()
+          },
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.starts_with("import"));
+    assert!(code.contains("\"../-/esm.sh/@prefresh/core.js\";"));
+    assert!(code.contains("var _s = __prefresh_sig__();"));
+    assert!(code.contains("__prefresh_reg__(_c, \"App\");"));
+    assert!(!code.contains("$Refresh"));
+    let r = resolver.borrow();
+    assert!(r
+      .dep_graph
+      .iter()
+      .any(|dep| dep.specifier == "https://esm.sh/@prefresh/core"));
+  }
+
+  #[test]
+  fn fast_refresh_stable_signatures() {
+    let signature_of = |source: &str, options: &RefreshOptions| {
+      let module = SWC::parse("/app.jsx", source, None).expect("could not parse module");
+      let (code, _) = swc_common::GLOBALS.set(&Globals::new(), || {
+        module
+          .apply_transform(
+            react_refresh_fold(options, false, false),
+            &SourceMapMode::None,
+            Default::default(),
+            false,
+            false,
+          )
+          .expect("could not transpile module")
+      });
+      code
+        .lines()
+        .find(|line| line.starts_with("_s(App, "))
+        .unwrap()
+        .to_owned()
+    };
+    let options = RefreshOptions::default();
+    let a = signature_of(
+      "function App() { const [n, setN] = useState(0); useEffect(() => {}); return n }",
+      &options,
+    );
+    let b = signature_of(
+      "function App() {\n  const [ n,setN ] = useState( 0 /* zero */ )\n  useEffect(() => { console.log(n) })\n  return n\n}",
+      &options,
+    );
+    let c = signature_of(
+      "function App() { const [n, setN] = useState(1); useEffect(() => {}); return n }",
+      &options,
+    );
+    assert_eq!(a, b);
+    assert_ne!(a, c);
+
+    let options = RefreshOptions {
+      signature_literal_args: false,
+      ..Default::default()
+    };
+    assert_eq!(
+      signature_of(
+        "function App() { const [n, setN] = useState(0); return n }",
+        &options
+      ),
+      signature_of(
+        "function App() { const [n, setN] = useState(1); return n }",
+        &options
+      )
+    );
+  }
+
   #[test]
   fn fast_refresh() {
     let source = r#"
@@ -1121,7 +1572,7 @@ export default function App() {
     return <h1 >{foo}</h1>;
 };
 _c4 = App;
-_s(App, "useState{[foo, setFoo](0)}\nuseState{bar(() => 0)}\nuseReducer{[state, dispatch](initialState)}\nuseEffect{}");
+_s(App, "useState{[foo, setFoo](0)}\nuseState{bar}\nuseReducer{[state, dispatch]}\nuseEffect{}");
 $RefreshReg$(_c, "Hello");
 $RefreshReg$(_c2, "Bar");
 $RefreshReg$(_c3, "Baz");
@@ -1452,4 +1903,93 @@ export default function() {
 "#;
     assert!(t("/app.jsx", source, expect));
   }
+
+  #[test]
+  fn fast_refresh_stable_output() {
+    let source = r#"
+      import React, { useState } from "https://esm.sh/react"
+      import { useDeno } from "https://deno.land/x/aleph/mod.ts"
+      import a from "./a.module.css"
+      import b from "./b.module.css"
+      function useCounter() {
+        const [count] = useState(0)
+        return count
+      }
+      export const A = () => <p className={a.title}>{useCounter()}</p>
+      export const B = React.memo(() => <p className={b.title}>{useCounter()}</p>)
+      export default function App() {
+        const version = useDeno(() => Deno.version)
+        return <>
+          <style>{`p { color: red; }`}</style>
+          <style>{`h1 { color: ${version}; }`}</style>
+          <A />
+          <B />
+        </>
+      }
+    "#;
+    let options: crate::Options = serde_json::from_str(
+      r#"{
+        "isDev": true,
+        "hmr": true,
+        "refresh": true,
+        "cssModules": { "/a.module.css": ".title {}", "/b.module.css": ".title {}" }
+      }"#,
+    )
+    .unwrap();
+    // the maps of the output are iterated in different orders by the different hashers
+    let transform = || {
+      let output =
+        crate::transform("/pages/index.tsx", source, &options).expect("could not transform module");
+      serde_json::to_string(&output).unwrap()
+    };
+    let output = transform();
+    assert!(output.contains("React.memo(_c2 = ()=>"));
+    for _ in 0..4 {
+      assert_eq!(transform(), output);
+    }
+  }
+
+  #[test]
+  fn refresh_boundaries() {
+    let boundary = |source: &str| {
+      let module = SWC::parse("/components/app.tsx", source, None).expect("could not parse module");
+      refresh_boundary(&module.module)
+    };
+    assert_eq!(
+      boundary(
+        r#"
+        export type Props = { title: string }
+        export const Header = () => <h1 />
+        export default function App() { return <Header /> }
+        "#
+      ),
+      RefreshBoundary::Boundary
+    );
+    assert_eq!(
+      boundary(
+        r#"
+        export const Header = () => <h1 />, title = "App"
+        export default memo(Header)
+        "#
+      ),
+      RefreshBoundary::Mixed
+    );
+    assert_eq!(
+      boundary(
+        r#"
+        export * from "./header.tsx"
+        export { Footer } from "./footer.tsx"
+        "#
+      ),
+      RefreshBoundary::Mixed
+    );
+    assert_eq!(
+      boundary("export const title = 'App'\nexport function useTitle() {}"),
+      RefreshBoundary::None
+    );
+    assert_eq!(
+      boundary("console.log('side effect')"),
+      RefreshBoundary::None
+    );
+  }
 }
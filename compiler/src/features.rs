@@ -0,0 +1,370 @@
+use crate::resolve_fold::is_call_expr_by_name;
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeSet, HashSet};
+use swc_ecma_ast::*;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// The global APIs that may need the polyfills, by the global name.
+const GLOBAL_APIS: &[&str] = &[
+  "AggregateError",
+  "BigInt",
+  "FinalizationRegistry",
+  "WeakRef",
+  "globalThis",
+  "queueMicrotask",
+  "structuredClone",
+];
+
+/// The static methods that may need the polyfills, by the object and the method.
+const STATIC_APIS: &[(&str, &str, &str)] = &[
+  ("Array", "fromAsync", "Array.fromAsync"),
+  ("Object", "fromEntries", "Object.fromEntries"),
+  ("Object", "hasOwn", "Object.hasOwn"),
+  ("Promise", "allSettled", "Promise.allSettled"),
+  ("Promise", "any", "Promise.any"),
+];
+
+/// The prototype methods that may need the polyfills, which are detected by the
+/// calls of the method names on any object, like `list.at(-1)`.
+const PROTOTYPE_APIS: &[(&str, &str)] = &[
+  ("at", "Array.prototype.at"),
+  ("findLast", "Array.prototype.findLast"),
+  ("findLastIndex", "Array.prototype.findLastIndex"),
+  ("flat", "Array.prototype.flat"),
+  ("flatMap", "Array.prototype.flatMap"),
+  ("matchAll", "String.prototype.matchAll"),
+  ("replaceAll", "String.prototype.replaceAll"),
+];
+
+/// The syntax and the API features that a module uses, for deciding whether the
+/// legacy bundle or the polyfills are needed. The names are sorted.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsedFeatures {
+  /// the syntax features newer than ES2015, like `optional-chaining`.
+  pub syntax: Vec<String>,
+  /// the global APIs, like `BigInt` or `Array.prototype.at`. the globals that are
+  /// shadowed by the bindings of the module are not reported.
+  pub apis: Vec<String>,
+}
+
+/// Detects the features that the module uses, the module is not changed.
+pub fn detect_features(module: &Module) -> UsedFeatures {
+  let mut detector = FeatureDetector {
+    syntax: BTreeSet::new(),
+    apis: BTreeSet::new(),
+    bindings: HashSet::new(),
+    fn_depth: 0,
+  };
+  Program::Module(module.clone()).fold_with(&mut detector);
+  let FeatureDetector {
+    syntax,
+    apis,
+    bindings,
+    ..
+  } = detector;
+  UsedFeatures {
+    syntax: syntax.into_iter().map(|name| name.into()).collect(),
+    apis: apis
+      .into_iter()
+      .filter(|api| !bindings.contains(api.split('.').next().unwrap()))
+      .map(|name| name.into())
+      .collect(),
+  }
+}
+
+struct FeatureDetector {
+  syntax: BTreeSet<&'static str>,
+  apis: BTreeSet<&'static str>,
+  /// the names of the bindings that shadow the globals.
+  bindings: HashSet<String>,
+  fn_depth: u32,
+}
+
+impl FeatureDetector {
+  fn add_binding(&mut self, ident: &Ident) {
+    self.bindings.insert(ident.sym.as_ref().into());
+  }
+}
+
+impl Fold for FeatureDetector {
+  noop_fold_type!();
+
+  fn fold_expr(&mut self, expr: Expr) -> Expr {
+    match &expr {
+      Expr::Ident(id) => {
+        if let Some(api) = GLOBAL_APIS.iter().find(|api| id.sym.as_ref().eq(**api)) {
+          self.apis.insert(api);
+        }
+      }
+      Expr::Member(MemberExpr {
+        obj: ExprOrSuper::Expr(obj),
+        prop,
+        computed: false,
+        ..
+      }) => {
+        if let (Expr::Ident(obj), Expr::Ident(prop)) = (obj.as_ref(), prop.as_ref()) {
+          if let Some((_, _, api)) = STATIC_APIS
+            .iter()
+            .find(|(o, p, _)| obj.sym.as_ref().eq(*o) && prop.sym.as_ref().eq(*p))
+          {
+            self.apis.insert(api);
+          }
+        }
+      }
+      Expr::Lit(Lit::BigInt(_)) => {
+        self.syntax.insert("bigint");
+      }
+      Expr::MetaProp(_) => {
+        self.syntax.insert("import-meta");
+      }
+      Expr::OptChain(_) => {
+        self.syntax.insert("optional-chaining");
+      }
+      Expr::Bin(BinExpr { op, .. }) => match op {
+        BinaryOp::NullishCoalescing => {
+          self.syntax.insert("nullish-coalescing");
+        }
+        BinaryOp::Exp => {
+          self.syntax.insert("exponentiation");
+        }
+        _ => {}
+      },
+      Expr::Assign(AssignExpr { op, .. }) => match op {
+        AssignOp::AndAssign | AssignOp::OrAssign | AssignOp::NullishAssign => {
+          self.syntax.insert("logical-assignment");
+        }
+        AssignOp::ExpAssign => {
+          self.syntax.insert("exponentiation");
+        }
+        _ => {}
+      },
+      Expr::Await(_) if self.fn_depth == 0 => {
+        self.syntax.insert("top-level-await");
+      }
+      _ => {}
+    }
+    expr.fold_children_with(self)
+  }
+
+  // match: import("./lazy.tsx") or list.at(-1)
+  fn fold_call_expr(&mut self, call: CallExpr) -> CallExpr {
+    if is_call_expr_by_name(&call, "import") {
+      self.syntax.insert("dynamic-import");
+    } else if let ExprOrSuper::Expr(callee) = &call.callee {
+      // match: list?.at(-1)
+      let callee = match callee.as_ref() {
+        Expr::OptChain(OptChainExpr { expr, .. }) => expr.as_ref(),
+        callee => callee,
+      };
+      if let Expr::Member(MemberExpr {
+        prop,
+        computed: false,
+        ..
+      }) = callee
+      {
+        if let Expr::Ident(prop) = prop.as_ref() {
+          if let Some((_, api)) = PROTOTYPE_APIS
+            .iter()
+            .find(|(method, _)| prop.sym.as_ref().eq(*method))
+          {
+            self.apis.insert(api);
+          }
+        }
+      }
+    }
+    call.fold_children_with(self)
+  }
+
+  fn fold_function(&mut self, function: Function) -> Function {
+    if function.is_async {
+      self.syntax.insert(if function.is_generator {
+        "async-generators"
+      } else {
+        "async-functions"
+      });
+    }
+    self.fn_depth += 1;
+    let function = function.fold_children_with(self);
+    self.fn_depth -= 1;
+    function
+  }
+
+  fn fold_arrow_expr(&mut self, arrow: ArrowExpr) -> ArrowExpr {
+    if arrow.is_async {
+      self.syntax.insert("async-functions");
+    }
+    self.fn_depth += 1;
+    let arrow = arrow.fold_children_with(self);
+    self.fn_depth -= 1;
+    arrow
+  }
+
+  // match: for await (const chunk of stream) {}
+  fn fold_for_of_stmt(&mut self, stmt: ForOfStmt) -> ForOfStmt {
+    if stmt.await_token.is_some() {
+      self.syntax.insert("for-await");
+      if self.fn_depth == 0 {
+        self.syntax.insert("top-level-await");
+      }
+    }
+    stmt.fold_children_with(self)
+  }
+
+  // match: { ...props }
+  fn fold_prop_or_spread(&mut self, prop: PropOrSpread) -> PropOrSpread {
+    if let PropOrSpread::Spread(_) = &prop {
+      self.syntax.insert("object-rest-spread");
+    }
+    prop.fold_children_with(self)
+  }
+
+  // match: const { a, ...rest } = props
+  fn fold_object_pat_prop(&mut self, prop: ObjectPatProp) -> ObjectPatProp {
+    if let ObjectPatProp::Rest(_) = &prop {
+      self.syntax.insert("object-rest-spread");
+    }
+    prop.fold_children_with(self)
+  }
+
+  // match: try {} catch {}
+  fn fold_catch_clause(&mut self, clause: CatchClause) -> CatchClause {
+    if clause.param.is_none() {
+      self.syntax.insert("optional-catch-binding");
+    }
+    clause.fold_children_with(self)
+  }
+
+  fn fold_class_member(&mut self, member: ClassMember) -> ClassMember {
+    match &member {
+      ClassMember::ClassProp(_) => {
+        self.syntax.insert("class-fields");
+      }
+      ClassMember::PrivateProp(_) | ClassMember::PrivateMethod(_) => {
+        self.syntax.insert("private-fields");
+      }
+      _ => {}
+    }
+    member.fold_children_with(self)
+  }
+
+  // match: export * as ns from "./mod.ts"
+  fn fold_export_namespace_specifier(
+    &mut self,
+    specifier: ExportNamespaceSpecifier,
+  ) -> ExportNamespaceSpecifier {
+    self.syntax.insert("export-namespace-from");
+    specifier
+  }
+
+  fn fold_binding_ident(&mut self, ident: BindingIdent) -> BindingIdent {
+    self.add_binding(&ident.id);
+    ident.fold_children_with(self)
+  }
+
+  fn fold_fn_decl(&mut self, decl: FnDecl) -> FnDecl {
+    self.add_binding(&decl.ident);
+    decl.fold_children_with(self)
+  }
+
+  fn fold_class_decl(&mut self, decl: ClassDecl) -> ClassDecl {
+    self.add_binding(&decl.ident);
+    decl.fold_children_with(self)
+  }
+
+  fn fold_import_specifier(&mut self, specifier: ImportSpecifier) -> ImportSpecifier {
+    match &specifier {
+      ImportSpecifier::Named(ImportNamedSpecifier { local, .. })
+      | ImportSpecifier::Default(ImportDefaultSpecifier { local, .. })
+      | ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => self.add_binding(local),
+    }
+    specifier
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::swc::SWC;
+  use crate::{transform, Options};
+
+  #[test]
+  fn used_features() {
+    let source = r#"
+      import { at } from "./utils.ts"
+      import * as ns from "./ns.ts"
+      export * as utils from "./utils.ts"
+      const config = await import("./config.ts")
+      const big = 10n ** 2n
+      class Store {
+        count = 0
+        #items = []
+        static from(entries) {
+          return Object.fromEntries(entries ?? [])
+        }
+      }
+      export async function load({ id, ...rest }: { id: string }) {
+        try {
+          config.cache ||= new Map()
+          return [config?.items.at(-1), { ...rest }, globalThis, Promise.allSettled([])]
+        } catch {
+          return "".replaceAll("a", "b")
+        }
+      }
+      function structuredClone(value: BigIntLike) {
+        return JSON.parse(JSON.stringify(value))
+      }
+      console.log(import.meta.url, ns, at, Store, big, structuredClone(BigInt))
+    "#;
+    let module = SWC::parse("/app.ts", source, None).expect("could not parse module");
+    let features = detect_features(&module.module);
+    assert_eq!(
+      features.syntax,
+      vec![
+        "async-functions",
+        "bigint",
+        "class-fields",
+        "dynamic-import",
+        "exponentiation",
+        "export-namespace-from",
+        "import-meta",
+        "logical-assignment",
+        "nullish-coalescing",
+        "object-rest-spread",
+        "optional-catch-binding",
+        "optional-chaining",
+        "private-fields",
+        "top-level-await",
+      ]
+    );
+    assert_eq!(
+      features.apis,
+      vec![
+        "Array.prototype.at",
+        "BigInt",
+        "Object.fromEntries",
+        "Promise.allSettled",
+        "String.prototype.replaceAll",
+        "globalThis",
+      ]
+    );
+
+    let module = SWC::parse("/app.js", "export const a = [1].map(n => n + 1)", None)
+      .expect("could not parse module");
+    assert_eq!(detect_features(&module.module), UsedFeatures::default());
+
+    let code = "export const last = (list?: number[]) => list?.at(-1) ?? 0";
+    let options: Options = serde_json::from_str(r#"{"reportFeatures":true}"#).unwrap();
+    let output = transform("/last.ts", code, &options).expect("could not transform module");
+    let features = output.features.expect("missing features");
+    assert_eq!(
+      features.syntax,
+      vec!["nullish-coalescing", "optional-chaining"]
+    );
+    assert_eq!(features.apis, vec!["Array.prototype.at"]);
+    let options: Options = serde_json::from_str("{}").unwrap();
+    let output = transform("/last.ts", code, &options).expect("could not transform module");
+    assert!(output.features.is_none());
+  }
+}
@@ -0,0 +1,560 @@
+//! The C ABI of the compiler for the native backends, e.g. Deno FFI, which is
+//! free of the memory limits of wasm. The functions take the NUL-terminated
+//! UTF-8 strings with the JSON options (the same as the wasm exports) and return
+//! a JSON string of `{ "ok": output }` or `{ "error": { "diagnostics": [...] } }`
+//! that must be freed with `aleph_free_string`. The panics of the compiler are
+//! caught and returned as the errors, as they must not unwind into the caller.
+
+use crate::error::{DiagnosticBuffer, DiagnosticCode};
+use crate::import_map::{validate_import_map, ImportHashMap};
+use crate::memory::InitOptions;
+use crate::{
+  build_modules, bundle_modules, convert_cjs, emit_module_handle, free_module_handle, init_memory,
+  lookup_generated_position, lookup_original_position, parse, parse_ast, parse_export_names,
+  parse_module_handle, reset_memory, resolve_specifier, transform, transform_both,
+  transform_html_entry, transform_modules, Options, SWCOptions, TransformInput,
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+  any::Any,
+  collections::HashMap,
+  ffi::{CStr, CString},
+  os::raw::c_char,
+  panic::{catch_unwind, AssertUnwindSafe},
+};
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+enum FfiResult<T> {
+  Ok(T),
+  Error(DiagnosticBuffer),
+}
+
+/// reads a NUL-terminated UTF-8 string, the `name` is used in the error message.
+unsafe fn read_str<'a>(ptr: *const c_char, name: &str) -> Result<&'a str, DiagnosticBuffer> {
+  if ptr.is_null() {
+    return Err(DiagnosticBuffer::from_message(
+      "",
+      DiagnosticCode::InvalidOptions,
+      format!("missing {}", name),
+    ));
+  }
+  CStr::from_ptr(ptr).to_str().map_err(|err| {
+    DiagnosticBuffer::from_message(
+      "",
+      DiagnosticCode::InvalidOptions,
+      format!("invalid {}: {}", name, err),
+    )
+  })
+}
+
+/// reads a NUL-terminated JSON string, the null pointer is read as `{}`.
+unsafe fn read_json<T: DeserializeOwned>(
+  ptr: *const c_char,
+  name: &str,
+) -> Result<T, DiagnosticBuffer> {
+  let json = if ptr.is_null() {
+    "{}"
+  } else {
+    read_str(ptr, name)?
+  };
+  serde_json::from_str(json).map_err(|err| {
+    DiagnosticBuffer::from_message(
+      "",
+      DiagnosticCode::InvalidOptions,
+      format!("failed to parse {}: {}", name, err),
+    )
+  })
+}
+
+/// calls the `f` and serializes the result, a panic is returned as the error.
+fn into_c_string<T, F>(f: F) -> *mut c_char
+where
+  T: Serialize,
+  F: FnOnce() -> Result<T, DiagnosticBuffer>,
+{
+  let result = match catch_unwind(AssertUnwindSafe(f)) {
+    Ok(Ok(output)) => FfiResult::Ok(output),
+    Ok(Err(buffer)) => FfiResult::Error(buffer),
+    Err(payload) => FfiResult::Error(DiagnosticBuffer::from_message(
+      "",
+      DiagnosticCode::TransformError,
+      format!("the compiler panicked: {}", panic_message(&*payload)),
+    )),
+  };
+  // the JSON string never contains the NUL byte, which is escaped.
+  CString::new(serde_json::to_string(&result).unwrap())
+    .unwrap()
+    .into_raw()
+}
+
+/// the message of the `panic!`, which is a `&str` or a `String`.
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+  if let Some(message) = payload.downcast_ref::<&str>() {
+    message
+  } else if let Some(message) = payload.downcast_ref::<String>() {
+    message.as_str()
+  } else {
+    "unknown error"
+  }
+}
+
+/// Transforms the module, like `transformSync`.
+///
+/// # Safety
+///
+/// The arguments must be valid NUL-terminated strings, the `options` can be null.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_transform(
+  url: *const c_char,
+  code: *const c_char,
+  options: *const c_char,
+) -> *mut c_char {
+  into_c_string(|| {
+    let options: Options = read_json(options, "options")?;
+    transform(read_str(url, "url")?, read_str(code, "code")?, &options)
+  })
+}
+
+/// Transforms the module into the dev and prod variants, like `transformBothSync`.
+///
+/// # Safety
+///
+/// The arguments must be valid NUL-terminated strings, the `options` can be null.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_transform_both(
+  url: *const c_char,
+  code: *const c_char,
+  options: *const c_char,
+) -> *mut c_char {
+  into_c_string(|| {
+    let options: Options = read_json(options, "options")?;
+    transform_both(read_str(url, "url")?, read_str(code, "code")?, &options)
+  })
+}
+
+/// Transforms many modules in one call, like `transformAll`, the `sources` is a
+/// JSON array of `{ filename, code }`.
+///
+/// # Safety
+///
+/// The arguments must be valid NUL-terminated strings, the `options` can be null.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_transform_all(
+  sources: *const c_char,
+  options: *const c_char,
+) -> *mut c_char {
+  into_c_string(|| {
+    let sources: Vec<TransformInput> = read_json(sources, "sources")?;
+    let options: Options = read_json(options, "options")?;
+    transform_modules(&sources, &options)
+  })
+}
+
+/// Transforms many modules with the manifest of the emitted files, like `buildAll`.
+///
+/// # Safety
+///
+/// The arguments must be valid NUL-terminated strings, the `options` can be null.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_build_all(
+  sources: *const c_char,
+  options: *const c_char,
+) -> *mut c_char {
+  into_c_string(|| {
+    let sources: Vec<TransformInput> = read_json(sources, "sources")?;
+    let options: Options = read_json(options, "options")?;
+    build_modules(&sources, &options)
+  })
+}
+
+/// Parses the metadata of the module, like `parseSync`.
+///
+/// # Safety
+///
+/// The arguments must be valid NUL-terminated strings, the `options` can be null.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_parse(
+  url: *const c_char,
+  code: *const c_char,
+  options: *const c_char,
+) -> *mut c_char {
+  into_c_string(|| {
+    let options: SWCOptions = read_json(options, "options")?;
+    parse(read_str(url, "url")?, read_str(code, "code")?, &options)
+  })
+}
+
+/// Parses the export names of the module, like `parseExportNamesSync`.
+///
+/// # Safety
+///
+/// The arguments must be valid NUL-terminated strings, the `options` can be null.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_parse_export_names(
+  url: *const c_char,
+  code: *const c_char,
+  options: *const c_char,
+) -> *mut c_char {
+  into_c_string(|| {
+    let options: SWCOptions = read_json(options, "options")?;
+    parse_export_names(read_str(url, "url")?, read_str(code, "code")?, &options)
+  })
+}
+
+/// Returns the AST of the module as JSON, like `astSync`.
+///
+/// # Safety
+///
+/// The arguments must be valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_ast(filename: *const c_char, code: *const c_char) -> *mut c_char {
+  into_c_string(|| parse_ast(read_str(filename, "filename")?, read_str(code, "code")?))
+}
+
+/// Resolves the `specifier` imported by the `referrer`, like `resolveSync`.
+///
+/// # Safety
+///
+/// The arguments must be valid NUL-terminated strings, the `options` can be null.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_resolve(
+  specifier: *const c_char,
+  referrer: *const c_char,
+  options: *const c_char,
+) -> *mut c_char {
+  into_c_string(|| {
+    let options: Options = read_json(options, "options")?;
+    resolve_specifier(
+      read_str(specifier, "specifier")?,
+      read_str(referrer, "referrer")?,
+      &options,
+    )
+  })
+}
+
+/// Validates the import map, like `validateImportMapSync`.
+///
+/// # Safety
+///
+/// The `import_map` must be a valid NUL-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_validate_import_map(import_map: *const c_char) -> *mut c_char {
+  into_c_string(|| {
+    let import_map: ImportHashMap = read_json(import_map, "import map")?;
+    Ok(validate_import_map(&import_map))
+  })
+}
+
+/// Bundles the module graph of the `entry`, like `bundleSync`, the `modules` is a
+/// JSON object of the sources keyed by the fixed url.
+///
+/// # Safety
+///
+/// The arguments must be valid NUL-terminated strings, the `options` can be null.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_bundle(
+  entry: *const c_char,
+  modules: *const c_char,
+  options: *const c_char,
+) -> *mut c_char {
+  into_c_string(|| {
+    let modules: HashMap<String, String> = read_json(modules, "modules")?;
+    let options: Options = read_json(options, "options")?;
+    bundle_modules(read_str(entry, "entry")?, &modules, &options)
+  })
+}
+
+/// Transforms an HTML entry, like `transformHtmlSync`.
+///
+/// # Safety
+///
+/// The arguments must be valid NUL-terminated strings, the `options` can be null.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_transform_html(
+  url: *const c_char,
+  code: *const c_char,
+  options: *const c_char,
+) -> *mut c_char {
+  into_c_string(|| {
+    let options: Options = read_json(options, "options")?;
+    transform_html_entry(read_str(url, "url")?, read_str(code, "code")?, &options)
+  })
+}
+
+/// Converts a CommonJS module into an ES module facade, like `convertCJS`.
+///
+/// # Safety
+///
+/// The arguments must be valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_convert_cjs(url: *const c_char, code: *const c_char) -> *mut c_char {
+  into_c_string(|| convert_cjs(read_str(url, "url")?, read_str(code, "code")?))
+}
+
+/// Looks up the original position in the source map, like `lookupOriginalPosition`.
+///
+/// # Safety
+///
+/// The `map` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_lookup_original_position(
+  map: *const c_char,
+  line: u32,
+  column: u32,
+) -> *mut c_char {
+  into_c_string(|| lookup_original_position(read_str(map, "source map")?, line, column))
+}
+
+/// Looks up the generated position in the source map, like `lookupGeneratedPosition`.
+///
+/// # Safety
+///
+/// The `map` and the `source` must be valid NUL-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_lookup_generated_position(
+  map: *const c_char,
+  source: *const c_char,
+  line: u32,
+  column: u32,
+) -> *mut c_char {
+  into_c_string(|| {
+    lookup_generated_position(
+      read_str(map, "source map")?,
+      read_str(source, "source")?,
+      line,
+      column,
+    )
+  })
+}
+
+/// Parses the module into a handle, like `parseModuleSync`. The handle is only valid
+/// on the thread that parsed the module.
+///
+/// # Safety
+///
+/// The arguments must be valid NUL-terminated strings, the `options` can be null.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_parse_module(
+  url: *const c_char,
+  code: *const c_char,
+  options: *const c_char,
+) -> *mut c_char {
+  into_c_string(|| {
+    let options: Options = read_json(options, "options")?;
+    parse_module_handle(read_str(url, "url")?, read_str(code, "code")?, &options)
+  })
+}
+
+/// Emits the parsed module of the handle, like `emitModuleSync`.
+///
+/// # Safety
+///
+/// The `options` must be a valid NUL-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_emit_module(handle: u32, options: *const c_char) -> *mut c_char {
+  into_c_string(|| {
+    let options: Options = read_json(options, "options")?;
+    emit_module_handle(handle, &options)
+  })
+}
+
+/// Frees the parsed module of the handle, like `freeModuleSync`.
+#[no_mangle]
+pub extern "C" fn aleph_free_module(handle: u32) -> *mut c_char {
+  into_c_string(|| Ok(free_module_handle(handle)))
+}
+
+/// Tunes the memory of the compiler, like `initMemorySync`.
+///
+/// # Safety
+///
+/// The `options` must be a valid NUL-terminated string or null.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_init_memory(options: *const c_char) -> *mut c_char {
+  into_c_string(|| {
+    let options: InitOptions = read_json(options, "options")?;
+    Ok(init_memory(&options))
+  })
+}
+
+/// Drops the global caches of the compiler, like `resetMemorySync`.
+#[no_mangle]
+pub extern "C" fn aleph_reset_memory() -> *mut c_char {
+  into_c_string(|| Ok(reset_memory()))
+}
+
+/// Frees a string that is returned by the functions above.
+///
+/// # Safety
+///
+/// The `ptr` must be returned by this library and not be freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn aleph_free_string(ptr: *mut c_char) {
+  if !ptr.is_null() {
+    drop(CString::from_raw(ptr));
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn call<F>(f: F) -> serde_json::Value
+  where
+    F: FnOnce() -> *mut c_char,
+  {
+    let ptr = f();
+    let output = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_owned();
+    unsafe { aleph_free_string(ptr) };
+    serde_json::from_str(output.as_str()).unwrap()
+  }
+
+  #[test]
+  fn ffi_transform() {
+    let url = CString::new("/pages/index.tsx").unwrap();
+    let code = CString::new("export default function Index() { return <h1>Hi</h1> }").unwrap();
+    let options = CString::new(r#"{"swcOptions":{"target":"es2015"}}"#).unwrap();
+    let output = call(|| unsafe { aleph_transform(url.as_ptr(), code.as_ptr(), options.as_ptr()) });
+    assert!(output["ok"]["code"]
+      .as_str()
+      .unwrap()
+      .contains("React.createElement(\"h1\", null, \"Hi\")"));
+
+    let output = call(|| unsafe { aleph_transform(url.as_ptr(), code.as_ptr(), std::ptr::null()) });
+    assert!(output["ok"]["hash"].is_string());
+
+    let code = CString::new("export default function Index() {").unwrap();
+    let output = call(|| unsafe { aleph_transform(url.as_ptr(), code.as_ptr(), std::ptr::null()) });
+    assert!(output["error"]["diagnostics"][0]["message"].is_string());
+
+    let options = CString::new(r#"{"unknownOption":true}"#).unwrap();
+    let output = call(|| unsafe { aleph_transform(url.as_ptr(), code.as_ptr(), options.as_ptr()) });
+    assert!(output["error"]["diagnostics"][0]["message"]
+      .as_str()
+      .unwrap()
+      .starts_with("failed to parse options"));
+  }
+
+  #[test]
+  fn ffi_catch_panic() {
+    let output = call(|| into_c_string::<(), _>(|| panic!("unexpected {}", "node")));
+    assert_eq!(
+      output["error"]["diagnostics"][0]["message"],
+      "the compiler panicked: unexpected node"
+    );
+    let output = call(|| into_c_string::<(), _>(|| panic!("unexpected node")));
+    assert_eq!(
+      output["error"]["diagnostics"][0]["message"],
+      "the compiler panicked: unexpected node"
+    );
+    assert_eq!(output["error"]["diagnostics"][0]["code"], "transform-error");
+  }
+
+  #[test]
+  fn ffi_transform_all() {
+    let sources = (0..16)
+      .map(|i| {
+        format!(
+          r#"{{"filename":"/pages/page{}.tsx","code":"import React from 'react'\nexport default () => <p>{}</p>"}}"#,
+          i, i
+        )
+      })
+      .collect::<Vec<String>>();
+    let sources = CString::new(format!("[{}]", sources.join(","))).unwrap();
+    let options =
+      CString::new(r#"{"importMap":{"imports":{"react":"https://esm.sh/react"}}}"#).unwrap();
+    let output = call(|| unsafe { aleph_transform_all(sources.as_ptr(), options.as_ptr()) });
+    let outputs = output["ok"].as_array().unwrap();
+    assert_eq!(outputs.len(), 16);
+    for (i, output) in outputs.iter().enumerate() {
+      let code = output["code"].as_str().unwrap();
+      assert!(code.contains(format!("React.createElement(\"p\", null, \"{}\")", i).as_str()));
+      assert_eq!(output["deps"][0]["specifier"], "https://esm.sh/react");
+    }
+
+    let sources = CString::new(
+      r#"[{"filename":"/a.ts","code":"import './b.ts'"},{"filename":"/b.ts","code":"import './a.ts'; import('./c.ts')"},{"filename":"/c.ts","code":"import './b.ts'"}]"#,
+    )
+    .unwrap();
+    let output = call(|| unsafe { aleph_transform_all(sources.as_ptr(), std::ptr::null()) });
+    let outputs = output["ok"].as_array().unwrap();
+    assert_eq!(outputs[0]["warnings"][0]["code"], "import-cycle");
+    assert_eq!(
+      outputs[0]["warnings"][0]["message"],
+      "import cycle: /a.ts -> /b.ts -> /a.ts"
+    );
+    assert_eq!(
+      outputs[1]["warnings"][0]["cycle"],
+      serde_json::json!(["/b.ts", "/a.ts", "/b.ts"])
+    );
+    assert!(outputs[2]["warnings"].is_null());
+
+    let sources = CString::new(
+      r#"[{"filename":"/a.ts","code":"export {}"},{"filename":"/b.ts","code":"export {"}]"#,
+    )
+    .unwrap();
+    let output = call(|| unsafe { aleph_transform_all(sources.as_ptr(), std::ptr::null()) });
+    assert!(output["error"]["diagnostics"][0]["message"].is_string());
+  }
+
+  #[test]
+  fn ffi_resolve() {
+    let specifier = CString::new("../components/logo.tsx").unwrap();
+    let referrer = CString::new("/pages/index.tsx").unwrap();
+    let output =
+      call(|| unsafe { aleph_resolve(specifier.as_ptr(), referrer.as_ptr(), std::ptr::null()) });
+    assert_eq!(output["ok"]["url"], "/components/logo.tsx");
+  }
+
+  #[test]
+  fn ffi_validate_import_map() {
+    let import_map = CString::new(r#"{"imports":{"react":"preact/compat"}}"#).unwrap();
+    let output = call(|| unsafe { aleph_validate_import_map(import_map.as_ptr()) });
+    assert_eq!(output["ok"]["errors"][0]["key"], "react");
+    assert!(output["ok"]["warnings"].as_array().unwrap().is_empty());
+  }
+
+  #[test]
+  fn ffi_module_handles() {
+    let url = CString::new("/app.tsx").unwrap();
+    let code = CString::new("export default () => <p>{import.meta.env.SSR}</p>").unwrap();
+    let output =
+      call(|| unsafe { aleph_parse_module(url.as_ptr(), code.as_ptr(), std::ptr::null()) });
+    let handle = output["ok"].as_u64().unwrap() as u32;
+    let options = CString::new(r#"{"swcOptions":{"targetEnv":"deno"}}"#).unwrap();
+    let output = call(|| unsafe { aleph_emit_module(handle, options.as_ptr()) });
+    assert!(output["ok"]["code"].as_str().unwrap().contains("true"));
+    let options = CString::new(r#"{"swcOptions":{"targetEnv":"browser"}}"#).unwrap();
+    let output = call(|| unsafe { aleph_emit_module(handle, options.as_ptr()) });
+    assert!(output["ok"]["code"].as_str().unwrap().contains("false"));
+    assert_eq!(call(|| aleph_free_module(handle))["ok"], true);
+    assert_eq!(call(|| aleph_free_module(handle))["ok"], false);
+    let output = call(|| unsafe { aleph_emit_module(handle, std::ptr::null()) });
+    assert_eq!(
+      output["error"]["diagnostics"][0]["message"],
+      format!("invalid module handle: {}", handle)
+    );
+  }
+
+  #[test]
+  fn ffi_memory() {
+    let options = CString::new(r#"{"initialMemory":1048576}"#).unwrap();
+    let output = call(|| unsafe { aleph_init_memory(options.as_ptr()) });
+    // the memory size of the native backend is unknown
+    assert!(output["ok"]["memoryBytes"].is_null());
+    assert!(output["ok"]["cachedSourceMaps"].is_number());
+    let output = call(|| aleph_reset_memory());
+    assert!(output["ok"]["cachedSourceMaps"].is_number());
+
+    assert!(output["ok"]["parsedModules"].is_number());
+
+    let options = CString::new(r#"{"sourceMapCache":1}"#).unwrap();
+    let output = call(|| unsafe { aleph_init_memory(options.as_ptr()) });
+    assert!(output["error"]["diagnostics"][0]["message"]
+      .as_str()
+      .unwrap()
+      .contains("unknown field `sourceMapCache`"));
+  }
+}
@@ -0,0 +1,166 @@
+use crate::ast_utils::new_str;
+use crate::fast_refresh::is_refresh_boundary;
+use crate::resolve::Resolver;
+
+use std::{cell::RefCell, rc::Rc};
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::quote_ident;
+use swc_ecma_visit::{noop_fold_type, Fold};
+
+/// Creates a fold that wraps the module with the HMR runtime of aleph in development,
+/// the modules that only export components (the refresh boundaries) accept their
+/// own updates if fast refresh is enabled for the module:
+///
+/// ```js
+/// import { createHotContext } from "https://deno.land/x/aleph/framework/core/hmr.ts";
+/// import.meta.hot = createHotContext("/pages/index.tsx");
+/// // ...
+/// import.meta.hot.accept();
+/// ```
+pub fn hmr_fold(resolver: Rc<RefCell<Resolver>>, fast_refresh: bool) -> impl Fold {
+  HmrFold {
+    resolver,
+    fast_refresh,
+  }
+}
+
+struct HmrFold {
+  resolver: Rc<RefCell<Resolver>>,
+  fast_refresh: bool,
+}
+
+impl Fold for HmrFold {
+  noop_fold_type!();
+
+  fn fold_module(&mut self, mut module: Module) -> Module {
+    let resolver = self.resolver.borrow();
+    let accept = self.fast_refresh && is_refresh_boundary(&module);
+
+    // import { createHotContext } from "https://deno.land/x/aleph/framework/core/hmr.ts"
+    let import = ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+      span: DUMMY_SP,
+      specifiers: vec![ImportSpecifier::Named(ImportNamedSpecifier {
+        span: DUMMY_SP,
+        local: quote_ident!("createHotContext"),
+        imported: None,
+      })],
+      src: new_str(&format!(
+        "{}/framework/core/hmr.ts",
+        resolver.get_aleph_pkg_uri()
+      )),
+      type_only: false,
+      asserts: None,
+    }));
+    // import.meta.hot = createHotContext("/pages/index.tsx")
+    let create_context = ModuleItem::Stmt(Stmt::Expr(ExprStmt {
+      span: DUMMY_SP,
+      expr: Box::new(Expr::Assign(AssignExpr {
+        span: DUMMY_SP,
+        op: AssignOp::Assign,
+        left: PatOrExpr::Expr(Box::new(new_import_meta_hot())),
+        right: Box::new(Expr::Call(CallExpr {
+          span: DUMMY_SP,
+          callee: ExprOrSuper::Expr(Box::new(Expr::Ident(quote_ident!("createHotContext")))),
+          args: vec![ExprOrSpread {
+            spread: None,
+            expr: Box::new(Expr::Lit(Lit::Str(new_str(resolver.specifier.as_str())))),
+          }],
+          type_args: None,
+        })),
+      })),
+    }));
+    module.body.insert(0, import);
+    module.body.insert(1, create_context);
+
+    if accept {
+      // import.meta.hot.accept()
+      module.body.push(ModuleItem::Stmt(Stmt::Expr(ExprStmt {
+        span: DUMMY_SP,
+        expr: Box::new(Expr::Call(CallExpr {
+          span: DUMMY_SP,
+          callee: ExprOrSuper::Expr(Box::new(Expr::Member(MemberExpr {
+            span: DUMMY_SP,
+            obj: ExprOrSuper::Expr(Box::new(new_import_meta_hot())),
+            prop: Box::new(Expr::Ident(quote_ident!("accept"))),
+            computed: false,
+          }))),
+          args: vec![],
+          type_args: None,
+        })),
+      })));
+    }
+    module
+  }
+}
+
+fn new_import_meta_hot() -> Expr {
+  Expr::Member(MemberExpr {
+    span: DUMMY_SP,
+    obj: ExprOrSuper::Expr(Box::new(Expr::MetaProp(MetaPropExpr {
+      meta: quote_ident!("import"),
+      prop: quote_ident!("meta"),
+    }))),
+    prop: Box::new(Expr::Ident(quote_ident!("hot"))),
+    computed: false,
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::import_map::ImportHashMap;
+  use crate::resolve::Resolver;
+  use crate::swc::{EmitOptions, SWC};
+  use std::{cell::RefCell, rc::Rc};
+
+  #[test]
+  fn hmr_wrapper() {
+    let transform = |specifier: &str, source: &str| {
+      let module = SWC::parse(specifier, source, None).expect("could not parse module");
+      let resolver = Rc::new(RefCell::new(Resolver::new(
+        specifier,
+        ImportHashMap::default(),
+        Some("https://deno.land/x/aleph@v0.3.0".into()),
+        None,
+        false,
+        vec![],
+      )));
+      let (code, _) = module
+        .transform(
+          resolver,
+          &EmitOptions {
+            is_dev: true,
+            hmr: true,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      code
+    };
+
+    let code = transform(
+      "/pages/index.tsx",
+      r#"
+      import React from "https://esm.sh/react"
+      export type Props = {}
+      export default function Index() {
+        return <h1>Hi</h1>
+      }
+    "#,
+    );
+    assert!(code.contains(
+      "import { createHotContext } from \"../-/deno.land/x/aleph@v0.3.0/framework/core/hmr.js\";\nimport.meta.hot = createHotContext(\"/pages/index.tsx\");"
+    ));
+    assert!(code.trim_end().ends_with("import.meta.hot.accept();"));
+
+    let code = transform(
+      "/lib/util.ts",
+      r#"
+      export const sum = (a: number, b: number) => a + b
+    "#,
+    );
+    assert!(code.contains("import.meta.hot = createHotContext(\"/lib/util.ts\");"));
+    assert!(!code.contains("import.meta.hot.accept()"));
+  }
+}
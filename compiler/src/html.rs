@@ -0,0 +1,167 @@
+use regex::{Captures, Match, Regex};
+
+lazy_static! {
+  static ref RE_SCRIPT: Regex = Regex::new(r"(?is)<script\b([^>]*)>(.*?)</script\s*>").unwrap();
+  static ref RE_ATTR: Regex =
+    Regex::new(r#"([^\s"'=/>]+)(?:\s*=\s*(?:"([^"]*)"|'([^']*)'|([^\s"'=<>`]+)))?"#).unwrap();
+  static ref RE_COMMENT: Regex = Regex::new(r"(?s)<!--.*?-->").unwrap();
+}
+
+/// A module script of the HTML.
+#[derive(Debug, PartialEq)]
+pub enum ModuleScript<'a> {
+  /// the `src` of `<script type="module" src="...">`, the entities are decoded.
+  Src(&'a str),
+  /// the code of `<script type="module">...</script>`.
+  Inline(&'a str),
+}
+
+/// Rewrites the module scripts (`<script type="module">`) of an HTML entry like
+/// `index.html`, the `rewrite` returns the new `src` of the external scripts or the
+/// new code of the inline scripts. The classic scripts, the JSON scripts like
+/// `<script type="importmap">` and the scripts in the comments are kept as is.
+pub fn transform_html<F, E>(html: &str, mut rewrite: F) -> Result<String, E>
+where
+  F: FnMut(ModuleScript) -> Result<String, E>,
+{
+  let comments: Vec<(usize, usize)> = RE_COMMENT
+    .find_iter(html)
+    .map(|m| (m.start(), m.end()))
+    .collect();
+  let mut output = String::with_capacity(html.len());
+  let mut last = 0;
+  for caps in RE_SCRIPT.captures_iter(html) {
+    let tag = caps.get(0).unwrap();
+    if comments
+      .iter()
+      .any(|(start, end)| tag.start() >= *start && tag.start() < *end)
+    {
+      continue;
+    }
+    let attrs = caps.get(1).unwrap();
+    let attr_caps: Vec<Captures> = RE_ATTR.captures_iter(attrs.as_str()).collect();
+    let is_module = attr_caps.iter().any(|attr| {
+      attr[1].eq_ignore_ascii_case("type")
+        && matches!(attr_value(attr), Some(value) if value.as_str().trim().eq_ignore_ascii_case("module"))
+    });
+    if !is_module {
+      continue;
+    }
+    match attr_caps
+      .iter()
+      .find(|attr| attr[1].eq_ignore_ascii_case("src"))
+    {
+      Some(attr) => {
+        // match: <script type="module" src>
+        let value = match attr_value(attr) {
+          Some(value) => value,
+          None => continue,
+        };
+        let start = attrs.start() + value.start();
+        let src = decode_entities(value.as_str());
+        let new_src = encode_entities(rewrite(ModuleScript::Src(src.as_str()))?.as_str());
+        output.push_str(&html[last..start]);
+        // the unquoted value is quoted since the new one may have the special chars
+        if attr.get(4).is_some() {
+          output.push('"');
+          output.push_str(new_src.as_str());
+          output.push('"');
+        } else {
+          output.push_str(new_src.as_str());
+        }
+        last = attrs.start() + value.end();
+      }
+      None => {
+        let code = caps.get(2).unwrap();
+        if code.as_str().trim().is_empty() {
+          continue;
+        }
+        let new_code = rewrite(ModuleScript::Inline(code.as_str()))?;
+        output.push_str(&html[last..code.start()]);
+        output.push('\n');
+        // the script would be closed by the `</script` in the strings of the code
+        output.push_str(
+          new_code
+            .trim_end()
+            .replace("</script", "<\\/script")
+            .as_str(),
+        );
+        output.push('\n');
+        last = code.end();
+      }
+    }
+  }
+  output.push_str(&html[last..]);
+  Ok(output)
+}
+
+fn attr_value<'a>(attr: &Captures<'a>) -> Option<Match<'a>> {
+  attr.get(2).or_else(|| attr.get(3)).or_else(|| attr.get(4))
+}
+
+fn decode_entities(value: &str) -> String {
+  value
+    .replace("&quot;", "\"")
+    .replace("&#39;", "'")
+    .replace("&lt;", "<")
+    .replace("&gt;", ">")
+    .replace("&amp;", "&")
+}
+
+fn encode_entities(value: &str) -> String {
+  value
+    .replace('&', "&amp;")
+    .replace('"', "&quot;")
+    .replace('\'', "&#39;")
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn rewrite_module_scripts() {
+    let html = r#"<!DOCTYPE html>
+<html>
+  <head>
+    <script type="importmap">{ "imports": {} }</script>
+    <script src="/legacy.js"></script>
+    <!-- <script type="module" src="./commented.ts"></script> -->
+    <script type="module" src="./main.ts?a=1&amp;b=2"></script>
+    <script type=module src=/app.tsx async></script>
+  </head>
+  <body>
+    <script type="module">
+      import { render } from "./render.ts"
+      render("</p>")
+    </script>
+  </body>
+</html>
+"#;
+    let mut scripts = vec![];
+    let output = transform_html(html, |script| -> Result<String, ()> {
+      let output = match &script {
+        ModuleScript::Src(src) => format!("{}.js", src),
+        ModuleScript::Inline(_) => {
+          "import { render } from \"./render.js\";\nrender(\"</script>\");".into()
+        }
+      };
+      scripts.push(format!("{:?}", script));
+      Ok(output)
+    })
+    .unwrap();
+    println!("{}", output);
+    assert_eq!(scripts.len(), 3);
+    assert_eq!(scripts[0], "Src(\"./main.ts?a=1&b=2\")");
+    assert_eq!(scripts[1], "Src(\"/app.tsx\")");
+    assert!(scripts[2].starts_with("Inline("));
+    assert!(output.contains(r#"<script type="importmap">{ "imports": {} }</script>"#));
+    assert!(output.contains(r#"<script src="/legacy.js"></script>"#));
+    assert!(output.contains(r#"<!-- <script type="module" src="./commented.ts"></script> -->"#));
+    assert!(output.contains(r#"<script type="module" src="./main.ts?a=1&amp;b=2.js"></script>"#));
+    assert!(output.contains(r#"<script type=module src="/app.tsx.js" async></script>"#));
+    assert!(output.contains(
+      "<script type=\"module\">\nimport { render } from \"./render.js\";\nrender(\"<\\/script>\");\n</script>"
+    ));
+  }
+}
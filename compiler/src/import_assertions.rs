@@ -0,0 +1,312 @@
+use crate::resolve::Resolver;
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::quote_ident;
+use swc_ecma_visit::{noop_fold_type, Fold};
+
+/// Creates a fold that inlines the JSON modules imported with the `json` assertion,
+/// the `json_modules` are the prefetched contents keyed by the fixed url:
+///
+/// ```js
+/// import config from "./config.json" assert { type: "json" }
+/// ```
+///
+/// is transformed to:
+///
+/// ```js
+/// const config = JSON.parse("{\"name\":\"app\"}");
+/// ```
+///
+/// The imports of the JSON modules that are not prefetched keep the assertions.
+pub fn json_module_fold(
+  resolver: Rc<RefCell<Resolver>>,
+  json_modules: HashMap<String, String>,
+) -> impl Fold {
+  JsonModuleFold {
+    resolver,
+    json_modules,
+  }
+}
+
+struct JsonModuleFold {
+  resolver: Rc<RefCell<Resolver>>,
+  json_modules: HashMap<String, String>,
+}
+
+impl JsonModuleFold {
+  /// returns the minified JSON of the import that asserts a JSON module.
+  fn get_json(&self, decl: &ImportDecl) -> Option<String> {
+    if get_assertion_type(&decl.asserts)? != "json" {
+      return None;
+    }
+    let fixed_url = self.resolver.borrow().fix_url(decl.src.value.as_ref());
+    let json = self.json_modules.get(&fixed_url)?;
+    let value: serde_json::Value = serde_json::from_str(json).ok()?;
+    Some(value.to_string())
+  }
+}
+
+impl Fold for JsonModuleFold {
+  noop_fold_type!();
+
+  fn fold_module_items(&mut self, items: Vec<ModuleItem>) -> Vec<ModuleItem> {
+    let mut output = Vec::with_capacity(items.len());
+    for item in items {
+      if let ModuleItem::ModuleDecl(ModuleDecl::Import(decl)) = &item {
+        if let Some(json) = self.get_json(decl) {
+          if let Some(decls) = new_default_var_decls(&decl.specifiers, new_json_parse(&json)) {
+            if !decls.is_empty() {
+              output.push(ModuleItem::Stmt(Stmt::Decl(Decl::Var(VarDecl {
+                span: DUMMY_SP,
+                kind: VarDeclKind::Const,
+                declare: false,
+                decls,
+              }))));
+            }
+            continue;
+          }
+        }
+      }
+      output.push(item);
+    }
+    output
+  }
+}
+
+/// creates the declarations of the import specifiers with the `value` of the default
+/// export, returns `None` if the specifiers import the names other than `default`.
+pub fn new_default_var_decls(
+  specifiers: &[ImportSpecifier],
+  value: Expr,
+) -> Option<Vec<VarDeclarator>> {
+  let mut decls = vec![];
+  for specifier in specifiers {
+    let (local, init) = match specifier {
+      // match: import config from "./config.json"
+      ImportSpecifier::Default(ImportDefaultSpecifier { local, .. }) => {
+        (local.clone(), value.clone())
+      }
+      // match: import { default as config } from "./config.json"
+      ImportSpecifier::Named(ImportNamedSpecifier {
+        local,
+        imported: Some(imported),
+        ..
+      }) if imported.sym.as_ref().eq("default") => (local.clone(), value.clone()),
+      // match: import * as config from "./config.json"
+      ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => (
+        local.clone(),
+        Expr::Object(ObjectLit {
+          span: DUMMY_SP,
+          props: vec![PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+            key: PropName::Ident(quote_ident!("default")),
+            value: Box::new(value.clone()),
+          })))],
+        }),
+      ),
+      _ => return None,
+    };
+    decls.push(VarDeclarator {
+      span: DUMMY_SP,
+      name: Pat::Ident(BindingIdent {
+        id: local,
+        type_ann: None,
+      }),
+      init: Some(Box::new(init)),
+      definite: false,
+    });
+  }
+  Some(decls)
+}
+
+// match: JSON.parse("{}")
+pub fn new_json_parse(json: &str) -> Expr {
+  Expr::Call(CallExpr {
+    span: DUMMY_SP,
+    callee: ExprOrSuper::Expr(Box::new(Expr::Member(MemberExpr {
+      span: DUMMY_SP,
+      obj: ExprOrSuper::Expr(Box::new(Expr::Ident(quote_ident!("JSON")))),
+      prop: Box::new(Expr::Ident(quote_ident!("parse"))),
+      computed: false,
+    }))),
+    args: vec![ExprOrSpread {
+      spread: None,
+      expr: Box::new(Expr::Lit(Lit::Str(Str {
+        span: DUMMY_SP,
+        value: json.into(),
+        has_escape: false,
+        kind: StrKind::Synthesized,
+      }))),
+    }],
+    type_args: None,
+  })
+}
+
+/// returns the `type` of the import assertions, like `assert { type: "json" }`.
+fn get_assertion_type(asserts: &Option<ObjectLit>) -> Option<&str> {
+  for (key, value) in get_assertions(asserts.as_ref()?) {
+    if key == "type" {
+      return Some(value);
+    }
+  }
+  None
+}
+
+fn get_assertions(asserts: &ObjectLit) -> Vec<(&str, &str)> {
+  let mut assertions = vec![];
+  for prop in asserts.props.iter() {
+    if let PropOrSpread::Prop(prop) = prop {
+      if let Prop::KeyValue(KeyValueProp { key, value }) = prop.as_ref() {
+        let key = match key {
+          PropName::Ident(id) => id.sym.as_ref(),
+          PropName::Str(s) => s.value.as_ref(),
+          _ => continue,
+        };
+        if let Expr::Lit(Lit::Str(value)) = value.as_ref() {
+          assertions.push((key, value.value.as_ref()));
+        }
+      }
+    }
+  }
+  assertions
+}
+
+/// collects the sources and the assertions of the import/export declarations,
+/// the codegen of swc doesn't emit the assertions so they are restored by
+/// `restore_import_assertions` after the emit.
+pub fn collect_import_assertions(module: &Module) -> Vec<(String, Vec<(String, String)>)> {
+  let mut collected = vec![];
+  for item in module.body.iter() {
+    let (src, asserts) = match item {
+      ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl { src, asserts, .. })) => (src, asserts),
+      ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(NamedExport {
+        src: Some(src),
+        asserts,
+        ..
+      })) => (src, asserts),
+      ModuleItem::ModuleDecl(ModuleDecl::ExportAll(ExportAll { src, asserts, .. })) => {
+        (src, asserts)
+      }
+      _ => continue,
+    };
+    if let Some(asserts) = asserts {
+      let assertions: Vec<(String, String)> = get_assertions(asserts)
+        .into_iter()
+        .map(|(key, value)| (key.into(), value.into()))
+        .collect();
+      if !assertions.is_empty() {
+        collected.push((src.value.as_ref().into(), assertions));
+      }
+    }
+  }
+  collected
+}
+
+/// adds the collected assertions back to the emitted code, in the order of the
+/// declarations.
+pub fn restore_import_assertions(
+  code: &str,
+  assertions: &[(String, Vec<(String, String)>)],
+  minify: bool,
+) -> String {
+  let mut output = String::with_capacity(code.len());
+  let mut rest = code;
+  for (src, assertions) in assertions {
+    let src = format!("{:?}", src);
+    let patterns = [
+      format!("from {};", src),
+      format!("from{};", src),
+      format!("import {};", src),
+    ];
+    let found = patterns
+      .iter()
+      .filter_map(|pattern| {
+        rest
+          .find(pattern.as_str())
+          .map(|idx| idx + pattern.len() - 1)
+      })
+      .min();
+    if let Some(end) = found {
+      let props: Vec<String> = assertions
+        .iter()
+        .map(|(key, value)| {
+          if minify {
+            format!("{}:{:?}", key, value)
+          } else {
+            format!("{}: {:?}", key, value)
+          }
+        })
+        .collect();
+      output.push_str(&rest[..end]);
+      if minify {
+        output.push_str(format!("assert{{{}}}", props.join(",")).as_str());
+      } else {
+        output.push_str(format!(" assert {{ {} }}", props.join(", ")).as_str());
+      }
+      rest = &rest[end..];
+    }
+  }
+  output.push_str(rest);
+  output
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::swc::{test_resolver, EmitOptions, SWC};
+  use std::collections::HashMap;
+
+  #[test]
+  fn import_assertions() {
+    let source = r#"
+      import config from "./config.json" assert { type: "json" }
+      import * as data from "../data.json" assert { type: "json" }
+      import { default as pkg } from "https://deno.land/x/mod/package.json" assert { type: "json" }
+      export { default as meta } from "./meta.json" assert { type: "json" }
+      console.log(config, data, pkg)
+    "#;
+    let transform = |json_modules: HashMap<String, String>| {
+      let module = SWC::parse("/pages/index.ts", source, None).expect("could not parse module");
+      let resolver = test_resolver("/pages/index.ts");
+      let (code, _) = module
+        .transform(
+          resolver,
+          &EmitOptions {
+            json_modules,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      code
+    };
+
+    let code = transform(HashMap::new());
+    assert!(code.contains(
+      "import config from \"./config.json.js#/pages/config.json@000000\" assert { type: \"json\" };"
+    ));
+    assert!(code.contains(
+      "import * as data from \"../data.json.js#/data.json@000000\" assert { type: \"json\" };"
+    ));
+    assert!(code.contains(
+      "import { default as pkg } from \"../-/deno.land/x/mod/package.json.js\" assert { type: \"json\" };"
+    ));
+    assert!(code.contains(
+      "export { default as meta } from \"./meta.json.js#/pages/meta.json@000000\" assert { type: \"json\" };"
+    ));
+
+    let mut json_modules = HashMap::new();
+    json_modules.insert(
+      "/pages/config.json".into(),
+      "{ \"name\": \"app\", \"version\": 1 }".into(),
+    );
+    json_modules.insert("/data.json".into(), "[1, 2, 3]".into());
+    let code = transform(json_modules);
+    assert!(
+      code.contains("const config = JSON.parse(\"{\\\"name\\\":\\\"app\\\",\\\"version\\\":1}\");")
+    );
+    assert!(code.contains("const data = {\n    default: JSON.parse(\"[1,2,3]\")\n};"));
+    assert!(code.contains("import { default as pkg } from"));
+    assert!(!code.contains("config.json.js"));
+  }
+}
@@ -0,0 +1,365 @@
+use std::collections::HashSet;
+use swc_common::{Span, DUMMY_SP};
+use swc_ecma_ast::*;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// Checks the TypeScript `import x = require("mod")` and `export =` that can't be
+/// converted to ESM by the `import_equals_fold` safely, the messages suggest the
+/// rewrites. Returns the spans of the errors with the messages, in the order of the
+/// source.
+pub fn check_import_equals(module: &Module) -> Vec<(Span, String)> {
+  let refs = collect_value_refs(module);
+  let mut errors = vec![];
+  for item in module.body.iter() {
+    match item {
+      // match: import x = require("mod")
+      ModuleItem::ModuleDecl(ModuleDecl::TsImportEquals(TsImportEqualsDecl {
+        span,
+        declare: false,
+        is_export,
+        id,
+        module_ref: TsModuleRef::TsExternalModuleRef(TsExternalModuleRef { expr: src, .. }),
+      })) => {
+        let name = id.sym.as_ref();
+        let src = src.value.as_ref();
+        if *is_export {
+          errors.push((
+            *span,
+            format!(
+              "Cannot convert 'export import {0} = require(\"{1}\")' to ESM, use 'export * as {0} from \"{1}\"' for the namespace of the module, or 'export {{ default as {0} }} from \"{1}\"' for the `module.exports` of a CommonJS module",
+              name, src
+            ),
+          ));
+        } else if refs.bare.contains(name) {
+          errors.push((
+            *span,
+            format!(
+              "Cannot convert 'import {0} = require(\"{1}\")' to ESM because '{0}' is used as a value, use 'import {0} from \"{1}\"' for the `module.exports` of a CommonJS module, or 'import * as {0} from \"{1}\"' for the namespace of the module",
+              name, src
+            ),
+          ));
+        }
+      }
+      // match: export = app
+      ModuleItem::ModuleDecl(ModuleDecl::TsExportAssignment(TsExportAssignment {
+        span, ..
+      }))
+        if module.body.iter().any(is_value_export) =>
+      {
+        errors.push((
+          *span,
+          "Cannot convert 'export =' to ESM in a module with other exports, use 'export default' and the named exports instead".into(),
+        ));
+      }
+      _ => {}
+    }
+  }
+  errors
+}
+
+/// Creates a fold that converts the TypeScript `import =` and `export =` to the ESM
+/// equivalents before the types are stripped, the swc strip drops or panics on some
+/// of them:
+/// - `import x = require("mod")` -> `import * as x from "mod"`, the `x` is only
+///   used by the member expressions like `x.foo` (see `check_import_equals`)
+/// - `import x = A.B` -> `const x = A.B`, or dropped if the alias is only used as a
+///   type, like TypeScript
+/// - `export import x = A.B` -> `export const x = A.B`
+/// - `export = app` -> `export default app`
+pub fn import_equals_fold() -> impl Fold {
+  ImportEqualsFold {}
+}
+
+struct ImportEqualsFold {}
+
+impl Fold for ImportEqualsFold {
+  noop_fold_type!();
+
+  fn fold_module(&mut self, module: Module) -> Module {
+    let has_import_equals = module.body.iter().any(|item| {
+      matches!(
+        item,
+        ModuleItem::ModuleDecl(ModuleDecl::TsImportEquals(_))
+          | ModuleItem::ModuleDecl(ModuleDecl::TsExportAssignment(_))
+      )
+    });
+    if !has_import_equals {
+      return module;
+    }
+    let refs = collect_value_refs(&module);
+    let mut body = Vec::with_capacity(module.body.len());
+    for item in module.body {
+      match item {
+        ModuleItem::ModuleDecl(ModuleDecl::TsImportEquals(decl)) => {
+          if let Some(item) = convert_import_equals(decl, &refs) {
+            body.push(item);
+          }
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::TsExportAssignment(TsExportAssignment {
+          span,
+          expr,
+        })) => body.push(ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(
+          ExportDefaultExpr { span, expr },
+        ))),
+        _ => body.push(item),
+      }
+    }
+    Module { body, ..module }
+  }
+}
+
+fn convert_import_equals(decl: TsImportEqualsDecl, refs: &ValueRefs) -> Option<ModuleItem> {
+  let TsImportEqualsDecl {
+    span,
+    declare,
+    is_export,
+    id,
+    module_ref,
+  } = decl;
+  if declare {
+    return None;
+  }
+  match module_ref {
+    // match: import x = require("mod")
+    TsModuleRef::TsExternalModuleRef(TsExternalModuleRef { expr: src, .. }) => {
+      // the others are reported by `check_import_equals`, the imports that are only
+      // used as the types are dropped like TypeScript
+      if is_export || refs.bare.contains(id.sym.as_ref()) || !refs.all.contains(id.sym.as_ref()) {
+        return None;
+      }
+      Some(ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+        span,
+        specifiers: vec![ImportSpecifier::Namespace(ImportStarAsSpecifier {
+          span: DUMMY_SP,
+          local: id,
+        })],
+        src,
+        type_only: false,
+        asserts: None,
+      })))
+    }
+    // match: import x = A.B
+    TsModuleRef::TsEntityName(name) => {
+      if !is_export && !refs.all.contains(id.sym.as_ref()) {
+        return None;
+      }
+      let decl = Decl::Var(VarDecl {
+        span,
+        kind: VarDeclKind::Const,
+        declare: false,
+        decls: vec![VarDeclarator {
+          span: DUMMY_SP,
+          name: Pat::Ident(BindingIdent { id, type_ann: None }),
+          init: Some(Box::new(entity_name_to_expr(name))),
+          definite: false,
+        }],
+      });
+      Some(if is_export {
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { span, decl }))
+      } else {
+        ModuleItem::Stmt(Stmt::Decl(decl))
+      })
+    }
+  }
+}
+
+fn entity_name_to_expr(name: TsEntityName) -> Expr {
+  match name {
+    TsEntityName::Ident(id) => Expr::Ident(id),
+    TsEntityName::TsQualifiedName(qualified) => {
+      let TsQualifiedName { left, right } = *qualified;
+      Expr::Member(MemberExpr {
+        span: DUMMY_SP,
+        obj: ExprOrSuper::Expr(Box::new(entity_name_to_expr(left))),
+        prop: Box::new(Expr::Ident(right)),
+        computed: false,
+      })
+    }
+  }
+}
+
+/// checks if the module item exports a value, the types are not counted.
+fn is_value_export(item: &ModuleItem) -> bool {
+  match item {
+    ModuleItem::ModuleDecl(decl) => match decl {
+      ModuleDecl::ExportDecl(ExportDecl { decl, .. }) => !matches!(
+        decl,
+        Decl::TsInterface(_) | Decl::TsTypeAlias(_) | Decl::TsModule(_)
+      ),
+      ModuleDecl::ExportNamed(NamedExport { type_only, .. }) => !type_only,
+      ModuleDecl::ExportDefaultDecl(ExportDefaultDecl { decl, .. }) => {
+        !matches!(decl, DefaultDecl::TsInterfaceDecl(_))
+      }
+      ModuleDecl::ExportDefaultExpr(_) | ModuleDecl::ExportAll(_) => true,
+      ModuleDecl::TsImportEquals(TsImportEqualsDecl { is_export, .. }) => *is_export,
+      _ => false,
+    },
+    ModuleItem::Stmt(_) => false,
+  }
+}
+
+/// the names that are referenced as the values, by name without the scopes.
+struct ValueRefs {
+  all: HashSet<String>,
+  /// the references that are not the objects of the member expressions, like `x()`
+  bare: HashSet<String>,
+}
+
+fn collect_value_refs(module: &Module) -> ValueRefs {
+  let mut collector = ValueRefsCollector {
+    refs: ValueRefs {
+      all: HashSet::new(),
+      bare: HashSet::new(),
+    },
+  };
+  Program::Module(module.clone()).fold_with(&mut collector);
+  collector.refs
+}
+
+struct ValueRefsCollector {
+  refs: ValueRefs,
+}
+
+impl ValueRefsCollector {
+  fn add_ref(&mut self, id: &Ident, bare: bool) {
+    self.refs.all.insert(id.sym.as_ref().into());
+    if bare {
+      self.refs.bare.insert(id.sym.as_ref().into());
+    }
+  }
+}
+
+impl Fold for ValueRefsCollector {
+  noop_fold_type!();
+
+  fn fold_expr(&mut self, expr: Expr) -> Expr {
+    match &expr {
+      Expr::Ident(id) => {
+        self.add_ref(id, true);
+        expr
+      }
+      Expr::Member(MemberExpr {
+        obj,
+        prop,
+        computed,
+        ..
+      }) => {
+        // match: x.foo
+        match obj {
+          ExprOrSuper::Expr(obj) => match obj.as_ref() {
+            Expr::Ident(id) => self.add_ref(id, false),
+            obj => {
+              obj.clone().fold_with(self);
+            }
+          },
+          ExprOrSuper::Super(_) => {}
+        }
+        // the property of a non-computed member expression is not a reference
+        if *computed {
+          prop.clone().fold_with(self);
+        }
+        expr
+      }
+      _ => expr.fold_children_with(self),
+    }
+  }
+
+  // match: export { x }
+  fn fold_export_named_specifier(
+    &mut self,
+    specifier: ExportNamedSpecifier,
+  ) -> ExportNamedSpecifier {
+    self.add_ref(&specifier.orig, true);
+    specifier
+  }
+
+  // match: <x.Button />
+  fn fold_jsx_element_name(&mut self, name: JSXElementName) -> JSXElementName {
+    match &name {
+      JSXElementName::Ident(id) => self.add_ref(id, true),
+      JSXElementName::JSXMemberExpr(member) => {
+        let mut obj = &member.obj;
+        while let JSXObject::JSXMemberExpr(member) = obj {
+          obj = &member.obj;
+        }
+        if let JSXObject::Ident(id) = obj {
+          self.add_ref(id, false);
+        }
+      }
+      JSXElementName::JSXNamespacedName(_) => {}
+    }
+    name
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::swc::SWC;
+  use crate::{transform, Options};
+
+  #[test]
+  fn import_equals() {
+    let source = r#"
+      import path = require("https://deno.land/std/path/mod.ts")
+      import type_only = require("./types.ts")
+      namespace UI {
+        export const Button = () => null
+        export interface ButtonProps {}
+      }
+      import Button = UI.Button
+      import Props = UI.ButtonProps
+      const props: Props = {}
+      const t: type_only.T = 1
+      export = [path.join("a", "b"), Button, props, t]
+    "#;
+    let options: Options = serde_json::from_str("{}").unwrap();
+    let output = transform("/app.ts", source, &options).expect("could not transform module");
+    println!("{}", output.code);
+    assert!(output
+      .code
+      .contains("import * as path from \"./-/deno.land/std/path/mod.js\";"));
+    assert!(!output.code.contains("types"));
+    assert!(output.code.contains("const Button = UI.Button;"));
+    assert!(!output.code.contains("const Props"));
+    assert!(output.code.contains("export default [\n    path.join("));
+    assert!(output
+      .deps
+      .iter()
+      .any(|dep| dep.specifier == "https://deno.land/std/path/mod.ts"));
+
+    let source = r#"
+      namespace UI {
+        export const themes = { dark: "black" }
+      }
+      export import Theme = UI.themes.dark
+    "#;
+    let output = transform("/theme.ts", source, &options).expect("could not transform module");
+    assert!(output.code.contains("export const Theme = UI.themes.dark;"));
+
+    let source = r#"
+      import express = require("npm:express")
+      import * as fs from "./fs.ts"
+      export import lib = require("./lib.ts")
+      const app = express()
+      export const port = 8080
+      export = app
+    "#;
+    let module = SWC::parse("/server.ts", source, None).expect("could not parse module");
+    let errors = check_import_equals(&module.module);
+    assert_eq!(errors.len(), 3);
+    assert!(errors[0].1.starts_with(
+      "Cannot convert 'import express = require(\"npm:express\")' to ESM because 'express' is used as a value, use 'import express from \"npm:express\"'"
+    ));
+    assert!(errors[1]
+      .1
+      .contains("use 'export * as lib from \"./lib.ts\"'"));
+    assert!(errors[2]
+      .1
+      .starts_with("Cannot convert 'export =' to ESM in a module with other exports"));
+    match transform("/server.ts", source, &options) {
+      Err(err) => assert!(err.to_string().contains("Cannot convert")),
+      Ok(_) => panic!("the import assignments should be reported"),
+    }
+  }
+}
@@ -1,13 +1,16 @@
+use crate::error::ImportMapDiagnostic;
+
 use indexmap::IndexMap;
 use path_slash::PathBufExt;
 use relative_path::RelativePath;
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use std::{collections::HashMap, path::Path};
+use url::Url;
 
 type SpecifierHashMap = HashMap<String, String>;
 type SpecifierMap = IndexMap<String, String>;
 
-#[derive(Clone, Debug, Deserialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 #[serde(deny_unknown_fields)]
 pub struct ImportHashMap {
   #[serde(default)]
@@ -33,78 +36,165 @@ pub struct ImportMap {
 
 impl ImportMap {
   pub fn from_hashmap(map: ImportHashMap) -> Self {
-    let mut imports: SpecifierMap = IndexMap::new();
+    let imports = to_specifier_map(&map.imports);
     let mut scopes = IndexMap::new();
-    for (k, v) in map.imports.iter() {
-      if k.eq("@/") || k.eq("~/") {
-        imports.insert(
-          k.into(),
-          RelativePath::new(v)
-            .normalize()
-            .to_path(Path::new("/"))
-            .to_slash()
-            .unwrap()
-            .into(),
-        );
-      } else {
-        imports.insert(k.into(), v.into());
-      }
-    }
     for (k, v) in map.scopes.iter() {
-      let mut map: SpecifierMap = IndexMap::new();
-      for (k, v) in v.iter() {
-        if k.eq("@/") || k.eq("~/") {
-          imports.insert(
-            k.into(),
-            RelativePath::new(v)
-              .normalize()
-              .to_path(Path::new("/"))
-              .to_slash()
-              .unwrap()
-              .into(),
-          );
-        } else {
-          map.insert(k.into(), v.into());
-        }
+      if check_scope(k).is_ok() {
+        scopes.insert(k.into(), to_specifier_map(v));
       }
-      scopes.insert(k.into(), map);
     }
+    // the longest scope prefix wins
+    scopes.sort_by(|a: &String, _, b: &String, _| b.len().cmp(&a.len()));
     ImportMap { imports, scopes }
   }
 
+  /// resolve the `url` imported by the module `specifier`, the scopes that
+  /// match the `specifier` are checked before the top-level imports.
   pub fn resolve(&self, specifier: &str, url: &str) -> String {
     for (prefix, scope_imports) in self.scopes.iter() {
-      if prefix.ends_with("/") && specifier.starts_with(prefix) {
-        match scope_imports.get(url) {
-          Some(alias) => {
-            return alias.to_owned();
-          }
-          _ => {}
-        };
-        for (k, alias) in scope_imports.iter() {
-          if k.ends_with("/") && url.starts_with(k) {
-            let mut alias = alias.to_owned();
-            alias.push_str(url[k.len()..].into());
-            return alias;
-          }
+      if prefix.eq(specifier) || (prefix.ends_with("/") && specifier.starts_with(prefix)) {
+        if let Some(alias) = resolve_specifier(scope_imports, url) {
+          return alias;
         }
       }
     }
-    match self.imports.get(url) {
-      Some(alias) => {
-        return alias.to_owned();
+    match resolve_specifier(&self.imports, url) {
+      Some(alias) => alias,
+      None => url.into(),
+    }
+  }
+}
+
+fn to_specifier_map(map: &SpecifierHashMap) -> SpecifierMap {
+  let mut imports: SpecifierMap = IndexMap::new();
+  for (k, v) in map.iter() {
+    if k.eq("@/") || k.eq("~/") {
+      imports.insert(
+        k.into(),
+        RelativePath::new(v)
+          .normalize()
+          .to_path(Path::new("/"))
+          .to_slash()
+          .unwrap(),
+      );
+    } else if check_entry(k, v).is_ok() {
+      imports.insert(k.into(), v.into());
+    }
+  }
+  // the longest package prefix wins
+  imports.sort_by(|a: &String, _, b: &String, _| b.len().cmp(&a.len()));
+  imports
+}
+
+/// The result of `validate_import_map`, the entries of the `errors` are ignored
+/// by the resolver, the entries of the `warnings` are kept.
+#[derive(Clone, Debug, Default, Serialize)]
+pub struct ImportMapValidation {
+  pub errors: Vec<ImportMapDiagnostic>,
+  pub warnings: Vec<ImportMapDiagnostic>,
+}
+
+/// validates the entries of the import map, which are ignored silently by
+/// `ImportMap::from_hashmap` if they are invalid.
+pub fn validate_import_map(map: &ImportHashMap) -> ImportMapValidation {
+  let mut validation = ImportMapValidation::default();
+  let mut push =
+    |scope: Option<&String>, key: &str, value: &str, result: Result<Option<String>, String>| {
+      let diagnostic = |reason| ImportMapDiagnostic {
+        scope: scope.cloned(),
+        key: key.into(),
+        value: value.into(),
+        reason,
+      };
+      match result {
+        Ok(Some(reason)) => validation.warnings.push(diagnostic(reason)),
+        Err(reason) => validation.errors.push(diagnostic(reason)),
+        Ok(None) => {}
       }
-      _ => {}
     };
-    for (k, alias) in self.imports.iter() {
-      if k.ends_with("/") && url.starts_with(k) {
-        let mut alias = alias.to_owned();
-        alias.push_str(url[k.len()..].into());
-        return alias;
-      }
+  for (k, v) in map.imports.iter() {
+    push(None, k, v, check_entry(k, v));
+  }
+  for (scope, imports) in map.scopes.iter() {
+    if let Err(reason) = check_scope(scope) {
+      push(Some(scope), "", "", Err(reason));
+      continue;
+    }
+    for (k, v) in imports.iter() {
+      push(Some(scope), k, v, check_entry(k, v));
     }
-    url.into()
   }
+  // the hashmap has no order
+  let sort_key = |d: &ImportMapDiagnostic| (d.scope.clone(), d.key.clone());
+  validation.errors.sort_by_key(sort_key);
+  validation.warnings.sort_by_key(sort_key);
+  validation
+}
+
+/// checks an entry of the imports, returns the reason if the entry is invalid,
+/// or the warning that the entry is kept with.
+fn check_entry(key: &str, value: &str) -> Result<Option<String>, String> {
+  if key.is_empty() {
+    return Err("the specifier is empty".into());
+  }
+  if value.is_empty() {
+    return Err("the address is empty".into());
+  }
+  // the `@/` and `~/` aliases map to the directories of the app
+  if key.eq("@/") || key.eq("~/") {
+    return Ok(None);
+  }
+  if !is_url_like(value) {
+    return Err(format!(
+      "the address {:?} is a bare specifier, it must be a URL or start with \"/\", \"./\" or \"../\"",
+      value
+    ));
+  }
+  if key.ends_with('/') && !value.ends_with('/') {
+    // a package prefix must map to an address with a trailing slash,
+    // otherwise the mapping is invalid and ignored per the spec.
+    return Err("the package prefix must map to an address with a trailing slash".into());
+  }
+  if !key.ends_with('/') && value.ends_with('/') {
+    return Ok(Some(
+      "the address has a trailing slash but the specifier doesn't, add a trailing slash to the specifier to map the subpaths".into(),
+    ));
+  }
+  Ok(None)
+}
+
+/// checks the prefix of a scope, returns the reason if the scope is invalid.
+fn check_scope(scope: &str) -> Result<(), String> {
+  if scope.is_empty() {
+    return Err("the scope is empty".into());
+  }
+  if !is_url_like(scope) {
+    return Err(format!(
+      "the scope {:?} is a bare specifier, it must be a URL or start with \"/\", \"./\" or \"../\"",
+      scope
+    ));
+  }
+  Ok(())
+}
+
+/// checks if the `s` is an absolute URL (e.g. `https://esm.sh/react`, `npm:react`)
+/// or a path.
+fn is_url_like(s: &str) -> bool {
+  s.starts_with('/') || s.starts_with("./") || s.starts_with("../") || Url::parse(s).is_ok()
+}
+
+fn resolve_specifier(imports: &SpecifierMap, url: &str) -> Option<String> {
+  if let Some(alias) = imports.get(url) {
+    return Some(alias.to_owned());
+  }
+  for (k, alias) in imports.iter() {
+    if k.ends_with("/") && url.starts_with(k) {
+      let mut alias = alias.to_owned();
+      alias.push_str(url[k.len()..].into());
+      return Some(alias);
+    }
+  }
+  None
 }
 
 #[cfg(test)]
@@ -152,4 +242,117 @@ mod tests {
       "https://esm.sh/react@16.4.0"
     );
   }
+
+  #[test]
+  fn resolve_import_map_scopes() {
+    let mut imports: SpecifierHashMap = HashMap::new();
+    let mut scopes: HashMap<String, SpecifierHashMap> = HashMap::new();
+    imports.insert("react".into(), "https://esm.sh/react".into());
+    imports.insert("swr".into(), "https://esm.sh/swr".into());
+    let mut scope_imports: SpecifierHashMap = HashMap::new();
+    scope_imports.insert("react".into(), "https://esm.sh/react@16".into());
+    scopes.insert("/vendor/".into(), scope_imports);
+    let mut scope_imports: SpecifierHashMap = HashMap::new();
+    scope_imports.insert("react".into(), "https://esm.sh/react@15".into());
+    scopes.insert("/vendor/legacy/".into(), scope_imports);
+    let mut scope_imports: SpecifierHashMap = HashMap::new();
+    scope_imports.insert("react".into(), "https://esm.sh/react@14".into());
+    scopes.insert("/pages/old.tsx".into(), scope_imports);
+    let import_map = ImportMap::from_hashmap(ImportHashMap { imports, scopes });
+    assert_eq!(
+      import_map.resolve("/vendor/mod.ts", "react"),
+      "https://esm.sh/react@16"
+    );
+    assert_eq!(
+      import_map.resolve("/vendor/legacy/mod.ts", "react"),
+      "https://esm.sh/react@15"
+    );
+    assert_eq!(
+      import_map.resolve("/pages/old.tsx", "react"),
+      "https://esm.sh/react@14"
+    );
+    assert_eq!(
+      import_map.resolve("/pages/old.tsx.bak", "react"),
+      "https://esm.sh/react"
+    );
+    assert_eq!(
+      import_map.resolve("/vendor/legacy/mod.ts", "swr"),
+      "https://esm.sh/swr"
+    );
+    assert_eq!(
+      import_map.resolve("/app.tsx", "react"),
+      "https://esm.sh/react"
+    );
+  }
+
+  #[test]
+  fn resolve_import_map_package_prefix() {
+    let mut imports: SpecifierHashMap = HashMap::new();
+    imports.insert("react".into(), "https://esm.sh/react@17".into());
+    imports.insert("react/".into(), "https://esm.sh/react@17/".into());
+    imports.insert("lodash/".into(), "https://esm.sh/lodash".into());
+    imports.insert("lodash/fp/".into(), "https://esm.sh/lodash/fp/".into());
+    let import_map = ImportMap::from_hashmap(ImportHashMap {
+      imports,
+      scopes: HashMap::new(),
+    });
+    assert_eq!(
+      import_map.resolve("/app.tsx", "react"),
+      "https://esm.sh/react@17"
+    );
+    assert_eq!(
+      import_map.resolve("/app.tsx", "react/jsx-runtime"),
+      "https://esm.sh/react@17/jsx-runtime"
+    );
+    assert_eq!(
+      import_map.resolve("/app.tsx", "lodash/fp/map"),
+      "https://esm.sh/lodash/fp/map"
+    );
+    assert_eq!(import_map.resolve("/app.tsx", "lodash/map"), "lodash/map");
+  }
+
+  #[test]
+  fn validate_import_maps() {
+    let mut imports: SpecifierHashMap = HashMap::new();
+    let mut scopes: HashMap<String, SpecifierHashMap> = HashMap::new();
+    imports.insert("@/".into(), "./".into());
+    imports.insert("react".into(), "https://esm.sh/react".into());
+    imports.insert("lodash/".into(), "https://esm.sh/lodash".into());
+    imports.insert("preact".into(), "preact/compat".into());
+    imports.insert("".into(), "https://esm.sh/empty".into());
+    imports.insert("swr".into(), "https://esm.sh/swr/".into());
+    imports.insert("fs".into(), "node:fs".into());
+    let mut scope_imports: SpecifierHashMap = HashMap::new();
+    scope_imports.insert("react".into(), "".into());
+    scopes.insert("/vendor/".into(), scope_imports.clone());
+    scopes.insert("vendor".into(), scope_imports);
+    let map = ImportHashMap { imports, scopes };
+    let validation = validate_import_map(&map);
+    assert_eq!(
+      validation
+        .errors
+        .iter()
+        .map(|d| (d.scope.as_deref(), d.key.as_str(), d.value.as_str()))
+        .collect::<Vec<_>>(),
+      vec![
+        (None, "", "https://esm.sh/empty"),
+        (None, "lodash/", "https://esm.sh/lodash"),
+        (None, "preact", "preact/compat"),
+        (Some("/vendor/"), "react", ""),
+        (Some("vendor"), "", ""),
+      ]
+    );
+    assert!(validation.errors[2].reason.contains("bare specifier"));
+    assert_eq!(validation.warnings.len(), 1);
+    assert_eq!(validation.warnings[0].key, "swr");
+
+    let import_map = ImportMap::from_hashmap(map);
+    let mut keys = import_map.imports.keys().collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(keys, vec!["@/", "fs", "react", "swr"]);
+    assert_eq!(
+      import_map.scopes.keys().collect::<Vec<_>>(),
+      vec!["/vendor/"]
+    );
+  }
 }
@@ -0,0 +1,250 @@
+use crate::ast_utils::new_str;
+use crate::resolve_fold::get_worker_url;
+
+use std::collections::HashMap;
+use swc_common::{Span, DUMMY_SP};
+use swc_ecma_ast::*;
+use swc_ecma_utils::quote_ident;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// Creates a fold that replaces the `import.meta.url` with the `url`, since the
+/// modules are rewritten to the cached local paths that don't reflect the original
+/// url, and `import.meta.main` with `false` if `stub_main` is set for the browser,
+/// where a module is never the main module:
+///
+/// ```js
+/// console.log(import.meta.url, import.meta.main)
+/// ```
+///
+/// is transformed to:
+///
+/// ```js
+/// console.log("https://deno.land/x/mod/mod.ts", false)
+/// ```
+///
+/// The `import.meta.url` of the web worker urls is kept, which are resolved
+/// relative to the local path.
+pub fn import_meta_fold(url: String, stub_main: bool) -> impl Fold {
+  ImportMetaFold { url, stub_main }
+}
+
+struct ImportMetaFold {
+  url: String,
+  stub_main: bool,
+}
+
+impl Fold for ImportMetaFold {
+  noop_fold_type!();
+
+  fn fold_expr(&mut self, expr: Expr) -> Expr {
+    match expr {
+      // match: new Worker(new URL("./worker.ts", import.meta.url), { ... })
+      Expr::New(mut new) if get_worker_url(&new).is_some() => {
+        let mut args = new.args.take().unwrap_or_default();
+        let rest = args.split_off(1).fold_with(self);
+        args.extend(rest);
+        Expr::New(NewExpr {
+          args: Some(args),
+          ..new
+        })
+      }
+      Expr::Member(member) => match get_import_meta_prop(&member) {
+        Some("url") => Expr::Lit(Lit::Str(new_str(self.url.as_str()))),
+        Some("main") if self.stub_main => Expr::Lit(Lit::Bool(Bool {
+          span: DUMMY_SP,
+          value: false,
+        })),
+        _ => Expr::Member(member.fold_children_with(self)),
+      },
+      _ => expr.fold_children_with(self),
+    }
+  }
+}
+
+/// Creates a fold that expands the `import.meta.glob` calls like Vite, the `glob_files`
+/// are the files matched by the patterns (keyed by the pattern), which are passed
+/// by the caller since the compiler can't access the file system:
+///
+/// ```js
+/// const pages = import.meta.glob("./pages/*.tsx")
+/// ```
+///
+/// is transformed to:
+///
+/// ```js
+/// const pages = {
+///   "./pages/about.tsx": () => import("./pages/about.tsx"),
+///   "./pages/index.tsx": () => import("./pages/index.tsx"),
+/// }
+/// ```
+///
+/// The dynamic imports are resolved and reported as the deps by the resolve fold,
+/// the calls of the patterns that are not in `glob_files` are kept.
+pub fn import_glob_fold(glob_files: HashMap<String, Vec<String>>) -> impl Fold {
+  ImportGlobFold { glob_files }
+}
+
+struct ImportGlobFold {
+  glob_files: HashMap<String, Vec<String>>,
+}
+
+impl Fold for ImportGlobFold {
+  noop_fold_type!();
+
+  fn fold_expr(&mut self, expr: Expr) -> Expr {
+    if let Expr::Call(call) = &expr {
+      if let Some(files) =
+        get_import_glob(call).and_then(|(pattern, _)| self.glob_files.get(&pattern))
+      {
+        return Expr::Object(ObjectLit {
+          span: DUMMY_SP,
+          props: files
+            .iter()
+            .map(|file| {
+              PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                key: PropName::Str(new_str(file)),
+                value: Box::new(new_lazy_import(file)),
+              })))
+            })
+            .collect(),
+        });
+      }
+    }
+    expr.fold_children_with(self)
+  }
+}
+
+/// returns the pattern of the `import.meta.glob` call, match: `import.meta.glob("./pages/*.tsx")`.
+pub fn get_import_glob(call: &CallExpr) -> Option<(String, Span)> {
+  match &call.callee {
+    ExprOrSuper::Expr(callee) => match callee.as_ref() {
+      Expr::Member(member) if get_import_meta_prop(member) == Some("glob") => {}
+      _ => return None,
+    },
+    _ => return None,
+  };
+  match call.args.first()?.expr.as_ref() {
+    Expr::Lit(Lit::Str(s)) => Some((s.value.as_ref().into(), s.span)),
+    Expr::Tpl(Tpl {
+      exprs,
+      quasis,
+      span,
+    }) if exprs.is_empty() && quasis.len() == 1 => {
+      Some((quasis[0].raw.value.as_ref().into(), *span))
+    }
+    _ => None,
+  }
+}
+
+// match: () => import("./pages/index.tsx")
+fn new_lazy_import(file: &str) -> Expr {
+  Expr::Arrow(ArrowExpr {
+    span: DUMMY_SP,
+    params: vec![],
+    body: BlockStmtOrExpr::Expr(Box::new(Expr::Call(CallExpr {
+      span: DUMMY_SP,
+      callee: ExprOrSuper::Expr(Box::new(Expr::Ident(quote_ident!("import")))),
+      args: vec![ExprOrSpread {
+        spread: None,
+        expr: Box::new(Expr::Lit(Lit::Str(new_str(file)))),
+      }],
+      type_args: None,
+    }))),
+    is_async: false,
+    is_generator: false,
+    type_params: None,
+    return_type: None,
+  })
+}
+
+/// returns the property name of `import.meta.*`, e.g. `url` and `main`.
+fn get_import_meta_prop(member: &MemberExpr) -> Option<&str> {
+  match (&member.obj, member.prop.as_ref(), member.computed) {
+    (ExprOrSuper::Expr(obj), Expr::Ident(prop), false) => match obj.as_ref() {
+      Expr::MetaProp(MetaPropExpr {
+        meta,
+        prop: meta_prop,
+      }) if meta.sym.eq("import") && meta_prop.sym.eq("meta") => Some(prop.sym.as_ref()),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::swc::{test_resolver, EmitOptions, TargetEnv, SWC};
+
+  #[test]
+  fn rewrite_import_meta() {
+    let source = r#"
+      const worker = new Worker(new URL("./worker.ts", import.meta.url))
+      const root = new URL(".", import.meta.url)
+      if (import.meta.main) {
+        console.log(import.meta["url"])
+      }
+    "#;
+    let transform = |options: EmitOptions| {
+      let module =
+        SWC::parse("https://deno.land/x/mod/mod.ts", source, None).expect("could not parse module");
+      let resolver = test_resolver("https://deno.land/x/mod/mod.ts");
+      let (code, _) = module
+        .transform(resolver, &options)
+        .expect("could not transform module");
+      println!("{}", code);
+      code
+    };
+
+    let code = transform(EmitOptions::default());
+    assert!(code.contains("new Worker(new URL(\"./worker.js\", import.meta.url))"));
+    assert!(code.contains("new URL(\".\", \"https://deno.land/x/mod/mod.ts\")"));
+    assert!(code.contains("if (import.meta.main)"));
+    assert!(code.contains("console.log(import.meta[\"url\"])"));
+
+    let code = transform(EmitOptions {
+      import_meta_url: Some("https://example.com/mod.ts".into()),
+      target_env: Some(TargetEnv::Browser),
+      ..Default::default()
+    });
+    assert!(code.contains("new URL(\".\", \"https://example.com/mod.ts\")"));
+    assert!(code.contains("if (false)"));
+  }
+
+  #[test]
+  fn import_glob() {
+    let source = r#"
+      const pages = import.meta.glob("./pages/*.tsx")
+      const posts = import.meta.glob(`../posts/*.md`)
+    "#;
+    let module = SWC::parse("/app.tsx", source, None).expect("could not parse module");
+    let resolver = test_resolver("/app.tsx");
+    let mut glob_files = HashMap::new();
+    glob_files.insert(
+      "./pages/*.tsx".to_owned(),
+      vec![
+        "./pages/about.tsx".to_owned(),
+        "./pages/index.tsx".to_owned(),
+      ],
+    );
+    let (code, _) = module
+      .transform(
+        resolver.clone(),
+        &EmitOptions {
+          glob_files,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code
+      .contains("\"./pages/about.tsx\": ()=>import(\"./pages/about.js#/pages/about.tsx@000000\")"));
+    assert!(code
+      .contains("\"./pages/index.tsx\": ()=>import(\"./pages/index.js#/pages/index.tsx@000000\")"));
+    assert!(code.contains("const posts = import.meta.glob(`../posts/*.md`)"));
+    let r = resolver.borrow();
+    assert_eq!(r.dep_graph.len(), 2);
+    assert_eq!(r.dep_graph[0].specifier, "/pages/about.tsx");
+    assert!(r.dep_graph[0].is_dynamic);
+  }
+}
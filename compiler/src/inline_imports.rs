@@ -0,0 +1,166 @@
+use crate::import_assertions::{new_default_var_decls, new_json_parse};
+use crate::resolve::Resolver;
+
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_visit::{noop_fold_type, Fold};
+
+/// The default byte size limit of the inlined imports.
+pub const DEFAULT_INLINE_LIMIT: usize = 4096;
+
+/// Creates a fold that inlines the small `.json` and `?text` imports as the constants,
+/// which saves the requests of the tiny files in development. The `contents` are
+/// keyed by the fixed url without the query, the imports of the contents that are
+/// larger than the `limit` (in bytes) are kept:
+///
+/// ```js
+/// import config from "./config.json"
+/// import readme from "./README.md?text"
+/// ```
+///
+/// is transformed to:
+///
+/// ```js
+/// const config = JSON.parse("{\"name\":\"app\"}");
+/// const readme = "# App\n";
+/// ```
+///
+/// The imports with the assertions are left to the `json_module_fold`.
+pub fn inline_import_fold(
+  resolver: Rc<RefCell<Resolver>>,
+  contents: HashMap<String, String>,
+  limit: usize,
+) -> impl Fold {
+  InlineImportFold {
+    resolver,
+    contents,
+    limit,
+  }
+}
+
+struct InlineImportFold {
+  resolver: Rc<RefCell<Resolver>>,
+  contents: HashMap<String, String>,
+  limit: usize,
+}
+
+impl InlineImportFold {
+  /// returns the value of the import to inline, `None` if the import is not a
+  /// `.json` or a `?text` import, the content is not provided or too large.
+  fn get_value(&self, decl: &ImportDecl) -> Option<Expr> {
+    if decl.asserts.is_some() {
+      return None;
+    }
+    let src = decl.src.value.as_ref();
+    let mut parts = src.splitn(2, '?');
+    let path = parts.next().unwrap().split('#').next().unwrap();
+    let is_text = match parts.next() {
+      Some(query) => query
+        .split('#')
+        .next()
+        .unwrap()
+        .split('&')
+        .any(|param| param == "text" || param.starts_with("text=")),
+      None => false,
+    };
+    if !is_text && !path.ends_with(".json") {
+      return None;
+    }
+    let fixed_url = self.resolver.borrow().fix_url(path);
+    let content = self.contents.get(&fixed_url)?;
+    if content.len() > self.limit {
+      return None;
+    }
+    if is_text {
+      return Some(Expr::Lit(Lit::Str(Str {
+        span: DUMMY_SP,
+        value: content.as_str().into(),
+        has_escape: false,
+        kind: StrKind::Synthesized,
+      })));
+    }
+    let value: serde_json::Value = serde_json::from_str(content).ok()?;
+    Some(new_json_parse(value.to_string().as_str()))
+  }
+}
+
+impl Fold for InlineImportFold {
+  noop_fold_type!();
+
+  fn fold_module_items(&mut self, items: Vec<ModuleItem>) -> Vec<ModuleItem> {
+    let mut output = Vec::with_capacity(items.len());
+    for item in items {
+      if let ModuleItem::ModuleDecl(ModuleDecl::Import(decl)) = &item {
+        if !decl.type_only {
+          if let Some(value) = self.get_value(decl) {
+            if let Some(decls) = new_default_var_decls(&decl.specifiers, value) {
+              // match: import "./config.json"
+              if !decls.is_empty() {
+                output.push(ModuleItem::Stmt(Stmt::Decl(Decl::Var(VarDecl {
+                  span: DUMMY_SP,
+                  kind: VarDeclKind::Const,
+                  declare: false,
+                  decls,
+                }))));
+              }
+              continue;
+            }
+          }
+        }
+      }
+      output.push(item);
+    }
+    output
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::swc::{test_resolver, EmitOptions, SWC};
+  use std::collections::HashMap;
+
+  #[test]
+  fn inline_imports() {
+    let source = r#"
+      import config from "./config.json"
+      import * as data from "../data.json"
+      import readme from "./README.md?text"
+      import large from "./large.json"
+      import { name } from "./meta.json"
+      import pkg from "./package.json" assert { type: "json" }
+      console.log(config, data, readme, large, name, pkg)
+    "#;
+    let mut contents = HashMap::new();
+    contents.insert("/pages/config.json".into(), "{ \"name\": \"app\" }".into());
+    contents.insert("/data.json".into(), "[1, 2, 3]".into());
+    contents.insert("/pages/README.md".into(), "# App\n\n\"docs\"".into());
+    contents.insert(
+      "/pages/large.json".into(),
+      format!("[{}]", "0,".repeat(32) + "0"),
+    );
+    contents.insert("/pages/meta.json".into(), "{ \"name\": \"meta\" }".into());
+    contents.insert("/pages/package.json".into(), "{}".into());
+    let module = SWC::parse("/pages/index.ts", source, None).expect("could not parse module");
+    let resolver = test_resolver("/pages/index.ts");
+    let (code, _) = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          inline_imports: contents,
+          inline_limit: 32,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains("const config = JSON.parse(\"{\\\"name\\\":\\\"app\\\"}\");"));
+    assert!(code.contains("const data = {\n    default: JSON.parse(\"[1,2,3]\")\n};"));
+    assert!(code.contains("const readme = \"# App\\n\\n\\\"docs\\\"\";"));
+    assert!(code.contains("import large from \"./large.json.js#/pages/large.json@000000\";"));
+    assert!(code.contains("import { name } from \"./meta.json.js#/pages/meta.json@000000\";"));
+    assert!(code.contains(
+      "import pkg from \"./package.json.js#/pages/package.json@000000\" assert { type: \"json\" };"
+    ));
+  }
+}
@@ -0,0 +1,260 @@
+use std::collections::{HashMap, HashSet};
+use swc_common::Span;
+use swc_ecma_ast::*;
+use swc_ecma_visit::{Fold, FoldWith};
+
+/// Checks the constructs that can't be transpiled file by file without the type
+/// information, like the `isolatedModules` flag of the TypeScript compiler:
+/// - re-exporting an imported binding that is only used as a type without `export type`
+/// - accessing an ambient const enum (`declare const enum`)
+///
+/// Returns the spans of the errors with the messages, in the order of the source.
+pub fn check_isolated_modules(module: &Module) -> Vec<(Span, String)> {
+  let mut checker = IsolatedModulesChecker {
+    imports: HashSet::new(),
+    exports: vec![],
+    value_refs: HashMap::new(),
+    type_refs: HashSet::new(),
+    ambient_const_enums: HashSet::new(),
+  };
+  Program::Module(module.clone()).fold_with(&mut checker);
+
+  let mut errors = vec![];
+  for export in checker.exports.iter() {
+    let name = export.sym.as_ref();
+    if checker.imports.contains(name)
+      && checker.type_refs.contains(name)
+      && !checker.value_refs.contains_key(name)
+    {
+      errors.push((
+        export.span,
+        format!(
+          "Re-exporting a type when the '--isolatedModules' flag is provided requires using 'export type': '{}'",
+          name
+        ),
+      ));
+    }
+  }
+  for name in checker.ambient_const_enums.iter() {
+    if let Some(spans) = checker.value_refs.get(name) {
+      for span in spans {
+        errors.push((
+          *span,
+          format!(
+            "Cannot access ambient const enums when the '--isolatedModules' flag is provided: '{}'",
+            name
+          ),
+        ));
+      }
+    }
+  }
+  errors.sort_by_key(|(span, _)| span.lo);
+  errors
+}
+
+struct IsolatedModulesChecker {
+  /// the local names of the value imports
+  imports: HashSet<String>,
+  /// the local bindings that are exported by `export { a }` or `export default a`
+  exports: Vec<Ident>,
+  value_refs: HashMap<String, Vec<Span>>,
+  type_refs: HashSet<String>,
+  ambient_const_enums: HashSet<String>,
+}
+
+impl IsolatedModulesChecker {
+  fn add_value_ref(&mut self, id: &Ident) {
+    self
+      .value_refs
+      .entry(id.sym.as_ref().into())
+      .or_default()
+      .push(id.span);
+  }
+
+  fn add_type_ref(&mut self, name: &TsEntityName) {
+    match name {
+      TsEntityName::Ident(id) => {
+        self.type_refs.insert(id.sym.as_ref().into());
+      }
+      TsEntityName::TsQualifiedName(qualified) => self.add_type_ref(&qualified.left),
+    }
+  }
+}
+
+// the types are visited, so `noop_fold_type!()` is not used here.
+impl Fold for IsolatedModulesChecker {
+  fn fold_module_decl(&mut self, decl: ModuleDecl) -> ModuleDecl {
+    match &decl {
+      // match: import { FC } from "https://esm.sh/react"
+      ModuleDecl::Import(ImportDecl {
+        specifiers,
+        type_only: false,
+        ..
+      }) => {
+        for specifier in specifiers {
+          let local = match specifier {
+            ImportSpecifier::Named(ImportNamedSpecifier { local, .. }) => local,
+            ImportSpecifier::Default(ImportDefaultSpecifier { local, .. }) => local,
+            ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => local,
+          };
+          self.imports.insert(local.sym.as_ref().into());
+        }
+      }
+      // match: export { FC }
+      ModuleDecl::ExportNamed(NamedExport {
+        specifiers,
+        src: None,
+        type_only: false,
+        ..
+      }) => {
+        for specifier in specifiers {
+          if let ExportSpecifier::Named(ExportNamedSpecifier { orig, .. }) = specifier {
+            self.exports.push(orig.clone());
+          }
+        }
+      }
+      // match: export default FC
+      ModuleDecl::ExportDefaultExpr(ExportDefaultExpr { expr, .. }) => {
+        if let Expr::Ident(id) = expr.as_ref() {
+          self.exports.push(id.clone());
+          return decl;
+        }
+      }
+      _ => {}
+    }
+    decl.fold_children_with(self)
+  }
+
+  // match: declare const enum Direction { Up, Down }
+  fn fold_ts_enum_decl(&mut self, decl: TsEnumDecl) -> TsEnumDecl {
+    if decl.declare && decl.is_const {
+      self.ambient_const_enums.insert(decl.id.sym.as_ref().into());
+    }
+    decl.fold_children_with(self)
+  }
+
+  fn fold_expr(&mut self, expr: Expr) -> Expr {
+    match expr {
+      Expr::Ident(id) => {
+        self.add_value_ref(&id);
+        Expr::Ident(id)
+      }
+      // the property of a non-computed member expression is not a reference
+      Expr::Member(MemberExpr {
+        span,
+        obj,
+        prop,
+        computed: false,
+      }) => Expr::Member(MemberExpr {
+        span,
+        obj: obj.fold_with(self),
+        prop,
+        computed: false,
+      }),
+      _ => expr.fold_children_with(self),
+    }
+  }
+
+  // match: { FC }
+  fn fold_prop(&mut self, prop: Prop) -> Prop {
+    if let Prop::Shorthand(id) = &prop {
+      self.add_value_ref(id);
+    }
+    prop.fold_children_with(self)
+  }
+
+  // match: <App />
+  fn fold_jsx_element_name(&mut self, name: JSXElementName) -> JSXElementName {
+    if let JSXElementName::Ident(id) = &name {
+      self.add_value_ref(id);
+    }
+    name.fold_children_with(self)
+  }
+
+  fn fold_jsx_object(&mut self, obj: JSXObject) -> JSXObject {
+    if let JSXObject::Ident(id) = &obj {
+      self.add_value_ref(id);
+    }
+    obj.fold_children_with(self)
+  }
+
+  // match: const app: FC = () => null
+  fn fold_ts_type_ref(&mut self, type_ref: TsTypeRef) -> TsTypeRef {
+    self.add_type_ref(&type_ref.type_name);
+    type_ref.fold_children_with(self)
+  }
+
+  // match: class App implements FC {}
+  fn fold_ts_expr_with_type_args(&mut self, expr: TsExprWithTypeArgs) -> TsExprWithTypeArgs {
+    self.add_type_ref(&expr.expr);
+    expr.fold_children_with(self)
+  }
+
+  // match: type App = typeof app
+  fn fold_ts_type_query(&mut self, query: TsTypeQuery) -> TsTypeQuery {
+    if let TsTypeQueryExpr::TsEntityName(name) = &query.expr_name {
+      self.add_type_ref(name);
+    }
+    query.fold_children_with(self)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::error::DiagnosticBuffer;
+  use crate::swc::{test_resolver, EmitOptions, SWC};
+
+  #[test]
+  fn isolated_modules_errors() {
+    let source = r#"
+      import { FC, useState } from "https://esm.sh/react"
+      import { Props } from "./types.ts"
+      import { Config } from "./config.ts"
+      import type { Theme } from "./theme.ts"
+      declare const enum Direction { Up, Down }
+      const App: FC<Props> = () => {
+        const [dir] = useState(Direction.Up)
+        return null
+      }
+      const theme: Theme = { config: Config }
+      export { App, FC, Props, Config, Theme }
+    "#;
+    let module = SWC::parse("/app.tsx", source, None).expect("could not parse module");
+    let errors = check_isolated_modules(&module.module);
+    let messages: Vec<&str> = errors.iter().map(|(_, message)| message.as_str()).collect();
+    assert_eq!(messages.len(), 3);
+    assert_eq!(
+      messages[0],
+      "Cannot access ambient const enums when the '--isolatedModules' flag is provided: 'Direction'"
+    );
+    assert!(messages[1].ends_with("requires using 'export type': 'FC'"));
+    assert!(messages[2].ends_with("requires using 'export type': 'Props'"));
+
+    let resolver = test_resolver("/app.tsx");
+    let err = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          isolated_modules: true,
+          ..Default::default()
+        },
+      )
+      .err()
+      .unwrap();
+    let buffer = err.downcast::<DiagnosticBuffer>().unwrap();
+    assert_eq!(buffer.diagnostics.len(), 3);
+    assert_eq!(buffer.diagnostics[0].line, 8);
+    assert_eq!(buffer.diagnostics[1].line, 12);
+
+    let source = r#"
+      import type { FC } from "https://esm.sh/react"
+      import { Props } from "./types.ts"
+      const enum Direction { Up, Down }
+      export const dir = Direction.Up
+      export type { FC, Props }
+    "#;
+    let module = SWC::parse("/app.ts", source, None).expect("could not parse module");
+    assert!(check_isolated_modules(&module.module).is_empty());
+  }
+}
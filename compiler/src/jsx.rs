@@ -1,5 +1,5 @@
 use crate::resolve::{is_remote_url, DependencyDescriptor, InlineStyle, Resolver};
-use crate::resolve_fold::create_aleph_pack_var_decl_member;
+use crate::resolve_fold::{create_aleph_pack_var_decl_member, import_resolve_fold};
 
 use sha1::{Digest, Sha1};
 use std::{cell::RefCell, rc::Rc};
@@ -12,6 +12,8 @@ pub fn aleph_jsx_fold(
   resolver: Rc<RefCell<Resolver>>,
   source: Rc<SourceMap>,
   is_dev: bool,
+  jsx_source: bool,
+  strip_data_test_attrs: bool,
 ) -> (impl Fold, impl Fold) {
   (
     AlephJsxFold {
@@ -19,24 +21,35 @@ pub fn aleph_jsx_fold(
       source,
       inline_style_idx: 0,
       is_dev,
+      jsx_source,
+      strip_data_test_attrs,
+      derived_classes: vec![],
+      in_derived_constructor: false,
     },
     AlephJsxBuiltinModuleResolveFold { resolver: resolver },
   )
 }
 
 /// aleph.js jsx fold, core functions include:
-/// - add `__sourceFile` prop in development mode
+/// - add `__source` and `__self` props in development mode
 /// - resolve `a` to `Anchor`
 /// - resolve `head` to `Head`
 /// - resolve `link` to `Link`
 /// - resolve `style` to `Style`
 /// - resolve `script` to `Script`
 /// - optimize `img` in producation mode
+/// - strip the `data-testid` and `data-test` attributes with `strip_data_test_attrs`
 struct AlephJsxFold {
   resolver: Rc<RefCell<Resolver>>,
   source: Rc<SourceMap>,
   inline_style_idx: i32,
   is_dev: bool,
+  jsx_source: bool,
+  strip_data_test_attrs: bool,
+  /// whether the classes in the current scope extend a super class
+  derived_classes: Vec<bool>,
+  /// `this` can't be accessed before `super()` in the constructors of derived classes
+  in_derived_constructor: bool,
 }
 
 impl AlephJsxFold {
@@ -126,17 +139,19 @@ impl AlephJsxFold {
             if should_replace {
               let mut href_prop_index: i32 = -1;
               let mut href_prop_value = "";
+              let mut href_prop_span = None;
 
               for (i, attr) in el.attrs.iter().enumerate() {
                 match &attr {
                   JSXAttrOrSpread::JSXAttr(JSXAttr {
                     name: JSXAttrName::Ident(id),
-                    value: Some(JSXAttrValue::Lit(Lit::Str(Str { value, .. }))),
+                    value: Some(JSXAttrValue::Lit(Lit::Str(Str { value, span, .. }))),
                     ..
                   }) => match id.sym.as_ref() {
                     "href" => {
                       href_prop_index = i as i32;
                       href_prop_value = value.as_ref();
+                      href_prop_span = Some(*span);
                     }
                     _ => {}
                   },
@@ -145,7 +160,8 @@ impl AlephJsxFold {
               }
 
               let mut resolver = self.resolver.borrow_mut();
-              let (resolved_path, fixed_url) = resolver.resolve(href_prop_value, false);
+              let (resolved_path, fixed_url) =
+                resolver.resolve(href_prop_value, false, href_prop_span);
               resolver.add_extra_import(resolved_path.as_str());
 
               if href_prop_index >= 0 {
@@ -212,6 +228,11 @@ impl AlephJsxFold {
             resolver.dep_graph.push(DependencyDescriptor {
               specifier: "#".to_owned() + id.as_str(),
               is_dynamic: false,
+              span: None,
+              kind: None,
+              chunk_name: None,
+              reexport: false,
+              lazy: false,
             });
             resolver.used_builtin_jsx_tags.insert(name.into());
             el.name = JSXElementName::Ident(quote_ident!(rename_builtin_tag(name)));
@@ -228,11 +249,16 @@ impl AlephJsxFold {
       _ => {}
     };
 
+    if self.strip_data_test_attrs {
+      el.attrs.retain(|attr| !is_data_test_attr(attr));
+    }
+
     // copy from https://github.com/swc-project/swc/blob/master/ecmascript/transforms/src/react/jsx_src.rs
-    if self.is_dev {
+    if self.is_dev && self.jsx_source {
       let resolver = self.resolver.borrow_mut();
       match self.source.span_to_lines(el.span) {
         Ok(file_lines) => {
+          let loc = self.source.lookup_char_pos(el.span.lo);
           el.attrs.push(JSXAttrOrSpread::JSXAttr(JSXAttr {
             span: DUMMY_SP,
             name: JSXAttrName::Ident(quote_ident!("__source")),
@@ -258,6 +284,13 @@ impl AlephJsxFold {
                         value: (file_lines.lines[0].line_index + 1) as _,
                       }))),
                     }))),
+                    PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                      key: PropName::Ident(quote_ident!("columnNumber")),
+                      value: Box::new(Expr::Lit(Lit::Num(Number {
+                        span: DUMMY_SP,
+                        value: (loc.col.0 + 1) as _,
+                      }))),
+                    }))),
                   ],
                 }
                 .into(),
@@ -267,6 +300,17 @@ impl AlephJsxFold {
         }
         _ => {}
       };
+      // copy from https://github.com/swc-project/swc/blob/master/ecmascript/transforms/react/src/jsx_self/mod.rs
+      if !self.in_derived_constructor {
+        el.attrs.push(JSXAttrOrSpread::JSXAttr(JSXAttr {
+          span: DUMMY_SP,
+          name: JSXAttrName::Ident(quote_ident!("__self")),
+          value: Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+            span: DUMMY_SP,
+            expr: JSXExpr::Expr(Box::new(Expr::This(ThisExpr { span: DUMMY_SP }))),
+          })),
+        }));
+      }
     }
 
     (el, inline_style)
@@ -276,6 +320,32 @@ impl AlephJsxFold {
 impl Fold for AlephJsxFold {
   noop_fold_type!();
 
+  fn fold_class(&mut self, class: Class) -> Class {
+    let in_derived_constructor = self.in_derived_constructor;
+    self.in_derived_constructor = false;
+    self.derived_classes.push(class.super_class.is_some());
+    let class = class.fold_children_with(self);
+    self.derived_classes.pop();
+    self.in_derived_constructor = in_derived_constructor;
+    class
+  }
+
+  fn fold_constructor(&mut self, constructor: Constructor) -> Constructor {
+    let in_derived_constructor = self.in_derived_constructor;
+    self.in_derived_constructor = *self.derived_classes.last().unwrap_or(&false);
+    let constructor = constructor.fold_children_with(self);
+    self.in_derived_constructor = in_derived_constructor;
+    constructor
+  }
+
+  fn fold_function(&mut self, function: Function) -> Function {
+    let in_derived_constructor = self.in_derived_constructor;
+    self.in_derived_constructor = false;
+    let function = function.fold_children_with(self);
+    self.in_derived_constructor = in_derived_constructor;
+    function
+  }
+
   fn fold_jsx_element(&mut self, mut el: JSXElement) -> JSXElement {
     if el.span == DUMMY_SP {
       return el;
@@ -367,6 +437,7 @@ impl Fold for AlephJsxBuiltinModuleResolveFold {
       let (resolved_path, fixed_url) = resolver.resolve(
         format!("{}/framework/react/{}.ts", aleph_pkg_uri, name).as_str(),
         false,
+        None,
       );
       if resolver.bundle_mode && resolver.bundle_external.contains(fixed_url.as_str()) {
         items.push(ModuleItem::Stmt(Stmt::Decl(Decl::Var(VarDecl {
@@ -419,6 +490,21 @@ impl Fold for AlephJsxBuiltinModuleResolveFold {
   }
 }
 
+pub fn jsx_runtime_resolve_fold(resolver: Rc<RefCell<Resolver>>, import_source: &str) -> impl Fold {
+  import_resolve_fold(resolver, format!("{}/jsx-runtime", import_source).as_str())
+}
+
+/// checks if the attribute is a test-only attribute like `data-testid="submit"`.
+fn is_data_test_attr(attr: &JSXAttrOrSpread) -> bool {
+  match attr {
+    JSXAttrOrSpread::JSXAttr(JSXAttr {
+      name: JSXAttrName::Ident(id),
+      ..
+    }) => matches!(id.sym.as_ref(), "data-testid" | "data-test"),
+    _ => false,
+  }
+}
+
 fn rename_builtin_tag(name: &str) -> String {
   let mut c = name.chars();
   let mut name = match c.next() {
@@ -433,8 +519,10 @@ fn rename_builtin_tag(name: &str) -> String {
 
 #[cfg(test)]
 mod tests {
-  use crate::resolve::DependencyDescriptor;
-  use crate::swc::st;
+  use super::*;
+  use crate::import_map::ImportHashMap;
+  use crate::resolve::SourceSpan;
+  use crate::swc::{st, test_resolver, EmitOptions, JsxRuntime, SWC};
 
   #[test]
   fn resolve_jsx_builtin_tags() {
@@ -489,6 +577,13 @@ mod tests {
       .as_str()
     ));
     assert!(code.contains("React.createElement(__ALEPH_Script,"));
+    let span_of = |s: &str| {
+      let start = source.find(s).unwrap() as u32;
+      Some(SourceSpan {
+        start,
+        end: start + s.len() as u32,
+      })
+    };
     let r = resolver.borrow_mut();
     assert_eq!(
       r.dep_graph,
@@ -496,26 +591,56 @@ mod tests {
         DependencyDescriptor {
           specifier: "https://esm.sh/react".into(),
           is_dynamic: false,
+          span: span_of("\"https://esm.sh/react\""),
+          kind: None,
+          chunk_name: None,
+          reexport: false,
+          lazy: false,
         },
         DependencyDescriptor {
           specifier: "/style/index.css".into(),
           is_dynamic: false,
+          span: span_of("\"../style/index.css\""),
+          kind: None,
+          chunk_name: None,
+          reexport: false,
+          lazy: false,
         },
         DependencyDescriptor {
           specifier: "https://deno.land/x/aleph@v0.3.0/framework/react/head.ts".into(),
           is_dynamic: false,
+          span: None,
+          kind: None,
+          chunk_name: None,
+          reexport: false,
+          lazy: false,
         },
         DependencyDescriptor {
           specifier: "https://deno.land/x/aleph@v0.3.0/framework/react/stylelink.ts".into(),
           is_dynamic: false,
+          span: None,
+          kind: None,
+          chunk_name: None,
+          reexport: false,
+          lazy: false,
         },
         DependencyDescriptor {
           specifier: "https://deno.land/x/aleph@v0.3.0/framework/react/anchor.ts".into(),
           is_dynamic: false,
+          span: None,
+          kind: None,
+          chunk_name: None,
+          reexport: false,
+          lazy: false,
         },
         DependencyDescriptor {
           specifier: "https://deno.land/x/aleph@v0.3.0/framework/react/script.ts".into(),
           is_dynamic: false,
+          span: None,
+          kind: None,
+          chunk_name: None,
+          reexport: false,
+          lazy: false,
         }
       ]
     );
@@ -552,4 +677,128 @@ mod tests {
     let r = resolver.borrow_mut();
     assert!(r.inline_styles.len() == 2);
   }
+
+  #[test]
+  fn jsx_automatic_runtime() {
+    let source = r#"
+      export default function Index() {
+        return (
+          <>
+            <h1 className="title">Hello World</h1>
+          </>
+        )
+      }
+    "#;
+    let module = SWC::parse("/pages/index.tsx", source, None).expect("could not parse module");
+    let resolver = Rc::new(RefCell::new(Resolver::new(
+      "/pages/index.tsx",
+      ImportHashMap::default(),
+      None,
+      Some("17.0.1".into()),
+      false,
+      vec![],
+    )));
+    let (code, _) = module
+      .transform(
+        resolver.clone(),
+        &EmitOptions {
+          jsx_runtime: JsxRuntime::Automatic,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains(
+      "import { jsx as _jsx, Fragment as _Fragment } from \"../-/esm.sh/react@17.0.1/jsx-runtime.js\""
+    ));
+    assert!(code.contains("_jsx(_Fragment, {"));
+    assert!(!code.contains("React.createElement"));
+    let r = resolver.borrow();
+    assert!(r
+      .dep_graph
+      .iter()
+      .any(|dep| dep.specifier.eq("https://esm.sh/react@17.0.1/jsx-runtime")));
+  }
+
+  #[test]
+  fn jsx_source_annotations() {
+    let source = r#"
+      export default function Index() {
+        return <h1 className="title">Hello World</h1>
+      }
+      class Page extends React.Component {
+        constructor(props) {
+          super(props)
+          this.title = <title>Page</title>
+        }
+      }
+    "#;
+    let transform = |is_dev: bool, jsx_source: bool| {
+      let module = SWC::parse("/pages/index.tsx", source, None).expect("could not parse module");
+      let resolver = test_resolver("/pages/index.tsx");
+      let (code, _) = module
+        .transform(
+          resolver,
+          &EmitOptions {
+            is_dev,
+            jsx_source,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      code
+    };
+
+    let code = transform(true, true);
+    assert!(code.contains("fileName: \"/pages/index.tsx\""));
+    assert!(code.contains("lineNumber: 3"));
+    assert!(code.contains("columnNumber: 16"));
+    assert!(code.contains("lineNumber: 8"));
+    assert_eq!(code.matches("__self: this").count(), 1);
+
+    let code = transform(true, false);
+    assert!(!code.contains("__source"));
+    assert!(!code.contains("__self"));
+
+    let code = transform(false, true);
+    assert!(!code.contains("__source"));
+    assert!(!code.contains("__self"));
+  }
+
+  #[test]
+  fn strip_data_test_attrs() {
+    let source = r#"
+      export default function Form({ id }) {
+        return <form data-testid="form" data-id={id}><button data-test>OK</button></form>
+      }
+    "#;
+    let transform = |is_dev: bool| {
+      let module = SWC::parse("/pages/form.tsx", source, None).expect("could not parse module");
+      let resolver = test_resolver("/pages/form.tsx");
+      let (code, _) = module
+        .transform(
+          resolver,
+          &EmitOptions {
+            is_dev,
+            jsx_source: false,
+            strip_data_test_attrs: true,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      code
+    };
+
+    let code = transform(false);
+    assert!(!code.contains("data-testid"));
+    assert!(!code.contains("data-test\""));
+    assert!(code.contains("\"data-id\": id"));
+    assert!(code.contains("React.createElement(\"button\", null, \"OK\")"));
+
+    let code = transform(true);
+    assert!(code.contains("\"data-testid\": \"form\""));
+    assert!(code.contains("\"data-test\": true"));
+  }
 }
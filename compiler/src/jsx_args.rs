@@ -0,0 +1,185 @@
+use serde::{Deserialize, Serialize};
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// How the `key` or the `ref` prop of a JSX element is passed to the factory of the
+/// classic JSX runtime.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsxPropMode {
+  /// passes the prop in the props object, like `React.createElement`.
+  #[default]
+  Props,
+  /// extracts the prop as a separate arg after the props object, for the view
+  /// libraries whose factory takes it out of the props.
+  Arg,
+}
+
+/// Creates a fold that extracts the `key` and the `ref` props of the JSX elements
+/// with the `Arg` mode before the JSX is transformed by the classic runtime. The
+/// extracted props are moved to the first children, as the children are passed to the
+/// factory in order, `void 0` is passed if the element has no such a prop:
+///
+/// ```jsx
+/// <li key={id} ref={el}>{name}</li>
+/// <></>
+/// ```
+///
+/// is transformed to (with the `Arg` mode for both):
+///
+/// ```js
+/// h("li", null, id, el, name)
+/// h(Fragment, null, void 0, void 0)
+/// ```
+///
+/// The props in the spread attributes like `{...props}` are not extracted.
+pub fn jsx_args_fold(key: JsxPropMode, ref_: JsxPropMode) -> impl Fold {
+  JsxArgsFold { key, ref_ }
+}
+
+struct JsxArgsFold {
+  key: JsxPropMode,
+  ref_: JsxPropMode,
+}
+
+impl JsxArgsFold {
+  /// the names of the props that are extracted, in the order of the args.
+  fn arg_names(&self) -> Vec<&'static str> {
+    let mut names = vec![];
+    if self.key == JsxPropMode::Arg {
+      names.push("key");
+    }
+    if self.ref_ == JsxPropMode::Arg {
+      names.push("ref");
+    }
+    names
+  }
+}
+
+impl Fold for JsxArgsFold {
+  noop_fold_type!();
+
+  fn fold_jsx_element(&mut self, el: JSXElement) -> JSXElement {
+    let mut el = el.fold_children_with(self);
+    let names = self.arg_names();
+    let mut args: Vec<Option<JSXElementChild>> = names.iter().map(|_| None).collect();
+    el.opening.attrs.retain(|attr| match attr {
+      JSXAttrOrSpread::JSXAttr(JSXAttr {
+        name: JSXAttrName::Ident(name),
+        value,
+        ..
+      }) => match names.iter().position(|n| name.sym.as_ref().eq(*n)) {
+        Some(i) => {
+          args[i] = Some(attr_value_to_child(value.clone()));
+          false
+        }
+        None => true,
+      },
+      _ => true,
+    });
+    let children = args
+      .into_iter()
+      .map(|arg| arg.unwrap_or_else(void_child))
+      .chain(el.children)
+      .collect();
+    el.children = children;
+    el
+  }
+
+  fn fold_jsx_fragment(&mut self, fragment: JSXFragment) -> JSXFragment {
+    let mut fragment = fragment.fold_children_with(self);
+    let children = self
+      .arg_names()
+      .iter()
+      .map(|_| void_child())
+      .chain(fragment.children)
+      .collect();
+    fragment.children = children;
+    fragment
+  }
+}
+
+fn attr_value_to_child(value: Option<JSXAttrValue>) -> JSXElementChild {
+  match value {
+    // match: <li key />
+    None => expr_child(Expr::Lit(Lit::Bool(Bool {
+      span: DUMMY_SP,
+      value: true,
+    }))),
+    Some(JSXAttrValue::Lit(lit)) => expr_child(Expr::Lit(lit)),
+    Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+      expr: JSXExpr::Expr(expr),
+      ..
+    })) => expr_child(*expr),
+    Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+      expr: JSXExpr::JSXEmptyExpr(_),
+      ..
+    })) => void_child(),
+    Some(JSXAttrValue::JSXElement(el)) => JSXElementChild::JSXElement(el),
+    Some(JSXAttrValue::JSXFragment(fragment)) => JSXElementChild::JSXFragment(fragment),
+  }
+}
+
+fn expr_child(expr: Expr) -> JSXElementChild {
+  JSXElementChild::JSXExprContainer(JSXExprContainer {
+    span: DUMMY_SP,
+    expr: JSXExpr::Expr(Box::new(expr)),
+  })
+}
+
+// match: void 0
+fn void_child() -> JSXElementChild {
+  expr_child(Expr::Unary(UnaryExpr {
+    span: DUMMY_SP,
+    op: UnaryOp::Void,
+    arg: Box::new(Expr::Lit(Lit::Num(Number {
+      span: DUMMY_SP,
+      value: 0.0,
+    }))),
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{transform, Options};
+
+  #[test]
+  fn jsx_args() {
+    let source = r#"
+      export default ({ items, list }) => (
+        <ul ref={list}>
+          {items.map(({ id, name }) => <li key={id}>{name}</li>)}
+          <>{"end"}</>
+        </ul>
+      )
+    "#;
+    let options: Options = serde_json::from_str(
+      r#"{"swcOptions":{"jsxFactory":"View.h","jsxFragmentFactory":"View.Fragment","jsxKey":"arg","jsxRef":"arg"}}"#,
+    )
+    .unwrap();
+    let output = transform("/app.jsx", source, &options).expect("could not transform module");
+    println!("{}", output.code);
+    assert!(output
+      .code
+      .contains("View.h(\"ul\", null, void 0, list, items.map("));
+    assert!(output
+      .code
+      .contains("View.h(\"li\", null, id, void 0, name)"));
+    assert!(output
+      .code
+      .contains("View.h(View.Fragment, null, void 0, void 0, \"end\")"));
+
+    let options: Options =
+      serde_json::from_str(r#"{"swcOptions":{"jsxFactory":"h","jsxKey":"arg"}}"#).unwrap();
+    let output = transform("/app.jsx", source, &options).expect("could not transform module");
+    assert!(output
+      .code
+      .contains("h(\"ul\", {\n        ref: list\n    }, void 0,"));
+    assert!(output.code.contains("h(\"li\", null, id, name)"));
+
+    let options: Options = serde_json::from_str("{}").unwrap();
+    let output = transform("/app.jsx", source, &options).expect("could not transform module");
+    assert!(output.code.contains("key: id"));
+  }
+}
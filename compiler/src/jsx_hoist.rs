@@ -0,0 +1,242 @@
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::private_ident;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// Creates a fold that hoists the static JSX elements of the functions to the
+/// constants of the module scope, like `@babel/plugin-transform-react-constant-elements`:
+///
+/// ```jsx
+/// function App({ children }) {
+///   return <main><h1>Hello</h1>{children}</main>
+/// }
+/// ```
+///
+/// is transformed to:
+///
+/// ```jsx
+/// const _jsx_static1 = <h1>Hello</h1>
+/// function App({ children }) {
+///   return <main>{_jsx_static1}{children}</main>
+/// }
+/// ```
+///
+/// An element is static if it's an intrinsic element with the literal props (without
+/// the spread props or a `ref`) and the static children, so the element is created
+/// once instead of on each render.
+pub fn jsx_hoist_fold() -> impl Fold {
+  JsxHoistFold {
+    depth: 0,
+    hoisted: vec![],
+  }
+}
+
+struct JsxHoistFold {
+  /// the depth of the functions, the elements of the module scope are created once
+  /// already.
+  depth: usize,
+  hoisted: Vec<VarDeclarator>,
+}
+
+impl JsxHoistFold {
+  fn hoist(&mut self, el: Box<JSXElement>) -> Ident {
+    let id = private_ident!(format!("_jsx_static{}", self.hoisted.len() + 1));
+    self.hoisted.push(VarDeclarator {
+      span: DUMMY_SP,
+      name: Pat::Ident(BindingIdent {
+        id: id.clone(),
+        type_ann: None,
+      }),
+      init: Some(Box::new(Expr::JSXElement(el))),
+      definite: false,
+    });
+    id
+  }
+}
+
+impl Fold for JsxHoistFold {
+  noop_fold_type!();
+
+  fn fold_module(&mut self, module: Module) -> Module {
+    let mut module = module.fold_children_with(self);
+    if !self.hoisted.is_empty() {
+      // after the imports of the jsx factory
+      let index = module
+        .body
+        .iter()
+        .position(|item| !is_import_or_directive(item))
+        .unwrap_or(module.body.len());
+      module.body.insert(
+        index,
+        ModuleItem::Stmt(Stmt::Decl(Decl::Var(VarDecl {
+          span: DUMMY_SP,
+          kind: VarDeclKind::Const,
+          declare: false,
+          decls: self.hoisted.drain(..).collect(),
+        }))),
+      );
+    }
+    module
+  }
+
+  fn fold_function(&mut self, func: Function) -> Function {
+    self.depth += 1;
+    let func = func.fold_children_with(self);
+    self.depth -= 1;
+    func
+  }
+
+  fn fold_arrow_expr(&mut self, arrow: ArrowExpr) -> ArrowExpr {
+    self.depth += 1;
+    let arrow = arrow.fold_children_with(self);
+    self.depth -= 1;
+    arrow
+  }
+
+  fn fold_constructor(&mut self, constructor: Constructor) -> Constructor {
+    self.depth += 1;
+    let constructor = constructor.fold_children_with(self);
+    self.depth -= 1;
+    constructor
+  }
+
+  fn fold_expr(&mut self, expr: Expr) -> Expr {
+    match expr {
+      Expr::JSXElement(el) if self.depth > 0 && is_static_element(&el) => {
+        Expr::Ident(self.hoist(el))
+      }
+      _ => expr.fold_children_with(self),
+    }
+  }
+
+  // match: <main><h1>Hello</h1>{children}</main>
+  fn fold_jsx_element_child(&mut self, child: JSXElementChild) -> JSXElementChild {
+    match child {
+      JSXElementChild::JSXElement(el) if self.depth > 0 && is_static_element(&el) => {
+        JSXElementChild::JSXExprContainer(JSXExprContainer {
+          span: DUMMY_SP,
+          expr: JSXExpr::Expr(Box::new(Expr::Ident(self.hoist(el)))),
+        })
+      }
+      _ => child.fold_children_with(self),
+    }
+  }
+}
+
+fn is_import_or_directive(item: &ModuleItem) -> bool {
+  match item {
+    ModuleItem::ModuleDecl(ModuleDecl::Import(_)) => true,
+    ModuleItem::Stmt(Stmt::Expr(ExprStmt { expr, .. })) => {
+      matches!(expr.as_ref(), Expr::Lit(Lit::Str(_)))
+    }
+    _ => false,
+  }
+}
+
+/// checks if the element is an intrinsic element like `<p>` without the dynamic
+/// props or children.
+fn is_static_element(el: &JSXElement) -> bool {
+  let is_intrinsic = match &el.opening.name {
+    JSXElementName::Ident(id) => id.sym.starts_with(|c: char| c.is_ascii_lowercase()),
+    JSXElementName::JSXNamespacedName(_) => true,
+    JSXElementName::JSXMemberExpr(_) => false,
+  };
+  is_intrinsic
+    && el.opening.attrs.iter().all(|attr| match attr {
+      JSXAttrOrSpread::JSXAttr(JSXAttr { name, value, .. }) => {
+        let is_ref = matches!(name, JSXAttrName::Ident(id) if id.sym.eq("ref"));
+        !is_ref
+          && match value {
+            None | Some(JSXAttrValue::Lit(_)) => true,
+            Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+              expr: JSXExpr::Expr(expr),
+              ..
+            })) => is_literal(expr),
+            _ => false,
+          }
+      }
+      JSXAttrOrSpread::SpreadElement(_) => false,
+    })
+    && el.children.iter().all(is_static_child)
+}
+
+fn is_static_child(child: &JSXElementChild) -> bool {
+  match child {
+    JSXElementChild::JSXText(_) => true,
+    JSXElementChild::JSXExprContainer(JSXExprContainer { expr, .. }) => match expr {
+      JSXExpr::JSXEmptyExpr(_) => true,
+      JSXExpr::Expr(expr) => is_literal(expr),
+    },
+    JSXElementChild::JSXElement(el) => is_static_element(el),
+    JSXElementChild::JSXFragment(fragment) => fragment.children.iter().all(is_static_child),
+    JSXElementChild::JSXSpreadChild(_) => false,
+  }
+}
+
+fn is_literal(expr: &Expr) -> bool {
+  matches!(
+    expr,
+    Expr::Lit(Lit::Str(_))
+      | Expr::Lit(Lit::Num(_))
+      | Expr::Lit(Lit::Bool(_))
+      | Expr::Lit(Lit::Null(_))
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::swc::{test_resolver, EmitOptions, SWC};
+
+  fn transform(source: &str, is_dev: bool) -> String {
+    let module = SWC::parse("/pages/index.tsx", source, None).expect("could not parse module");
+    let resolver = test_resolver("/pages/index.tsx");
+    let (code, _) = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          is_dev,
+          hoist_static_jsx: true,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    code
+  }
+
+  #[test]
+  fn hoist_static_jsx() {
+    let source = r#"
+      import React from "https://esm.sh/react"
+      const Logo = <img src="/logo.svg" />
+      export default function Index({ title, onClick }) {
+        const ref = React.useRef()
+        return (
+          <main>
+            <h1 className="title" hidden={false}>Hello {"world"}</h1>
+            <p>{title}</p>
+            <input ref={ref} />
+            <button style={{ color: "red" }} onClick={onClick}>OK</button>
+            <Logo />
+            <ul><li>1</li><li>2</li></ul>
+          </main>
+        )
+      }
+    "#;
+    let code = transform(source, false);
+    assert!(code.contains(
+      "const _jsx_static1 = /*#__PURE__*/ React.createElement(\"h1\", {\n    className: \"title\",\n    hidden: false\n}, \"Hello \", \"world\")"
+    ));
+    assert!(code.contains(
+      "_jsx_static2 = /*#__PURE__*/ React.createElement(\"ul\", null, /*#__PURE__*/ React.createElement(\"li\", null, \"1\"), /*#__PURE__*/ React.createElement(\"li\", null, \"2\"))"
+    ));
+    assert!(code.contains("React.createElement(\"main\", null, _jsx_static1, "));
+    assert!(code.contains("React.createElement(\"p\", null, title)"));
+    assert!(code.contains("React.createElement(\"input\", {\n        ref: ref\n    })"));
+    assert!(code.contains("onClick: onClick\n    }, \"OK\")"));
+    assert!(code.contains("), _jsx_static2)"));
+    // the elements of the module scope are kept
+    assert!(code.contains("const Logo = /*#__PURE__*/ React.createElement(\"img\""));
+    assert!(!transform(source, true).contains("_jsx_static"));
+  }
+}
@@ -0,0 +1,156 @@
+use crate::swc::{EmitOptions, JsxRuntime};
+
+use swc_common::comments::{CommentKind, SingleThreadedComments};
+use swc_ecma_ast::Module;
+
+/// The JSX pragmas in the leading comments of a module:
+///
+/// ```js
+/// /** @jsx h */
+/// /** @jsxFrag Fragment */
+/// /** @jsxImportSource https://esm.sh/preact */
+/// ```
+#[derive(Debug, Default, PartialEq)]
+pub struct JsxPragmas {
+  pub runtime: Option<JsxRuntime>,
+  pub factory: Option<String>,
+  pub fragment_factory: Option<String>,
+  pub import_source: Option<String>,
+}
+
+/// parses the pragmas of the block comments before the first statement, where the
+/// JSX transform of swc reads them.
+pub fn parse_jsx_pragmas(comments: &SingleThreadedComments, module: &Module) -> JsxPragmas {
+  let mut pragmas = JsxPragmas::default();
+  comments.with_leading(module.span.lo, |comments| {
+    for comment in comments.iter() {
+      if comment.kind != CommentKind::Block {
+        continue;
+      }
+      for line in comment.text.lines() {
+        let line = line.trim().trim_start_matches('*').trim();
+        let (name, value) = match line.find(char::is_whitespace) {
+          Some(index) => (&line[..index], line[index..].trim()),
+          None => continue,
+        };
+        // the comment may have other text after the value
+        let value = value
+          .split_whitespace()
+          .next()
+          .unwrap_or_default()
+          .to_owned();
+        match name {
+          "@jsx" => pragmas.factory = Some(value),
+          "@jsxFrag" => pragmas.fragment_factory = Some(value),
+          "@jsxImportSource" => pragmas.import_source = Some(value),
+          "@jsxRuntime" => match value.as_str() {
+            "classic" => pragmas.runtime = Some(JsxRuntime::Classic),
+            "automatic" => pragmas.runtime = Some(JsxRuntime::Automatic),
+            _ => {}
+          },
+          _ => {}
+        }
+      }
+    }
+  });
+  pragmas
+}
+
+/// returns the options with the JSX pragmas of the module, which override the
+/// `jsx_factory`, `jsx_fragment_factory` and `jsx_import_source` options. Like deno,
+/// the `@jsxImportSource` pragma switches the module to the automatic runtime and
+/// the `@jsx` pragma to the classic runtime.
+pub fn apply_jsx_pragmas(
+  comments: &SingleThreadedComments,
+  module: &Module,
+  options: &EmitOptions,
+) -> Option<EmitOptions> {
+  let pragmas = parse_jsx_pragmas(comments, module);
+  if pragmas == JsxPragmas::default() {
+    return None;
+  }
+  let runtime = if let Some(runtime) = &pragmas.runtime {
+    runtime.clone()
+  } else if pragmas.factory.is_some() || pragmas.fragment_factory.is_some() {
+    JsxRuntime::Classic
+  } else if pragmas.import_source.is_some() {
+    JsxRuntime::Automatic
+  } else {
+    options.jsx_runtime.clone()
+  };
+  Some(EmitOptions {
+    jsx_runtime: runtime,
+    jsx_factory: pragmas
+      .factory
+      .unwrap_or_else(|| options.jsx_factory.clone()),
+    jsx_fragment_factory: pragmas
+      .fragment_factory
+      .unwrap_or_else(|| options.jsx_fragment_factory.clone()),
+    jsx_import_source: pragmas
+      .import_source
+      .unwrap_or_else(|| options.jsx_import_source.clone()),
+    ..options.clone()
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::swc::{st, SWC};
+
+  #[test]
+  fn parse_pragmas() {
+    let source = r#"
+      /**
+       * @jsx h
+       * @jsxFrag Fragment
+       */
+      import { h, Fragment } from "https://esm.sh/preact"
+    "#;
+    let module = SWC::parse("/app.tsx", source, None).expect("could not parse module");
+    assert_eq!(
+      parse_jsx_pragmas(&module.comments, &module.module),
+      JsxPragmas {
+        runtime: None,
+        factory: Some("h".into()),
+        fragment_factory: Some("Fragment".into()),
+        import_source: None,
+      }
+    );
+    let source = r#"
+      import { h } from "https://esm.sh/preact"
+      /** @jsx h */
+      export default () => <p />
+    "#;
+    let module = SWC::parse("/app.tsx", source, None).expect("could not parse module");
+    assert_eq!(
+      parse_jsx_pragmas(&module.comments, &module.module),
+      JsxPragmas::default()
+    );
+  }
+
+  #[test]
+  fn transform_with_jsx_pragmas() {
+    let source = r#"
+      /** @jsx h */
+      /** @jsxFrag Fragment */
+      import { h, Fragment } from "https://esm.sh/preact"
+      export default function App() {
+        return <><p>Hi</p></>
+      }
+    "#;
+    let (code, _) = st("/app.tsx", source, false);
+    assert!(code.contains("h(Fragment, null, h(\"p\", null, \"Hi\"))"));
+    assert!(!code.contains("React.createElement"));
+
+    let source = r#"
+      /** @jsxImportSource https://esm.sh/preact */
+      export default function App() {
+        return <p>Hi</p>
+      }
+    "#;
+    let (code, _) = st("/app.tsx", source, false);
+    assert!(code.contains("import { jsx as _jsx } from \"./-/esm.sh/preact/jsx-runtime.js\""));
+    assert!(code.contains("_jsx(\"p\", {"));
+  }
+}
@@ -0,0 +1,238 @@
+use serde::{Deserialize, Serialize};
+use swc_ecma_ast::*;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// How the whitespace of the JSX text is normalized, the server and the client must
+/// agree on it to hydrate the text nodes.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsxWhitespace {
+  /// collapses every run of the whitespace into a space, the behavior of swc.
+  #[default]
+  Collapse,
+  /// trims the lines and joins the non-empty lines with a space, the spaces inside
+  /// of a line are kept, like `@babel/plugin-transform-react-jsx`.
+  Babel,
+  /// like `Babel`, but the unicode spaces (like the no-break space) are trimmed and the
+  /// leading spaces of the first line are kept, like the JSX transform of tsc.
+  TypeScript,
+}
+
+/// Creates a fold that normalizes the JSX text children with the `whitespace` mode
+/// before the JSX is transformed, the normalized text is kept as a string literal
+/// child so the JSX transform of swc doesn't collapse it again:
+///
+/// ```jsx
+/// <p>
+///   Hello,  {name}
+/// </p>
+/// ```
+///
+/// is transformed to (with the `Babel` mode):
+///
+/// ```jsx
+/// <p>{"Hello,  "}{name}</p>
+/// ```
+///
+/// The empty string literal children like `{""}` are dropped with `drop_empty`.
+pub fn jsx_whitespace_fold(whitespace: JsxWhitespace, drop_empty: bool) -> impl Fold {
+  JsxWhitespaceFold {
+    whitespace,
+    drop_empty,
+  }
+}
+
+struct JsxWhitespaceFold {
+  whitespace: JsxWhitespace,
+  drop_empty: bool,
+}
+
+impl JsxWhitespaceFold {
+  fn normalize_children(&self, children: Vec<JSXElementChild>) -> Vec<JSXElementChild> {
+    children
+      .into_iter()
+      .filter_map(|child| match child {
+        JSXElementChild::JSXText(text) => {
+          let value = match self.whitespace {
+            JsxWhitespace::Collapse => return Some(JSXElementChild::JSXText(text)),
+            JsxWhitespace::Babel => babel_jsx_text(text.value.as_ref()),
+            JsxWhitespace::TypeScript => typescript_jsx_text(text.value.as_ref()),
+          }?;
+          Some(JSXElementChild::JSXExprContainer(JSXExprContainer {
+            span: text.span,
+            expr: JSXExpr::Expr(Box::new(Expr::Lit(Lit::Str(Str {
+              span: text.span,
+              value: value.into(),
+              has_escape: false,
+              kind: StrKind::Synthesized,
+            })))),
+          }))
+        }
+        // match: {""}
+        JSXElementChild::JSXExprContainer(JSXExprContainer {
+          expr: JSXExpr::Expr(expr),
+          ..
+        }) if self.drop_empty && is_empty_str(&expr) => None,
+        _ => Some(child),
+      })
+      .collect()
+  }
+}
+
+impl Fold for JsxWhitespaceFold {
+  noop_fold_type!();
+
+  fn fold_jsx_element(&mut self, el: JSXElement) -> JSXElement {
+    let el = el.fold_children_with(self);
+    JSXElement {
+      children: self.normalize_children(el.children),
+      ..el
+    }
+  }
+
+  fn fold_jsx_fragment(&mut self, fragment: JSXFragment) -> JSXFragment {
+    let fragment = fragment.fold_children_with(self);
+    JSXFragment {
+      children: self.normalize_children(fragment.children),
+      ..fragment
+    }
+  }
+}
+
+fn is_empty_str(expr: &Expr) -> bool {
+  match expr {
+    Expr::Lit(Lit::Str(s)) => s.value.is_empty(),
+    Expr::Tpl(Tpl { exprs, quasis, .. }) => {
+      exprs.is_empty() && quasis.iter().all(|quasi| quasi.raw.value.is_empty())
+    }
+    Expr::Paren(ParenExpr { expr, .. }) => is_empty_str(expr),
+    _ => false,
+  }
+}
+
+/// the `cleanJSXElementLiteralChild` of babel, returns `None` if the text is dropped.
+fn babel_jsx_text(text: &str) -> Option<String> {
+  let text = text.replace("\r\n", "\n").replace('\r', "\n");
+  let lines: Vec<&str> = text.split('\n').collect();
+  let last_non_empty_line = lines
+    .iter()
+    .rposition(|line| line.chars().any(|c| c != ' ' && c != '\t'))
+    .unwrap_or(0);
+  let mut output = String::new();
+  for (i, line) in lines.iter().enumerate() {
+    let line = line.replace('\t', " ");
+    let mut trimmed = line.as_str();
+    if i > 0 {
+      trimmed = trimmed.trim_start_matches(' ');
+    }
+    if i < lines.len() - 1 {
+      trimmed = trimmed.trim_end_matches(' ');
+    }
+    if !trimmed.is_empty() {
+      output.push_str(trimmed);
+      if i != last_non_empty_line {
+        output.push(' ');
+      }
+    }
+  }
+  if output.is_empty() {
+    None
+  } else {
+    Some(output)
+  }
+}
+
+/// the `fixupWhitespaceAndDecodeEntities` of tsc, the entities are decoded by the
+/// parser already. returns `None` if the text is dropped.
+fn typescript_jsx_text(text: &str) -> Option<String> {
+  let mut output: Option<String> = None;
+  let mut add_line = |line: &str| {
+    output = Some(match output.take() {
+      Some(output) => output + " " + line,
+      None => line.to_owned(),
+    });
+  };
+  let mut first_non_whitespace = Some(0);
+  let mut last_non_whitespace = None;
+  for (i, c) in text.char_indices() {
+    if is_ts_line_break(c) {
+      if let (Some(first), Some(last)) = (first_non_whitespace, last_non_whitespace) {
+        add_line(&text[first..last]);
+      }
+      first_non_whitespace = None;
+    } else if !is_ts_single_line_whitespace(c) {
+      last_non_whitespace = Some(i + c.len_utf8());
+      if first_non_whitespace.is_none() {
+        first_non_whitespace = Some(i);
+      }
+    }
+  }
+  if let Some(first) = first_non_whitespace {
+    add_line(&text[first..]);
+  }
+  output
+}
+
+fn is_ts_line_break(c: char) -> bool {
+  matches!(c, '\n' | '\r' | '\u{2028}' | '\u{2029}')
+}
+
+fn is_ts_single_line_whitespace(c: char) -> bool {
+  matches!(
+    c,
+    ' ' | '\t' | '\u{0B}' | '\u{0C}' | '\u{85}' | '\u{A0}' | '\u{1680}' | '\u{2000}'
+      ..='\u{200B}' | '\u{202F}' | '\u{205F}' | '\u{3000}' | '\u{FEFF}'
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::swc::{test_resolver, EmitOptions, SWC};
+
+  fn t(whitespace: JsxWhitespace, drop_empty: bool) -> String {
+    let source = "export default () => (\n  <p>\n    Hello,  {name}{\"\"}\n    <b>\n\u{a0}bold\u{a0}\n</b>\n    <>  welcome\t\n\n  home  </>\n  </p>\n)";
+    let module = SWC::parse("/app.jsx", source, None).expect("could not parse module");
+    let resolver = test_resolver("/app.jsx");
+    let (code, _) = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          jsx_whitespace: whitespace,
+          jsx_drop_empty_children: drop_empty,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    code
+  }
+
+  #[test]
+  fn jsx_whitespace() {
+    let code = t(JsxWhitespace::Collapse, false);
+    assert!(code.contains("\"Hello, \", name, \"\""));
+    assert!(code.contains("React.createElement(\"b\", null, \"bold\")"));
+    assert!(code.contains("\" welcome home \""));
+
+    let code = t(JsxWhitespace::Babel, false);
+    assert!(code.contains("\"Hello,  \", name, \"\""));
+    assert!(code.contains("React.createElement(\"b\", null, \"\\xa0bold\\xa0\")"));
+    assert!(code.contains("\"  welcome home  \""));
+
+    let code = t(JsxWhitespace::TypeScript, true);
+    assert!(code.contains("\"Hello,  \", name,"));
+    assert!(!code.contains("\"\""));
+    assert!(code.contains("React.createElement(\"b\", null, \"bold\")"));
+    assert!(code.contains("\"  welcome home  \""));
+
+    assert_eq!(babel_jsx_text("\n  a  \n  b\t\n  "), Some("a b".into()));
+    assert_eq!(babel_jsx_text("\n   \n"), None);
+    assert_eq!(
+      typescript_jsx_text("\u{a0}a\u{a0}\n\u{a0}b\u{a0}"),
+      Some("\u{a0}a b\u{a0}".into())
+    );
+    assert_eq!(typescript_jsx_text("  \n  "), None);
+    assert_eq!(typescript_jsx_text("   "), Some("   ".into()));
+  }
+}
@@ -0,0 +1,295 @@
+use std::{cell::RefCell, collections::HashMap, rc::Rc};
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::quote_ident;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// the original names of the functions and the classes, keyed by the span of the
+/// identifiers which is kept when the identifiers are renamed.
+type OriginalNames = Rc<RefCell<HashMap<(u32, u32), String>>>;
+
+/// Creates the folds that keep the `name` of the functions and the classes that are
+/// renamed by the transforms, like the hygiene of a bundle that renames the
+/// conflicting `function helper() {}` to `helper1`. The first fold records the
+/// original names before the transforms, the second one defines the names of the
+/// renamed declarations after the hygiene:
+///
+/// ```js
+/// function helper1() {}
+/// Object.defineProperty(helper1, "name", { value: "helper", configurable: true });
+/// ```
+///
+/// The classes with a static `name` member are skipped.
+pub fn keep_names_fold(keep_fn_names: bool, keep_class_names: bool) -> (impl Fold, impl Fold) {
+  let names: OriginalNames = Rc::new(RefCell::new(HashMap::new()));
+  (
+    OriginalNamesCollector {
+      names: names.clone(),
+      keep_fn_names,
+      keep_class_names,
+    },
+    KeepNamesFold { names },
+  )
+}
+
+struct OriginalNamesCollector {
+  names: OriginalNames,
+  keep_fn_names: bool,
+  keep_class_names: bool,
+}
+
+impl OriginalNamesCollector {
+  fn record(&self, ident: &Ident) {
+    // the generated identifiers have no position
+    if ident.span.lo.0 == 0 && ident.span.hi.0 == 0 {
+      return;
+    }
+    self.names.borrow_mut().insert(
+      (ident.span.lo.0, ident.span.hi.0),
+      ident.sym.as_ref().into(),
+    );
+  }
+}
+
+impl Fold for OriginalNamesCollector {
+  noop_fold_type!();
+
+  fn fold_fn_decl(&mut self, decl: FnDecl) -> FnDecl {
+    if self.keep_fn_names {
+      self.record(&decl.ident);
+    }
+    decl.fold_children_with(self)
+  }
+
+  fn fold_class_decl(&mut self, decl: ClassDecl) -> ClassDecl {
+    if self.keep_class_names && !has_static_name(&decl.class) {
+      self.record(&decl.ident);
+    }
+    decl.fold_children_with(self)
+  }
+
+  fn fold_default_decl(&mut self, decl: DefaultDecl) -> DefaultDecl {
+    match &decl {
+      // match: export default function foo() {}
+      DefaultDecl::Fn(FnExpr {
+        ident: Some(ident), ..
+      }) if self.keep_fn_names => self.record(ident),
+      // match: export default class Foo {}
+      DefaultDecl::Class(ClassExpr {
+        ident: Some(ident),
+        class,
+      }) if self.keep_class_names && !has_static_name(class) => self.record(ident),
+      _ => {}
+    }
+    decl.fold_children_with(self)
+  }
+}
+
+struct KeepNamesFold {
+  names: OriginalNames,
+}
+
+impl KeepNamesFold {
+  /// returns the statement that defines the original name of the renamed declaration.
+  fn define_name(&self, ident: &Ident) -> Option<Stmt> {
+    let names = self.names.borrow();
+    let name = names.get(&(ident.span.lo.0, ident.span.hi.0))?;
+    if name == ident.sym.as_ref() {
+      return None;
+    }
+    Some(new_define_name_stmt(ident, name))
+  }
+}
+
+impl Fold for KeepNamesFold {
+  noop_fold_type!();
+
+  fn fold_module_items(&mut self, items: Vec<ModuleItem>) -> Vec<ModuleItem> {
+    let items = items.fold_children_with(self);
+    let mut output = Vec::with_capacity(items.len());
+    for item in items {
+      let ident = match &item {
+        ModuleItem::Stmt(Stmt::Decl(decl))
+        | ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => {
+          get_decl_ident(decl)
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(ExportDefaultDecl {
+          decl, ..
+        })) => match decl {
+          DefaultDecl::Fn(FnExpr { ident, .. }) => ident.as_ref(),
+          DefaultDecl::Class(ClassExpr { ident, .. }) => ident.as_ref(),
+          _ => None,
+        },
+        _ => None,
+      };
+      let stmt = ident.and_then(|ident| self.define_name(ident));
+      output.push(item);
+      if let Some(stmt) = stmt {
+        output.push(ModuleItem::Stmt(stmt));
+      }
+    }
+    output
+  }
+
+  fn fold_stmts(&mut self, stmts: Vec<Stmt>) -> Vec<Stmt> {
+    let stmts = stmts.fold_children_with(self);
+    let mut output = Vec::with_capacity(stmts.len());
+    for stmt in stmts {
+      let define = match &stmt {
+        Stmt::Decl(decl) => get_decl_ident(decl).and_then(|ident| self.define_name(ident)),
+        _ => None,
+      };
+      output.push(stmt);
+      if let Some(define) = define {
+        output.push(define);
+      }
+    }
+    output
+  }
+}
+
+fn get_decl_ident(decl: &Decl) -> Option<&Ident> {
+  match decl {
+    Decl::Fn(FnDecl { ident, .. }) => Some(ident),
+    Decl::Class(ClassDecl { ident, .. }) => Some(ident),
+    _ => None,
+  }
+}
+
+/// checks if the class has a static `name` member, which defines the name itself.
+fn has_static_name(class: &Class) -> bool {
+  class.body.iter().any(|member| {
+    let (key, is_static) = match member {
+      ClassMember::ClassProp(ClassProp {
+        key,
+        is_static,
+        computed: false,
+        ..
+      }) => (key.as_ref(), *is_static),
+      ClassMember::Method(ClassMethod {
+        key: PropName::Ident(key),
+        is_static,
+        ..
+      }) => {
+        return *is_static && key.sym.as_ref() == "name";
+      }
+      _ => return false,
+    };
+    is_static && matches!(key, Expr::Ident(id) if id.sym.as_ref() == "name")
+  })
+}
+
+// match: Object.defineProperty(foo1, "name", { value: "foo", configurable: true });
+fn new_define_name_stmt(ident: &Ident, name: &str) -> Stmt {
+  let str_lit = |value: &str| {
+    Box::new(Expr::Lit(Lit::Str(Str {
+      span: DUMMY_SP,
+      value: value.into(),
+      has_escape: false,
+      kind: Default::default(),
+    })))
+  };
+  Stmt::Expr(ExprStmt {
+    span: DUMMY_SP,
+    expr: Box::new(Expr::Call(CallExpr {
+      span: DUMMY_SP,
+      callee: ExprOrSuper::Expr(Box::new(Expr::Member(MemberExpr {
+        span: DUMMY_SP,
+        obj: ExprOrSuper::Expr(Box::new(Expr::Ident(quote_ident!("Object")))),
+        prop: Box::new(Expr::Ident(quote_ident!("defineProperty"))),
+        computed: false,
+      }))),
+      args: vec![
+        ExprOrSpread {
+          spread: None,
+          expr: Box::new(Expr::Ident(ident.clone())),
+        },
+        ExprOrSpread {
+          spread: None,
+          expr: str_lit("name"),
+        },
+        ExprOrSpread {
+          spread: None,
+          expr: Box::new(Expr::Object(ObjectLit {
+            span: DUMMY_SP,
+            props: vec![
+              PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                key: PropName::Ident(quote_ident!("value")),
+                value: str_lit(name),
+              }))),
+              PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+                key: PropName::Ident(quote_ident!("configurable")),
+                value: Box::new(Expr::Lit(Lit::Bool(Bool {
+                  span: DUMMY_SP,
+                  value: true,
+                }))),
+              }))),
+            ],
+          })),
+        },
+      ],
+      type_args: None,
+    })),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::bundle::bundle;
+  use crate::swc::{test_resolver, EmitOptions};
+  use std::collections::HashMap;
+
+  #[test]
+  fn keep_names() {
+    let mut sources = HashMap::new();
+    sources.insert(
+      "/main.ts".to_owned(),
+      r#"
+        import { run } from "./lib.ts"
+        function helper() { return "main" }
+        class Task {}
+        console.log(run(), helper(), new Task())
+      "#
+      .to_owned(),
+    );
+    sources.insert(
+      "/lib.ts".to_owned(),
+      r#"
+        function helper() { return "lib" }
+        class Task { static name = "LibTask" }
+        export function run() {
+          function unique() {}
+          return [helper(), new Task(), unique]
+        }
+      "#
+      .to_owned(),
+    );
+    let transform = |keep_fn_names: bool, keep_class_names: bool| {
+      let resolver = test_resolver("/main.ts");
+      let (code, ..) = bundle(
+        "/main.ts",
+        &sources,
+        resolver,
+        &EmitOptions {
+          keep_fn_names,
+          keep_class_names,
+          ..Default::default()
+        },
+      )
+      .expect("could not bundle modules");
+      println!("{}", code);
+      code
+    };
+
+    let code = transform(false, false);
+    assert!(code.contains("function helper1() {"));
+    assert!(!code.contains("defineProperty"));
+
+    let code = transform(true, true);
+    assert!(code.contains(
+      "function helper1() {\n    return \"main\";\n}\nObject.defineProperty(helper1, \"name\", {\n    value: \"helper\",\n    configurable: true\n});"
+    ));
+    assert!(code.contains("Object.defineProperty(Task1, \"name\", {\n    value: \"Task\","));
+    assert_eq!(code.matches("defineProperty").count(), 2);
+  }
+}
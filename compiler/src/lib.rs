@@ -1,27 +1,116 @@
 #[macro_use]
 extern crate lazy_static;
 
+mod asset;
+mod ast_json;
+mod ast_utils;
+mod bundle;
+mod cache;
+mod cjs_interop;
+mod comments;
+mod const_enum;
+mod coverage;
+mod css;
+mod cycles;
+mod define;
+mod directive;
+mod display_name;
+mod drop;
 mod error;
 mod fast_refresh;
+mod features;
+#[cfg(not(target_arch = "wasm32"))]
+mod ffi;
 mod fixer;
+mod hmr;
+mod html;
+mod import_assertions;
+mod import_equals;
 mod import_map;
+mod import_meta;
+mod inline_imports;
+mod isolated_modules;
 mod jsx;
+mod jsx_args;
+mod jsx_hoist;
+mod jsx_pragma;
+mod jsx_whitespace;
+mod keep_names;
+mod mangle;
+mod manifest;
+mod mappings;
+mod markdown;
+mod memory;
+mod metadata;
+mod module_handles;
+mod pure;
 mod resolve;
 mod resolve_fold;
+mod route_meta;
+mod scoped_css;
 mod source_type;
+mod stats;
+mod styled;
 mod swc;
+mod system_js;
+mod targets;
+mod top_level_await;
+mod tree_shake;
+mod typescript;
+mod unused_imports;
 
-use import_map::ImportHashMap;
-use resolve::{DependencyDescriptor, InlineStyle, Resolver};
+use ast_json::module_to_json;
+use bundle::bundle;
+use cache::{cache_key, read_cache, write_cache};
+use cjs_interop::cjs_to_esm;
+use comments::{extract_legal_comments, CommentsMode};
+use const_enum::ConstEnums;
+use coverage::FileCoverage;
+use cycles::{find_cycles, new_cycle_warning, rotate_cycle};
+use directive::{parse_module_directive, parse_server_exports, ModuleDirective};
+use error::{DiagnosticBuffer, DiagnosticCode, DiagnosticMessage};
+use fast_refresh::{
+  deserialize_refresh_options, refresh_boundary, RefreshBoundary, RefreshOptions,
+};
+use features::{detect_features, UsedFeatures};
+use html::{transform_html, ModuleScript};
+use import_map::{validate_import_map, ImportHashMap};
+use indexmap::IndexMap;
+use inline_imports::DEFAULT_INLINE_LIMIT;
+use jsx_args::JsxPropMode;
+use jsx_whitespace::JsxWhitespace;
+use manifest::{build_manifest, BuildManifest, EmittedFile};
+use mappings::{GeneratedPosition, OriginalPosition};
+use markdown::{compile_markdown, is_markdown_url};
+use memory::{InitOptions, MemoryStats};
+use metadata::{parse_export_signature, parse_metadata, ExportSignature, ModuleMetadata};
+use pure::is_side_effect_free;
+#[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+use rayon::prelude::*;
+use resolve::{DependencyDescriptor, InlineStyle, RemoteDependency, ResolveRule, Resolver};
+use route_meta::{is_api_module, parse_route_meta};
 use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
 use source_type::SourceType;
-use std::collections::HashMap;
+use stats::{now, TransformStats};
+use std::collections::{BTreeMap, HashMap};
 use std::{cell::RefCell, rc::Rc};
-use swc::{EmitOptions, SWC};
+use styled::StyledOptions;
+use swc::{
+  deserialize_parser_features, EmitOptions, JsxMode, JsxRuntime, ModuleType, ParserFeatures,
+  SourceMapMode, TargetEnv, SWC,
+};
 use swc_ecmascript::parser::JscTarget;
+use targets::{deserialize_targets, TargetFeatures};
+use typescript::{
+  to_js, JsAstNode, JsBuildOutput, JsBundleOutput, JsConvertCJSOutput, JsDualTransformOutput,
+  JsExportNames, JsGeneratedPosition, JsImportMap, JsImportMapValidation, JsInitOptions,
+  JsMemoryStats, JsModules, JsOptions, JsOriginalPosition, JsParseOutput, JsResolveOutput,
+  JsSWCOptions, JsTransformHtmlOutput, JsTransformInputs, JsTransformOutput, JsTransformOutputs,
+};
 use wasm_bindgen::prelude::{wasm_bindgen, JsValue};
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct Options {
   #[serde(default)]
@@ -33,11 +122,62 @@ pub struct Options {
   #[serde(default)]
   pub react_version: String,
 
+  #[serde(default)]
+  pub npm_cdn: String,
+
+  #[serde(default)]
+  pub npm_versions: HashMap<String, String>,
+
+  #[serde(default)]
+  pub versions: HashMap<String, String>,
+
+  #[serde(default)]
+  pub node_polyfills: HashMap<String, String>,
+
+  #[serde(default)]
+  pub aliases: HashMap<String, String>,
+
+  #[serde(default)]
+  pub base_url: String,
+
+  #[serde(default)]
+  pub remote_prefix: String,
+
+  #[serde(default)]
+  pub local_prefix: String,
+
+  #[serde(default)]
+  pub output_ext: String,
+
+  #[serde(default)]
+  pub output_exts: HashMap<String, String>,
+
+  #[serde(default)]
+  pub dep_hashes: HashMap<String, String>,
+
+  #[serde(default)]
+  pub asset_url: String,
+
+  #[serde(default)]
+  pub resolve_rules: Vec<ResolveRule>,
+
+  #[serde(default)]
+  pub lock: HashMap<String, String>,
+
   #[serde(default)]
   pub swc_options: SWCOptions,
 
   #[serde(default)]
-  pub source_map: bool,
+  pub source_map: SourceMapMode,
+
+  #[serde(default)]
+  pub input_source_map: Option<String>,
+
+  #[serde(default)]
+  pub inline_sources_content: bool,
+
+  #[serde(default)]
+  pub source_root: Option<String>,
 
   #[serde(default)]
   pub is_dev: bool,
@@ -45,17 +185,127 @@ pub struct Options {
   #[serde(default)]
   pub transpile_only: bool,
 
+  /// returns the `stats` of the transform.
+  #[serde(default)]
+  pub measure: bool,
+
+  #[serde(default)]
+  pub hmr: bool,
+
   #[serde(default)]
   pub resolve_star_exports: bool,
 
+  #[serde(default)]
+  pub extract_css: bool,
+
+  /// scopes the CSS of the `css` tagged templates to the components.
+  #[serde(default)]
+  pub scoped_css: bool,
+
+  /// passes the chunk urls of the lazy components as the second argument of the
+  /// lazy calls, like `React.lazy(() => import("./chart.js"), "/chart.js")`.
+  #[serde(default)]
+  pub lazy_preload: bool,
+
+  /// replaces the server-only exports with the stubs for the browser.
+  #[serde(default)]
+  pub strip_server_exports: bool,
+
+  /// returns the `routeMeta` of the page and the api modules.
+  #[serde(default)]
+  pub extract_route_meta: bool,
+
+  /// returns the syntax and the API features that the module uses in `features`.
+  #[serde(default)]
+  pub report_features: bool,
+
+  #[serde(default)]
+  pub css_modules: HashMap<String, String>,
+
+  #[serde(default)]
+  pub json_modules: HashMap<String, String>,
+
+  /// the contents of the `.json` and the `?text` imports to inline, keyed by the
+  /// fixed url without the query.
+  #[serde(default)]
+  pub inline_imports: HashMap<String, String>,
+
+  /// the byte size limit of the inlined imports, default is 4096.
+  #[serde(default)]
+  pub inline_limit: Option<usize>,
+
+  #[serde(default)]
+  pub const_enums: HashMap<String, ConstEnums>,
+
+  #[serde(default)]
+  pub import_meta_url: Option<String>,
+
+  #[serde(default)]
+  pub glob_files: HashMap<String, Vec<String>>,
+
   #[serde(default)]
   pub bundle_mode: bool,
 
   #[serde(default)]
   pub bundle_external: Vec<String>,
+
+  #[serde(default)]
+  pub define: HashMap<String, String>,
+
+  /// the export names that are used by the other modules, the unused exports are
+  /// dropped with the bindings that are not referenced anymore.
+  #[serde(default)]
+  pub used_exports: Option<Vec<String>>,
+
+  /// the deps (the fixed urls) that are side-effect free, the imports of them are
+  /// dropped by the tree shaking if none of the specifiers is used.
+  #[serde(default)]
+  pub side_effect_free_deps: Vec<String>,
+
+  #[serde(default)]
+  pub drop_console: bool,
+
+  #[serde(default)]
+  pub drop_debugger: bool,
+
+  #[serde(default)]
+  pub strip_data_test_attrs: bool,
+
+  #[serde(default)]
+  pub strip_calls: Vec<String>,
+
+  #[serde(default)]
+  pub keep_fn_names: bool,
+
+  #[serde(default)]
+  pub keep_class_names: bool,
+
+  #[serde(default)]
+  pub hoist_static_jsx: bool,
+
+  #[serde(default)]
+  pub coverage: bool,
+
+  #[serde(default)]
+  pub comments: CommentsMode,
+
+  #[serde(default, deserialize_with = "deserialize_refresh_options")]
+  pub refresh: RefreshOptions,
+
+  #[serde(default)]
+  pub styled: Option<StyledOptions>,
+
+  /// the `cacheKey` of the previous outputs keyed by the url, the transform of an
+  /// unchanged module is skipped with an `unchanged` output.
+  #[serde(default, skip_serializing)]
+  pub cache_keys: HashMap<String, String>,
+
+  /// persists the outputs in the directory by the `cacheKey`, native builds only.
+  #[serde(default, skip_serializing)]
+  pub cache_dir: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, Serialize)]
 #[serde(deny_unknown_fields, rename_all = "camelCase")]
 pub struct SWCOptions {
   #[serde(default)]
@@ -64,11 +314,84 @@ pub struct SWCOptions {
   #[serde(default = "default_target")]
   pub target: JscTarget,
 
+  /// the syntax features of the target browsers, as a browserslist-like query or
+  /// the features object, the unsupported features are lowered.
+  #[serde(default, deserialize_with = "deserialize_targets")]
+  pub targets: Option<TargetFeatures>,
+
+  #[serde(default)]
+  pub jsx: JsxMode,
+
   #[serde(default = "default_pragma")]
   pub jsx_factory: String,
 
   #[serde(default = "default_pragma_frag")]
   pub jsx_fragment_factory: String,
+
+  #[serde(default)]
+  pub jsx_runtime: JsxRuntime,
+
+  #[serde(default = "default_jsx_import_source")]
+  pub jsx_import_source: String,
+
+  #[serde(default = "default_jsx_source")]
+  pub jsx_source: bool,
+
+  /// normalizes the whitespace of the JSX text like babel or tsc.
+  #[serde(default)]
+  pub jsx_whitespace: JsxWhitespace,
+
+  #[serde(default)]
+  pub jsx_drop_empty_children: bool,
+
+  /// passes the `key` prop as a separate arg of the classic JSX factory.
+  #[serde(default)]
+  pub jsx_key: JsxPropMode,
+
+  /// passes the `ref` prop as a separate arg of the classic JSX factory.
+  #[serde(default)]
+  pub jsx_ref: JsxPropMode,
+
+  #[serde(default)]
+  pub module_type: ModuleType,
+
+  #[serde(default)]
+  pub target_env: Option<TargetEnv>,
+
+  #[serde(default)]
+  pub experimental_decorators: bool,
+
+  /// the syntax features of the parser, the `experimentalDecorators` option enables
+  /// the `decorators` feature too.
+  #[serde(default, deserialize_with = "deserialize_parser_features")]
+  pub parser: ParserFeatures,
+
+  #[serde(default)]
+  pub isolated_modules: bool,
+
+  #[serde(default)]
+  pub emit_decorator_metadata: bool,
+
+  /// compresses the output and mangles the local names in production.
+  #[serde(default)]
+  pub minify: bool,
+
+  #[serde(default)]
+  pub external_helpers: bool,
+
+  #[serde(default)]
+  pub lower_top_level_await: bool,
+
+  #[serde(default)]
+  pub lower_class_fields: bool,
+
+  #[serde(default = "default_use_define_for_class_fields")]
+  pub use_define_for_class_fields: bool,
+
+  /// recovers from the syntax errors in `parseSync` and returns the errors with the
+  /// partial metadata, instead of failing.
+  #[serde(default)]
+  pub tolerant: bool,
 }
 
 impl Default for SWCOptions {
@@ -76,8 +399,43 @@ impl Default for SWCOptions {
     SWCOptions {
       source_type: SourceType::default(),
       target: default_target(),
+      targets: None,
+      jsx: JsxMode::default(),
       jsx_factory: default_pragma(),
       jsx_fragment_factory: default_pragma_frag(),
+      jsx_runtime: JsxRuntime::default(),
+      jsx_import_source: default_jsx_import_source(),
+      jsx_source: default_jsx_source(),
+      jsx_whitespace: JsxWhitespace::default(),
+      jsx_drop_empty_children: false,
+      jsx_key: JsxPropMode::default(),
+      jsx_ref: JsxPropMode::default(),
+      module_type: ModuleType::default(),
+      target_env: None,
+      experimental_decorators: false,
+      parser: ParserFeatures::default(),
+      isolated_modules: false,
+      emit_decorator_metadata: false,
+      minify: false,
+      external_helpers: false,
+      lower_top_level_await: false,
+      lower_class_fields: false,
+      use_define_for_class_fields: default_use_define_for_class_fields(),
+      tolerant: false,
+    }
+  }
+}
+
+impl SWCOptions {
+  /// Returns the syntax features of the parser.
+  pub fn parser_features(&self) -> ParserFeatures {
+    ParserFeatures {
+      decorators: if self.experimental_decorators {
+        Some(true)
+      } else {
+        self.parser.decorators
+      },
+      ..self.parser.clone()
     }
   }
 }
@@ -94,85 +452,1228 @@ fn default_pragma_frag() -> String {
   "React.Fragment".into()
 }
 
-#[derive(Serialize)]
+fn default_jsx_import_source() -> String {
+  "https://esm.sh/react".into()
+}
+
+fn default_jsx_source() -> bool {
+  true
+}
+
+fn default_use_define_for_class_fields() -> bool {
+  true
+}
+
+#[derive(Clone, Default, Deserialize, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct TransformOutput {
   pub code: String,
-  #[serde(skip_serializing_if = "Option::is_none")]
+  /// the hex sha-1 of the emitted code
+  pub hash: String,
+  /// the external source map with `sourceMap: "external"`
   pub map: Option<String>,
+  /// whether the module has no side effects when it's evaluated
+  pub side_effect_free: bool,
+  /// the export signature, the `export *` targets are the fixed urls
+  pub exports: ExportSignature,
+  /// whether the module only exports the components, for the HMR client to accept
+  /// or to bubble the updates
+  pub refresh_boundary: RefreshBoundary,
+  /// the `"use client"` or `"use server"` directive of the module
+  pub directive: Option<ModuleDirective>,
+  /// the server-only exports, like the functions with the `"use server"` directive
+  pub server_exports: Option<Vec<String>>,
   pub deps: Vec<DependencyDescriptor>,
-  pub inline_styles: HashMap<String, InlineStyle>,
+  /// the remote dependencies with the integrity in the lock
+  pub remote_deps: Option<Vec<RemoteDependency>>,
+  /// the absolute urls of the static dependencies, for the `modulepreload` links
+  pub preload: Option<Vec<String>>,
+  pub inline_styles: BTreeMap<String, InlineStyle>,
   pub star_exports: Option<Vec<String>>,
+  pub dynamic_import_patterns: Option<Vec<String>>,
+  pub deno_hooks: Option<Vec<String>>,
+  pub css_deps: Option<Vec<String>>,
+  pub css_modules: Option<BTreeMap<String, String>>,
+  /// the scoped CSS of the `css` templates keyed by the scope class with the
+  /// `scopedCss` option
+  pub scoped_css: Option<BTreeMap<String, String>>,
+  /// the fixed urls of the assets that are imported as the urls with the
+  /// `assetUrl` option
+  pub assets: Option<Vec<String>>,
+  /// the istanbul coverage map of the instrumented module with the `coverage`
+  /// option
+  pub coverage: Option<FileCoverage>,
+  /// the front-matter of the markdown module
+  pub front_matter: Option<serde_json::Map<String, serde_json::Value>>,
+  /// the exported constants with the static values with the `extractRouteMeta`
+  /// option, like the `config` of a page
+  pub route_meta: Option<serde_json::Map<String, serde_json::Value>>,
+  /// the syntax and the API features that the module uses with the `reportFeatures`
+  /// option, for deciding whether the legacy bundle or the polyfills are needed
+  pub features: Option<UsedFeatures>,
+  /// the non-fatal warnings, like the unused imports
+  pub warnings: Option<Vec<DiagnosticMessage>>,
+  /// the timing and the sizes with the `measure` option
+  pub stats: Option<TransformStats>,
+  /// the legal comments that are stripped with `comments: "legal"`, like
+  /// `/*! MIT License */`.
+  pub legal_comments: Option<String>,
+  /// the hash of the input and the options, which is passed back with the
+  /// `cacheKeys` option to skip the next transform of the unchanged module.
+  pub cache_key: Option<String>,
+  /// whether the module is unchanged since the `cacheKeys`, the other fields are
+  /// empty and the caller reuses its previous output.
+  pub unchanged: bool,
 }
 
 #[wasm_bindgen(js_name = "parseExportNamesSync")]
 pub fn parse_export_names_sync(
   url: &str,
   code: &str,
-  options: JsValue,
-) -> Result<JsValue, JsValue> {
+  options: JsSWCOptions,
+) -> Result<JsExportNames, JsValue> {
   console_error_panic_hook::set_once();
 
   let options: SWCOptions = options
     .into_serde()
-    .map_err(|err| format!("failed to parse options: {}", err))
-    .unwrap();
-  let module = SWC::parse(url, code, Some(options.source_type)).expect("could not parse module");
-  let export_names = module.parse_export_names().unwrap();
-  Ok(JsValue::from_serde(&export_names).unwrap())
+    .map_err(|err| format!("failed to parse options: {}", err))?;
+  let export_names = parse_export_names(url, code, &options)?;
+  Ok(to_js(&export_names))
+}
+
+/// Parses the export names of the module.
+pub fn parse_export_names(
+  url: &str,
+  code: &str,
+  options: &SWCOptions,
+) -> Result<Vec<String>, DiagnosticBuffer> {
+  let module = SWC::parse_with_features(
+    url,
+    code,
+    Some(options.source_type.clone()),
+    &options.parser_features(),
+  )
+  .map_err(|err| diagnostics_error(url, err))?;
+  module
+    .parse_export_names()
+    .map_err(|err| diagnostics_error(url, err))
+}
+
+/// Returns the AST of the module as JSON for the tooling, with the spans mapped to
+/// the lines and the columns.
+#[wasm_bindgen(js_name = "astSync")]
+pub fn ast_sync(filename: &str, code: &str) -> Result<JsAstNode, JsValue> {
+  console_error_panic_hook::set_once();
+
+  let ast = parse_ast(filename, code)?;
+  Ok(to_js(&ast))
+}
+
+/// Parses the AST of the module, the source type is detected by the `filename`.
+pub fn parse_ast(filename: &str, code: &str) -> Result<serde_json::Value, DiagnosticBuffer> {
+  let module = SWC::parse(filename, code, None).map_err(|err| diagnostics_error(filename, err))?;
+  Ok(module_to_json(&module))
+}
+
+#[wasm_bindgen(js_name = "parseSync")]
+pub fn parse_sync(url: &str, code: &str, options: JsSWCOptions) -> Result<JsParseOutput, JsValue> {
+  console_error_panic_hook::set_once();
+
+  let options: SWCOptions = options
+    .into_serde()
+    .map_err(|err| format!("failed to parse options: {}", err))?;
+  Ok(to_js(&parse(url, code, &options)?))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ParseOutput {
+  #[serde(flatten)]
+  pub metadata: ModuleMetadata,
+  /// the syntax errors in the tolerant mode
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub errors: Option<Vec<DiagnosticMessage>>,
+}
+
+/// Parses the metadata of the module, like the imports and the exports.
+pub fn parse(url: &str, code: &str, options: &SWCOptions) -> Result<ParseOutput, DiagnosticBuffer> {
+  if options.tolerant {
+    let (module, errors) = SWC::parse_tolerant(
+      url,
+      code,
+      Some(options.source_type.clone()),
+      &options.parser_features(),
+    );
+    return Ok(ParseOutput {
+      metadata: module
+        .map(|module| parse_module_metadata(url, &module))
+        .unwrap_or_default(),
+      errors: Some(errors),
+    });
+  }
+  let module = SWC::parse_with_features(
+    url,
+    code,
+    Some(options.source_type.clone()),
+    &options.parser_features(),
+  )
+  .map_err(|err| diagnostics_error(url, err))?;
+  Ok(ParseOutput {
+    metadata: parse_module_metadata(url, &module),
+    errors: None,
+  })
+}
+
+/// parses the metadata of the module, with the route metadata of an api module.
+fn parse_module_metadata(url: &str, module: &SWC) -> ModuleMetadata {
+  let mut metadata = parse_metadata(&module.module);
+  if is_api_module(url) {
+    metadata.route_meta = Some(parse_route_meta(&module.module)).filter(|meta| !meta.is_empty());
+  }
+  metadata
 }
 
 #[wasm_bindgen(js_name = "transformSync")]
-pub fn transform_sync(url: &str, code: &str, options: JsValue) -> Result<JsValue, JsValue> {
+pub fn transform_sync(
+  url: &str,
+  code: &str,
+  options: JsOptions,
+) -> Result<JsTransformOutput, JsValue> {
   console_error_panic_hook::set_once();
 
   let options: Options = options
     .into_serde()
-    .map_err(|err| format!("failed to parse options: {}", err))
-    .unwrap();
-  let resolver = Rc::new(RefCell::new(Resolver::new(
+    .map_err(|err| format!("failed to parse options: {}", err))?;
+  let output = transform(url, code, &options)?;
+  Ok(to_js(&output))
+}
+
+/// Transforms the module, the backend-agnostic part of `transformSync`.
+pub fn transform(
+  url: &str,
+  code: &str,
+  options: &Options,
+) -> Result<TransformOutput, DiagnosticBuffer> {
+  let resolver = new_resolver(url, options)?;
+  transform_module(url, code, resolver, options)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolveOutput {
+  /// the fixed url after the import map and the CDN rules are applied
+  pub url: String,
+  /// the path of the compiled module relative to the referrer
+  pub resolved_path: String,
+}
+
+/// Resolves the `specifier` imported by the `referrer` in the same way as the
+/// transformer does, only the resolve options (import map, CDN, etc.) are used.
+#[wasm_bindgen(js_name = "resolveSync")]
+pub fn resolve_sync(
+  specifier: &str,
+  referrer: &str,
+  options: JsOptions,
+) -> Result<JsResolveOutput, JsValue> {
+  console_error_panic_hook::set_once();
+
+  let options: Options = options
+    .into_serde()
+    .map_err(|err| format!("failed to parse options: {}", err))?;
+  let output = resolve_specifier(specifier, referrer, &options)?;
+  Ok(to_js(&output))
+}
+
+/// Resolves the `specifier` imported by the `referrer`.
+pub fn resolve_specifier(
+  specifier: &str,
+  referrer: &str,
+  options: &Options,
+) -> Result<ResolveOutput, DiagnosticBuffer> {
+  let resolver = new_resolver(referrer, options)?;
+  let (resolved_path, url) = resolver.borrow_mut().resolve(specifier, false, None);
+  Ok(ResolveOutput { url, resolved_path })
+}
+
+/// Looks up the original position of the generated position (the `line` is 1-based
+/// and the `column` is 0-based) in the source `map`, for symbolicating the stack
+/// traces of the SSR.
+#[wasm_bindgen(js_name = "lookupOriginalPosition")]
+pub fn lookup_original_position_sync(
+  map: &str,
+  line: u32,
+  column: u32,
+) -> Result<JsOriginalPosition, JsValue> {
+  console_error_panic_hook::set_once();
+
+  Ok(to_js(&lookup_original_position(map, line, column)?))
+}
+
+/// Looks up the original position, the backend-agnostic part of `lookupOriginalPosition`.
+pub fn lookup_original_position(
+  map: &str,
+  line: u32,
+  column: u32,
+) -> Result<Option<OriginalPosition>, DiagnosticBuffer> {
+  mappings::lookup_original_position(map, line, column).map_err(invalid_source_map)
+}
+
+/// Looks up the generated position of the original position of the `source` in the
+/// source `map`, the inverse of `lookupOriginalPosition`.
+#[wasm_bindgen(js_name = "lookupGeneratedPosition")]
+pub fn lookup_generated_position_sync(
+  map: &str,
+  source: &str,
+  line: u32,
+  column: u32,
+) -> Result<JsGeneratedPosition, JsValue> {
+  console_error_panic_hook::set_once();
+
+  Ok(to_js(&lookup_generated_position(
+    map, source, line, column,
+  )?))
+}
+
+/// Looks up the generated position, the backend-agnostic part of `lookupGeneratedPosition`.
+pub fn lookup_generated_position(
+  map: &str,
+  source: &str,
+  line: u32,
+  column: u32,
+) -> Result<Option<GeneratedPosition>, DiagnosticBuffer> {
+  mappings::lookup_generated_position(map, source, line, column).map_err(invalid_source_map)
+}
+
+/// Tunes the memory of the compiler for the long-running processes like the dev
+/// server, e.g. pre-grows the wasm memory, returns the memory stats.
+#[wasm_bindgen(js_name = "initMemorySync")]
+pub fn init_memory_sync(options: JsInitOptions) -> Result<JsMemoryStats, JsValue> {
+  console_error_panic_hook::set_once();
+
+  let options: InitOptions = options
+    .into_serde()
+    .map_err(|err| format!("failed to parse options: {}", err))?;
+  Ok(to_js(&init_memory(&options)))
+}
+
+/// Tunes the memory, the backend-agnostic part of `initMemorySync`.
+pub fn init_memory(options: &InitOptions) -> MemoryStats {
+  memory::init(options)
+}
+
+/// Parses the module and keeps it in memory, returns the handle of the parsed module
+/// that can be emitted with the different options by `emitModuleSync`, without parsing
+/// the module again (e.g. for SSR and the client). The handle must be freed by
+/// `freeModuleSync`.
+#[wasm_bindgen(js_name = "parseModuleSync")]
+pub fn parse_module_sync(url: &str, code: &str, options: JsOptions) -> Result<u32, JsValue> {
+  console_error_panic_hook::set_once();
+
+  let options: Options = options
+    .into_serde()
+    .map_err(|err| format!("failed to parse options: {}", err))?;
+  Ok(parse_module_handle(url, code, &options)?)
+}
+
+/// Parses the module into a handle, the backend-agnostic part of `parseModuleSync`.
+/// The parse options (like the `sourceType`, `extractRouteMeta` and `reportFeatures`)
+/// are applied here, the others are ignored.
+pub fn parse_module_handle(
+  url: &str,
+  code: &str,
+  options: &Options,
+) -> Result<u32, DiagnosticBuffer> {
+  let parsed = parse_module(url, code, options)?;
+  Ok(module_handles::insert_module(url, parsed))
+}
+
+/// Emits the module of the handle that is returned by `parseModuleSync`, like
+/// `transformSync` with the emit options.
+#[wasm_bindgen(js_name = "emitModuleSync")]
+pub fn emit_module_sync(handle: u32, options: JsOptions) -> Result<JsTransformOutput, JsValue> {
+  console_error_panic_hook::set_once();
+
+  let options: Options = options
+    .into_serde()
+    .map_err(|err| format!("failed to parse options: {}", err))?;
+  Ok(to_js(&emit_module_handle(handle, &options)?))
+}
+
+/// Emits the parsed module, the backend-agnostic part of `emitModuleSync`. The parse
+/// options are taken from `parse_module_handle`.
+pub fn emit_module_handle(
+  handle: u32,
+  options: &Options,
+) -> Result<TransformOutput, DiagnosticBuffer> {
+  module_handles::with_forked_module(handle, |url, parsed| {
+    let resolver = new_resolver(url, options)?;
+    emit_module(url, parsed, resolver, &new_emit_options(options))
+  })
+  .unwrap_or_else(|| {
+    Err(DiagnosticBuffer::from_message(
+      "",
+      DiagnosticCode::InvalidOptions,
+      format!("invalid module handle: {}", handle),
+    ))
+  })
+}
+
+/// Frees the parsed module of the handle, returns `false` if the handle is unknown
+/// or already freed.
+#[wasm_bindgen(js_name = "freeModuleSync")]
+pub fn free_module_sync(handle: u32) -> bool {
+  free_module_handle(handle)
+}
+
+/// Frees the parsed module, the backend-agnostic part of `freeModuleSync`.
+pub fn free_module_handle(handle: u32) -> bool {
+  module_handles::remove_module(handle)
+}
+
+/// Drops the global caches of the compiler (the parsed source maps of the position
+/// lookups), returns the memory stats.
+#[wasm_bindgen(js_name = "resetMemorySync")]
+pub fn reset_memory_sync() -> JsMemoryStats {
+  console_error_panic_hook::set_once();
+
+  to_js(&reset_memory())
+}
+
+/// Drops the global caches, the backend-agnostic part of `resetMemorySync`.
+pub fn reset_memory() -> MemoryStats {
+  memory::reset()
+}
+
+fn invalid_source_map(err: anyhow::Error) -> DiagnosticBuffer {
+  DiagnosticBuffer::from_message("", DiagnosticCode::InvalidOptions, err.to_string())
+}
+
+/// Validates the import map, the invalid entries that are ignored by the resolver
+/// are reported in the `errors` with the reason.
+#[wasm_bindgen(js_name = "validateImportMapSync")]
+pub fn validate_import_map_sync(import_map: JsImportMap) -> Result<JsImportMapValidation, JsValue> {
+  console_error_panic_hook::set_once();
+
+  let import_map: ImportHashMap = import_map
+    .into_serde()
+    .map_err(|err| format!("failed to parse import map: {}", err))?;
+  Ok(to_js(&validate_import_map(&import_map)))
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BundleOutput {
+  pub code: String,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub map: Option<String>,
+  /// the external dependencies of the bundle
+  pub deps: Vec<DependencyDescriptor>,
+  /// the bundled modules in evaluation order
+  pub modules: Vec<String>,
+  /// the import cycles between the bundled modules
+  pub warnings: Option<Vec<DiagnosticMessage>>,
+  /// the emitted file of the bundle and the chunks of the dynamic imports
+  pub manifest: BuildManifest,
+}
+
+/// Bundles the module graph of the `entry` into a single ES module, the `modules`
+/// are the pre-fetched sources keyed by the fixed url, the imports of other modules
+/// are kept as the external imports of the bundle.
+#[wasm_bindgen(js_name = "bundleSync")]
+pub fn bundle_sync(
+  entry: &str,
+  modules: JsModules,
+  options: JsOptions,
+) -> Result<JsBundleOutput, JsValue> {
+  console_error_panic_hook::set_once();
+
+  let modules: HashMap<String, String> = modules
+    .into_serde()
+    .map_err(|err| format!("failed to parse modules: {}", err))?;
+  let options: Options = options
+    .into_serde()
+    .map_err(|err| format!("failed to parse options: {}", err))?;
+  let output = bundle_modules(entry, &modules, &options)?;
+  Ok(to_js(&output))
+}
+
+/// Bundles the module graph of the `entry`, the backend-agnostic part of `bundleSync`.
+pub fn bundle_modules(
+  entry: &str,
+  modules: &HashMap<String, String>,
+  options: &Options,
+) -> Result<BundleOutput, DiagnosticBuffer> {
+  let resolver = new_resolver(entry, options)?;
+  let (code, map, modules, cycles) =
+    bundle(entry, modules, resolver.clone(), &new_emit_options(options))
+      .map_err(|err| diagnostics_error(entry, err))?;
+  let deps = resolver.borrow().dep_graph.clone();
+  let warnings: Vec<DiagnosticMessage> = cycles
+    .iter()
+    .map(|cycle| new_cycle_warning(cycle))
+    .collect();
+  let mut hasher = Sha1::new();
+  hasher.update(code.as_bytes());
+  let hash = format!("{:x}", hasher.finalize());
+  let bundled: Vec<String> = modules
+    .iter()
+    .filter(|specifier| specifier.as_str() != entry)
+    .cloned()
+    .collect();
+  let manifest = build_manifest(
+    &resolver.borrow(),
+    &[EmittedFile {
+      specifier: entry,
+      hash: hash.as_str(),
+      deps: &deps,
+      bundled: &bundled,
+    }],
+  );
+  Ok(BundleOutput {
+    code,
+    map,
+    deps,
+    modules,
+    manifest,
+    warnings: if !warnings.is_empty() {
+      Some(warnings)
+    } else {
+      None
+    },
+  })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransformHtmlOutput {
+  pub code: String,
+  /// the deps of the module scripts, including the imports of the inline scripts
+  pub deps: Vec<DependencyDescriptor>,
+}
+
+/// Transforms an HTML entry like `index.html`, the `src` of the module scripts is
+/// resolved and the inline module scripts are transformed in the same way as the
+/// modules, the classic scripts are kept as is.
+#[wasm_bindgen(js_name = "transformHtmlSync")]
+pub fn transform_html_sync(
+  url: &str,
+  code: &str,
+  options: JsOptions,
+) -> Result<JsTransformHtmlOutput, JsValue> {
+  console_error_panic_hook::set_once();
+
+  let options: Options = options
+    .into_serde()
+    .map_err(|err| format!("failed to parse options: {}", err))?;
+  Ok(to_js(&transform_html_entry(url, code, &options)?))
+}
+
+/// Transforms an HTML entry, the backend-agnostic part of `transformHtmlSync`.
+pub fn transform_html_entry(
+  url: &str,
+  code: &str,
+  options: &Options,
+) -> Result<TransformHtmlOutput, DiagnosticBuffer> {
+  let resolver = new_resolver(url, options)?;
+  // the inline scripts have no file for the source maps
+  let emit_options = EmitOptions {
+    source_map: SourceMapMode::None,
+    ..new_emit_options(options)
+  };
+  let code = transform_html(code, |script| match script {
+    ModuleScript::Src(src) => Ok(resolver.borrow_mut().resolve(src, false, None).0),
+    ModuleScript::Inline(code) => {
+      let parsed = parse_module(url, code, options)?;
+      Ok(emit_module(url, parsed, resolver.clone(), &emit_options)?.code)
+    }
+  })?;
+  let deps = resolver.borrow().dep_graph.clone();
+  Ok(TransformHtmlOutput { code, deps })
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConvertCJSOutput {
+  pub code: String,
+  /// the named exports that are inferred from the static `exports.x = ...` assignments
+  pub exports: Vec<String>,
+  /// the specifiers of the static `require()` calls, which are imported by the facade
+  pub requires: Vec<String>,
+}
+
+/// Converts a CommonJS module into an ES module facade with the named exports, for
+/// the remote modules that are served as CommonJS, like the CJS builds of the CDN.
+#[wasm_bindgen(js_name = "convertCJS")]
+pub fn convert_cjs_sync(url: &str, code: &str) -> Result<JsConvertCJSOutput, JsValue> {
+  console_error_panic_hook::set_once();
+
+  Ok(to_js(&convert_cjs(url, code)?))
+}
+
+/// Converts a CommonJS module, the backend-agnostic part of `convertCJS`.
+pub fn convert_cjs(url: &str, code: &str) -> Result<ConvertCJSOutput, DiagnosticBuffer> {
+  let facade = cjs_to_esm(url, code).map_err(|err| diagnostics_error(url, err))?;
+  Ok(ConvertCJSOutput {
+    code: facade.code,
+    exports: facade.exports,
+    requires: facade.requires,
+  })
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TransformInput {
+  pub filename: String,
+  pub code: String,
+}
+
+/// Transforms many modules in one call to save the cost of crossing the wasm boundary,
+/// the import map is parsed once and shared by all the modules.
+#[wasm_bindgen(js_name = "transformAll")]
+pub fn transform_all(
+  sources: JsTransformInputs,
+  options: JsOptions,
+) -> Result<JsTransformOutputs, JsValue> {
+  console_error_panic_hook::set_once();
+
+  let sources: Vec<TransformInput> = sources
+    .into_serde()
+    .map_err(|err| format!("failed to parse sources: {}", err))?;
+  let options: Options = options
+    .into_serde()
+    .map_err(|err| format!("failed to parse options: {}", err))?;
+  let outputs = transform_modules(&sources, &options)?;
+  Ok(to_js(&outputs))
+}
+
+/// Transforms many modules with the shared options, the backend-agnostic part of
+/// `transformAll`. With the `parallel` feature the native builds transform the
+/// modules across the threads of rayon, the outputs keep the order of the `sources`
+/// and the error of the first failed module is returned. The static import cycles
+/// between the `sources` are reported in the `warnings` of every module in the cycle.
+pub fn transform_modules(
+  sources: &[TransformInput],
+  options: &Options,
+) -> Result<Vec<TransformOutput>, DiagnosticBuffer> {
+  let resolver = build_resolver("", options)?;
+  let transform = |TransformInput { filename, code }: &TransformInput| {
+    let resolver = Rc::new(RefCell::new(resolver.fork(filename)));
+    transform_module(filename, code, resolver, options)
+  };
+  #[cfg(all(feature = "parallel", not(target_arch = "wasm32")))]
+  let outputs = sources.par_iter().map(transform).collect::<Vec<_>>();
+  #[cfg(not(all(feature = "parallel", not(target_arch = "wasm32"))))]
+  let outputs = sources.iter().map(transform);
+  let mut outputs = outputs.into_iter().collect::<Result<Vec<_>, _>>()?;
+  add_cycle_warnings(sources, &mut outputs);
+  Ok(outputs)
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildOutput {
+  /// the outputs in the order of the sources
+  pub modules: Vec<TransformOutput>,
+  /// the emitted files of the modules and the chunks of the dynamic imports
+  pub manifest: BuildManifest,
+}
+
+/// Transforms many modules like `transformAll`, with the manifest of the emitted files
+/// that maps the specifiers to the hashed file names, the manifest of the `export`
+/// command.
+#[wasm_bindgen(js_name = "buildAll")]
+pub fn build_all(sources: JsTransformInputs, options: JsOptions) -> Result<JsBuildOutput, JsValue> {
+  console_error_panic_hook::set_once();
+
+  let sources: Vec<TransformInput> = sources
+    .into_serde()
+    .map_err(|err| format!("failed to parse sources: {}", err))?;
+  let options: Options = options
+    .into_serde()
+    .map_err(|err| format!("failed to parse options: {}", err))?;
+  let output = build_modules(&sources, &options)?;
+  Ok(to_js(&output))
+}
+
+/// Transforms many modules with the manifest, the backend-agnostic part of `buildAll`.
+/// The unchanged modules of the `cacheKeys` have no emitted code, so they are not in
+/// the manifest.
+pub fn build_modules(
+  sources: &[TransformInput],
+  options: &Options,
+) -> Result<BuildOutput, DiagnosticBuffer> {
+  let modules = transform_modules(sources, options)?;
+  let resolver = build_resolver("", options)?;
+  let files: Vec<EmittedFile> = sources
+    .iter()
+    .zip(modules.iter())
+    .filter(|(_, output)| !output.unchanged)
+    .map(|(input, output)| EmittedFile {
+      specifier: input.filename.as_str(),
+      hash: output.hash.as_str(),
+      deps: &output.deps,
+      bundled: &[],
+    })
+    .collect();
+  let manifest = build_manifest(&resolver, &files);
+  Ok(BuildOutput { modules, manifest })
+}
+
+/// adds the warnings of the import cycles between the `sources`, the unchanged
+/// outputs have no deps so the cycles through them are not found.
+fn add_cycle_warnings(sources: &[TransformInput], outputs: &mut [TransformOutput]) {
+  let graph: IndexMap<String, Vec<String>> = sources
+    .iter()
+    .zip(outputs.iter())
+    .map(|(input, output)| {
+      let deps = output
+        .deps
+        .iter()
+        .filter(|dep| !dep.is_dynamic && dep.kind.is_none())
+        .map(|dep| dep.specifier.clone())
+        .collect();
+      (input.filename.clone(), deps)
+    })
+    .collect();
+  let cycles = find_cycles(&graph);
+  if cycles.is_empty() {
+    return;
+  }
+  for (input, output) in sources.iter().zip(outputs.iter_mut()) {
+    for cycle in cycles.iter() {
+      if let Some(cycle) = rotate_cycle(cycle, input.filename.as_str()) {
+        output
+          .warnings
+          .get_or_insert_with(Vec::new)
+          .push(new_cycle_warning(&cycle));
+      }
+    }
+  }
+}
+
+fn new_resolver(url: &str, options: &Options) -> Result<Rc<RefCell<Resolver>>, DiagnosticBuffer> {
+  Ok(Rc::new(RefCell::new(build_resolver(url, options)?)))
+}
+
+fn build_resolver(url: &str, options: &Options) -> Result<Resolver, DiagnosticBuffer> {
+  let resolver = Resolver::new(
     url,
-    options.import_map,
+    options.import_map.clone(),
     match options.aleph_pkg_uri.as_str() {
       "" => None,
-      _ => Some(options.aleph_pkg_uri),
+      _ => Some(options.aleph_pkg_uri.clone()),
     },
     match options.react_version.as_str() {
       "" => None,
-      _ => Some(options.react_version),
+      _ => Some(options.react_version.clone()),
     },
     options.bundle_mode,
-    options.bundle_external,
-  )));
-  let module =
-    SWC::parse(url, code, Some(options.swc_options.source_type)).expect("could not parse module");
+    options.bundle_external.clone(),
+  );
+  let resolver = resolver.with_output_paths(
+    options.remote_prefix.as_str(),
+    options.local_prefix.as_str(),
+    options.output_ext.as_str(),
+  );
+  let resolver = resolver
+    .with_output_exts(options.output_exts.clone())
+    .with_dep_hashes(options.dep_hashes.clone())
+    .with_asset_url(options.asset_url.as_str())
+    .with_lock(options.lock.clone())
+    .with_versions(options.versions.clone())
+    .with_aliases(options.aliases.clone())
+    .with_base_url(options.base_url.as_str())
+    .with_resolve_rules(&options.resolve_rules)
+    .map_err(|err| {
+      DiagnosticBuffer::from_message(
+        url,
+        DiagnosticCode::InvalidOptions,
+        format!("invalid resolve rule: {}", err),
+      )
+    })?;
+  // the node builtin modules are only polyfilled for the browser
+  let resolver = match options.swc_options.target_env {
+    Some(TargetEnv::Browser) => resolver.with_node_polyfills(options.node_polyfills.clone()),
+    _ => resolver,
+  };
+  Ok(match options.npm_cdn.as_str() {
+    "" => resolver,
+    _ => resolver.with_npm_cdn(options.npm_cdn.as_str(), options.npm_versions.clone()),
+  })
+}
+
+#[derive(Serialize)]
+pub struct DualTransformOutput {
+  pub dev: TransformOutput,
+  pub prod: TransformOutput,
+}
+
+/// Transforms the module into the dev variant (with fast refresh and the HMR
+/// wrapper) and the minified prod variant, the module is parsed only once.
+#[wasm_bindgen(js_name = "transformBothSync")]
+pub fn transform_both_sync(
+  url: &str,
+  code: &str,
+  options: JsOptions,
+) -> Result<JsDualTransformOutput, JsValue> {
+  console_error_panic_hook::set_once();
+
+  let options: Options = options
+    .into_serde()
+    .map_err(|err| format!("failed to parse options: {}", err))?;
+  let output = transform_both(url, code, &options)?;
+  Ok(to_js(&output))
+}
+
+/// Transforms the module into the dev and prod variants, the backend-agnostic part
+/// of `transformBothSync`.
+pub fn transform_both(
+  url: &str,
+  code: &str,
+  options: &Options,
+) -> Result<DualTransformOutput, DiagnosticBuffer> {
+  let resolver = new_resolver(url, options)?;
+  let parsed = parse_module(url, code, options)?;
+  let dev_options = EmitOptions {
+    hmr: true,
+    ..new_variant_emit_options(options, true)
+  };
+  let prod_options = EmitOptions {
+    hmr: false,
+    minify: true,
+    ..new_variant_emit_options(options, false)
+  };
+  let dev = emit_module(
+    url,
+    parsed.fork(),
+    Rc::new(RefCell::new(resolver.borrow().fork(url))),
+    &dev_options,
+  )?;
+  let prod = emit_module(url, parsed, resolver, &prod_options)?;
+  Ok(DualTransformOutput { dev, prod })
+}
+
+fn transform_module(
+  url: &str,
+  code: &str,
+  resolver: Rc<RefCell<Resolver>>,
+  options: &Options,
+) -> Result<TransformOutput, DiagnosticBuffer> {
+  let key = cache_key(url, code, options);
+  if options.cache_keys.get(url) == Some(&key) {
+    return Ok(TransformOutput {
+      cache_key: Some(key),
+      unchanged: true,
+      ..Default::default()
+    });
+  }
+  if let Some(output) = options
+    .cache_dir
+    .as_ref()
+    .and_then(|cache_dir| read_cache(cache_dir, &key))
+  {
+    return Ok(output);
+  }
+  let parsed = parse_module(url, code, options)?;
+  let output = TransformOutput {
+    cache_key: Some(key),
+    ..emit_module(url, parsed, resolver, &new_emit_options(options))?
+  };
+  if let Some(cache_dir) = &options.cache_dir {
+    write_cache(cache_dir, &output);
+  }
+  Ok(output)
+}
+
+/// A parsed module with the metadata that doesn't depend on the emit options.
+struct ParsedModule {
+  module: SWC,
+  side_effect_free: bool,
+  exports: ExportSignature,
+  refresh_boundary: RefreshBoundary,
+  directive: Option<ModuleDirective>,
+  server_exports: Vec<String>,
+  front_matter: Option<serde_json::Map<String, serde_json::Value>>,
+  route_meta: Option<serde_json::Map<String, serde_json::Value>>,
+  features: Option<UsedFeatures>,
+  stats: Option<TransformStats>,
+}
+
+impl ParsedModule {
+  fn fork(&self) -> Self {
+    ParsedModule {
+      module: self.module.fork(),
+      side_effect_free: self.side_effect_free,
+      exports: self.exports.clone(),
+      refresh_boundary: self.refresh_boundary,
+      directive: self.directive,
+      server_exports: self.server_exports.clone(),
+      front_matter: self.front_matter.clone(),
+      route_meta: self.route_meta.clone(),
+      features: self.features.clone(),
+      stats: self.stats.clone(),
+    }
+  }
+}
+
+fn parse_module(
+  url: &str,
+  code: &str,
+  options: &Options,
+) -> Result<ParsedModule, DiagnosticBuffer> {
+  let start = now();
+  let input_bytes = code.len();
+  // the markdown is compiled into a JSX module first
+  let markdown = if is_markdown_url(url) {
+    Some(compile_markdown(code))
+  } else {
+    None
+  };
+  let (code, source_type) = match &markdown {
+    Some((code, _)) => (code.as_str(), SourceType::JSX),
+    None => (code, options.swc_options.source_type.clone()),
+  };
+  let module = SWC::parse_with_features(
+    url,
+    code,
+    Some(source_type),
+    &options.swc_options.parser_features(),
+  )
+  .map_err(|err| diagnostics_error(url, err))?;
+  let side_effect_free = is_side_effect_free(&module.module);
+  let exports = parse_export_signature(&module.module);
+  let refresh_boundary = refresh_boundary(&module.module);
+  let directive = parse_module_directive(&module.module);
+  let server_exports = parse_server_exports(&module.module);
+  let route_meta = if options.extract_route_meta {
+    Some(parse_route_meta(&module.module)).filter(|meta| !meta.is_empty())
+  } else {
+    None
+  };
+  let features = if options.report_features {
+    Some(detect_features(&module.module))
+  } else {
+    None
+  };
+  Ok(ParsedModule {
+    module,
+    side_effect_free,
+    exports,
+    refresh_boundary,
+    directive,
+    server_exports,
+    front_matter: match markdown {
+      Some((_, front_matter)) if !front_matter.is_empty() => Some(front_matter),
+      _ => None,
+    },
+    route_meta,
+    features,
+    stats: if options.measure {
+      Some(TransformStats {
+        parse_ms: now() - start,
+        input_bytes,
+        ..Default::default()
+      })
+    } else {
+      None
+    },
+  })
+}
+
+fn emit_module(
+  url: &str,
+  parsed: ParsedModule,
+  resolver: Rc<RefCell<Resolver>>,
+  options: &EmitOptions,
+) -> Result<TransformOutput, DiagnosticBuffer> {
+  let ParsedModule {
+    module,
+    side_effect_free,
+    mut exports,
+    refresh_boundary,
+    directive,
+    server_exports,
+    front_matter,
+    route_meta,
+    features,
+    mut stats,
+  } = parsed;
+  // the star exports are resolved by the emit options
+  exports.star_exports = exports
+    .star_exports
+    .iter()
+    .map(|src| resolver.borrow().fix_url(src))
+    .collect();
+  let warnings = module.check_imports(&resolver.borrow(), options);
+  let legal_comments = if options.comments == CommentsMode::Legal {
+    extract_legal_comments(&module.comments)
+  } else {
+    None
+  };
+  let mut unused_stats = TransformStats::default();
   let (code, map) = module
-    .transform(
+    .transform_with_stats(
       resolver.clone(),
-      &EmitOptions {
-        target: options.swc_options.target,
-        jsx_factory: options.swc_options.jsx_factory.clone(),
-        jsx_fragment_factory: options.swc_options.jsx_fragment_factory.clone(),
-        source_map: options.source_map,
-        is_dev: options.is_dev,
-        transpile_only: options.transpile_only,
-        resolve_star_exports: options.resolve_star_exports,
-      },
+      options,
+      stats.as_mut().unwrap_or(&mut unused_stats),
     )
-    .expect("could not transform module");
-  let r = resolver.borrow_mut();
-  Ok(
-    JsValue::from_serde(&TransformOutput {
-      code,
-      map,
-      deps: r.dep_graph.clone(),
-      inline_styles: r.inline_styles.clone(),
-      star_exports: if r.star_exports.len() > 0 {
-        Some(r.star_exports.clone())
-      } else {
-        None
-      },
-    })
-    .unwrap(),
-  )
+    .map_err(|err| diagnostics_error(url, err))?;
+  if let Some(stats) = stats.as_mut() {
+    stats.output_bytes = code.len();
+  }
+  let mut hasher = Sha1::new();
+  hasher.update(code.as_bytes());
+  let hash = format!("{:x}", hasher.finalize());
+  let r = resolver.borrow();
+  Ok(TransformOutput {
+    code,
+    hash,
+    map,
+    side_effect_free,
+    exports,
+    refresh_boundary,
+    directive,
+    server_exports: if !server_exports.is_empty() {
+      Some(server_exports)
+    } else {
+      None
+    },
+    deps: r.dep_graph.clone(),
+    remote_deps: if !r.remote_deps.is_empty() {
+      Some(r.remote_deps.clone())
+    } else {
+      None
+    },
+    preload: if !r.preload.is_empty() {
+      Some(r.preload.clone())
+    } else {
+      None
+    },
+    inline_styles: r.inline_styles.clone(),
+    star_exports: if !r.star_exports.is_empty() {
+      Some(r.star_exports.clone())
+    } else {
+      None
+    },
+    dynamic_import_patterns: if !r.dynamic_import_patterns.is_empty() {
+      Some(r.dynamic_import_patterns.clone())
+    } else {
+      None
+    },
+    deno_hooks: if !r.deno_hooks.is_empty() {
+      Some(r.deno_hooks.clone())
+    } else {
+      None
+    },
+    css_deps: if !r.css_deps.is_empty() {
+      Some(r.css_deps.clone())
+    } else {
+      None
+    },
+    css_modules: if !r.css_modules.is_empty() {
+      Some(r.css_modules.clone())
+    } else {
+      None
+    },
+    scoped_css: if !r.scoped_css.is_empty() {
+      Some(r.scoped_css.clone())
+    } else {
+      None
+    },
+    assets: if !r.assets.is_empty() {
+      Some(r.assets.clone())
+    } else {
+      None
+    },
+    coverage: r.coverage.clone(),
+    front_matter,
+    route_meta,
+    features,
+    warnings: if !warnings.is_empty() {
+      Some(warnings)
+    } else {
+      None
+    },
+    stats,
+    legal_comments,
+    cache_key: None,
+    unchanged: false,
+  })
+}
+
+fn new_emit_options(options: &Options) -> EmitOptions {
+  new_variant_emit_options(options, options.is_dev)
+}
+
+/// the emit options of the dev or prod variant, the flags that depend on the mode are
+/// derived from the `is_dev` of the variant instead of the options.
+fn new_variant_emit_options(options: &Options, is_dev: bool) -> EmitOptions {
+  EmitOptions {
+    target: options.swc_options.target,
+    features: options.swc_options.targets.clone().unwrap_or_default(),
+    jsx: options.swc_options.jsx.clone(),
+    jsx_factory: options.swc_options.jsx_factory.clone(),
+    jsx_fragment_factory: options.swc_options.jsx_fragment_factory.clone(),
+    jsx_runtime: options.swc_options.jsx_runtime.clone(),
+    jsx_import_source: options.swc_options.jsx_import_source.clone(),
+    jsx_source: options.swc_options.jsx_source,
+    jsx_whitespace: options.swc_options.jsx_whitespace,
+    jsx_drop_empty_children: options.swc_options.jsx_drop_empty_children,
+    jsx_key: options.swc_options.jsx_key,
+    jsx_ref: options.swc_options.jsx_ref,
+    module_type: options.swc_options.module_type.clone(),
+    target_env: options.swc_options.target_env.clone(),
+    emit_decorator_metadata: options.swc_options.emit_decorator_metadata,
+    isolated_modules: options.swc_options.isolated_modules,
+    source_map: options.source_map.clone(),
+    input_source_map: options.input_source_map.clone(),
+    inline_sources_content: options.inline_sources_content,
+    source_root: options.source_root.clone(),
+    minify: options.swc_options.minify && !is_dev,
+    // the comments are kept in development
+    comments: if is_dev {
+      CommentsMode::All
+    } else {
+      options.comments
+    },
+    external_helpers: options.swc_options.external_helpers,
+    lower_top_level_await: options.swc_options.lower_top_level_await,
+    lower_class_fields: options.swc_options.lower_class_fields,
+    use_define_for_class_fields: options.swc_options.use_define_for_class_fields,
+    define: options.define.clone(),
+    used_exports: options.used_exports.clone(),
+    side_effect_free_deps: options.side_effect_free_deps.clone(),
+    drop_console: options.drop_console,
+    drop_debugger: options.drop_debugger,
+    strip_data_test_attrs: options.strip_data_test_attrs,
+    strip_calls: options.strip_calls.clone(),
+    keep_fn_names: options.keep_fn_names,
+    keep_class_names: options.keep_class_names,
+    hoist_static_jsx: options.hoist_static_jsx,
+    coverage: options.coverage,
+    refresh: options.refresh.clone(),
+    styled: options.styled.clone(),
+    is_dev,
+    hmr: options.hmr,
+    transpile_only: options.transpile_only,
+    resolve_star_exports: options.resolve_star_exports,
+    extract_css: options.extract_css,
+    scoped_css: options.scoped_css,
+    lazy_preload: options.lazy_preload,
+    strip_server_exports: options.strip_server_exports,
+    css_modules: options.css_modules.clone(),
+    json_modules: options.json_modules.clone(),
+    inline_imports: options.inline_imports.clone(),
+    inline_limit: options.inline_limit.unwrap_or(DEFAULT_INLINE_LIMIT),
+    const_enums: options.const_enums.clone(),
+    import_meta_url: options.import_meta_url.clone(),
+    glob_files: options.glob_files.clone(),
+  }
+}
+
+/// converts an error to the diagnostics of the module.
+fn diagnostics_error(url: &str, err: anyhow::Error) -> DiagnosticBuffer {
+  match err.downcast::<DiagnosticBuffer>() {
+    Ok(buffer) => buffer,
+    Err(err) => {
+      DiagnosticBuffer::from_message(url, DiagnosticCode::TransformError, err.to_string())
+    }
+  }
+}
+
+/// the diagnostics are thrown as a `{ diagnostics }` object in JS.
+impl From<DiagnosticBuffer> for JsValue {
+  fn from(buffer: DiagnosticBuffer) -> Self {
+    JsValue::from_serde(&buffer).unwrap()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn transform_both_variants() {
+    let source = r#"
+      // the greeting
+      export const greeting = () => console.log("hello")
+    "#;
+    for is_dev in [true, false] {
+      let options = Options {
+        is_dev,
+        comments: CommentsMode::None,
+        ..serde_json::from_str("{}").unwrap()
+      };
+      let output = transform_both("/greeting.ts", source, &options).expect("could not transform");
+      // the comments are kept in the dev variant only, whatever the `isDev` is
+      assert!(output.dev.code.contains("// the greeting"));
+      assert!(!output.prod.code.contains("the greeting"));
+      assert!(!output.prod.code.contains("\n  "));
+    }
+  }
+
+  #[test]
+  fn resolve_with_import_map_and_aliases() {
+    let options: Options = serde_json::from_str(
+      r#"{
+        "importMap": { "imports": { "react": "https://esm.sh/react" }, "scopes": {} },
+        "aliases": { "~/*": "./src/*" }
+      }"#,
+    )
+    .unwrap();
+    let output = resolve_specifier("react", "/pages/index.tsx", &options).unwrap();
+    assert_eq!(output.url, "https://esm.sh/react");
+    assert_eq!(output.resolved_path, "../-/esm.sh/react.js");
+    let output = resolve_specifier("~/utils.ts", "/pages/index.tsx", &options).unwrap();
+    assert_eq!(output.url, "/src/utils.ts");
+    assert_eq!(output.resolved_path, "../src/utils.js#/src/utils.ts@000000");
+  }
+
+  #[cfg(feature = "parallel")]
+  #[test]
+  fn transform_modules_in_parallel() {
+    let sources: Vec<TransformInput> = (0..16)
+      .map(|i| TransformInput {
+        filename: format!("/components/c{}.tsx", i),
+        code: match i {
+          5 | 11 => format!("export default function C{}() {{", i),
+          _ => format!(
+            "import {{ c }} from './c{}.tsx'\nexport default () => <p>{{c}} {}</p>",
+            i + 1,
+            i
+          ),
+        },
+      })
+      .collect();
+    let options: Options = serde_json::from_str("{}").unwrap();
+
+    // the first error in the order of the sources is reported, like the sequential path
+    let err = transform_modules(&sources, &options)
+      .err()
+      .expect("the syntax errors should be reported");
+    let sequential = sources
+      .iter()
+      .find_map(|source| transform(&source.filename, &source.code, &options).err())
+      .unwrap();
+    assert_eq!(err.diagnostics[0].file_name, "/components/c5.tsx");
+    assert_eq!(
+      serde_json::to_value(&err).unwrap(),
+      serde_json::to_value(&sequential).unwrap()
+    );
+
+    // the outputs are in the order of the sources
+    let sources: Vec<TransformInput> = sources
+      .into_iter()
+      .filter(|source| !source.code.ends_with('{'))
+      .collect();
+    let outputs = transform_modules(&sources, &options).expect("could not transform modules");
+    assert_eq!(outputs.len(), sources.len());
+    for (source, output) in sources.iter().zip(outputs.iter()) {
+      let sequential = transform(&source.filename, &source.code, &options).unwrap();
+      assert_eq!(
+        serde_json::to_value(output).unwrap(),
+        serde_json::to_value(&sequential).unwrap()
+      );
+    }
+  }
 }
@@ -0,0 +1,530 @@
+use std::collections::{HashMap, HashSet};
+use swc_ecma_ast::*;
+use swc_ecma_utils::{find_ids, id, Id};
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// the reserved words and the globals that can't be used as the mangled names.
+const RESERVED_NAMES: &str = "do if in for let new try var case else enum eval null this true \
+  void with await break catch class const false super throw while yield delete export import \
+  public return static switch typeof default extends finally package private continue debugger \
+  function arguments interface protected implements instanceof undefined NaN Infinity";
+
+/// Creates a fold that mangles the names of the local bindings, like the variables and
+/// the parameters of the functions, to the short names:
+///
+/// ```js
+/// export function greeting(name) {
+///   const message = "Hello " + name
+///   return message
+/// }
+/// ```
+///
+/// is transformed to:
+///
+/// ```js
+/// export function greeting(a) {
+///   const b = "Hello " + a
+///   return b
+/// }
+/// ```
+///
+/// The top-level bindings are kept since they may be exported, the references of the
+/// globals are kept as well. The bindings are told apart by the syntax contexts of the
+/// scope resolver which must run before, every binding gets a name which is not used
+/// anywhere else in the module so the renaming never shadows another binding. The
+/// modules that call `eval` are not mangled.
+pub fn mangle_fold() -> impl Fold {
+  MangleFold {}
+}
+
+struct MangleFold {}
+
+impl Fold for MangleFold {
+  noop_fold_type!();
+
+  fn fold_module(&mut self, module: Module) -> Module {
+    let mut collector = BindingsCollector::default();
+    let module = module.fold_with(&mut collector);
+    if collector.has_eval {
+      return module;
+    }
+    let mut renames = HashMap::new();
+    let mut names = NameGenerator::new(&collector.names);
+    for binding in collector.locals {
+      if !collector.top_level.contains(&binding) && !collector.kept.contains(&binding) {
+        renames.insert(binding, names.next());
+      }
+    }
+    module.fold_with(&mut Renamer { renames })
+  }
+}
+
+/// Collects the local bindings of the module in the order they are declared.
+#[derive(Default)]
+struct BindingsCollector {
+  fn_depth: usize,
+  block_depth: usize,
+  locals: Vec<Id>,
+  local_set: HashSet<Id>,
+  top_level: HashSet<Id>,
+  /// the exported bindings and the JSX elements that need the capitalized names.
+  kept: HashSet<Id>,
+  /// all the names in the module, including the property names.
+  names: HashSet<String>,
+  has_eval: bool,
+}
+
+impl BindingsCollector {
+  fn declare(&mut self, ids: Vec<Id>, is_top_level: bool) {
+    for binding in ids {
+      if is_top_level {
+        self.top_level.insert(binding);
+      } else if self.local_set.insert(binding.clone()) {
+        self.locals.push(binding);
+      }
+    }
+  }
+
+  fn is_top_level_scope(&self) -> bool {
+    self.fn_depth == 0 && self.block_depth == 0
+  }
+}
+
+impl Fold for BindingsCollector {
+  noop_fold_type!();
+
+  fn fold_ident(&mut self, ident: Ident) -> Ident {
+    if ident.sym.eq("eval") {
+      self.has_eval = true;
+    }
+    self.names.insert(ident.sym.as_ref().into());
+    ident
+  }
+
+  fn fold_with_stmt(&mut self, stmt: WithStmt) -> WithStmt {
+    self.has_eval = true;
+    stmt.fold_children_with(self)
+  }
+
+  fn fold_import_decl(&mut self, decl: ImportDecl) -> ImportDecl {
+    let ids = decl
+      .specifiers
+      .iter()
+      .map(|specifier| match specifier {
+        ImportSpecifier::Named(ImportNamedSpecifier { local, .. })
+        | ImportSpecifier::Default(ImportDefaultSpecifier { local, .. })
+        | ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => id(local),
+      })
+      .collect();
+    self.declare(ids, true);
+    decl.fold_children_with(self)
+  }
+
+  fn fold_export_named_specifier(
+    &mut self,
+    specifier: ExportNamedSpecifier,
+  ) -> ExportNamedSpecifier {
+    self.kept.insert(id(&specifier.orig));
+    specifier.fold_children_with(self)
+  }
+
+  fn fold_jsx_element_name(&mut self, name: JSXElementName) -> JSXElementName {
+    if let JSXElementName::Ident(ident) = &name {
+      self.kept.insert(id(ident));
+    }
+    name.fold_children_with(self)
+  }
+
+  fn fold_export_default_decl(&mut self, decl: ExportDefaultDecl) -> ExportDefaultDecl {
+    match &decl.decl {
+      // match: export default function foo() {}
+      DefaultDecl::Fn(FnExpr {
+        ident: Some(ident), ..
+      })
+      // match: export default class Foo {}
+      | DefaultDecl::Class(ClassExpr {
+        ident: Some(ident), ..
+      }) => self.declare(vec![id(ident)], true),
+      _ => {}
+    }
+    decl.fold_children_with(self)
+  }
+
+  fn fold_fn_decl(&mut self, decl: FnDecl) -> FnDecl {
+    self.declare(vec![id(&decl.ident)], self.is_top_level_scope());
+    decl.fold_children_with(self)
+  }
+
+  fn fold_class_decl(&mut self, decl: ClassDecl) -> ClassDecl {
+    self.declare(vec![id(&decl.ident)], self.is_top_level_scope());
+    decl.fold_children_with(self)
+  }
+
+  fn fold_fn_expr(&mut self, expr: FnExpr) -> FnExpr {
+    if let Some(ident) = &expr.ident {
+      self.declare(vec![id(ident)], false);
+    }
+    expr.fold_children_with(self)
+  }
+
+  fn fold_class_expr(&mut self, expr: ClassExpr) -> ClassExpr {
+    if let Some(ident) = &expr.ident {
+      self.declare(vec![id(ident)], false);
+    }
+    expr.fold_children_with(self)
+  }
+
+  fn fold_var_decl(&mut self, decl: VarDecl) -> VarDecl {
+    // the `var` declarations are hoisted to the function scope
+    let is_top_level = match decl.kind {
+      VarDeclKind::Var => self.fn_depth == 0,
+      _ => self.is_top_level_scope(),
+    };
+    self.declare(find_ids(&decl.decls), is_top_level);
+    decl.fold_children_with(self)
+  }
+
+  fn fold_function(&mut self, function: Function) -> Function {
+    self.fn_depth += 1;
+    self.declare(find_ids(&function.params), false);
+    let function = function.fold_children_with(self);
+    self.fn_depth -= 1;
+    function
+  }
+
+  fn fold_arrow_expr(&mut self, expr: ArrowExpr) -> ArrowExpr {
+    self.fn_depth += 1;
+    self.declare(find_ids(&expr.params), false);
+    let expr = expr.fold_children_with(self);
+    self.fn_depth -= 1;
+    expr
+  }
+
+  fn fold_constructor(&mut self, constructor: Constructor) -> Constructor {
+    self.fn_depth += 1;
+    self.declare(find_ids(&constructor.params), false);
+    let constructor = constructor.fold_children_with(self);
+    self.fn_depth -= 1;
+    constructor
+  }
+
+  fn fold_setter_prop(&mut self, prop: SetterProp) -> SetterProp {
+    self.fn_depth += 1;
+    self.declare(find_ids(&prop.param), false);
+    let prop = prop.fold_children_with(self);
+    self.fn_depth -= 1;
+    prop
+  }
+
+  fn fold_getter_prop(&mut self, prop: GetterProp) -> GetterProp {
+    self.fn_depth += 1;
+    let prop = prop.fold_children_with(self);
+    self.fn_depth -= 1;
+    prop
+  }
+
+  fn fold_catch_clause(&mut self, clause: CatchClause) -> CatchClause {
+    if let Some(param) = &clause.param {
+      self.declare(find_ids(param), false);
+    }
+    self.block_depth += 1;
+    let clause = clause.fold_children_with(self);
+    self.block_depth -= 1;
+    clause
+  }
+
+  fn fold_block_stmt(&mut self, block: BlockStmt) -> BlockStmt {
+    self.block_depth += 1;
+    let block = block.fold_children_with(self);
+    self.block_depth -= 1;
+    block
+  }
+}
+
+/// generates the shortest names which are not used in the module.
+struct NameGenerator<'a> {
+  used: &'a HashSet<String>,
+  index: usize,
+}
+
+impl<'a> NameGenerator<'a> {
+  const FIRST_CHARS: &'static [u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ$_";
+  const CHARS: &'static [u8] = b"abcdefghijklmnopqrstuvwxyzABCDEFGHIJKLMNOPQRSTUVWXYZ$_0123456789";
+
+  fn new(used: &'a HashSet<String>) -> Self {
+    NameGenerator { used, index: 0 }
+  }
+
+  fn next(&mut self) -> String {
+    loop {
+      let name = Self::name_of(self.index);
+      self.index += 1;
+      if !self.used.contains(&name)
+        && !RESERVED_NAMES
+          .split_whitespace()
+          .any(|reserved| reserved == name)
+      {
+        return name;
+      }
+    }
+  }
+
+  /// match: a, b, ..., _, aa, ba, ..., __, aaa
+  fn name_of(index: usize) -> String {
+    let mut name = String::new();
+    let mut n = index;
+    name.push(Self::FIRST_CHARS[n % Self::FIRST_CHARS.len()] as char);
+    n /= Self::FIRST_CHARS.len();
+    while n > 0 {
+      n -= 1;
+      name.push(Self::CHARS[n % Self::CHARS.len()] as char);
+      n /= Self::CHARS.len();
+    }
+    name
+  }
+}
+
+/// renames the bindings and their references, the property names are kept.
+struct Renamer {
+  renames: HashMap<Id, String>,
+}
+
+impl Renamer {
+  fn renamed(&self, ident: &Ident) -> Option<Ident> {
+    let name = self.renames.get(&id(ident))?;
+    Some(Ident {
+      sym: name.as_str().into(),
+      ..ident.clone()
+    })
+  }
+}
+
+impl Fold for Renamer {
+  noop_fold_type!();
+
+  fn fold_ident(&mut self, ident: Ident) -> Ident {
+    self.renamed(&ident).unwrap_or(ident)
+  }
+
+  fn fold_prop(&mut self, prop: Prop) -> Prop {
+    match prop {
+      // match: { a } -> { a: b }
+      Prop::Shorthand(ident) => match self.renamed(&ident) {
+        Some(renamed) => Prop::KeyValue(KeyValueProp {
+          key: PropName::Ident(ident),
+          value: Box::new(Expr::Ident(renamed)),
+        }),
+        None => Prop::Shorthand(ident),
+      },
+      _ => prop.fold_children_with(self),
+    }
+  }
+
+  fn fold_object_pat_prop(&mut self, prop: ObjectPatProp) -> ObjectPatProp {
+    match prop {
+      // match: const { a = 1 } = obj -> const { a: b = 1 } = obj
+      ObjectPatProp::Assign(AssignPatProp { span, key, value }) => match self.renamed(&key) {
+        Some(renamed) => {
+          let left = Pat::Ident(BindingIdent {
+            id: renamed,
+            type_ann: None,
+          });
+          let value = match value {
+            Some(value) => Pat::Assign(AssignPat {
+              span,
+              left: Box::new(left),
+              right: value.fold_with(self),
+              type_ann: None,
+            }),
+            None => left,
+          };
+          ObjectPatProp::KeyValue(KeyValuePatProp {
+            key: PropName::Ident(key),
+            value: Box::new(value),
+          })
+        }
+        None => ObjectPatProp::Assign(AssignPatProp {
+          span,
+          key,
+          value: value.fold_with(self),
+        }),
+      },
+      _ => prop.fold_children_with(self),
+    }
+  }
+
+  fn fold_prop_name(&mut self, name: PropName) -> PropName {
+    match name {
+      PropName::Computed(computed) => PropName::Computed(computed.fold_with(self)),
+      _ => name,
+    }
+  }
+
+  fn fold_member_expr(&mut self, expr: MemberExpr) -> MemberExpr {
+    let prop = if expr.computed {
+      expr.prop.fold_with(self)
+    } else {
+      expr.prop
+    };
+    MemberExpr {
+      obj: expr.obj.fold_with(self),
+      prop,
+      ..expr
+    }
+  }
+
+  fn fold_class_prop(&mut self, prop: ClassProp) -> ClassProp {
+    let key = if prop.computed {
+      prop.key.fold_with(self)
+    } else {
+      prop.key
+    };
+    ClassProp {
+      key,
+      value: prop.value.fold_with(self),
+      ..prop
+    }
+  }
+
+  fn fold_private_name(&mut self, name: PrivateName) -> PrivateName {
+    name
+  }
+
+  fn fold_meta_prop_expr(&mut self, expr: MetaPropExpr) -> MetaPropExpr {
+    expr
+  }
+
+  fn fold_labeled_stmt(&mut self, stmt: LabeledStmt) -> LabeledStmt {
+    LabeledStmt {
+      body: stmt.body.fold_with(self),
+      ..stmt
+    }
+  }
+
+  fn fold_break_stmt(&mut self, stmt: BreakStmt) -> BreakStmt {
+    stmt
+  }
+
+  fn fold_continue_stmt(&mut self, stmt: ContinueStmt) -> ContinueStmt {
+    stmt
+  }
+
+  fn fold_export_named_specifier(
+    &mut self,
+    specifier: ExportNamedSpecifier,
+  ) -> ExportNamedSpecifier {
+    specifier
+  }
+
+  fn fold_jsx_attr_name(&mut self, name: JSXAttrName) -> JSXAttrName {
+    name
+  }
+
+  fn fold_jsx_member_expr(&mut self, expr: JSXMemberExpr) -> JSXMemberExpr {
+    JSXMemberExpr {
+      obj: expr.obj.fold_with(self),
+      ..expr
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::swc::{test_resolver, EmitOptions, JsxMode, SWC};
+
+  fn transform(source: &str) -> String {
+    transform_with(source, JsxMode::Transform)
+  }
+
+  fn transform_with(source: &str, jsx_mode: JsxMode) -> String {
+    let module = SWC::parse("/app.jsx", source, None).expect("could not parse module");
+    let resolver = test_resolver("/app.jsx");
+    let (code, _) = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          minify: true,
+          jsx: jsx_mode,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    code
+  }
+
+  #[test]
+  fn mangle_locals() {
+    let code = transform(
+      r#"
+      const a = 1
+      export function greeting(name, { title, suffix = "!" }) {
+        const message = "Hello " + title + name + suffix + a
+        const user = { name, message }
+        label: for (const item of [message]) {
+          if (item.length) break label
+        }
+        return user.message + console.log(message)
+      }
+      export const add = (left, right) => left + right
+    "#,
+    );
+    assert!(code.contains("const a=1"));
+    assert!(code.contains("export function greeting("));
+    assert!(code.contains("export const add=("));
+    assert!(!code.contains("left"));
+    assert!(!code.contains("suffix="));
+    assert!(code.contains("title:"));
+    assert!(code.contains("suffix:"));
+    assert!(code.contains("name:"));
+    assert!(code.contains("message:"));
+    assert!(code.contains(".message+console.log("));
+    assert!(code.contains("label:for(const"));
+    assert!(code.contains("break label"));
+  }
+
+  #[test]
+  fn mangle_without_shadowing() {
+    let code = transform(
+      r#"
+      export function outer(value) {
+        return function inner(b) {
+          return value + b + globalThis.c
+        }
+      }
+    "#,
+    );
+    assert!(!code.contains("value"));
+    assert!(!code.contains("inner"));
+    assert!(code.contains("globalThis.c"));
+    // `b` and `c` are used in the module
+    assert!(code.contains("outer(a){return function d(e){return a+e+globalThis.c;};}"));
+  }
+
+  #[test]
+  fn keep_jsx_element_names() {
+    let code = transform_with(
+      r#"
+      export function App({ items }) {
+        const Item = ({ item }) => <li>{item}</li>
+        return <ul>{items.map(item => <Item item={item} />)}</ul>
+      }
+    "#,
+      JsxMode::Preserve,
+    );
+    assert!(code.contains("<Item item={"));
+    assert!(!code.contains("items.map"));
+  }
+
+  #[test]
+  fn skip_eval() {
+    let code = transform(
+      r#"
+      export function run(code) {
+        return eval(code)
+      }
+    "#,
+    );
+    assert!(code.contains("function run(code){return eval(code);}"));
+  }
+}
@@ -0,0 +1,192 @@
+use crate::resolve::{DependencyDescriptor, Resolver};
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+/// The manifest of a build, which maps the modules to the emitted files, for the
+/// `export` command to write the files and the server to find the chunks.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BuildManifest {
+  /// the emitted files keyed by the specifier (the fixed url) of the modules, the
+  /// bundled modules share the file of the bundle.
+  pub modules: BTreeMap<String, ManifestEntry>,
+  /// the emitted files of the dynamic imports keyed by the chunk name, like
+  /// `import(/* alephChunkName: "admin" */ "./admin.tsx")`.
+  pub chunks: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+  /// the emitted file name with the content hash, like `/pages/index.1a2b3c4d.js`.
+  pub file: String,
+  /// the hex sha-1 of the emitted code.
+  pub hash: String,
+  /// the emitted files that the dynamic imports of the module landed in, keyed by the
+  /// specifier of the import.
+  #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+  pub dynamic_imports: BTreeMap<String, String>,
+}
+
+/// A file that is emitted by a build, a transformed module or a bundle.
+pub struct EmittedFile<'a> {
+  pub specifier: &'a str,
+  pub hash: &'a str,
+  pub deps: &'a [DependencyDescriptor],
+  /// the modules that are bundled into the file, besides the `specifier`.
+  pub bundled: &'a [String],
+}
+
+/// Builds the manifest of the emitted `files`. The file names get the first 8 chars
+/// of the hash before the extension, the dynamic imports of the modules that are not
+/// emitted by the build (like the remote modules) land in the files without a hash.
+pub fn build_manifest(resolver: &Resolver, files: &[EmittedFile]) -> BuildManifest {
+  let mut file_names: HashMap<&str, String> = HashMap::new();
+  for file in files.iter() {
+    let file_name = hashed_file_name(&resolver.output_path(file.specifier), file.hash);
+    for specifier in file.bundled.iter() {
+      file_names.insert(specifier.as_str(), file_name.clone());
+    }
+    file_names.insert(file.specifier, file_name);
+  }
+  let mut manifest = BuildManifest::default();
+  let mut chunks: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+  for file in files.iter() {
+    let file_name = file_names[file.specifier].clone();
+    let mut dynamic_imports = BTreeMap::new();
+    for dep in file.deps.iter().filter(|dep| dep.is_dynamic) {
+      let chunk = match file_names.get(dep.specifier.as_str()) {
+        Some(file_name) => file_name.clone(),
+        None => resolver.output_path(dep.specifier.as_str()),
+      };
+      if let Some(chunk_name) = &dep.chunk_name {
+        chunks
+          .entry(chunk_name.clone())
+          .or_default()
+          .insert(chunk.clone());
+      }
+      dynamic_imports.insert(dep.specifier.clone(), chunk);
+    }
+    for specifier in file.bundled.iter() {
+      manifest.modules.insert(
+        specifier.clone(),
+        ManifestEntry {
+          file: file_name.clone(),
+          hash: file.hash.into(),
+          dynamic_imports: BTreeMap::new(),
+        },
+      );
+    }
+    manifest.modules.insert(
+      file.specifier.into(),
+      ManifestEntry {
+        file: file_name,
+        hash: file.hash.into(),
+        dynamic_imports,
+      },
+    );
+  }
+  manifest.chunks = chunks
+    .into_iter()
+    .map(|(name, files)| (name, files.into_iter().collect()))
+    .collect();
+  manifest
+}
+
+/// inserts the short hash before the extension of the file name, like
+/// `/pages/index.js` -> `/pages/index.1a2b3c4d.js`.
+fn hashed_file_name(path: &str, hash: &str) -> String {
+  let hash = &hash[..hash.len().min(8)];
+  let file_start = path.rfind('/').map_or(0, |i| i + 1);
+  match path[file_start..].rfind('.') {
+    Some(i) if i > 0 => format!(
+      "{}.{}{}",
+      &path[..file_start + i],
+      hash,
+      &path[file_start + i..]
+    ),
+    _ => format!("{}.{}", path, hash),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::{build_modules, bundle_modules, Options, TransformInput};
+
+  #[test]
+  fn build_manifest() {
+    let sources = vec![
+      TransformInput {
+        filename: "/pages/index.tsx".into(),
+        code: r#"
+          const Admin = () => import(/* alephChunkName: "admin" */ "./admin.tsx")
+          const Chart = () => import(/* alephChunkName: "admin" */ "https://esm.sh/chart.js")
+          export default () => [Admin, Chart]
+        "#
+        .into(),
+      },
+      TransformInput {
+        filename: "/pages/admin.tsx".into(),
+        code: "export default () => null".into(),
+      },
+    ];
+    let options: Options = serde_json::from_str("{}").unwrap();
+    let output = build_modules(&sources, &options).expect("could not build modules");
+    let manifest = output.manifest;
+    let index = &manifest.modules["/pages/index.tsx"];
+    assert_eq!(
+      index.file,
+      format!("/pages/index.{}.js", &output.modules[0].hash[..8])
+    );
+    assert_eq!(index.hash, output.modules[0].hash);
+    let admin_file = format!("/pages/admin.{}.js", &output.modules[1].hash[..8]);
+    assert_eq!(manifest.modules["/pages/admin.tsx"].file, admin_file);
+    assert!(manifest.modules["/pages/admin.tsx"]
+      .dynamic_imports
+      .is_empty());
+    assert_eq!(
+      index.dynamic_imports["/pages/admin.tsx"],
+      admin_file.as_str()
+    );
+    assert_eq!(
+      index.dynamic_imports["https://esm.sh/chart.js"],
+      "/-/esm.sh/chart.js"
+    );
+    assert_eq!(
+      manifest.chunks["admin"],
+      vec!["/-/esm.sh/chart.js".to_owned(), admin_file]
+    );
+
+    let mut modules = HashMap::new();
+    modules.insert(
+      "/pages/about.tsx".into(),
+      r#"
+        import { title } from "../lib/title.ts"
+        export const load = () => import("./team.tsx")
+        export default title
+      "#
+      .into(),
+    );
+    modules.insert(
+      "/lib/title.ts".into(),
+      r#"export const title = "About""#.into(),
+    );
+    let output =
+      bundle_modules("/pages/about.tsx", &modules, &options).expect("could not bundle modules");
+    let about = &output.manifest.modules["/pages/about.tsx"];
+    assert!(about.file.starts_with("/pages/about."));
+    assert_eq!(output.manifest.modules["/lib/title.ts"].file, about.file);
+    assert_eq!(about.dynamic_imports["/pages/team.tsx"], "/pages/team.js");
+
+    assert_eq!(
+      hashed_file_name("/-/esm.sh/react.js", "1a2b3c4d5e"),
+      "/-/esm.sh/react.1a2b3c4d.js"
+    );
+    assert_eq!(
+      hashed_file_name("/.aleph/LICENSE", "1a2b"),
+      "/.aleph/LICENSE.1a2b"
+    );
+  }
+}
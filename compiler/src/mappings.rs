@@ -0,0 +1,267 @@
+use indexmap::IndexMap;
+use serde::Serialize;
+use sourcemap::{SourceMap, Token};
+use std::{
+  cell::RefCell,
+  collections::hash_map::DefaultHasher,
+  hash::{Hash, Hasher},
+};
+
+/// The default number of the parsed source maps that are kept for the lookups.
+pub const DEFAULT_SOURCE_MAP_CACHE_SIZE: usize = 16;
+
+thread_local! {
+  /// the symbolication of a stack trace looks up the same source maps again and
+  /// again, so the parsed maps are kept instead of parsing the JSON for every frame.
+  /// the parsed maps can't be shared between the threads, the native backend keeps a
+  /// cache for each thread.
+  static SOURCE_MAPS: RefCell<SourceMapCache> =
+    RefCell::new(SourceMapCache::new(DEFAULT_SOURCE_MAP_CACHE_SIZE));
+}
+
+/// A position in the original source, the `line` is 1-based and the `column` is
+/// 0-based, like the positions of the diagnostics.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OriginalPosition {
+  pub source: String,
+  pub line: u32,
+  pub column: u32,
+  pub name: Option<String>,
+}
+
+/// A position in the generated code, the `line` is 1-based and the `column` is 0-based.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GeneratedPosition {
+  pub line: u32,
+  pub column: u32,
+}
+
+/// The parsed source maps keyed by the hash and the length of the JSON, the least
+/// recently used maps are dropped when there are more than the `capacity`.
+pub struct SourceMapCache {
+  capacity: usize,
+  maps: IndexMap<(u64, usize), SourceMap>,
+}
+
+impl SourceMapCache {
+  pub fn new(capacity: usize) -> Self {
+    SourceMapCache {
+      capacity,
+      maps: IndexMap::new(),
+    }
+  }
+
+  /// sets the capacity, `0` disables the cache.
+  pub fn set_capacity(&mut self, capacity: usize) {
+    self.capacity = capacity;
+    self.evict();
+  }
+
+  /// drops all the maps, returns the number of the dropped maps.
+  pub fn clear(&mut self) -> usize {
+    let len = self.maps.len();
+    self.maps = IndexMap::new();
+    len
+  }
+
+  pub fn len(&self) -> usize {
+    self.maps.len()
+  }
+
+  /// calls the `f` with the parsed source `map`, which is parsed only if it's not
+  /// in the cache.
+  pub fn with_source_map<T>(
+    &mut self,
+    map: &str,
+    f: impl FnOnce(&SourceMap) -> T,
+  ) -> Result<T, anyhow::Error> {
+    let mut hasher = DefaultHasher::new();
+    map.hash(&mut hasher);
+    let key = (hasher.finish(), map.len());
+    // the map is inserted again as the most recently used one
+    let source_map = match self.maps.shift_remove(&key) {
+      Some(source_map) => source_map,
+      None => parse_source_map(map)?,
+    };
+    let output = f(&source_map);
+    if self.capacity > 0 {
+      self.maps.insert(key, source_map);
+      self.evict();
+    }
+    Ok(output)
+  }
+
+  fn evict(&mut self) {
+    while self.maps.len() > self.capacity {
+      self.maps.shift_remove_index(0);
+    }
+  }
+}
+
+/// calls the `f` with the cache of the parsed source maps of the current thread.
+pub fn with_source_maps<T>(f: impl FnOnce(&mut SourceMapCache) -> T) -> T {
+  SOURCE_MAPS.with(|cache| f(&mut cache.borrow_mut()))
+}
+
+fn with_cached_source_map<T>(
+  map: &str,
+  f: impl FnOnce(&SourceMap) -> T,
+) -> Result<T, anyhow::Error> {
+  with_source_maps(|cache| cache.with_source_map(map, f))
+}
+
+fn parse_source_map(map: &str) -> Result<SourceMap, anyhow::Error> {
+  SourceMap::from_slice(map.as_bytes())
+    .map_err(|err| anyhow::anyhow!("invalid source map: {}", err))
+}
+
+/// Looks up the original position of the generated position in the source `map`, for
+/// symbolicating the stack traces. The closest mapping before the `column` on the
+/// same line is used, returns `None` if the line has no mappings.
+pub fn lookup_original_position(
+  map: &str,
+  line: u32,
+  column: u32,
+) -> Result<Option<OriginalPosition>, anyhow::Error> {
+  if line == 0 {
+    return Ok(None);
+  }
+  with_cached_source_map(map, |map| {
+    let token = match map.lookup_token(line - 1, column) {
+      Some(token) if token.get_dst_line() == line - 1 => token,
+      _ => return None,
+    };
+    token.get_source().map(|source| OriginalPosition {
+      source: source.into(),
+      line: token.get_src_line() + 1,
+      column: token.get_src_col(),
+      name: token.get_name().map(|name| name.into()),
+    })
+  })
+}
+
+/// Looks up the generated position of the original position of the `source` in the
+/// source `map`, the inverse of `lookup_original_position`. The closest mapping
+/// before the `column` on the same line is used, or the first mapping after it.
+pub fn lookup_generated_position(
+  map: &str,
+  source: &str,
+  line: u32,
+  column: u32,
+) -> Result<Option<GeneratedPosition>, anyhow::Error> {
+  if line == 0 {
+    return Ok(None);
+  }
+  with_cached_source_map(map, |map| {
+    let mut before: Option<Token> = None;
+    let mut after: Option<Token> = None;
+    for token in map.tokens() {
+      if token.get_source() != Some(source) || token.get_src_line() != line - 1 {
+        continue;
+      }
+      let col = token.get_src_col();
+      // the tokens are in the generated order, the first one of the same column wins
+      if col <= column {
+        if !matches!(before, Some(t) if t.get_src_col() >= col) {
+          before = Some(token);
+        }
+      } else if !matches!(after, Some(t) if t.get_src_col() <= col) {
+        after = Some(token);
+      }
+    }
+    before.or(after).map(|token| GeneratedPosition {
+      line: token.get_dst_line() + 1,
+      column: token.get_dst_col(),
+    })
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::swc::{test_resolver, EmitOptions, SourceMapMode, SWC};
+
+  #[test]
+  fn lookup_positions() {
+    let source =
+      "type Name = string\n\nexport function greet(name: Name) {\n  return 'Hello ' + name\n}\n";
+    let module = SWC::parse("/greet.ts", source, None).expect("could not parse module");
+    let resolver = test_resolver("/greet.ts");
+    let (code, map) = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          source_map: SourceMapMode::External,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    let map = map.unwrap();
+    println!("{}", code);
+    assert!(code.starts_with("export function greet(name) {\n    return 'Hello ' + name;"));
+
+    // `name` of `+ name` at the line 2 of the output
+    let column = code.lines().nth(1).unwrap().rfind("name").unwrap() as u32;
+    let original = lookup_original_position(map.as_str(), 2, column)
+      .unwrap()
+      .expect("missing original position");
+    assert_eq!(original.source, "/greet.ts");
+    assert_eq!(original.line, 4);
+    assert_eq!(original.column, 20);
+    assert_eq!(
+      lookup_generated_position(map.as_str(), "/greet.ts", 4, 20).unwrap(),
+      Some(GeneratedPosition { line: 2, column })
+    );
+
+    assert_eq!(
+      lookup_original_position(map.as_str(), 100, 0).unwrap(),
+      None
+    );
+    assert_eq!(
+      lookup_generated_position(map.as_str(), "/other.ts", 4, 20).unwrap(),
+      None
+    );
+    assert!(lookup_original_position("{", 1, 0).is_err());
+
+    let mut cache = SourceMapCache::new(2);
+    let maps: Vec<String> = (0..3)
+      .map(|i| {
+        format!(
+          r#"{{"version":3,"sources":["/{}.ts"],"names":[],"mappings":"AAAA"}}"#,
+          i
+        )
+      })
+      .collect();
+    let source = |cache: &mut SourceMapCache, map: &str| {
+      cache
+        .with_source_map(map, |map| map.get_source(0).unwrap().to_owned())
+        .unwrap()
+    };
+    assert_eq!(source(&mut cache, &maps[0]), "/0.ts");
+    assert_eq!(source(&mut cache, &maps[1]), "/1.ts");
+    assert_eq!(source(&mut cache, &maps[0]), "/0.ts");
+    assert_eq!(cache.len(), 2);
+    // the least recently used `/1.ts` is dropped
+    assert_eq!(source(&mut cache, &maps[2]), "/2.ts");
+    let sources = |cache: &SourceMapCache| -> Vec<String> {
+      cache
+        .maps
+        .values()
+        .map(|map| map.get_source(0).unwrap().to_owned())
+        .collect()
+    };
+    assert_eq!(sources(&cache), vec!["/0.ts", "/2.ts"]);
+    assert!(cache.with_source_map("{", |_| {}).is_err());
+    assert_eq!(sources(&cache), vec!["/0.ts", "/2.ts"]);
+    cache.set_capacity(1);
+    assert_eq!(sources(&cache), vec!["/2.ts"]);
+    assert_eq!(source(&mut cache, &maps[1]), "/1.ts");
+    assert_eq!(sources(&cache), vec!["/1.ts"]);
+    assert_eq!(cache.clear(), 1);
+    cache.set_capacity(0);
+    assert_eq!(source(&mut cache, &maps[0]), "/0.ts");
+    assert_eq!(cache.len(), 0);
+  }
+}
@@ -0,0 +1,1209 @@
+use serde_json::{Map, Number, Value};
+
+/// checks if the url is a markdown module, like `/pages/docs.md`.
+pub fn is_markdown_url(url: &str) -> bool {
+  url.ends_with(".md") || url.ends_with(".markdown")
+}
+
+/// Compiles the markdown into a JSX module that exports the page component by
+/// default, the front-matter (a YAML block fenced by `---` at the beginning) is
+/// returned and attached to the component as `frontMatter`:
+///
+/// ```jsx
+/// import React from "https://esm.sh/react";
+///
+/// export default function MarkdownPage(props) {
+///   return (
+///     <div className="markdown-page" {...props}>
+///       <h1 id="hello-world">{"Hello World"}</h1>
+///     </div>
+///   );
+/// }
+///
+/// MarkdownPage.frontMatter = {"title":"Hello"};
+/// ```
+///
+/// The CommonMark blocks and inlines are supported except the raw HTML, which is
+/// rendered as text.
+pub fn compile_markdown(source: &str) -> (String, Map<String, Value>) {
+  let (front_matter, body) = split_front_matter(source);
+  let front_matter = match front_matter {
+    Some(yaml) => parse_front_matter(yaml),
+    None => Map::new(),
+  };
+  let lines: Vec<String> = body
+    .lines()
+    .map(|line| line.replace('\t', "    "))
+    .collect();
+  let mut jsx = String::new();
+  render_blocks(&lines, false, &mut jsx);
+  let code = format!(
+    r#"import React from "https://esm.sh/react";
+
+export default function MarkdownPage(props) {{
+  return (
+    <div className="markdown-page" {{...props}}>
+{}    </div>
+  );
+}}
+
+MarkdownPage.frontMatter = {};
+"#,
+    jsx,
+    Value::Object(front_matter.clone())
+  );
+  (code, front_matter)
+}
+
+fn split_front_matter(source: &str) -> (Option<&str>, &str) {
+  let source = source.trim_start_matches('\u{feff}');
+  if let Some(rest) = source
+    .strip_prefix("---\n")
+    .or_else(|| source.strip_prefix("---\r\n"))
+  {
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+      if line.trim_end() == "---" {
+        return (Some(&rest[..offset]), &rest[offset + line.len()..]);
+      }
+      offset += line.len();
+    }
+  }
+  (None, source)
+}
+
+/* front-matter */
+
+/// parses the subset of YAML that is used by the front-matter: the block mappings
+/// and sequences, the flow collections and the scalars.
+fn parse_front_matter(yaml: &str) -> Map<String, Value> {
+  let mut lines: Vec<(usize, String)> = vec![];
+  for line in yaml.lines() {
+    let content = line.trim_start();
+    if content.is_empty() || content.starts_with('#') {
+      continue;
+    }
+    lines.push((line.len() - content.len(), content.trim_end().into()));
+  }
+  let mut i = 0;
+  let indent = lines.first().map_or(0, |(indent, _)| *indent);
+  parse_yaml_mapping(&mut lines, &mut i, indent)
+}
+
+fn parse_yaml_mapping(
+  lines: &mut [(usize, String)],
+  i: &mut usize,
+  indent: usize,
+) -> Map<String, Value> {
+  let mut map = Map::new();
+  while *i < lines.len() && lines[*i].0 == indent {
+    let (key, rest) = match split_yaml_key(lines[*i].1.as_str()) {
+      Some((key, rest)) => (key, rest),
+      None => {
+        *i += 1;
+        continue;
+      }
+    };
+    *i += 1;
+    let value = match rest.as_str() {
+      "" => parse_yaml_block(lines, i, indent),
+      "|" | ">" => {
+        let mut text: Vec<String> = vec![];
+        let mut base: Option<usize> = None;
+        while *i < lines.len() && lines[*i].0 > indent {
+          let (line_indent, content) = &lines[*i];
+          let base = *base.get_or_insert(*line_indent);
+          text.push(" ".repeat(line_indent.saturating_sub(base)) + content);
+          *i += 1;
+        }
+        if rest == "|" {
+          Value::String(text.join("\n") + "\n")
+        } else {
+          Value::String(text.join(" ") + "\n")
+        }
+      }
+      _ => parse_yaml_scalar(rest.as_str()),
+    };
+    map.insert(key, value);
+  }
+  map
+}
+
+/// parses the nested block of a key without inline value.
+fn parse_yaml_block(lines: &mut [(usize, String)], i: &mut usize, indent: usize) -> Value {
+  if *i < lines.len() {
+    let (line_indent, content) = &lines[*i];
+    let line_indent = *line_indent;
+    let is_sequence = content == "-" || content.starts_with("- ");
+    // the sequence of a mapping can be at the same indent of the key
+    if is_sequence && line_indent >= indent {
+      return Value::Array(parse_yaml_sequence(lines, i, line_indent));
+    }
+    if line_indent > indent {
+      return Value::Object(parse_yaml_mapping(lines, i, line_indent));
+    }
+  }
+  Value::Null
+}
+
+fn parse_yaml_sequence(lines: &mut [(usize, String)], i: &mut usize, indent: usize) -> Vec<Value> {
+  let mut seq = vec![];
+  while *i < lines.len() && lines[*i].0 == indent {
+    let content = lines[*i].1.clone();
+    if content != "-" && !content.starts_with("- ") {
+      break;
+    }
+    let item = content[1..].trim_start();
+    if item.is_empty() {
+      *i += 1;
+      seq.push(parse_yaml_block(lines, i, indent + 1));
+    } else if split_yaml_key(item).is_some() {
+      // match: `- name: aleph`, the item is a mapping at the indent of the key
+      let item_indent = indent + (content.len() - item.len());
+      lines[*i] = (item_indent, item.into());
+      seq.push(Value::Object(parse_yaml_mapping(lines, i, item_indent)));
+    } else {
+      *i += 1;
+      seq.push(parse_yaml_scalar(item));
+    }
+  }
+  seq
+}
+
+fn split_yaml_key(content: &str) -> Option<(String, String)> {
+  if content.starts_with('"') || content.starts_with('\'') {
+    let quote = content.chars().next().unwrap();
+    let end = content[1..].find(quote)? + 1;
+    let rest = content[end + 1..].trim_start();
+    let rest = rest.strip_prefix(':')?;
+    return Some((
+      content[1..end].into(),
+      strip_yaml_comment(rest).trim().into(),
+    ));
+  }
+  let colon = match content.find(": ") {
+    Some(colon) => colon,
+    None if content.ends_with(':') => content.len() - 1,
+    None => return None,
+  };
+  let key = content[..colon].trim();
+  if key.is_empty() || key.starts_with("- ") || key.starts_with('[') || key.starts_with('{') {
+    return None;
+  }
+  Some((
+    key.into(),
+    strip_yaml_comment(&content[colon + 1..]).trim().into(),
+  ))
+}
+
+fn strip_yaml_comment(value: &str) -> &str {
+  if value.trim_start().starts_with('"') || value.trim_start().starts_with('\'') {
+    return value;
+  }
+  match value.find(" #") {
+    Some(idx) => &value[..idx],
+    None => value,
+  }
+}
+
+fn parse_yaml_scalar(value: &str) -> Value {
+  let value = value.trim();
+  if value.len() >= 2 && value.starts_with('"') && value.ends_with('"') {
+    return serde_json::from_str(value)
+      .unwrap_or_else(|_| Value::String(value[1..value.len() - 1].into()));
+  }
+  if value.len() >= 2 && value.starts_with('\'') && value.ends_with('\'') {
+    return Value::String(value[1..value.len() - 1].replace("''", "'"));
+  }
+  if value.len() >= 2 && value.starts_with('[') && value.ends_with(']') {
+    return Value::Array(
+      split_yaml_flow(&value[1..value.len() - 1])
+        .iter()
+        .map(|item| parse_yaml_scalar(item))
+        .collect(),
+    );
+  }
+  if value.len() >= 2 && value.starts_with('{') && value.ends_with('}') {
+    let mut map = Map::new();
+    for item in split_yaml_flow(&value[1..value.len() - 1]) {
+      if let Some((key, rest)) = split_yaml_key(item.as_str()) {
+        map.insert(key, parse_yaml_scalar(rest.as_str()));
+      }
+    }
+    return Value::Object(map);
+  }
+  match value {
+    "" | "~" | "null" | "Null" | "NULL" => return Value::Null,
+    "true" | "True" | "TRUE" => return Value::Bool(true),
+    "false" | "False" | "FALSE" => return Value::Bool(false),
+    _ => {}
+  }
+  if let Ok(n) = value.parse::<i64>() {
+    return Value::Number(n.into());
+  }
+  if value.contains('.')
+    && value
+      .chars()
+      .all(|c| c.is_ascii_digit() || "+-.eE".contains(c))
+  {
+    if let Some(n) = value.parse::<f64>().ok().and_then(Number::from_f64) {
+      return Value::Number(n);
+    }
+  }
+  Value::String(value.into())
+}
+
+/// splits the items of a flow collection by the top-level commas.
+fn split_yaml_flow(value: &str) -> Vec<String> {
+  let mut items = vec![];
+  let mut item = String::new();
+  let mut depth = 0;
+  let mut quote: Option<char> = None;
+  for c in value.chars() {
+    match quote {
+      Some(q) => {
+        if c == q {
+          quote = None;
+        }
+      }
+      None => match c {
+        '"' | '\'' => quote = Some(c),
+        '[' | '{' => depth += 1,
+        ']' | '}' => depth -= 1,
+        ',' if depth == 0 => {
+          items.push(item.trim().to_owned());
+          item.clear();
+          continue;
+        }
+        _ => {}
+      },
+    }
+    item.push(c);
+  }
+  if !item.trim().is_empty() {
+    items.push(item.trim().to_owned());
+  }
+  items
+}
+
+/* blocks */
+
+fn render_blocks(lines: &[String], tight: bool, out: &mut String) {
+  let mut i = 0;
+  while i < lines.len() {
+    let line = lines[i].as_str();
+    let content = line.trim_start();
+    let indent = line.len() - content.len();
+    if content.is_empty() {
+      i += 1;
+      continue;
+    }
+
+    // indented code block
+    if indent >= 4 {
+      let mut code: Vec<&str> = vec![];
+      while i < lines.len() {
+        let line = lines[i].as_str();
+        if line.trim().is_empty() {
+          code.push("");
+        } else if line.len() - line.trim_start().len() >= 4 {
+          code.push(&line[4..]);
+        } else {
+          break;
+        }
+        i += 1;
+      }
+      while code.last() == Some(&"") {
+        code.pop();
+      }
+      render_code_block(&code.join("\n"), "", out);
+      continue;
+    }
+
+    // fenced code block
+    if let Some((fence, lang)) = parse_fence(content) {
+      let mut code: Vec<&str> = vec![];
+      i += 1;
+      while i < lines.len() {
+        let line = lines[i].as_str();
+        let trimmed = line.trim();
+        if trimmed.starts_with(fence.as_str()) && trimmed.chars().all(|c| fence.starts_with(c)) {
+          i += 1;
+          break;
+        }
+        let line_indent = line.len() - line.trim_start().len();
+        code.push(&line[line_indent.min(indent)..]);
+        i += 1;
+      }
+      render_code_block(&code.join("\n"), lang.as_str(), out);
+      continue;
+    }
+
+    // heading
+    if let Some((level, text)) = parse_atx_heading(content) {
+      render_heading(level, text, out);
+      i += 1;
+      continue;
+    }
+
+    // thematic break
+    if is_thematic_break(content) {
+      out.push_str("<hr />\n");
+      i += 1;
+      continue;
+    }
+
+    // blockquote
+    if content.starts_with('>') {
+      let mut quoted: Vec<String> = vec![];
+      while i < lines.len() {
+        let content = lines[i].trim_start();
+        if let Some(rest) = content.strip_prefix('>') {
+          quoted.push(rest.strip_prefix(' ').unwrap_or(rest).into());
+        } else if !content.is_empty()
+          && matches!(quoted.last(), Some(l) if !l.trim().is_empty())
+          && !starts_block(content)
+        {
+          // the lazy continuation of a paragraph
+          quoted.push(content.into());
+        } else {
+          break;
+        }
+        i += 1;
+      }
+      out.push_str("<blockquote>\n");
+      render_blocks(&quoted, false, out);
+      out.push_str("</blockquote>\n");
+      continue;
+    }
+
+    // list
+    if let Some(marker) = parse_list_marker(line) {
+      i = render_list(lines, i, marker, out);
+      continue;
+    }
+
+    // paragraph
+    let mut paragraph: Vec<&str> = vec![content];
+    i += 1;
+    let mut setext_level = 0;
+    while i < lines.len() {
+      let content = lines[i].trim_start();
+      if content.is_empty() {
+        break;
+      }
+      let underline = content.trim_end();
+      if lines[i].len() - content.len() < 4 && !underline.is_empty() {
+        if underline.chars().all(|c| c == '=') {
+          setext_level = 1;
+          i += 1;
+          break;
+        }
+        if underline.chars().all(|c| c == '-') {
+          setext_level = 2;
+          i += 1;
+          break;
+        }
+      }
+      if starts_block(content) {
+        break;
+      }
+      paragraph.push(content);
+      i += 1;
+    }
+    let text = paragraph.join("\n");
+    if setext_level > 0 {
+      render_heading(setext_level, text.as_str(), out);
+    } else if tight {
+      out.push_str(render_inlines(&parse_inlines(text.as_str())).as_str());
+      out.push('\n');
+    } else {
+      out.push_str("<p>");
+      out.push_str(render_inlines(&parse_inlines(text.as_str())).as_str());
+      out.push_str("</p>\n");
+    }
+  }
+}
+
+/// checks if the line interrupts a paragraph.
+fn starts_block(content: &str) -> bool {
+  parse_fence(content).is_some()
+    || parse_atx_heading(content).is_some()
+    || is_thematic_break(content)
+    || content.starts_with('>')
+    || match parse_list_marker(content) {
+      // only the list items that are not empty and the ordered ones starting with 1
+      Some(marker) => {
+        !content[marker.width..].trim().is_empty() && (!marker.ordered || marker.start == 1)
+      }
+      None => false,
+    }
+}
+
+fn parse_fence(content: &str) -> Option<(String, String)> {
+  let c = content.chars().next()?;
+  if c != '`' && c != '~' {
+    return None;
+  }
+  let len = content.chars().take_while(|ch| *ch == c).count();
+  if len < 3 {
+    return None;
+  }
+  let info = content[len..].trim();
+  if c == '`' && info.contains('`') {
+    return None;
+  }
+  let lang = info.split_whitespace().next().unwrap_or("");
+  Some((c.to_string().repeat(len), lang.into()))
+}
+
+fn parse_atx_heading(content: &str) -> Option<(usize, &str)> {
+  let level = content.chars().take_while(|c| *c == '#').count();
+  if level == 0 || level > 6 {
+    return None;
+  }
+  let rest = &content[level..];
+  if !rest.is_empty() && !rest.starts_with(' ') {
+    return None;
+  }
+  // remove the closing sequence, like `## title ##`
+  let mut text = rest.trim();
+  let closing = text.trim_end_matches('#');
+  if closing.is_empty() || closing.ends_with(' ') {
+    text = closing.trim_end();
+  }
+  Some((level, text))
+}
+
+fn is_thematic_break(content: &str) -> bool {
+  let mut chars = content.chars().filter(|c| !c.is_whitespace());
+  let first = match chars.next() {
+    Some(c) if c == '-' || c == '*' || c == '_' => c,
+    _ => return false,
+  };
+  let mut count = 1;
+  for c in chars {
+    if c != first {
+      return false;
+    }
+    count += 1;
+  }
+  count >= 3
+}
+
+struct ListMarker {
+  ordered: bool,
+  start: u32,
+  /// the bullet char or the delimiter of the ordered list
+  delimiter: char,
+  indent: usize,
+  /// the offset of the item content
+  width: usize,
+}
+
+fn parse_list_marker(line: &str) -> Option<ListMarker> {
+  let content = line.trim_start();
+  let indent = line.len() - content.len();
+  if indent >= 4 || is_thematic_break(content) {
+    return None;
+  }
+  let (ordered, start, delimiter, marker_len) = match content.chars().next()? {
+    c @ '-' | c @ '*' | c @ '+' => (false, 0, c, 1),
+    c if c.is_ascii_digit() => {
+      let digits = content.chars().take_while(|c| c.is_ascii_digit()).count();
+      if digits > 9 {
+        return None;
+      }
+      let delimiter = content[digits..].chars().next()?;
+      if delimiter != '.' && delimiter != ')' {
+        return None;
+      }
+      (true, content[..digits].parse().ok()?, delimiter, digits + 1)
+    }
+    _ => return None,
+  };
+  let rest = &content[marker_len..];
+  if !rest.is_empty() && !rest.starts_with(' ') {
+    return None;
+  }
+  let spaces = rest.chars().take_while(|c| *c == ' ').count();
+  // an indented code block in the list item starts after one space
+  let spaces = if spaces == 0 || spaces > 4 || rest.trim().is_empty() {
+    1
+  } else {
+    spaces
+  };
+  Some(ListMarker {
+    ordered,
+    start,
+    delimiter,
+    indent,
+    width: indent + marker_len + spaces,
+  })
+}
+
+/// renders the list starts at the line `i`, returns the index of the next line.
+fn render_list(lines: &[String], mut i: usize, marker: ListMarker, out: &mut String) -> usize {
+  let mut items: Vec<Vec<String>> = vec![];
+  let mut loose = false;
+  let mut width = marker.width;
+  loop {
+    let line = lines[i].as_str();
+    let mut item: Vec<String> = vec![line.get(width..).unwrap_or("").trim_end().into()];
+    i += 1;
+    while i < lines.len() {
+      let line = lines[i].as_str();
+      let content = line.trim_start();
+      let indent = line.len() - content.len();
+      if content.is_empty() {
+        item.push("".into());
+      } else if indent >= width {
+        item.push(line[width..].into());
+      } else if matches!(item.last(), Some(l) if !l.trim().is_empty())
+        && parse_list_marker(line).is_none()
+        && !starts_block(content)
+      {
+        // the lazy continuation of a paragraph
+        item.push(content.into());
+      } else {
+        break;
+      }
+      i += 1;
+    }
+    let mut blank_lines = 0;
+    while matches!(item.last(), Some(l) if l.trim().is_empty()) {
+      item.pop();
+      blank_lines += 1;
+    }
+    if item.iter().any(|l| l.trim().is_empty()) && has_blank_between_blocks(&item) {
+      loose = true;
+    }
+    items.push(item);
+
+    // the next item of the same list
+    match lines.get(i).and_then(|line| parse_list_marker(line)) {
+      Some(next)
+        if next.ordered == marker.ordered
+          && next.delimiter == marker.delimiter
+          && next.indent < marker.width =>
+      {
+        if blank_lines > 0 {
+          loose = true;
+        }
+        width = next.width;
+      }
+      _ => {
+        // the blank lines before the next block belong to the list
+        i -= blank_lines.min(i);
+        while i < lines.len() && lines[i].trim().is_empty() {
+          i += 1;
+        }
+        break;
+      }
+    }
+  }
+
+  if marker.ordered {
+    if marker.start != 1 {
+      out.push_str(format!("<ol start={{{}}}>\n", marker.start).as_str());
+    } else {
+      out.push_str("<ol>\n");
+    }
+  } else {
+    out.push_str("<ul>\n");
+  }
+  for item in items {
+    out.push_str("<li>");
+    if loose {
+      out.push('\n');
+    }
+    render_blocks(&item, !loose, out);
+    out.push_str("</li>\n");
+  }
+  out.push_str(if marker.ordered { "</ol>\n" } else { "</ul>\n" });
+  i
+}
+
+/// checks if the blank lines of the item separate the direct blocks, but not
+/// the lines in a fenced code block or a nested list.
+fn has_blank_between_blocks(item: &[String]) -> bool {
+  let mut in_fence: Option<String> = None;
+  for (idx, line) in item.iter().enumerate() {
+    let content = line.trim_start();
+    if let Some(fence) = &in_fence {
+      if content.trim_end().starts_with(fence.as_str()) {
+        in_fence = None;
+      }
+      continue;
+    }
+    if let Some((fence, _)) = parse_fence(content) {
+      in_fence = Some(fence);
+      continue;
+    }
+    if content.is_empty() && idx > 0 {
+      // a blank line followed by an unindented line
+      if let Some(next) = item[idx + 1..].iter().find(|l| !l.trim().is_empty()) {
+        if !next.starts_with(' ') {
+          return true;
+        }
+      }
+    }
+  }
+  false
+}
+
+fn render_heading(level: usize, text: &str, out: &mut String) {
+  let inlines = parse_inlines(text);
+  out.push_str(
+    format!(
+      "<h{} id={}>{}</h{}>\n",
+      level,
+      Value::String(slugify(plain_text(&inlines).as_str())),
+      render_inlines(&inlines),
+      level
+    )
+    .as_str(),
+  );
+}
+
+fn render_code_block(code: &str, lang: &str, out: &mut String) {
+  out.push_str("<pre><code");
+  if !lang.is_empty() {
+    out.push_str(format!(" className={}", Value::String(format!("language-{}", lang))).as_str());
+  }
+  out.push('>');
+  if !code.is_empty() {
+    out.push_str(format!("{{{}}}", Value::String(format!("{}\n", code))).as_str());
+  }
+  out.push_str("</code></pre>\n");
+}
+
+/// creates the id of a heading, like `Hello World!` -> `hello-world`.
+fn slugify(text: &str) -> String {
+  let mut slug = String::new();
+  for c in text.trim().chars() {
+    if c.is_alphanumeric() || c == '_' {
+      slug.extend(c.to_lowercase());
+    } else if (c.is_whitespace() || c == '-') && !slug.ends_with('-') {
+      slug.push('-');
+    }
+  }
+  slug.trim_matches('-').into()
+}
+
+/* inlines */
+
+#[derive(Debug, PartialEq)]
+enum Inline {
+  Text(String),
+  Code(String),
+  Break,
+  Tag(&'static str, Vec<Inline>),
+  Link {
+    href: String,
+    title: Option<String>,
+    children: Vec<Inline>,
+  },
+  Image {
+    src: String,
+    alt: String,
+    title: Option<String>,
+  },
+}
+
+fn parse_inlines(text: &str) -> Vec<Inline> {
+  let chars: Vec<char> = text.chars().collect();
+  let mut inlines: Vec<Inline> = vec![];
+  let mut buf = String::new();
+  let mut i = 0;
+  macro_rules! flush {
+    () => {
+      if !buf.is_empty() {
+        inlines.push(Inline::Text(buf.clone()));
+        buf.clear();
+      }
+    };
+  }
+  while i < chars.len() {
+    let c = chars[i];
+    match c {
+      '\\' if i + 1 < chars.len() && chars[i + 1] == '\n' => {
+        flush!();
+        inlines.push(Inline::Break);
+        i += 2;
+      }
+      '\\' if i + 1 < chars.len() && chars[i + 1].is_ascii_punctuation() => {
+        buf.push(chars[i + 1]);
+        i += 2;
+      }
+      '\n' => {
+        if buf.ends_with("  ") {
+          buf.truncate(buf.trim_end_matches(' ').len());
+          flush!();
+          inlines.push(Inline::Break);
+        } else {
+          buf.truncate(buf.trim_end_matches(' ').len());
+          buf.push('\n');
+        }
+        i += 1;
+      }
+      '`' => {
+        let len = run_length(&chars, i, '`');
+        match find_code_span_end(&chars, i + len, len) {
+          Some(end) => {
+            let code: String = chars[i + len..end].iter().collect();
+            let code = code.replace('\n', " ");
+            let code = if code.len() > 2 && code.starts_with(' ') && code.ends_with(' ') {
+              code[1..code.len() - 1].into()
+            } else {
+              code
+            };
+            flush!();
+            inlines.push(Inline::Code(code));
+            i = end + len;
+          }
+          None => {
+            buf.extend(&chars[i..i + len]);
+            i += len;
+          }
+        }
+      }
+      '*' | '_' | '~' => {
+        let len = run_length(&chars, i, c);
+        let is_intraword = c == '_' && i > 0 && chars[i - 1].is_alphanumeric();
+        let (tag, delim_len) = match (c, len) {
+          ('~', 2) => ("del", 2),
+          ('~', _) => ("", 0),
+          (_, 1) => ("em", 1),
+          (_, 2) => ("strong", 2),
+          (_, 3) => ("strong+em", 3),
+          _ => ("", 0),
+        };
+        let can_open = i + len < chars.len() && !chars[i + len].is_whitespace();
+        match find_delimiter_end(&chars, i + len, c, delim_len) {
+          Some(end) if !tag.is_empty() && can_open && !is_intraword => {
+            let inner: String = chars[i + len..end].iter().collect();
+            flush!();
+            let children = parse_inlines(inner.as_str());
+            inlines.push(match tag {
+              "strong+em" => Inline::Tag("strong", vec![Inline::Tag("em", children)]),
+              _ => Inline::Tag(tag, children),
+            });
+            i = end + len;
+          }
+          _ => {
+            buf.extend(&chars[i..i + len]);
+            i += len;
+          }
+        }
+      }
+      '!' if i + 1 < chars.len() && chars[i + 1] == '[' => match parse_link(&chars, i + 1) {
+        Some((label, href, title, end)) => {
+          flush!();
+          inlines.push(Inline::Image {
+            src: href,
+            alt: plain_text(&parse_inlines(label.as_str())),
+            title,
+          });
+          i = end;
+        }
+        None => {
+          buf.push(c);
+          i += 1;
+        }
+      },
+      '[' => match parse_link(&chars, i) {
+        Some((label, href, title, end)) => {
+          flush!();
+          inlines.push(Inline::Link {
+            href,
+            title,
+            children: parse_inlines(label.as_str()),
+          });
+          i = end;
+        }
+        None => {
+          buf.push(c);
+          i += 1;
+        }
+      },
+      // match: <https://alephjs.org>
+      '<' => {
+        let end = chars[i + 1..]
+          .iter()
+          .position(|c| *c == '>' || *c == '<' || c.is_whitespace())
+          .map(|idx| idx + i + 1);
+        match end {
+          Some(end) if chars[end] == '>' => {
+            let url: String = chars[i + 1..end].iter().collect();
+            if is_autolink(url.as_str()) {
+              flush!();
+              inlines.push(Inline::Link {
+                href: if url.contains(':') {
+                  url.clone()
+                } else {
+                  format!("mailto:{}", url)
+                },
+                title: None,
+                children: vec![Inline::Text(url)],
+              });
+              i = end + 1;
+            } else {
+              buf.push(c);
+              i += 1;
+            }
+          }
+          _ => {
+            buf.push(c);
+            i += 1;
+          }
+        }
+      }
+      _ => {
+        buf.push(c);
+        i += 1;
+      }
+    }
+  }
+  flush!();
+  inlines
+}
+
+fn run_length(chars: &[char], start: usize, c: char) -> usize {
+  chars[start..].iter().take_while(|ch| **ch == c).count()
+}
+
+fn find_code_span_end(chars: &[char], start: usize, len: usize) -> Option<usize> {
+  let mut i = start;
+  while i < chars.len() {
+    if chars[i] == '`' {
+      let run = run_length(chars, i, '`');
+      if run == len {
+        return Some(i);
+      }
+      i += run;
+    } else {
+      i += 1;
+    }
+  }
+  None
+}
+
+/// finds the closing delimiter run that has the same length and is not preceded
+/// by a whitespace, the code spans and the escapes are skipped.
+fn find_delimiter_end(chars: &[char], start: usize, c: char, len: usize) -> Option<usize> {
+  if len == 0 {
+    return None;
+  }
+  let mut i = start;
+  while i < chars.len() {
+    match chars[i] {
+      '\\' => i += 2,
+      '`' => {
+        let run = run_length(chars, i, '`');
+        i = match find_code_span_end(chars, i + run, run) {
+          Some(end) => end + run,
+          None => i + run,
+        };
+      }
+      ch if ch == c => {
+        let run = run_length(chars, i, c);
+        let closes = i > start && !chars[i - 1].is_whitespace();
+        let is_intraword = c == '_' && i + run < chars.len() && chars[i + run].is_alphanumeric();
+        if run == len && closes && !is_intraword {
+          return Some(i);
+        }
+        i += run;
+      }
+      _ => i += 1,
+    }
+  }
+  None
+}
+
+/// parses `[label](href "title")` at the index of `[`, returns the label, the href,
+/// the title and the end index.
+fn parse_link(chars: &[char], start: usize) -> Option<(String, String, Option<String>, usize)> {
+  let mut depth = 0;
+  let mut i = start;
+  let mut label_end = None;
+  while i < chars.len() {
+    match chars[i] {
+      '\\' => i += 1,
+      '[' => depth += 1,
+      ']' => {
+        depth -= 1;
+        if depth == 0 {
+          label_end = Some(i);
+          break;
+        }
+      }
+      _ => {}
+    }
+    i += 1;
+  }
+  let label_end = label_end?;
+  if chars.get(label_end + 1) != Some(&'(') {
+    return None;
+  }
+  let mut i = label_end + 2;
+  while i < chars.len() && chars[i].is_whitespace() {
+    i += 1;
+  }
+  let mut href = String::new();
+  if chars.get(i) == Some(&'<') {
+    i += 1;
+    while i < chars.len() && chars[i] != '>' {
+      if chars[i] == '\n' {
+        return None;
+      }
+      href.push(chars[i]);
+      i += 1;
+    }
+    i += 1;
+  } else {
+    let mut parens = 0;
+    while i < chars.len() && !chars[i].is_whitespace() {
+      match chars[i] {
+        '(' => parens += 1,
+        ')' if parens == 0 => break,
+        ')' => parens -= 1,
+        '\\' if i + 1 < chars.len() && chars[i + 1].is_ascii_punctuation() => i += 1,
+        _ => {}
+      }
+      href.push(chars[i]);
+      i += 1;
+    }
+  }
+  while i < chars.len() && chars[i].is_whitespace() {
+    i += 1;
+  }
+  let mut title = None;
+  if let Some(quote) = chars
+    .get(i)
+    .copied()
+    .filter(|c| *c == '"' || *c == '\'' || *c == '(')
+  {
+    let close = if quote == '(' { ')' } else { quote };
+    let mut text = String::new();
+    i += 1;
+    while i < chars.len() && chars[i] != close {
+      text.push(chars[i]);
+      i += 1;
+    }
+    i += 1;
+    title = Some(text);
+    while i < chars.len() && chars[i].is_whitespace() {
+      i += 1;
+    }
+  }
+  if chars.get(i) != Some(&')') {
+    return None;
+  }
+  let label: String = chars[start + 1..label_end].iter().collect();
+  Some((label, href, title, i + 1))
+}
+
+fn is_autolink(url: &str) -> bool {
+  match url.find(':') {
+    Some(idx) => {
+      let scheme = &url[..idx];
+      scheme.len() >= 2
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+          .chars()
+          .all(|c| c.is_ascii_alphanumeric() || "+.-".contains(c))
+    }
+    None => {
+      let parts: Vec<&str> = url.split('@').collect();
+      parts.len() == 2 && !parts[0].is_empty() && parts[1].contains('.')
+    }
+  }
+}
+
+fn plain_text(inlines: &[Inline]) -> String {
+  let mut text = String::new();
+  for inline in inlines {
+    match inline {
+      Inline::Text(s) | Inline::Code(s) => text.push_str(s.as_str()),
+      Inline::Break => text.push('\n'),
+      Inline::Tag(_, children) | Inline::Link { children, .. } => {
+        text.push_str(plain_text(children).as_str())
+      }
+      Inline::Image { alt, .. } => text.push_str(alt.as_str()),
+    }
+  }
+  text
+}
+
+/// renders the inlines as JSX, the texts are quoted as string literals so the
+/// braces and the angle brackets don't need to be escaped.
+fn render_inlines(inlines: &[Inline]) -> String {
+  let mut jsx = String::new();
+  for inline in inlines {
+    match inline {
+      Inline::Text(text) => jsx.push_str(format!("{{{}}}", Value::String(text.clone())).as_str()),
+      Inline::Code(code) => {
+        jsx.push_str(format!("<code>{{{}}}</code>", Value::String(code.clone())).as_str())
+      }
+      Inline::Break => jsx.push_str("<br />"),
+      Inline::Tag(tag, children) => {
+        jsx.push_str(format!("<{}>{}</{}>", tag, render_inlines(children), tag).as_str())
+      }
+      Inline::Link {
+        href,
+        title,
+        children,
+      } => {
+        jsx.push_str(format!("<a href={}", Value::String(href.clone())).as_str());
+        if let Some(title) = title {
+          jsx.push_str(format!(" title={}", Value::String(title.clone())).as_str());
+        }
+        jsx.push_str(format!(">{}</a>", render_inlines(children)).as_str());
+      }
+      Inline::Image { src, alt, title } => {
+        jsx.push_str(
+          format!(
+            "<img src={} alt={}",
+            Value::String(src.clone()),
+            Value::String(alt.clone())
+          )
+          .as_str(),
+        );
+        if let Some(title) = title {
+          jsx.push_str(format!(" title={}", Value::String(title.clone())).as_str());
+        }
+        jsx.push_str(" />");
+      }
+    }
+  }
+  jsx
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::import_map::ImportHashMap;
+  use crate::resolve::Resolver;
+  use crate::source_type::SourceType;
+  use crate::swc::{EmitOptions, SWC};
+  use serde_json::json;
+  use std::{cell::RefCell, rc::Rc};
+
+  fn render(source: &str) -> String {
+    let lines: Vec<String> = source.lines().map(|line| line.into()).collect();
+    let mut jsx = String::new();
+    render_blocks(&lines, false, &mut jsx);
+    jsx
+  }
+
+  #[test]
+  fn markdown_blocks() {
+    assert_eq!(
+      render("# Hello *World*\n\nSetext\n---"),
+      "<h1 id=\"hello-world\">{\"Hello \"}<em>{\"World\"}</em></h1>\n<h2 id=\"setext\">{\"Setext\"}</h2>\n"
+    );
+    assert_eq!(
+      render("a **b** `{c}`\nd  \ne"),
+      "<p>{\"a \"}<strong>{\"b\"}</strong>{\" \"}<code>{\"{c}\"}</code>{\"\\nd\"}<br />{\"e\"}</p>\n"
+    );
+    assert_eq!(
+      render("[Docs](/docs \"Docs\") ![logo](./logo.svg) <https://alephjs.org>"),
+      "<p><a href=\"/docs\" title=\"Docs\">{\"Docs\"}</a>{\" \"}<img src=\"./logo.svg\" alt=\"logo\" />{\" \"}<a href=\"https://alephjs.org\">{\"https://alephjs.org\"}</a></p>\n"
+    );
+    assert_eq!(
+      render("```ts\nconst a = <A />\n```"),
+      "<pre><code className=\"language-ts\">{\"const a = <A />\\n\"}</code></pre>\n"
+    );
+    assert_eq!(
+      render("- a\n- b\n  - c\n\n3. d\n4. e"),
+      "<ul>\n<li>{\"a\"}\n</li>\n<li>{\"b\"}\n<ul>\n<li>{\"c\"}\n</li>\n</ul>\n</li>\n</ul>\n<ol start={3}>\n<li>{\"d\"}\n</li>\n<li>{\"e\"}\n</li>\n</ol>\n"
+    );
+    assert_eq!(
+      render("- a\n\n- b"),
+      "<ul>\n<li>\n<p>{\"a\"}</p>\n</li>\n<li>\n<p>{\"b\"}</p>\n</li>\n</ul>\n"
+    );
+    assert_eq!(
+      render("> quote\ncontinued\n\n***\n\n    code"),
+      "<blockquote>\n<p>{\"quote\\ncontinued\"}</p>\n</blockquote>\n<hr />\n<pre><code>{\"code\\n\"}</code></pre>\n"
+    );
+    assert_eq!(
+      render("snake_case_name and \\*escaped\\*"),
+      "<p>{\"snake_case_name and *escaped*\"}</p>\n"
+    );
+  }
+
+  #[test]
+  fn markdown_front_matter() {
+    let (code, front_matter) = compile_markdown(
+      r#"---
+title: "Hello: World"
+date: 2021-05-01
+draft: false
+order: 3
+rating: 4.5
+tags: [aleph, "deno"]
+authors:
+  - name: X
+    url: https://example.com
+  - Y
+nav:
+  prev: /docs/intro # comment
+summary: |
+  line 1
+  line 2
+---
+
+# Hello
+"#,
+    );
+    assert_eq!(
+      Value::Object(front_matter),
+      json!({
+        "title": "Hello: World",
+        "date": "2021-05-01",
+        "draft": false,
+        "order": 3,
+        "rating": 4.5,
+        "tags": ["aleph", "deno"],
+        "authors": [{ "name": "X", "url": "https://example.com" }, "Y"],
+        "nav": { "prev": "/docs/intro" },
+        "summary": "line 1\nline 2\n",
+      })
+    );
+    assert!(code.contains("<h1 id=\"hello\">{\"Hello\"}</h1>"));
+    assert!(code.contains("MarkdownPage.frontMatter = {"));
+
+    let (code, front_matter) = compile_markdown("Hello\n---\n");
+    assert!(front_matter.is_empty());
+    assert!(code.contains("<h2 id=\"hello\">{\"Hello\"}</h2>"));
+  }
+
+  #[test]
+  fn transform_markdown() {
+    let (code, _) = compile_markdown(
+      "---\ntitle: Docs\n---\n\n# Docs {#id}\n\nSee [About](/about) and [Deno](https://deno.land).\n",
+    );
+    let module = SWC::parse("/pages/docs.md", code.as_str(), Some(SourceType::JSX))
+      .expect("could not parse module");
+    let resolver = Rc::new(RefCell::new(Resolver::new(
+      "/pages/docs.md",
+      ImportHashMap::default(),
+      Some("https://deno.land/x/aleph@v0.3.0".into()),
+      None,
+      false,
+      vec![],
+    )));
+    let (code, _) = module
+      .transform(resolver, &EmitOptions::default())
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains("export default function MarkdownPage(props)"));
+    assert!(code
+      .contains("React.createElement(\"h1\", {\n        id: \"docs-id\"\n    }, \"Docs {#id}\")"));
+    assert!(code.contains(
+      "React.createElement(__ALEPH_Anchor, {\n        href: \"/about\"\n    }, \"About\")"
+    ));
+    assert!(code.contains("MarkdownPage.frontMatter = {\n    \"title\": \"Docs\"\n};"));
+  }
+}
@@ -0,0 +1,88 @@
+use crate::mappings::with_source_maps;
+use crate::module_handles::module_count;
+
+use serde::{Deserialize, Serialize};
+
+/// The options of `init`, which tunes the memory of the compiler for the long-running
+/// processes like the dev server.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct InitOptions {
+  /// pre-grows the wasm memory to the bytes, so the first transforms don't grow the
+  /// memory again and again. the memory is allocated and freed at once, the freed
+  /// memory is reused by the allocator. ignored by the native backend.
+  #[serde(default)]
+  pub initial_memory: Option<usize>,
+  /// the number of the parsed source maps that are kept for the position lookups,
+  /// `0` disables the cache.
+  #[serde(default)]
+  pub source_map_cache_size: Option<usize>,
+}
+
+/// The memory usage of the compiler.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MemoryStats {
+  /// the size of the wasm memory in bytes, `None` with the native backend.
+  pub memory_bytes: Option<usize>,
+  /// the number of the parsed source maps in the cache.
+  pub cached_source_maps: usize,
+  /// the number of the parsed modules of `parseModuleSync` that are not freed.
+  pub parsed_modules: usize,
+}
+
+/// Applies the `options`, returns the memory stats after that.
+pub fn init(options: &InitOptions) -> MemoryStats {
+  if let Some(size) = options.source_map_cache_size {
+    with_source_maps(|cache| cache.set_capacity(size));
+  }
+  if let Some(bytes) = options.initial_memory {
+    pre_grow_memory(bytes);
+  }
+  memory_stats()
+}
+
+/// Drops the global caches of the compiler, returns the memory stats after that. The
+/// swc globals and the source maps of the transforms are created for each call and
+/// freed after it, so the parsed source maps of the lookups are the only caches (of
+/// the current thread with the native backend).
+/// The parsed modules of `parseModuleSync` are kept until they are freed.
+/// The wasm memory never shrinks, the freed memory is reused by the next transforms.
+pub fn reset() -> MemoryStats {
+  with_source_maps(|cache| cache.clear());
+  memory_stats()
+}
+
+pub fn memory_stats() -> MemoryStats {
+  MemoryStats {
+    memory_bytes: memory_bytes(),
+    cached_source_maps: with_source_maps(|cache| cache.len()),
+    parsed_modules: module_count(),
+  }
+}
+
+#[cfg(target_arch = "wasm32")]
+const WASM_PAGE_SIZE: usize = 64 * 1024;
+
+#[cfg(target_arch = "wasm32")]
+fn memory_bytes() -> Option<usize> {
+  Some(core::arch::wasm32::memory_size(0) * WASM_PAGE_SIZE)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn memory_bytes() -> Option<usize> {
+  None
+}
+
+/// grows the memory by the allocator instead of `memory.grow`, the pages that are
+/// grown directly are never used by the allocator.
+#[cfg(target_arch = "wasm32")]
+fn pre_grow_memory(bytes: usize) {
+  let current = core::arch::wasm32::memory_size(0) * WASM_PAGE_SIZE;
+  if bytes > current {
+    drop(Vec::<u8>::with_capacity(bytes - current));
+  }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn pre_grow_memory(_bytes: usize) {}
@@ -0,0 +1,331 @@
+use crate::directive::{parse_module_directive, ModuleDirective};
+use crate::import_meta::get_import_glob;
+use crate::resolve::{DependencyDescriptor, DependencyKind};
+use crate::resolve_fold::{
+  get_lazy_import_span, get_worker_url, is_call_expr_by_name, ExportsParser,
+};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use swc_common::Span;
+use swc_ecma_ast::*;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// The metadata of a module that is collected without emitting code.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleMetadata {
+  pub exports: Vec<String>,
+  pub imports: Vec<DependencyDescriptor>,
+  /// the patterns of the `import.meta.glob` calls, the caller passes the matched
+  /// files to the transform.
+  pub import_globs: Vec<String>,
+  pub has_default_export: bool,
+  pub uses_jsx: bool,
+  pub has_top_level_await: bool,
+  /// the span of the first top-level `await`.
+  #[serde(skip)]
+  pub top_level_await_span: Option<Span>,
+  /// the `"use client"` or `"use server"` directive
+  pub directive: Option<ModuleDirective>,
+  /// the exported constants with the static values of an api module (under `api/`),
+  /// like `export const methods = ["GET"]`, for the router to register the handlers
+  /// without importing the module
+  pub route_meta: Option<Map<String, Value>>,
+}
+
+/// parse the metadata of the module.
+pub fn parse_metadata(module: &Module) -> ModuleMetadata {
+  let program = Program::Module(module.clone());
+  let mut exports_parser = ExportsParser { names: vec![] };
+  let program = program.fold_with(&mut exports_parser);
+  let mut metadata_parser = MetadataParser {
+    imports: vec![],
+    import_globs: vec![],
+    uses_jsx: false,
+    top_level_await: None,
+    fn_depth: 0,
+    lazy_import: None,
+  };
+  program.fold_with(&mut metadata_parser);
+  ModuleMetadata {
+    has_default_export: exports_parser.names.iter().any(|name| name.eq("default")),
+    exports: exports_parser.names,
+    imports: metadata_parser.imports,
+    import_globs: metadata_parser.import_globs,
+    uses_jsx: metadata_parser.uses_jsx,
+    has_top_level_await: metadata_parser.top_level_await.is_some(),
+    top_level_await_span: metadata_parser.top_level_await,
+    directive: parse_module_directive(module),
+    route_meta: None,
+  }
+}
+
+/// The export signature of a module, the HMR runtime diffs the signatures across
+/// the rebuilds to decide between applying an update and a full reload.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExportSignature {
+  /// the exported names, including `default`.
+  pub names: Vec<String>,
+  /// the sources of `export * from "..."`.
+  pub star_exports: Vec<String>,
+  pub has_default_export: bool,
+}
+
+/// parse the export signature of the module.
+pub fn parse_export_signature(module: &Module) -> ExportSignature {
+  let mut exports_parser = ExportsParser { names: vec![] };
+  Program::Module(module.clone()).fold_with(&mut exports_parser);
+  let mut signature = ExportSignature::default();
+  for name in exports_parser.names {
+    // the star exports are recorded as `{src}` by the parser
+    if name.starts_with('{') && name.ends_with('}') {
+      signature.star_exports.push(name[1..name.len() - 1].into());
+    } else {
+      if name.eq("default") {
+        signature.has_default_export = true;
+      }
+      signature.names.push(name);
+    }
+  }
+  signature
+}
+
+struct MetadataParser {
+  imports: Vec<DependencyDescriptor>,
+  import_globs: Vec<String>,
+  uses_jsx: bool,
+  /// the span of the first top-level `await`
+  top_level_await: Option<Span>,
+  fn_depth: u32,
+  /// the span of the `src` of the dynamic import in the loader of a lazy component
+  lazy_import: Option<Span>,
+}
+
+impl MetadataParser {
+  fn push_import(&mut self, src: &Str, is_dynamic: bool, reexport: bool) {
+    self.imports.push(DependencyDescriptor {
+      specifier: src.value.as_ref().into(),
+      is_dynamic,
+      span: Some(src.span.into()),
+      kind: None,
+      chunk_name: None,
+      reexport,
+      lazy: is_dynamic && self.lazy_import == Some(src.span),
+    });
+  }
+}
+
+impl Fold for MetadataParser {
+  noop_fold_type!();
+
+  fn fold_module_decl(&mut self, decl: ModuleDecl) -> ModuleDecl {
+    match &decl {
+      // match: import React from "https://esm.sh/react"
+      ModuleDecl::Import(ImportDecl {
+        src,
+        type_only: false,
+        ..
+      }) => self.push_import(src, false, false),
+      // match: export { useState } from "https://esm.sh/react"
+      ModuleDecl::ExportNamed(NamedExport {
+        src: Some(src),
+        type_only: false,
+        ..
+      }) => self.push_import(src, false, true),
+      // match: export * from "https://esm.sh/react"
+      ModuleDecl::ExportAll(ExportAll { src, .. }) => self.push_import(src, false, true),
+      _ => {}
+    }
+    decl.fold_children_with(self)
+  }
+
+  // match: import("https://esm.sh/react") or import.meta.glob("./pages/*.tsx")
+  fn fold_call_expr(&mut self, call: CallExpr) -> CallExpr {
+    // match: React.lazy(() => import("./chart.tsx"))
+    if let Some(span) = get_lazy_import_span(&call) {
+      let lazy_import = self.lazy_import.replace(span);
+      let call = call.fold_children_with(self);
+      self.lazy_import = lazy_import;
+      return call;
+    }
+    if is_call_expr_by_name(&call, "import") {
+      if let Some(ExprOrSpread { expr, .. }) = call.args.first() {
+        if let Expr::Lit(Lit::Str(src)) = expr.as_ref() {
+          self.push_import(src, true, false);
+        }
+      }
+    } else if let Some((pattern, _)) = get_import_glob(&call) {
+      self.import_globs.push(pattern);
+    }
+    call.fold_children_with(self)
+  }
+
+  // match: new Worker(new URL("./worker.ts", import.meta.url))
+  fn fold_new_expr(&mut self, new: NewExpr) -> NewExpr {
+    if let Some((url, span)) = get_worker_url(&new) {
+      self.imports.push(DependencyDescriptor {
+        specifier: url,
+        is_dynamic: true,
+        span: Some(span.into()),
+        kind: Some(DependencyKind::Worker),
+        chunk_name: None,
+        reexport: false,
+        lazy: false,
+      });
+    }
+    new.fold_children_with(self)
+  }
+
+  fn fold_jsx_element(&mut self, el: JSXElement) -> JSXElement {
+    self.uses_jsx = true;
+    el.fold_children_with(self)
+  }
+
+  fn fold_jsx_fragment(&mut self, fragment: JSXFragment) -> JSXFragment {
+    self.uses_jsx = true;
+    fragment.fold_children_with(self)
+  }
+
+  fn fold_await_expr(&mut self, expr: AwaitExpr) -> AwaitExpr {
+    if self.fn_depth == 0 && self.top_level_await.is_none() {
+      self.top_level_await = Some(expr.span);
+    }
+    expr.fold_children_with(self)
+  }
+
+  // match: for await (const chunk of stream) {}
+  fn fold_for_of_stmt(&mut self, stmt: ForOfStmt) -> ForOfStmt {
+    if self.fn_depth == 0 && self.top_level_await.is_none() {
+      self.top_level_await = stmt.await_token;
+    }
+    stmt.fold_children_with(self)
+  }
+
+  fn fold_function(&mut self, function: Function) -> Function {
+    self.fn_depth += 1;
+    let function = function.fold_children_with(self);
+    self.fn_depth -= 1;
+    function
+  }
+
+  fn fold_arrow_expr(&mut self, arrow: ArrowExpr) -> ArrowExpr {
+    self.fn_depth += 1;
+    let arrow = arrow.fold_children_with(self);
+    self.fn_depth -= 1;
+    arrow
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::swc::SWC;
+
+  #[test]
+  fn parse_module_metadata() {
+    let source = r#"
+      import React from "https://esm.sh/react"
+      import type { FC } from "https://esm.sh/react"
+      export { useState } from "https://esm.sh/react"
+      const data = await fetch("/api/data").then(res => res.json())
+      const Logo = React.lazy(() => import("../components/logo.tsx"))
+      const worker = new Worker(new URL("./worker.ts", import.meta.url))
+      const posts = import.meta.glob("./posts/*.md")
+      export default async function App() {
+        await Promise.resolve()
+        return <Logo />
+      }
+    "#;
+    let module = SWC::parse("/pages/index.tsx", source, None).expect("could not parse module");
+    let metadata = parse_metadata(&module.module);
+    assert_eq!(metadata.exports, vec!["useState", "default"]);
+    assert_eq!(
+      metadata
+        .imports
+        .iter()
+        .map(|dep| (dep.specifier.as_str(), dep.is_dynamic))
+        .collect::<Vec<(&str, bool)>>(),
+      vec![
+        ("https://esm.sh/react", false),
+        ("https://esm.sh/react", false),
+        ("../components/logo.tsx", true),
+        ("./worker.ts", true)
+      ]
+    );
+    assert_eq!(metadata.imports[3].kind, Some(DependencyKind::Worker));
+    assert!(metadata.imports[2].lazy);
+    assert!(!metadata.imports[3].lazy);
+    assert_eq!(metadata.import_globs, vec!["./posts/*.md"]);
+    assert!(metadata.has_default_export);
+    assert!(metadata.uses_jsx);
+    assert!(metadata.has_top_level_await);
+    assert_eq!(metadata.directive, None);
+
+    assert_eq!(
+      parse_export_signature(&module.module),
+      ExportSignature {
+        names: vec!["useState".into(), "default".into()],
+        star_exports: vec![],
+        has_default_export: true,
+      }
+    );
+
+    let source = r#"
+      export const run = async () => {
+        for await (const chunk of Deno.iter(Deno.stdin)) {}
+      }
+    "#;
+    let module = SWC::parse("/run.ts", source, None).expect("could not parse module");
+    let metadata = parse_metadata(&module.module);
+    assert_eq!(metadata.exports, vec!["run"]);
+    assert!(metadata.imports.is_empty());
+    assert!(!metadata.has_default_export);
+    assert!(!metadata.uses_jsx);
+    assert!(!metadata.has_top_level_await);
+  }
+
+  #[test]
+  fn parse_module_reexports() {
+    let source = r#"
+      import { log } from "./log.ts"
+      export * from "./util.ts"
+      export * as colors from "./colors.ts"
+      export { a as b } from "./a.ts"
+      export type { Props } from "./types.ts"
+    "#;
+    let module = SWC::parse("/mod.ts", source, None).expect("could not parse module");
+    let metadata = parse_metadata(&module.module);
+    assert_eq!(
+      metadata
+        .imports
+        .iter()
+        .map(|dep| (dep.specifier.as_str(), dep.reexport))
+        .collect::<Vec<(&str, bool)>>(),
+      vec![
+        ("./log.ts", false),
+        ("./util.ts", true),
+        ("./colors.ts", true),
+        ("./a.ts", true)
+      ]
+    );
+  }
+
+  #[test]
+  fn parse_module_export_signature() {
+    let source = r#"
+      export * from "./hooks.ts"
+      export * as utils from "./utils.ts"
+      export type { Props } from "./types.ts"
+      export interface State {}
+      export const [a, { b }] = [1, { b: 2 }]
+      export function render() {}
+    "#;
+    let module = SWC::parse("/lib/mod.ts", source, None).expect("could not parse module");
+    let signature = parse_export_signature(&module.module);
+    assert_eq!(signature.names, vec!["utils", "a", "b", "render"]);
+    assert_eq!(signature.star_exports, vec!["./hooks.ts"]);
+    assert!(!signature.has_default_export);
+  }
+}
@@ -0,0 +1,52 @@
+use crate::ParsedModule;
+
+use std::{cell::RefCell, collections::HashMap};
+
+thread_local! {
+  // the parsed modules hold the `Rc`s of swc, so they are kept per thread (the wasm
+  // module has only one), the handles of the native backend are only valid on the
+  // thread that parsed the module.
+  static PARSED_MODULES: RefCell<ParsedModules> = RefCell::new(ParsedModules::default());
+}
+
+/// The parsed modules that are kept in memory by the handles, so a module can be
+/// emitted many times with the different options without parsing it again.
+#[derive(Default)]
+struct ParsedModules {
+  /// the last handle, the handle `0` is never used.
+  last_handle: u32,
+  modules: HashMap<u32, (String, ParsedModule)>,
+}
+
+/// Keeps the parsed module of the `url`, returns the handle of it.
+pub fn insert_module(url: &str, parsed: ParsedModule) -> u32 {
+  PARSED_MODULES.with(|modules| {
+    let mut modules = modules.borrow_mut();
+    modules.last_handle = modules.last_handle.checked_add(1).unwrap_or(1);
+    let handle = modules.last_handle;
+    modules.modules.insert(handle, (url.into(), parsed));
+    handle
+  })
+}
+
+/// Calls the `f` with the url and a fork of the parsed module, the module is kept for
+/// the next calls. Returns `None` if the handle is unknown or freed.
+pub fn with_forked_module<T>(handle: u32, f: impl FnOnce(&str, ParsedModule) -> T) -> Option<T> {
+  // the module is forked before the call, so `f` can parse other modules
+  let (url, parsed) = PARSED_MODULES.with(|modules| {
+    let modules = modules.borrow();
+    let (url, parsed) = modules.modules.get(&handle)?;
+    Some((url.clone(), parsed.fork()))
+  })?;
+  Some(f(&url, parsed))
+}
+
+/// Drops the parsed module, returns `false` if the handle is unknown or freed.
+pub fn remove_module(handle: u32) -> bool {
+  PARSED_MODULES.with(|modules| modules.borrow_mut().modules.remove(&handle).is_some())
+}
+
+/// Returns the number of the parsed modules that are not freed.
+pub fn module_count() -> usize {
+  PARSED_MODULES.with(|modules| modules.borrow().modules.len())
+}
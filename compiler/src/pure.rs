@@ -0,0 +1,425 @@
+use regex::Regex;
+use std::collections::HashSet;
+use swc_common::{
+  comments::{Comment, CommentKind, Comments, SingleThreadedComments},
+  Span, Spanned, DUMMY_SP,
+};
+use swc_ecma_ast::*;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+lazy_static! {
+  static ref RE_REACT_MODULE: Regex = Regex::new(r"(^|/|\])react(@[^/]*)?(\.m?js)?$").unwrap();
+  static ref RE_JSX_RUNTIME_MODULE: Regex =
+    Regex::new(r"(^|/)react(@[^/]*)?/jsx-(dev-)?runtime(\.m?js)?$").unwrap();
+}
+
+/// the React APIs that don't have side effects, same as
+/// `babel-plugin-transform-react-pure-annotations`.
+const PURE_REACT_APIS: &[&str] = &[
+  "cloneElement",
+  "createContext",
+  "createElement",
+  "createFactory",
+  "createRef",
+  "forwardRef",
+  "isValidElement",
+  "lazy",
+  "memo",
+];
+
+const PURE_JSX_RUNTIME_APIS: &[&str] = &["jsx", "jsxs", "jsxDEV"];
+
+/// Creates a fold that annotates the calls of the React APIs (including the calls
+/// that are transformed from JSX) with `/*#__PURE__*/`, the bundlers can drop them
+/// if the results are unused.
+pub fn pure_annotation_fold(comments: SingleThreadedComments) -> impl Fold {
+  PureAnnotationFold {
+    comments,
+    callees: PureCallees::default(),
+  }
+}
+
+/// The callees that are imported from React.
+#[derive(Default)]
+struct PureCallees {
+  /// the local names of `import React from "react"` and `import * as React from "react"`
+  namespaces: HashSet<String>,
+  /// the local names of `import { memo } from "react"`
+  idents: HashSet<String>,
+}
+
+impl PureCallees {
+  fn from_module(module: &Module) -> Self {
+    let mut callees = PureCallees::default();
+    for item in module.body.iter() {
+      if let ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+        src,
+        specifiers,
+        type_only: false,
+        ..
+      })) = item
+      {
+        let src = src.value.as_ref();
+        let apis = if RE_JSX_RUNTIME_MODULE.is_match(src) {
+          PURE_JSX_RUNTIME_APIS
+        } else if RE_REACT_MODULE.is_match(src) {
+          PURE_REACT_APIS
+        } else {
+          continue;
+        };
+        for specifier in specifiers {
+          match specifier {
+            ImportSpecifier::Default(ImportDefaultSpecifier { local, .. })
+            | ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => {
+              if apis == PURE_REACT_APIS {
+                callees.namespaces.insert(local.sym.as_ref().into());
+              }
+            }
+            ImportSpecifier::Named(ImportNamedSpecifier {
+              local, imported, ..
+            }) => {
+              let name = imported.as_ref().unwrap_or(local).sym.as_ref();
+              if apis.contains(&name) {
+                callees.idents.insert(local.sym.as_ref().into());
+              }
+            }
+          }
+        }
+      }
+    }
+    callees
+  }
+
+  fn is_pure_call(&self, call: &CallExpr) -> bool {
+    let callee = match &call.callee {
+      ExprOrSuper::Expr(callee) => callee.as_ref(),
+      _ => return false,
+    };
+    match callee {
+      // match: memo(), _jsx()
+      Expr::Ident(id) => self.idents.contains(id.sym.as_ref()),
+      // match: React.createElement()
+      Expr::Member(MemberExpr {
+        obj: ExprOrSuper::Expr(obj),
+        prop,
+        computed: false,
+        ..
+      }) => match (obj.as_ref(), prop.as_ref()) {
+        (Expr::Ident(obj), Expr::Ident(prop)) => {
+          self.namespaces.contains(obj.sym.as_ref()) && PURE_REACT_APIS.contains(&prop.sym.as_ref())
+        }
+        _ => false,
+      },
+      _ => false,
+    }
+  }
+}
+
+struct PureAnnotationFold {
+  comments: SingleThreadedComments,
+  callees: PureCallees,
+}
+
+impl PureAnnotationFold {
+  fn add_pure_comment(&self, span: Span) {
+    let mut comments = self.comments.take_leading(span.lo).unwrap_or_default();
+    if !comments.iter().any(|c| c.text.eq("#__PURE__")) {
+      comments.push(Comment {
+        kind: CommentKind::Block,
+        span: DUMMY_SP,
+        text: "#__PURE__".into(),
+      });
+    }
+    self.comments.add_leading_comments(span.lo, comments);
+  }
+}
+
+impl Fold for PureAnnotationFold {
+  noop_fold_type!();
+
+  fn fold_module(&mut self, module: Module) -> Module {
+    self.callees = PureCallees::from_module(&module);
+    module.fold_children_with(self)
+  }
+
+  fn fold_call_expr(&mut self, call: CallExpr) -> CallExpr {
+    let mut call = call.fold_children_with(self);
+    if self.callees.is_pure_call(&call) {
+      // the calls transformed from JSX may have no position, the comment is
+      // emitted at the position of the first argument (the tag name) then.
+      if call.span.is_dummy() {
+        if let Some(arg) = call.args.first() {
+          call.span = arg.expr.span();
+        }
+      }
+      if !call.span.is_dummy() {
+        self.add_pure_comment(call.span);
+      }
+    }
+    call
+  }
+}
+
+/// checks if the module is free of side effects when it's evaluated: the top-level
+/// statements only declare the bindings and the initializers are provably pure,
+/// the bundlers can drop the module if none of its exports are used.
+pub fn is_side_effect_free(module: &Module) -> bool {
+  let checker = SideEffectsChecker::new(module);
+  module.body.iter().all(|item| match item {
+    ModuleItem::ModuleDecl(decl) => match decl {
+      // the import without specifiers is for the side effects, like `import "./style.css"`
+      ModuleDecl::Import(ImportDecl {
+        specifiers,
+        type_only,
+        ..
+      }) => *type_only || !specifiers.is_empty(),
+      ModuleDecl::ExportDecl(ExportDecl { decl, .. }) => checker.is_pure_decl(decl),
+      ModuleDecl::ExportNamed(_) | ModuleDecl::ExportAll(_) => true,
+      ModuleDecl::ExportDefaultDecl(ExportDefaultDecl { decl, .. }) => match decl {
+        DefaultDecl::Class(ClassExpr { class, .. }) => checker.is_pure_class(class),
+        _ => true,
+      },
+      ModuleDecl::ExportDefaultExpr(ExportDefaultExpr { expr, .. }) => checker.is_pure_expr(expr),
+      ModuleDecl::TsImportEquals(_) => false,
+      ModuleDecl::TsExportAssignment(_) | ModuleDecl::TsNamespaceExport(_) => true,
+    },
+    ModuleItem::Stmt(Stmt::Decl(decl)) => checker.is_pure_decl(decl),
+    ModuleItem::Stmt(Stmt::Empty(_)) => true,
+    ModuleItem::Stmt(_) => false,
+  })
+}
+
+/// Checks if the declarations and the expressions of a module are free of side
+/// effects, the calls of the React APIs imported by the module are pure.
+pub struct SideEffectsChecker {
+  callees: PureCallees,
+}
+
+impl SideEffectsChecker {
+  pub fn new(module: &Module) -> Self {
+    SideEffectsChecker {
+      callees: PureCallees::from_module(module),
+    }
+  }
+
+  pub fn is_pure_decl(&self, decl: &Decl) -> bool {
+    match decl {
+      Decl::Fn(_) | Decl::TsInterface(_) | Decl::TsTypeAlias(_) => true,
+      Decl::Class(ClassDecl { class, .. }) => self.is_pure_class(class),
+      Decl::Var(VarDecl { decls, declare, .. }) => {
+        *declare
+          || decls.iter().all(|decl| match &decl.init {
+            Some(init) => matches!(decl.name, Pat::Ident(_)) && self.is_pure_expr(init),
+            None => true,
+          })
+      }
+      Decl::TsEnum(TsEnumDecl { members, .. }) => members.iter().all(|member| match &member.init {
+        Some(init) => self.is_pure_expr(init),
+        None => true,
+      }),
+      Decl::TsModule(TsModuleDecl { declare, .. }) => *declare,
+    }
+  }
+
+  pub fn is_pure_class(&self, class: &Class) -> bool {
+    if !class.decorators.is_empty() {
+      return false;
+    }
+    if let Some(super_class) = &class.super_class {
+      if !self.is_pure_expr(super_class) {
+        return false;
+      }
+    }
+    class.body.iter().all(|member| match member {
+      ClassMember::Constructor(_)
+      | ClassMember::PrivateMethod(_)
+      | ClassMember::TsIndexSignature(_)
+      | ClassMember::Empty(_) => true,
+      ClassMember::Method(ClassMethod { key, function, .. }) => {
+        function.decorators.is_empty() && self.is_pure_prop_name(key)
+      }
+      ClassMember::ClassProp(ClassProp {
+        key,
+        value,
+        is_static,
+        decorators,
+        computed,
+        ..
+      }) => {
+        decorators.is_empty()
+          && (!computed || self.is_pure_expr(key))
+          && (!is_static || self.is_pure_value(value))
+      }
+      ClassMember::PrivateProp(PrivateProp {
+        value,
+        is_static,
+        decorators,
+        ..
+      }) => decorators.is_empty() && (!is_static || self.is_pure_value(value)),
+    })
+  }
+
+  fn is_pure_value(&self, value: &Option<Box<Expr>>) -> bool {
+    match value {
+      Some(value) => self.is_pure_expr(value),
+      None => true,
+    }
+  }
+
+  fn is_pure_prop_name(&self, key: &PropName) -> bool {
+    match key {
+      PropName::Computed(ComputedPropName { expr, .. }) => self.is_pure_expr(expr),
+      _ => true,
+    }
+  }
+
+  pub fn is_pure_expr(&self, expr: &Expr) -> bool {
+    match expr {
+      Expr::Lit(_)
+      | Expr::Ident(_)
+      | Expr::This(_)
+      | Expr::Arrow(_)
+      | Expr::Fn(_)
+      | Expr::MetaProp(_)
+      | Expr::JSXElement(_)
+      | Expr::JSXFragment(_) => true,
+      Expr::Class(ClassExpr { class, .. }) => self.is_pure_class(class),
+      // the expressions of the template are evaluated, the tagged templates are calls
+      Expr::Tpl(Tpl { exprs, .. }) => exprs.iter().all(|expr| self.is_pure_expr(expr)),
+      Expr::Array(ArrayLit { elems, .. }) => elems.iter().all(|elem| match elem {
+        Some(ExprOrSpread { spread: None, expr }) => self.is_pure_expr(expr),
+        Some(_) => false,
+        None => true,
+      }),
+      Expr::Object(ObjectLit { props, .. }) => props.iter().all(|prop| match prop {
+        PropOrSpread::Prop(prop) => match prop.as_ref() {
+          Prop::Shorthand(_) => true,
+          Prop::KeyValue(KeyValueProp { key, value }) => {
+            self.is_pure_prop_name(key) && self.is_pure_expr(value)
+          }
+          Prop::Method(MethodProp { key, .. })
+          | Prop::Getter(GetterProp { key, .. })
+          | Prop::Setter(SetterProp { key, .. }) => self.is_pure_prop_name(key),
+          Prop::Assign(_) => false,
+        },
+        PropOrSpread::Spread(_) => false,
+      }),
+      Expr::Unary(UnaryExpr { op, arg, .. }) => *op != UnaryOp::Delete && self.is_pure_expr(arg),
+      Expr::Bin(BinExpr { left, right, .. }) => self.is_pure_expr(left) && self.is_pure_expr(right),
+      Expr::Cond(CondExpr {
+        test, cons, alt, ..
+      }) => self.is_pure_expr(test) && self.is_pure_expr(cons) && self.is_pure_expr(alt),
+      Expr::Seq(SeqExpr { exprs, .. }) => exprs.iter().all(|expr| self.is_pure_expr(expr)),
+      Expr::Paren(ParenExpr { expr, .. })
+      | Expr::TsAs(TsAsExpr { expr, .. })
+      | Expr::TsTypeAssertion(TsTypeAssertion { expr, .. })
+      | Expr::TsNonNull(TsNonNullExpr { expr, .. })
+      | Expr::TsConstAssertion(TsConstAssertion { expr, .. }) => self.is_pure_expr(expr),
+      Expr::Call(call) => {
+        self.callees.is_pure_call(call)
+          && call
+            .args
+            .iter()
+            .all(|arg| arg.spread.is_none() && self.is_pure_expr(&arg.expr))
+      }
+      _ => false,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::import_map::ImportHashMap;
+  use crate::resolve::Resolver;
+  use crate::swc::{EmitOptions, JsxRuntime, SWC};
+  use std::{cell::RefCell, rc::Rc};
+
+  #[test]
+  fn pure_annotations() {
+    let source = r#"
+      import React, { createContext, memo as m } from "https://esm.sh/react"
+      export const Theme = createContext("light")
+      export const Logo = m(() => <img src="/logo.svg" />)
+      export default function App() {
+        const el = React.cloneElement(<Logo />)
+        return <div>{el}</div>
+      }
+      export const noop = log("noop")
+    "#;
+    let transform = |jsx_runtime: JsxRuntime| {
+      let module = SWC::parse("/app.tsx", source, None).expect("could not parse module");
+      let resolver = Rc::new(RefCell::new(Resolver::new(
+        "/app.tsx",
+        ImportHashMap::default(),
+        None,
+        Some("17.0.1".into()),
+        false,
+        vec![],
+      )));
+      let (code, _) = module
+        .transform(
+          resolver,
+          &EmitOptions {
+            jsx_runtime,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      code
+    };
+
+    let code = transform(JsxRuntime::Classic);
+    assert!(code.contains("export const Theme = /*#__PURE__*/ createContext(\"light\")"));
+    assert!(code.contains(
+      "export const Logo = /*#__PURE__*/ m(()=>/*#__PURE__*/ React.createElement(\"img\""
+    ));
+    assert!(code.contains(
+      "const el = /*#__PURE__*/ React.cloneElement(/*#__PURE__*/ React.createElement(Logo"
+    ));
+    assert!(code.contains("/*#__PURE__*/ React.createElement(\"div\", null, el)"));
+    assert!(code.contains("export const noop = log(\"noop\")"));
+
+    let code = transform(JsxRuntime::Automatic);
+    assert!(code.contains("/*#__PURE__*/ _jsx(\"img\""));
+    assert!(code.contains("/*#__PURE__*/ _jsx(\"div\""));
+  }
+
+  #[test]
+  fn side_effect_free() {
+    let check = |source: &str| {
+      let module = SWC::parse("/app.tsx", source, None).expect("could not parse module");
+      is_side_effect_free(&module.module)
+    };
+    assert!(check(
+      r#"
+      import React, { memo, createContext } from "https://esm.sh/react"
+      import type { FC } from "https://esm.sh/react"
+      export { useState } from "https://esm.sh/react"
+      export type Props = { title: string }
+      export const Theme = createContext({ color: "light", size: [1, 2] })
+      export const Title = memo(({ title }: Props) => <h1>{title}</h1>)
+      export enum Size { Small = 1, Large = Small * 2 }
+      class Store { static version = `v${1}`; state = init() }
+      export default function App() {
+        document.title = "App"
+        return <Title title="App" />
+      }
+    "#
+    ));
+    assert!(!check(r#"import "./style.css"; export const a = 1"#));
+    assert!(!check(r#"export const a = init()"#));
+    assert!(!check(r#"console.log("loaded")"#));
+    assert!(!check(r#"export const { a } = config"#));
+    assert!(!check(r#"export default new Store()"#));
+    assert!(check(r#"export const a = `a`, b = `b${a}${`c${1 + 2}`}`"#));
+    assert!(!check(r#"export const a = `a${init()}`"#));
+    assert!(!check(r#"export const a = `a${`b${init()}`}`"#));
+    assert!(!check(r#"export const a = css`color: red`"#));
+    assert!(!check(r#"class Store { static state = init() }"#));
+    assert!(!check(
+      r#"import { memo } from "https://esm.sh/preact"; export const A = memo(() => null)"#
+    ));
+  }
+}
@@ -1,3 +1,4 @@
+use crate::coverage::FileCoverage;
 use crate::import_map::{ImportHashMap, ImportMap};
 
 use indexmap::IndexSet;
@@ -5,12 +6,14 @@ use path_slash::PathBufExt;
 use pathdiff::diff_paths;
 use regex::Regex;
 use relative_path::RelativePath;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
 use std::{
-  collections::HashMap,
+  collections::{BTreeMap, HashMap},
   path::{Path, PathBuf},
   str::FromStr,
+  sync::Arc,
 };
+use swc_common::Span;
 use url::Url;
 
 lazy_static! {
@@ -24,14 +27,128 @@ lazy_static! {
   .unwrap();
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+/// the cdn of the `npm:` specifiers when the `npm_cdn` is not specified.
+const DEFAULT_NPM_CDN: &str = "https://esm.sh";
+
+/// the extensions of the modules that are rewritten to the `output_ext` unless an
+/// `output_exts` rule matches.
+const MODULE_EXTS: &[&str] = &[".js", ".jsx", ".ts", ".tsx", ".mjs"];
+
+/// the stub of the node builtin modules with an empty polyfill.
+const EMPTY_MODULE_URL: &str = "data:text/javascript,export default {}";
+
+/// the hosts of the registries that serve the versioned packages, like
+/// `https://deno.land/std@0.100.0/http/server.ts` or `https://esm.sh/react@17.0.2`.
+const REGISTRY_HOSTS: &[&str] = &[
+  "deno.land",
+  "esm.sh",
+  "cdn.skypack.dev",
+  "unpkg.com",
+  "jspm.dev",
+];
+
+/// the extensions of the assets that are imported as the urls.
+const ASSET_EXTS: &[&str] = &[
+  ".svg", ".png", ".jpg", ".jpeg", ".gif", ".webp", ".avif", ".ico", ".bmp", ".woff", ".woff2",
+  ".ttf", ".otf", ".eot", ".mp3", ".mp4", ".webm", ".ogg", ".wav", ".wasm", ".pdf", ".txt",
+];
+
+/// the builtin modules of node, which are imported without the `node:` prefix by
+/// the npm packages.
+const NODE_BUILTINS: &[&str] = &[
+  "assert",
+  "buffer",
+  "child_process",
+  "cluster",
+  "console",
+  "constants",
+  "crypto",
+  "dgram",
+  "dns",
+  "domain",
+  "events",
+  "fs",
+  "http",
+  "http2",
+  "https",
+  "module",
+  "net",
+  "os",
+  "path",
+  "perf_hooks",
+  "process",
+  "punycode",
+  "querystring",
+  "readline",
+  "stream",
+  "string_decoder",
+  "timers",
+  "tls",
+  "tty",
+  "url",
+  "util",
+  "vm",
+  "worker_threads",
+  "zlib",
+];
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DependencyDescriptor {
   pub specifier: String,
   pub is_dynamic: bool,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub span: Option<SourceSpan>,
+  /// the kind of the dependency if it's not imported as a module.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub kind: Option<DependencyKind>,
+  /// the name of the split chunk of a dynamic import, which is set by the
+  /// `/* alephChunkName: "admin" */` comment.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub chunk_name: Option<String>,
+  /// whether the dependency is re-exported by `export ... from`, the exports of a
+  /// barrel module include the exports of its re-exported dependencies.
+  #[serde(default, skip_serializing_if = "is_false")]
+  pub reexport: bool,
+  /// whether the dynamic import is the loader of a lazy component, like
+  /// `React.lazy(() => import("./chart.tsx"))`, the framework prefetches the chunk of
+  /// the lazy boundary before it's rendered.
+  #[serde(default, skip_serializing_if = "is_false")]
+  pub lazy: bool,
+}
+
+fn is_false(value: &bool) -> bool {
+  !value
+}
+
+/// The kind of a dependency that is loaded in another way than `import`, the build
+/// emits it as a separate entry.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DependencyKind {
+  /// `new Worker(new URL("./worker.ts", import.meta.url))`
+  Worker,
+}
+
+/// The byte range of an import specifier in the module source.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct SourceSpan {
+  pub start: u32,
+  pub end: u32,
+}
+
+impl From<Span> for SourceSpan {
+  // each module is parsed into its own source map, so the byte positions
+  // of a span are the offsets in the module source.
+  fn from(span: Span) -> Self {
+    SourceSpan {
+      start: span.lo.0,
+      end: span.hi.0,
+    }
+  }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 #[serde(rename_all = "camelCase")]
 pub struct InlineStyle {
   pub r#type: String,
@@ -39,6 +156,53 @@ pub struct InlineStyle {
   pub exprs: Vec<String>,
 }
 
+/// A remote dependency of the module, for maintaining the lockfile and the
+/// `<link rel="modulepreload" integrity="...">` headers.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteDependency {
+  /// the fixed url of the dependency.
+  pub url: String,
+  /// the path of the compiled module relative to the referrer.
+  pub resolved_path: String,
+  /// the expected integrity in the lock, like `sha384-...`, the framework computes
+  /// it when the url is not locked yet.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub integrity: Option<String>,
+}
+
+/// A custom resolve rule, the specifiers that match the `test` regex are rewritten
+/// with the `replace` pattern (supports the `$1` captures) before the builtin rules,
+/// e.g. `{ test: "^~/", replace: "/" }`.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ResolveRule {
+  pub test: String,
+  pub replace: String,
+}
+
+/// the options of the resolver that don't change after it's built, the forks share
+/// them with an `Arc`.
+#[derive(Clone)]
+struct ResolverConfig {
+  import_map: ImportMap,
+  aleph_pkg_uri: Option<String>,
+  react_version: Option<String>,
+  npm_cdn: Option<String>,
+  npm_versions: HashMap<String, String>,
+  node_polyfills: Option<HashMap<String, String>>,
+  remote_prefix: String,
+  local_prefix: String,
+  output_ext: String,
+  output_exts: Vec<(String, String)>,
+  dep_hashes: HashMap<String, String>,
+  asset_url: Option<String>,
+  resolve_rules: Vec<(Regex, String)>,
+  aliases: Vec<(String, String)>,
+  base_url: Option<String>,
+  lock: HashMap<String, String>,
+  versions: HashMap<String, String>,
+}
+
 /// A Resolver to resolve aleph.js import/export URL.
 pub struct Resolver {
   /// the text specifier associated with the import/export statement.
@@ -47,23 +211,40 @@ pub struct Resolver {
   pub specifier_is_remote: bool,
   /// dependency graph
   pub dep_graph: Vec<DependencyDescriptor>,
-  /// inline styles
-  pub inline_styles: HashMap<String, InlineStyle>,
+  /// inline styles, the maps of the output are ordered to keep it stable across rebuilds
+  pub inline_styles: BTreeMap<String, InlineStyle>,
   /// bundle mode
   pub bundle_mode: bool,
   /// bundled modules
   pub bundle_external: IndexSet<String>,
   /// star exports
   pub star_exports: Vec<String>,
+  /// dynamic imports with runtime parts, like `/pages/posts/*.tsx`
+  pub dynamic_import_patterns: Vec<String>,
+  /// the ids of the `useDeno` hooks that are signed by the compiler
+  pub deno_hooks: Vec<String>,
+  /// css imports that are removed from the module
+  pub css_deps: Vec<String>,
+  /// the generated CSS of the CSS modules, keyed by the url
+  pub css_modules: BTreeMap<String, String>,
+  /// the scoped CSS of the `css` templates, keyed by the scope class
+  pub scoped_css: BTreeMap<String, String>,
+  /// the fixed urls of the imported assets like images and fonts
+  pub assets: Vec<String>,
+  /// the remote dependencies with the integrity in the lock
+  pub remote_deps: Vec<RemoteDependency>,
+  /// the absolute urls of the static dependencies for the `modulepreload` links
+  pub preload: Vec<String>,
   /// extra imports
   pub extra_imports: IndexSet<String>,
   /// builtin jsx tags like `a`, `link`, `head`, etc
   pub used_builtin_jsx_tags: IndexSet<String>,
+  /// the coverage map of the module with the `coverage` option
+  pub coverage: Option<FileCoverage>,
 
-  // private
-  import_map: ImportMap,
-  aleph_pkg_uri: Option<String>,
-  react_version: Option<String>,
+  // private, the options are shared by the forks across the threads of the parallel
+  // transform
+  config: Arc<ResolverConfig>,
 }
 
 impl Resolver {
@@ -83,20 +264,338 @@ impl Resolver {
       specifier: specifier.into(),
       specifier_is_remote: is_remote_url(specifier),
       used_builtin_jsx_tags: IndexSet::new(),
+      coverage: None,
       dep_graph: Vec::new(),
       star_exports: Vec::new(),
-      inline_styles: HashMap::new(),
-      import_map: ImportMap::from_hashmap(import_map),
-      aleph_pkg_uri,
-      react_version,
+      dynamic_import_patterns: Vec::new(),
+      deno_hooks: Vec::new(),
+      css_deps: Vec::new(),
+      css_modules: BTreeMap::new(),
+      scoped_css: BTreeMap::new(),
+      assets: Vec::new(),
+      inline_styles: BTreeMap::new(),
       bundle_mode,
       bundle_external: set,
       extra_imports: IndexSet::new(),
+      remote_deps: Vec::new(),
+      preload: Vec::new(),
+      config: Arc::new(ResolverConfig {
+        import_map: ImportMap::from_hashmap(import_map),
+        aleph_pkg_uri,
+        react_version,
+        npm_cdn: None,
+        npm_versions: HashMap::new(),
+        node_polyfills: None,
+        remote_prefix: "/-/".into(),
+        local_prefix: "/".into(),
+        output_ext: "js".into(),
+        output_exts: Vec::new(),
+        dep_hashes: HashMap::new(),
+        asset_url: None,
+        resolve_rules: Vec::new(),
+        aliases: Vec::new(),
+        base_url: None,
+        lock: HashMap::new(),
+        versions: HashMap::new(),
+      }),
+    }
+  }
+
+  /// the options can only be changed before the resolver is forked.
+  fn config_mut(&mut self) -> &mut ResolverConfig {
+    Arc::make_mut(&mut self.config)
+  }
+
+  /// rewrites the bare specifiers that are not covered by the import map to the
+  /// `npm_cdn`, e.g. `dayjs` -> `https://esm.sh/dayjs`, the `versions` pins the
+  /// version of a package.
+  pub fn with_npm_cdn(mut self, npm_cdn: &str, versions: HashMap<String, String>) -> Self {
+    self.config_mut().npm_cdn = Some(npm_cdn.trim_end_matches('/').into());
+    self.config_mut().npm_versions = versions;
+    self
+  }
+
+  /// maps the `node:` builtin modules to the polyfills for the browser, keyed by
+  /// the module name, e.g. `path` -> `https://esm.sh/path-browserify`, the
+  /// `node:` specifiers without a polyfill are kept untouched. The bare imports
+  /// of the builtin modules, like `import { EventEmitter } from "events"`, are
+  /// treated as the `node:` specifiers if they have a polyfill or there is no npm
+  /// cdn to resolve them, and an empty polyfill stubs the module with an empty
+  /// object.
+  pub fn with_node_polyfills(mut self, polyfills: HashMap<String, String>) -> Self {
+    self.config_mut().node_polyfills = Some(polyfills);
+    self
+  }
+
+  /// changes the output paths of the compiled modules, the remote modules are cached
+  /// under the `remote_prefix` (default `/-/`) and the local modules are emitted under
+  /// the `local_prefix` (default `/`), the extensions of the modules are rewritten to
+  /// the `output_ext` (default `js`), e.g. `.tsx` -> `.js`.
+  pub fn with_output_paths(
+    mut self,
+    remote_prefix: &str,
+    local_prefix: &str,
+    output_ext: &str,
+  ) -> Self {
+    if !remote_prefix.is_empty() {
+      self.config_mut().remote_prefix = format!("/{}/", remote_prefix.trim_matches('/'));
+    }
+    if !local_prefix.is_empty() {
+      self.config_mut().local_prefix = match local_prefix.trim_matches('/') {
+        "" => "/".into(),
+        prefix => format!("/{}/", prefix),
+      };
+    }
+    if !output_ext.is_empty() {
+      self.config_mut().output_ext = output_ext.trim_start_matches('.').into();
+    }
+    self
+  }
+
+  /// adds the rules that rewrite the extensions of the imports, keyed by the extension
+  /// of the source (the longest matched one wins) to the output extension, e.g.
+  /// `{ ".page.tsx": ".page.js", ".json": ".json" }`, the `""` key matches the paths
+  /// without an extension, like `{ "": ".js" }`. the other modules are rewritten to
+  /// the `output_ext` and the other files (like `style.css`) get the `output_ext`
+  /// appended.
+  pub fn with_output_exts(mut self, rules: HashMap<String, String>) -> Self {
+    let dotted = |ext: String| match ext.as_str() {
+      "" => ext,
+      _ if ext.starts_with('.') => ext,
+      _ => format!(".{}", ext),
+    };
+    let mut rules: Vec<(String, String)> = rules
+      .into_iter()
+      .map(|(ext, output)| (dotted(ext), dotted(output)))
+      .collect();
+    rules.sort_by(|(a, _), (b, _)| b.len().cmp(&a.len()).then(a.cmp(b)));
+    self.config_mut().output_exts = rules;
+    self
+  }
+
+  /// embeds the content hashes of the local dependencies into the rewritten import
+  /// urls for cache busting, the `hashes` are keyed by the fixed url of the module,
+  /// e.g. `./logo.tsx` -> `./logo.js#/logo.tsx@1a2b3c`.
+  pub fn with_dep_hashes(mut self, hashes: HashMap<String, String>) -> Self {
+    self.config_mut().dep_hashes = hashes;
+    self
+  }
+
+  /// resolves the imports of the assets (by the extension, like `./logo.svg`) to the
+  /// url constants, the `template` formats the emitted url with the `[url]` (the
+  /// fixed url) and the `[hash]` (the hash placeholder of the `dep_hashes`)
+  /// placeholders, e.g. `/_aleph/assets[url]?v=[hash]`.
+  pub fn with_asset_url(mut self, template: &str) -> Self {
+    if !template.is_empty() {
+      self.config_mut().asset_url = Some(template.into());
+    }
+    self
+  }
+
+  /// adds the custom resolve rules that are consulted before the import map, the
+  /// first matched rule wins.
+  pub fn with_resolve_rules(mut self, rules: &[ResolveRule]) -> Result<Self, regex::Error> {
+    for rule in rules {
+      self
+        .config_mut()
+        .resolve_rules
+        .push((Regex::new(rule.test.as_str())?, rule.replace.clone()));
+    }
+    Ok(self)
+  }
+
+  /// adds the path aliases like the `paths` of tsconfig, e.g. `{ "~/*": "./src/*" }`,
+  /// the targets are relative to the root. an alias with a `*` matches the prefix
+  /// (the longest one wins) and the rest replaces the `*` of the target.
+  pub fn with_aliases(mut self, aliases: HashMap<String, String>) -> Self {
+    let mut aliases: Vec<(String, String)> = aliases
+      .into_iter()
+      .map(|(alias, target)| match target.strip_prefix("./") {
+        Some(target) => (alias, format!("/{}", target)),
+        None => (alias, target),
+      })
+      .collect();
+    aliases.sort_by(|(a, _), (b, _)| {
+      let prefix_len = |alias: &str| alias.split('*').next().unwrap().len();
+      prefix_len(b).cmp(&prefix_len(a)).then(a.cmp(b))
+    });
+    self.config_mut().aliases = aliases;
+    self
+  }
+
+  /// resolves the root-absolute imports of the local modules (like `/components/logo.tsx`)
+  /// against the `base_url`, the root (a path or a remote url) that the imports are
+  /// relative to, e.g. with `./src` the import is resolved to `/src/components/logo.tsx`.
+  /// the imports of the remote modules are relative to their hosts and kept untouched.
+  pub fn with_base_url(mut self, base_url: &str) -> Self {
+    let base_url = if is_remote_url(base_url) {
+      base_url.trim_end_matches('/').to_owned()
+    } else {
+      let path = base_url.trim_start_matches('.').trim_matches('/');
+      match RelativePath::new(path).normalize().as_str() {
+        "" => String::new(),
+        path => format!("/{}", path),
+      }
+    };
+    self.config_mut().base_url = match base_url.as_str() {
+      "" => None,
+      _ => Some(base_url),
+    };
+    self
+  }
+
+  /// applies the first matched alias to the url.
+  fn apply_alias(&self, url: &str) -> Option<String> {
+    for (alias, target) in self.config.aliases.iter() {
+      match alias.split_once('*') {
+        Some((prefix, suffix)) => {
+          if url.len() >= prefix.len() + suffix.len()
+            && url.starts_with(prefix)
+            && url.ends_with(suffix)
+          {
+            let matched = &url[prefix.len()..url.len() - suffix.len()];
+            return Some(target.replacen('*', matched, 1));
+          }
+        }
+        None => {
+          if url.eq(alias) {
+            return Some(target.clone());
+          }
+        }
+      }
+    }
+    None
+  }
+
+  /// carries the integrity of the remote modules from the `lock` (keyed by the fixed
+  /// url) into the `remote_deps`.
+  pub fn with_lock(mut self, lock: HashMap<String, String>) -> Self {
+    self.config_mut().lock = lock;
+    self
+  }
+
+  /// pins the versions of the remote modules, the `versions` is keyed by the package
+  /// name (to the version) or by the url (to the pinned url), e.g.
+  /// `{ "std": "0.100.0" }` rewrites `https://deno.land/std/http/server.ts` to
+  /// `https://deno.land/std@0.100.0/http/server.ts`, and
+  /// `{ "https://esm.sh/swr": "https://esm.sh/swr@0.5.6" }` rewrites the url and the
+  /// sub-modules of it.
+  pub fn with_versions(mut self, versions: HashMap<String, String>) -> Self {
+    self.config_mut().versions = versions;
+    self
+  }
+
+  /// returns the hash placeholder of the local module, the first 6 chars of the
+  /// known content hash or `000000`.
+  fn hash_placeholder(&self, fixed_url: &str) -> String {
+    match self.config.dep_hashes.get(fixed_url) {
+      Some(hash) if hash.len() >= 6 => hash[..6].into(),
+      _ => "000000".into(),
+    }
+  }
+
+  /// returns the emitted url of an asset import and records the asset, or `None` if
+  /// the url is not an asset or the assets are not resolved.
+  pub fn resolve_asset(&mut self, url: &str) -> Option<String> {
+    let template = self.config.asset_url.as_ref()?;
+    if !is_asset_url(url) {
+      return None;
+    }
+    let fixed_url = self.fix_url(url);
+    let emitted_url = template
+      .replace("[url]", fixed_url.as_str())
+      .replace("[hash]", self.hash_placeholder(fixed_url.as_str()).as_str());
+    if !self.assets.contains(&fixed_url) {
+      self.assets.push(fixed_url);
+    }
+    Some(emitted_url)
+  }
+
+  /// rewrites the extension of the file name by the `output_exts` rules or the
+  /// `output_ext` of the modules, the `bundling` marker is inserted before the output
+  /// extension. returns `None` if the file name is not a module and no rule matches.
+  pub fn rewrite_ext(&self, file_name: &str, bundling: bool) -> Option<String> {
+    let (ext, output) = self
+      .config
+      .output_exts
+      .iter()
+      .find(|(ext, _)| !ext.is_empty() && file_name.ends_with(ext.as_str()))
+      .map(|(ext, output)| (ext.as_str(), output.clone()))
+      .or_else(|| {
+        MODULE_EXTS
+          .iter()
+          .find(|ext| file_name.ends_with(*ext))
+          .map(|ext| (*ext, format!(".{}", self.config.output_ext)))
+      })
+      .or_else(|| {
+        if Path::new(file_name).extension().is_some() {
+          return None;
+        }
+        self
+          .config
+          .output_exts
+          .iter()
+          .find(|(ext, _)| ext.is_empty())
+          .map(|(_, output)| ("", output.clone()))
+      })?;
+    Some(format!(
+      "{}{}{}",
+      &file_name[..file_name.len() - ext.len()],
+      if bundling { ".bundling" } else { "" },
+      output
+    ))
+  }
+
+  /// returns the absolute path that the module of the `specifier` (a fixed url) is
+  /// emitted to, like `/pages/index.tsx` -> `/pages/index.js` and
+  /// `https://esm.sh/react` -> `/-/esm.sh/react.js`.
+  pub fn output_path(&self, specifier: &str) -> String {
+    let path = if is_remote_url(specifier) {
+      self.fix_import_url(specifier)
+    } else {
+      format!(
+        "{}{}",
+        self.config.local_prefix.trim_end_matches('/'),
+        specifier
+      )
+    };
+    match self.rewrite_ext(path.as_str(), false) {
+      Some(path) => path,
+      None if Path::new(path.as_str()).extension().is_some() => {
+        format!("{}.{}", path, self.config.output_ext)
+      }
+      None => path,
+    }
+  }
+
+  /// creates a new resolver for the `specifier` that shares the options of this
+  /// resolver without copying them, only the state of the module is new.
+  pub fn fork(&self, specifier: &str) -> Self {
+    Resolver {
+      specifier: specifier.into(),
+      specifier_is_remote: is_remote_url(specifier),
+      used_builtin_jsx_tags: IndexSet::new(),
+      coverage: None,
+      dep_graph: Vec::new(),
+      star_exports: Vec::new(),
+      dynamic_import_patterns: Vec::new(),
+      deno_hooks: Vec::new(),
+      css_deps: Vec::new(),
+      css_modules: BTreeMap::new(),
+      scoped_css: BTreeMap::new(),
+      assets: Vec::new(),
+      inline_styles: BTreeMap::new(),
+      bundle_mode: self.bundle_mode,
+      bundle_external: self.bundle_external.clone(),
+      extra_imports: IndexSet::new(),
+      remote_deps: Vec::new(),
+      preload: Vec::new(),
+      config: self.config.clone(),
     }
   }
 
   pub fn get_aleph_pkg_uri(&self) -> String {
-    if let Some(aleph_pkg_uri) = &self.aleph_pkg_uri {
+    if let Some(aleph_pkg_uri) = &self.config.aleph_pkg_uri {
       return aleph_pkg_uri.into();
     }
     "https://deno.land/x/aleph".into()
@@ -139,14 +638,14 @@ impl Resolver {
       Some(os_str) => match os_str.to_str() {
         Some(s) => {
           if RE_ENDS_WITH_VERSION.is_match(url.path()) {
-            "js"
+            self.config.output_ext.as_str()
           } else {
             s
           }
         }
-        None => "js",
+        None => self.config.output_ext.as_str(),
       },
-      None => "js",
+      None => self.config.output_ext.as_str(),
     });
     if let Some(os_str) = path.file_name() {
       if let Some(s) = os_str.to_str() {
@@ -169,7 +668,7 @@ impl Resolver {
         path_buf.set_file_name(file_name);
       }
     }
-    let mut p = "/-/".to_owned();
+    let mut p = self.config.remote_prefix.clone();
     let scheme = url.scheme();
     if scheme == "http" {
       p.push_str("http_");
@@ -187,16 +686,76 @@ impl Resolver {
     p
   }
 
-  /// resolve import/export url.
+  /// fix an import/export url to the full url, applying the import map.
   // [/pages/index.tsx]
-  // - `https://esm.sh/swr` -> `../-/esm.sh/swr.js`
-  // - `https://esm.sh/react` -> `../-/esm.sh/react@${REACT_VERSION}.js`
-  // - `https://deno.land/x/aleph/mod.ts` -> `../-/deno.land/x/aleph@v${ALEPH_VERSION}/mod.ts`
-  // - `../components/logo.tsx` -> `../components/logo.js#/components/logo.tsx@000000`
-  // - `../styles/app.css` -> `../styles/app.css.js#/styles/app.css@000000`
-  pub fn resolve(&mut self, url: &str, is_dynamic: bool) -> (String, String) {
+  // - `../components/logo.tsx` -> `/components/logo.tsx`
+  // - `react` -> `https://esm.sh/react@${REACT_VERSION}`
+  // - `https://deno.land/x/aleph/mod.ts` -> `${ALEPH_PKG_URI}/mod.ts`
+  // - `npm:react@17` -> `https://esm.sh/react@17`
+  // - `node:path` -> `node:path` (or the polyfill)
+  pub fn fix_url(&self, url: &str) -> String {
+    // the inline modules are not paths
+    if is_data_url(url) {
+      return url.into();
+    }
+    // resolve the root-absolute url against the base url
+    let mut url = match &self.config.base_url {
+      Some(base_url) if !self.specifier_is_remote && is_root_absolute(url) => {
+        format!("{}{}", base_url, url)
+      }
+      _ => url.to_owned(),
+    };
+    // apply custom resolve rules
+    for (re, replace) in self.config.resolve_rules.iter() {
+      if re.is_match(url.as_str()) {
+        url = re.replace(url.as_str(), replace.as_str()).into();
+        break;
+      }
+    }
+    // apply path aliases
+    if let Some(aliased) = self.apply_alias(url.as_str()) {
+      url = aliased;
+    }
     // apply import map
-    let url = self.import_map.resolve(self.specifier.as_str(), url);
+    let mut url = self
+      .config
+      .import_map
+      .resolve(self.specifier.as_str(), url.as_str());
+    // the bare imports of the node builtin modules in the browser
+    if let Some(polyfills) = &self.config.node_polyfills {
+      if is_node_builtin(url.as_str())
+        && (polyfills.contains_key(url.as_str()) || self.config.npm_cdn.is_none())
+      {
+        url = format!("node:{}", url);
+      }
+    }
+    // map the node builtin module to the polyfill, or keep it untouched
+    if let Some(name) = url.strip_prefix("node:") {
+      match self
+        .config
+        .node_polyfills
+        .as_ref()
+        .and_then(|polyfills| polyfills.get(name))
+      {
+        Some(polyfill) if polyfill.is_empty() => return EMPTY_MODULE_URL.into(),
+        Some(polyfill) => url = polyfill.clone(),
+        None => return url,
+      }
+    }
+    // rewrite npm specifier to npm cdn
+    if let Some(pkg) = url.strip_prefix("npm:") {
+      url = format!(
+        "{}/{}",
+        self.config.npm_cdn.as_deref().unwrap_or(DEFAULT_NPM_CDN),
+        self.pin_npm_version(pkg.trim_start_matches('/'))
+      );
+    }
+    // rewrite bare specifier to npm cdn
+    if let Some(npm_cdn) = &self.config.npm_cdn {
+      if is_bare_specifier(url.as_str()) {
+        url = format!("{}/{}", npm_cdn, self.pin_npm_version(url.as_str()));
+      }
+    }
     let mut fixed_url: String = if is_remote_url(url.as_str()) {
       url.into()
     } else {
@@ -230,7 +789,7 @@ impl Resolver {
       }
     };
     // fix deno.land/x/aleph url
-    if let Some(aleph_pkg_uri) = &self.aleph_pkg_uri {
+    if let Some(aleph_pkg_uri) = &self.config.aleph_pkg_uri {
       if fixed_url.starts_with("https://deno.land/x/aleph/") {
         fixed_url = format!(
           "{}/{}",
@@ -240,7 +799,7 @@ impl Resolver {
       }
     }
     // fix react/react-dom url
-    if let Some(version) = &self.react_version {
+    if let Some(version) = &self.config.react_version {
       if RE_REACT_URL.is_match(fixed_url.as_str()) {
         let caps = RE_REACT_URL.captures(fixed_url.as_str()).unwrap();
         let mut host = caps.get(1).map_or("", |m| m.as_str());
@@ -258,6 +817,150 @@ impl Resolver {
         }
       }
     }
+    // pin the version of remote module
+    if !self.config.versions.is_empty() && is_remote_url(fixed_url.as_str()) {
+      fixed_url = self.pin_remote_version(fixed_url.as_str());
+    }
+    fixed_url
+  }
+
+  /// pin the version of the remote url by the `versions`, the longest matched url key
+  /// wins over the package name keys.
+  fn pin_remote_version(&self, url: &str) -> String {
+    let matched = self
+      .config
+      .versions
+      .iter()
+      .filter(|(key, _)| is_remote_url(key))
+      .filter(|(key, _)| {
+        let key = key.trim_end_matches('/');
+        matches!(url.strip_prefix(key), Some(rest) if rest.is_empty() || rest.starts_with('/') || rest.starts_with('?'))
+      })
+      .max_by_key(|(key, _)| key.trim_end_matches('/').len());
+    if let Some((key, pinned)) = matched {
+      let key = key.trim_end_matches('/');
+      return format!("{}{}", pinned.trim_end_matches('/'), &url[key.len()..]);
+    }
+    if let Some((pkg_url, name, None, rest)) = split_remote_package(url) {
+      if let Some(version) = self.config.versions.get(name) {
+        return format!("{}@{}{}", pkg_url, version, rest);
+      }
+    }
+    url.into()
+  }
+
+  /// pin the version of the package specifier, like `dayjs/plugin/utc` -> `dayjs@1.10.4/plugin/utc`.
+  fn pin_npm_version(&self, specifier: &str) -> String {
+    let mut segments = specifier.splitn(if specifier.starts_with('@') { 3 } else { 2 }, '/');
+    let mut name = segments.next().unwrap().to_owned();
+    if specifier.starts_with('@') {
+      if let Some(pkg) = segments.next() {
+        name = format!("{}/{}", name, pkg);
+      }
+    }
+    let path = segments.next();
+    // already has a version, like `dayjs@1.10.4` or `@babel/core@7`
+    if name.rfind('@').unwrap_or(0) > 0 {
+      return specifier.into();
+    }
+    match self.config.npm_versions.get(&name) {
+      Some(version) => match path {
+        Some(path) => format!("{}@{}/{}", name, version, path),
+        None => format!("{}@{}", name, version),
+      },
+      None => specifier.into(),
+    }
+  }
+
+  /// record a dynamic import pattern like `./posts/*.tsx` and return the fixed pattern.
+  pub fn resolve_dynamic_import_pattern(&mut self, pattern: &str) -> String {
+    let fixed_pattern = self.fix_url(pattern);
+    self.dynamic_import_patterns.push(fixed_pattern.clone());
+    fixed_pattern
+  }
+
+  /// resolve the url of a web worker like a dynamic import, the dependency is
+  /// marked with the `worker` kind.
+  pub fn resolve_worker(&mut self, url: &str, span: Option<Span>) -> (String, String) {
+    let resolved = self.resolve(url, true, span);
+    if let Some(dep) = self.dep_graph.last_mut() {
+      dep.kind = Some(DependencyKind::Worker);
+    }
+    resolved
+  }
+
+  /// resolve the url of an `export ... from` like an import, the dependency is
+  /// marked as re-exported.
+  pub fn resolve_reexport(&mut self, url: &str, span: Option<Span>) -> (String, String) {
+    let deps_len = self.dep_graph.len();
+    let resolved = self.resolve(url, false, span);
+    // the inline modules are not added to the deps graph
+    if self.dep_graph.len() > deps_len {
+      if let Some(dep) = self.dep_graph.last_mut() {
+        dep.reexport = true;
+      }
+    }
+    resolved
+  }
+
+  /// returns the absolute url of the resolved path of a dependency, which is relative
+  /// to the emitted module, e.g. `../components/logo.js#/components/logo.tsx@000000`
+  /// of `/pages/index.tsx` -> `/components/logo.js#/components/logo.tsx@000000`.
+  pub fn absolute_path(&self, path: &str) -> String {
+    let mut buf = if self.specifier_is_remote {
+      PathBuf::from(self.fix_import_url(self.specifier.as_str()))
+    } else {
+      PathBuf::from(format!(
+        "{}{}",
+        self.config.local_prefix.trim_end_matches('/'),
+        self.specifier.as_str()
+      ))
+    };
+    buf.pop();
+    // the hash placeholder is not a part of the path
+    let (path, hash) = match path.split_once('#') {
+      Some((path, hash)) => (path, Some(hash)),
+      None => (path, None),
+    };
+    buf.push(path);
+    let mut url = "/".to_owned()
+      + RelativePath::new(buf.to_slash().unwrap().as_str())
+        .normalize()
+        .as_str();
+    if let Some(hash) = hash {
+      url.push('#');
+      url.push_str(hash);
+    }
+    url
+  }
+
+  /// resolve import/export url.
+  // [/pages/index.tsx]
+  // - `https://esm.sh/swr` -> `../-/esm.sh/swr.js`
+  // - `https://esm.sh/react` -> `../-/esm.sh/react@${REACT_VERSION}.js`
+  // - `https://deno.land/x/aleph/mod.ts` -> `../-/deno.land/x/aleph@v${ALEPH_VERSION}/mod.ts`
+  // - `../components/logo.tsx` -> `../components/logo.js#/components/logo.tsx@000000`
+  // - `../styles/app.css` -> `../styles/app.css.js#/styles/app.css@000000`
+  pub fn resolve(&mut self, url: &str, is_dynamic: bool, span: Option<Span>) -> (String, String) {
+    // the inline modules have no file to fetch or emit, so they are kept untouched
+    // and not added to the deps graph
+    if is_data_url(url) {
+      return (url.into(), url.into());
+    }
+    let fixed_url = self.fix_url(url);
+    // the node builtin modules are resolved by the runtime
+    if is_node_specifier(fixed_url.as_str()) {
+      self.dep_graph.push(DependencyDescriptor {
+        specifier: fixed_url.clone(),
+        is_dynamic,
+        span: span.map(SourceSpan::from),
+        kind: None,
+        chunk_name: None,
+        reexport: false,
+        lazy: false,
+      });
+      return (fixed_url.clone(), fixed_url);
+    }
     let is_remote = is_remote_url(fixed_url.as_str());
     let mut resolved_path = if is_remote {
       if self.specifier_is_remote {
@@ -269,7 +972,12 @@ impl Resolver {
         )
         .unwrap()
       } else {
-        let mut buf = PathBuf::from(self.specifier.as_str());
+        // the local modules are emitted under the `local_prefix`
+        let mut buf = PathBuf::from(format!(
+          "{}{}",
+          self.config.local_prefix.trim_end_matches('/'),
+          self.specifier.as_str()
+        ));
         buf.pop();
         diff_paths(
           self.fix_import_url(fixed_url.as_str()),
@@ -310,67 +1018,147 @@ impl Resolver {
       }
     };
     // fix extension & add hash placeholder
-    match resolved_path.extension() {
-      Some(os_str) => match os_str.to_str() {
-        Some(s) => match s {
-          "js" | "jsx" | "ts" | "tsx" | "mjs" => {
-            let mut filename = resolved_path
-              .file_name()
-              .unwrap()
-              .to_str()
-              .unwrap()
-              .trim_end_matches(s)
-              .to_owned();
-            if self.bundle_mode && !is_dynamic {
-              filename.push_str("bundling.");
-            }
-            filename.push_str("js");
-            if !is_remote && !self.specifier_is_remote {
-              filename.push_str("#");
-              filename.push_str(fixed_url.as_str());
-              filename.push_str("@000000");
-            }
-            resolved_path.set_file_name(filename);
-          }
-          _ => {
-            let mut filename = resolved_path
-              .file_name()
-              .unwrap()
-              .to_str()
-              .unwrap()
-              .to_owned();
-            if self.bundle_mode && !is_dynamic {
-              filename.push_str(".bundling");
-            }
-            filename.push_str(".js");
-            if !is_remote && !self.specifier_is_remote {
-              filename.push('#');
-              filename.push_str(fixed_url.as_str());
-              filename.push_str("@000000");
-            }
-            resolved_path.set_file_name(filename);
-          }
-        },
-        None => {}
-      },
-      None => {}
-    };
+    if let Some(filename) = resolved_path.file_name().and_then(|name| name.to_str()) {
+      let bundling = self.bundle_mode && !is_dynamic;
+      let filename = match self.rewrite_ext(filename, bundling) {
+        Some(filename) => Some(filename),
+        // the other files are compiled to the modules, like `style.css` -> `style.css.js`
+        None if resolved_path.extension().is_some() => Some(format!(
+          "{}{}.{}",
+          filename,
+          if bundling { ".bundling" } else { "" },
+          self.config.output_ext
+        )),
+        None => None,
+      };
+      if let Some(mut filename) = filename {
+        if !is_remote && !self.specifier_is_remote {
+          filename.push('#');
+          filename.push_str(fixed_url.as_str());
+          filename.push('@');
+          filename.push_str(self.hash_placeholder(fixed_url.as_str()).as_str());
+        }
+        resolved_path.set_file_name(filename);
+      }
+    }
     self.dep_graph.push(DependencyDescriptor {
       specifier: fixed_url.clone(),
       is_dynamic,
+      span: span.map(SourceSpan::from),
+      kind: None,
+      chunk_name: None,
+      reexport: false,
+      lazy: false,
     });
-    let path = resolved_path.to_slash().unwrap();
+    let mut path = resolved_path.to_slash().unwrap();
     if !path.starts_with("./") && !path.starts_with("../") && !path.starts_with("/") {
-      return (format!("./{}", path), fixed_url);
+      path = format!("./{}", path);
+    }
+    if !is_dynamic {
+      let preload = self.absolute_path(path.as_str());
+      if !self.preload.contains(&preload) {
+        self.preload.push(preload);
+      }
+    }
+    if is_remote && !self.remote_deps.iter().any(|dep| dep.url == fixed_url) {
+      self.remote_deps.push(RemoteDependency {
+        url: fixed_url.clone(),
+        resolved_path: path.clone(),
+        integrity: self.config.lock.get(&fixed_url).cloned(),
+      });
     }
     (path, fixed_url)
   }
 }
 
+/// checks if the url is relative to the root, like `/components/logo.tsx`, the
+/// protocol-relative urls like `//esm.sh/react` are not.
+fn is_root_absolute(url: &str) -> bool {
+  url.starts_with('/') && !url.starts_with("//")
+}
+
 pub fn is_remote_url(url: &str) -> bool {
   return url.starts_with("https://") || url.starts_with("http://");
 }
 
+/// checks if the url is a node builtin module like `node:path`.
+pub fn is_node_specifier(url: &str) -> bool {
+  url.starts_with("node:")
+}
+
+/// checks if the bare specifier is a node builtin module like `path` or
+/// `fs/promises`.
+pub fn is_node_builtin(url: &str) -> bool {
+  NODE_BUILTINS.contains(&url.split('/').next().unwrap_or_default())
+}
+
+/// checks if the url is a non-code asset like an image, a font or a wasm module.
+pub fn is_asset_url(url: &str) -> bool {
+  let path = url.split(&['?', '#'][..]).next().unwrap();
+  ASSET_EXTS.iter().any(|ext| path.ends_with(ext))
+}
+
+/// checks if the url is an inline module like `data:text/javascript,...` or a
+/// `blob:` url that is created at runtime.
+pub fn is_data_url(url: &str) -> bool {
+  url.starts_with("data:") || url.starts_with("blob:")
+}
+
+/// splits the url of a package of the registries into the url of the package, the
+/// name, the version and the rest, e.g. `https://deno.land/std@0.100.0/http/server.ts`
+/// -> (`https://deno.land/std`, `std`, `Some("0.100.0")`, `/http/server.ts`). returns
+/// `None` if the url is not a package of the registries.
+pub fn split_remote_package(url: &str) -> Option<(&str, &str, Option<&str>, &str)> {
+  let rest = url
+    .strip_prefix("https://")
+    .or_else(|| url.strip_prefix("http://"))?;
+  let host = rest.split('/').next().unwrap();
+  if !REGISTRY_HOSTS.contains(&host) {
+    return None;
+  }
+  let mut start = url.len() - rest.len() + host.len() + 1;
+  if start > url.len() {
+    return None;
+  }
+  if host == "deno.land" && url[start..].starts_with("x/") {
+    start += 2;
+  }
+  let path = &url[start..];
+  let segment_end = |from: usize| {
+    path[from..]
+      .find(&['/', '?'][..])
+      .map_or(path.len(), |i| from + i)
+  };
+  // the scoped packages like `@babel/core`
+  let end = match path.strip_prefix('@') {
+    Some(scoped) => match scoped.find('/') {
+      Some(i) => segment_end(i + 2),
+      None => path.len(),
+    },
+    None => segment_end(0),
+  };
+  let segment = &path[..end];
+  if segment.is_empty() {
+    return None;
+  }
+  // the `@` of the scope is not the version
+  let (name, version) = match segment[1..].find('@') {
+    Some(i) => (&segment[..i + 1], Some(&segment[i + 2..])),
+    None => (segment, None),
+  };
+  Some((&url[..start + name.len()], name, version, &path[end..]))
+}
+
+/// checks if the url is a bare specifier like `dayjs` or `@babel/core`.
+fn is_bare_specifier(url: &str) -> bool {
+  !(is_remote_url(url)
+    || url.starts_with('/')
+    || url.starts_with('.')
+    || url.starts_with('~')
+    || url.starts_with("@/")
+    || url.contains(':'))
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -423,16 +1211,12 @@ mod tests {
   }
 
   #[test]
-  fn resolve_local() {
+  fn resolve_npm_cdn() {
     let mut imports: HashMap<String, String> = HashMap::new();
-    imports.insert("@/".into(), "./".into());
-    imports.insert("~/".into(), "./".into());
-    imports.insert("react".into(), "https://esm.sh/react".into());
-    imports.insert("react-dom/".into(), "https://esm.sh/react-dom/".into());
-    imports.insert(
-      "https://deno.land/x/aleph/".into(),
-      "http://localhost:2020/".into(),
-    );
+    imports.insert("swr".into(), "https://cdn.skypack.dev/swr".into());
+    let mut versions: HashMap<String, String> = HashMap::new();
+    versions.insert("dayjs".into(), "1.10.4".into());
+    versions.insert("@babel/core".into(), "7.13.10".into());
     let mut resolver = Resolver::new(
       "/pages/index.tsx",
       ImportHashMap {
@@ -440,117 +1224,726 @@ mod tests {
         scopes: HashMap::new(),
       },
       None,
-      Some("17.0.1".into()),
+      None,
       false,
       vec![],
+    )
+    .with_npm_cdn("https://esm.sh/", versions);
+    assert_eq!(resolver.fix_url("dayjs"), "https://esm.sh/dayjs@1.10.4");
+    assert_eq!(
+      resolver.fix_url("dayjs/plugin/utc"),
+      "https://esm.sh/dayjs@1.10.4/plugin/utc"
     );
     assert_eq!(
-      resolver.resolve("https://esm.sh/react", false),
-      (
-        "../-/esm.sh/react@17.0.1.js".into(),
-        "https://esm.sh/react@17.0.1".into()
-      )
+      resolver.fix_url("dayjs@1.9.0"),
+      "https://esm.sh/dayjs@1.9.0"
     );
     assert_eq!(
-      resolver.resolve("https://esm.sh/react-refresh", false),
-      (
-        "../-/esm.sh/react-refresh.js".into(),
-        "https://esm.sh/react-refresh".into()
-      )
+      resolver.fix_url("@babel/core"),
+      "https://esm.sh/@babel/core@7.13.10"
     );
+    assert_eq!(resolver.fix_url("lodash/get"), "https://esm.sh/lodash/get");
+    assert_eq!(resolver.fix_url("swr"), "https://cdn.skypack.dev/swr");
+    assert_eq!(resolver.fix_url("./lib.ts"), "/pages/lib.ts");
     assert_eq!(
-      resolver.resolve("https://deno.land/x/aleph/framework/react/link.ts", false),
+      resolver.resolve("dayjs", false, None),
       (
-        "../-/http_localhost_2020/framework/react/link.js".into(),
-        "http://localhost:2020/framework/react/link.ts".into()
+        "../-/esm.sh/dayjs@1.10.4.js".into(),
+        "https://esm.sh/dayjs@1.10.4".into()
       )
     );
+  }
+
+  #[test]
+  fn resolver_fork() {
+    let mut imports: HashMap<String, String> = HashMap::new();
+    imports.insert("react".into(), "https://esm.sh/react".into());
+    let resolver = Resolver::new(
+      "",
+      ImportHashMap {
+        imports,
+        scopes: HashMap::new(),
+      },
+      None,
+      Some("17.0.1".into()),
+      false,
+      vec![],
+    );
+    let mut forked = resolver.fork("/pages/index.tsx");
     assert_eq!(
-      resolver.resolve("https://esm.sh/react@16", false),
+      forked.resolve("react", false, None),
       (
         "../-/esm.sh/react@17.0.1.js".into(),
         "https://esm.sh/react@17.0.1".into()
       )
     );
+    assert_eq!(forked.dep_graph.len(), 1);
+    assert!(resolver.dep_graph.is_empty());
+    // the options are shared instead of copied
+    assert!(Arc::ptr_eq(&resolver.config, &forked.config));
+  }
+
+  #[test]
+  fn resolve_output_paths() {
+    let mut resolver = Resolver::new(
+      "/pages/index.tsx",
+      ImportHashMap::default(),
+      None,
+      None,
+      false,
+      vec![],
+    )
+    .with_output_paths("_cache", "/.aleph/", ".mjs");
     assert_eq!(
-      resolver.resolve("https://esm.sh/react-dom", false),
-      (
-        "../-/esm.sh/react-dom@17.0.1.js".into(),
-        "https://esm.sh/react-dom@17.0.1".into()
-      )
+      resolver.fix_import_url("https://esm.sh/react"),
+      "/_cache/esm.sh/react.mjs"
     );
     assert_eq!(
-      resolver.resolve("https://esm.sh/react-dom@16.14.0", false),
+      resolver.resolve("https://esm.sh/react", false, None),
       (
-        "../-/esm.sh/react-dom@17.0.1.js".into(),
-        "https://esm.sh/react-dom@17.0.1".into()
+        "../../_cache/esm.sh/react.mjs".into(),
+        "https://esm.sh/react".into()
       )
     );
     assert_eq!(
-      resolver.resolve("https://esm.sh/react-dom/server", false),
+      resolver.resolve("../components/logo.tsx", false, None),
       (
-        "../-/esm.sh/react-dom@17.0.1/server.js".into(),
-        "https://esm.sh/react-dom@17.0.1/server".into()
+        "../components/logo.mjs#/components/logo.tsx@000000".into(),
+        "/components/logo.tsx".into()
       )
     );
     assert_eq!(
-      resolver.resolve("https://esm.sh/react-dom@16.13.1/server", false),
+      resolver.resolve("../styles/app.css", false, None),
       (
-        "../-/esm.sh/react-dom@17.0.1/server.js".into(),
-        "https://esm.sh/react-dom@17.0.1/server".into()
+        "../styles/app.css.mjs#/styles/app.css@000000".into(),
+        "/styles/app.css".into()
       )
     );
+    let mut forked = resolver.fork("https://deno.land/x/mod.ts");
     assert_eq!(
-      resolver.resolve("react-dom/server", false),
-      (
-        "../-/esm.sh/react-dom@17.0.1/server.js".into(),
-        "https://esm.sh/react-dom@17.0.1/server".into()
-      )
+      forked.resolve("./util.ts", false, None),
+      ("./util.mjs".into(), "https://deno.land/x/util.ts".into())
     );
     assert_eq!(
-      resolver.resolve("react", false),
-      (
-        "../-/esm.sh/react@17.0.1.js".into(),
-        "https://esm.sh/react@17.0.1".into()
+      resolver.output_path("/pages/index.tsx"),
+      "/.aleph/pages/index.mjs"
+    );
+    assert_eq!(
+      resolver.output_path("/styles/app.css"),
+      "/.aleph/styles/app.css.mjs"
+    );
+    assert_eq!(
+      resolver.output_path("https://deno.land/x/util.ts"),
+      "/_cache/deno.land/x/util.mjs"
+    );
+  }
+
+  #[test]
+  fn resolve_output_exts() {
+    let mut rules: HashMap<String, String> = HashMap::new();
+    rules.insert(".page.tsx".into(), ".page.js".into());
+    rules.insert("island.tsx".into(), "island.mjs".into());
+    rules.insert(".json".into(), ".json".into());
+    rules.insert("".into(), ".js".into());
+    let mut resolver = Resolver::new(
+      "/pages/index.tsx",
+      ImportHashMap::default(),
+      None,
+      None,
+      false,
+      vec![],
+    )
+    .with_output_exts(rules);
+    assert_eq!(
+      resolver.resolve("./about.page.tsx", false, None).0,
+      "./about.page.js#/pages/about.page.tsx@000000"
+    );
+    assert_eq!(
+      resolver
+        .resolve("../islands/counter.island.tsx", false, None)
+        .0,
+      "../islands/counter.island.mjs#/islands/counter.island.tsx@000000"
+    );
+    assert_eq!(
+      resolver.resolve("../components/logo.tsx", false, None).0,
+      "../components/logo.js#/components/logo.tsx@000000"
+    );
+    assert_eq!(
+      resolver.resolve("../data/posts.json", false, None).0,
+      "../data/posts.json#/data/posts.json@000000"
+    );
+    assert_eq!(
+      resolver.resolve("../lib/utils", false, None).0,
+      "../lib/utils.js#/lib/utils@000000"
+    );
+    assert_eq!(
+      resolver.resolve("../styles/app.css", false, None).0,
+      "../styles/app.css.js#/styles/app.css@000000"
+    );
+    assert_eq!(
+      resolver.rewrite_ext("index.page.tsx", true),
+      Some("index.bundling.page.js".into())
+    );
+    assert_eq!(resolver.rewrite_ext("app.css", false), None);
+  }
+
+  #[test]
+  fn resolve_with_rules() {
+    let mut imports: HashMap<String, String> = HashMap::new();
+    imports.insert("react".into(), "https://esm.sh/react".into());
+    let resolver = Resolver::new(
+      "/pages/index.tsx",
+      ImportHashMap {
+        imports,
+        scopes: HashMap::new(),
+      },
+      None,
+      None,
+      false,
+      vec![],
+    )
+    .with_resolve_rules(&[
+      ResolveRule {
+        test: "^~/".into(),
+        replace: "/".into(),
+      },
+      ResolveRule {
+        test: r"^@/components/(\w+)$".into(),
+        replace: "/components/$1.tsx".into(),
+      },
+      ResolveRule {
+        test: "^preact$".into(),
+        replace: "react".into(),
+      },
+    ])
+    .unwrap();
+    assert_eq!(resolver.fix_url("~/lib/util.ts"), "/lib/util.ts");
+    assert_eq!(
+      resolver.fix_url("@/components/logo"),
+      "/components/logo.tsx"
+    );
+    assert_eq!(resolver.fix_url("preact"), "https://esm.sh/react");
+    assert_eq!(resolver.fix_url("./app.tsx"), "/pages/app.tsx");
+    assert!(Resolver::new(
+      "/pages/index.tsx",
+      ImportHashMap::default(),
+      None,
+      None,
+      false,
+      vec![]
+    )
+    .with_resolve_rules(&[ResolveRule {
+      test: "(".into(),
+      replace: "".into(),
+    }])
+    .is_err());
+  }
+
+  #[test]
+  fn resolve_dep_hashes() {
+    let mut hashes: HashMap<String, String> = HashMap::new();
+    hashes.insert(
+      "/components/logo.tsx".into(),
+      "1a2b3c4d5e6f7a8b9c0d1e2f3a4b5c6d7e8f9a0b".into(),
+    );
+    let mut resolver = Resolver::new(
+      "/pages/index.tsx",
+      ImportHashMap::default(),
+      None,
+      None,
+      false,
+      vec![],
+    )
+    .with_dep_hashes(hashes);
+    assert_eq!(
+      resolver.resolve("../components/logo.tsx", false, None),
+      (
+        "../components/logo.js#/components/logo.tsx@1a2b3c".into(),
+        "/components/logo.tsx".into()
       )
     );
     assert_eq!(
-      resolver.resolve("https://deno.land/x/aleph/mod.ts", false),
+      resolver.resolve("../styles/app.css", false, None),
+      (
+        "../styles/app.css.js#/styles/app.css@000000".into(),
+        "/styles/app.css".into()
+      )
+    );
+  }
+
+  #[test]
+  fn resolve_npm_and_node_specifiers() {
+    let mut versions: HashMap<String, String> = HashMap::new();
+    versions.insert("dayjs".into(), "1.10.4".into());
+    let resolver = Resolver::new(
+      "/pages/index.tsx",
+      ImportHashMap::default(),
+      None,
+      Some("17.0.1".into()),
+      false,
+      vec![],
+    );
+    assert_eq!(resolver.fix_url("npm:swr@0.5"), "https://esm.sh/swr@0.5");
+    assert_eq!(
+      resolver.fix_url("npm:react@16"),
+      "https://esm.sh/react@17.0.1"
+    );
+    assert_eq!(resolver.fix_url("node:path"), "node:path");
+    let mut polyfills: HashMap<String, String> = HashMap::new();
+    polyfills.insert("path".into(), "npm:path-browserify".into());
+    let mut resolver = resolver
+      .with_npm_cdn("https://cdn.esm.sh/v41", versions)
+      .with_node_polyfills(polyfills);
+    assert_eq!(
+      resolver.fix_url("npm:dayjs/plugin/utc"),
+      "https://cdn.esm.sh/v41/dayjs@1.10.4/plugin/utc"
+    );
+    assert_eq!(
+      resolver.fix_url("npm:/@babel/core@7"),
+      "https://cdn.esm.sh/v41/@babel/core@7"
+    );
+    assert_eq!(
+      resolver.resolve("node:path", false, None),
+      (
+        "../-/cdn.esm.sh/v41/path-browserify.js".into(),
+        "https://cdn.esm.sh/v41/path-browserify".into()
+      )
+    );
+    assert_eq!(
+      resolver.resolve("node:fs", false, None),
+      ("node:fs".into(), "node:fs".into())
+    );
+    assert_eq!(
+      resolver.dep_graph.last().map(|dep| dep.specifier.as_str()),
+      Some("node:fs")
+    );
+    assert!(resolver.remote_deps.iter().all(|dep| dep.url != "node:fs"));
+  }
+
+  #[test]
+  fn resolve_bare_node_builtins() {
+    let resolver = Resolver::new(
+      "/pages/index.tsx",
+      ImportHashMap::default(),
+      None,
+      None,
+      false,
+      vec![],
+    );
+    // only the browser resolves the bare builtin modules
+    assert_eq!(resolver.fix_url("path"), "/pages/path");
+    let mut polyfills: HashMap<String, String> = HashMap::new();
+    polyfills.insert("buffer".into(), "https://esm.sh/buffer".into());
+    polyfills.insert("fs".into(), "".into());
+    let resolver = resolver.with_node_polyfills(polyfills.clone());
+    assert_eq!(resolver.fix_url("buffer"), "https://esm.sh/buffer");
+    assert_eq!(resolver.fix_url("node:buffer"), "https://esm.sh/buffer");
+    assert_eq!(
+      resolver.fix_url("fs"),
+      "data:text/javascript,export default {}"
+    );
+    assert_eq!(resolver.fix_url("events"), "node:events");
+    assert_eq!(resolver.fix_url("fs/promises"), "node:fs/promises");
+    assert_eq!(resolver.fix_url("pathname"), "/pages/pathname");
+    // the npm cdn resolves the bare builtin modules without a polyfill
+    let resolver = resolver
+      .with_npm_cdn("https://esm.sh", HashMap::new())
+      .with_node_polyfills(polyfills);
+    assert_eq!(resolver.fix_url("buffer"), "https://esm.sh/buffer");
+    assert_eq!(resolver.fix_url("events"), "https://esm.sh/events");
+  }
+
+  #[test]
+  fn resolve_data_urls() {
+    let mut resolver = Resolver::new(
+      "https://deno.land/x/mod/mod.ts",
+      ImportHashMap::default(),
+      None,
+      None,
+      false,
+      vec![],
+    );
+    let data_url = "data:text/javascript,export default 1";
+    assert_eq!(resolver.fix_url(data_url), data_url);
+    assert_eq!(
+      resolver.resolve(data_url, false, None),
+      (data_url.into(), data_url.into())
+    );
+    let blob_url = "blob:https://example.com/550e8400-e29b-41d4-a716-446655440000";
+    assert_eq!(
+      resolver.resolve(blob_url, true, None),
+      (blob_url.into(), blob_url.into())
+    );
+    assert!(resolver.dep_graph.is_empty());
+    assert!(resolver.remote_deps.is_empty());
+  }
+
+  #[test]
+  fn resolve_aliases() {
+    let mut aliases: HashMap<String, String> = HashMap::new();
+    aliases.insert("~/*".into(), "./src/*".into());
+    aliases.insert("~/components/*".into(), "./src/ui/*.tsx".into());
+    aliases.insert("config".into(), "./config.ts".into());
+    aliases.insert("react".into(), "https://esm.sh/react@17.0.2".into());
+    let mut imports: HashMap<String, String> = HashMap::new();
+    imports.insert("/src/lib/".into(), "https://esm.sh/lib/".into());
+    let resolver = Resolver::new(
+      "/pages/index.tsx",
+      ImportHashMap {
+        imports,
+        scopes: HashMap::new(),
+      },
+      None,
+      None,
+      false,
+      vec![],
+    )
+    .with_aliases(aliases);
+    assert_eq!(resolver.fix_url("~/utils.ts"), "/src/utils.ts");
+    assert_eq!(resolver.fix_url("~/components/logo"), "/src/ui/logo.tsx");
+    assert_eq!(resolver.fix_url("config"), "/config.ts");
+    assert_eq!(resolver.fix_url("config/dev.ts"), "/pages/config/dev.ts");
+    assert_eq!(resolver.fix_url("react"), "https://esm.sh/react@17.0.2");
+    assert_eq!(
+      resolver.fix_url("~/lib/mod.ts"),
+      "https://esm.sh/lib/mod.ts"
+    );
+    assert_eq!(resolver.fix_url("./~/x.ts"), "/pages/~/x.ts");
+  }
+
+  #[test]
+  fn resolve_base_url() {
+    let mut aliases: HashMap<String, String> = HashMap::new();
+    aliases.insert("~/*".into(), "./lib/*".into());
+    let mut resolver = Resolver::new(
+      "/src/pages/index.tsx",
+      ImportHashMap::default(),
+      None,
+      None,
+      false,
+      vec![],
+    )
+    .with_aliases(aliases)
+    .with_base_url("./src/");
+    assert_eq!(
+      resolver.fix_url("/components/logo.tsx"),
+      "/src/components/logo.tsx"
+    );
+    assert_eq!(resolver.fix_url("../utils.ts"), "/src/utils.ts");
+    assert_eq!(resolver.fix_url("~/mod.ts"), "/lib/mod.ts");
+    assert_eq!(resolver.fix_url("//esm.sh/react"), "//esm.sh/react");
+    assert_eq!(
+      resolver.resolve("/components/logo.tsx", false, None),
+      (
+        "../components/logo.js#/src/components/logo.tsx@000000".into(),
+        "/src/components/logo.tsx".into()
+      )
+    );
+
+    let resolver = resolver.with_base_url("https://cdn.example.com/app/");
+    assert_eq!(
+      resolver.fix_url("/components/logo.tsx"),
+      "https://cdn.example.com/app/components/logo.tsx"
+    );
+    let resolver = resolver.with_base_url(".");
+    assert_eq!(
+      resolver.fix_url("/components/logo.tsx"),
+      "/components/logo.tsx"
+    );
+
+    let resolver = Resolver::new(
+      "https://deno.land/x/mod/src/index.ts",
+      ImportHashMap::default(),
+      None,
+      None,
+      false,
+      vec![],
+    )
+    .with_base_url("./src");
+    assert_eq!(
+      resolver.fix_url("/x/mod/util.ts"),
+      "https://deno.land/x/mod/util.ts"
+    );
+  }
+
+  #[test]
+  fn resolve_remote_versions() {
+    let mut versions: HashMap<String, String> = HashMap::new();
+    versions.insert("std".into(), "0.100.0".into());
+    versions.insert("oak".into(), "v7.5.0".into());
+    versions.insert("@babel/core".into(), "7.14.3".into());
+    versions.insert("dayjs".into(), "1.10.4".into());
+    versions.insert(
+      "https://esm.sh/swr".into(),
+      "https://esm.sh/swr@0.5.6".into(),
+    );
+    versions.insert(
+      "https://example.com/lib/".into(),
+      "https://example.com/lib@2/".into(),
+    );
+    let resolver = Resolver::new(
+      "/pages/index.tsx",
+      ImportHashMap::default(),
+      None,
+      None,
+      false,
+      vec![],
+    )
+    .with_npm_cdn("https://esm.sh", HashMap::new())
+    .with_versions(versions);
+    assert_eq!(
+      resolver.fix_url("https://deno.land/std/http/server.ts"),
+      "https://deno.land/std@0.100.0/http/server.ts"
+    );
+    assert_eq!(
+      resolver.fix_url("https://deno.land/std@0.99.0/http/server.ts"),
+      "https://deno.land/std@0.99.0/http/server.ts"
+    );
+    assert_eq!(
+      resolver.fix_url("https://deno.land/x/oak/mod.ts"),
+      "https://deno.land/x/oak@v7.5.0/mod.ts"
+    );
+    assert_eq!(
+      resolver.fix_url("https://esm.sh/@babel/core?target=es2020"),
+      "https://esm.sh/@babel/core@7.14.3?target=es2020"
+    );
+    assert_eq!(
+      resolver.fix_url("dayjs/plugin/utc"),
+      "https://esm.sh/dayjs@1.10.4/plugin/utc"
+    );
+    assert_eq!(
+      resolver.fix_url("https://esm.sh/swr"),
+      "https://esm.sh/swr@0.5.6"
+    );
+    assert_eq!(
+      resolver.fix_url("https://esm.sh/swr/infinite"),
+      "https://esm.sh/swr@0.5.6/infinite"
+    );
+    assert_eq!(
+      resolver.fix_url("https://esm.sh/swrv"),
+      "https://esm.sh/swrv"
+    );
+    assert_eq!(
+      resolver.fix_url("https://example.com/lib/mod.ts"),
+      "https://example.com/lib@2/mod.ts"
+    );
+    assert_eq!(
+      split_remote_package("https://esm.sh/@babel/core@7.14.3/lib/index.js"),
+      Some((
+        "https://esm.sh/@babel/core",
+        "@babel/core",
+        Some("7.14.3"),
+        "/lib/index.js"
+      ))
+    );
+    assert_eq!(split_remote_package("https://example.com/lib.ts"), None);
+    assert_eq!(split_remote_package("https://deno.land/"), None);
+  }
+
+  #[test]
+  fn resolve_preload() {
+    let mut resolver = Resolver::new(
+      "/pages/index.tsx",
+      ImportHashMap::default(),
+      None,
+      None,
+      false,
+      vec![],
+    )
+    .with_output_paths("", "/_dist", "");
+    resolver.resolve("https://esm.sh/react", false, None);
+    resolver.resolve("../components/logo.tsx", false, None);
+    resolver.resolve_reexport("./about.tsx", None);
+    resolver.resolve("../components/logo.tsx", false, None);
+    resolver.resolve("./blog.tsx", true, None);
+    resolver.resolve_worker("../workers/sw.ts", None);
+    assert_eq!(
+      resolver.preload,
+      vec![
+        "/-/esm.sh/react.js",
+        "/_dist/components/logo.js#/components/logo.tsx@000000",
+        "/_dist/pages/about.js#/pages/about.tsx@000000",
+      ]
+    );
+    let mut forked = resolver.fork("https://deno.land/x/aleph/mod.ts");
+    forked.resolve("./framework/core/hmr.ts", false, None);
+    assert_eq!(
+      forked.preload,
+      vec!["/-/deno.land/x/aleph/framework/core/hmr.js"]
+    );
+  }
+
+  #[test]
+  fn resolve_remote_deps() {
+    let mut lock: HashMap<String, String> = HashMap::new();
+    lock.insert("https://esm.sh/react".into(), "sha384-abc".into());
+    let mut resolver = Resolver::new(
+      "/pages/index.tsx",
+      ImportHashMap::default(),
+      None,
+      None,
+      false,
+      vec![],
+    )
+    .with_lock(lock);
+    resolver.resolve("https://esm.sh/react", false, None);
+    resolver.resolve("https://esm.sh/swr", false, None);
+    resolver.resolve("https://esm.sh/react", true, None);
+    resolver.resolve("../components/logo.tsx", false, None);
+    assert_eq!(
+      resolver.remote_deps,
+      vec![
+        RemoteDependency {
+          url: "https://esm.sh/react".into(),
+          resolved_path: "../-/esm.sh/react.js".into(),
+          integrity: Some("sha384-abc".into()),
+        },
+        RemoteDependency {
+          url: "https://esm.sh/swr".into(),
+          resolved_path: "../-/esm.sh/swr.js".into(),
+          integrity: None,
+        },
+      ]
+    );
+  }
+
+  #[test]
+  fn resolve_local() {
+    let mut imports: HashMap<String, String> = HashMap::new();
+    imports.insert("@/".into(), "./".into());
+    imports.insert("~/".into(), "./".into());
+    imports.insert("react".into(), "https://esm.sh/react".into());
+    imports.insert("react-dom/".into(), "https://esm.sh/react-dom/".into());
+    imports.insert(
+      "https://deno.land/x/aleph/".into(),
+      "http://localhost:2020/".into(),
+    );
+    let mut resolver = Resolver::new(
+      "/pages/index.tsx",
+      ImportHashMap {
+        imports,
+        scopes: HashMap::new(),
+      },
+      None,
+      Some("17.0.1".into()),
+      false,
+      vec![],
+    );
+    assert_eq!(
+      resolver.resolve("https://esm.sh/react", false, None),
+      (
+        "../-/esm.sh/react@17.0.1.js".into(),
+        "https://esm.sh/react@17.0.1".into()
+      )
+    );
+    assert_eq!(
+      resolver.resolve("https://esm.sh/react-refresh", false, None),
+      (
+        "../-/esm.sh/react-refresh.js".into(),
+        "https://esm.sh/react-refresh".into()
+      )
+    );
+    assert_eq!(
+      resolver.resolve(
+        "https://deno.land/x/aleph/framework/react/link.ts",
+        false,
+        None
+      ),
+      (
+        "../-/http_localhost_2020/framework/react/link.js".into(),
+        "http://localhost:2020/framework/react/link.ts".into()
+      )
+    );
+    assert_eq!(
+      resolver.resolve("https://esm.sh/react@16", false, None),
+      (
+        "../-/esm.sh/react@17.0.1.js".into(),
+        "https://esm.sh/react@17.0.1".into()
+      )
+    );
+    assert_eq!(
+      resolver.resolve("https://esm.sh/react-dom", false, None),
+      (
+        "../-/esm.sh/react-dom@17.0.1.js".into(),
+        "https://esm.sh/react-dom@17.0.1".into()
+      )
+    );
+    assert_eq!(
+      resolver.resolve("https://esm.sh/react-dom@16.14.0", false, None),
+      (
+        "../-/esm.sh/react-dom@17.0.1.js".into(),
+        "https://esm.sh/react-dom@17.0.1".into()
+      )
+    );
+    assert_eq!(
+      resolver.resolve("https://esm.sh/react-dom/server", false, None),
+      (
+        "../-/esm.sh/react-dom@17.0.1/server.js".into(),
+        "https://esm.sh/react-dom@17.0.1/server".into()
+      )
+    );
+    assert_eq!(
+      resolver.resolve("https://esm.sh/react-dom@16.13.1/server", false, None),
+      (
+        "../-/esm.sh/react-dom@17.0.1/server.js".into(),
+        "https://esm.sh/react-dom@17.0.1/server".into()
+      )
+    );
+    assert_eq!(
+      resolver.resolve("react-dom/server", false, None),
+      (
+        "../-/esm.sh/react-dom@17.0.1/server.js".into(),
+        "https://esm.sh/react-dom@17.0.1/server".into()
+      )
+    );
+    assert_eq!(
+      resolver.resolve("react", false, None),
+      (
+        "../-/esm.sh/react@17.0.1.js".into(),
+        "https://esm.sh/react@17.0.1".into()
+      )
+    );
+    assert_eq!(
+      resolver.resolve("https://deno.land/x/aleph/mod.ts", false, None),
       (
         "../-/http_localhost_2020/mod.js".into(),
         "http://localhost:2020/mod.ts".into()
       )
     );
     assert_eq!(
-      resolver.resolve("../components/logo.tsx", false),
+      resolver.resolve("../components/logo.tsx", false, None),
       (
         "../components/logo.js#/components/logo.tsx@000000".into(),
         "/components/logo.tsx".into()
       )
     );
     assert_eq!(
-      resolver.resolve("../styles/app.css", false),
+      resolver.resolve("../styles/app.css", false, None),
       (
         "../styles/app.css.js#/styles/app.css@000000".into(),
         "/styles/app.css".into()
       )
     );
     assert_eq!(
-      resolver.resolve("https://esm.sh/tailwindcss/dist/tailwind.min.css", false),
+      resolver.resolve(
+        "https://esm.sh/tailwindcss/dist/tailwind.min.css",
+        false,
+        None
+      ),
       (
         "../-/esm.sh/tailwindcss/dist/tailwind.min.css.js".into(),
         "https://esm.sh/tailwindcss/dist/tailwind.min.css".into()
       )
     );
     assert_eq!(
-      resolver.resolve("@/components/logo.tsx", false),
+      resolver.resolve("@/components/logo.tsx", false, None),
       (
         "../components/logo.js#/components/logo.tsx@000000".into(),
         "/components/logo.tsx".into()
       )
     );
     assert_eq!(
-      resolver.resolve("~/components/logo.tsx", false),
+      resolver.resolve("~/components/logo.tsx", false, None),
       (
         "../components/logo.js#/components/logo.tsx@000000".into(),
         "/components/logo.tsx".into()
@@ -558,6 +1951,31 @@ mod tests {
     );
   }
 
+  #[test]
+  fn resolve_package_prefix() {
+    let mut imports: HashMap<String, String> = HashMap::new();
+    imports.insert("react".into(), "https://esm.sh/react".into());
+    imports.insert("react/".into(), "https://esm.sh/react/".into());
+    let mut resolver = Resolver::new(
+      "/pages/index.tsx",
+      ImportHashMap {
+        imports,
+        scopes: HashMap::new(),
+      },
+      None,
+      Some("17.0.1".into()),
+      false,
+      vec![],
+    );
+    assert_eq!(
+      resolver.resolve("react/jsx-runtime", false, None),
+      (
+        "../-/esm.sh/react@17.0.1/jsx-runtime.js".into(),
+        "https://esm.sh/react@17.0.1/jsx-runtime".into()
+      )
+    );
+  }
+
   #[test]
   fn resolve_remote_1() {
     let mut resolver = Resolver::new(
@@ -569,21 +1987,25 @@ mod tests {
       vec![],
     );
     assert_eq!(
-      resolver.resolve("https://cdn.esm.sh/react@17.0.1/es2020/react.js", false),
+      resolver.resolve(
+        "https://cdn.esm.sh/react@17.0.1/es2020/react.js",
+        false,
+        None
+      ),
       (
         "../cdn.esm.sh/react@17.0.1/es2020/react.js".into(),
         "https://cdn.esm.sh/react@17.0.1/es2020/react.js".into()
       )
     );
     assert_eq!(
-      resolver.resolve("./react", false),
+      resolver.resolve("./react", false, None),
       (
         "./react@17.0.1.js".into(),
         "https://esm.sh/react@17.0.1".into()
       )
     );
     assert_eq!(
-      resolver.resolve("/react", false),
+      resolver.resolve("/react", false, None),
       (
         "./react@17.0.1.js".into(),
         "https://esm.sh/react@17.0.1".into()
@@ -602,18 +2024,22 @@ mod tests {
       vec![],
     );
     assert_eq!(
-      resolver.resolve("https://cdn.esm.sh/preact@10.5.7/es2020/preact.js", false),
+      resolver.resolve(
+        "https://cdn.esm.sh/preact@10.5.7/es2020/preact.js",
+        false,
+        None
+      ),
       (
         "../../cdn.esm.sh/preact@10.5.7/es2020/preact.js".into(),
         "https://cdn.esm.sh/preact@10.5.7/es2020/preact.js".into()
       )
     );
     assert_eq!(
-      resolver.resolve("../preact", false),
+      resolver.resolve("../preact", false, None),
       ("../preact.js".into(), "https://esm.sh/preact".into())
     );
     assert_eq!(
-      resolver.resolve("/preact", false),
+      resolver.resolve("/preact", false, None),
       ("../preact.js".into(), "https://esm.sh/preact".into())
     );
   }
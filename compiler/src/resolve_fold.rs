@@ -1,22 +1,40 @@
-use crate::resolve::{DependencyDescriptor, Resolver};
+use crate::ast_utils::new_str;
+use crate::resolve::{is_remote_url, DependencyDescriptor, Resolver};
 
+use path_slash::PathBufExt;
+use pathdiff::diff_paths;
+use regex::Regex;
 use sha1::{Digest, Sha1};
-use std::{cell::RefCell, rc::Rc};
-use swc_common::{SourceMap, Span, DUMMY_SP};
+use std::{cell::RefCell, collections::HashSet, path::PathBuf, rc::Rc};
+use swc_common::{comments::SingleThreadedComments, SourceMap, Span, DUMMY_SP};
 use swc_ecma_ast::*;
 use swc_ecma_utils::quote_ident;
 use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
 
+lazy_static! {
+  // match: /* alephChunkName: "admin" */
+  static ref RE_CHUNK_NAME: Regex =
+    Regex::new(r#"^\s*alephChunkName\s*:\s*["']([^"']+)["']\s*$"#).unwrap();
+}
+
 pub fn resolve_fold(
   resolver: Rc<RefCell<Resolver>>,
   source: Rc<SourceMap>,
+  comments: SingleThreadedComments,
   resolve_star_exports: bool,
+  strip_deno_hooks: bool,
+  lazy_preload: bool,
 ) -> impl Fold {
   ResolveFold {
     deno_hooks_idx: 0,
     resolver,
     source,
+    comments,
     resolve_star_exports,
+    strip_deno_hooks,
+    lazy_preload,
+    lazy_import: None,
+    lazy_chunk_url: None,
   }
 }
 
@@ -24,7 +42,17 @@ pub struct ResolveFold {
   deno_hooks_idx: i32,
   resolver: Rc<RefCell<Resolver>>,
   source: Rc<SourceMap>,
+  /// for reading the chunk names of the dynamic imports
+  comments: SingleThreadedComments,
   resolve_star_exports: bool,
+  /// removes the callbacks of the `useDeno` hooks that only run on the server
+  strip_deno_hooks: bool,
+  /// passes the chunk url of a lazy component as the second argument of the lazy call
+  lazy_preload: bool,
+  /// the span of the `src` of the dynamic import in the loader of a lazy component
+  lazy_import: Option<Span>,
+  /// the chunk url of the resolved lazy import
+  lazy_chunk_url: Option<String>,
 }
 
 impl ResolveFold {
@@ -46,6 +74,72 @@ impl ResolveFold {
     );
     ident
   }
+
+  /// returns the chunk name of the dynamic import from the magic comment before the
+  /// `src`, like `import(/* alephChunkName: "admin" */ "./admin.tsx")`.
+  fn get_chunk_name(&self, src_span: Span) -> Option<String> {
+    self.comments.with_leading(src_span.lo, |comments| {
+      comments
+        .iter()
+        .find_map(|c| RE_CHUNK_NAME.captures(c.text.as_str()))
+        .map(|caps| caps[1].to_owned())
+    })
+  }
+
+  /// rewrites the static parts of a dynamic import with runtime parts, like
+  /// `import("./posts/" + name + ".tsx")` or `` import(`./posts/${name}.tsx`) ``,
+  /// and records the import pattern `/pages/posts/*.tsx` for code splitting.
+  fn fold_dynamic_import_pattern(&mut self, call: &mut CallExpr) {
+    let expr = match call.args.first_mut() {
+      Some(ExprOrSpread { expr, .. }) => expr.as_mut(),
+      _ => return,
+    };
+    let parts = match get_dynamic_import_parts(expr) {
+      Some(parts) => parts,
+      None => return,
+    };
+    // only the imports with a static prefix can be resolved
+    if !matches!(parts.first(), Some(Some(_))) {
+      return;
+    }
+    let suffix = match parts.last() {
+      Some(Some(suffix)) if parts.len() > 1 => Some(suffix.clone()),
+      _ => None,
+    };
+    let mut pattern = String::new();
+    for part in parts.iter() {
+      match part {
+        Some(s) => pattern.push_str(s),
+        None => pattern.push('*'),
+      }
+    }
+    let mut resolver = self.resolver.borrow_mut();
+    let fixed_pattern = resolver.resolve_dynamic_import_pattern(pattern.as_str());
+    let fixed_prefix = fixed_pattern.split('*').next().unwrap().to_owned();
+    if resolver.bundle_mode {
+      call.callee = ExprOrSuper::Expr(Box::new(Expr::MetaProp(MetaPropExpr {
+        meta: quote_ident!("__ALEPH"),
+        prop: quote_ident!("import"),
+      })));
+      set_dynamic_import_parts(expr, fixed_prefix, suffix);
+    } else if !resolver.specifier_is_remote && !is_remote_url(fixed_prefix.as_str()) {
+      let mut buf = PathBuf::from(resolver.specifier.as_str());
+      buf.pop();
+      let mut rel_prefix = diff_paths(&fixed_prefix, buf).unwrap().to_slash().unwrap();
+      if !rel_prefix.starts_with("./") && !rel_prefix.starts_with("../") {
+        rel_prefix = "./".to_owned() + rel_prefix.as_str();
+      }
+      if fixed_prefix.ends_with('/') && !rel_prefix.ends_with('/') {
+        rel_prefix.push('/');
+      }
+      let suffix = suffix.map(|suffix| {
+        resolver
+          .rewrite_ext(suffix.as_str(), false)
+          .unwrap_or(suffix)
+      });
+      set_dynamic_import_parts(expr, rel_prefix, suffix);
+    }
+  }
 }
 
 impl Fold for ResolveFold {
@@ -68,6 +162,11 @@ impl Fold for ResolveFold {
   //   - `export * from "https://esm.sh/react"` -> `export const $$star_N = __ALEPH.pack["https://esm.sh/react"]`
   fn fold_module_items(&mut self, module_items: Vec<ModuleItem>) -> Vec<ModuleItem> {
     let mut items = Vec::<ModuleItem>::new();
+    let module_items = if self.resolver.borrow().bundle_mode || self.strip_deno_hooks {
+      drop_deno_hook_imports(module_items)
+    } else {
+      module_items
+    };
 
     for item in module_items {
       match item {
@@ -80,8 +179,11 @@ impl Fold for ResolveFold {
                 ModuleItem::ModuleDecl(ModuleDecl::Import(import_decl))
               } else {
                 let mut resolver = self.resolver.borrow_mut();
-                let (resolved_path, fixed_url) =
-                  resolver.resolve(import_decl.src.value.as_ref(), false);
+                let (resolved_path, fixed_url) = resolver.resolve(
+                  import_decl.src.value.as_ref(),
+                  false,
+                  Some(import_decl.src.span),
+                );
                 if resolver.bundle_mode && resolver.bundle_external.contains(fixed_url.as_str()) {
                   let mut names: Vec<(Ident, Option<String>)> = vec![];
                   let mut ns: Option<Ident> = None;
@@ -127,7 +229,7 @@ impl Fold for ResolveFold {
                   }
                 } else {
                   ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
-                    src: new_str(resolved_path),
+                    src: new_str(&resolved_path),
                     ..import_decl
                   }))
                 }
@@ -139,6 +241,7 @@ impl Fold for ResolveFold {
               type_only,
               specifiers,
               src: Some(src),
+              asserts,
               ..
             }) => {
               if type_only {
@@ -152,7 +255,8 @@ impl Fold for ResolveFold {
                 }))
               } else {
                 let mut resolver = self.resolver.borrow_mut();
-                let (resolved_path, fixed_url) = resolver.resolve(src.value.as_ref(), false);
+                let (resolved_path, fixed_url) =
+                  resolver.resolve_reexport(src.value.as_ref(), Some(src.span));
                 if resolver.bundle_mode && resolver.bundle_external.contains(fixed_url.as_str()) {
                   let mut names: Vec<(Ident, Option<String>)> = vec![];
                   let mut ns: Option<Ident> = None;
@@ -202,17 +306,18 @@ impl Fold for ResolveFold {
                   ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(NamedExport {
                     span: DUMMY_SP,
                     specifiers,
-                    src: Some(new_str(resolved_path)),
+                    src: Some(new_str(&resolved_path)),
                     type_only: false,
-                    asserts: None,
+                    asserts,
                   }))
                 }
               }
             }
             // match: export * from "https://esm.sh/react"
-            ModuleDecl::ExportAll(ExportAll { src, .. }) => {
+            ModuleDecl::ExportAll(ExportAll { src, asserts, .. }) => {
               let mut resolver = self.resolver.borrow_mut();
-              let (resolved_path, fixed_url) = resolver.resolve(src.value.as_ref(), false);
+              let (resolved_path, fixed_url) =
+                resolver.resolve_reexport(src.value.as_ref(), Some(src.span));
               if resolver.bundle_mode && resolver.bundle_external.contains(fixed_url.as_str()) {
                 resolver.star_exports.push(fixed_url.clone());
                 ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
@@ -235,14 +340,14 @@ impl Fold for ResolveFold {
                   resolver.star_exports.push(fixed_url.clone());
                   ModuleItem::ModuleDecl(ModuleDecl::ExportAll(ExportAll {
                     span: DUMMY_SP,
-                    src: new_str(src.into()),
-                    asserts: None,
+                    src: new_str(&src),
+                    asserts,
                   }))
                 } else {
                   ModuleItem::ModuleDecl(ModuleDecl::ExportAll(ExportAll {
                     span: DUMMY_SP,
-                    src: new_str(resolved_path.into()),
-                    asserts: None,
+                    src: new_str(&resolved_path),
+                    asserts,
                   }))
                 }
               }
@@ -261,36 +366,41 @@ impl Fold for ResolveFold {
   }
 
   fn fold_expr(&mut self, expr: Expr) -> Expr {
-    let specifier = self.resolver.borrow().specifier.clone();
     let expr = match expr {
-      Expr::Member(MemberExpr {
-        span,
-        obj,
-        prop,
-        computed,
-      }) => {
-        let mut is_import_meta_url = false;
-        if let Expr::Ident(id) = prop.as_ref() {
-          if id.sym.as_ref().eq("url") {
-            if let ExprOrSuper::Expr(ref expr) = obj {
-              if let Expr::MetaProp(MetaPropExpr { meta, prop }) = expr.as_ref() {
-                if meta.sym.as_ref().eq("import") && prop.sym.as_ref().eq("meta") {
-                  is_import_meta_url = true
-                }
-              }
-            }
-          }
-        }
-        if is_import_meta_url {
-          Expr::Lit(Lit::Str(new_str(specifier)))
-        } else {
-          Expr::Member(MemberExpr {
-            span,
-            obj,
-            prop,
-            computed,
-          })
-        }
+      // resolve the web worker url
+      // - `new Worker(new URL("./worker.ts", import.meta.url))` -> `new Worker(new URL("./worker.js#/pages/worker.ts@000000", import.meta.url))`
+      Expr::New(new) if get_worker_url(&new).is_some() => {
+        let (url, span) = get_worker_url(&new).unwrap();
+        let (resolved_path, _) = self
+          .resolver
+          .borrow_mut()
+          .resolve_worker(url.as_str(), Some(span));
+        let mut args = new.args.unwrap_or_default();
+        args[0] = ExprOrSpread {
+          spread: None,
+          expr: Box::new(Expr::New(NewExpr {
+            span: DUMMY_SP,
+            callee: Box::new(Expr::Ident(quote_ident!("URL"))),
+            args: Some(vec![
+              ExprOrSpread {
+                spread: None,
+                expr: Box::new(Expr::Lit(Lit::Str(new_str(&resolved_path)))),
+              },
+              ExprOrSpread {
+                spread: None,
+                expr: Box::new(new_import_meta_url()),
+              },
+            ]),
+            type_args: None,
+          })),
+        };
+        // the options of the worker, the `import.meta.url` of the new url is kept
+        let rest = args.split_off(1).fold_with(self);
+        args.extend(rest);
+        return Expr::New(NewExpr {
+          args: Some(args),
+          ..new
+        });
       }
       _ => expr,
     };
@@ -302,19 +412,45 @@ impl Fold for ResolveFold {
   // - `import("https://esm.sh/rect")` -> `import("/-/esm.sh/react.js")`
   // - `import("../components/logo.tsx")` -> `import("../components/logo.js#/components/logo.tsx@000000")`
   // - `import("../components/logo.tsx")` -> `__ALEPH.import("../components/logo.js#/components/logo.tsx@000000", "/pages/index.tsx")`
+  // - `import("./posts/" + name + ".tsx")` -> `import("./posts/" + name + ".js")`
   // - `useDeno(() => {})` -> `useDeno(() => {}, false, "useDeno.KEY")`
   fn fold_call_expr(&mut self, mut call: CallExpr) -> CallExpr {
+    // match: React.lazy(() => import("./chart.tsx"))
+    if let Some(span) = get_lazy_import_span(&call) {
+      self.lazy_import = Some(span);
+      let mut call = call.fold_children_with(self);
+      self.lazy_import = None;
+      // - `React.lazy(() => import("./chart.js#/chart.tsx@000000"), "/chart.js#/chart.tsx@000000")`
+      if let Some(chunk_url) = self.lazy_chunk_url.take() {
+        if self.lazy_preload && call.args.len() == 1 {
+          call.args.push(ExprOrSpread {
+            spread: None,
+            expr: Box::new(Expr::Lit(Lit::Str(new_str(&chunk_url)))),
+          });
+        }
+      }
+      return call;
+    }
     if is_call_expr_by_name(&call, "import") {
-      let url = match call.args.first() {
+      let (url, span) = match call.args.first() {
         Some(ExprOrSpread { expr, .. }) => match expr.as_ref() {
-          Expr::Lit(lit) => match lit {
-            Lit::Str(s) => s.value.as_ref(),
-            _ => return call,
-          },
-          _ => return call,
+          Expr::Lit(Lit::Str(s)) => (s.value.as_ref().to_owned(), s.span),
+          Expr::Tpl(Tpl {
+            exprs,
+            quasis,
+            span,
+          }) if exprs.is_empty() && quasis.len() == 1 => {
+            (quasis[0].raw.value.as_ref().to_owned(), *span)
+          }
+          _ => {
+            self.fold_dynamic_import_pattern(&mut call);
+            return call.fold_children_with(self);
+          }
         },
         _ => return call,
       };
+      let url = url.as_str();
+      let chunk_name = self.get_chunk_name(span);
       let mut resolver = self.resolver.borrow_mut();
       if resolver.bundle_mode {
         call.callee = ExprOrSuper::Expr(Box::new(Expr::MetaProp(MetaPropExpr {
@@ -322,16 +458,35 @@ impl Fold for ResolveFold {
           prop: quote_ident!("import"),
         })))
       }
-      let (resolved_path, fixed_url) = resolver.resolve(url, true);
+      let (resolved_path, fixed_url) = resolver.resolve(url, true, Some(span));
+      let lazy = self.lazy_import == Some(span);
+      if chunk_name.is_some() || lazy {
+        // the inline modules are not added to the deps graph
+        if let Some(dep) = resolver
+          .dep_graph
+          .last_mut()
+          .filter(|dep| dep.is_dynamic && dep.specifier == fixed_url)
+        {
+          dep.chunk_name = chunk_name;
+          dep.lazy = lazy;
+        }
+      }
+      if lazy {
+        self.lazy_chunk_url = Some(if resolver.bundle_mode {
+          fixed_url.clone()
+        } else {
+          resolver.absolute_path(resolved_path.as_str())
+        });
+      }
       if resolver.bundle_mode {
         call.args = vec![ExprOrSpread {
           spread: None,
-          expr: Box::new(Expr::Lit(Lit::Str(new_str(fixed_url)))),
+          expr: Box::new(Expr::Lit(Lit::Str(new_str(&fixed_url)))),
         }];
       } else {
         call.args = vec![ExprOrSpread {
           spread: None,
-          expr: Box::new(Expr::Lit(Lit::Str(new_str(resolved_path)))),
+          expr: Box::new(Expr::Lit(Lit::Str(new_str(&resolved_path)))),
         }];
       }
     } else if is_call_expr_by_name(&call, "useDeno") {
@@ -349,8 +504,8 @@ impl Fold for ResolveFold {
       if let Some(span) = callback_span {
         let bundle_mode = self.resolver.borrow().bundle_mode;
         let id = self.new_use_deno_hook_ident(span);
-        if bundle_mode {
-          // tree-shake useDeno callback in bundle mode
+        if bundle_mode || self.strip_deno_hooks {
+          // tree-shake useDeno callback in bundle mode or for the browser
           call.args[0] = ExprOrSpread {
             spread: None,
             expr: Box::new(Expr::Lit(Lit::Null(Null { span: DUMMY_SP }))),
@@ -368,19 +523,25 @@ impl Fold for ResolveFold {
         if call.args.len() > 2 {
           call.args[2] = ExprOrSpread {
             spread: None,
-            expr: Box::new(Expr::Lit(Lit::Str(new_str(id.clone())))),
+            expr: Box::new(Expr::Lit(Lit::Str(new_str(&id)))),
           };
         } else {
           call.args.push(ExprOrSpread {
             spread: None,
-            expr: Box::new(Expr::Lit(Lit::Str(new_str(id.clone())))),
+            expr: Box::new(Expr::Lit(Lit::Str(new_str(&id)))),
           });
         }
         let mut resolver = self.resolver.borrow_mut();
         resolver.dep_graph.push(DependencyDescriptor {
           specifier: "#".to_owned() + id.clone().as_str(),
           is_dynamic: false,
+          span: None,
+          kind: None,
+          chunk_name: None,
+          reexport: false,
+          lazy: false,
         });
+        resolver.deno_hooks.push(id);
       }
     }
 
@@ -478,6 +639,245 @@ impl Fold for ExportsParser {
   }
 }
 
+/// Creates a fold that resolves the import of `url` injected by a later transform,
+/// like the `jsx-runtime` import of the automatic jsx runtime.
+pub fn import_resolve_fold(resolver: Rc<RefCell<Resolver>>, url: &str) -> impl Fold {
+  ImportResolveFold {
+    resolver,
+    url: url.into(),
+  }
+}
+
+struct ImportResolveFold {
+  resolver: Rc<RefCell<Resolver>>,
+  url: String,
+}
+
+impl Fold for ImportResolveFold {
+  noop_fold_type!();
+
+  fn fold_module_items(&mut self, module_items: Vec<ModuleItem>) -> Vec<ModuleItem> {
+    let mut items = Vec::<ModuleItem>::new();
+    let mut resolver = self.resolver.borrow_mut();
+
+    for item in module_items {
+      match item {
+        ModuleItem::ModuleDecl(ModuleDecl::Import(import_decl))
+          if import_decl.src.value.as_ref().eq(self.url.as_str()) =>
+        {
+          let (resolved_path, fixed_url) = resolver.resolve(self.url.as_str(), false, None);
+          if resolver.bundle_mode && resolver.bundle_external.contains(fixed_url.as_str()) {
+            let names = import_decl
+              .specifiers
+              .into_iter()
+              .filter_map(|specifier| match specifier {
+                ImportSpecifier::Named(ImportNamedSpecifier {
+                  local, imported, ..
+                }) => Some((
+                  local,
+                  match imported {
+                    Some(name) => Some(name.sym.as_ref().into()),
+                    None => None,
+                  },
+                )),
+                _ => None,
+              })
+              .collect();
+            items.push(ModuleItem::Stmt(Stmt::Decl(Decl::Var(VarDecl {
+              span: DUMMY_SP,
+              kind: VarDeclKind::Const,
+              declare: false,
+              decls: vec![create_aleph_pack_var_decl_member(fixed_url.as_str(), names)],
+            }))));
+          } else {
+            items.push(ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+              src: Str {
+                span: DUMMY_SP,
+                value: resolved_path.into(),
+                has_escape: false,
+                kind: Default::default(),
+              },
+              ..import_decl
+            })));
+          }
+        }
+        _ => items.push(item),
+      }
+    }
+    items
+  }
+}
+
+/// returns the span of the `src` of the dynamic import that is the loader of a lazy
+/// component of `React.lazy`, `lazy` or `loadable`, like
+/// `React.lazy(() => import("./chart.tsx"))` or
+/// `lazy(() => import("./chart.tsx").then(mod => ({ default: mod.Chart })))`.
+pub fn get_lazy_import_span(call: &CallExpr) -> Option<Span> {
+  let is_lazy = match &call.callee {
+    ExprOrSuper::Expr(callee) => match callee.as_ref() {
+      Expr::Ident(id) => matches!(id.sym.as_ref(), "lazy" | "loadable"),
+      // match: React.lazy
+      Expr::Member(MemberExpr {
+        obj: ExprOrSuper::Expr(obj),
+        prop,
+        computed: false,
+        ..
+      }) => matches!(
+        (obj.as_ref(), prop.as_ref()),
+        (Expr::Ident(obj), Expr::Ident(prop)) if obj.sym.as_ref() == "React" && prop.sym.as_ref() == "lazy"
+      ),
+      _ => false,
+    },
+    ExprOrSuper::Super(_) => false,
+  };
+  if !is_lazy {
+    return None;
+  }
+  let body = match call.args.first()?.expr.as_ref() {
+    Expr::Arrow(ArrowExpr {
+      body: BlockStmtOrExpr::Expr(expr),
+      ..
+    }) => expr.as_ref(),
+    Expr::Arrow(ArrowExpr {
+      body: BlockStmtOrExpr::BlockStmt(block),
+      ..
+    })
+    | Expr::Fn(FnExpr {
+      function: Function {
+        body: Some(block), ..
+      },
+      ..
+    }) => match block.stmts.as_slice() {
+      [Stmt::Return(ReturnStmt { arg: Some(arg), .. })] => arg.as_ref(),
+      _ => return None,
+    },
+    _ => return None,
+  };
+  get_import_src_span(body)
+}
+
+fn get_import_src_span(expr: &Expr) -> Option<Span> {
+  match expr {
+    Expr::Paren(ParenExpr { expr, .. }) => get_import_src_span(expr),
+    Expr::Call(call) if is_call_expr_by_name(call, "import") => {
+      match call.args.first()?.expr.as_ref() {
+        Expr::Lit(Lit::Str(src)) => Some(src.span),
+        Expr::Tpl(Tpl {
+          exprs,
+          quasis,
+          span,
+        }) if exprs.is_empty() && quasis.len() == 1 => Some(*span),
+        _ => None,
+      }
+    }
+    // match: import("./chart.tsx").then(...)
+    Expr::Call(CallExpr {
+      callee: ExprOrSuper::Expr(callee),
+      ..
+    }) => match callee.as_ref() {
+      Expr::Member(MemberExpr {
+        obj: ExprOrSuper::Expr(obj),
+        prop,
+        computed: false,
+        ..
+      }) if matches!(prop.as_ref(), Expr::Ident(prop) if prop.sym.as_ref() == "then") => {
+        get_import_src_span(obj)
+      }
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+/// removes the imports that are only used by the callbacks of the `useDeno` hooks, as
+/// the callbacks are stripped, before the imports are resolved as the deps. The import
+/// declarations that have no specifiers left are removed too.
+fn drop_deno_hook_imports(module_items: Vec<ModuleItem>) -> Vec<ModuleItem> {
+  let mut collector = HookRefsCollector {
+    in_hook: false,
+    refs: HashSet::new(),
+    hook_refs: HashSet::new(),
+  };
+  for item in module_items.iter() {
+    if !matches!(item, ModuleItem::ModuleDecl(ModuleDecl::Import(_))) {
+      item.clone().fold_with(&mut collector);
+    }
+  }
+  let is_hook_only = |local: &Ident| {
+    let name: &str = local.sym.as_ref();
+    collector.hook_refs.contains(name) && !collector.refs.contains(name)
+  };
+  module_items
+    .into_iter()
+    .filter_map(|item| match item {
+      ModuleItem::ModuleDecl(ModuleDecl::Import(decl))
+        if !decl.type_only && !decl.specifiers.is_empty() =>
+      {
+        let specifiers: Vec<ImportSpecifier> = decl
+          .specifiers
+          .into_iter()
+          .filter(|specifier| match specifier {
+            ImportSpecifier::Named(ImportNamedSpecifier { local, .. })
+            | ImportSpecifier::Default(ImportDefaultSpecifier { local, .. })
+            | ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => {
+              !is_hook_only(local)
+            }
+          })
+          .collect();
+        if specifiers.is_empty() {
+          None
+        } else {
+          Some(ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+            specifiers,
+            ..decl
+          })))
+        }
+      }
+      _ => Some(item),
+    })
+    .collect()
+}
+
+/// collects the referenced names inside and outside of the `useDeno` callbacks.
+struct HookRefsCollector {
+  in_hook: bool,
+  refs: HashSet<String>,
+  hook_refs: HashSet<String>,
+}
+
+impl Fold for HookRefsCollector {
+  noop_fold_type!();
+
+  fn fold_call_expr(&mut self, call: CallExpr) -> CallExpr {
+    if !self.in_hook && is_call_expr_by_name(&call, "useDeno") {
+      let mut args = call.args.iter();
+      if let Some(ExprOrSpread { expr, .. }) = args.next() {
+        if let Expr::Fn(_) | Expr::Arrow(_) = expr.as_ref() {
+          self.in_hook = true;
+          expr.clone().fold_with(self);
+          self.in_hook = false;
+        } else {
+          expr.clone().fold_with(self);
+        }
+      }
+      args.for_each(|arg| {
+        arg.clone().fold_with(self);
+      });
+      return call;
+    }
+    call.fold_children_with(self)
+  }
+
+  fn fold_ident(&mut self, ident: Ident) -> Ident {
+    if self.in_hook {
+      self.hook_refs.insert(ident.sym.as_ref().into());
+    } else {
+      self.refs.insert(ident.sym.as_ref().into());
+    }
+    ident
+  }
+}
+
 pub fn is_call_expr_by_name(call: &CallExpr, name: &str) -> bool {
   let callee = match &call.callee {
     ExprOrSuper::Super(_) => return false,
@@ -557,21 +957,160 @@ pub fn create_aleph_pack_var_decl_member(
   }
 }
 
-fn new_str(str: String) -> Str {
-  Str {
-    span: DUMMY_SP,
-    value: str.into(),
-    has_escape: false,
-    kind: Default::default(),
+/// collects the static (`Some`) and runtime (`None`) parts of a template literal or
+/// a string concatenation.
+fn get_dynamic_import_parts(expr: &Expr) -> Option<Vec<Option<String>>> {
+  let mut parts = Vec::new();
+  match expr {
+    Expr::Tpl(Tpl { exprs, quasis, .. }) => {
+      for (i, quasi) in quasis.iter().enumerate() {
+        if !quasi.raw.value.is_empty() {
+          parts.push(Some(quasi.raw.value.as_ref().into()));
+        }
+        if i < exprs.len() {
+          parts.push(None);
+        }
+      }
+    }
+    Expr::Bin(_) => {
+      if !get_concat_parts(expr, &mut parts) {
+        return None;
+      }
+    }
+    _ => return None,
+  };
+  Some(parts)
+}
+
+fn get_concat_parts(expr: &Expr, parts: &mut Vec<Option<String>>) -> bool {
+  match expr {
+    Expr::Lit(Lit::Str(s)) => parts.push(Some(s.value.as_ref().into())),
+    Expr::Bin(BinExpr {
+      op: BinaryOp::Add,
+      left,
+      right,
+      ..
+    }) => return get_concat_parts(left.as_ref(), parts) && get_concat_parts(right.as_ref(), parts),
+    Expr::Bin(_) => return false,
+    _ => parts.push(None),
+  };
+  true
+}
+
+/// replaces the first static part with `prefix` and the last static part with `suffix`.
+fn set_dynamic_import_parts(expr: &mut Expr, prefix: String, suffix: Option<String>) {
+  match expr {
+    Expr::Tpl(Tpl { quasis, .. }) => {
+      if let Some(suffix) = suffix {
+        set_tpl_element(quasis.last_mut().unwrap(), suffix);
+      }
+      set_tpl_element(quasis.first_mut().unwrap(), prefix);
+    }
+    Expr::Bin(_) => {
+      if let Some(suffix) = suffix {
+        if let Expr::Lit(Lit::Str(s)) = last_concat_part(expr) {
+          *s = new_str(&suffix);
+        }
+      }
+      if let Expr::Lit(Lit::Str(s)) = first_concat_part(expr) {
+        *s = new_str(&prefix);
+      }
+    }
+    _ => {}
+  }
+}
+
+fn set_tpl_element(el: &mut TplElement, value: String) {
+  el.raw = new_str(&value);
+  el.cooked = Some(new_str(&value));
+}
+
+fn first_concat_part(expr: &mut Expr) -> &mut Expr {
+  match expr {
+    Expr::Bin(BinExpr { left, .. }) => first_concat_part(left.as_mut()),
+    _ => expr,
   }
 }
 
+fn last_concat_part(expr: &mut Expr) -> &mut Expr {
+  match expr {
+    Expr::Bin(BinExpr { right, .. }) => last_concat_part(right.as_mut()),
+    _ => expr,
+  }
+}
+
+/// returns the url and the span of a web worker,
+/// match: `new Worker(new URL("./worker.ts", import.meta.url))` or `SharedWorker`.
+pub fn get_worker_url(new: &NewExpr) -> Option<(String, Span)> {
+  match new.callee.as_ref() {
+    Expr::Ident(id) if id.sym.eq("Worker") || id.sym.eq("SharedWorker") => {}
+    _ => return None,
+  };
+  let url = match new.args.as_ref()?.first()? {
+    ExprOrSpread { spread: None, expr } => match expr.as_ref() {
+      Expr::New(url) => url,
+      _ => return None,
+    },
+    _ => return None,
+  };
+  match url.callee.as_ref() {
+    Expr::Ident(id) if id.sym.eq("URL") => {}
+    _ => return None,
+  };
+  match url.args.as_ref()?.as_slice() {
+    [ExprOrSpread {
+      spread: None,
+      expr: specifier,
+    }, ExprOrSpread {
+      spread: None,
+      expr: base,
+    }] if is_import_meta_url(base) => match specifier.as_ref() {
+      Expr::Lit(Lit::Str(s)) => Some((s.value.as_ref().into(), s.span)),
+      _ => None,
+    },
+    _ => None,
+  }
+}
+
+fn is_import_meta_url(expr: &Expr) -> bool {
+  match expr {
+    Expr::Member(MemberExpr {
+      obj: ExprOrSuper::Expr(obj),
+      prop,
+      computed: false,
+      ..
+    }) => match (obj.as_ref(), prop.as_ref()) {
+      (
+        Expr::MetaProp(MetaPropExpr {
+          meta,
+          prop: meta_prop,
+        }),
+        Expr::Ident(id),
+      ) => meta.sym.eq("import") && meta_prop.sym.eq("meta") && id.sym.eq("url"),
+      _ => false,
+    },
+    _ => false,
+  }
+}
+
+fn new_import_meta_url() -> Expr {
+  Expr::Member(MemberExpr {
+    span: DUMMY_SP,
+    obj: ExprOrSuper::Expr(Box::new(Expr::MetaProp(MetaPropExpr {
+      meta: quote_ident!("import"),
+      prop: quote_ident!("meta"),
+    }))),
+    prop: Box::new(Expr::Ident(quote_ident!("url"))),
+    computed: false,
+  })
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
   use crate::import_map::ImportHashMap;
-  use crate::resolve::Resolver;
-  use crate::swc::{st, EmitOptions, SWC};
+  use crate::resolve::{DependencyKind, Resolver, SourceSpan};
+  use crate::swc::{st, test_resolver, EmitOptions, TargetEnv, SWC};
   use sha1::{Digest, Sha1};
   use std::collections::HashMap;
 
@@ -622,9 +1161,31 @@ mod tests {
     assert!(code.contains("import Logo from \"../component/logo.js#/component/logo.tsx@000000\""));
     assert!(code.contains("import Logo2 from \"../component/logo.js#/component/logo.tsx@000000\""));
     assert!(code.contains("import Logo3 from \"../component/logo.js#/component/logo.tsx@000000\""));
-    assert!(code.contains("const AsyncLogo = React.lazy(()=>import(\"../components/async-logo.js#/components/async-logo.tsx@000000\")"));
+    assert!(code.contains("const AsyncLogo = /*#__PURE__*/ React.lazy(()=>import(\"../components/async-logo.js#/components/async-logo.tsx@000000\")"));
     assert!(code.contains("export { useState } from \"../-/esm.sh/react@17.0.1.js\""));
     assert!(code.contains("export * from \"../-/esm.sh/swr.js\""));
+    let r = resolver.borrow();
+    let dep = r
+      .dep_graph
+      .iter()
+      .find(|dep| dep.specifier.eq("/components/async-logo.tsx"))
+      .unwrap();
+    let start = source.find("'../components/async-logo.tsx'").unwrap() as u32;
+    assert!(dep.is_dynamic);
+    assert_eq!(
+      dep.span,
+      Some(SourceSpan {
+        start,
+        end: start + 30,
+      })
+    );
+    let dep = r
+      .dep_graph
+      .iter()
+      .find(|dep| dep.specifier.eq("https://esm.sh/swr"))
+      .unwrap();
+    assert!(!dep.is_dynamic);
+    assert!(dep.span.is_some());
   }
 
   #[test]
@@ -663,15 +1224,223 @@ mod tests {
     let id_2 = id_2.trim_end_matches('=');
 
     for _ in 0..3 {
-      let (code, _) = st(specifer, source, false);
+      let (code, resolver) = st(specifer, source, false);
       assert!(code.contains(format!("0, \"useDeno-{}\"", id_1).as_str()));
       assert!(code.contains(format!("1000, \"useDeno-{}\"", id_2).as_str()));
+      assert_eq!(
+        resolver.borrow().deno_hooks,
+        vec![format!("useDeno-{}", id_1), format!("useDeno-{}", id_2)]
+      );
       let (code, _) = st(specifer, source, true);
       assert!(code.contains(format!("null, 0, \"useDeno-{}\"", id_1).as_str()));
       assert!(code.contains(format!("null, 1000, \"useDeno-{}\"", id_2).as_str()));
     }
   }
 
+  #[test]
+  fn strip_deno_hook_imports() {
+    let source = r#"
+      import { readJson, exists } from "https://deno.land/std/fs/mod.ts"
+      import * as path from "https://deno.land/std/path/mod.ts"
+      import { useState } from "https://esm.sh/react"
+      export default function Index() {
+        const [count] = useState(0)
+        const data = useDeno(async () => {
+          const file = path.join("data", "index.json")
+          return await exists(file) ? await readJson(file) : null
+        })
+        return data && exists && count
+      }
+    "#;
+    let transform = |target_env: Option<TargetEnv>| {
+      let module = SWC::parse("/pages/index.js", source, None).expect("could not parse module");
+      let resolver = test_resolver("/pages/index.js");
+      let (code, _) = module
+        .transform(
+          resolver.clone(),
+          &EmitOptions {
+            target_env,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      let deps: Vec<String> = resolver
+        .borrow()
+        .dep_graph
+        .iter()
+        .filter(|dep| !dep.specifier.starts_with("#useDeno-"))
+        .map(|dep| dep.specifier.clone())
+        .collect();
+      (code, deps)
+    };
+
+    let (code, deps) = transform(Some(TargetEnv::Browser));
+    assert!(code.contains("import { exists } from \"../-/deno.land/std/fs/mod.js\";"));
+    assert!(!code.contains("readJson"));
+    assert!(!code.contains("std/path"));
+    assert!(code.contains("import { useState } from \"../-/esm.sh/react.js\";"));
+    assert_eq!(
+      deps,
+      vec!["https://deno.land/std/fs/mod.ts", "https://esm.sh/react"]
+    );
+
+    let (code, deps) = transform(None);
+    assert!(code.contains("import { readJson, exists } from"));
+    assert!(code.contains("import * as path from"));
+    assert_eq!(deps.len(), 3);
+  }
+
+  #[test]
+  fn resolve_dynamic_import_patterns() {
+    let source = r#"
+      const lib = import(`../lib.ts`)
+      const post = import("./posts/" + name + ".tsx")
+      const page = import(`../pages/${name}.tsx`)
+      const mod = import(name)
+    "#;
+    let module = SWC::parse("/pages/index.tsx", source, None).expect("could not parse module");
+    let resolver = test_resolver("/pages/index.tsx");
+    let (code, _) = module
+      .transform(resolver.clone(), &EmitOptions::default())
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains("import(\"../lib.js#/lib.ts@000000\")"));
+    assert!(code.contains("import(\"./posts/\" + name + \".js\")"));
+    assert!(code.contains("import(`./${name}.js`)"));
+    assert!(code.contains("import(name)"));
+    let r = resolver.borrow();
+    assert_eq!(r.dep_graph.len(), 1);
+    assert!(r.dep_graph[0].is_dynamic);
+    assert_eq!(
+      r.dynamic_import_patterns,
+      vec!["/pages/posts/*.tsx", "/pages/*.tsx"]
+    );
+
+    let (code, _) = st("/pages/index.tsx", source, true);
+    assert!(code.contains("__ALEPH.import(\"/pages/posts/\" + name + \".tsx\""));
+    assert!(code.contains("__ALEPH.import(`/pages/${name}.tsx`"));
+  }
+
+  #[test]
+  fn resolve_dynamic_import_chunk_names() {
+    let source = r#"
+      const Admin = React.lazy(() => import(/* alephChunkName: "admin" */ "./admin.tsx"))
+      const Settings = React.lazy(() => import(/* webpackChunkName: "settings" */ "./settings.tsx"))
+    "#;
+    let (code, resolver) = st("/pages/index.tsx", source, false);
+    assert!(code.contains("import(\"./admin.js#/pages/admin.tsx@000000\")"));
+    let r = resolver.borrow();
+    assert_eq!(r.dep_graph.len(), 2);
+    assert_eq!(r.dep_graph[0].chunk_name, Some("admin".into()));
+    assert_eq!(r.dep_graph[1].chunk_name, None);
+  }
+
+  #[test]
+  fn resolve_lazy_imports() {
+    let source = r#"
+      import loadable from "https://esm.sh/@loadable/component"
+      const Chart = React.lazy(() => import("../components/chart.tsx"))
+      const Map = lazy(function () {
+        return import("https://esm.sh/leaflet").then(mod => ({ default: mod.Map }))
+      })
+      const Editor = loadable(() => import(`./editor.tsx`))
+      const data = import("./data.ts")
+    "#;
+    let transform = |lazy_preload: bool| {
+      let module = SWC::parse("/pages/index.tsx", source, None).expect("could not parse module");
+      let resolver = test_resolver("/pages/index.tsx");
+      let (code, _) = module
+        .transform(
+          resolver.clone(),
+          &EmitOptions {
+            lazy_preload,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      (code, resolver)
+    };
+    let (code, resolver) = transform(false);
+    assert!(code.contains(
+      "React.lazy(()=>import(\"../components/chart.js#/components/chart.tsx@000000\")\n)"
+    ));
+    let r = resolver.borrow();
+    assert_eq!(
+      r.dep_graph
+        .iter()
+        .map(|dep| (dep.specifier.as_str(), dep.lazy))
+        .collect::<Vec<(&str, bool)>>(),
+      vec![
+        ("https://esm.sh/@loadable/component", false),
+        ("/components/chart.tsx", true),
+        ("https://esm.sh/leaflet", true),
+        ("/pages/editor.tsx", true),
+        ("/pages/data.ts", false),
+      ]
+    );
+
+    let (code, _) = transform(true);
+    assert!(code.contains(
+      "import(\"../components/chart.js#/components/chart.tsx@000000\")\n, \"/components/chart.js#/components/chart.tsx@000000\")"
+    ));
+    assert!(code.contains("}, \"/-/esm.sh/leaflet.js\")"));
+    assert!(code.contains(", \"/pages/editor.js#/pages/editor.tsx@000000\")"));
+    assert!(code.contains("const data = import(\"./data.js#/pages/data.ts@000000\");"));
+  }
+
+  #[test]
+  fn resolve_reexports() {
+    let source = r#"
+      import { log } from "./log.ts"
+      export * from "./util.ts"
+      export * as colors from "https://deno.land/std/fmt/colors.ts"
+      export { a as b } from "./a.ts"
+      export * from "data:text/javascript,export const c = 1"
+    "#;
+    let (code, resolver) = st("/lib/mod.ts", source, false);
+    assert!(code.contains("export * from \"./util.js#/lib/util.ts@000000\""));
+    assert!(code.contains("export * as colors from \"../-/deno.land/std/fmt/colors.js\""));
+    assert!(code.contains("export { a as b } from \"./a.js#/lib/a.ts@000000\""));
+    let r = resolver.borrow();
+    assert_eq!(
+      r.dep_graph
+        .iter()
+        .map(|dep| (dep.specifier.as_str(), dep.reexport))
+        .collect::<Vec<(&str, bool)>>(),
+      vec![
+        ("/lib/log.ts", false),
+        ("/lib/util.ts", true),
+        ("https://deno.land/std/fmt/colors.ts", true),
+        ("/lib/a.ts", true)
+      ]
+    );
+  }
+
+  #[test]
+  fn resolve_worker() {
+    let source = r#"
+      const worker = new Worker(new URL("./worker.ts", import.meta.url), { type: "module" })
+      const shared = new SharedWorker(new URL("https://esm.sh/some-worker", import.meta.url))
+      const other = new Worker("./worker.js")
+    "#;
+    let (code, resolver) = st("/pages/index.tsx", source, false);
+    assert!(code.contains(
+      "new Worker(new URL(\"./worker.js#/pages/worker.ts@000000\", import.meta.url), {\n    type: \"module\"\n})"
+    ));
+    assert!(
+      code.contains("new SharedWorker(new URL(\"../-/esm.sh/some-worker.js\", import.meta.url))")
+    );
+    assert!(code.contains("new Worker(\"./worker.js\")"));
+    let r = resolver.borrow();
+    assert_eq!(r.dep_graph.len(), 2);
+    assert_eq!(r.dep_graph[0].specifier, "/pages/worker.ts");
+    assert_eq!(r.dep_graph[0].kind, Some(DependencyKind::Worker));
+    assert!(r.dep_graph[0].is_dynamic);
+    assert_eq!(r.dep_graph[1].kind, Some(DependencyKind::Worker));
+  }
+
   #[test]
   fn resolve_import_meta_url() {
     let source = r#"
@@ -0,0 +1,238 @@
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use swc_ecma_ast::*;
+
+/// parses the route metadata of a page or an api module, which are the exported
+/// constants with the statically analyzable values, evaluated as JSON:
+///
+/// ```ts
+/// export const config = { ssr: false, revalidate: 60 }
+/// export const paths = ["/blog/hello", "/blog/world"]
+/// ```
+///
+/// is reported as `{ "config": { "ssr": false, "revalidate": 60 }, "paths": [...] }`,
+/// so the server can read the route config without importing the module. The
+/// exports with the other values (like `getStaticPaths` functions) are omitted, they
+/// are in the `exports` of the output.
+pub fn parse_route_meta(module: &Module) -> Map<String, Value> {
+  let mut consts: HashMap<&str, &Expr> = HashMap::new();
+  let mut exported: Vec<(&str, &str)> = vec![];
+  for item in module.body.iter() {
+    match item {
+      // match: const config = {}
+      ModuleItem::Stmt(Stmt::Decl(Decl::Var(var))) if var.kind == VarDeclKind::Const => {
+        push_const_decls(var, &mut consts);
+      }
+      // match: export const config = {}
+      ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+        decl: Decl::Var(var),
+        ..
+      }))
+        if var.kind == VarDeclKind::Const =>
+      {
+        for (name, _) in push_const_decls(var, &mut consts) {
+          exported.push((name, name));
+        }
+      }
+      // match: export { config, paths as routes }
+      ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(NamedExport {
+        specifiers,
+        src: None,
+        type_only: false,
+        ..
+      })) => {
+        for specifier in specifiers.iter() {
+          if let ExportSpecifier::Named(ExportNamedSpecifier {
+            orig,
+            exported: name,
+            ..
+          }) = specifier
+          {
+            let name = name.as_ref().unwrap_or(orig);
+            exported.push((name.sym.as_ref(), orig.sym.as_ref()));
+          }
+        }
+      }
+      _ => {}
+    }
+  }
+  let mut meta = Map::new();
+  for (name, local) in exported {
+    if let Some(value) = consts.get(local).and_then(|expr| eval_json(expr)) {
+      meta.insert(name.into(), value);
+    }
+  }
+  meta
+}
+
+/// checks if the module is an api route, which is under a `api/` directory, like
+/// `/api/user.ts` or `/routes/api/posts/[id].ts`.
+pub fn is_api_module(specifier: &str) -> bool {
+  let path = specifier.split(&['?', '#'][..]).next().unwrap();
+  let mut segments: Vec<&str> = path.split('/').collect();
+  segments.pop();
+  segments.contains(&"api")
+}
+
+fn push_const_decls<'a>(
+  var: &'a VarDecl,
+  consts: &mut HashMap<&'a str, &'a Expr>,
+) -> Vec<(&'a str, &'a Expr)> {
+  var
+    .decls
+    .iter()
+    .filter_map(|decl| match (&decl.name, &decl.init) {
+      (Pat::Ident(BindingIdent { id, .. }), Some(init)) => {
+        consts.insert(id.sym.as_ref(), init.as_ref());
+        Some((id.sym.as_ref(), init.as_ref()))
+      }
+      _ => None,
+    })
+    .collect()
+}
+
+/// evaluates the literal expression as JSON, returns `None` if the value isn't
+/// static or has no JSON representation.
+fn eval_json(expr: &Expr) -> Option<Value> {
+  match expr {
+    Expr::Lit(Lit::Null(_)) => Some(Value::Null),
+    Expr::Lit(Lit::Bool(Bool { value, .. })) => Some(json!(value)),
+    Expr::Lit(Lit::Num(Number { value, .. })) => number_json(*value),
+    Expr::Lit(Lit::Str(Str { value, .. })) => Some(json!(value.as_ref())),
+    Expr::Tpl(Tpl { exprs, quasis, .. }) if exprs.is_empty() => quasis
+      .first()
+      .map(|quasi| json!(quasi.cooked.as_ref().unwrap_or(&quasi.raw).value.as_ref())),
+    Expr::Unary(UnaryExpr {
+      op: UnaryOp::Minus,
+      arg,
+      ..
+    }) => match arg.as_ref() {
+      Expr::Lit(Lit::Num(Number { value, .. })) => number_json(-value),
+      _ => None,
+    },
+    Expr::Array(ArrayLit { elems, .. }) => elems
+      .iter()
+      .map(|elem| match elem {
+        Some(ExprOrSpread { spread: None, expr }) => eval_json(expr),
+        _ => None,
+      })
+      .collect::<Option<Vec<Value>>>()
+      .map(Value::Array),
+    Expr::Object(ObjectLit { props, .. }) => {
+      let mut object = Map::new();
+      for prop in props.iter() {
+        match prop {
+          PropOrSpread::Prop(prop) => match prop.as_ref() {
+            Prop::KeyValue(KeyValueProp { key, value }) => {
+              let key = match key {
+                PropName::Ident(id) => id.sym.as_ref().to_owned(),
+                PropName::Str(s) => s.value.as_ref().to_owned(),
+                PropName::Num(n) => match number_json(n.value)? {
+                  Value::Number(n) => n.to_string(),
+                  _ => return None,
+                },
+                _ => return None,
+              };
+              object.insert(key, eval_json(value)?);
+            }
+            _ => return None,
+          },
+          PropOrSpread::Spread(_) => return None,
+        }
+      }
+      Some(Value::Object(object))
+    }
+    Expr::Paren(ParenExpr { expr, .. }) => eval_json(expr),
+    // match: { ssr: false } as const
+    Expr::TsAs(TsAsExpr { expr, .. }) => eval_json(expr),
+    Expr::TsConstAssertion(TsConstAssertion { expr, .. }) => eval_json(expr),
+    Expr::TsTypeAssertion(TsTypeAssertion { expr, .. }) => eval_json(expr),
+    _ => None,
+  }
+}
+
+fn number_json(value: f64) -> Option<Value> {
+  if !value.is_finite() {
+    return None;
+  }
+  // keeps the integers as the integers of JSON, e.g. `60` instead of `60.0`
+  if value.fract() == 0.0 && value.abs() < 9007199254740992.0 {
+    Some(json!(value as i64))
+  } else {
+    Some(json!(value))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::swc::SWC;
+
+  #[test]
+  fn parse_page_route_meta() {
+    let source = r#"
+      const revalidate = 60
+      const paths = [`/blog/hello`, "/blog/world"] as const
+      export const config = {
+        ssr: false,
+        "cache-control": null,
+        regions: ["fra", 'iad'],
+        retries: -1,
+        ratio: 0.5,
+      }
+      export const getStaticPaths = async () => paths
+      export const title = "Blog", dynamic = Date.now()
+      export { revalidate, paths as routes }
+      export default function Blog() {
+        return <h1>Blog</h1>
+      }
+    "#;
+    let module = SWC::parse("/pages/blog.tsx", source, None).expect("could not parse module");
+    let meta = parse_route_meta(&module.module);
+    assert_eq!(
+      Value::Object(meta),
+      json!({
+        "config": {
+          "ssr": false,
+          "cache-control": null,
+          "regions": ["fra", "iad"],
+          "retries": -1,
+          "ratio": 0.5,
+        },
+        "title": "Blog",
+        "revalidate": 60,
+        "routes": ["/blog/hello", "/blog/world"],
+      })
+    );
+
+    let source = r#"
+      const base = { ssr: true }
+      export const config = { ...base, revalidate: 60 }
+    "#;
+    let module = SWC::parse("/api/user.ts", source, None).expect("could not parse module");
+    assert!(parse_route_meta(&module.module).is_empty());
+  }
+
+  #[test]
+  fn api_modules() {
+    assert!(is_api_module("/api/user.ts"));
+    assert!(is_api_module("/routes/api/posts/[id].ts?v=1"));
+    assert!(!is_api_module("/pages/api.tsx"));
+    assert!(!is_api_module("/pages/apis/user.ts"));
+
+    let source = r#"
+      export const methods = ["GET", "POST"]
+      export const runtime = { edge: true }
+      export function GET(req: Request) {
+        return new Response("user")
+      }
+    "#;
+    let output = crate::parse("/api/user.ts", source, &Default::default()).unwrap();
+    assert_eq!(
+      output.metadata.route_meta.map(Value::Object),
+      Some(json!({ "methods": ["GET", "POST"], "runtime": { "edge": true } }))
+    );
+    let output = crate::parse("/lib/user.ts", source, &Default::default()).unwrap();
+    assert_eq!(output.metadata.route_meta, None);
+  }
+}
@@ -0,0 +1,486 @@
+use crate::ast_utils::new_str;
+use crate::css::{new_apply_css_stmt, scope_css};
+use crate::resolve::Resolver;
+
+use sha1::{Digest, Sha1};
+use std::{cell::RefCell, rc::Rc};
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::quote_ident;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// Creates a fold that scopes the CSS of the `css` tagged templates to the components
+/// without a runtime, like styled-jsx. The CSS of a template in a function is scoped
+/// to the JSX elements of the same top-level declaration, the CSS of a template at
+/// the module level is scoped to all the JSX elements of the module:
+///
+/// ```jsx
+/// export default function Button() {
+///   css`button { color: red; }`
+///   return <button className="primary">Hi</button>
+/// }
+/// ```
+///
+/// is transformed to:
+///
+/// ```jsx
+/// export default function Button() {
+///   return <button className="primary css-xxxxxxxx">Hi</button>
+/// }
+/// ```
+///
+/// The scoped CSS is recorded in the `scoped_css` of the resolver by the scope class,
+/// with `apply_css` (in development) it's applied by the `applyCSS()` calls of the
+/// aleph runtime. The other uses of the templates are replaced with the scope class.
+/// Only the intrinsic elements like `<button>` get the scope class, the templates
+/// with the expressions are not supported and left as is. The fold does nothing if
+/// the module declares or imports its own `css`, like the one of emotion.
+pub fn scoped_css_fold(resolver: Rc<RefCell<Resolver>>, apply_css: bool) -> impl Fold {
+  ScopedCssFold {
+    resolver,
+    apply_css,
+  }
+}
+
+struct ScopedCssFold {
+  resolver: Rc<RefCell<Resolver>>,
+  apply_css: bool,
+}
+
+impl ScopedCssFold {
+  /// returns the scope class of the CSS, the CSS is scoped and recorded.
+  fn add_css(&self, css: &[String], items: &mut Vec<ModuleItem>) -> Option<String> {
+    if css.is_empty() {
+      return None;
+    }
+    let css = css.join("\n");
+    let mut resolver = self.resolver.borrow_mut();
+    let mut hasher = Sha1::new();
+    hasher.update(resolver.specifier.as_bytes());
+    hasher.update(b"\n");
+    hasher.update(css.as_bytes());
+    let scope = format!("css-{}", &format!("{:x}", hasher.finalize())[..8]);
+    let scoped = scope_css(&css, &scope);
+    if self.apply_css {
+      // applyCSS("/components/button.tsx#css-xxxxxxxx", "button.css-xxxxxxxx{}")
+      let url = format!("{}#{}", resolver.specifier, scope);
+      items.push(new_apply_css_stmt(&url, Some(&scoped)));
+    }
+    resolver.scoped_css.insert(scope.clone(), scoped);
+    Some(scope)
+  }
+}
+
+impl Fold for ScopedCssFold {
+  noop_fold_type!();
+
+  fn fold_module_items(&mut self, module_items: Vec<ModuleItem>) -> Vec<ModuleItem> {
+    if declares_css(&module_items) {
+      return module_items;
+    }
+    let mut collectors = Vec::with_capacity(module_items.len());
+    let mut module_css = vec![];
+    for item in module_items.iter() {
+      let mut collector = CssTemplateCollector {
+        css: vec![],
+        fn_depth: 0,
+        module_css: vec![],
+      };
+      item.clone().fold_with(&mut collector);
+      module_css.append(&mut collector.module_css);
+      collectors.push(collector.css);
+    }
+    if module_css.is_empty() && collectors.iter().all(|css| css.is_empty()) {
+      return module_items;
+    }
+
+    let mut items = Vec::with_capacity(module_items.len());
+    let module_scope = self.add_css(&module_css, &mut items);
+    let mut uses_apply_css = module_scope.is_some();
+    for (item, css) in module_items.into_iter().zip(collectors) {
+      // match: css`:global(body) { margin: 0; }`
+      if let ModuleItem::Stmt(stmt) = &item {
+        if is_css_template_stmt(stmt) {
+          continue;
+        }
+      }
+      let scope = self.add_css(&css, &mut items);
+      uses_apply_css = uses_apply_css || scope.is_some();
+      let mut rewriter = ScopeRewriter {
+        module_scope: module_scope.clone(),
+        scope,
+        fn_depth: 0,
+      };
+      items.push(item.fold_with(&mut rewriter));
+    }
+
+    if self.apply_css && uses_apply_css && !imports_apply_css(&items) {
+      // import { applyCSS } from "https://deno.land/x/aleph/framework/core/style.ts"
+      let src = format!(
+        "{}/framework/core/style.ts",
+        self.resolver.borrow().get_aleph_pkg_uri()
+      );
+      items.insert(
+        0,
+        ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+          span: DUMMY_SP,
+          specifiers: vec![ImportSpecifier::Named(ImportNamedSpecifier {
+            span: DUMMY_SP,
+            local: quote_ident!("applyCSS"),
+            imported: None,
+          })],
+          src: Str {
+            span: DUMMY_SP,
+            value: src.into(),
+            has_escape: false,
+            kind: Default::default(),
+          },
+          type_only: false,
+          asserts: None,
+        })),
+      );
+    }
+    items
+  }
+}
+
+/// collects the CSS of the `css` templates of a module item.
+struct CssTemplateCollector {
+  /// the CSS of the templates in the functions.
+  css: Vec<String>,
+  fn_depth: u32,
+  /// the CSS of the templates at the module level.
+  module_css: Vec<String>,
+}
+
+impl Fold for CssTemplateCollector {
+  noop_fold_type!();
+
+  fn fold_tagged_tpl(&mut self, tpl: TaggedTpl) -> TaggedTpl {
+    if let Some(css) = get_css_template(&tpl) {
+      if self.fn_depth > 0 {
+        self.css.push(css);
+      } else {
+        self.module_css.push(css);
+      }
+    }
+    tpl.fold_children_with(self)
+  }
+
+  fn fold_function(&mut self, function: Function) -> Function {
+    self.fn_depth += 1;
+    let function = function.fold_children_with(self);
+    self.fn_depth -= 1;
+    function
+  }
+
+  fn fold_arrow_expr(&mut self, arrow: ArrowExpr) -> ArrowExpr {
+    self.fn_depth += 1;
+    let arrow = arrow.fold_children_with(self);
+    self.fn_depth -= 1;
+    arrow
+  }
+}
+
+/// replaces the `css` templates with the scope classes, and adds the scope classes to
+/// the intrinsic JSX elements.
+struct ScopeRewriter {
+  module_scope: Option<String>,
+  scope: Option<String>,
+  fn_depth: u32,
+}
+
+impl ScopeRewriter {
+  fn scope_classes(&self) -> Option<String> {
+    match (&self.module_scope, &self.scope) {
+      (Some(module_scope), Some(scope)) => Some(format!("{} {}", module_scope, scope)),
+      (Some(scope), None) | (None, Some(scope)) => Some(scope.clone()),
+      (None, None) => None,
+    }
+  }
+}
+
+impl Fold for ScopeRewriter {
+  noop_fold_type!();
+
+  fn fold_stmts(&mut self, stmts: Vec<Stmt>) -> Vec<Stmt> {
+    // match: css`button { color: red; }`
+    stmts
+      .into_iter()
+      .filter(|stmt| !is_css_template_stmt(stmt))
+      .collect::<Vec<Stmt>>()
+      .fold_children_with(self)
+  }
+
+  fn fold_expr(&mut self, expr: Expr) -> Expr {
+    if let Expr::TaggedTpl(tpl) = &expr {
+      if get_css_template(tpl).is_some() {
+        let scope = if self.fn_depth > 0 {
+          self.scope.as_ref()
+        } else {
+          self.module_scope.as_ref()
+        };
+        if let Some(scope) = scope {
+          return Expr::Lit(Lit::Str(new_str(scope)));
+        }
+      }
+    }
+    expr.fold_children_with(self)
+  }
+
+  fn fold_function(&mut self, function: Function) -> Function {
+    self.fn_depth += 1;
+    let function = function.fold_children_with(self);
+    self.fn_depth -= 1;
+    function
+  }
+
+  fn fold_arrow_expr(&mut self, arrow: ArrowExpr) -> ArrowExpr {
+    self.fn_depth += 1;
+    let arrow = arrow.fold_children_with(self);
+    self.fn_depth -= 1;
+    arrow
+  }
+
+  fn fold_jsx_opening_element(&mut self, el: JSXOpeningElement) -> JSXOpeningElement {
+    let mut el = el.fold_children_with(self);
+    let classes = match (&el.name, self.scope_classes()) {
+      (JSXElementName::Ident(id), Some(classes))
+        if id
+          .sym
+          .as_ref()
+          .starts_with(|c: char| c.is_ascii_lowercase()) =>
+      {
+        classes
+      }
+      _ => return el,
+    };
+    let class_attr = el.attrs.iter_mut().find_map(|attr| match attr {
+      JSXAttrOrSpread::JSXAttr(attr) if is_class_attr(attr) => Some(attr),
+      _ => None,
+    });
+    match class_attr {
+      Some(attr) => {
+        attr.value = Some(match attr.value.take() {
+          // match: className="primary"
+          Some(JSXAttrValue::Lit(Lit::Str(s))) => {
+            JSXAttrValue::Lit(Lit::Str(new_str(&format!("{} {}", s.value, classes))))
+          }
+          // match: className={styles.button}
+          Some(JSXAttrValue::JSXExprContainer(JSXExprContainer {
+            span,
+            expr: JSXExpr::Expr(expr),
+          })) => JSXAttrValue::JSXExprContainer(JSXExprContainer {
+            span,
+            expr: JSXExpr::Expr(Box::new(new_class_concat(*expr, &classes))),
+          }),
+          _ => JSXAttrValue::Lit(Lit::Str(new_str(&classes))),
+        });
+      }
+      None => el.attrs.push(JSXAttrOrSpread::JSXAttr(JSXAttr {
+        span: DUMMY_SP,
+        name: JSXAttrName::Ident(quote_ident!("className")),
+        value: Some(JSXAttrValue::Lit(Lit::Str(new_str(&classes)))),
+      })),
+    }
+    el
+  }
+}
+
+/// returns the CSS of a `css` tagged template without the expressions.
+fn get_css_template(tpl: &TaggedTpl) -> Option<String> {
+  match tpl.tag.as_ref() {
+    Expr::Ident(id) if id.sym.as_ref() == "css" && tpl.exprs.is_empty() => {
+      let quasi = tpl.quasis.first()?;
+      let css = quasi.cooked.as_ref().unwrap_or(&quasi.raw);
+      Some(css.value.as_ref().trim().into())
+    }
+    _ => None,
+  }
+}
+
+fn is_css_template_stmt(stmt: &Stmt) -> bool {
+  match stmt {
+    Stmt::Expr(ExprStmt { expr, .. }) => match expr.as_ref() {
+      Expr::TaggedTpl(tpl) => get_css_template(tpl).is_some(),
+      _ => false,
+    },
+    _ => false,
+  }
+}
+
+// match: className="primary" or class="primary"
+fn is_class_attr(attr: &JSXAttr) -> bool {
+  match &attr.name {
+    JSXAttrName::Ident(id) => id.sym.as_ref() == "className" || id.sym.as_ref() == "class",
+    _ => false,
+  }
+}
+
+/// checks if the module declares or imports its own `css`.
+fn declares_css(items: &[ModuleItem]) -> bool {
+  items.iter().any(|item| {
+    match item {
+    ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl { specifiers, .. })) => {
+      specifiers.iter().any(|specifier| match specifier {
+        ImportSpecifier::Named(ImportNamedSpecifier { local, .. })
+        | ImportSpecifier::Default(ImportDefaultSpecifier { local, .. })
+        | ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => {
+          local.sym.as_ref() == "css"
+        }
+      })
+    }
+    ModuleItem::Stmt(Stmt::Decl(decl))
+    | ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => match decl {
+      Decl::Fn(FnDecl { ident, declare, .. }) => !declare && ident.sym.as_ref() == "css",
+      Decl::Var(VarDecl { decls, declare, .. }) => {
+        !declare
+          && decls.iter().any(|decl| {
+            matches!(&decl.name, Pat::Ident(BindingIdent { id, .. }) if id.sym.as_ref() == "css")
+          })
+      }
+      _ => false,
+    },
+    _ => false,
+  }
+  })
+}
+
+fn imports_apply_css(items: &[ModuleItem]) -> bool {
+  items.iter().any(|item| match item {
+    ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl { specifiers, .. })) => {
+      specifiers.iter().any(|specifier| {
+        matches!(specifier, ImportSpecifier::Named(ImportNamedSpecifier { local, .. }) if local.sym.as_ref() == "applyCSS")
+      })
+    }
+    _ => false,
+  })
+}
+
+// match: (expr || "") + " css-xxxxxxxx"
+fn new_class_concat(expr: Expr, classes: &str) -> Expr {
+  Expr::Bin(BinExpr {
+    span: DUMMY_SP,
+    op: BinaryOp::Add,
+    left: Box::new(Expr::Paren(ParenExpr {
+      span: DUMMY_SP,
+      expr: Box::new(Expr::Bin(BinExpr {
+        span: DUMMY_SP,
+        op: BinaryOp::LogicalOr,
+        left: Box::new(expr),
+        right: Box::new(Expr::Lit(Lit::Str(new_str("")))),
+      })),
+    })),
+    right: Box::new(Expr::Lit(Lit::Str(new_str(&format!(" {}", classes))))),
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::import_map::ImportHashMap;
+  use crate::resolve::Resolver;
+  use crate::swc::{EmitOptions, SWC};
+  use std::{cell::RefCell, rc::Rc};
+
+  fn t(specifier: &str, source: &str, is_dev: bool) -> (String, Vec<(String, String)>) {
+    let module = SWC::parse(specifier, source, None).expect("could not parse module");
+    let resolver = Rc::new(RefCell::new(Resolver::new(
+      specifier,
+      ImportHashMap::default(),
+      Some("https://deno.land/x/aleph@v0.3.0".into()),
+      None,
+      false,
+      vec![],
+    )));
+    let (code, _) = module
+      .transform(
+        resolver.clone(),
+        &EmitOptions {
+          scoped_css: true,
+          is_dev,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    let scoped_css = resolver
+      .borrow()
+      .scoped_css
+      .iter()
+      .map(|(scope, css)| (scope.clone(), css.clone()))
+      .collect();
+    (code, scoped_css)
+  }
+
+  #[test]
+  fn scoped_css() {
+    let source = r#"
+      import styles from "./button.module.css"
+      css`:global(body) { margin: 0; }`
+      export default function Button({ primary }) {
+        css`
+          button, .icon:hover > svg::before { color: red; }
+          @media (max-width: 600px) { button { margin: 0; } }
+        `
+        return (
+          <button className="button" data-primary={primary}>
+            <span className={styles.title}>Hi</span>
+            <Icon />
+          </button>
+        )
+      }
+      export const Icon = () => <i class="icon" />
+    "#;
+    let (code, scoped_css) = t("/components/button.jsx", source, false);
+    assert_eq!(scoped_css.len(), 2);
+    let (scope, css) = scoped_css
+      .iter()
+      .find(|(_, css)| css.contains("button"))
+      .unwrap();
+    let (module_scope, module_css) = scoped_css.iter().find(|(s, _)| s != scope).unwrap();
+    assert!(scope.starts_with("css-") && scope.len() == 12);
+    assert_eq!(
+      css.as_str(),
+      format!(
+        "button.{0}, .icon:hover > svg.{0}::before {{ color: red; }}\n          @media (max-width: 600px) {{ button.{0} {{ margin: 0; }} }}",
+        scope
+      )
+    );
+    assert_eq!(module_css, "body { margin: 0; }");
+    assert!(!code.contains("css`"));
+    assert!(code.contains(&format!(
+      "React.createElement(\"button\", {{\n        className: \"button {} {}\",",
+      module_scope, scope
+    )));
+    assert!(code.contains(&format!(
+      "className: (styles.title || \"\") + \" {} {}\"",
+      module_scope, scope
+    )));
+    assert!(code.contains("React.createElement(Icon, null)"));
+    // the Icon doesn't have its own CSS
+    assert!(code.contains(&format!(
+      "React.createElement(\"i\", {{\n        class: \"icon {}\"",
+      module_scope
+    )));
+    assert!(!code.contains("applyCSS"));
+
+    let (code, _) = t("/components/button.jsx", source, true);
+    assert!(code.contains(
+      "import { applyCSS } from \"../-/deno.land/x/aleph@v0.3.0/framework/core/style.js\";"
+    ));
+    assert!(code.contains(&format!(
+      "applyCSS(\"/components/button.jsx#{}\", \"body {{ margin: 0; }}\");",
+      module_scope
+    )));
+
+    // the `css` of emotion
+    let source = r#"
+      import { css } from "https://esm.sh/@emotion/css"
+      const title = css`color: red;`
+      export default () => <h1 className={title}>Hi</h1>
+    "#;
+    let (code, scoped_css) = t("/pages/index.jsx", source, false);
+    assert!(scoped_css.is_empty());
+    assert!(code.contains("css`color: red;`"));
+  }
+}
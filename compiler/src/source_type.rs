@@ -1,7 +1,15 @@
-use serde::Deserialize;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 
-#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+lazy_static! {
+  static ref RE_JSX_PRAGMA: Regex =
+    Regex::new(r"/\*[\s\*]*@jsx(Frag|ImportSource|Runtime)?\b").unwrap();
+  static ref RE_JSX_ELEMENT: Regex =
+    Regex::new(r"<(/?>|/?[A-Za-z][\w\.:\-]*(\s[^<>]*)?/?>)").unwrap();
+}
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub enum SourceType {
   #[serde(rename = "js")]
   JS,
@@ -40,6 +48,17 @@ impl Default for SourceType {
 }
 
 impl SourceType {
+  /// detects the source type by the extension of the specifier, the `.js` modules
+  /// (and the modules without a known extension) that have a `/* @jsx */` pragma or
+  /// look like containing JSX elements are treated as JSX, the JSX syntax is a
+  /// superset of JS so a false positive is harmless.
+  pub fn detect(specifier: &str, source: &str) -> Self {
+    match SourceType::from_path(Path::new(specifier)) {
+      SourceType::JS | SourceType::Unknown if has_jsx(source) => SourceType::JSX,
+      source_type => source_type,
+    }
+  }
+
   fn from_path(path: &Path) -> Self {
     match path.extension() {
       None => SourceType::Unknown,
@@ -55,6 +74,10 @@ impl SourceType {
   }
 }
 
+fn has_jsx(source: &str) -> bool {
+  RE_JSX_PRAGMA.is_match(source) || RE_JSX_ELEMENT.is_match(source)
+}
+
 #[cfg(test)]
 mod tests {
   use super::*;
@@ -72,4 +95,35 @@ mod tests {
     );
     assert_eq!(SourceType::from(Path::new("/foo/bar")), SourceType::Unknown);
   }
+
+  #[test]
+  fn detect_jsx() {
+    assert_eq!(
+      SourceType::detect("/app.js", "export default () => <div />"),
+      SourceType::JSX
+    );
+    assert_eq!(
+      SourceType::detect(
+        "/app.js",
+        "const App = () => <>\n  <App.Header title=\"x\" />\n</>"
+      ),
+      SourceType::JSX
+    );
+    assert_eq!(
+      SourceType::detect("/app.js", "/** @jsx h */\nexport default () => h('div')"),
+      SourceType::JSX
+    );
+    assert_eq!(
+      SourceType::detect("/app.js", "export const lt = (a, b) => a < b"),
+      SourceType::JS
+    );
+    assert_eq!(
+      SourceType::detect("https://esm.sh/preact", "export default h(\"div\")"),
+      SourceType::Unknown
+    );
+    assert_eq!(
+      SourceType::detect("/app.ts", "const el = <div />"),
+      SourceType::TS
+    );
+  }
 }
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+#[cfg(not(target_arch = "wasm32"))]
+use once_cell::sync::Lazy;
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Instant;
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::wasm_bindgen;
+
+/// The timing and the sizes of a transform, which are collected with the `measure`
+/// option for the `--analyze` breakdown of the build.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TransformStats {
+  pub parse_ms: f64,
+  pub transform_ms: f64,
+  pub emit_ms: f64,
+  pub input_bytes: usize,
+  pub output_bytes: usize,
+}
+
+#[cfg(target_arch = "wasm32")]
+#[wasm_bindgen]
+extern "C" {
+  #[wasm_bindgen(js_namespace = performance, js_name = "now")]
+  fn performance_now() -> f64;
+}
+
+/// returns the current time in milliseconds, `std::time::Instant` isn't available
+/// in wasm so `performance.now()` is used instead.
+#[cfg(target_arch = "wasm32")]
+pub fn now() -> f64 {
+  performance_now()
+}
+
+/// returns the milliseconds since the first call.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn now() -> f64 {
+  static START: Lazy<Instant> = Lazy::new(Instant::now);
+  START.elapsed().as_secs_f64() * 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::{transform, Options};
+
+  #[test]
+  fn transform_stats() {
+    let code = "export default function Index() { return <h1>Hi</h1> }";
+    let options: Options = serde_json::from_str(r#"{"measure":true}"#).unwrap();
+    let output = transform("/pages/index.tsx", code, &options).expect("could not transform module");
+    let stats = output.stats.expect("missing stats");
+    assert_eq!(stats.input_bytes, code.len());
+    assert_eq!(stats.output_bytes, output.code.len());
+    assert!(stats.parse_ms >= 0.0 && stats.transform_ms >= 0.0 && stats.emit_ms >= 0.0);
+
+    let options: Options = serde_json::from_str("{}").unwrap();
+    let output = transform("/pages/index.tsx", code, &options).expect("could not transform module");
+    assert!(output.stats.is_none());
+  }
+}
@@ -0,0 +1,372 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::quote_ident;
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+lazy_static! {
+  static ref RE_STYLED_MODULE: Regex =
+    Regex::new(r"(^|/)styled-components(@[^/?#]+)?([/?#]|$)").unwrap();
+}
+
+/// The options of the styled components transform.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(deny_unknown_fields, rename_all = "camelCase")]
+pub struct StyledOptions {
+  /// adds the `displayName` of the styled components for React DevTools and the
+  /// readable class names. default is `true`.
+  #[serde(default = "default_true")]
+  pub display_name: bool,
+  /// adds the stable `componentId` of the styled components, so the class names
+  /// match between the server rendering and the hydration. default is `true`.
+  #[serde(default = "default_true")]
+  pub ssr: bool,
+  /// creates the JSX elements with the emotion `jsx` when the module uses the
+  /// `css` prop. default is `false`.
+  #[serde(default)]
+  pub emotion: bool,
+  /// the import source of the emotion JSX runtime.
+  #[serde(default = "default_emotion_import_source")]
+  pub emotion_import_source: String,
+}
+
+impl Default for StyledOptions {
+  fn default() -> Self {
+    StyledOptions {
+      display_name: true,
+      ssr: true,
+      emotion: false,
+      emotion_import_source: default_emotion_import_source(),
+    }
+  }
+}
+
+fn default_true() -> bool {
+  true
+}
+
+fn default_emotion_import_source() -> String {
+  "https://esm.sh/@emotion/react".into()
+}
+
+/// Creates a fold that configures the styled components like the babel plugin of
+/// styled-components, the `displayName` is the name of the binding and the
+/// `componentId` is hashed from the module `specifier` with the index of the
+/// component:
+///
+/// ```js
+/// const Button = styled.button.withConfig({
+///   displayName: "Button",
+///   componentId: "sc-1a2b3c-0"
+/// })`color: red;`
+/// ```
+pub fn styled_components_fold(specifier: &str, options: StyledOptions) -> impl Fold {
+  let mut hasher = Sha1::new();
+  hasher.update(specifier);
+  let hash = base64::encode(hasher.finalize())
+    .replace("+", "")
+    .replace("/", "")
+    .replace("=", "");
+  StyledComponentsFold {
+    options,
+    hash: hash[..6].into(),
+    styled: vec![],
+    index: 0,
+  }
+}
+
+struct StyledComponentsFold {
+  options: StyledOptions,
+  hash: String,
+  /// the local names of the `styled` imports
+  styled: Vec<String>,
+  index: usize,
+}
+
+impl StyledComponentsFold {
+  /// returns the styled component factory with the config, or `None` if the
+  /// expression is not a factory, match: `styled.div`, `styled(Link)`,
+  /// `styled.div.attrs(...)`.
+  fn with_config(&self, expr: &Expr, config: &ObjectLit) -> Option<Expr> {
+    match expr {
+      Expr::Member(MemberExpr {
+        obj: ExprOrSuper::Expr(obj),
+        computed: false,
+        ..
+      }) if self.is_styled(obj) => Some(new_with_config_call(expr, config)),
+      Expr::Call(call) => {
+        let callee = match &call.callee {
+          ExprOrSuper::Expr(callee) => callee,
+          _ => return None,
+        };
+        if self.is_styled(callee) {
+          return Some(new_with_config_call(expr, config));
+        }
+        // the config goes before the attrs, match: `styled.div.attrs(...)`
+        if let Expr::Member(member) = callee.as_ref() {
+          if let (ExprOrSuper::Expr(obj), Expr::Ident(prop)) = (&member.obj, member.prop.as_ref()) {
+            if !member.computed && prop.sym.eq("attrs") {
+              let obj = self.with_config(obj, config)?;
+              return Some(Expr::Call(CallExpr {
+                callee: ExprOrSuper::Expr(Box::new(Expr::Member(MemberExpr {
+                  obj: ExprOrSuper::Expr(Box::new(obj)),
+                  ..member.clone()
+                }))),
+                ..call.clone()
+              }));
+            }
+          }
+        }
+        None
+      }
+      _ => None,
+    }
+  }
+
+  fn is_styled(&self, expr: &Expr) -> bool {
+    match expr {
+      Expr::Ident(id) => self.styled.iter().any(|name| id.sym.eq(name.as_str())),
+      _ => false,
+    }
+  }
+
+  fn new_config(&self, display_name: Option<&str>) -> ObjectLit {
+    let mut props = vec![];
+    if let (true, Some(name)) = (self.options.display_name, display_name) {
+      props.push(new_str_prop("displayName", name));
+    }
+    if self.options.ssr {
+      let component_id = format!("sc-{}-{}", self.hash, self.index);
+      props.push(new_str_prop("componentId", component_id.as_str()));
+    }
+    ObjectLit {
+      span: DUMMY_SP,
+      props,
+    }
+  }
+
+  /// adds the config to the styled component, match: styled.div`...` or styled.div({...})
+  fn transform(&mut self, expr: Expr, display_name: Option<&str>) -> Expr {
+    let factory = match &expr {
+      Expr::TaggedTpl(TaggedTpl { tag, .. }) => tag,
+      Expr::Call(CallExpr {
+        callee: ExprOrSuper::Expr(callee),
+        ..
+      }) => callee,
+      _ => return expr,
+    };
+    let config = self.new_config(display_name);
+    let factory = match self.with_config(factory, &config) {
+      Some(factory) => Box::new(factory),
+      None => return expr,
+    };
+    self.index += 1;
+    match expr {
+      Expr::TaggedTpl(tpl) => Expr::TaggedTpl(TaggedTpl {
+        tag: factory,
+        ..tpl
+      }),
+      Expr::Call(call) => Expr::Call(CallExpr {
+        callee: ExprOrSuper::Expr(factory),
+        ..call
+      }),
+      _ => unreachable!(),
+    }
+  }
+}
+
+impl Fold for StyledComponentsFold {
+  noop_fold_type!();
+
+  fn fold_module(&mut self, module: Module) -> Module {
+    for item in module.body.iter() {
+      if let ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+        specifiers, src, ..
+      })) = item
+      {
+        if RE_STYLED_MODULE.is_match(src.value.as_ref()) {
+          for specifier in specifiers {
+            if let ImportSpecifier::Default(ImportDefaultSpecifier { local, .. }) = specifier {
+              self.styled.push(local.sym.as_ref().into());
+            }
+          }
+        }
+      }
+    }
+    if self.styled.is_empty() {
+      return module;
+    }
+    module.fold_children_with(self)
+  }
+
+  // match: const Button = styled.button`...`
+  fn fold_var_declarator(&mut self, decl: VarDeclarator) -> VarDeclarator {
+    let name = match &decl.name {
+      Pat::Ident(BindingIdent { id, .. }) => Some(id.sym.as_ref().to_owned()),
+      _ => None,
+    };
+    match (name, decl.init) {
+      (Some(name), Some(init)) => {
+        let init = (*init).fold_children_with(self);
+        VarDeclarator {
+          init: Some(Box::new(self.transform(init, Some(name.as_str())))),
+          ..decl
+        }
+      }
+      (_, init) => VarDeclarator {
+        init: init.fold_with(self),
+        ..decl
+      },
+    }
+  }
+
+  fn fold_expr(&mut self, expr: Expr) -> Expr {
+    let expr = expr.fold_children_with(self);
+    self.transform(expr, None)
+  }
+}
+
+/// checks if the module passes the `css` prop to a JSX element, match: `<div css={...} />`.
+pub fn uses_css_prop(module: &Module) -> bool {
+  let mut checker = CssPropChecker { found: false };
+  Program::Module(module.clone()).fold_with(&mut checker);
+  checker.found
+}
+
+struct CssPropChecker {
+  found: bool,
+}
+
+impl Fold for CssPropChecker {
+  noop_fold_type!();
+
+  fn fold_jsx_attr(&mut self, attr: JSXAttr) -> JSXAttr {
+    if let JSXAttrName::Ident(id) = &attr.name {
+      if id.sym.eq("css") {
+        self.found = true;
+      }
+    }
+    attr
+  }
+}
+
+fn new_with_config_call(factory: &Expr, config: &ObjectLit) -> Expr {
+  Expr::Call(CallExpr {
+    span: DUMMY_SP,
+    callee: ExprOrSuper::Expr(Box::new(Expr::Member(MemberExpr {
+      span: DUMMY_SP,
+      obj: ExprOrSuper::Expr(Box::new(factory.clone())),
+      prop: Box::new(Expr::Ident(quote_ident!("withConfig"))),
+      computed: false,
+    }))),
+    args: vec![ExprOrSpread {
+      spread: None,
+      expr: Box::new(Expr::Object(config.clone())),
+    }],
+    type_args: None,
+  })
+}
+
+fn new_str_prop(key: &str, value: &str) -> PropOrSpread {
+  PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+    key: PropName::Ident(quote_ident!(key)),
+    value: Box::new(Expr::Lit(Lit::Str(Str {
+      span: DUMMY_SP,
+      value: value.into(),
+      has_escape: false,
+      kind: Default::default(),
+    }))),
+  })))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::swc::{test_resolver, EmitOptions, SourceMapMode, SWC};
+
+  #[test]
+  fn styled_components() {
+    let source = r#"
+      import styled from "https://esm.sh/styled-components"
+      import { Link } from "https://deno.land/x/aleph/mod.ts"
+      const Button = styled.button`
+        color: ${props => props.color};
+      `
+      const Title = styled.h1.attrs({ role: "heading" })`font-size: 2em;`
+      export const NavLink = styled(Link)({ color: "red" })
+      export default styled.div`display: flex;`
+      const color = String("red")
+    "#;
+    let module =
+      SWC::parse("/components/button.tsx", source, None).expect("could not parse module");
+    let (code, _) = swc_common::GLOBALS.set(&Default::default(), || {
+      module
+        .apply_transform(
+          styled_components_fold("/components/button.tsx", StyledOptions::default()),
+          &SourceMapMode::None,
+          Default::default(),
+          false,
+          false,
+        )
+        .expect("could not transpile module")
+    });
+    println!("{}", code);
+    let hash = {
+      let mut hasher = Sha1::new();
+      hasher.update("/components/button.tsx");
+      base64::encode(hasher.finalize())
+        .replace("+", "")
+        .replace("/", "")
+        .replace("=", "")[..6]
+        .to_owned()
+    };
+    assert!(code.contains(&format!(
+      "const Button = styled.button.withConfig({{\n    displayName: \"Button\",\n    componentId: \"sc-{}-0\"\n}})`",
+      hash
+    )));
+    assert!(code.contains(&format!(
+      "const Title = styled.h1.withConfig({{\n    displayName: \"Title\",\n    componentId: \"sc-{}-1\"\n}}).attrs({{\n    role: \"heading\"\n}})`",
+      hash
+    )));
+    assert!(code.contains(&format!(
+      "export const NavLink = styled(Link).withConfig({{\n    displayName: \"NavLink\",\n    componentId: \"sc-{}-2\"\n}})({{",
+      hash
+    )));
+    assert!(code.contains(&format!(
+      "export default styled.div.withConfig({{\n    componentId: \"sc-{}-3\"\n}})`",
+      hash
+    )));
+    assert!(code.contains("const color = String(\"red\")"));
+  }
+
+  #[test]
+  fn emotion_css_prop() {
+    let source = r#"
+      import React from "https://esm.sh/react"
+      export default function App() {
+        return <div css={{ color: "red" }}>Hello</div>
+      }
+    "#;
+    let module = SWC::parse("/pages/index.tsx", source, None).expect("could not parse module");
+    assert!(uses_css_prop(&module.module));
+    let resolver = test_resolver("/pages/index.tsx");
+    let (code, _) = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          styled: Some(StyledOptions {
+            emotion: true,
+            ..Default::default()
+          }),
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    assert!(
+      code.contains("import { jsx as _jsx } from \"../-/esm.sh/@emotion/react/jsx-runtime.js\"")
+    );
+    assert!(code.contains("_jsx(\"div\", {"));
+  }
+}
@@ -1,56 +1,330 @@
-use crate::error::{DiagnosticBuffer, ErrorBuffer};
-use crate::fast_refresh::react_refresh_fold;
+use crate::asset::asset_import_fold;
+use crate::comments::{strip_comments, CommentsMode};
+use crate::const_enum::{check_const_enums, const_enum_fold, ConstEnums};
+use crate::coverage::coverage_fold;
+use crate::css::css_import_fold;
+use crate::define::define_fold;
+use crate::directive::strip_server_exports_fold;
+use crate::display_name::display_name_fold;
+use crate::drop::drop_fold;
+use crate::error::{DiagnosticBuffer, DiagnosticCode, DiagnosticMessage, ErrorBuffer};
+use crate::fast_refresh::{
+  parse_refresh_directive, react_refresh_fold, RefreshDirective, RefreshOptions,
+};
 use crate::fixer::compat_fixer_fold;
+use crate::hmr::hmr_fold;
+use crate::import_assertions::{
+  collect_import_assertions, json_module_fold, restore_import_assertions,
+};
+use crate::import_equals::{check_import_equals, import_equals_fold};
 use crate::import_map::ImportHashMap;
-use crate::jsx::aleph_jsx_fold;
+use crate::import_meta::{import_glob_fold, import_meta_fold};
+use crate::inline_imports::{inline_import_fold, DEFAULT_INLINE_LIMIT};
+use crate::isolated_modules::check_isolated_modules;
+use crate::jsx::{aleph_jsx_fold, jsx_runtime_resolve_fold};
+use crate::jsx_args::{jsx_args_fold, JsxPropMode};
+use crate::jsx_hoist::jsx_hoist_fold;
+use crate::jsx_pragma::apply_jsx_pragmas;
+use crate::jsx_whitespace::{jsx_whitespace_fold, JsxWhitespace};
+use crate::keep_names::keep_names_fold;
+use crate::mangle::mangle_fold;
+use crate::metadata::parse_metadata;
+use crate::pure::pure_annotation_fold;
 use crate::resolve::Resolver;
-use crate::resolve_fold::{resolve_fold, ExportsParser};
+use crate::resolve_fold::{import_resolve_fold, resolve_fold, ExportsParser};
+use crate::scoped_css::scoped_css_fold;
 use crate::source_type::SourceType;
+use crate::stats::{now, TransformStats};
+use crate::styled::{styled_components_fold, uses_css_prop, StyledOptions};
+use crate::system_js::system_js_fold;
+use crate::targets::TargetFeatures;
+use crate::top_level_await::top_level_await_fold;
+use crate::tree_shake::tree_shake_fold;
+use crate::unused_imports::{check_imports, check_node_builtins, check_unpinned_imports};
 
-use std::{cell::RefCell, cmp::min, path::Path, rc::Rc};
+use serde::{
+  de::{value::Error as ValueError, IntoDeserializer},
+  Deserialize, Serialize,
+};
+use sourcemap::SourceMapBuilder;
+use std::{cell::RefCell, cmp::min, collections::HashMap, path::Path, rc::Rc};
 use swc_common::{
   chain,
-  comments::SingleThreadedComments,
+  comments::{Comments, SingleThreadedComments},
   errors::{Handler, HandlerFlags},
-  FileName, Globals, Mark, SourceMap,
+  FileName, Globals, Mark, SourceMap, Span, Spanned,
+};
+use swc_ecma_transforms_compat::es2015;
+use swc_ecma_transforms_compat::es2016::exponentation;
+use swc_ecma_transforms_compat::es2017::async_to_generator;
+use swc_ecma_transforms_compat::es2018::{object_rest_spread, optional_catch_binding};
+use swc_ecma_transforms_compat::es2020::{
+  class_properties, export_namespace_from, nullish_coalescing, optional_chaining,
+  typescript_class_properties,
 };
-use swc_ecma_transforms_compat::{es2015, es2016, es2017, es2018, es2020};
 use swc_ecma_transforms_proposal::decorators;
 use swc_ecma_transforms_typescript::strip;
 use swc_ecmascript::{
-  ast::{Module, Program},
+  ast::{Decorator, Module, Program},
   codegen::{text_writer::JsWriter, Node},
-  parser::{lexer::Lexer, EsConfig, JscTarget, StringInput, Syntax, TsConfig},
-  transforms::{fixer, helpers, hygiene, pass::Optional, react},
-  visit::{Fold, FoldWith},
+  parser::{
+    error::Error as ParseError, lexer::Lexer, EsConfig, JscTarget, StringInput, Syntax, TsConfig,
+  },
+  transforms::{
+    fixer, helpers, hygiene,
+    modules::{common_js, import_analysis::import_analyzer},
+    optimization::{
+      simplifier,
+      simplify::{dead_branch_remover, expr_simplifier},
+    },
+    pass::Optional,
+    react,
+    resolver::{resolver as scope_resolver, resolver_with_mark},
+  },
+  visit::{noop_fold_type, Fold, FoldWith},
 };
 
+/// The runtime used to transform JSX.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsxRuntime {
+  /// `React.createElement(...)`
+  #[default]
+  Classic,
+  /// `import { jsx as _jsx } from "react/jsx-runtime"`
+  Automatic,
+}
+
+/// How JSX is emitted.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JsxMode {
+  /// transforms JSX into function calls of the `jsx_runtime`.
+  #[default]
+  Transform,
+  /// keeps JSX as is for another JSX-aware compiler or runtime.
+  Preserve,
+}
+
+/// How the source map of a transformed module is emitted.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceMapMode {
+  /// no source map.
+  #[default]
+  None,
+  /// return the source map as a separate JSON string.
+  External,
+  /// append the source map to the code as a base64 data url.
+  Inline,
+}
+
+/// The runtime environment that the module is emitted for.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TargetEnv {
+  Browser,
+  Deno,
+}
+
+/// The module format of the output.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModuleType {
+  /// `import`/`export` statements.
+  #[default]
+  Es6,
+  /// `require()` and `exports`, with the `__esModule` interop.
+  CommonJs,
+  /// `System.register()` of SystemJS for the legacy browsers.
+  SystemJs,
+}
+
 /// Options for transpiling a module.
 #[derive(Debug, Clone)]
 pub struct EmitOptions {
   pub target: JscTarget,
+  /// the features of the `targets`, the features that are not supported by the
+  /// `target` are lowered too.
+  pub features: TargetFeatures,
+  pub jsx: JsxMode,
   pub jsx_factory: String,
   pub jsx_fragment_factory: String,
+  pub jsx_runtime: JsxRuntime,
+  pub jsx_import_source: String,
+  /// adds the `__source` and `__self` props to the JSX elements in development.
+  pub jsx_source: bool,
+  /// normalizes the whitespace of the JSX text like babel or tsc, for the hydration
+  /// of the text nodes that are rendered by the server.
+  pub jsx_whitespace: JsxWhitespace,
+  /// drops the empty string literal children, like `{""}`.
+  pub jsx_drop_empty_children: bool,
+  /// how the `key` and the `ref` props are passed to the factory of the classic
+  /// runtime, the view libraries like Solid take them as the separate args.
+  pub jsx_key: JsxPropMode,
+  pub jsx_ref: JsxPropMode,
+  pub module_type: ModuleType,
+  /// strips the server-only code for the browser, like the `useDeno` callbacks and
+  /// the imports that are only used by them, the module runs in both the browser
+  /// and deno if not specified.
+  pub target_env: Option<TargetEnv>,
+  pub emit_decorator_metadata: bool,
+  pub isolated_modules: bool,
   pub is_dev: bool,
+  /// wraps the module with the HMR runtime in development.
+  pub hmr: bool,
   pub transpile_only: bool,
   pub resolve_star_exports: bool,
-  pub source_map: bool,
+  pub extract_css: bool,
+  /// scopes the CSS of the `css` tagged templates to the components.
+  pub scoped_css: bool,
+  /// passes the chunk urls of the lazy components as the second argument, like
+  /// `React.lazy(() => import("./chart.js"), "/chart.js")`, for preloading.
+  pub lazy_preload: bool,
+  /// replaces the server-only exports with the stubs, for the browser.
+  pub strip_server_exports: bool,
+  pub css_modules: HashMap<String, String>,
+  /// the prefetched contents of the JSON modules to inline, keyed by the fixed url.
+  pub json_modules: HashMap<String, String>,
+  /// the contents of the small `.json` and `?text` imports to inline, keyed by the
+  /// fixed url without the query.
+  pub inline_imports: HashMap<String, String>,
+  /// the byte size limit of the inlined imports.
+  pub inline_limit: usize,
+  /// the const enums exported by the modules to inline, keyed by the fixed url.
+  pub const_enums: HashMap<String, ConstEnums>,
+  /// replaces the `import.meta.url`, default is the specifier of the module.
+  pub import_meta_url: Option<String>,
+  /// the files matched by the `import.meta.glob` patterns, keyed by the pattern.
+  pub glob_files: HashMap<String, Vec<String>>,
+  pub source_map: SourceMapMode,
+  pub input_source_map: Option<String>,
+  /// embeds the original sources in the `sourcesContent` of the source map.
+  pub inline_sources_content: bool,
+  /// the `sourceRoot` of the source map, which the sources are relative to.
+  pub source_root: Option<String>,
+  /// simplifies the AST (like the dead code and the constant folding), mangles the
+  /// names of the local bindings and emits the code without whitespace.
+  pub minify: bool,
+  /// strips the comments except the `#__PURE__` annotations if not `All`.
+  pub comments: CommentsMode,
+  pub external_helpers: bool,
+  /// lowers the top-level `await` even if the `targets` support it, every module of
+  /// the app should be lowered as the importers wait for the `__tla` of the imports.
+  pub lower_top_level_await: bool,
+  /// lowers the class fields and the private fields even if the target supports
+  /// them, the private methods are kept since swc doesn't lower them.
+  pub lower_class_fields: bool,
+  /// uses the `Object.defineProperty` semantics for the class fields like in
+  /// the spec, otherwise the class fields are lowered to the assignments in the
+  /// constructor like the `useDefineForClassFields: false` of tsc.
+  pub use_define_for_class_fields: bool,
+  pub define: HashMap<String, String>,
+  /// drops the exports that are not used by the other modules, keeps all if `None`.
+  pub used_exports: Option<Vec<String>>,
+  /// the deps (the fixed urls) whose unused imports can be dropped by the tree shaking.
+  pub side_effect_free_deps: Vec<String>,
+  pub drop_console: bool,
+  pub drop_debugger: bool,
+  /// removes the `data-testid` and the `data-test` attributes of the JSX elements in
+  /// production.
+  pub strip_data_test_attrs: bool,
+  /// removes the calls of the functions in production, like `invariantDev` or
+  /// `dev.assert`.
+  pub strip_calls: Vec<String>,
+  /// keeps the `name` of the functions that are renamed by the transforms.
+  pub keep_fn_names: bool,
+  /// keeps the `name` of the classes that are renamed by the transforms.
+  pub keep_class_names: bool,
+  /// hoists the static JSX elements to the module scope in production.
+  pub hoist_static_jsx: bool,
+  /// instruments the module with the istanbul coverage counters.
+  pub coverage: bool,
+  pub refresh: RefreshOptions,
+  /// configures the styled components for SSR, and the emotion `css` prop.
+  pub styled: Option<StyledOptions>,
 }
 
 impl Default for EmitOptions {
   fn default() -> Self {
     EmitOptions {
       target: JscTarget::Es2020,
+      features: TargetFeatures::default(),
+      jsx: JsxMode::Transform,
       jsx_factory: "React.createElement".into(),
       jsx_fragment_factory: "React.Fragment".into(),
+      jsx_runtime: JsxRuntime::Classic,
+      jsx_import_source: "https://esm.sh/react".into(),
+      jsx_source: true,
+      jsx_whitespace: JsxWhitespace::default(),
+      jsx_drop_empty_children: false,
+      jsx_key: JsxPropMode::default(),
+      jsx_ref: JsxPropMode::default(),
+      module_type: ModuleType::Es6,
+      target_env: None,
+      emit_decorator_metadata: false,
+      isolated_modules: false,
       is_dev: false,
+      hmr: false,
       transpile_only: false,
       resolve_star_exports: false,
-      source_map: false,
+      extract_css: false,
+      scoped_css: false,
+      lazy_preload: false,
+      strip_server_exports: false,
+      css_modules: HashMap::new(),
+      json_modules: HashMap::new(),
+      inline_imports: HashMap::new(),
+      inline_limit: DEFAULT_INLINE_LIMIT,
+      const_enums: HashMap::new(),
+      import_meta_url: None,
+      glob_files: HashMap::new(),
+      source_map: SourceMapMode::None,
+      input_source_map: None,
+      inline_sources_content: false,
+      source_root: None,
+      minify: false,
+      comments: CommentsMode::All,
+      external_helpers: false,
+      lower_top_level_await: false,
+      lower_class_fields: false,
+      use_define_for_class_fields: true,
+      define: HashMap::new(),
+      used_exports: None,
+      side_effect_free_deps: vec![],
+      drop_console: false,
+      drop_debugger: false,
+      strip_data_test_attrs: false,
+      strip_calls: vec![],
+      keep_fn_names: false,
+      keep_class_names: false,
+      hoist_static_jsx: false,
+      coverage: false,
+      refresh: RefreshOptions::default(),
+      styled: None,
+    }
+  }
+}
+
+impl EmitOptions {
+  pub fn source_map_options(&self) -> SourceMapOptions<'_> {
+    SourceMapOptions {
+      input_source_map: self.input_source_map.as_deref(),
+      inline_sources_content: self.inline_sources_content,
+      source_root: self.source_root.as_deref(),
     }
   }
 }
 
+/// The options of the emitted source map besides the `SourceMapMode`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SourceMapOptions<'a> {
+  /// the source map of the input code, the output map is chained to its sources.
+  pub input_source_map: Option<&'a str>,
+  pub inline_sources_content: bool,
+  pub source_root: Option<&'a str>,
+}
+
 #[derive(Clone)]
 pub struct SWC {
   pub specifier: String,
@@ -61,6 +335,28 @@ pub struct SWC {
 }
 
 impl SWC {
+  /// clones the module with a copy of the comments, the comments added by the
+  /// transforms (like `#__PURE__`) don't leak into the other emits.
+  pub fn fork(&self) -> Self {
+    let comments = SingleThreadedComments::default();
+    {
+      let (leading, trailing) = self.comments.borrow_all();
+      for (pos, list) in leading.iter() {
+        comments.add_leading_comments(*pos, list.clone());
+      }
+      for (pos, list) in trailing.iter() {
+        comments.add_trailing_comments(*pos, list.clone());
+      }
+    }
+    SWC {
+      specifier: self.specifier.clone(),
+      module: self.module.clone(),
+      source_type: self.source_type.clone(),
+      source_map: self.source_map.clone(),
+      comments,
+    }
+  }
+
   /// parse the source of the module.
   ///
   /// ### Arguments
@@ -74,51 +370,189 @@ impl SWC {
     source: &str,
     source_type: Option<SourceType>,
   ) -> Result<Self, anyhow::Error> {
-    let source_map = SourceMap::default();
-    let source_file = source_map.new_source_file(
-      FileName::Real(Path::new(specifier).to_path_buf()),
-      source.into(),
-    );
-    let sm = &source_map;
-    let error_buffer = ErrorBuffer::new();
+    SWC::parse_with_features(specifier, source, source_type, &ParserFeatures::default())
+  }
+
+  /// parse the source of the module with the syntax `features` of the parser.
+  pub fn parse_with_features(
+    specifier: &str,
+    source: &str,
+    source_type: Option<SourceType>,
+    features: &ParserFeatures,
+  ) -> Result<Self, anyhow::Error> {
+    SWC::parse_with_source_map(
+      specifier,
+      source,
+      source_type,
+      features,
+      Rc::new(SourceMap::default()),
+      SingleThreadedComments::default(),
+    )
+  }
+
+  /// parse the source of the module into the shared `source_map` and `comments`,
+  /// the modules of a bundle are parsed into the same source map so their spans
+  /// don't overlap.
+  pub fn parse_with_source_map(
+    specifier: &str,
+    source: &str,
+    source_type: Option<SourceType>,
+    features: &ParserFeatures,
+    source_map: Rc<SourceMap>,
+    comments: SingleThreadedComments,
+  ) -> Result<Self, anyhow::Error> {
+    let sm = source_map.as_ref();
     let source_type = match source_type {
-      Some(source_type) => match source_type {
-        SourceType::Unknown => SourceType::from(Path::new(specifier)),
-        _ => source_type,
-      },
-      None => SourceType::from(Path::new(specifier)),
+      Some(SourceType::Unknown) | None => SourceType::detect(specifier, source),
+      Some(source_type) => source_type,
     };
-    let syntax = get_syntax(&source_type);
-    let input = StringInput::from(&*source_file);
-    let comments = SingleThreadedComments::default();
-    let lexer = Lexer::new(syntax, JscTarget::Es2020, input, Some(&comments));
-    let mut parser = swc_ecmascript::parser::Parser::new_from(lexer);
-    let handler = Handler::with_emitter_and_flags(
-      Box::new(error_buffer.clone()),
-      HandlerFlags {
-        can_emit_warnings: true,
-        dont_buffer_diagnostics: true,
-        ..HandlerFlags::default()
-      },
-    );
-    let module = parser
-      .parse_module()
-      .map_err(move |err| {
-        let mut diagnostic = err.into_diagnostic(&handler);
-        diagnostic.emit();
-        DiagnosticBuffer::from_error_buffer(error_buffer, |span| sm.lookup_char_pos(span.lo))
+    let (module, _) = parse_source(specifier, source, &source_type, features, sm, &comments);
+    let module = module.map_err(move |err| {
+      let error_buffer = ErrorBuffer::new();
+      let handler = Handler::with_emitter_and_flags(
+        Box::new(error_buffer.clone()),
+        HandlerFlags {
+          can_emit_warnings: true,
+          dont_buffer_diagnostics: true,
+          ..HandlerFlags::default()
+        },
+      );
+      let mut diagnostic = err.into_diagnostic(&handler);
+      diagnostic.emit();
+      DiagnosticBuffer::from_error_buffer(error_buffer, DiagnosticCode::SyntaxError, |span| {
+        sm.lookup_char_pos(span.lo)
       })
-      .unwrap();
+    })?;
+    if let Some((span, message)) = check_disabled_syntax(&module, &source_type, features) {
+      return Err(
+        DiagnosticBuffer {
+          diagnostics: vec![DiagnosticMessage::from_span(
+            span,
+            sm.lookup_char_pos(span.lo),
+            DiagnosticCode::SyntaxError,
+            message,
+          )],
+        }
+        .into(),
+      );
+    }
 
     Ok(SWC {
       specifier: specifier.into(),
       module,
       source_type,
-      source_map: Rc::new(source_map),
+      source_map,
       comments,
     })
   }
 
+  /// parse the source of the module and recover from the syntax errors, for the
+  /// editors and the dev overlay that show the metadata while the module is being
+  /// edited. the lines of the fatal errors are blanked out and parsed again, so the
+  /// positions of the other code don't change. returns the partial module (`None`
+  /// if the parser can't recover) with the errors in the order of the source.
+  pub fn parse_tolerant(
+    specifier: &str,
+    source: &str,
+    source_type: Option<SourceType>,
+    features: &ParserFeatures,
+  ) -> (Option<Self>, Vec<DiagnosticMessage>) {
+    let source_type = match source_type {
+      Some(SourceType::Unknown) | None => SourceType::detect(specifier, source),
+      Some(source_type) => source_type,
+    };
+    let mut source = source.to_owned();
+    let mut errors: Vec<DiagnosticMessage> = vec![];
+    let mut add_errors = |source_map: &SourceMap, errs: Vec<ParseError>| {
+      for err in errs {
+        let span = err.span();
+        let message = DiagnosticMessage::from_span(
+          span,
+          source_map.lookup_char_pos(span.lo),
+          DiagnosticCode::SyntaxError,
+          err.into_kind().msg().into(),
+        );
+        if !errors
+          .iter()
+          .any(|e| e.line == message.line && e.message == message.message)
+        {
+          errors.push(message);
+        }
+      }
+    };
+    let mut module = None;
+    // the offset of the last error and the source before its previous line is blanked
+    let mut retry: Option<(usize, String)> = None;
+    for _ in 0..MAX_RECOVERY_ATTEMPTS {
+      let source_map = Rc::new(SourceMap::default());
+      let comments = SingleThreadedComments::default();
+      let (result, recovered) = parse_source(
+        specifier,
+        source.as_str(),
+        &source_type,
+        features,
+        &source_map,
+        &comments,
+      );
+      match result {
+        Ok(m) => {
+          add_errors(&source_map, recovered);
+          if let Some((span, message)) = check_disabled_syntax(&m, &source_type, features) {
+            errors.push(DiagnosticMessage::from_span(
+              span,
+              source_map.lookup_char_pos(span.lo),
+              DiagnosticCode::SyntaxError,
+              message,
+            ));
+          }
+          module = Some(SWC {
+            specifier: specifier.into(),
+            module: m,
+            source_type: source_type.clone(),
+            source_map,
+            comments,
+          });
+          break;
+        }
+        Err(err) => {
+          // the errors after the first one may be caused by the parser's recovery,
+          // e.g. `1 + export` is parsed as an expression, so only the first error is
+          // reported and blanked out.
+          let err = recovered
+            .into_iter()
+            .chain(std::iter::once(err))
+            .min_by_key(|err| err.span().lo)
+            .unwrap();
+          let offset = source_map.lookup_byte_offset(err.span().lo).pos.0 as usize;
+          add_errors(&source_map, vec![err]);
+          // the error is usually caused by the incomplete statement on the previous
+          // line, e.g. `const a = 1 +`, which is blanked out first.
+          let blanked = match retry.take() {
+            // the error doesn't move, blank out the line of the error instead
+            Some((last_offset, last_source)) if last_offset == offset => {
+              source = last_source;
+              blank_line(&mut source, offset, false)
+            }
+            _ => {
+              let last_source = source.clone();
+              if blank_line(&mut source, offset, true) {
+                retry = Some((offset, last_source));
+                true
+              } else {
+                blank_line(&mut source, offset, false)
+              }
+            }
+          };
+          if !blanked {
+            break;
+          }
+        }
+      }
+    }
+    errors.sort_by_key(|e| (e.line, e.column));
+    (module, errors)
+  }
+
   /// transform a JS/TS/JSX/TSX file into a JS file, based on the supplied options.
   ///
   /// ### Arguments
@@ -131,6 +565,18 @@ impl SWC {
     resolver: Rc<RefCell<Resolver>>,
     options: &EmitOptions,
   ) -> Result<(String, Option<String>), anyhow::Error> {
+    self.transform_with_stats(resolver, options, &mut TransformStats::default())
+  }
+
+  /// transform the module like `transform`, the time of the transform and the emit
+  /// is recorded in the `stats`.
+  pub fn transform_with_stats(
+    self,
+    resolver: Rc<RefCell<Resolver>>,
+    options: &EmitOptions,
+    stats: &mut TransformStats,
+  ) -> Result<(String, Option<String>), anyhow::Error> {
+    let start = now();
     swc_common::GLOBALS.set(&Globals::new(), || {
       let specifier_is_remote = resolver.borrow().specifier_is_remote;
       let transpile_only = options.transpile_only;
@@ -139,77 +585,429 @@ impl SWC {
         SourceType::TSX => true,
         _ => false,
       };
+      if is_ts {
+        self.report_errors(check_import_equals(&self.module))?;
+      }
+      if is_ts && options.isolated_modules {
+        self.report_errors(check_isolated_modules(&self.module))?;
+      }
+      if is_ts && !options.const_enums.is_empty() && !transpile_only {
+        self.report_errors(check_const_enums(
+          &self.module,
+          resolver.clone(),
+          options.const_enums.clone(),
+        ))?;
+      }
       let is_jsx = match self.source_type {
         SourceType::JSX => true,
         SourceType::TSX => true,
         _ => false,
       };
-      let (aleph_jsx_fold, aleph_jsx_builtin_resolve_fold) =
-        aleph_jsx_fold(resolver.clone(), self.source_map.clone(), options.is_dev);
+      let transform_jsx = is_jsx && options.jsx == JsxMode::Transform && !transpile_only;
+      // the `@jsx` pragmas of the module override the jsx options
+      let pragma_options = apply_jsx_pragmas(&self.comments, &self.module, options);
+      let options = pragma_options.as_ref().unwrap_or(options);
+      // the elements with the emotion `css` prop are created by the emotion JSX runtime
+      let emotion_options;
+      let options = match &options.styled {
+        Some(styled) if styled.emotion && transform_jsx && uses_css_prop(&self.module) => {
+          emotion_options = EmitOptions {
+            jsx_runtime: JsxRuntime::Automatic,
+            jsx_import_source: styled.emotion_import_source.clone(),
+            ..options.clone()
+          };
+          &emotion_options
+        }
+        _ => options,
+      };
+      let (aleph_jsx_fold, aleph_jsx_builtin_resolve_fold) = aleph_jsx_fold(
+        resolver.clone(),
+        self.source_map.clone(),
+        options.is_dev,
+        options.jsx_source,
+        options.strip_data_test_attrs && !options.is_dev,
+      );
+      // the modules can opt out of fast refresh with `/* @refresh skip */` or the option
+      let refresh_directive = parse_refresh_directive(&self.comments);
+      let fast_refresh = options.is_dev
+        && options.refresh.enabled
+        && refresh_directive != Some(RefreshDirective::Skip)
+        && !specifier_is_remote
+        && !transpile_only;
       let root_mark = Mark::fresh(Mark::root());
+      let is_commonjs = options.module_type == ModuleType::CommonJs;
+      let is_systemjs = options.module_type == ModuleType::SystemJs;
+      let features = TargetFeatures::of_target(options.target).and(&options.features);
+      let lower_features = features != TargetFeatures::default();
+      // the native class fields always have the define semantics
+      let lower_class_fields = options.target < JscTarget::Es2020
+        || options.lower_class_fields
+        || !options.use_define_for_class_fields;
+      let lower_top_level_await = options.lower_top_level_await || !features.top_level_await;
+      let lower_decorators = !transpile_only && has_decorators(&self.module);
+      let hmr_runtime_url = format!(
+        "{}/framework/core/hmr.ts",
+        resolver.borrow().get_aleph_pkg_uri()
+      );
+      let mut define = options.define.clone();
+      if let Some(target_env) = &options.target_env {
+        define
+          .entry("import.meta.env.SSR".into())
+          .or_insert_with(|| (*target_env == TargetEnv::Deno).to_string());
+      }
+      let define_fold = define_fold(&define, &self.source_map)?;
+      // the factories of the classic JSX runtime are referenced after the JSX is transformed
+      let jsx_bindings: Vec<String> = match options.jsx_runtime {
+        JsxRuntime::Classic if transform_jsx => {
+          [&options.jsx_factory, &options.jsx_fragment_factory]
+            .iter()
+            .filter_map(|factory| factory.split('.').next())
+            .map(|name| name.to_owned())
+            .collect()
+        }
+        _ => vec![],
+      };
+      let used_exports = options.used_exports.clone().unwrap_or_default();
+      let keep_names = options.keep_fn_names || options.keep_class_names;
+      let (names_collector, keep_names_fold) =
+        keep_names_fold(options.keep_fn_names, options.keep_class_names);
       let mut passes = chain!(
+        Optional::new(names_collector, keep_names),
+        // before the imports are resolved and the types are stripped
+        Optional::new(import_equals_fold(), is_ts),
+        // before the simplifier drops the directives of the functions
+        Optional::new(
+          strip_server_exports_fold(),
+          options.strip_server_exports
+            && options.target_env == Some(TargetEnv::Browser)
+            && !transpile_only
+        ),
+        Optional::new(
+          chain!(define_fold, expr_simplifier(), dead_branch_remover()),
+          !define.is_empty()
+        ),
+        Optional::new(
+          drop_fold(
+            options.drop_console,
+            options.drop_debugger,
+            options.strip_calls.clone()
+          ),
+          (options.drop_console || options.drop_debugger || !options.strip_calls.is_empty())
+            && !options.is_dev
+        ),
+        // after the dead branches are removed, before the dropped imports are resolved
+        Optional::new(
+          tree_shake_fold(
+            resolver.clone(),
+            &used_exports,
+            jsx_bindings,
+            &options.side_effect_free_deps
+          ),
+          options.used_exports.is_some() && !transpile_only
+        ),
+        // after the dead branches are removed, before the spans of the source are lost
+        Optional::new(
+          coverage_fold(
+            resolver.clone(),
+            self.source_map.clone(),
+            self.specifier.as_str()
+          ),
+          options.coverage && !transpile_only
+        ),
+        Optional::new(
+          styled_components_fold(
+            self.specifier.as_str(),
+            options.styled.clone().unwrap_or_default()
+          ),
+          options.styled.is_some() && !transpile_only
+        ),
+        Optional::new(
+          css_import_fold(
+            resolver.clone(),
+            options.extract_css,
+            options.is_dev,
+            options.css_modules.clone()
+          ),
+          (options.extract_css || !options.css_modules.is_empty()) && !transpile_only
+        ),
+        // after the CSS imports that add the `applyCSS` import
+        Optional::new(
+          scoped_css_fold(resolver.clone(), options.is_dev),
+          options.scoped_css && !transpile_only
+        ),
+        // before the `.json` imports are resolved as the modules
+        Optional::new(
+          inline_import_fold(
+            resolver.clone(),
+            options.inline_imports.clone(),
+            options.inline_limit
+          ),
+          !options.inline_imports.is_empty() && !transpile_only
+        ),
+        Optional::new(asset_import_fold(resolver.clone()), !transpile_only),
+        Optional::new(
+          json_module_fold(resolver.clone(), options.json_modules.clone()),
+          !options.json_modules.is_empty() && !transpile_only
+        ),
+        Optional::new(
+          const_enum_fold(resolver.clone(), options.const_enums.clone()),
+          is_ts && !transpile_only
+        ),
+        Optional::new(
+          import_glob_fold(options.glob_files.clone()),
+          !options.glob_files.is_empty() && !transpile_only
+        ),
+        Optional::new(
+          import_meta_fold(
+            options
+              .import_meta_url
+              .clone()
+              .unwrap_or_else(|| self.specifier.clone()),
+            options.target_env == Some(TargetEnv::Browser),
+          ),
+          !transpile_only
+        ),
         Optional::new(
           resolve_fold(
             resolver.clone(),
             self.source_map.clone(),
+            self.comments.clone(),
             options.resolve_star_exports,
+            options.target_env == Some(TargetEnv::Browser),
+            options.lazy_preload,
           ),
           !transpile_only
         ),
-        Optional::new(aleph_jsx_fold, is_jsx && !transpile_only),
-        Optional::new(aleph_jsx_builtin_resolve_fold, is_jsx && !transpile_only),
+        Optional::new(aleph_jsx_fold, transform_jsx),
+        Optional::new(aleph_jsx_builtin_resolve_fold, transform_jsx),
         Optional::new(
-          react_refresh_fold(
-            "$RefreshReg$",
-            "$RefreshSig$",
-            false,
-            self.source_map.clone()
-          ),
+          display_name_fold(),
           options.is_dev && !specifier_is_remote && !transpile_only
         ),
+        Optional::new(
+          chain!(
+            react_refresh_fold(
+              &options.refresh,
+              false,
+              refresh_directive == Some(RefreshDirective::Reset)
+            ),
+            Optional::new(
+              import_resolve_fold(
+                resolver.clone(),
+                options
+                  .refresh
+                  .runtime_import
+                  .as_ref()
+                  .map_or("", |url| url.as_str())
+              ),
+              options.refresh.runtime_import.is_some()
+            )
+          ),
+          fast_refresh
+        ),
+        // after the refresh registrations to make `import.meta.hot.accept()` the last statement
+        Optional::new(
+          chain!(
+            hmr_fold(resolver.clone(), fast_refresh),
+            import_resolve_fold(resolver.clone(), hmr_runtime_url.as_str())
+          ),
+          options.hmr && options.is_dev && !specifier_is_remote && !transpile_only
+        ),
+        // before the static JSX is hoisted, as the text children are normalized
+        Optional::new(
+          jsx_whitespace_fold(options.jsx_whitespace, options.jsx_drop_empty_children),
+          transform_jsx
+            && (options.jsx_whitespace != JsxWhitespace::Collapse
+              || options.jsx_drop_empty_children)
+        ),
+        Optional::new(
+          jsx_hoist_fold(),
+          transform_jsx && options.hoist_static_jsx && !options.is_dev && !transpile_only
+        ),
+        // after the static JSX is hoisted, the extracted props are moved to the children
+        Optional::new(
+          jsx_args_fold(options.jsx_key, options.jsx_ref),
+          transform_jsx
+            && options.jsx_runtime == JsxRuntime::Classic
+            && (options.jsx_key == JsxPropMode::Arg || options.jsx_ref == JsxPropMode::Arg)
+        ),
         Optional::new(
           react::jsx(
             self.source_map.clone(),
             Some(&self.comments),
-            react::Options {
-              pragma: options.jsx_factory.clone(),
-              pragma_frag: options.jsx_fragment_factory.clone(),
-              // this will use `Object.assign()` instead of the `_extends` helper when spreading props.
-              use_builtins: true,
-              ..Default::default()
-            },
+            jsx_options(options),
           ),
-          is_jsx && !transpile_only
+          transform_jsx
+        ),
+        Optional::new(
+          jsx_runtime_resolve_fold(resolver.clone(), options.jsx_import_source.as_str()),
+          transform_jsx && options.jsx_runtime == JsxRuntime::Automatic
+        ),
+        Optional::new(
+          pure_annotation_fold(self.comments.clone()),
+          !options.is_dev && !transpile_only
         ),
         Optional::new(
           decorators::decorators(decorators::Config {
             legacy: true,
-            emit_metadata: false
+            emit_metadata: options.emit_decorator_metadata
           }),
-          !transpile_only
+          lower_decorators
+        ),
+        Optional::new(nullish_coalescing(), !features.nullish_coalescing),
+        Optional::new(optional_chaining(), !features.optional_chaining),
+        Optional::new(
+          class_properties(),
+          lower_class_fields && options.use_define_for_class_fields
         ),
-        Optional::new(es2020(), options.target < JscTarget::Es2020),
+        Optional::new(
+          typescript_class_properties(),
+          lower_class_fields && !options.use_define_for_class_fields
+        ),
+        Optional::new(export_namespace_from(), !features.export_namespace_from),
         Optional::new(strip(), is_ts),
-        Optional::new(es2018(), options.target < JscTarget::Es2018),
-        Optional::new(es2017(), options.target < JscTarget::Es2017),
-        Optional::new(es2016(), options.target < JscTarget::Es2016),
+        Optional::new(top_level_await_fold(), lower_top_level_await,),
+        Optional::new(object_rest_spread(), !features.object_rest_spread),
+        Optional::new(optional_catch_binding(), !features.optional_catch_binding),
+        Optional::new(async_to_generator(), !features.async_functions),
+        Optional::new(exponentation(), !features.exponentiation),
         Optional::new(
           es2015(root_mark, Default::default()),
           options.target < JscTarget::Es2015
         ),
         Optional::new(compat_fixer_fold(), options.target < JscTarget::Es2015),
+        Optional::new(
+          chain!(resolver_with_mark(root_mark), import_analyzer()),
+          is_commonjs
+        ),
         Optional::new(
           helpers::inject_helpers(),
-          options.target < JscTarget::Es2020
+          lower_features || lower_class_fields || is_commonjs
+        ),
+        // the helpers import is resolved through the import map
+        Optional::new(
+          import_resolve_fold(resolver.clone(), "@swc/helpers"),
+          options.external_helpers
         ),
-        Optional::new(hygiene(), options.target < JscTarget::Es2020),
+        Optional::new(common_js(root_mark, Default::default()), is_commonjs),
+        Optional::new(
+          chain!(resolver_with_mark(root_mark), system_js_fold()),
+          is_systemjs
+        ),
+        Optional::new(
+          chain!(
+            scope_resolver(),
+            simplifier(Default::default()),
+            mangle_fold()
+          ),
+          options.minify
+        ),
+        // the identifiers generated by the decorators transform need hygiene to be renamed.
+        Optional::new(
+          hygiene(),
+          lower_decorators
+            || lower_features
+            || lower_class_fields
+            || lower_top_level_await
+            || options.minify
+            || is_commonjs
+            || is_systemjs
+        ),
+        Optional::new(keep_names_fold, keep_names),
         fixer(Some(&self.comments)),
       );
 
-      self.apply_transform(&mut passes, options.source_map)
+      let program = self.fold_program(&mut passes, options.external_helpers);
+      if options.comments != CommentsMode::All {
+        strip_comments(&self.comments);
+      }
+      let emit_start = now();
+      stats.transform_ms = emit_start - start;
+      let output = self.emit_program(
+        program,
+        &options.source_map,
+        options.source_map_options(),
+        options.minify,
+      );
+      stats.emit_ms = now() - emit_start;
+      output
     })
   }
 
+  /// reports the errors of the checks, e.g. the constructs that can't be transpiled
+  /// file by file.
+  fn report_errors(&self, errors: Vec<(Span, String)>) -> Result<(), anyhow::Error> {
+    if errors.is_empty() {
+      return Ok(());
+    }
+    let error_buffer = ErrorBuffer::new();
+    let handler = Handler::with_emitter_and_flags(
+      Box::new(error_buffer.clone()),
+      HandlerFlags {
+        dont_buffer_diagnostics: true,
+        ..HandlerFlags::default()
+      },
+    );
+    for (span, message) in errors {
+      handler.struct_span_err(span, &message).emit();
+    }
+    Err(
+      DiagnosticBuffer::from_error_buffer(error_buffer, DiagnosticCode::TransformError, |span| {
+        self.source_map.lookup_char_pos(span.lo)
+      })
+      .into(),
+    )
+  }
+
+  /// checks the imports of the module, returns the warnings that don't fail the
+  /// transform, like the unused import bindings.
+  pub fn check_imports(
+    &self,
+    resolver: &Resolver,
+    options: &EmitOptions,
+  ) -> Vec<DiagnosticMessage> {
+    let pragma_options = apply_jsx_pragmas(&self.comments, &self.module, options);
+    let options = pragma_options.as_ref().unwrap_or(options);
+    // the classic runtime uses the factories implicitly, like `React.createElement`
+    let jsx_names: Vec<String> = match options.jsx_runtime {
+      JsxRuntime::Classic => vec![
+        options.jsx_factory.as_str(),
+        options.jsx_fragment_factory.as_str(),
+      ]
+      .into_iter()
+      .filter_map(|factory| factory.split('.').next())
+      .map(|name| name.into())
+      .collect(),
+      JsxRuntime::Automatic => vec![],
+    };
+    let mut warnings = check_imports(
+      &self.module,
+      resolver.specifier.as_str(),
+      |url| resolver.fix_url(url),
+      &jsx_names,
+    );
+    if options.target_env == Some(TargetEnv::Browser) {
+      warnings.extend(check_node_builtins(&self.module, |url| {
+        resolver.fix_url(url)
+      }));
+    }
+    warnings.extend(check_unpinned_imports(&self.module, |url| {
+      resolver.fix_url(url)
+    }));
+    warnings.sort_by_key(|(span, _)| span.lo);
+    warnings
+      .into_iter()
+      .map(|(span, message)| {
+        DiagnosticMessage::from_span(
+          span,
+          self.source_map.lookup_char_pos(span.lo),
+          DiagnosticCode::Warning,
+          message,
+        )
+      })
+      .collect()
+  }
+
   pub fn parse_export_names(&self) -> Result<Vec<String>, anyhow::Error> {
     let program = Program::Module(self.module.clone());
     let mut parser = ExportsParser { names: vec![] };
@@ -217,18 +1015,43 @@ impl SWC {
     Ok(parser.names)
   }
 
-  /// Apply transform with fold.
+  /// Apply transform with fold, the `external_helpers` makes the helpers imported
+  /// from `@swc/helpers` instead of inlining them.
   pub fn apply_transform<T: Fold>(
     &self,
-    mut tr: T,
-    source_map: bool,
+    tr: T,
+    source_map: &SourceMapMode,
+    source_map_options: SourceMapOptions,
+    minify: bool,
+    external_helpers: bool,
   ) -> Result<(String, Option<String>), anyhow::Error> {
+    let program = self.fold_program(tr, external_helpers);
+    self.emit_program(program, source_map, source_map_options, minify)
+  }
+
+  /// Apply the fold to the module.
+  pub fn fold_program<T: Fold>(&self, mut tr: T, external_helpers: bool) -> Program {
     let program = Program::Module(self.module.clone());
-    let program =
-      helpers::HELPERS.set(&helpers::Helpers::new(false), || program.fold_with(&mut tr));
+    helpers::HELPERS.set(&helpers::Helpers::new(external_helpers), || {
+      program.fold_with(&mut tr)
+    })
+  }
+
+  /// Emit the code of the program with the source map.
+  pub fn emit_program(
+    &self,
+    program: Program,
+    source_map: &SourceMapMode,
+    source_map_options: SourceMapOptions,
+    minify: bool,
+  ) -> Result<(String, Option<String>), anyhow::Error> {
+    let assertions = match &program {
+      Program::Module(module) => collect_import_assertions(module),
+      _ => vec![],
+    };
     let mut buf = Vec::new();
     let mut src_map_buf = Vec::new();
-    let src_map = if source_map {
+    let src_map = if *source_map != SourceMapMode::None {
       Some(&mut src_map_buf)
     } else {
       None
@@ -241,9 +1064,7 @@ impl SWC {
         src_map,
       ));
       let mut emitter = swc_ecmascript::codegen::Emitter {
-        cfg: swc_ecmascript::codegen::Config {
-          minify: false, // todo: use swc minify in the future, currently use terser
-        },
+        cfg: swc_ecmascript::codegen::Config { minify },
         comments: Some(&self.comments),
         cm: self.source_map.clone(),
         wr: writer,
@@ -252,55 +1073,280 @@ impl SWC {
     }
 
     // output
-    let src = String::from_utf8(buf).unwrap();
-    if source_map {
-      let mut buf = Vec::new();
-      self
-        .source_map
-        .build_source_map_from(&mut src_map_buf, None)
-        .to_writer(&mut buf)
-        .unwrap();
-      Ok((src, Some(String::from_utf8(buf).unwrap())))
-    } else {
+    let mut src = String::from_utf8(buf).unwrap();
+    if !assertions.is_empty() {
+      src = restore_import_assertions(src.as_str(), &assertions, minify);
+    }
+    if *source_map == SourceMapMode::None {
+      return Ok((src, None));
+    }
+    let mut map = self.source_map.build_source_map(&mut src_map_buf);
+    if let Some(input_source_map) = source_map_options.input_source_map {
+      let input_source_map = sourcemap::SourceMap::from_slice(input_source_map.as_bytes())
+        .map_err(|err| anyhow::anyhow!("invalid input source map: {}", err))?;
+      map = chain_source_map(&map, &input_source_map);
+    }
+    if !source_map_options.inline_sources_content {
+      for src_id in 0..map.get_source_count() {
+        map.set_source_contents(src_id, None);
+      }
+    }
+    let mut buf = Vec::new();
+    map.to_writer(&mut buf).unwrap();
+    // the `sourcemap` crate can't write the `sourceRoot`, add it to the JSON.
+    if let Some(source_root) = source_map_options.source_root {
+      let mut json: serde_json::Value = serde_json::from_slice(&buf).unwrap();
+      json["sourceRoot"] = source_root.into();
+      buf = serde_json::to_vec(&json).unwrap();
+    }
+    if *source_map == SourceMapMode::Inline {
+      src.push_str("\n//# sourceMappingURL=data:application/json;base64,");
+      src.push_str(base64::encode(buf).as_str());
       Ok((src, None))
+    } else {
+      Ok((src, Some(String::from_utf8(buf).unwrap())))
+    }
+  }
+}
+
+/// the max times that the tolerant parser blanks out a line and parses again.
+const MAX_RECOVERY_ATTEMPTS: usize = 16;
+
+/// parses the module, returns the module or the fatal error, with the errors that
+/// the parser recovered from.
+fn parse_source(
+  specifier: &str,
+  source: &str,
+  source_type: &SourceType,
+  features: &ParserFeatures,
+  source_map: &SourceMap,
+  comments: &SingleThreadedComments,
+) -> (Result<Module, ParseError>, Vec<ParseError>) {
+  let source_file = source_map.new_source_file(
+    FileName::Real(Path::new(specifier).to_path_buf()),
+    source.into(),
+  );
+  let syntax = get_syntax(source_type, features);
+  let input = StringInput::from(&*source_file);
+  let lexer = Lexer::new(syntax, JscTarget::Es2020, input, Some(comments));
+  let mut parser = swc_ecmascript::parser::Parser::new_from(lexer);
+  let module = parser.parse_module();
+  (module, parser.take_errors())
+}
+
+/// checks the syntax that is disabled by the parser features but always parsed by the
+/// TS parser, like the top-level `await`.
+fn check_disabled_syntax(
+  module: &Module,
+  source_type: &SourceType,
+  features: &ParserFeatures,
+) -> Option<(Span, String)> {
+  let is_ts = matches!(source_type, SourceType::TS | SourceType::TSX);
+  if !is_ts || features.top_level_await {
+    return None;
+  }
+  parse_metadata(module).top_level_await_span.map(|span| {
+    (
+      span,
+      "the top-level `await` is disabled by the `topLevelAwait` parser option".into(),
+    )
+  })
+}
+
+/// checks if the module has any decorator to be lowered.
+fn has_decorators(module: &Module) -> bool {
+  let mut finder = DecoratorsFinder { found: false };
+  module.clone().fold_with(&mut finder);
+  finder.found
+}
+
+struct DecoratorsFinder {
+  found: bool,
+}
+
+impl Fold for DecoratorsFinder {
+  noop_fold_type!();
+
+  fn fold_decorator(&mut self, decorator: Decorator) -> Decorator {
+    self.found = true;
+    decorator
+  }
+}
+
+/// replaces the code of the line at the `offset` with the spaces, or the previous
+/// line that is not blank if the line is blank, e.g. the error of an unclosed block
+/// is at the end of the source. with `previous`, the line before the line at the
+/// `offset` is blanked out. returns `false` if there is no line to blank out.
+fn blank_line(source: &mut String, offset: usize, previous: bool) -> bool {
+  let mut end = min(offset, source.len());
+  if previous {
+    match source[..end].rfind('\n') {
+      Some(i) => end = i,
+      None => return false,
+    }
+  }
+  loop {
+    let start = source[..end].rfind('\n').map_or(0, |i| i + 1);
+    let line_end = source[end..].find('\n').map_or(source.len(), |i| end + i);
+    let line = &source[start..line_end];
+    if !line.trim().is_empty() {
+      let blank: String = line.chars().map(|c| " ".repeat(c.len_utf8())).collect();
+      source.replace_range(start..line_end, blank.as_str());
+      return true;
     }
+    if start == 0 {
+      return false;
+    }
+    end = start - 1;
+  }
+}
+
+/// maps the positions of the `map` back to the original sources of the `input` map.
+fn chain_source_map(
+  map: &sourcemap::SourceMap,
+  input: &sourcemap::SourceMap,
+) -> sourcemap::SourceMap {
+  let mut builder = SourceMapBuilder::new(None);
+  for token in map.tokens() {
+    if let Some(orig) = input.lookup_token(token.get_src_line(), token.get_src_col()) {
+      let raw = builder.add(
+        token.get_dst_line(),
+        token.get_dst_col(),
+        orig.get_src_line(),
+        orig.get_src_col(),
+        orig.get_source(),
+        orig.get_name().or_else(|| token.get_name()),
+      );
+      if orig.has_source() && !builder.has_source_contents(raw.src_id) {
+        builder.set_source_contents(raw.src_id, input.get_source_contents(orig.get_src_id()));
+      }
+    }
+  }
+  builder.into_sourcemap()
+}
+
+/// the options of the react JSX transform.
+pub fn jsx_options(options: &EmitOptions) -> react::Options {
+  react::Options {
+    // the `Runtime` type of swc is private, create it from the string form.
+    runtime: Some(
+      Deserialize::deserialize(IntoDeserializer::<ValueError>::into_deserializer(
+        match options.jsx_runtime {
+          JsxRuntime::Classic => "classic",
+          JsxRuntime::Automatic => "automatic",
+        },
+      ))
+      .unwrap(),
+    ),
+    import_source: options.jsx_import_source.clone(),
+    pragma: options.jsx_factory.clone(),
+    pragma_frag: options.jsx_fragment_factory.clone(),
+    // this will use `Object.assign()` instead of the `_extends` helper when spreading props.
+    use_builtins: true,
+    ..Default::default()
   }
 }
 
-fn get_es_config(jsx: bool) -> EsConfig {
+/// The syntax features of the parser, instead of the defaults of the swc version.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "camelCase")]
+pub struct ParserFeatures {
+  /// the decorators, the default (`None`) disables them for JS/JSX and enables them
+  /// for TS/TSX.
+  pub decorators: Option<bool>,
+  /// `import data from "./data.json" assert { type: "json" }`
+  pub import_assertions: bool,
+  /// `export v from "mod"`, the TS parser doesn't support it.
+  pub export_default_from: bool,
+  /// the TS parser always parses the top-level `await`, it's reported as a syntax
+  /// error after the parsing if disabled.
+  pub top_level_await: bool,
+  /// the TS 4.9 `satisfies` operator, the `accessor` fields and the `using`
+  /// declarations are not supported by the parser of this swc version, enabling them
+  /// fails the options with an error that names the syntax instead of the parse
+  /// errors of the modules.
+  pub satisfies: bool,
+  pub accessor: bool,
+  pub explicit_resource_management: bool,
+}
+
+impl Default for ParserFeatures {
+  fn default() -> Self {
+    ParserFeatures {
+      decorators: None,
+      import_assertions: true,
+      export_default_from: true,
+      top_level_await: true,
+      satisfies: false,
+      accessor: false,
+      explicit_resource_management: false,
+    }
+  }
+}
+
+/// deserializes the parser features, the features that the parser doesn't support
+/// are rejected.
+pub fn deserialize_parser_features<'de, D>(deserializer: D) -> Result<ParserFeatures, D::Error>
+where
+  D: serde::Deserializer<'de>,
+{
+  let features = ParserFeatures::deserialize(deserializer)?;
+  let unsupported = [
+    ("satisfies", features.satisfies),
+    ("accessor", features.accessor),
+    (
+      "explicitResourceManagement",
+      features.explicit_resource_management,
+    ),
+  ];
+  match unsupported.iter().find(|(_, enabled)| *enabled) {
+    Some((name, _)) => Err(serde::de::Error::custom(format!(
+      "the `{}` syntax is not supported by the parser of this swc version",
+      name
+    ))),
+    None => Ok(features),
+  }
+}
+
+fn get_es_config(jsx: bool, features: &ParserFeatures) -> EsConfig {
+  let decorators = features.decorators.unwrap_or(false);
   EsConfig {
+    decorators,
+    decorators_before_export: decorators,
     class_private_methods: true,
     class_private_props: true,
     class_props: true,
     dynamic_import: true,
-    export_default_from: true,
+    export_default_from: features.export_default_from,
     export_namespace_from: true,
     import_meta: true,
     jsx,
     nullish_coalescing: true,
     num_sep: true,
     optional_chaining: true,
-    top_level_await: true,
+    top_level_await: features.top_level_await,
+    import_assertions: features.import_assertions,
     ..EsConfig::default()
   }
 }
 
-fn get_ts_config(tsx: bool) -> TsConfig {
+fn get_ts_config(tsx: bool, features: &ParserFeatures) -> TsConfig {
   TsConfig {
     tsx,
-    decorators: true,
+    decorators: features.decorators.unwrap_or(true),
     dynamic_import: true,
+    import_assertions: features.import_assertions,
     ..TsConfig::default()
   }
 }
 
-fn get_syntax(source_type: &SourceType) -> Syntax {
+fn get_syntax(source_type: &SourceType, features: &ParserFeatures) -> Syntax {
   match source_type {
-    SourceType::JS => Syntax::Es(get_es_config(false)),
-    SourceType::JSX => Syntax::Es(get_es_config(true)),
-    SourceType::TS => Syntax::Typescript(get_ts_config(false)),
-    SourceType::TSX => Syntax::Typescript(get_ts_config(true)),
-    _ => Syntax::Es(get_es_config(false)),
+    SourceType::JS => Syntax::Es(get_es_config(false, features)),
+    SourceType::JSX => Syntax::Es(get_es_config(true, features)),
+    SourceType::TS => Syntax::Typescript(get_ts_config(false, features)),
+    SourceType::TSX => Syntax::Typescript(get_ts_config(true, features)),
+    _ => Syntax::Es(get_es_config(false, features)),
   }
 }
 
@@ -309,7 +1355,7 @@ pub fn t<T: Fold>(specifier: &str, source: &str, tr: T, expect: &str) -> bool {
   let module = SWC::parse(specifier, source, None).expect("could not parse module");
   let (code, _) = swc_common::GLOBALS.set(&Globals::new(), || {
     module
-      .apply_transform(tr, false)
+      .apply_transform(tr, &SourceMapMode::None, Default::default(), false, false)
       .expect("could not transpile module")
   });
   let matched = code.as_str().trim().eq(expect.trim());
@@ -331,6 +1377,19 @@ pub fn t<T: Fold>(specifier: &str, source: &str, tr: T, expect: &str) -> bool {
   matched
 }
 
+/// creates the resolver of the `specifier` with the default options for the tests.
+#[allow(dead_code)]
+pub fn test_resolver(specifier: &str) -> Rc<RefCell<Resolver>> {
+  Rc::new(RefCell::new(Resolver::new(
+    specifier,
+    ImportHashMap::default(),
+    None,
+    None,
+    false,
+    vec![],
+  )))
+}
+
 #[allow(dead_code)]
 pub fn st(specifer: &str, source: &str, bundling: bool) -> (String, Rc<RefCell<Resolver>>) {
   let module = SWC::parse(specifer, source, None).expect("could not parse module");
@@ -406,4 +1465,511 @@ mod tests {
     assert!(code.contains("React.createElement(\"h1\", {"));
     assert!(code.contains("className: \"title\""));
   }
+
+  #[test]
+  fn fork_module() {
+    let source = r#"
+      import React from "https://esm.sh/react"
+      export const Logo = React.memo(() => React.createElement("img"))
+    "#;
+    let module = SWC::parse("/components/logo.js", source, None).expect("could not parse module");
+    let forked = module.fork();
+    let new_resolver = || test_resolver("/components/logo.js");
+    let (prod, _) = module
+      .transform(new_resolver(), &EmitOptions::default())
+      .expect("could not transform module");
+    let (dev, _) = forked
+      .transform(
+        new_resolver(),
+        &EmitOptions {
+          is_dev: true,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    assert!(prod.contains("/*#__PURE__*/ React.memo("));
+    assert!(!dev.contains("#__PURE__"));
+  }
+
+  #[test]
+  fn jsx_in_js() {
+    let source = r#"
+      import React from "https://esm.sh/react"
+      export default function App() {
+        return <div className="app" />
+      }
+    "#;
+    let (code, _) = st("/src/App.js", source, false);
+    assert!(code.contains("React.createElement(\"div\", {"));
+  }
+
+  #[test]
+  fn source_map() {
+    let source = r#"
+      const n: number = 1
+      export default n
+    "#;
+    let transform = |source_map: SourceMapMode, inline_sources_content: bool| {
+      let module = SWC::parse("/app.ts", source, None).expect("could not parse module");
+      let resolver = test_resolver("/app.ts");
+      module
+        .transform(
+          resolver,
+          &EmitOptions {
+            source_map,
+            inline_sources_content,
+            source_root: Some("https://example.com/src".into()),
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module")
+    };
+    let (code, map) = transform(SourceMapMode::None, false);
+    assert!(!code.contains("//# sourceMappingURL="));
+    assert!(map.is_none());
+    let (code, map) = transform(SourceMapMode::External, false);
+    assert!(!code.contains("//# sourceMappingURL="));
+    let map = map.unwrap();
+    assert!(map.contains("\"mappings\""));
+    assert!(map.contains("\"sourceRoot\":\"https://example.com/src\""));
+    let map = sourcemap::SourceMap::from_slice(map.as_bytes()).unwrap();
+    assert_eq!(map.get_source_contents(0), None);
+    let (_, map) = transform(SourceMapMode::External, true);
+    let map = sourcemap::SourceMap::from_slice(map.unwrap().as_bytes()).unwrap();
+    assert_eq!(map.get_source_contents(0), Some(source));
+    let (code, map) = transform(SourceMapMode::Inline, false);
+    assert!(code.contains("\n//# sourceMappingURL=data:application/json;base64,"));
+    assert!(map.is_none());
+  }
+
+  #[test]
+  fn input_source_map() {
+    // the input code is generated from `/pages/docs.mdx`
+    let source = "const title = \"Docs\";\nconsole.log(title);\n";
+    let mut builder = SourceMapBuilder::new(None);
+    let raw = builder.add(0, 0, 2, 0, Some("/pages/docs.mdx"), None);
+    builder.set_source_contents(raw.src_id, Some("---\n\n# Docs\n\n<Log />\n"));
+    builder.add(1, 0, 4, 0, Some("/pages/docs.mdx"), None);
+    let mut input_source_map = Vec::new();
+    builder
+      .into_sourcemap()
+      .to_writer(&mut input_source_map)
+      .unwrap();
+    let module = SWC::parse("/pages/docs.js", source, None).expect("could not parse module");
+    let resolver = test_resolver("/pages/docs.js");
+    let (_, map) = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          source_map: SourceMapMode::External,
+          input_source_map: Some(String::from_utf8(input_source_map).unwrap()),
+          inline_sources_content: true,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    let map = sourcemap::SourceMap::from_slice(map.unwrap().as_bytes()).unwrap();
+    assert_eq!(map.get_source(0), Some("/pages/docs.mdx"));
+    assert!(map.get_source_contents(0).unwrap().contains("# Docs"));
+    let token = map.lookup_token(1, 0).unwrap();
+    assert_eq!((token.get_src_line(), token.get_src_col()), (4, 0));
+  }
+
+  #[test]
+  fn parse_error_diagnostics() {
+    let source = "const a = 1\nconst b = ;\n";
+    let err = SWC::parse("/app.ts", source, None).err().unwrap();
+    let buffer = err.downcast::<DiagnosticBuffer>().unwrap();
+    assert_eq!(buffer.diagnostics.len(), 1);
+    let diagnostic = &buffer.diagnostics[0];
+    assert_eq!(diagnostic.file_name, "/app.ts");
+    assert_eq!(diagnostic.line, 2);
+    assert_eq!(diagnostic.column, 10);
+    assert_eq!(diagnostic.length, 1);
+    assert_eq!(diagnostic.code, DiagnosticCode::SyntaxError);
+    assert!(buffer.to_string().ends_with(" at 2:10"));
+  }
+
+  #[test]
+  fn error_code_frame() {
+    let source =
+      "import a from './a.ts'\n\nconst b = 1\nconst c = ;\nconst d = 2\nconst e = 3\nconst f = 4\n";
+    let err = SWC::parse("/app.ts", source, None).err().unwrap();
+    let buffer = err.downcast::<DiagnosticBuffer>().unwrap();
+    let frame = buffer.diagnostics[0].frame.as_ref().unwrap();
+    assert_eq!(
+      frame.iter().map(|line| line.line).collect::<Vec<_>>(),
+      vec![2, 3, 4, 5, 6]
+    );
+    assert_eq!(frame[2].text, "const c = ;");
+    assert_eq!(frame[2].highlight, Some((10, 11)));
+    assert_eq!(frame[1].highlight, None);
+    let json = serde_json::to_value(&buffer).unwrap();
+    assert_eq!(json["diagnostics"][0]["code"], "syntax-error");
+    assert_eq!(
+      json["diagnostics"][0]["frame"][2],
+      serde_json::json!({ "line": 4, "text": "const c = ;", "highlight": [10, 11] })
+    );
+  }
+
+  #[test]
+  fn parse_tolerant() {
+    let source = r#"
+      import React from "https://esm.sh/react"
+      import { useState } from
+      const a = 1 +
+      export default function App() {
+        const [count, setCount] = useState(0)
+        return <p>{count}</p>
+      }
+    "#;
+    let (module, errors) =
+      SWC::parse_tolerant("/pages/index.tsx", source, None, &Default::default());
+    let module = module.expect("could not recover the module");
+    assert_eq!(module.module.body.len(), 2);
+    assert_eq!(
+      errors
+        .iter()
+        .map(|e| (e.line, e.file_name.as_str()))
+        .collect::<Vec<_>>(),
+      vec![(4, "/pages/index.tsx"), (5, "/pages/index.tsx")]
+    );
+
+    // the unclosed block
+    let source = "export const a = 1\nexport function b() {\n  return a\n";
+    let (module, errors) = SWC::parse_tolerant("/app.ts", source, None, &Default::default());
+    assert!(module.is_some());
+    assert!(!errors.is_empty());
+
+    let (module, errors) =
+      SWC::parse_tolerant("/app.ts", "export const a = 1", None, &Default::default());
+    assert!(module.is_some());
+    assert!(errors.is_empty());
+  }
+
+  #[test]
+  fn minify() {
+    let source = r#"
+      import React from "https://esm.sh/react"
+      const unused = () => "unused"
+      function greeting(name: string) {
+        if (false) {
+          console.log("never")
+        }
+        return "Hello " + name + "!"
+      }
+      export default function Index() {
+        return <h1 className="title">{greeting("World")}</h1>
+      }
+    "#;
+    let module = SWC::parse("/pages/index.tsx", source, None).expect("could not parse module");
+    let resolver = test_resolver("/pages/index.tsx");
+    let (code, _) = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          minify: true,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(!code.contains("unused"));
+    assert!(!code.contains("never"));
+    assert!(!code.contains("\n  "));
+    assert!(code.contains("export default function Index(){"));
+  }
+
+  #[test]
+  fn decorators() {
+    let source = r#"
+      @Controller("/users")
+      export class UsersController {
+        constructor(private service: UserService) {}
+        @Get("/:id")
+        find(id: string): User {
+          return this.service.find(id)
+        }
+      }
+    "#;
+    let module = SWC::parse("/controllers/users.ts", source, None).expect("could not parse module");
+    let resolver = test_resolver("/controllers/users.ts");
+    let (code, _) = module
+      .transform(
+        resolver.clone(),
+        &EmitOptions {
+          emit_decorator_metadata: true,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains("\"design:paramtypes\""));
+    // the generated `_dec` identifiers are renamed by hygiene
+    assert!(code.contains("var _class, _dec, _dec1, _dec2;"));
+    assert!(!code.contains("@Get"));
+
+    let source = r#"
+      @observer
+      export class Store {}
+    "#;
+    assert!(SWC::parse("/store.js", source, None).is_err());
+    let module = SWC::parse_with_features(
+      "/store.js",
+      source,
+      None,
+      &ParserFeatures {
+        decorators: Some(true),
+        ..Default::default()
+      },
+    )
+    .expect("could not parse module");
+    let (code, _) = module
+      .transform(resolver, &EmitOptions::default())
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(!code.contains("@observer"));
+  }
+
+  #[test]
+  fn parser_features() {
+    // the TS parser always parses the decorators and the top-level `await`
+    let source = "@observer class Store {}";
+    assert!(SWC::parse("/store.ts", source, None).is_ok());
+    let features = ParserFeatures {
+      decorators: Some(false),
+      ..Default::default()
+    };
+    assert!(SWC::parse_with_features("/store.ts", source, None, &features).is_err());
+    let source = "export const data = await load()";
+    assert!(SWC::parse("/data.ts", source, None).is_ok());
+    let features = ParserFeatures {
+      top_level_await: false,
+      ..Default::default()
+    };
+    let err = SWC::parse_with_features("/data.ts", source, None, &features)
+      .err()
+      .expect("the top-level await should be rejected");
+    assert!(err
+      .to_string()
+      .contains("the top-level `await` is disabled by the `topLevelAwait` parser option"));
+    assert!(SWC::parse_with_features("/data.js", source, None, &features).is_err());
+
+    let source = r#"import data from "./data.json" assert { type: "json" }"#;
+    assert!(SWC::parse("/app.ts", source, None).is_ok());
+    let features = ParserFeatures {
+      import_assertions: false,
+      ..Default::default()
+    };
+    assert!(SWC::parse_with_features("/app.ts", source, None, &features).is_err());
+
+    let source = "export v from './v.js'";
+    assert!(SWC::parse("/app.js", source, None).is_ok());
+    let features = ParserFeatures {
+      export_default_from: false,
+      ..Default::default()
+    };
+    assert!(SWC::parse_with_features("/app.js", source, None, &features).is_err());
+
+    let options: crate::SWCOptions =
+      serde_json::from_str(r#"{ "parser": { "topLevelAwait": false } }"#).unwrap();
+    assert!(!options.parser.top_level_await);
+    assert!(options.parser.import_assertions);
+    let err = serde_json::from_str::<crate::SWCOptions>(r#"{ "parser": { "satisfies": true } }"#)
+      .err()
+      .expect("the satisfies feature should be rejected");
+    assert!(err
+      .to_string()
+      .contains("the `satisfies` syntax is not supported by the parser of this swc version"));
+  }
+
+  #[test]
+  fn commonjs() {
+    let source = r#"
+      import React, { useState } from "https://esm.sh/react"
+      export const version = React.version
+      export default function App() {
+        const [n] = useState(0)
+        return n
+      }
+    "#;
+    let module = SWC::parse("/app.ts", source, None).expect("could not parse module");
+    let resolver = test_resolver("/app.ts");
+    let (code, _) = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          module_type: ModuleType::CommonJs,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains("\"__esModule\""));
+    assert!(code.contains("require(\"./-/esm.sh/react.js\")"));
+    assert!(code.contains("exports.default = App;"));
+    assert!(code.contains("exports.version = version;"));
+    assert!(!code.contains("import "));
+    assert!(!code.contains("export "));
+  }
+
+  #[test]
+  fn external_helpers() {
+    let source = r#"
+      export class App {
+        async load() {
+          return await fetch("/data.json")
+        }
+      }
+    "#;
+    let module = SWC::parse("/app.ts", source, None).expect("could not parse module");
+    let mut imports = HashMap::new();
+    imports.insert(
+      "@swc/helpers".into(),
+      "https://esm.sh/@swc/helpers@0.2.0".into(),
+    );
+    let resolver = Rc::new(RefCell::new(Resolver::new(
+      "/app.ts",
+      ImportHashMap {
+        imports,
+        scopes: HashMap::new(),
+      },
+      None,
+      None,
+      false,
+      vec![],
+    )));
+    let (code, _) = module
+      .transform(
+        resolver.clone(),
+        &EmitOptions {
+          target: JscTarget::Es5,
+          external_helpers: true,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains("from \"./-/esm.sh/@swc/helpers@0.2.0.js\";"));
+    assert!(code.contains("swcHelpers.classCallCheck(this, App)"));
+    assert!(!code.contains("function _classCallCheck"));
+    assert!(resolver
+      .borrow()
+      .dep_graph
+      .iter()
+      .any(|dep| dep.specifier == "https://esm.sh/@swc/helpers@0.2.0"));
+  }
+
+  #[test]
+  fn class_fields() {
+    let source = r#"
+      export class Counter {
+        count = 0;
+        #step = 1;
+        static initial = 0;
+        inc() {
+          this.count += this.#step;
+        }
+      }
+    "#;
+    let transform = |options: EmitOptions| {
+      let module = SWC::parse("/counter.js", source, None).expect("could not parse module");
+      let resolver = test_resolver("/counter.js");
+      let (code, _) = module
+        .transform(resolver, &options)
+        .expect("could not transform module");
+      println!("{}", code);
+      code
+    };
+
+    let code = transform(EmitOptions::default());
+    assert!(code.contains("count = 0;"));
+    assert!(code.contains("#step=1;"));
+
+    let code = transform(EmitOptions {
+      lower_class_fields: true,
+      ..Default::default()
+    });
+    assert!(code.contains("_defineProperty(this, \"count\", 0)"));
+    assert!(code.contains("_step.set(this, {"));
+    assert!(code.contains("_defineProperty(Counter, \"initial\", 0)"));
+
+    let code = transform(EmitOptions {
+      use_define_for_class_fields: false,
+      ..Default::default()
+    });
+    assert!(code.contains("this.count = 0;"));
+    assert!(code.contains("Counter.initial = 0;"));
+    assert!(!code.contains("_defineProperty(this, \"count\""));
+  }
+
+  #[test]
+  fn target_env() {
+    let source = r#"
+      export default function Index() {
+        const version = useDeno(() => Deno.version.deno)
+        if (import.meta.env.SSR) {
+          console.log(Deno.env.get("SECRET"))
+        }
+        return version
+      }
+    "#;
+    let transform = |target_env: Option<TargetEnv>| {
+      let module = SWC::parse("/pages/index.ts", source, None).expect("could not parse module");
+      let resolver = test_resolver("/pages/index.ts");
+      let (code, _) = module
+        .transform(
+          resolver.clone(),
+          &EmitOptions {
+            target_env,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      assert_eq!(resolver.borrow().deno_hooks.len(), 1);
+      code
+    };
+
+    let code = transform(Some(TargetEnv::Browser));
+    assert!(!code.contains("Deno."));
+    assert!(code.contains("useDeno(null, 0, \"useDeno-"));
+
+    let code = transform(Some(TargetEnv::Deno));
+    assert!(code.contains("Deno.version.deno"));
+    assert!(code.contains("Deno.env.get(\"SECRET\")"));
+    assert!(!code.contains("import.meta.env.SSR"));
+
+    let code = transform(None);
+    assert!(code.contains("Deno.version.deno"));
+    assert!(code.contains("if (import.meta.env.SSR)"));
+  }
+
+  #[test]
+  fn preserve_jsx() {
+    let source = r#"
+      import React from "https://esm.sh/react"
+      import Logo from "../components/logo.tsx"
+      export default function Index({ title }: { title: string }) {
+        return <><Logo /><h1 className="title">{title}</h1></>
+      }
+    "#;
+    let module = SWC::parse("/pages/index.tsx", source, None).expect("could not parse module");
+    let resolver = test_resolver("/pages/index.tsx");
+    let (code, _) = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          jsx: JsxMode::Preserve,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains("<h1 className=\"title\">{title}</h1>"));
+    assert!(code.contains("<Logo"));
+    assert!(!code.contains("React.createElement"));
+    assert!(!code.contains("title: string"));
+    assert!(code.contains("import Logo from \"../components/logo.js#/components/logo.tsx@000000\""));
+  }
 }
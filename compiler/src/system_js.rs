@@ -0,0 +1,662 @@
+use crate::ast_utils::{new_assign_stmt, new_call, new_expr_stmt, new_member, new_str};
+use crate::metadata::parse_metadata;
+
+use indexmap::IndexMap;
+use std::collections::HashMap;
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::{find_ids, id, private_ident, quote_ident, Id};
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// Creates a fold that converts the module into the `System.register` format of
+/// SystemJS, for the legacy browsers without the native modules (the `nomodule`
+/// fallback). The imports are assigned by the setters of the dependencies, which
+/// are the resolved import urls, and the exports are published by `_export` when
+/// they are declared or assigned. The top-level bindings are hoisted as the `var`s of
+/// the `System.register` function, like SystemJS and babel do, the functions are
+/// declared there and the other declarations are assigned by `execute`:
+///
+/// ```js
+/// import React from "../-/esm.sh/react.js"
+/// export let count = 0
+/// export function inc() { count++ }
+/// ```
+///
+/// is transformed to:
+///
+/// ```js
+/// System.register(["../-/esm.sh/react.js"], function(_export, _context) {
+///   "use strict";
+///   var React, count;
+///   function inc() { _export("count", count + 1), count++; }
+///   _export("inc", inc);
+///   return {
+///     setters: [function(_m) { React = _m.default; }],
+///     execute: function() {
+///       count = 0;
+///       _export("count", count);
+///     }
+///   };
+/// });
+/// ```
+///
+/// The `import.meta` and the dynamic imports are rewritten to the `_context` of the
+/// module. The fold requires the resolver to mark the top-level bindings, so the
+/// local bindings that shadow the exports are not published.
+pub fn system_js_fold() -> impl Fold {
+  SystemJsFold {
+    export_ident: private_ident!("_export"),
+    context_ident: private_ident!("_context"),
+    exports: HashMap::new(),
+  }
+}
+
+struct SystemJsFold {
+  export_ident: Ident,
+  context_ident: Ident,
+  /// the export names of the top-level bindings.
+  exports: HashMap<Id, Vec<String>>,
+}
+
+/// The setter of a dependency, which is called with the namespace object of the
+/// dependency when it's evaluated.
+struct Setter {
+  param: Ident,
+  stmts: Vec<Stmt>,
+}
+
+impl SystemJsFold {
+  /// collects the export names of the top-level bindings, the default exports
+  /// without a name are named `_default`.
+  fn collect_exports(&mut self, module: &mut Module) {
+    for item in module.body.iter_mut() {
+      match item {
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => {
+          for ident in get_decl_ids(decl) {
+            self.add_export(&ident, ident.sym.as_ref());
+          }
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(ExportDefaultDecl {
+          decl, ..
+        })) => match decl {
+          DefaultDecl::Fn(FnExpr { ident, .. }) | DefaultDecl::Class(ClassExpr { ident, .. }) => {
+            let ident = ident.get_or_insert_with(|| private_ident!("_default"));
+            self.add_export(ident, "default");
+          }
+          DefaultDecl::TsInterfaceDecl(_) => {}
+        },
+        // match: export { a as b }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(NamedExport {
+          specifiers,
+          src: None,
+          ..
+        })) => {
+          for specifier in specifiers.iter() {
+            if let ExportSpecifier::Named(ExportNamedSpecifier { orig, exported, .. }) = specifier {
+              self.add_export(orig, exported.as_ref().unwrap_or(orig).sym.as_ref());
+            }
+          }
+        }
+        _ => {}
+      }
+    }
+  }
+
+  fn add_export(&mut self, ident: &Ident, name: &str) {
+    self.exports.entry(id(ident)).or_default().push(name.into());
+  }
+
+  /// returns the `_export` calls of the bindings.
+  fn new_export_stmts(&self, idents: &[Ident]) -> Vec<Stmt> {
+    let mut stmts = vec![];
+    for ident in idents {
+      if let Some(names) = self.exports.get(&id(ident)) {
+        for name in names {
+          stmts.push(new_expr_stmt(
+            self.new_export_call(name, Expr::Ident(ident.clone())),
+          ));
+        }
+      }
+    }
+    stmts
+  }
+
+  // match: _export("name", value)
+  fn new_export_call(&self, name: &str, value: Expr) -> Expr {
+    new_call(
+      Expr::Ident(self.export_ident.clone()),
+      vec![Expr::Lit(Lit::Str(new_str(name))), value],
+    )
+  }
+
+  /// hoists the top-level declaration out of `execute`, the function is moved to the
+  /// `System.register` function with its exports, the bindings of the other
+  /// declarations are added to the `vars` and assigned in the `stmts`.
+  fn hoist_decl(
+    &mut self,
+    decl: Decl,
+    vars: &mut Vec<Ident>,
+    hoisted: &mut Vec<Stmt>,
+    stmts: &mut Vec<Stmt>,
+  ) {
+    let ids = get_decl_ids(&decl);
+    match decl {
+      Decl::Fn(_) => {
+        hoisted.push(Stmt::Decl(decl).fold_with(self));
+        hoisted.extend(self.new_export_stmts(&ids));
+        return;
+      }
+      // match: class A {} -> A = class A {}
+      Decl::Class(ClassDecl { ident, class, .. }) => {
+        let class = class.fold_with(self);
+        let expr = Expr::Class(ClassExpr {
+          ident: Some(ident.clone()),
+          class,
+        });
+        stmts.push(new_assign_stmt(ident, expr));
+      }
+      // match: let { a, b } = obj -> ({ a, b } = obj)
+      Decl::Var(var) => {
+        let var = var.fold_with(self);
+        for decl in var.decls {
+          if let Some(init) = decl.init {
+            stmts.push(new_expr_stmt(Expr::Assign(AssignExpr {
+              span: DUMMY_SP,
+              op: AssignOp::Assign,
+              left: PatOrExpr::Pat(Box::new(decl.name)),
+              right: init,
+            })));
+          }
+        }
+      }
+      _ => {
+        stmts.push(Stmt::Decl(decl).fold_with(self));
+        return;
+      }
+    }
+    vars.extend(ids.iter().cloned());
+    stmts.extend(self.new_export_stmts(&ids));
+  }
+
+  fn get_exported_ident<'a>(&self, expr: &'a PatOrExpr) -> Option<&'a Ident> {
+    let ident = match expr {
+      PatOrExpr::Pat(pat) => match pat.as_ref() {
+        Pat::Ident(BindingIdent { id, .. }) => id,
+        Pat::Expr(expr) => match expr.as_ref() {
+          Expr::Ident(ident) => ident,
+          _ => return None,
+        },
+        _ => return None,
+      },
+      PatOrExpr::Expr(expr) => match expr.as_ref() {
+        Expr::Ident(ident) => ident,
+        _ => return None,
+      },
+    };
+    if self.exports.contains_key(&id(ident)) {
+      Some(ident)
+    } else {
+      None
+    }
+  }
+}
+
+impl Fold for SystemJsFold {
+  noop_fold_type!();
+
+  fn fold_module(&mut self, mut module: Module) -> Module {
+    let is_async = parse_metadata(&module).has_top_level_await;
+    self.collect_exports(&mut module);
+
+    let mut setters: IndexMap<String, Setter> = IndexMap::new();
+    // the imports and the top-level bindings
+    let mut vars = vec![];
+    // the function declarations and their exports, which are published before the
+    // execution
+    let mut hoisted = vec![];
+    let mut stmts = vec![];
+    for item in module.body {
+      match item {
+        ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+          specifiers, src, ..
+        })) => {
+          let setter = get_setter(&mut setters, &src);
+          let param = Expr::Ident(setter.param.clone());
+          for specifier in specifiers {
+            let (local, value) = match specifier {
+              ImportSpecifier::Named(ImportNamedSpecifier {
+                local, imported, ..
+              }) => {
+                let name = imported.as_ref().unwrap_or(&local).sym.as_ref().to_owned();
+                (local, new_member(param.clone(), name.as_str()))
+              }
+              ImportSpecifier::Default(ImportDefaultSpecifier { local, .. }) => {
+                (local, new_member(param.clone(), "default"))
+              }
+              ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => {
+                (local, param.clone())
+              }
+            };
+            setter.stmts.push(new_assign_stmt(local.clone(), value));
+            setter
+              .stmts
+              .extend(self.new_export_stmts(std::slice::from_ref(&local)));
+            vars.push(local);
+          }
+        }
+        // match: export { a as b } from "./mod.ts"
+        ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(NamedExport {
+          specifiers,
+          src: Some(src),
+          ..
+        })) => {
+          let setter = get_setter(&mut setters, &src);
+          let param = Expr::Ident(setter.param.clone());
+          for specifier in specifiers {
+            let (name, value) = match specifier {
+              ExportSpecifier::Named(ExportNamedSpecifier { orig, exported, .. }) => (
+                exported.as_ref().unwrap_or(&orig).sym.as_ref().to_owned(),
+                new_member(param.clone(), orig.sym.as_ref()),
+              ),
+              ExportSpecifier::Namespace(ExportNamespaceSpecifier { name, .. }) => {
+                (name.sym.as_ref().to_owned(), param.clone())
+              }
+              ExportSpecifier::Default(ExportDefaultSpecifier { exported }) => (
+                exported.sym.as_ref().to_owned(),
+                new_member(param.clone(), "default"),
+              ),
+            };
+            setter
+              .stmts
+              .push(new_expr_stmt(self.new_export_call(name.as_str(), value)));
+          }
+        }
+        // match: export * from "./mod.ts"
+        ModuleItem::ModuleDecl(ModuleDecl::ExportAll(ExportAll { src, .. })) => {
+          let export_ident = self.export_ident.clone();
+          let setter = get_setter(&mut setters, &src);
+          let stmt = new_star_export_stmt(export_ident, setter.param.clone());
+          setter.stmts.push(stmt);
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => {
+          self.hoist_decl(decl, &mut vars, &mut hoisted, &mut stmts);
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(ExportDefaultDecl {
+          decl, ..
+        })) => match decl {
+          DefaultDecl::Fn(FnExpr {
+            ident: Some(ident),
+            function,
+          }) => {
+            let decl = Decl::Fn(FnDecl {
+              ident,
+              declare: false,
+              function,
+            });
+            self.hoist_decl(decl, &mut vars, &mut hoisted, &mut stmts);
+          }
+          DefaultDecl::Class(ClassExpr {
+            ident: Some(ident),
+            class,
+          }) => {
+            let decl = Decl::Class(ClassDecl {
+              ident,
+              declare: false,
+              class,
+            });
+            self.hoist_decl(decl, &mut vars, &mut hoisted, &mut stmts);
+          }
+          _ => {}
+        },
+        // match: export default expr
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(ExportDefaultExpr {
+          expr, ..
+        })) => {
+          let expr = expr.fold_with(self);
+          stmts.push(new_expr_stmt(self.new_export_call("default", *expr)));
+        }
+        ModuleItem::ModuleDecl(_) => {}
+        // match: function a() {}; export { a as b }
+        ModuleItem::Stmt(Stmt::Decl(decl)) => {
+          self.hoist_decl(decl, &mut vars, &mut hoisted, &mut stmts);
+        }
+        ModuleItem::Stmt(stmt) => stmts.push(stmt.fold_with(self)),
+      }
+    }
+
+    let mut declare_stmts = vec![new_expr_stmt(Expr::Lit(Lit::Str(new_str("use strict"))))];
+    if !vars.is_empty() {
+      declare_stmts.push(Stmt::Decl(Decl::Var(VarDecl {
+        span: DUMMY_SP,
+        kind: VarDeclKind::Var,
+        declare: false,
+        decls: vars
+          .into_iter()
+          .map(|ident| VarDeclarator {
+            span: DUMMY_SP,
+            name: Pat::Ident(BindingIdent {
+              id: ident,
+              type_ann: None,
+            }),
+            init: None,
+            definite: false,
+          })
+          .collect(),
+      })));
+    }
+    declare_stmts.append(&mut hoisted);
+    let (deps, setters): (Vec<String>, Vec<Setter>) = setters.into_iter().unzip();
+    declare_stmts.push(Stmt::Return(ReturnStmt {
+      span: DUMMY_SP,
+      arg: Some(Box::new(Expr::Object(ObjectLit {
+        span: DUMMY_SP,
+        props: vec![
+          new_prop(
+            "setters",
+            Expr::Array(ArrayLit {
+              span: DUMMY_SP,
+              elems: setters
+                .into_iter()
+                .map(|Setter { param, stmts }| {
+                  Some(ExprOrSpread {
+                    spread: None,
+                    expr: Box::new(new_fn_expr(vec![param], stmts, false)),
+                  })
+                })
+                .collect(),
+            }),
+          ),
+          new_prop("execute", new_fn_expr(vec![], stmts, is_async)),
+        ],
+      }))),
+    }));
+
+    // match: System.register([...deps], function(_export, _context) { ... })
+    let register = new_call(
+      new_member(Expr::Ident(quote_ident!("System")), "register"),
+      vec![
+        Expr::Array(ArrayLit {
+          span: DUMMY_SP,
+          elems: deps
+            .iter()
+            .map(|dep| {
+              Some(ExprOrSpread {
+                spread: None,
+                expr: Box::new(Expr::Lit(Lit::Str(new_str(dep)))),
+              })
+            })
+            .collect(),
+        }),
+        new_fn_expr(
+          vec![self.export_ident.clone(), self.context_ident.clone()],
+          declare_stmts,
+          false,
+        ),
+      ],
+    );
+    Module {
+      body: vec![ModuleItem::Stmt(new_expr_stmt(register))],
+      ..module
+    }
+  }
+
+  fn fold_expr(&mut self, expr: Expr) -> Expr {
+    let expr = expr.fold_children_with(self);
+    match expr {
+      // match: count = 1 -> _export("count", count = 1)
+      Expr::Assign(assign) => {
+        let names = match self.get_exported_ident(&assign.left) {
+          Some(ident) => self.exports[&id(ident)].clone(),
+          None => return Expr::Assign(assign),
+        };
+        names.iter().fold(Expr::Assign(assign), |expr, name| {
+          self.new_export_call(name, expr)
+        })
+      }
+      // match: count++ -> (_export("count", count + 1), count++)
+      Expr::Update(update) => {
+        let ident = match update.arg.as_ref() {
+          Expr::Ident(ident) if self.exports.contains_key(&id(ident)) => ident.clone(),
+          _ => return Expr::Update(update),
+        };
+        let names = self.exports[&id(&ident)].clone();
+        if update.prefix {
+          return names.iter().fold(Expr::Update(update), |expr, name| {
+            self.new_export_call(name, expr)
+          });
+        }
+        let op = match update.op {
+          UpdateOp::PlusPlus => BinaryOp::Add,
+          UpdateOp::MinusMinus => BinaryOp::Sub,
+        };
+        let mut exprs: Vec<Box<Expr>> = names
+          .iter()
+          .map(|name| {
+            Box::new(self.new_export_call(
+              name,
+              Expr::Bin(BinExpr {
+                span: DUMMY_SP,
+                op,
+                left: Box::new(Expr::Ident(ident.clone())),
+                right: Box::new(Expr::Lit(Lit::Num(Number {
+                  span: DUMMY_SP,
+                  value: 1.0,
+                }))),
+              }),
+            ))
+          })
+          .collect();
+        exprs.push(Box::new(Expr::Update(update)));
+        Expr::Paren(ParenExpr {
+          span: DUMMY_SP,
+          expr: Box::new(Expr::Seq(SeqExpr {
+            span: DUMMY_SP,
+            exprs,
+          })),
+        })
+      }
+      // match: import.meta -> _context.meta
+      Expr::MetaProp(MetaPropExpr { meta, prop })
+        if meta.sym.eq("import") && prop.sym.eq("meta") =>
+      {
+        new_member(Expr::Ident(self.context_ident.clone()), "meta")
+      }
+      _ => expr,
+    }
+  }
+
+  // match: import("./mod.ts") -> _context.import("./mod.ts")
+  fn fold_call_expr(&mut self, call: CallExpr) -> CallExpr {
+    let call = call.fold_children_with(self);
+    match &call.callee {
+      ExprOrSuper::Expr(callee) => match callee.as_ref() {
+        Expr::Ident(ident) if ident.sym.eq("import") => CallExpr {
+          callee: ExprOrSuper::Expr(Box::new(new_member(
+            Expr::Ident(self.context_ident.clone()),
+            "import",
+          ))),
+          ..call
+        },
+        _ => call,
+      },
+      _ => call,
+    }
+  }
+}
+
+/// returns the setter of the dependency, the dependencies keep the import order.
+fn get_setter<'a>(setters: &'a mut IndexMap<String, Setter>, src: &Str) -> &'a mut Setter {
+  setters
+    .entry(src.value.as_ref().into())
+    .or_insert_with(|| Setter {
+      param: private_ident!("_m"),
+      stmts: vec![],
+    })
+}
+
+fn get_decl_ids(decl: &Decl) -> Vec<Ident> {
+  match decl {
+    Decl::Fn(FnDecl { ident, .. }) | Decl::Class(ClassDecl { ident, .. }) => vec![ident.clone()],
+    Decl::Var(var) => find_ids(&var.decls),
+    _ => vec![],
+  }
+}
+
+// match: for (var _k in _m) if (_k !== "default") _export(_k, _m[_k]);
+fn new_star_export_stmt(export_ident: Ident, param: Ident) -> Stmt {
+  let key = private_ident!("_k");
+  Stmt::ForIn(ForInStmt {
+    span: DUMMY_SP,
+    left: VarDeclOrPat::VarDecl(VarDecl {
+      span: DUMMY_SP,
+      kind: VarDeclKind::Var,
+      declare: false,
+      decls: vec![VarDeclarator {
+        span: DUMMY_SP,
+        name: Pat::Ident(BindingIdent {
+          id: key.clone(),
+          type_ann: None,
+        }),
+        init: None,
+        definite: false,
+      }],
+    }),
+    right: Box::new(Expr::Ident(param.clone())),
+    body: Box::new(Stmt::If(IfStmt {
+      span: DUMMY_SP,
+      test: Box::new(Expr::Bin(BinExpr {
+        span: DUMMY_SP,
+        op: BinaryOp::NotEqEq,
+        left: Box::new(Expr::Ident(key.clone())),
+        right: Box::new(Expr::Lit(Lit::Str(new_str("default")))),
+      })),
+      cons: Box::new(new_expr_stmt(new_call(
+        Expr::Ident(export_ident),
+        vec![
+          Expr::Ident(key.clone()),
+          Expr::Member(MemberExpr {
+            span: DUMMY_SP,
+            obj: ExprOrSuper::Expr(Box::new(Expr::Ident(param))),
+            prop: Box::new(Expr::Ident(key)),
+            computed: true,
+          }),
+        ],
+      ))),
+      alt: None,
+    })),
+  })
+}
+
+fn new_fn_expr(params: Vec<Ident>, stmts: Vec<Stmt>, is_async: bool) -> Expr {
+  Expr::Fn(FnExpr {
+    ident: None,
+    function: Function {
+      params: params
+        .into_iter()
+        .map(|ident| Param {
+          span: DUMMY_SP,
+          decorators: vec![],
+          pat: Pat::Ident(BindingIdent {
+            id: ident,
+            type_ann: None,
+          }),
+        })
+        .collect(),
+      decorators: vec![],
+      span: DUMMY_SP,
+      body: Some(BlockStmt {
+        span: DUMMY_SP,
+        stmts,
+      }),
+      is_generator: false,
+      is_async,
+      type_params: None,
+      return_type: None,
+    },
+  })
+}
+
+fn new_prop(key: &str, value: Expr) -> PropOrSpread {
+  PropOrSpread::Prop(Box::new(Prop::KeyValue(KeyValueProp {
+    key: PropName::Ident(quote_ident!(key)),
+    value: Box::new(value),
+  })))
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::swc::{test_resolver, EmitOptions, ModuleType, SWC};
+
+  #[test]
+  fn system_js() {
+    let source = r#"
+      import React, { useState as useLocalState } from "https://esm.sh/react"
+      import * as util from "./util.ts"
+      import "./style.css"
+      export { util }
+      export * from "./hooks.ts"
+      export { version as v } from "./version.ts"
+      export let count = 0
+      export function inc() {
+        count++
+        let count2 = 0
+        count2 = 1
+      }
+      export function reset() {
+        count = 0
+      }
+      export default function App() {
+        const [state] = useLocalState(0)
+        return React.createElement("div", null, state, import.meta.hot)
+      }
+      export const Lazy = () => import("./lazy.tsx")
+      export const { a, b: [c] } = { a: 1, b: [2] }
+      export class Store {}
+      const local = 1
+      function getLocal() {
+        return local
+      }
+      export { getLocal }
+    "#;
+    let module = SWC::parse("/app.tsx", source, None).expect("could not parse module");
+    let resolver = test_resolver("/app.tsx");
+    let (code, _) = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          module_type: ModuleType::SystemJs,
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.starts_with(
+      "System.register([\n    \"./-/esm.sh/react.js\",\n    \"./util.js#/util.ts@000000\",\n    \"./style.css.js#/style.css@000000\",\n    \"./hooks.js#/hooks.ts@000000\",\n    \"./version.js#/version.ts@000000\"\n], function(_export, _context) {\n    \"use strict\";\n    var React, useLocalState, util, count, Lazy, a, c, Store, local;\n    function inc() {"
+    ));
+    assert!(code.contains("React = _m.default;"));
+    assert!(code.contains("useLocalState = _m.useState;"));
+    assert!(code.contains("util = _m;\n                _export(\"util\", util);"));
+    assert!(code.contains("if (_k !== \"default\") _export(_k, _m[_k]);"));
+    assert!(code.contains("_export(\"v\", _m.version);"));
+    // the functions and their exports are hoisted out of `execute`
+    assert!(code.contains("_export(\"inc\", inc);\n    function reset() {"));
+    assert!(code.contains("_export(\"default\", App);\n    function getLocal() {"));
+    assert!(code.contains("_export(\"getLocal\", getLocal);\n    return {"));
+    assert!(code.contains(
+      "execute: function() {\n            count = 0;\n            _export(\"count\", count);"
+    ));
+    assert!(code.contains("({ a , b: [c]  } = {"));
+    assert!(code.contains("_export(\"a\", a);\n            _export(\"c\", c);"));
+    assert!(code
+      .contains("Store = class Store {\n            };\n            _export(\"Store\", Store);"));
+    assert!(code.contains("local = 1;"));
+    assert!(code.contains("_export(\"count\", count + 1), count++;"));
+    assert!(code.contains("count2 = 1;"));
+    assert!(code.contains("_export(\"count\", count = 0);"));
+    assert!(code.contains("_context.meta.hot"));
+    assert!(code.contains("_context.import(\"./lazy.js#/lazy.tsx@000000\")"));
+    assert!(code.contains("_export(\"Lazy\", Lazy);"));
+    assert!(!code.contains("import "));
+    assert!(!code.contains("export "));
+  }
+}
@@ -0,0 +1,337 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use swc_ecmascript::parser::JscTarget;
+
+/// The syntax features that the target browsers support, the unsupported features
+/// are lowered by their own passes in addition to the features newer than the
+/// `target`. The ES2015 syntax is lowered by the `target` only.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(default, deny_unknown_fields, rename_all = "camelCase")]
+pub struct TargetFeatures {
+  pub nullish_coalescing: bool,
+  pub optional_chaining: bool,
+  pub export_namespace_from: bool,
+  pub object_rest_spread: bool,
+  pub optional_catch_binding: bool,
+  pub async_functions: bool,
+  pub exponentiation: bool,
+  /// the top-level `await`, which is lowered into an async IIFE (see
+  /// `top_level_await_fold`).
+  pub top_level_await: bool,
+}
+
+impl Default for TargetFeatures {
+  fn default() -> Self {
+    TargetFeatures {
+      nullish_coalescing: true,
+      optional_chaining: true,
+      export_namespace_from: true,
+      object_rest_spread: true,
+      optional_catch_binding: true,
+      async_functions: true,
+      exponentiation: true,
+      top_level_await: true,
+    }
+  }
+}
+
+/// the first versions of the browsers that support the features, in the order of
+/// the fields of `TargetFeatures`.
+const BROWSER_VERSIONS: &[(&str, [(u32, u32); 8])] = &[
+  (
+    "chrome",
+    [
+      (80, 0),
+      (80, 0),
+      (72, 0),
+      (60, 0),
+      (66, 0),
+      (55, 0),
+      (52, 0),
+      (89, 0),
+    ],
+  ),
+  (
+    "edge",
+    [
+      (80, 0),
+      (80, 0),
+      (79, 0),
+      (79, 0),
+      (79, 0),
+      (15, 0),
+      (14, 0),
+      (89, 0),
+    ],
+  ),
+  (
+    "firefox",
+    [
+      (72, 0),
+      (74, 0),
+      (80, 0),
+      (55, 0),
+      (58, 0),
+      (52, 0),
+      (52, 0),
+      (89, 0),
+    ],
+  ),
+  (
+    "safari",
+    [
+      (13, 1),
+      (13, 1),
+      (14, 1),
+      (11, 1),
+      (11, 1),
+      (11, 0),
+      (10, 1),
+      (15, 0),
+    ],
+  ),
+  (
+    "ios_saf",
+    [
+      (13, 4),
+      (13, 4),
+      (14, 5),
+      (11, 3),
+      (11, 3),
+      (11, 0),
+      (10, 3),
+      (15, 0),
+    ],
+  ),
+  (
+    "opera",
+    [
+      (67, 0),
+      (67, 0),
+      (60, 0),
+      (47, 0),
+      (53, 0),
+      (42, 0),
+      (39, 0),
+      (75, 0),
+    ],
+  ),
+  (
+    "samsung",
+    [
+      (13, 0),
+      (13, 0),
+      (11, 0),
+      (8, 0),
+      (9, 0),
+      (6, 0),
+      (6, 0),
+      (15, 0),
+    ],
+  ),
+];
+
+impl TargetFeatures {
+  /// returns the features of the ES version. The top-level `await` is an ES2022
+  /// feature, but `Es2020` is the latest target of the swc version and the default,
+  /// so it keeps the top-level `await`, the `targets` lower it for the browsers.
+  pub fn of_target(target: JscTarget) -> Self {
+    TargetFeatures {
+      nullish_coalescing: target >= JscTarget::Es2020,
+      optional_chaining: target >= JscTarget::Es2020,
+      export_namespace_from: target >= JscTarget::Es2020,
+      object_rest_spread: target >= JscTarget::Es2018,
+      optional_catch_binding: target >= JscTarget::Es2018,
+      async_functions: target >= JscTarget::Es2017,
+      exponentiation: target >= JscTarget::Es2016,
+      top_level_await: target >= JscTarget::Es2020,
+    }
+  }
+
+  /// parses the browserslist-like query, like `"safari 13, edge 18"`, the features
+  /// are supported if all the browsers support them. The `ie` supports none of the
+  /// features.
+  pub fn from_query(query: &str) -> Result<Self, String> {
+    let mut features = TargetFeatures::default();
+    for browser in query.split(',').flat_map(|q| q.split(" or ")) {
+      let browser = browser.trim();
+      if browser.is_empty() {
+        continue;
+      }
+      let (name, version) = browser
+        .split_once(char::is_whitespace)
+        .ok_or_else(|| format!("missing the version of '{}'", browser))?;
+      let name = name.to_lowercase();
+      let version = version.trim().trim_start_matches(">=").trim();
+      let version = parse_version(version)
+        .ok_or_else(|| format!("invalid version '{}' of '{}'", version, name))?;
+      let supported = match name.as_str() {
+        "ie" => [false; 8],
+        name => {
+          let name = if name == "ios" { "ios_saf" } else { name };
+          let (_, versions) = BROWSER_VERSIONS
+            .iter()
+            .find(|(browser, _)| *browser == name)
+            .ok_or_else(|| format!("unknown browser '{}'", name))?;
+          let mut supported = [false; 8];
+          for (i, first) in versions.iter().enumerate() {
+            supported[i] = version >= *first;
+          }
+          supported
+        }
+      };
+      features = features.and(&TargetFeatures {
+        nullish_coalescing: supported[0],
+        optional_chaining: supported[1],
+        export_namespace_from: supported[2],
+        object_rest_spread: supported[3],
+        optional_catch_binding: supported[4],
+        async_functions: supported[5],
+        exponentiation: supported[6],
+        top_level_await: supported[7],
+      });
+    }
+    Ok(features)
+  }
+
+  /// returns the features that are supported by both.
+  pub fn and(&self, other: &TargetFeatures) -> Self {
+    TargetFeatures {
+      nullish_coalescing: self.nullish_coalescing && other.nullish_coalescing,
+      optional_chaining: self.optional_chaining && other.optional_chaining,
+      export_namespace_from: self.export_namespace_from && other.export_namespace_from,
+      object_rest_spread: self.object_rest_spread && other.object_rest_spread,
+      optional_catch_binding: self.optional_catch_binding && other.optional_catch_binding,
+      async_functions: self.async_functions && other.async_functions,
+      exponentiation: self.exponentiation && other.exponentiation,
+      top_level_await: self.top_level_await && other.top_level_await,
+    }
+  }
+}
+
+fn parse_version(version: &str) -> Option<(u32, u32)> {
+  let mut parts = version.split('.');
+  let major = parts.next()?.parse().ok()?;
+  let minor = match parts.next() {
+    Some(minor) => minor.parse().ok()?,
+    None => 0,
+  };
+  Some((major, minor))
+}
+
+/// deserializes the targets from a browserslist-like query or the features object.
+pub fn deserialize_targets<'de, D>(deserializer: D) -> Result<Option<TargetFeatures>, D::Error>
+where
+  D: Deserializer<'de>,
+{
+  #[derive(Deserialize)]
+  #[serde(untagged)]
+  enum QueryOrFeatures {
+    Query(String),
+    Features(TargetFeatures),
+  }
+
+  match Option::<QueryOrFeatures>::deserialize(deserializer)? {
+    Some(QueryOrFeatures::Query(query)) => TargetFeatures::from_query(query.as_str())
+      .map(Some)
+      .map_err(|err| serde::de::Error::custom(format!("invalid targets: {}", err))),
+    Some(QueryOrFeatures::Features(features)) => Ok(Some(features)),
+    None => Ok(None),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::swc::{test_resolver, EmitOptions, SWC};
+  use crate::SWCOptions;
+
+  #[test]
+  fn parse_targets() {
+    let features = TargetFeatures::from_query("safari 13, edge >= 18").unwrap();
+    assert_eq!(
+      features,
+      TargetFeatures {
+        nullish_coalescing: false,
+        optional_chaining: false,
+        export_namespace_from: false,
+        object_rest_spread: false,
+        optional_catch_binding: false,
+        async_functions: true,
+        exponentiation: true,
+        top_level_await: false,
+      }
+    );
+    assert_eq!(
+      TargetFeatures::from_query("Chrome 80 or iOS 14").unwrap(),
+      TargetFeatures {
+        export_namespace_from: false,
+        top_level_await: false,
+        ..Default::default()
+      }
+    );
+    assert!(!TargetFeatures::from_query("ie 11").unwrap().exponentiation);
+    assert!(TargetFeatures::from_query("netscape 4").is_err());
+    assert!(TargetFeatures::from_query("safari").is_err());
+
+    let options: SWCOptions = serde_json::from_str(
+      r#"{ "targets": { "optionalChaining": false, "nullishCoalescing": false } }"#,
+    )
+    .unwrap();
+    assert_eq!(
+      options.targets,
+      Some(TargetFeatures {
+        nullish_coalescing: false,
+        optional_chaining: false,
+        ..Default::default()
+      })
+    );
+    let options: SWCOptions = serde_json::from_str(r#"{ "targets": "firefox 72" }"#).unwrap();
+    assert_eq!(
+      options.targets.map(|features| features.optional_chaining),
+      Some(false)
+    );
+    assert!(serde_json::from_str::<SWCOptions>(r#"{ "targets": "netscape 4" }"#).is_err());
+    assert!(
+      serde_json::from_str::<SWCOptions>(r#"{ "targets": { "decorators": false } }"#).is_err()
+    );
+  }
+
+  #[test]
+  fn transform_with_targets() {
+    let source = r#"
+      export const title = (props) => props?.title ?? "untitled"
+      export async function load({ id, ...rest }) {
+        return [await fetch(id), rest, 2 ** 10]
+      }
+    "#;
+    let transform = |features: TargetFeatures| {
+      let module = SWC::parse("/app.js", source, None).expect("could not parse module");
+      let resolver = test_resolver("/app.js");
+      let (code, _) = module
+        .transform(
+          resolver,
+          &EmitOptions {
+            features,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      code
+    };
+    let code = transform(TargetFeatures::default());
+    assert!(code.contains("props?.title ?? \"untitled\""));
+    assert!(code.contains("...rest"));
+    let code = transform(TargetFeatures {
+      optional_chaining: false,
+      ..Default::default()
+    });
+    assert!(!code.contains("?."));
+    assert!(code.contains("??"));
+    let code = transform(TargetFeatures::from_query("safari 10.1").unwrap());
+    assert!(!code.contains("??"));
+    assert!(!code.contains("...rest"));
+    assert!(!code.contains("async function"));
+    assert!(code.contains("2 ** 10"));
+  }
+}
@@ -0,0 +1,455 @@
+use crate::ast_utils::{new_assign_stmt, new_call, new_expr_stmt, new_member};
+use crate::metadata::parse_metadata;
+
+use swc_common::DUMMY_SP;
+use swc_ecma_ast::*;
+use swc_ecma_utils::{find_ids, private_ident, quote_ident};
+use swc_ecma_visit::{noop_fold_type, Fold};
+
+/// Creates a fold that lowers the top-level `await` into an async IIFE for the
+/// runtimes that don't support it, the top-level declarations are hoisted as `let`
+/// bindings (the `const` can't be assigned later) and assigned in the IIFE. The
+/// promise of the IIFE is exported as `__tla`.
+///
+/// As the importers must wait for the `__tla` of their dependencies before they run,
+/// the modules with the static imports are lowered too, their IIFE runs after the
+/// `__tla` of every dependency is settled. The dependencies that are not lowered have
+/// no `__tla`, and the `__tla` of a dependency in an import cycle is not initialized
+/// yet, both are skipped:
+///
+/// ```js
+/// import { load } from "./lib.js"
+/// export const data = await load()
+/// ```
+///
+/// is lowered to:
+///
+/// ```js
+/// import { load } from "./lib.js";
+/// import * as __tla_0 from "./lib.js";
+/// export let data;
+/// export const __tla = Promise.all([
+///   (() => { try { return __tla_0.__tla } catch {} })(),
+/// ]).then(async () => {
+///   data = await load();
+/// });
+/// ```
+pub fn top_level_await_fold() -> impl Fold {
+  TopLevelAwaitFold {}
+}
+
+struct TopLevelAwaitFold {}
+
+impl TopLevelAwaitFold {
+  /// hoists the bindings of the declaration and returns the statement that
+  /// initializes them.
+  fn hoist_decl(&self, decl: Decl, exported: bool, hoisted: &mut Vec<ModuleItem>) -> Option<Stmt> {
+    match decl {
+      Decl::Var(VarDecl { decls, .. }) => {
+        let mut ids: Vec<Ident> = vec![];
+        let mut exprs: Vec<Box<Expr>> = vec![];
+        for VarDeclarator { name, init, .. } in decls {
+          ids.extend(find_ids::<_, Ident>(&name));
+          if let Some(init) = init {
+            exprs.push(Box::new(Expr::Assign(AssignExpr {
+              span: DUMMY_SP,
+              op: AssignOp::Assign,
+              left: PatOrExpr::Pat(Box::new(name)),
+              right: init,
+            })));
+          }
+        }
+        hoisted.push(new_let_decl(ids, exported));
+        match exprs.len() {
+          0 => None,
+          1 => Some(new_expr_stmt(*exprs.pop().unwrap())),
+          _ => Some(new_expr_stmt(Expr::Seq(SeqExpr {
+            span: DUMMY_SP,
+            exprs,
+          }))),
+        }
+      }
+      Decl::Class(ClassDecl { ident, class, .. }) => {
+        hoisted.push(new_let_decl(vec![ident.clone()], exported));
+        Some(new_assign_stmt(
+          ident.clone(),
+          Expr::Class(ClassExpr {
+            ident: Some(ident),
+            class,
+          }),
+        ))
+      }
+      // the function declarations are hoisted as they are
+      decl => {
+        hoisted.push(if exported {
+          ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+            span: DUMMY_SP,
+            decl,
+          }))
+        } else {
+          ModuleItem::Stmt(Stmt::Decl(decl))
+        });
+        None
+      }
+    }
+  }
+
+  /// hoists `export default <expr>` as `export { __default as default }`, the named
+  /// class keeps its binding as `export { Bar as default }`, the `__default` is a
+  /// private ident that doesn't clash with the bindings of the module.
+  fn hoist_default_export(
+    &self,
+    name: Option<Ident>,
+    expr: Expr,
+    hoisted: &mut Vec<ModuleItem>,
+  ) -> Stmt {
+    let default_ident = name.unwrap_or_else(|| private_ident!("__default"));
+    hoisted.push(new_let_decl(vec![default_ident.clone()], false));
+    hoisted.push(ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(
+      NamedExport {
+        span: DUMMY_SP,
+        specifiers: vec![ExportSpecifier::Named(ExportNamedSpecifier {
+          span: DUMMY_SP,
+          orig: default_ident.clone(),
+          exported: Some(quote_ident!("default")),
+        })],
+        src: None,
+        type_only: false,
+        asserts: None,
+      },
+    )));
+    new_assign_stmt(default_ident, expr)
+  }
+}
+
+impl Fold for TopLevelAwaitFold {
+  noop_fold_type!();
+
+  fn fold_module(&mut self, module: Module) -> Module {
+    // the static imports and the re-exports, the types are stripped already
+    let deps: Vec<Str> = module
+      .body
+      .iter()
+      .filter_map(|item| match item {
+        ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+          src,
+          type_only: false,
+          ..
+        }))
+        | ModuleItem::ModuleDecl(ModuleDecl::ExportAll(ExportAll { src, .. }))
+        | ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(NamedExport {
+          src: Some(src),
+          type_only: false,
+          ..
+        })) => Some(src.clone()),
+        _ => None,
+      })
+      .collect();
+    if deps.is_empty() && !parse_metadata(&module).has_top_level_await {
+      return module;
+    }
+
+    let mut hoisted: Vec<ModuleItem> = vec![];
+    // import * as __tla_0 from "./lib.js"
+    let dep_namespaces: Vec<Ident> = deps
+      .into_iter()
+      .enumerate()
+      .map(|(i, src)| {
+        let local = quote_ident!(format!("__tla_{}", i));
+        hoisted.push(ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+          span: DUMMY_SP,
+          specifiers: vec![ImportSpecifier::Namespace(ImportStarAsSpecifier {
+            span: DUMMY_SP,
+            local: local.clone(),
+          })],
+          src,
+          type_only: false,
+          asserts: None,
+        })));
+        local
+      })
+      .collect();
+    let mut stmts: Vec<Stmt> = vec![];
+    for item in module.body {
+      match item {
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => {
+          if let Some(stmt) = self.hoist_decl(decl, true, &mut hoisted) {
+            stmts.push(stmt);
+          }
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(ExportDefaultExpr {
+          expr, ..
+        })) => {
+          stmts.push(self.hoist_default_export(None, *expr, &mut hoisted));
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(ExportDefaultDecl {
+          decl: DefaultDecl::Class(class),
+          ..
+        })) => {
+          stmts.push(self.hoist_default_export(
+            class.ident.clone(),
+            Expr::Class(class),
+            &mut hoisted,
+          ));
+        }
+        ModuleItem::ModuleDecl(decl) => hoisted.push(ModuleItem::ModuleDecl(decl)),
+        ModuleItem::Stmt(Stmt::Decl(decl)) => {
+          if let Some(stmt) = self.hoist_decl(decl, false, &mut hoisted) {
+            stmts.push(stmt);
+          }
+        }
+        ModuleItem::Stmt(stmt) => stmts.push(stmt),
+      }
+    }
+
+    let body = new_arrow(
+      BlockStmt {
+        span: DUMMY_SP,
+        stmts,
+      },
+      true,
+    );
+    let iife = if dep_namespaces.is_empty() {
+      // export const __tla = (async () => { ... })()
+      new_call(new_paren(body), vec![])
+    } else {
+      // export const __tla = Promise.all([...]).then(async () => { ... })
+      let dep_tlas = dep_namespaces
+        .into_iter()
+        .map(|ns| {
+          Some(ExprOrSpread {
+            spread: None,
+            expr: Box::new(new_try_get_tla(ns)),
+          })
+        })
+        .collect();
+      let all = new_call(
+        new_member(Expr::Ident(quote_ident!("Promise")), "all"),
+        vec![Expr::Array(ArrayLit {
+          span: DUMMY_SP,
+          elems: dep_tlas,
+        })],
+      );
+      new_call(new_member(all, "then"), vec![body])
+    };
+    hoisted.push(ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+      span: DUMMY_SP,
+      decl: Decl::Var(VarDecl {
+        span: DUMMY_SP,
+        kind: VarDeclKind::Const,
+        declare: false,
+        decls: vec![VarDeclarator {
+          span: DUMMY_SP,
+          name: Pat::Ident(BindingIdent {
+            id: quote_ident!("__tla"),
+            type_ann: None,
+          }),
+          init: Some(Box::new(iife)),
+          definite: false,
+        }],
+      }),
+    })));
+
+    Module {
+      body: hoisted,
+      ..module
+    }
+  }
+}
+
+/// `(() => { try { return ns.__tla } catch {} })()`, the `__tla` of a module in an
+/// import cycle is in the temporal dead zone.
+fn new_try_get_tla(ns: Ident) -> Expr {
+  let try_stmt = Stmt::Try(TryStmt {
+    span: DUMMY_SP,
+    block: BlockStmt {
+      span: DUMMY_SP,
+      stmts: vec![Stmt::Return(ReturnStmt {
+        span: DUMMY_SP,
+        arg: Some(Box::new(new_member(Expr::Ident(ns), "__tla"))),
+      })],
+    },
+    handler: Some(CatchClause {
+      span: DUMMY_SP,
+      param: None,
+      body: BlockStmt {
+        span: DUMMY_SP,
+        stmts: vec![],
+      },
+    }),
+    finalizer: None,
+  });
+  let arrow = new_arrow(
+    BlockStmt {
+      span: DUMMY_SP,
+      stmts: vec![try_stmt],
+    },
+    false,
+  );
+  new_call(new_paren(arrow), vec![])
+}
+
+fn new_arrow(body: BlockStmt, is_async: bool) -> Expr {
+  Expr::Arrow(ArrowExpr {
+    span: DUMMY_SP,
+    params: vec![],
+    body: BlockStmtOrExpr::BlockStmt(body),
+    is_async,
+    is_generator: false,
+    type_params: None,
+    return_type: None,
+  })
+}
+
+fn new_paren(expr: Expr) -> Expr {
+  Expr::Paren(ParenExpr {
+    span: DUMMY_SP,
+    expr: Box::new(expr),
+  })
+}
+
+fn new_let_decl(ids: Vec<Ident>, exported: bool) -> ModuleItem {
+  let decl = Decl::Var(VarDecl {
+    span: DUMMY_SP,
+    kind: VarDeclKind::Let,
+    declare: false,
+    decls: ids
+      .into_iter()
+      .map(|id| VarDeclarator {
+        span: DUMMY_SP,
+        name: Pat::Ident(BindingIdent { id, type_ann: None }),
+        init: None,
+        definite: false,
+      })
+      .collect(),
+  });
+  if exported {
+    ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl {
+      span: DUMMY_SP,
+      decl,
+    }))
+  } else {
+    ModuleItem::Stmt(Stmt::Decl(decl))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::swc::{test_resolver, EmitOptions, SWC};
+  use crate::{transform, Options};
+
+  #[test]
+  fn lower_top_level_await() {
+    let transform = |source: &str| {
+      let module = SWC::parse("/app.ts", source, None).expect("could not parse module");
+      let resolver = test_resolver("/app.ts");
+      let (code, _) = module
+        .transform(
+          resolver,
+          &EmitOptions {
+            lower_top_level_await: true,
+            ..Default::default()
+          },
+        )
+        .expect("could not transform module");
+      println!("{}", code);
+      code
+    };
+
+    let code = transform(
+      r#"
+      import { load } from "./lib.ts"
+      const { a, b: [c] } = await load()
+      export const data: string = await fetch("/data.json").then(res => res.text()), size = data.length
+      export class Store {}
+      export function get() {
+        return data
+      }
+      console.log(a, c)
+      export default await load()
+    "#,
+    );
+    assert!(code.contains("import { load } from \"./lib.js#/lib.ts@000000\";"));
+    assert!(code.contains("let a, c;"));
+    assert!(code.contains("export let data, size;"));
+    assert!(code.contains("export let Store;"));
+    assert!(code.contains("export function get()"));
+    assert!(code.contains("export { __default as default };"));
+    assert!(code.contains("import * as __tla_0 from \"./lib.js#/lib.ts@000000\";"));
+    assert!(code.contains("export const __tla = Promise.all(["));
+    assert!(code.contains("return __tla_0.__tla;"));
+    assert!(code.contains("]).then(async ()=>{"));
+    assert!(code.contains("({ a , b: [c]  } = await load());"));
+    assert!(code.contains("data = await fetch(\"/data.json\")"));
+    assert!(code.contains("Store = class Store {"));
+    assert!(code.contains("__default = await load();"));
+    assert!(!code.contains("export default"));
+
+    let code = transform(
+      r#"
+      export const run = async () => await load()
+    "#,
+    );
+    assert!(!code.contains("__tla"));
+
+    let code = transform(
+      r#"
+      export const data = await fetch("/data.json")
+    "#,
+    );
+    assert!(code.contains("export const __tla = (async ()=>{"));
+    assert!(!code.contains("Promise.all"));
+
+    // the named class keeps its binding, the `__default` doesn't clash
+    let code = transform(
+      r#"
+      export default class Bar {}
+      new Bar(await fetch("/data.json"))
+    "#,
+    );
+    assert!(code.contains("let Bar;"));
+    assert!(code.contains("export { Bar as default };"));
+    assert!(code.contains("Bar = class Bar {"));
+    assert!(code.contains("new Bar(await fetch(\"/data.json\"))"));
+    let code = transform(
+      r#"
+      const __default = 1
+      export default await fetch("/data.json")
+      console.log(__default)
+    "#,
+    );
+    assert!(code.contains("__default = 1;"));
+    assert!(code.contains("export { __default1 as default };"));
+    assert!(code.contains("__default1 = await fetch(\"/data.json\");"));
+    assert!(code.contains("console.log(__default);"));
+
+    // the importers wait for the `__tla` of the imports
+    let code = transform(
+      r#"
+      import { data } from "./data.ts"
+      export * from "./lib.ts"
+      export const size = data.length
+    "#,
+    );
+    assert!(code.contains("import * as __tla_0 from \"./data.js#/data.ts@000000\";"));
+    assert!(code.contains("import * as __tla_1 from \"./lib.js#/lib.ts@000000\";"));
+    assert!(code.contains("export let size;"));
+    assert!(code.contains("size = data.length;"));
+  }
+
+  #[test]
+  fn lower_top_level_await_by_targets() {
+    let source = r#"
+      export const data = await fetch("/data.json")
+    "#;
+    let options: Options =
+      serde_json::from_str(r#"{"swcOptions":{"targets":"safari 14"}}"#).unwrap();
+    let output = transform("/app.ts", source, &options).expect("could not transform module");
+    assert!(output.code.contains("export const __tla ="));
+
+    let options: Options =
+      serde_json::from_str(r#"{"swcOptions":{"targets":"chrome 90"}}"#).unwrap();
+    let output = transform("/app.ts", source, &options).expect("could not transform module");
+    assert!(!output.code.contains("__tla"));
+  }
+}
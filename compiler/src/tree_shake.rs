@@ -0,0 +1,418 @@
+use crate::pure::SideEffectsChecker;
+use crate::resolve::Resolver;
+
+use std::{cell::RefCell, collections::HashSet, rc::Rc};
+use swc_ecma_ast::*;
+use swc_ecma_utils::{find_ids, id, Id};
+use swc_ecma_visit::{noop_fold_type, Fold, FoldWith};
+
+/// Creates a fold that drops the top-level exports which are not in the `used_exports`
+/// (from the analysis of the module graph), then the top-level bindings and the import
+/// specifiers that are not referenced anymore:
+///
+/// ```js
+/// import { a, b } from "./lib.ts"
+/// const c = () => b()
+/// export const used = a
+/// export const unused = c
+/// ```
+///
+/// is transformed with `["used"]` to:
+///
+/// ```js
+/// import { a } from "./lib.ts"
+/// export const used = a
+/// ```
+///
+/// The exports of `export * from "..."` are kept, the bindings with side effects are
+/// kept as well. The `preserved` names are kept since they are referenced by the
+/// transforms later, like the factory of the classic JSX runtime. An import whose
+/// specifiers are all dropped is kept as `import "./lib.ts"` for the side effects of
+/// the module, unless the module is in the `side_effect_free_deps`.
+pub fn tree_shake_fold(
+  resolver: Rc<RefCell<Resolver>>,
+  used_exports: &[String],
+  preserved: Vec<String>,
+  side_effect_free_deps: &[String],
+) -> impl Fold {
+  TreeShakeFold {
+    resolver,
+    used_exports: used_exports.iter().cloned().collect(),
+    preserved,
+    side_effect_free_deps: side_effect_free_deps.iter().cloned().collect(),
+  }
+}
+
+struct TreeShakeFold {
+  resolver: Rc<RefCell<Resolver>>,
+  used_exports: HashSet<String>,
+  preserved: Vec<String>,
+  side_effect_free_deps: HashSet<String>,
+}
+
+impl TreeShakeFold {
+  fn is_used(&self, name: &str) -> bool {
+    self.used_exports.contains(name)
+  }
+
+  fn is_side_effect_free_dep(&self, src: &str) -> bool {
+    let fixed_url = self.resolver.borrow().fix_url(src);
+    self.side_effect_free_deps.contains(&fixed_url)
+  }
+
+  /// drops the unused exports, the exported declarations become the local ones.
+  fn drop_exports(&self, items: Vec<ModuleItem>, checker: &SideEffectsChecker) -> Vec<ModuleItem> {
+    let mut output = Vec::with_capacity(items.len());
+    for item in items {
+      match item {
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { span, decl })) => {
+          let used = match &decl {
+            Decl::Fn(FnDecl { ident, .. }) | Decl::Class(ClassDecl { ident, .. }) => {
+              self.is_used(ident.sym.as_ref())
+            }
+            Decl::Var(VarDecl { decls, .. }) => {
+              let ids: Vec<Id> = find_ids(decls);
+              ids.iter().any(|(sym, _)| self.is_used(sym.as_ref()))
+            }
+            // the types are stripped by the typescript transform
+            _ => true,
+          };
+          output.push(if used {
+            ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { span, decl }))
+          } else {
+            ModuleItem::Stmt(Stmt::Decl(decl))
+          });
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultDecl(decl)) if !self.is_used("default") => {
+          match decl.decl {
+            DefaultDecl::Fn(FnExpr {
+              ident: Some(ident),
+              function,
+            }) => output.push(ModuleItem::Stmt(Stmt::Decl(Decl::Fn(FnDecl {
+              ident,
+              declare: false,
+              function,
+            })))),
+            DefaultDecl::Class(ClassExpr {
+              ident: Some(ident),
+              class,
+            }) => output.push(ModuleItem::Stmt(Stmt::Decl(Decl::Class(ClassDecl {
+              ident,
+              declare: false,
+              class,
+            })))),
+            DefaultDecl::Class(ClassExpr { ident: None, class })
+              if !checker.is_pure_class(&class) =>
+            {
+              output.push(ModuleItem::Stmt(Stmt::Expr(ExprStmt {
+                span: decl.span,
+                expr: Box::new(Expr::Class(ClassExpr { ident: None, class })),
+              })))
+            }
+            _ => {}
+          }
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportDefaultExpr(ExportDefaultExpr { span, expr }))
+          if !self.is_used("default") =>
+        {
+          if !checker.is_pure_expr(&expr) {
+            output.push(ModuleItem::Stmt(Stmt::Expr(ExprStmt { span, expr })));
+          }
+        }
+        ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(NamedExport {
+          span,
+          specifiers,
+          src,
+          type_only: false,
+          asserts,
+        })) => {
+          let specifiers: Vec<ExportSpecifier> = specifiers
+            .into_iter()
+            .filter(|specifier| {
+              let name = match specifier {
+                ExportSpecifier::Named(ExportNamedSpecifier { orig, exported, .. }) => {
+                  exported.as_ref().unwrap_or(orig)
+                }
+                ExportSpecifier::Default(ExportDefaultSpecifier { exported }) => exported,
+                ExportSpecifier::Namespace(ExportNamespaceSpecifier { name, .. }) => name,
+              };
+              self.is_used(name.sym.as_ref())
+            })
+            .collect();
+          if !specifiers.is_empty() {
+            output.push(ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(
+              NamedExport {
+                span,
+                specifiers,
+                src,
+                type_only: false,
+                asserts,
+              },
+            )));
+          }
+        }
+        _ => output.push(item),
+      }
+    }
+    output
+  }
+
+  /// drops the top-level bindings and the import specifiers that are not referenced,
+  /// returns `true` if anything is dropped.
+  fn drop_bindings(&self, items: &mut Vec<ModuleItem>, checker: &SideEffectsChecker) -> bool {
+    let mut collector = ReferenceCollector::default();
+    for item in items.iter() {
+      collector.collect_item(item);
+    }
+    let refs = collector.refs;
+    let is_referenced = |ident: &Ident| {
+      refs.contains(&id(ident)) || self.preserved.iter().any(|name| ident.sym.eq(name))
+    };
+    let len = items.len();
+    let mut changed = false;
+    items.retain_mut(|item| match item {
+      ModuleItem::Stmt(Stmt::Decl(Decl::Fn(FnDecl { ident, .. }))) => is_referenced(ident),
+      ModuleItem::Stmt(Stmt::Decl(Decl::Class(ClassDecl { ident, class, .. }))) => {
+        is_referenced(ident) || !checker.is_pure_class(class)
+      }
+      ModuleItem::Stmt(Stmt::Decl(Decl::Var(var))) if !var.declare => {
+        let count = var.decls.len();
+        var.decls.retain(|decl| match (&decl.name, &decl.init) {
+          (Pat::Ident(BindingIdent { id, .. }), init) => {
+            is_referenced(id) || matches!(init, Some(init) if !checker.is_pure_expr(init))
+          }
+          _ => true,
+        });
+        changed |= var.decls.len() != count;
+        !var.decls.is_empty()
+      }
+      ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+        specifiers,
+        src,
+        type_only: false,
+        ..
+      }))
+        if !specifiers.is_empty() =>
+      {
+        let count = specifiers.len();
+        specifiers.retain(|specifier| match specifier {
+          ImportSpecifier::Named(ImportNamedSpecifier { local, .. })
+          | ImportSpecifier::Default(ImportDefaultSpecifier { local, .. })
+          | ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => is_referenced(local),
+        });
+        changed |= specifiers.len() != count;
+        // match: import "./lib.ts"
+        !specifiers.is_empty() || !self.is_side_effect_free_dep(src.value.as_ref())
+      }
+      _ => true,
+    });
+    changed || items.len() != len
+  }
+}
+
+impl Fold for TreeShakeFold {
+  noop_fold_type!();
+
+  fn fold_module(&mut self, module: Module) -> Module {
+    let checker = SideEffectsChecker::new(&module);
+    let mut body = self.drop_exports(module.body, &checker);
+    while self.drop_bindings(&mut body, &checker) {}
+    Module { body, ..module }
+  }
+}
+
+/// Collects the references of the identifiers, the names of the top-level
+/// declarations are not references.
+#[derive(Default)]
+struct ReferenceCollector {
+  refs: HashSet<Id>,
+}
+
+impl ReferenceCollector {
+  fn collect_decl(&mut self, decl: &Decl) {
+    match decl {
+      Decl::Fn(FnDecl { function, .. }) => {
+        function.clone().fold_with(self);
+      }
+      Decl::Class(ClassDecl { class, .. }) => {
+        class.clone().fold_with(self);
+      }
+      Decl::Var(VarDecl { decls, .. }) => {
+        for decl in decls {
+          // the patterns may have the default values and the computed keys
+          if !matches!(decl.name, Pat::Ident(_)) {
+            decl.name.clone().fold_with(self);
+          }
+          decl.init.clone().fold_with(self);
+        }
+      }
+      _ => {
+        decl.clone().fold_with(self);
+      }
+    }
+  }
+
+  fn collect_item(&mut self, item: &ModuleItem) {
+    match item {
+      ModuleItem::Stmt(Stmt::Decl(decl))
+      | ModuleItem::ModuleDecl(ModuleDecl::ExportDecl(ExportDecl { decl, .. })) => {
+        self.collect_decl(decl)
+      }
+      ModuleItem::ModuleDecl(ModuleDecl::Import(_)) => {}
+      _ => {
+        item.clone().fold_with(self);
+      }
+    }
+  }
+}
+
+impl Fold for ReferenceCollector {
+  noop_fold_type!();
+
+  fn fold_ident(&mut self, ident: Ident) -> Ident {
+    self.refs.insert(id(&ident));
+    ident
+  }
+
+  // the property of a non-computed member expression is not a reference
+  fn fold_member_expr(&mut self, member: MemberExpr) -> MemberExpr {
+    if member.computed {
+      return member.fold_children_with(self);
+    }
+    MemberExpr {
+      obj: member.obj.fold_with(self),
+      ..member
+    }
+  }
+
+  fn fold_prop_name(&mut self, name: PropName) -> PropName {
+    match name {
+      PropName::Computed(_) => name.fold_children_with(self),
+      _ => name,
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::bundle::bundle;
+  use crate::swc::{test_resolver, EmitOptions, SWC};
+  use std::collections::HashMap;
+
+  #[test]
+  fn tree_shake_exports() {
+    let source = r#"
+      import { a, b } from "./lib.ts"
+      import * as util from "./util.ts"
+      import "./style.css"
+      const c = () => b()
+      const d = util.d()
+      function e() {
+        return c()
+      }
+      class F {}
+      export const used = a
+      export const unused = e
+      export function unusedFn() {
+        return new F()
+      }
+      export { c as renamed, d }
+      export * from "./all.ts"
+      export default function App() {}
+    "#;
+    let module = SWC::parse("/app.ts", source, None).expect("could not parse module");
+    let resolver = test_resolver("/app.ts");
+    let (code, _) = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          used_exports: Some(vec!["used".into(), "d".into()]),
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains("import { a } from \"./lib.js#/lib.ts@000000\";"));
+    assert!(code.contains("import * as util from \"./util.js#/util.ts@000000\";"));
+    assert!(code.contains("\"./style.css.js#/style.css@000000\";"));
+    assert!(code.contains("const d = util.d();"));
+    assert!(code.contains("export const used = a;"));
+    assert!(code.contains("export { d };"));
+    assert!(code.contains("export * from \"./all.js#/all.ts@000000\";"));
+    assert!(!code.contains("const c"));
+    assert!(!code.contains("function e"));
+    assert!(!code.contains("class F"));
+    assert!(!code.contains("unused"));
+    assert!(!code.contains("renamed"));
+    assert!(!code.contains("App"));
+  }
+
+  #[test]
+  fn tree_shake_imports() {
+    let source = r#"
+      import { a } from "./side.ts"
+      import { b } from "./pure.ts"
+      import { c } from "./used.ts"
+      export const unused = () => a + b
+      export const used = c
+    "#;
+    let module = SWC::parse("/app.js", source, None).expect("could not parse module");
+    let resolver = test_resolver("/app.js");
+    let (code, _) = module
+      .transform(
+        resolver,
+        &EmitOptions {
+          used_exports: Some(vec!["used".into()]),
+          side_effect_free_deps: vec!["/pure.ts".into()],
+          ..Default::default()
+        },
+      )
+      .expect("could not transform module");
+    println!("{}", code);
+    assert!(code.contains("\"./side.js#/side.ts@000000\";"));
+    assert!(!code.contains("{ a }"));
+    assert!(!code.contains("pure"));
+    assert!(code.contains("import { c } from \"./used.js#/used.ts@000000\";"));
+  }
+
+  #[test]
+  fn tree_shake_bundle() {
+    let sources: HashMap<String, String> = vec![
+      (
+        "/main.ts",
+        r#"
+        import { sum } from "./util.ts"
+        export const answer = sum(40, 2)
+        export const question = "?"
+        "#,
+      ),
+      (
+        "/util.ts",
+        r#"
+        export const sum = (a: number, b: number) => a + b
+        export const mul = (a: number, b: number) => a * b
+        "#,
+      ),
+    ]
+    .into_iter()
+    .map(|(specifier, source)| (specifier.to_owned(), source.to_owned()))
+    .collect();
+    let resolver = test_resolver("/main.ts");
+    let (code, _, _, _) = bundle(
+      "/main.ts",
+      &sources,
+      resolver,
+      &EmitOptions {
+        used_exports: Some(vec!["answer".into()]),
+        ..Default::default()
+      },
+    )
+    .expect("could not bundle modules");
+    println!("{}", code);
+    assert!(code.contains("const sum = "));
+    assert!(code.contains("const answer = sum(40, 2);"));
+    assert!(code.contains("export { answer as answer };"));
+    assert!(!code.contains("mul"));
+    assert!(!code.contains("question"));
+  }
+}
@@ -0,0 +1,520 @@
+use serde::Serialize;
+use wasm_bindgen::prelude::{wasm_bindgen, JsValue};
+use wasm_bindgen::JsCast;
+
+/// The TypeScript definitions of the options and the outputs, which are appended to
+/// the `.d.ts` of the wasm module, so the bindings are typed instead of `any`. The
+/// interfaces mirror the serde shape of the Rust types, the `typescript_interfaces`
+/// test checks the fields.
+#[wasm_bindgen(typescript_custom_section)]
+const TS_TYPES: &'static str = r#"
+export type SourceType = "js" | "jsx" | "ts" | "tsx" | "??";
+
+export type JscTarget = "es3" | "es5" | "es2015" | "es2016" | "es2017" | "es2018" | "es2019" | "es2020";
+
+export interface ImportMap {
+  imports?: Record<string, string>;
+  scopes?: Record<string, Record<string, string>>;
+}
+
+export interface ResolveRule {
+  test: string;
+  replace: string;
+}
+
+export interface RefreshOptions {
+  enabled?: boolean;
+  registration?: string;
+  signature?: string;
+  runtimeImport?: string | null;
+  signatureLiteralArgs?: boolean;
+}
+
+export interface StyledOptions {
+  displayName?: boolean;
+  ssr?: boolean;
+  emotion?: boolean;
+  emotionImportSource?: string;
+}
+
+export interface TargetFeatures {
+  nullishCoalescing?: boolean;
+  optionalChaining?: boolean;
+  exportNamespaceFrom?: boolean;
+  objectRestSpread?: boolean;
+  optionalCatchBinding?: boolean;
+  asyncFunctions?: boolean;
+  exponentiation?: boolean;
+  topLevelAwait?: boolean;
+}
+
+export interface ParserFeatures {
+  decorators?: boolean;
+  importAssertions?: boolean;
+  exportDefaultFrom?: boolean;
+  topLevelAwait?: boolean;
+  satisfies?: boolean;
+  accessor?: boolean;
+  explicitResourceManagement?: boolean;
+}
+
+export interface SWCOptions {
+  sourceType?: SourceType;
+  target?: JscTarget;
+  targets?: string | TargetFeatures | null;
+  jsx?: "transform" | "preserve";
+  jsxFactory?: string;
+  jsxFragmentFactory?: string;
+  jsxRuntime?: "classic" | "automatic";
+  jsxImportSource?: string;
+  jsxSource?: boolean;
+  jsxWhitespace?: "collapse" | "babel" | "typescript";
+  jsxDropEmptyChildren?: boolean;
+  jsxKey?: "props" | "arg";
+  jsxRef?: "props" | "arg";
+  moduleType?: "es6" | "commonjs" | "systemjs";
+  targetEnv?: "browser" | "deno" | null;
+  experimentalDecorators?: boolean;
+  parser?: ParserFeatures;
+  isolatedModules?: boolean;
+  emitDecoratorMetadata?: boolean;
+  /** compresses the output and mangles the local names in production. */
+  minify?: boolean;
+  externalHelpers?: boolean;
+  lowerTopLevelAwait?: boolean;
+  lowerClassFields?: boolean;
+  useDefineForClassFields?: boolean;
+  tolerant?: boolean;
+}
+
+export interface Options {
+  importMap?: ImportMap;
+  alephPkgUri?: string;
+  reactVersion?: string;
+  npmCdn?: string;
+  npmVersions?: Record<string, string>;
+  versions?: Record<string, string>;
+  nodePolyfills?: Record<string, string>;
+  aliases?: Record<string, string>;
+  baseUrl?: string;
+  remotePrefix?: string;
+  localPrefix?: string;
+  outputExt?: string;
+  outputExts?: Record<string, string>;
+  depHashes?: Record<string, string>;
+  assetUrl?: string;
+  resolveRules?: ResolveRule[];
+  lock?: Record<string, string>;
+  swcOptions?: SWCOptions;
+  sourceMap?: "none" | "external" | "inline";
+  inputSourceMap?: string | null;
+  inlineSourcesContent?: boolean;
+  sourceRoot?: string | null;
+  isDev?: boolean;
+  transpileOnly?: boolean;
+  measure?: boolean;
+  hmr?: boolean;
+  resolveStarExports?: boolean;
+  extractCss?: boolean;
+  scopedCss?: boolean;
+  lazyPreload?: boolean;
+  stripServerExports?: boolean;
+  extractRouteMeta?: boolean;
+  reportFeatures?: boolean;
+  cssModules?: Record<string, string>;
+  jsonModules?: Record<string, string>;
+  inlineImports?: Record<string, string>;
+  inlineLimit?: number | null;
+  constEnums?: Record<string, Record<string, Record<string, number | string>>>;
+  importMetaUrl?: string | null;
+  globFiles?: Record<string, string[]>;
+  bundleMode?: boolean;
+  bundleExternal?: string[];
+  define?: Record<string, string>;
+  usedExports?: string[] | null;
+  sideEffectFreeDeps?: string[];
+  dropConsole?: boolean;
+  dropDebugger?: boolean;
+  stripDataTestAttrs?: boolean;
+  stripCalls?: string[];
+  keepFnNames?: boolean;
+  keepClassNames?: boolean;
+  hoistStaticJsx?: boolean;
+  coverage?: boolean;
+  comments?: "all" | "legal" | "none";
+  refresh?: boolean | RefreshOptions;
+  styled?: StyledOptions | null;
+  cacheKeys?: Record<string, string>;
+  cacheDir?: string | null;
+}
+
+export interface TransformInput {
+  filename: string;
+  code: string;
+}
+
+export interface SourceSpan {
+  start: number;
+  end: number;
+}
+
+export interface DependencyDescriptor {
+  specifier: string;
+  isDynamic: boolean;
+  span?: SourceSpan;
+  kind?: "worker";
+  chunkName?: string;
+  reexport?: boolean;
+  lazy?: boolean;
+}
+
+export interface RemoteDependency {
+  url: string;
+  resolvedPath: string;
+  integrity?: string;
+}
+
+export interface InlineStyle {
+  type: string;
+  quasis: string[];
+  exprs: string[];
+}
+
+export interface ExportSignature {
+  names: string[];
+  starExports: string[];
+  hasDefaultExport: boolean;
+}
+
+export interface DiagnosticMessage {
+  fileName: string;
+  line: number;
+  column: number;
+  length: number;
+  message: string;
+  code: "syntax-error" | "transform-error" | "invalid-options" | "warning" | "import-cycle";
+  frame?: CodeFrameLine[];
+  cycle?: string[];
+}
+
+export interface CodeFrameLine {
+  line: number;
+  text: string;
+  highlight: [number, number] | null;
+}
+
+export interface CoverageLocation {
+  start: { line: number; column: number };
+  end: { line: number; column: number };
+}
+
+export interface FileCoverage {
+  path: string;
+  statementMap: Record<string, CoverageLocation>;
+  fnMap: Record<string, { name: string; decl: CoverageLocation; loc: CoverageLocation; line: number }>;
+  branchMap: Record<string, { loc: CoverageLocation; type: string; locations: CoverageLocation[]; line: number }>;
+  s: Record<string, number>;
+  f: Record<string, number>;
+  b: Record<string, number[]>;
+}
+
+export interface TransformStats {
+  parseMs: number;
+  transformMs: number;
+  emitMs: number;
+  inputBytes: number;
+  outputBytes: number;
+}
+
+export interface TransformOutput {
+  code: string;
+  hash: string;
+  map: string | null;
+  sideEffectFree: boolean;
+  exports: ExportSignature;
+  refreshBoundary: "boundary" | "mixed" | "none";
+  directive: "client" | "server" | null;
+  serverExports: string[] | null;
+  deps: DependencyDescriptor[];
+  remoteDeps: RemoteDependency[] | null;
+  preload: string[] | null;
+  inlineStyles: Record<string, InlineStyle>;
+  starExports: string[] | null;
+  dynamicImportPatterns: string[] | null;
+  denoHooks: string[] | null;
+  cssDeps: string[] | null;
+  cssModules: Record<string, string> | null;
+  scopedCss: Record<string, string> | null;
+  assets: string[] | null;
+  coverage: FileCoverage | null;
+  frontMatter: Record<string, unknown> | null;
+  routeMeta: Record<string, unknown> | null;
+  features: UsedFeatures | null;
+  warnings: DiagnosticMessage[] | null;
+  stats: TransformStats | null;
+  legalComments: string | null;
+  cacheKey: string | null;
+  unchanged: boolean;
+}
+
+export interface UsedFeatures {
+  syntax: string[];
+  apis: string[];
+}
+
+export interface DualTransformOutput {
+  dev: TransformOutput;
+  prod: TransformOutput;
+}
+
+export interface ParseOutput {
+  exports: string[];
+  imports: DependencyDescriptor[];
+  importGlobs: string[];
+  hasDefaultExport: boolean;
+  usesJsx: boolean;
+  hasTopLevelAwait: boolean;
+  directive: "client" | "server" | null;
+  routeMeta: Record<string, unknown> | null;
+  errors?: DiagnosticMessage[];
+}
+
+export interface ResolveOutput {
+  url: string;
+  resolvedPath: string;
+}
+
+export interface ImportMapDiagnostic {
+  scope?: string;
+  key: string;
+  value: string;
+  reason: string;
+}
+
+export interface ImportMapValidation {
+  errors: ImportMapDiagnostic[];
+  warnings: ImportMapDiagnostic[];
+}
+
+export interface BundleOutput {
+  code: string;
+  map?: string;
+  deps: DependencyDescriptor[];
+  modules: string[];
+  warnings: DiagnosticMessage[] | null;
+  manifest: BuildManifest;
+}
+
+export interface ManifestEntry {
+  file: string;
+  hash: string;
+  dynamicImports?: Record<string, string>;
+}
+
+export interface BuildManifest {
+  modules: Record<string, ManifestEntry>;
+  chunks: Record<string, string[]>;
+}
+
+export interface BuildOutput {
+  modules: TransformOutput[];
+  manifest: BuildManifest;
+}
+
+export interface OriginalPosition {
+  source: string;
+  line: number;
+  column: number;
+  name: string | null;
+}
+
+export interface GeneratedPosition {
+  line: number;
+  column: number;
+}
+
+export interface InitOptions {
+  initialMemory?: number | null;
+  sourceMapCacheSize?: number | null;
+}
+
+export interface MemoryStats {
+  memoryBytes: number | null;
+  cachedSourceMaps: number;
+  parsedModules: number;
+}
+
+export interface TransformHtmlOutput {
+  code: string;
+  deps: DependencyDescriptor[];
+}
+
+export interface ConvertCJSOutput {
+  code: string;
+  exports: string[];
+  requires: string[];
+}
+
+export interface AstSpan {
+  start: number;
+  end: number;
+  loc: { start: { line: number, column: number }, end: { line: number, column: number } };
+}
+
+export interface AstNode {
+  type: string;
+  span: AstSpan;
+  [key: string]: unknown;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+  #[wasm_bindgen(typescript_type = "Options")]
+  pub type JsOptions;
+  #[wasm_bindgen(typescript_type = "SWCOptions")]
+  pub type JsSWCOptions;
+  #[wasm_bindgen(typescript_type = "ImportMap")]
+  pub type JsImportMap;
+  #[wasm_bindgen(typescript_type = "TransformInput[]")]
+  pub type JsTransformInputs;
+  #[wasm_bindgen(typescript_type = "Record<string, string>")]
+  pub type JsModules;
+  #[wasm_bindgen(typescript_type = "TransformOutput")]
+  pub type JsTransformOutput;
+  #[wasm_bindgen(typescript_type = "TransformOutput[]")]
+  pub type JsTransformOutputs;
+  #[wasm_bindgen(typescript_type = "DualTransformOutput")]
+  pub type JsDualTransformOutput;
+  #[wasm_bindgen(typescript_type = "ParseOutput")]
+  pub type JsParseOutput;
+  #[wasm_bindgen(typescript_type = "string[]")]
+  pub type JsExportNames;
+  #[wasm_bindgen(typescript_type = "ResolveOutput")]
+  pub type JsResolveOutput;
+  #[wasm_bindgen(typescript_type = "ImportMapValidation")]
+  pub type JsImportMapValidation;
+  #[wasm_bindgen(typescript_type = "BundleOutput")]
+  pub type JsBundleOutput;
+  #[wasm_bindgen(typescript_type = "BuildOutput")]
+  pub type JsBuildOutput;
+  #[wasm_bindgen(typescript_type = "TransformHtmlOutput")]
+  pub type JsTransformHtmlOutput;
+  #[wasm_bindgen(typescript_type = "ConvertCJSOutput")]
+  pub type JsConvertCJSOutput;
+  #[wasm_bindgen(typescript_type = "OriginalPosition | null")]
+  pub type JsOriginalPosition;
+  #[wasm_bindgen(typescript_type = "GeneratedPosition | null")]
+  pub type JsGeneratedPosition;
+  #[wasm_bindgen(typescript_type = "InitOptions")]
+  pub type JsInitOptions;
+  #[wasm_bindgen(typescript_type = "MemoryStats")]
+  pub type JsMemoryStats;
+  #[wasm_bindgen(typescript_type = "AstNode")]
+  pub type JsAstNode;
+}
+
+/// converts the output to the typed JS value.
+pub fn to_js<T: JsCast>(output: &impl Serialize) -> T {
+  JsValue::from_serde(output).unwrap().unchecked_into()
+}
+
+#[cfg(test)]
+mod tests {
+  use crate::coverage::FileCoverage;
+  use crate::error::{CodeFrameLine, DiagnosticMessage};
+  use crate::fast_refresh::RefreshOptions;
+  use crate::features::UsedFeatures;
+  use crate::memory::InitOptions;
+  use crate::metadata::ExportSignature;
+  use crate::resolve::{DependencyDescriptor, InlineStyle, RemoteDependency, ResolveRule};
+  use crate::stats::TransformStats;
+  use crate::styled::StyledOptions;
+  use crate::swc::ParserFeatures;
+  use crate::targets::TargetFeatures;
+  use crate::{Options, SWCOptions, TransformInput, TransformOutput};
+  use serde::de::{self, Deserialize, Deserializer, Visitor};
+
+  /// a deserializer that records the field names of a struct.
+  struct FieldNames<'a>(&'a mut Vec<&'static str>);
+
+  impl<'de, 'a> Deserializer<'de> for FieldNames<'a> {
+    type Error = de::value::Error;
+
+    fn deserialize_struct<V: Visitor<'de>>(
+      self,
+      _name: &'static str,
+      fields: &'static [&'static str],
+      _visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+      self.0.extend(fields);
+      Err(de::Error::custom("done"))
+    }
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Self::Error> {
+      Err(de::Error::custom("not a struct"))
+    }
+
+    serde::forward_to_deserialize_any! {
+      bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+      option unit unit_struct newtype_struct seq tuple tuple_struct map enum identifier
+      ignored_any
+    }
+  }
+
+  fn field_names<'de, T: Deserialize<'de>>() -> Vec<&'static str> {
+    let mut names = vec![];
+    let _ = T::deserialize(FieldNames(&mut names));
+    names
+  }
+
+  /// returns the fields of the interface in `TS_TYPES`, which is read from the source
+  /// since the custom section isn't kept as a Rust item.
+  fn interface_fields(name: &str) -> Vec<&'static str> {
+    let source = include_str!("typescript.rs");
+    let ts_types = &source[source.find("r#\"").unwrap()..source.find("\"#;").unwrap()];
+    let start = ts_types
+      .find(format!("export interface {} {{\n", name).as_str())
+      .unwrap_or_else(|| panic!("missing interface {}", name));
+    let body = &ts_types[start..];
+    let body = &body[body.find('\n').unwrap() + 1..body.find("\n}").unwrap()];
+    body
+      .lines()
+      .map(|line| line.trim())
+      .filter(|line| !line.starts_with("/*"))
+      .map(|line| line.split(['?', ':']).next().unwrap())
+      .collect()
+  }
+
+  #[test]
+  fn typescript_interfaces() {
+    let interfaces = vec![
+      ("Options", field_names::<Options>()),
+      ("SWCOptions", field_names::<SWCOptions>()),
+      ("TargetFeatures", field_names::<TargetFeatures>()),
+      ("ParserFeatures", field_names::<ParserFeatures>()),
+      ("RefreshOptions", field_names::<RefreshOptions>()),
+      ("StyledOptions", field_names::<StyledOptions>()),
+      ("ResolveRule", field_names::<ResolveRule>()),
+      ("TransformInput", field_names::<TransformInput>()),
+      ("TransformOutput", field_names::<TransformOutput>()),
+      (
+        "DependencyDescriptor",
+        field_names::<DependencyDescriptor>(),
+      ),
+      ("RemoteDependency", field_names::<RemoteDependency>()),
+      ("InlineStyle", field_names::<InlineStyle>()),
+      ("ExportSignature", field_names::<ExportSignature>()),
+      ("DiagnosticMessage", field_names::<DiagnosticMessage>()),
+      ("TransformStats", field_names::<TransformStats>()),
+      ("FileCoverage", field_names::<FileCoverage>()),
+      ("CodeFrameLine", field_names::<CodeFrameLine>()),
+      ("InitOptions", field_names::<InitOptions>()),
+      ("UsedFeatures", field_names::<UsedFeatures>()),
+    ];
+    for (name, fields) in interfaces {
+      assert!(!fields.is_empty(), "no fields of {}", name);
+      assert_eq!(interface_fields(name), fields, "interface {}", name);
+    }
+  }
+}
@@ -0,0 +1,399 @@
+use crate::resolve::{is_node_builtin, is_node_specifier, split_remote_package};
+
+use std::collections::HashSet;
+use swc_common::Span;
+use swc_ecma_ast::*;
+use swc_ecma_visit::{Fold, FoldWith};
+
+/// Checks the imports of the module for the mistakes that don't fail the build:
+/// - an import binding that is never used (neither as a value nor as a type)
+/// - a module that is imported by more than one import declaration
+/// - an import of the module's own file
+///
+/// The `fix_url` maps an import url to the fixed url that is compared with the
+/// `specifier` of the module, the `jsx_names` are the bindings that are used by
+/// the JSX elements implicitly, like `React` for the classic runtime.
+///
+/// Returns the spans of the warnings with the messages, in the order of the source.
+pub fn check_imports<F>(
+  module: &Module,
+  specifier: &str,
+  fix_url: F,
+  jsx_names: &[String],
+) -> Vec<(Span, String)>
+where
+  F: Fn(&str) -> String,
+{
+  let mut checker = ImportsChecker {
+    refs: HashSet::new(),
+    has_jsx: false,
+  };
+  Program::Module(module.clone()).fold_with(&mut checker);
+  if checker.has_jsx {
+    for name in jsx_names {
+      checker.refs.insert(name.clone());
+    }
+  }
+
+  let mut warnings = vec![];
+  let mut imported: HashSet<String> = HashSet::new();
+  for item in module.body.iter() {
+    if let ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+      specifiers,
+      src,
+      type_only: false,
+      ..
+    })) = item
+    {
+      let fixed_url = fix_url(src.value.as_ref());
+      if fixed_url.eq(specifier) {
+        warnings.push((
+          src.span,
+          format!("The module imports itself: '{}'", src.value),
+        ));
+      } else if !imported.insert(fixed_url) {
+        warnings.push((
+          src.span,
+          format!("The module '{}' is imported more than once", src.value),
+        ));
+      }
+      for specifier in specifiers {
+        let local = match specifier {
+          ImportSpecifier::Named(ImportNamedSpecifier { local, .. }) => local,
+          ImportSpecifier::Default(ImportDefaultSpecifier { local, .. }) => local,
+          ImportSpecifier::Namespace(ImportStarAsSpecifier { local, .. }) => local,
+        };
+        if !checker.refs.contains(local.sym.as_ref()) {
+          warnings.push((
+            local.span,
+            format!("'{}' is imported but never used", local.sym),
+          ));
+        }
+      }
+    }
+  }
+  warnings.sort_by_key(|(span, _)| span.lo);
+  warnings
+}
+
+/// Checks the imports and the re-exports of the node builtin modules that are not
+/// available in the browser, which are the `node:` specifiers and the bare builtin
+/// modules like `events` that are not mapped to a polyfill by the `fix_url`.
+pub fn check_node_builtins<F>(module: &Module, fix_url: F) -> Vec<(Span, String)>
+where
+  F: Fn(&str) -> String,
+{
+  let mut warnings = vec![];
+  for item in module.body.iter() {
+    let src = match item {
+      ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+        src,
+        type_only: false,
+        ..
+      })) => src,
+      ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(NamedExport {
+        src: Some(src),
+        type_only: false,
+        ..
+      })) => src,
+      ModuleItem::ModuleDecl(ModuleDecl::ExportAll(ExportAll { src, .. })) => src,
+      _ => continue,
+    };
+    let fixed_url = fix_url(src.value.as_ref());
+    if is_node_specifier(fixed_url.as_str()) || is_node_builtin(fixed_url.as_str()) {
+      warnings.push((
+        src.span,
+        format!(
+          "The node builtin module '{}' is not available in the browser, add a polyfill to the `nodePolyfills` option",
+          src.value
+        ),
+      ));
+    }
+  }
+  warnings
+}
+
+/// Checks the imports and the re-exports of the remote modules that are not pinned
+/// to a version, like `https://deno.land/std/http/server.ts`, the versions of the
+/// unpinned modules may change between the builds.
+pub fn check_unpinned_imports<F>(module: &Module, fix_url: F) -> Vec<(Span, String)>
+where
+  F: Fn(&str) -> String,
+{
+  let mut warnings = vec![];
+  for item in module.body.iter() {
+    let src = match item {
+      ModuleItem::ModuleDecl(ModuleDecl::Import(ImportDecl {
+        src,
+        type_only: false,
+        ..
+      })) => src,
+      ModuleItem::ModuleDecl(ModuleDecl::ExportNamed(NamedExport {
+        src: Some(src),
+        type_only: false,
+        ..
+      })) => src,
+      ModuleItem::ModuleDecl(ModuleDecl::ExportAll(ExportAll { src, .. })) => src,
+      _ => continue,
+    };
+    let fixed_url = fix_url(src.value.as_ref());
+    if let Some((_, _, None, _)) = split_remote_package(fixed_url.as_str()) {
+      warnings.push((
+        src.span,
+        format!(
+          "The remote module '{}' is not pinned to a version, add the version to the url or the `versions` option",
+          fixed_url
+        ),
+      ));
+    }
+  }
+  warnings
+}
+
+/// Collects the names that are referenced by the module, as a value or as a type,
+/// the shadowed names are treated as references.
+struct ImportsChecker {
+  refs: HashSet<String>,
+  has_jsx: bool,
+}
+
+impl ImportsChecker {
+  fn add_ref(&mut self, id: &Ident) {
+    self.refs.insert(id.sym.as_ref().into());
+  }
+
+  fn add_type_ref(&mut self, name: &TsEntityName) {
+    match name {
+      TsEntityName::Ident(id) => self.add_ref(id),
+      TsEntityName::TsQualifiedName(qualified) => self.add_type_ref(&qualified.left),
+    }
+  }
+}
+
+// the import declarations are not visited, so the import bindings are not
+// counted as references.
+impl Fold for ImportsChecker {
+  fn fold_module_decl(&mut self, decl: ModuleDecl) -> ModuleDecl {
+    match &decl {
+      ModuleDecl::Import(_) => decl,
+      // match: export { useState }
+      ModuleDecl::ExportNamed(NamedExport {
+        specifiers,
+        src: None,
+        ..
+      }) => {
+        for specifier in specifiers {
+          if let ExportSpecifier::Named(ExportNamedSpecifier { orig, .. }) = specifier {
+            self.add_ref(orig);
+          }
+        }
+        decl
+      }
+      _ => decl.fold_children_with(self),
+    }
+  }
+
+  fn fold_expr(&mut self, expr: Expr) -> Expr {
+    match expr {
+      Expr::Ident(id) => {
+        self.add_ref(&id);
+        Expr::Ident(id)
+      }
+      // the property of a non-computed member expression is not a reference
+      Expr::Member(MemberExpr {
+        span,
+        obj,
+        prop,
+        computed: false,
+      }) => Expr::Member(MemberExpr {
+        span,
+        obj: obj.fold_with(self),
+        prop,
+        computed: false,
+      }),
+      _ => expr.fold_children_with(self),
+    }
+  }
+
+  // match: { useState }
+  fn fold_prop(&mut self, prop: Prop) -> Prop {
+    if let Prop::Shorthand(id) = &prop {
+      self.add_ref(id);
+    }
+    prop.fold_children_with(self)
+  }
+
+  fn fold_jsx_element(&mut self, el: JSXElement) -> JSXElement {
+    self.has_jsx = true;
+    el.fold_children_with(self)
+  }
+
+  fn fold_jsx_fragment(&mut self, fragment: JSXFragment) -> JSXFragment {
+    self.has_jsx = true;
+    fragment.fold_children_with(self)
+  }
+
+  // match: <App />
+  fn fold_jsx_element_name(&mut self, name: JSXElementName) -> JSXElementName {
+    if let JSXElementName::Ident(id) = &name {
+      self.add_ref(id);
+    }
+    name.fold_children_with(self)
+  }
+
+  fn fold_jsx_object(&mut self, obj: JSXObject) -> JSXObject {
+    if let JSXObject::Ident(id) = &obj {
+      self.add_ref(id);
+    }
+    obj.fold_children_with(self)
+  }
+
+  // match: const app: FC = () => null
+  fn fold_ts_type_ref(&mut self, type_ref: TsTypeRef) -> TsTypeRef {
+    self.add_type_ref(&type_ref.type_name);
+    type_ref.fold_children_with(self)
+  }
+
+  // match: class App implements FC {}
+  fn fold_ts_expr_with_type_args(&mut self, expr: TsExprWithTypeArgs) -> TsExprWithTypeArgs {
+    self.add_type_ref(&expr.expr);
+    expr.fold_children_with(self)
+  }
+
+  // match: type App = typeof app
+  fn fold_ts_type_query(&mut self, query: TsTypeQuery) -> TsTypeQuery {
+    if let TsTypeQueryExpr::TsEntityName(name) = &query.expr_name {
+      self.add_type_ref(name);
+    }
+    query.fold_children_with(self)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::resolve::Resolver;
+  use crate::swc::SWC;
+
+  fn check(specifier: &str, source: &str) -> Vec<String> {
+    let module = SWC::parse(specifier, source, None).expect("could not parse module");
+    let resolver = Resolver::new(specifier, Default::default(), None, None, false, vec![]);
+    check_imports(
+      &module.module,
+      specifier,
+      |url| resolver.fix_url(url),
+      &["React".into()],
+    )
+    .into_iter()
+    .map(|(_, message)| message)
+    .collect()
+  }
+
+  #[test]
+  fn unused_imports() {
+    let source = r#"
+      import React, { useState, useEffect } from "https://esm.sh/react"
+      import type { FC } from "https://esm.sh/react"
+      import { Head } from "https://deno.land/x/aleph/mod.ts"
+      import * as path from "https://deno.land/std/path/mod.ts"
+      import Logo from "../components/logo.tsx"
+      import { Props } from "./types.ts"
+      import { format } from "./utils.ts"
+      import { parse } from "./utils.ts"
+      import { self } from "./index.tsx"
+      const App = ({ title }: Props) => {
+        const [count] = useState(0)
+        return <Head><Logo /></Head>
+      }
+      export { parse, title }
+      export default { App, label: path.basename }
+    "#;
+    assert_eq!(
+      check("/pages/index.tsx", source),
+      vec![
+        "'useEffect' is imported but never used",
+        "'format' is imported but never used",
+        "The module './utils.ts' is imported more than once",
+        "'self' is imported but never used",
+        "The module imports itself: './index.tsx'",
+      ]
+    );
+  }
+
+  #[test]
+  fn unused_jsx_runtime_import() {
+    let source = r#"
+      import React from "https://esm.sh/react"
+      export default function App() {
+        return null
+      }
+    "#;
+    assert_eq!(
+      check("/pages/index.tsx", source),
+      vec!["'React' is imported but never used"]
+    );
+  }
+
+  #[test]
+  fn node_builtins_in_browser() {
+    let source = r#"
+      import { Buffer } from "buffer"
+      import { EventEmitter } from "events"
+      import type { Stats } from "node:fs"
+      import { readFile } from "node:fs/promises"
+      export * from "node:path"
+      export default { Buffer, EventEmitter, readFile }
+    "#;
+    let module = SWC::parse("/lib/io.ts", source, None).expect("could not parse module");
+    let mut polyfills = std::collections::HashMap::new();
+    polyfills.insert("buffer".into(), "https://esm.sh/buffer".into());
+    polyfills.insert("path".into(), "".into());
+    let resolver = Resolver::new("/lib/io.ts", Default::default(), None, None, false, vec![])
+      .with_node_polyfills(polyfills);
+    let warnings: Vec<String> = check_node_builtins(&module.module, |url| resolver.fix_url(url))
+      .into_iter()
+      .map(|(_, message)| message)
+      .collect();
+    assert_eq!(
+      warnings,
+      vec![
+        "The node builtin module 'events' is not available in the browser, add a polyfill to the `nodePolyfills` option",
+        "The node builtin module 'node:fs/promises' is not available in the browser, add a polyfill to the `nodePolyfills` option",
+      ]
+    );
+  }
+
+  #[test]
+  fn unpinned_remote_imports() {
+    let source = r#"
+      import { serve } from "https://deno.land/std/http/server.ts"
+      import { Application } from "https://deno.land/x/oak@v7.5.0/mod.ts"
+      import { assert } from "https://deno.land/std/testing/asserts.ts"
+      import dayjs from "https://esm.sh/dayjs"
+      import useSWR from "https://esm.sh/swr"
+      export * from "https://example.com/lib.ts"
+      export default { serve, Application, assert, dayjs, useSWR }
+    "#;
+    let module = SWC::parse("/server.ts", source, None).expect("could not parse module");
+    let mut versions = std::collections::HashMap::new();
+    versions.insert("std".into(), "0.100.0".into());
+    versions.insert(
+      "https://deno.land/std/testing".into(),
+      "https://deno.land/std@0.99.0/testing".into(),
+    );
+    let resolver = Resolver::new("/server.ts", Default::default(), None, None, false, vec![])
+      .with_npm_cdn("https://esm.sh", Default::default())
+      .with_versions(versions);
+    let warnings: Vec<String> = check_unpinned_imports(&module.module, |url| resolver.fix_url(url))
+      .into_iter()
+      .map(|(_, message)| message)
+      .collect();
+    assert_eq!(
+      warnings,
+      vec![
+        "The remote module 'https://esm.sh/dayjs' is not pinned to a version, add the version to the url or the `versions` option",
+        "The remote module 'https://esm.sh/swr' is not pinned to a version, add the version to the url or the `versions` option",
+      ]
+    );
+  }
+}
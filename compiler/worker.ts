@@ -0,0 +1,18 @@
+// the worker of the compiler, it transforms the modules above the `asyncThreshold` of
+// `transform()` off the main thread, the errors are posted back as plain objects.
+import { transformRaw } from './mod.ts'
+
+// deno-lint-ignore no-explicit-any
+const ctx = self as any
+
+ctx.onmessage = async ({ data: { id, url, code, options } }: MessageEvent) => {
+  try {
+    ctx.postMessage({ id, output: await transformRaw(url, code, options) })
+  } catch (error) {
+    if (error !== null && typeof error === 'object' && Array.isArray(error.diagnostics)) {
+      ctx.postMessage({ id, error: { diagnostics: error.diagnostics } })
+    } else {
+      ctx.postMessage({ id, error: { message: String(error?.message ?? error) } })
+    }
+  }
+}
@@ -51,7 +51,7 @@ export function applyCSS(url: string, css?: string) {
         el = document.createElement('style')
         el.type = 'text/css'
         el.appendChild(document.createTextNode(css))
-      } else if (util.isLikelyHttpURL(url)) {
+      } else if (util.isLikelyHttpURL(url) || url.startsWith('/')) {
         el = document.createElement('link')
         el.rel = 'stylesheet'
         el.href = url
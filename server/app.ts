@@ -1063,7 +1063,7 @@ export class Application implements ServerApplication {
         },
         // workaround for https://github.com/denoland/deno/issues/9849
         resolveStarExports: !this.isDev && Deno.version.deno.replace(/\.\d+$/, '') === '1.8',
-        sourceMap: this.isDev,
+        sourceMap: this.isDev ? 'external' : 'none',
         loaders: this.config.plugins.filter(isLoaderPlugin)
       })
 